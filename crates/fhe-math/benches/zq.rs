@@ -1,5 +1,5 @@
 use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
-use fhe_math::zq::Modulus;
+use fhe_math::zq::{Modulus, ReductionBackend};
 use rand::thread_rng;
 
 pub fn zq_benchmark(c: &mut Criterion) {
@@ -40,10 +40,23 @@ pub fn zq_benchmark(c: &mut Criterion) {
             b.iter(|| q.mul_vec_vt(&mut a, &c));
         });
 
+        let q_montgomery = Modulus::new_with_backend(p, ReductionBackend::Montgomery).unwrap();
+        group.bench_function(BenchmarkId::new("mul_montgomery_vec", vector_size), |b| {
+            b.iter(|| q_montgomery.mul_montgomery_vec(&mut a, &c));
+        });
+
+        group.bench_function(BenchmarkId::new("to_montgomery_vec", vector_size), |b| {
+            b.iter(|| q_montgomery.to_montgomery_vec(&mut a));
+        });
+
         group.bench_function(BenchmarkId::new("mul_shoup_vec", vector_size), |b| {
             b.iter(|| q.mul_shoup_vec(&mut a, &c, &c_shoup));
         });
 
+        group.bench_function(BenchmarkId::new("reduce_vec", vector_size), |b| {
+            b.iter(|| q.reduce_vec(&mut a));
+        });
+
         group.bench_function(BenchmarkId::new("scalar_mul_vec", vector_size), |b| {
             b.iter(|| q.scalar_mul_vec(&mut a, scalar));
         });
@@ -12,10 +12,18 @@ pub enum Error {
     #[error("Invalid modulus: modulus {0} should be between 2 and (1 << 62) - 1.")]
     InvalidModulus(u64),
 
+    /// Indicates an invalid 128-bit modulus
+    #[error("Invalid modulus: modulus {0} should be between 2 and (1 << 126) - 1.")]
+    InvalidModulus128(u128),
+
     /// Indicates an error in the serialization / deserialization.
     #[error("{0}")]
     Serialization(String),
 
+    /// Indicates that the decoding of a serialization failed.
+    #[error("Decoding error: {0}")]
+    Decoding(#[source] prost::DecodeError),
+
     /// Indicates that there is no more contexts to switch to.
     #[error("This is the last context.")]
     NoMoreContext,
@@ -32,10 +40,87 @@ pub enum Error {
     #[error("Invalid seed: got {0} bytes, expected {1} bytes.")]
     InvalidSeedSize(usize, usize),
 
-    /// Indicates a default error
-    /// TODO: To delete when transition is over
-    #[error("{0}")]
-    Default(String),
+    /// Indicates that the degree is not a power of two larger or equal to
+    /// [`MIN_DEGREE`](crate::rq::MIN_DEGREE).
+    #[error("Invalid degree: {0} is not a power of two larger or equal to 2.")]
+    InvalidDegree(usize),
+
+    /// Indicates that two objects have different degrees.
+    #[error("Incompatible degrees: {0} and {1}.")]
+    IncompatibleDegrees(usize, usize),
+
+    /// Indicates that there is no context at a level.
+    #[error("No context at level {0}.")]
+    InvalidLevel(usize),
+
+    /// Indicates that the list of moduli is empty.
+    #[error("The list of moduli is empty.")]
+    EmptyModuli,
+
+    /// Indicates that the moduli are not coprime.
+    #[error("The moduli are not coprime.")]
+    ModuliNotCoprime,
+
+    /// Indicates that a modulus does not support the requested operation.
+    #[error("Unsupported modulus {0} for this operation.")]
+    UnsupportedModulus(u64),
+
+    /// Indicates that the index of a cyclotomic polynomial is invalid.
+    #[error("Invalid index of cyclotomic polynomial: {0}.")]
+    InvalidCyclotomicIndex(usize),
+
+    /// Indicates that a substitution exponent is invalid.
+    #[error("Invalid exponent: {0} is not odd modulo 2 * degree.")]
+    InvalidExponent(usize),
+
+    /// Indicates that a variance is invalid.
+    #[error("Invalid variance: {0} is not an integer between 1 and 65536.")]
+    InvalidVariance(usize),
+
+    /// Indicates that the parameters of a noise distribution are invalid.
+    #[error("Invalid noise distribution: {0}.")]
+    InvalidDistribution(String),
+
+    /// Indicates that a value is not invertible.
+    #[error("The value is not invertible.")]
+    NotInvertible,
+
+    /// Indicates a division by zero.
+    #[error("Division by zero.")]
+    DivisionByZero,
+
+    /// Indicates that an input is empty.
+    #[error("At least one input is empty.")]
+    EmptyInput,
+
+    /// Indicates that an operation does not support lazy coefficients.
+    #[error("The polynomial has lazy coefficients.")]
+    LazyCoefficients,
+
+    /// Indicates that a representation must be specified.
+    #[error("The representation must be specified.")]
+    MissingRepresentation,
+
+    /// Indicates that the number of values is invalid.
+    #[error("Invalid number of values: found {found}, expected {expected}.")]
+    InvalidLength {
+        /// The expected number of values.
+        expected: usize,
+        /// The number of values found.
+        found: usize,
+    },
+
+    /// Indicates that coefficients are not reduced modulo the moduli.
+    #[error("The coefficients are not reduced modulo the moduli.")]
+    UnreducedCoefficients,
+
+    /// Indicates that a serialization uses an unsupported format version.
+    #[error("Unsupported serialization format version {0}.")]
+    UnsupportedVersion(u32),
+
+    /// Indicates that the logarithm of the base of a gadget is invalid.
+    #[error("Invalid gadget base: 2^{0} is not between 2 and 2^62.")]
+    InvalidGadgetBase(usize),
 }
 
 #[cfg(test)]
@@ -48,6 +133,10 @@ mod tests {
             Error::InvalidModulus(0).to_string(),
             "Invalid modulus: modulus 0 should be between 2 and (1 << 62) - 1."
         );
+        assert_eq!(
+            Error::InvalidModulus128(0).to_string(),
+            "Invalid modulus: modulus 0 should be between 2 and (1 << 126) - 1."
+        );
         assert_eq!(Error::Serialization("test".to_string()).to_string(), "test");
         assert_eq!(
             Error::NoMoreContext.to_string(),
@@ -66,5 +155,38 @@ mod tests {
             Error::InvalidSeedSize(0, 1).to_string(),
             "Invalid seed: got 0 bytes, expected 1 bytes."
         );
+        assert_eq!(
+            Error::InvalidDegree(3).to_string(),
+            "Invalid degree: 3 is not a power of two larger or equal to 2."
+        );
+        assert_eq!(
+            Error::IncompatibleDegrees(8, 16).to_string(),
+            "Incompatible degrees: 8 and 16."
+        );
+        assert_eq!(Error::InvalidLevel(2).to_string(), "No context at level 2.");
+        assert_eq!(
+            Error::UnsupportedModulus(2).to_string(),
+            "Unsupported modulus 2 for this operation."
+        );
+        assert_eq!(
+            Error::InvalidLength {
+                expected: 16,
+                found: 17
+            }
+            .to_string(),
+            "Invalid number of values: found 17, expected 16."
+        );
+    }
+
+    #[test]
+    fn source() {
+        use prost::Message;
+        use std::error::Error as StdError;
+
+        let e = crate::proto::rq::Rq::decode(&[0xffu8][..]).unwrap_err();
+        let error = Error::Decoding(e.clone());
+        assert_eq!(error.to_string(), format!("Decoding error: {e}"));
+        assert_eq!(error.source().unwrap().to_string(), e.to_string());
+        assert!(Error::InvalidContext.source().is_none());
     }
 }
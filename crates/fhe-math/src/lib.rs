@@ -13,6 +13,7 @@ pub mod rq;
 pub mod zq;
 
 pub use errors::{Error, Result};
+pub use zq::primes;
 
 #[cfg(test)]
 #[macro_use]
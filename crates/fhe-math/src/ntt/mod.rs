@@ -1,30 +1,116 @@
 //! Number-Theoretic Transform in ZZ_q.
 
-use fhe_util::is_prime;
+use itertools::izip;
+use ndarray::{ArrayViewMut2, Axis};
 
 mod native;
 pub use native::NttOperator;
 
+/// Kernel used to compute the NTT of all the residues of a polynomial.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum NttKernel {
+    /// Transform the residues one after the other on the current thread.
+    #[default]
+    Sequential,
+    /// Transform the residues in up to the given number of tasks on the rayon
+    /// global pool. Each residue is then transformed on a single thread, even
+    /// with the `parallel-ntt` feature, so that the threads are not nested.
+    Threaded(usize),
+}
+
+/// Computes the forward NTT of each row of `coefficients`, where the i-th row
+/// is transformed by `ops[i]`; the rows without operator are left unchanged.
+pub(crate) fn forward_batch(
+    ops: &[Option<NttOperator>],
+    coefficients: ArrayViewMut2<u64>,
+    variable_time: bool,
+    kernel: NttKernel,
+) {
+    if variable_time {
+        batch(ops, coefficients, kernel, |op, v| unsafe {
+            op.forward_vt(v.as_mut_ptr())
+        })
+    } else {
+        batch(ops, coefficients, kernel, NttOperator::forward)
+    }
+}
+
+/// Computes the backward NTT of each row of `coefficients`, where the i-th row
+/// is transformed by `ops[i]`; the rows without operator are left unchanged.
+pub(crate) fn backward_batch(
+    ops: &[Option<NttOperator>],
+    coefficients: ArrayViewMut2<u64>,
+    variable_time: bool,
+    kernel: NttKernel,
+) {
+    if variable_time {
+        batch(ops, coefficients, kernel, |op, v| unsafe {
+            op.backward_vt(v.as_mut_ptr())
+        })
+    } else {
+        batch(ops, coefficients, kernel, NttOperator::backward)
+    }
+}
+
+/// Applies `f` to the rows of `coefficients` using the kernel `kernel`.
+fn batch<F>(
+    ops: &[Option<NttOperator>],
+    mut coefficients: ArrayViewMut2<u64>,
+    kernel: NttKernel,
+    f: F,
+) where
+    F: Fn(&NttOperator, &mut [u64]) + Sync,
+{
+    debug_assert_eq!(ops.len(), coefficients.nrows());
+    let rows = coefficients.nrows();
+    match kernel {
+        NttKernel::Threaded(threads) if threads > 1 && rows > 1 => {
+            let chunk_size = rows.div_ceil(threads);
+            rayon::scope(|scope| {
+                for (mut chunk, ops) in izip!(
+                    coefficients.axis_chunks_iter_mut(Axis(0), chunk_size),
+                    ops.chunks(chunk_size)
+                ) {
+                    let f = &f;
+                    scope.spawn(move |_| {
+                        izip!(chunk.outer_iter_mut(), ops).for_each(|(mut v, op)| {
+                            if let Some(op) = op {
+                                f(op, v.as_slice_mut().unwrap())
+                            }
+                        })
+                    });
+                }
+            })
+        }
+        _ => izip!(coefficients.outer_iter_mut(), ops).for_each(|(mut v, op)| {
+            if let Some(op) = op {
+                f(op, v.as_slice_mut().unwrap())
+            }
+        }),
+    }
+}
+
 /// Returns whether a modulus p is prime and supports the Number Theoretic
 /// Transform of size n.
 ///
-/// Aborts if n is not a power of 2 that is >= 8.
+/// Aborts if n is not a power of 2 that is >= 2.
 pub(crate) fn supports_ntt(p: u64, n: usize) -> bool {
-    assert!(n >= 8 && n.is_power_of_two());
+    assert!(n >= 2 && n.is_power_of_two());
 
-    p % ((n as u64) << 1) == 1 && is_prime(p)
+    crate::zq::primes::is_ntt_friendly(p, n)
 }
 
 #[cfg(test)]
 mod tests {
     use rand::thread_rng;
 
-    use super::{supports_ntt, NttOperator};
-    use crate::zq::Modulus;
+    use super::{backward_batch, forward_batch, supports_ntt, NttKernel, NttOperator};
+    use crate::zq::{primes::generate_prime, Modulus, MAX_MODULUS_BITS};
+    use ndarray::Array2;
 
     #[test]
     fn constructor() {
-        for size in [32, 1024] {
+        for size in [2, 4, 32, 1024] {
             for p in [1153, 4611686018326724609] {
                 let q = Modulus::new(p).unwrap();
                 let supports_ntt = supports_ntt(p, size);
@@ -45,7 +131,7 @@ mod tests {
         let ntests = 100;
         let mut rng = thread_rng();
 
-        for size in [32, 1024] {
+        for size in [2, 4, 32, 1024] {
             for p in [1153, 4611686018326724609] {
                 let q = Modulus::new(p).unwrap();
 
@@ -79,7 +165,7 @@ mod tests {
         let ntests = 100;
         let mut rng = thread_rng();
 
-        for size in [32, 1024] {
+        for size in [2, 4, 32, 1024] {
             for p in [1153, 4611686018326724609] {
                 let q = Modulus::new(p).unwrap();
 
@@ -103,4 +189,77 @@ mod tests {
             }
         }
     }
+
+    #[test]
+    fn max_modulus_bits() {
+        let mut rng = thread_rng();
+        for size in [2, 4, 32, 1024] {
+            // The largest modulus supporting the NTT, with the largest coefficients.
+            let p =
+                generate_prime(MAX_MODULUS_BITS, 2 * size as u64, 1 << MAX_MODULUS_BITS).unwrap();
+            let q = Modulus::new(p).unwrap();
+            let op = NttOperator::new(&q, size).unwrap();
+            for a in [vec![p - 1; size], q.random_vec(size, &mut rng)] {
+                let mut b = a.clone();
+                let mut b_vt = a.clone();
+                let mut b_lazy = a.clone();
+                op.forward(&mut b);
+                unsafe {
+                    op.forward_vt(b_vt.as_mut_ptr());
+                    op.forward_vt_lazy(b_lazy.as_mut_ptr());
+                }
+                q.reduce_vec(&mut b_lazy);
+                assert_eq!(b, b_vt);
+                assert_eq!(b, b_lazy);
+
+                op.backward(&mut b);
+                unsafe { op.backward_vt(b_vt.as_mut_ptr()) }
+                assert_eq!(b, a);
+                assert_eq!(b_vt, a);
+            }
+        }
+    }
+
+    #[test]
+    fn batch() {
+        let mut rng = thread_rng();
+        let size = 32;
+        let moduli = [
+            4611686018326724609,
+            4611686018309947393,
+            4611686018232352769,
+            1153,
+            4611686018171535361,
+            1019,
+        ];
+        let q = moduli.map(|p| Modulus::new(p).unwrap());
+        // The last modulus does not support the NTT, and its row is unchanged.
+        let ops = q.clone().map(|qi| NttOperator::new(&qi, size));
+        assert!(ops.last().unwrap().is_none());
+        let mut a = Array2::zeros((moduli.len(), size));
+        for (mut row, qi) in a.outer_iter_mut().zip(q.iter()) {
+            row.as_slice_mut()
+                .unwrap()
+                .copy_from_slice(&qi.random_vec(size, &mut rng));
+        }
+        let mut expected = a.clone();
+        for (mut row, op) in expected.outer_iter_mut().zip(ops.iter().flatten()) {
+            op.forward(row.as_slice_mut().unwrap());
+        }
+
+        for kernel in [
+            NttKernel::Sequential,
+            NttKernel::Threaded(0),
+            NttKernel::Threaded(2),
+            NttKernel::Threaded(8),
+        ] {
+            for variable_time in [false, true] {
+                let mut b = a.clone();
+                forward_batch(&ops, b.view_mut(), variable_time, kernel);
+                assert_eq!(b, expected);
+                backward_batch(&ops, b.view_mut(), variable_time, kernel);
+                assert_eq!(b, a);
+            }
+        }
+    }
 }
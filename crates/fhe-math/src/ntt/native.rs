@@ -1,8 +1,22 @@
 use crate::zq::Modulus;
+use fhe_util::is_prime;
 use itertools::Itertools;
 use rand::{Rng, SeedableRng};
 use rand_chacha::ChaCha8Rng;
-use std::iter::successors;
+use std::{
+    collections::HashMap,
+    iter::successors,
+    sync::{Arc, Mutex, OnceLock},
+};
+
+mod composite;
+#[cfg(feature = "parallel-ntt")]
+mod parallel;
+
+use composite::Composite;
+
+/// Process-wide cache of the NTT operators, indexed by modulus and size.
+static CACHE: OnceLock<Mutex<HashMap<(u64, usize), NttOperator>>> = OnceLock::new();
 
 /// Number-Theoretic Transform operator.
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -10,23 +24,64 @@ pub struct NttOperator {
     p: Modulus,
     p_twice: u64,
     size: usize,
-    omegas: Box<[u64]>,
-    omegas_shoup: Box<[u64]>,
-    zetas_inv: Box<[u64]>,
-    zetas_inv_shoup: Box<[u64]>,
+    omegas: Arc<[u64]>,
+    omegas_shoup: Arc<[u64]>,
+    zetas_inv: Arc<[u64]>,
+    zetas_inv_shoup: Arc<[u64]>,
     size_inv: u64,
     size_inv_shoup: u64,
+    // The transforms modulo the prime factors of a composite modulus.
+    composite: Option<Arc<Composite>>,
 }
 
 impl NttOperator {
     /// Create an NTT operator given a modulus for a specific size.
     ///
-    /// Aborts if the size is not a power of 2 that is >= 8 in debug mode.
+    /// The modulus can be a prime, or the product of two distinct primes
+    /// supporting the NTT of this size, in which case the transform is
+    /// computed modulo each prime and recombined.
+    ///
+    /// The precomputed tables are cached for the lifetime of the process, and
+    /// shared by all the operators with the same modulus and size.
+    ///
+    /// Aborts if the size is not a power of 2 that is >= 2 in debug mode.
     /// Returns None if the modulus does not support the NTT for this specific
     /// size.
     pub fn new(p: &Modulus, size: usize) -> Option<Self> {
-        if !super::supports_ntt(p.p, size) {
+        let cache = CACHE.get_or_init(Default::default);
+        if let Some(op) = cache.lock().unwrap().get(&(p.p, size)) {
+            return Some(op.clone());
+        }
+        let op = Self::compute(p, size)?;
+        Some(
+            cache
+                .lock()
+                .unwrap()
+                .entry((p.p, size))
+                .or_insert(op)
+                .clone(),
+        )
+    }
+
+    /// Compute the tables of an NTT operator.
+    fn compute(p: &Modulus, size: usize) -> Option<Self> {
+        assert!(size >= 2 && size.is_power_of_two());
+        if p.p % ((size as u64) << 1) != 1 {
             None
+        } else if !is_prime(p.p) {
+            let composite = Composite::new(p, size)?;
+            Some(Self {
+                p: p.clone(),
+                p_twice: p.p * 2,
+                size,
+                omegas: Arc::new([]),
+                omegas_shoup: Arc::new([]),
+                zetas_inv: Arc::new([]),
+                zetas_inv_shoup: Arc::new([]),
+                size_inv: 0,
+                size_inv_shoup: 0,
+                composite: Some(Arc::new(composite)),
+            })
         } else {
             let size_inv = p.inv(size as u64)?;
 
@@ -55,12 +110,13 @@ impl NttOperator {
                 p: p.clone(),
                 p_twice: p.p * 2,
                 size,
-                omegas: omegas.into_boxed_slice(),
-                omegas_shoup: omegas_shoup.into_boxed_slice(),
-                zetas_inv: zetas_inv.into_boxed_slice(),
-                zetas_inv_shoup: zetas_inv_shoup.into_boxed_slice(),
+                omegas: omegas.into(),
+                omegas_shoup: omegas_shoup.into(),
+                zetas_inv: zetas_inv.into(),
+                zetas_inv_shoup: zetas_inv_shoup.into(),
                 size_inv,
                 size_inv_shoup: p.shoup(size_inv),
+                composite: None,
             })
         }
     }
@@ -70,6 +126,15 @@ impl NttOperator {
     pub fn forward(&self, a: &mut [u64]) {
         debug_assert_eq!(a.len(), self.size);
 
+        if let Some(composite) = &self.composite {
+            return composite.forward(a);
+        }
+
+        #[cfg(feature = "parallel-ntt")]
+        if self.size >= parallel::PARALLEL_THRESHOLD && parallel::threads() > 1 {
+            return unsafe { self.forward_parallel(a.as_mut_ptr(), parallel::threads(), false) };
+        }
+
         let n = self.size;
         let a_ptr = a.as_mut_ptr();
 
@@ -116,6 +181,15 @@ impl NttOperator {
     pub fn backward(&self, a: &mut [u64]) {
         debug_assert_eq!(a.len(), self.size);
 
+        if let Some(composite) = &self.composite {
+            return composite.backward(a);
+        }
+
+        #[cfg(feature = "parallel-ntt")]
+        if self.size >= parallel::PARALLEL_THRESHOLD && parallel::threads() > 1 {
+            return unsafe { self.backward_parallel(a.as_mut_ptr(), parallel::threads(), false) };
+        }
+
         let a_ptr = a.as_mut_ptr();
 
         let mut k = 0;
@@ -167,6 +241,10 @@ impl NttOperator {
     /// This function is not constant time and its timing may reveal information
     /// about the value being reduced.
     pub(crate) unsafe fn forward_vt_lazy(&self, a_ptr: *mut u64) {
+        if let Some(composite) = &self.composite {
+            return composite.forward(std::slice::from_raw_parts_mut(a_ptr, self.size));
+        }
+
         let mut l = self.size >> 1;
         let mut m = 1;
         let mut k = 1;
@@ -210,6 +288,15 @@ impl NttOperator {
     /// This function is not constant time and its timing may reveal information
     /// about the value being reduced.
     pub unsafe fn forward_vt(&self, a_ptr: *mut u64) {
+        if let Some(composite) = &self.composite {
+            return composite.forward(std::slice::from_raw_parts_mut(a_ptr, self.size));
+        }
+
+        #[cfg(feature = "parallel-ntt")]
+        if self.size >= parallel::PARALLEL_THRESHOLD && parallel::threads() > 1 {
+            return self.forward_parallel(a_ptr, parallel::threads(), true);
+        }
+
         self.forward_vt_lazy(a_ptr);
         for i in 0..self.size {
             *a_ptr.add(i) = self.reduce3_vt(*a_ptr.add(i))
@@ -223,6 +310,15 @@ impl NttOperator {
     /// This function is not constant time and its timing may reveal information
     /// about the value being reduced.
     pub unsafe fn backward_vt(&self, a_ptr: *mut u64) {
+        if let Some(composite) = &self.composite {
+            return composite.backward(std::slice::from_raw_parts_mut(a_ptr, self.size));
+        }
+
+        #[cfg(feature = "parallel-ntt")]
+        if self.size >= parallel::PARALLEL_THRESHOLD && parallel::threads() > 1 {
+            return self.backward_parallel(a_ptr, parallel::threads(), true);
+        }
+
         let mut k = 0;
         let mut m = self.size >> 1;
         let mut l = 1;
@@ -348,7 +444,7 @@ impl NttOperator {
 
     /// Returns a 2n-th primitive root modulo p.
     ///
-    /// Aborts if p is not prime or n is not a power of 2 that is >= 8.
+    /// Aborts if p is not prime or n is not a power of 2 that is >= 2.
     fn primitive_root(n: usize, p: &Modulus) -> u64 {
         debug_assert!(super::supports_ntt(p.p, n));
 
@@ -379,3 +475,23 @@ impl NttOperator {
         (p.pow(a, n as u64) == 1) && (p.pow(a, (n / 2) as u64) != 1)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::NttOperator;
+    use crate::zq::Modulus;
+    use std::sync::Arc;
+
+    #[test]
+    fn cache() {
+        for size in [32, 1024] {
+            let q = Modulus::new(4611686018326724609).unwrap();
+            let op = NttOperator::new(&q, size).unwrap();
+            let other_op = NttOperator::new(&Modulus::new(4611686018326724609).unwrap(), size);
+            assert_eq!(other_op.as_ref(), Some(&op));
+            assert!(Arc::ptr_eq(&op.omegas, &other_op.unwrap().omegas));
+            assert_eq!(NttOperator::compute(&q, size).unwrap(), op);
+        }
+        assert!(NttOperator::new(&Modulus::new(1153).unwrap(), 1024).is_none());
+    }
+}
@@ -0,0 +1,203 @@
+//! NTT modulo a product of two NTT-friendly primes.
+//!
+//! Since `Z_p = Z_p1 x Z_p2`, the NTT modulo `p = p1 * p2` is computed with
+//! the NTT modulo each prime, and the outputs are recombined by the Chinese
+//! remainder theorem. The recombined transform is the NTT for the root of
+//! unity whose residues are the roots of unity used modulo each prime, so it
+//! has the same coefficients ordering and the same properties as the NTT
+//! modulo a prime.
+
+use super::NttOperator;
+use crate::zq::Modulus;
+use fhe_util::{inverse, is_prime};
+use itertools::izip;
+
+/// The NTT modulo a product of two primes, each supporting the NTT.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(super) struct Composite {
+    factors: [Modulus; 2],
+    ops: [NttOperator; 2],
+    p1_inv_mod_p2: u64,
+    p1_inv_mod_p2_shoup: u64,
+}
+
+impl Composite {
+    /// Creates the NTT of size `size` modulo the composite modulus `p`.
+    ///
+    /// Returns None if p is not the product of two distinct primes supporting
+    /// the NTT of size `size`.
+    pub(super) fn new(p: &Modulus, size: usize) -> Option<Self> {
+        let p1 = split(p)?;
+        let p2 = p.p / p1;
+        if !is_prime(p1) || !is_prime(p2) || p1 == p2 {
+            return None;
+        }
+        let factors = [Modulus::new(p1).ok()?, Modulus::new(p2).ok()?];
+        let ops = [
+            NttOperator::new(&factors[0], size)?,
+            NttOperator::new(&factors[1], size)?,
+        ];
+        let p1_inv_mod_p2 = inverse(p1 % p2, p2)?;
+        Some(Self {
+            p1_inv_mod_p2_shoup: factors[1].shoup(p1_inv_mod_p2),
+            p1_inv_mod_p2,
+            factors,
+            ops,
+        })
+    }
+
+    /// Compute the forward NTT in place, in constant time.
+    pub(super) fn forward(&self, a: &mut [u64]) {
+        self.transform(a, NttOperator::forward)
+    }
+
+    /// Compute the backward NTT in place, in constant time.
+    pub(super) fn backward(&self, a: &mut [u64]) {
+        self.transform(a, NttOperator::backward)
+    }
+
+    /// Applies the transform `f` modulo each prime, and recombines the
+    /// outputs.
+    fn transform<F: Fn(&NttOperator, &mut [u64])>(&self, a: &mut [u64], f: F) {
+        let [q1, q2] = &self.factors;
+        let mut a1 = a.to_vec();
+        let mut a2 = a.to_vec();
+        q1.reduce_vec(&mut a1);
+        q2.reduce_vec(&mut a2);
+        f(&self.ops[0], &mut a1);
+        f(&self.ops[1], &mut a2);
+
+        // a = a1 + p1 * ((a2 - a1) / p1 mod p2) < p1 * p2.
+        izip!(a.iter_mut(), a1.iter(), a2.iter()).for_each(|(ai, a1i, a2i)| {
+            let t = q2.mul_shoup(
+                q2.sub(*a2i, q2.reduce(*a1i)),
+                self.p1_inv_mod_p2,
+                self.p1_inv_mod_p2_shoup,
+            );
+            *ai = *a1i + q1.p * t
+        });
+    }
+}
+
+/// Returns a non-trivial factor of the composite modulus `p`, using Pollard's
+/// rho algorithm with Brent's cycle detection, in variable time.
+fn split(p: &Modulus) -> Option<u64> {
+    let n = p.p;
+    if n % 2 == 0 {
+        return Some(2);
+    }
+    if is_prime(n) {
+        return None;
+    }
+
+    // The iterations are batched to compute a single gcd per batch.
+    const BATCH: usize = 128;
+    for c in 1..n {
+        let f = |x: u64| p.add(p.mul(x, x), c);
+        let (mut x, mut y, mut ys) = (2u64, 2u64, 2u64);
+        let (mut d, mut r, mut product) = (1u64, 1usize, 1u64);
+        while d == 1 {
+            x = y;
+            for _ in 0..r {
+                y = f(y);
+            }
+            let mut k = 0;
+            while k < r && d == 1 {
+                ys = y;
+                for _ in 0..BATCH.min(r - k) {
+                    y = f(y);
+                    product = p.mul(product, x.abs_diff(y));
+                }
+                d = gcd(product, n);
+                k += BATCH;
+            }
+            r *= 2;
+        }
+        if d == n {
+            // Backtrack the last batch one step at a time.
+            loop {
+                ys = f(ys);
+                d = gcd(x.abs_diff(ys), n);
+                if d > 1 {
+                    break;
+                }
+            }
+        }
+        if d != n {
+            return Some(d);
+        }
+    }
+    None
+}
+
+/// Greatest common divisor of a and b.
+const fn gcd(mut a: u64, mut b: u64) -> u64 {
+    while b != 0 {
+        (a, b) = (b, a % b);
+    }
+    a
+}
+
+#[cfg(test)]
+mod tests {
+    use super::split;
+    use crate::ntt::NttOperator;
+    use crate::zq::Modulus;
+    use rand::thread_rng;
+
+    #[test]
+    fn composite() {
+        let mut rng = thread_rng();
+        // 12289 and 40961 support the NTT of size up to 2048 and 4096.
+        let (p1, p2) = (12289u64, 40961u64);
+        let d = split(&Modulus::new(p1 * p2).unwrap()).unwrap();
+        assert!(d == p1 || d == p2);
+        let (p1, p2) = (4294955009u64, 1073738753u64);
+        let d = split(&Modulus::new(p1 * p2).unwrap()).unwrap();
+        assert!(d == p1 || d == p2);
+        assert!(split(&Modulus::new(1153).unwrap()).is_none());
+
+        for size in [2, 16, 1024] {
+            let q = Modulus::new(12289 * 40961).unwrap();
+            let q1 = Modulus::new(12289).unwrap();
+            let op = NttOperator::new(&q, size).unwrap();
+            let op1 = NttOperator::new(&q1, size).unwrap();
+
+            // The product of polynomials modulo x^size + 1 matches the product
+            // modulo each prime.
+            let a = q.random_vec(size, &mut rng);
+            let b = q.random_vec(size, &mut rng);
+            let (mut a_ntt, mut b_ntt) = (a.clone(), b.clone());
+            op.forward(&mut a_ntt);
+            let mut a_vt = a.clone();
+            unsafe { op.forward_vt(a_vt.as_mut_ptr()) }
+            assert_eq!(a_vt, a_ntt);
+            op.forward(&mut b_ntt);
+            q.mul_vec(&mut a_ntt, &b_ntt);
+            let mut c = a_ntt.clone();
+            op.backward(&mut c);
+            unsafe { op.backward_vt(a_ntt.as_mut_ptr()) }
+            assert_eq!(a_ntt, c);
+
+            let (mut a1, mut b1) = (a.clone(), b.clone());
+            q1.reduce_vec(&mut a1);
+            q1.reduce_vec(&mut b1);
+            op1.forward(&mut a1);
+            op1.forward(&mut b1);
+            q1.mul_vec(&mut a1, &b1);
+            op1.backward(&mut a1);
+            q1.reduce_vec(&mut c);
+            assert_eq!(c, a1);
+
+            let mut d = a.clone();
+            op.forward(&mut d);
+            op.backward(&mut d);
+            assert_eq!(d, a);
+        }
+
+        // The factors must both support the NTT, and be distinct.
+        assert!(NttOperator::new(&Modulus::new(12289 * 40961).unwrap(), 4096).is_none());
+        assert!(NttOperator::new(&Modulus::new(12289 * 12289).unwrap(), 16).is_none());
+        assert!(NttOperator::new(&Modulus::new(12289 * 40961 * 65537).unwrap(), 16).is_none());
+    }
+}
@@ -0,0 +1,201 @@
+//! Multi-threaded NTT for large sizes.
+//!
+//! Each layer of butterflies is split in contiguous ranges of butterflies, one
+//! per thread of the rayon global pool, and the layers are computed one after
+//! the other.
+
+use super::NttOperator;
+use rayon::prelude::*;
+
+/// Minimum size from which the NTT is split across threads.
+pub(super) const PARALLEL_THRESHOLD: usize = 32768;
+
+/// Number of threads used by the multi-threaded NTT. When already running on a
+/// thread of the pool, e.g. within a batch of transforms, the NTT is computed
+/// on the current thread only.
+pub(super) fn threads() -> usize {
+    if rayon::current_thread_index().is_some() {
+        1
+    } else {
+        rayon::current_num_threads()
+    }
+}
+
+/// Pointer to the coefficients shared between the threads, which only access
+/// disjoint coefficients between two synchronizations.
+#[derive(Clone, Copy)]
+struct SharedPtr(*mut u64);
+
+unsafe impl Send for SharedPtr {}
+unsafe impl Sync for SharedPtr {}
+
+impl SharedPtr {
+    const fn get(self) -> *mut u64 {
+        self.0
+    }
+}
+
+impl NttOperator {
+    /// Compute the forward NTT in place on `threads` threads.
+    ///
+    /// # Safety
+    /// This function assumes that a_ptr points to at least `size` elements.
+    /// When `variable_time` is set, this function is not constant time.
+    pub(super) unsafe fn forward_parallel(
+        &self,
+        a_ptr: *mut u64,
+        threads: usize,
+        variable_time: bool,
+    ) {
+        let a = SharedPtr(a_ptr);
+        let mut l = self.size >> 1;
+        let mut m = 1;
+        while l > 0 {
+            self.run(threads, |t| {
+                let a_ptr = a.get();
+                let (start, end) = self.range(t, threads, self.size >> 1);
+                self.for_each_butterfly(start, end, l, |i, j| {
+                    let omega = *self.omegas.get_unchecked(m + i);
+                    let omega_shoup = *self.omegas_shoup.get_unchecked(m + i);
+                    let uj = &mut *a_ptr.add(j);
+                    let ujl = &mut *a_ptr.add(j + l);
+                    if variable_time {
+                        self.butterfly_vt(uj, ujl, omega, omega_shoup);
+                        if l == 1 {
+                            *uj = self.reduce3_vt(*uj);
+                            *ujl = self.reduce3_vt(*ujl);
+                        }
+                    } else {
+                        self.butterfly(uj, ujl, omega, omega_shoup);
+                        if l == 1 {
+                            *uj = self.reduce3(*uj);
+                            *ujl = self.reduce3(*ujl);
+                        }
+                    }
+                })
+            });
+            l >>= 1;
+            m <<= 1;
+        }
+    }
+
+    /// Compute the backward NTT in place on `threads` threads.
+    ///
+    /// # Safety
+    /// This function assumes that a_ptr points to at least `size` elements.
+    /// When `variable_time` is set, this function is not constant time.
+    pub(super) unsafe fn backward_parallel(
+        &self,
+        a_ptr: *mut u64,
+        threads: usize,
+        variable_time: bool,
+    ) {
+        let a = SharedPtr(a_ptr);
+        let mut m = self.size >> 1;
+        let mut l = 1;
+        while m > 0 {
+            let k = self.size - 2 * m;
+            self.run(threads, |t| {
+                let a_ptr = a.get();
+                let (start, end) = self.range(t, threads, self.size >> 1);
+                self.for_each_butterfly(start, end, l, |i, j| {
+                    let zeta_inv = *self.zetas_inv.get_unchecked(k + i);
+                    let zeta_inv_shoup = *self.zetas_inv_shoup.get_unchecked(k + i);
+                    let uj = &mut *a_ptr.add(j);
+                    let ujl = &mut *a_ptr.add(j + l);
+                    if variable_time {
+                        self.inv_butterfly_vt(uj, ujl, zeta_inv, zeta_inv_shoup);
+                    } else {
+                        self.inv_butterfly(uj, ujl, zeta_inv, zeta_inv_shoup);
+                    }
+                })
+            });
+            l <<= 1;
+            m >>= 1;
+        }
+
+        self.run(threads, |t| {
+            let a_ptr = a.get();
+            let (start, end) = self.range(t, threads, self.size);
+            for i in start..end {
+                *a_ptr.add(i) = self
+                    .p
+                    .mul_shoup(*a_ptr.add(i), self.size_inv, self.size_inv_shoup)
+            }
+        })
+    }
+
+    /// Runs `f(t)` for each thread index `t` on the rayon global pool, and
+    /// returns once all of them are done.
+    fn run<F>(&self, threads: usize, f: F)
+    where
+        F: Fn(usize) + Sync + Send,
+    {
+        (0..threads).into_par_iter().for_each(f)
+    }
+
+    /// Returns the range of `n` elements assigned to the thread `t`.
+    const fn range(&self, t: usize, threads: usize, n: usize) -> (usize, usize) {
+        (t * n / threads, (t + 1) * n / threads)
+    }
+
+    /// Calls `f(i, j)` for the butterflies of indices in `start..end` of a
+    /// layer with distance `l`, where `i` is the index of the block and `j`
+    /// the index of the first element of the butterfly.
+    unsafe fn for_each_butterfly<F>(&self, start: usize, end: usize, l: usize, mut f: F)
+    where
+        F: FnMut(usize, usize),
+    {
+        let mut b = start;
+        while b < end {
+            let i = b / l;
+            let block_end = end.min((i + 1) * l);
+            let s = 2 * i * l + b % l;
+            for j in s..(s + block_end - b) {
+                f(i, j)
+            }
+            b = block_end;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{threads, PARALLEL_THRESHOLD};
+    use crate::{ntt::NttOperator, zq::primes::generate_prime, zq::Modulus};
+    use rand::thread_rng;
+
+    #[test]
+    fn parallel() {
+        let mut rng = thread_rng();
+        let size = PARALLEL_THRESHOLD;
+        let p = generate_prime(62, 2 * size as u64, u64::MAX >> 2).unwrap();
+        let q = Modulus::new(p).unwrap();
+        let op = NttOperator::new(&q, size).unwrap();
+
+        // The lazy forward NTT is always computed on a single thread.
+        let a = q.random_vec(size, &mut rng);
+        let mut expected = a.clone();
+        unsafe {
+            op.forward_vt_lazy(expected.as_mut_ptr());
+            q.reduce_vec(&mut expected);
+        }
+
+        for threads in [2, 3, 8] {
+            for variable_time in [false, true] {
+                let mut b = a.clone();
+                unsafe {
+                    op.forward_parallel(b.as_mut_ptr(), threads, variable_time);
+                }
+                assert_eq!(b, expected);
+                unsafe {
+                    op.backward_parallel(b.as_mut_ptr(), threads, variable_time);
+                }
+                assert_eq!(b, a);
+            }
+        }
+
+        // The NTT is not split further when running on a thread of the pool.
+        rayon::scope(|_| assert_eq!(threads(), 1));
+    }
+}
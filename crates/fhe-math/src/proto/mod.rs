@@ -4,3 +4,11 @@
 
 /// Protobuf for polynomials.
 pub mod rq;
+
+/// The version of the serialization format of the messages.
+///
+/// The version is only increased by changes which older releases cannot
+/// read; fields may be added without increasing it, since unknown fields are
+/// ignored when decoding. Messages of any version up to this one, including
+/// those serialized without a version by older releases, are deserialized.
+pub const FORMAT_VERSION: u32 = 1;
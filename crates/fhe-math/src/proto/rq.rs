@@ -11,6 +11,9 @@ pub struct Rq {
     pub coefficients: ::prost::alloc::vec::Vec<u8>,
     #[prost(bool, tag = "4")]
     pub allow_variable_time: bool,
+    /// The version of the format, 0 for the messages of older releases.
+    #[prost(uint32, tag = "5")]
+    pub version: u32,
 }
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, ::prost::Enumeration)]
 #[repr(i32)]
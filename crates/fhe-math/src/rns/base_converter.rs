@@ -0,0 +1,167 @@
+#![warn(missing_docs, unused_imports)]
+
+//! Fast base conversion between RNS bases.
+
+use super::RnsContext;
+use crate::{zq::Modulus, Error, Result};
+use itertools::{izip, Itertools};
+use ndarray::{Array2, ArrayView2, Axis};
+use num_bigint::BigUint;
+use num_bigint_dig::{BigUint as BigUintDig, ModInverse};
+use num_traits::{One, ToPrimitive};
+
+/// Fast base conversion from a basis `from` = (q_1, ..., q_k) of product q to
+/// a basis `to`, as described in <https://eprint.iacr.org/2016/510>.
+///
+/// For each target modulus p, the conversion computes
+/// sum_i [x_i * factor * (q / q_i)^(-1)]_{q_i} * (q / q_i) mod p,
+/// which is congruent to `factor * x + u * q` modulo p, for some integer
+/// 0 <= u < k. The conversion is therefore exact up to a small multiple of q,
+/// which the calling algorithm is expected to correct or tolerate.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BaseConverter {
+    from: Box<[Modulus]>,
+    to: Box<[Modulus]>,
+    tilde: Box<[u64]>,
+    tilde_shoup: Box<[u64]>,
+    star: Box<[Box<[u64]>]>,
+}
+
+impl BaseConverter {
+    /// Creates a fast base converter from the moduli `from` to the moduli
+    /// `to`, where the input is multiplied by `factor` during the conversion.
+    ///
+    /// Returns an error if `from` is empty or if its moduli are not coprime.
+    pub fn new(from: &[Modulus], to: &[Modulus], factor: u64) -> Result<Self> {
+        if from.is_empty() {
+            return Err(Error::EmptyModuli);
+        }
+        let product = from
+            .iter()
+            .fold(BigUint::one(), |acc, qi| acc * qi.modulus());
+
+        let mut tilde = Vec::with_capacity(from.len());
+        for qi in from {
+            let q_star_i = (&product / qi.modulus()) % qi.modulus();
+            let q_tilde_i = BigUintDig::from(q_star_i.to_u64().unwrap())
+                .mod_inverse(BigUintDig::from(qi.modulus()))
+                .and_then(|inv| inv.to_u64())
+                .ok_or(Error::ModuliNotCoprime)?;
+            tilde.push(qi.mul(q_tilde_i, qi.reduce(factor)));
+        }
+        let tilde_shoup = izip!(&tilde, from)
+            .map(|(t, qi)| qi.shoup(*t))
+            .collect_vec();
+
+        let star = to
+            .iter()
+            .map(|p| {
+                from.iter()
+                    .map(|qi| ((&product / qi.modulus()) % p.modulus()).to_u64().unwrap())
+                    .collect_vec()
+                    .into_boxed_slice()
+            })
+            .collect_vec();
+
+        Ok(Self {
+            from: from.to_vec().into_boxed_slice(),
+            to: to.to_vec().into_boxed_slice(),
+            tilde: tilde.into_boxed_slice(),
+            tilde_shoup: tilde_shoup.into_boxed_slice(),
+            star: star.into_boxed_slice(),
+        })
+    }
+
+    /// Returns the moduli of the source basis.
+    pub fn from_moduli(&self) -> &[Modulus] {
+        &self.from
+    }
+
+    /// Returns the moduli of the target basis.
+    pub fn to_moduli(&self) -> &[Modulus] {
+        &self.to
+    }
+
+    /// Converts the coefficients `input`, with one row per modulus of the
+    /// source basis, into coefficients with one row per modulus of the target
+    /// basis.
+    ///
+    /// Aborts if the number of rows of `input` differs from the size of the
+    /// source basis in debug mode.
+    pub fn convert(&self, input: ArrayView2<u64>) -> Array2<u64> {
+        debug_assert_eq!(input.nrows(), self.from.len());
+
+        let mut y = input.to_owned();
+        izip!(
+            y.outer_iter_mut(),
+            self.from.iter(),
+            self.tilde.iter(),
+            self.tilde_shoup.iter()
+        )
+        .for_each(|(mut yi, qi, t, t_shoup)| {
+            yi.iter_mut()
+                .for_each(|yij| *yij = qi.mul_shoup(*yij, *t, *t_shoup))
+        });
+
+        let mut out = Array2::<u64>::zeros((self.to.len(), input.ncols()));
+        izip!(out.outer_iter_mut(), self.to.iter(), self.star.iter()).for_each(
+            |(mut out_j, pj, star_j)| {
+                izip!(out_j.iter_mut(), y.axis_iter(Axis(1))).for_each(|(out_jk, y_k)| {
+                    let mut acc = 0u128;
+                    for (yik, star_ij) in izip!(y_k.iter(), star_j.iter()) {
+                        acc += pj.lazy_reduce_u128((*yik as u128) * (*star_ij as u128)) as u128;
+                    }
+                    *out_jk = pj.reduce_u128(acc)
+                })
+            },
+        );
+        out
+    }
+}
+
+impl RnsContext {
+    /// Creates a fast base converter from the moduli of this context to the
+    /// moduli of the context `to`.
+    pub fn base_converter(&self, to: &RnsContext) -> Result<BaseConverter> {
+        BaseConverter::new(&self.moduli, &to.moduli, 1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::rns::RnsContext;
+    use ndarray::Array2;
+    use num_bigint::BigUint;
+    use rand::{thread_rng, RngCore};
+    use std::error::Error;
+
+    #[test]
+    fn convert() -> Result<(), Box<dyn Error>> {
+        let mut rng = thread_rng();
+        let from = RnsContext::new(&[4, 15, 1153, 4611686018326724609])?;
+        let to = RnsContext::new(&[4611686018309947393, 1009, 4611686018232352769])?;
+        let converter = from.base_converter(&to)?;
+        assert!(to.base_converter(&RnsContext::new(&[2])?).is_ok());
+
+        let ncols = 20;
+        let values = (0..ncols)
+            .map(|_| BigUint::from(rng.next_u64()) * rng.next_u64() % from.modulus())
+            .collect::<Vec<_>>();
+        let mut input = Array2::zeros((4, ncols));
+        for (mut column, v) in input.columns_mut().into_iter().zip(values.iter()) {
+            column
+                .iter_mut()
+                .zip(from.project(v))
+                .for_each(|(c, r)| *c = r);
+        }
+
+        let output = converter.convert(input.view());
+        assert_eq!(output.dim(), (3, ncols));
+        for (column, v) in output.columns().into_iter().zip(values.iter()) {
+            // The output is v + u * q for some u < 4.
+            let lifted = to.lift(column);
+            assert!((0u64..4).any(|u| lifted == (v + from.modulus() * u) % to.modulus()));
+        }
+        Ok(())
+    }
+}
@@ -10,9 +10,11 @@ use num_bigint_dig::{BigInt as BigIntDig, BigUint as BigUintDig, ExtendedGcd, Mo
 use num_traits::{cast::ToPrimitive, One, Zero};
 use std::{cmp::Ordering, fmt::Debug};
 
+mod base_converter;
 mod scaler;
 
-pub use scaler::{RnsScaler, ScalingFactor};
+pub use base_converter::BaseConverter;
+pub use scaler::{RnsScaler, RnsScalerBuilder, RoundingMode, ScalingFactor};
 
 /// Context for a Residue Number System.
 #[derive(Default, Clone, PartialEq, Eq)]
@@ -46,7 +48,7 @@ impl RnsContext {
     /// Returns an error if the list is empty, or if the moduli are no coprime.
     pub fn new(moduli_u64: &[u64]) -> Result<Self> {
         if moduli_u64.is_empty() {
-            Err(Error::Default("The list of moduli is empty".to_string()))
+            Err(Error::EmptyModuli)
         } else {
             let mut product = BigUint::one();
             let mut product_dig = BigUintDig::one();
@@ -58,7 +60,7 @@ impl RnsContext {
                         let (d, _, _) = BigUintDig::from(moduli_u64[i])
                             .extended_gcd(&BigUintDig::from(moduli_u64[j]));
                         if d.cmp(&BigIntDig::from(1)) != Ordering::Equal {
-                            return Err(Error::Default("The moduli are not coprime".to_string()));
+                            return Err(Error::ModuliNotCoprime);
                         }
                     }
                 }
@@ -108,10 +110,23 @@ impl RnsContext {
     }
 
     /// Returns the product of the moduli used when creating the RNS context.
+    ///
+    /// ```
+    /// # use fhe_math::rns::RnsContext;
+    /// # use num_bigint::BigUint;
+    /// let rns = RnsContext::new(&[4, 15, 1153])?;
+    /// assert_eq!(rns.modulus(), &BigUint::from(4u64 * 15 * 1153));
+    /// # Ok::<(), fhe_math::Error>(())
+    /// ```
     pub const fn modulus(&self) -> &BigUint {
         &self.product
     }
 
+    /// Returns the moduli used when creating the RNS context.
+    pub fn moduli(&self) -> &[u64] {
+        &self.moduli_u64
+    }
+
     /// Project a BigUint into its rests.
     pub fn project(&self, a: &BigUint) -> Vec<u64> {
         let mut rests = Vec::with_capacity(self.moduli_u64.len());
@@ -121,10 +136,24 @@ impl RnsContext {
         rests
     }
 
-    /// Lift rests into a BigUint.
+    /// Lift rests into a BigUint, using the Chinese remainder theorem: the
+    /// output is the unique integer in `[0, q)` congruent to the i-th rest
+    /// modulo the i-th modulus, where `q` is the [product of the
+    /// moduli](Self::modulus).
     ///
     /// Aborts if the number of rests is different than the number of moduli in
     /// debug mode.
+    ///
+    /// ```
+    /// # use fhe_math::rns::RnsContext;
+    /// # use ndarray::ArrayView1;
+    /// # use num_bigint::BigUint;
+    /// let rns = RnsContext::new(&[4, 15, 1153])?;
+    /// let rests = rns.project(&BigUint::from(12345u64));
+    /// assert_eq!(rests, vec![1, 0, 815]);
+    /// assert_eq!(rns.lift(ArrayView1::from(&rests)), BigUint::from(12345u64));
+    /// # Ok::<(), fhe_math::Error>(())
+    /// ```
     pub fn lift(&self, rests: ArrayView1<u64>) -> BigUint {
         let mut result = BigUint::zero();
         izip!(rests.iter(), self.garner.iter())
@@ -132,10 +161,41 @@ impl RnsContext {
         result % &self.product
     }
 
-    /// Getter for the i-th garner coefficient.
+    /// Getter for the i-th garner coefficient, i.e., `q*_i * q~_i` where
+    /// `q*_i = q / q_i` and `q~_i = (q / q_i)^(-1) mod q_i`. This coefficient
+    /// is congruent to 1 modulo the i-th modulus `q_i` and to 0 modulo the
+    /// other moduli, so that `sum_i garner_i * x_i mod q` is the
+    /// [lift](Self::lift) of the rests `x_i`.
+    ///
+    /// Returns None if i is not smaller than the number of moduli.
+    ///
+    /// ```
+    /// # use fhe_math::rns::RnsContext;
+    /// # use num_bigint::BigUint;
+    /// let rns = RnsContext::new(&[4, 15, 1153])?;
+    /// let garner = rns.get_garner(1).unwrap();
+    /// assert_eq!(garner % 4u64, BigUint::from(0u64));
+    /// assert_eq!(garner % 15u64, BigUint::from(1u64));
+    /// assert_eq!(garner % 1153u64, BigUint::from(0u64));
+    /// assert!(rns.get_garner(3).is_none());
+    /// # Ok::<(), fhe_math::Error>(())
+    /// ```
     pub fn get_garner(&self, i: usize) -> Option<&BigUint> {
         self.garner.get(i)
     }
+
+    /// Getter for `q*_i = q / q_i`, the product of all the moduli but the i-th
+    /// one. Returns None if i is not smaller than the number of moduli.
+    pub fn get_q_star(&self, i: usize) -> Option<&BigUint> {
+        self.q_star.get(i)
+    }
+
+    /// Getter for `q~_i = (q / q_i)^(-1) mod q_i`, the inverse of
+    /// [`q*_i`](Self::get_q_star) modulo the i-th modulus. Returns None if i
+    /// is not smaller than the number of moduli.
+    pub fn get_q_tilde(&self, i: usize) -> Option<u64> {
+        self.q_tilde.get(i).copied()
+    }
 }
 
 #[cfg(test)]
@@ -156,13 +216,13 @@ mod tests {
 
         let e = RnsContext::new(&[]);
         assert!(e.is_err());
-        assert_eq!(e.unwrap_err().to_string(), "The list of moduli is empty");
+        assert_eq!(e.unwrap_err(), crate::Error::EmptyModuli);
         let e = RnsContext::new(&[2, 4]);
         assert!(e.is_err());
-        assert_eq!(e.unwrap_err().to_string(), "The moduli are not coprime");
+        assert_eq!(e.unwrap_err(), crate::Error::ModuliNotCoprime);
         let e = RnsContext::new(&[2, 3, 5, 30]);
         assert!(e.is_err());
-        assert_eq!(e.unwrap_err().to_string(), "The moduli are not coprime");
+        assert_eq!(e.unwrap_err(), crate::Error::ModuliNotCoprime);
     }
 
     #[test]
@@ -176,6 +236,17 @@ mod tests {
         }
         assert!(rns.get_garner(3).is_none());
 
+        for (i, qi) in [4u64, 15, 1153].into_iter().enumerate() {
+            let q_star = rns.get_q_star(i).unwrap();
+            let q_tilde = rns.get_q_tilde(i).unwrap();
+            assert_eq!(q_star * qi, *rns.modulus());
+            assert_eq!((q_star * q_tilde) % qi, BigUint::from(1u64));
+            assert_eq!(&(q_star * q_tilde), rns.get_garner(i).unwrap());
+        }
+        assert!(rns.get_q_star(3).is_none());
+        assert!(rns.get_q_tilde(3).is_none());
+        assert_eq!(rns.moduli(), &[4, 15, 1153]);
+
         Ok(())
     }
 
@@ -3,6 +3,7 @@
 //! RNS scaler inspired from Remark 3.2 of <https://eprint.iacr.org/2021/204.pdf>.
 
 use super::RnsContext;
+use crate::{Error, Result};
 use ethnum::{u256, U256};
 use itertools::{izip, Itertools};
 use ndarray::{ArrayView1, ArrayViewMut1};
@@ -39,6 +40,79 @@ impl ScalingFactor {
     }
 }
 
+/// Rounding applied to the scaled values.
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RoundingMode {
+    /// The input is lifted in (-q/2, q/2], and the scaled value is rounded to
+    /// the nearest integer, with ties rounded up.
+    #[default]
+    Centered,
+    /// The input is lifted in [0, q), and the scaled value is rounded to the
+    /// nearest integer, with ties rounded up.
+    Round,
+    /// The input is lifted in [0, q), and the scaled value is floored.
+    Floor,
+}
+
+/// Builder for a [`RnsScaler`] by an arbitrary rational.
+#[derive(Debug)]
+pub struct RnsScalerBuilder {
+    from: Arc<RnsContext>,
+    to: Arc<RnsContext>,
+    numerator: BigUint,
+    denominator: BigUint,
+    rounding_mode: RoundingMode,
+}
+
+impl RnsScalerBuilder {
+    /// Creates a builder of a scaler from the context `from` to the context
+    /// `to`, which scales by 1 with the [`RoundingMode::Centered`] rounding by
+    /// default.
+    pub fn new(from: &Arc<RnsContext>, to: &Arc<RnsContext>) -> Self {
+        Self {
+            from: from.clone(),
+            to: to.clone(),
+            numerator: BigUint::one(),
+            denominator: BigUint::one(),
+            rounding_mode: RoundingMode::default(),
+        }
+    }
+
+    /// Sets the numerator of the scaling factor.
+    pub fn set_numerator(&mut self, numerator: &BigUint) -> &mut Self {
+        self.numerator = numerator.clone();
+        self
+    }
+
+    /// Sets the denominator of the scaling factor.
+    pub fn set_denominator(&mut self, denominator: &BigUint) -> &mut Self {
+        self.denominator = denominator.clone();
+        self
+    }
+
+    /// Sets the rounding mode.
+    pub fn set_rounding_mode(&mut self, rounding_mode: RoundingMode) -> &mut Self {
+        self.rounding_mode = rounding_mode;
+        self
+    }
+
+    /// Builds the scaler.
+    ///
+    /// Returns an error if the denominator is 0.
+    pub fn build(&self) -> Result<RnsScaler> {
+        if self.denominator.is_zero() {
+            return Err(Error::DivisionByZero);
+        }
+        let mut scaler = RnsScaler::new(
+            &self.from,
+            &self.to,
+            ScalingFactor::new(&self.numerator, &self.denominator),
+        );
+        scaler.rounding_mode = self.rounding_mode;
+        Ok(scaler)
+    }
+}
+
 /// Scaler for a RNS context.
 /// This is a helper struct to perform RNS scaling.
 #[derive(Default, Debug, Clone, PartialEq, Eq)]
@@ -46,6 +120,7 @@ pub struct RnsScaler {
     from: Arc<RnsContext>,
     to: Arc<RnsContext>,
     scaling_factor: ScalingFactor,
+    rounding_mode: RoundingMode,
 
     gamma: Box<[u64]>,
     gamma_shoup: Box<[u64]>,
@@ -147,6 +222,7 @@ impl RnsScaler {
             from: from.clone(),
             to: to.clone(),
             scaling_factor,
+            rounding_mode: RoundingMode::Centered,
             gamma: gamma.into_boxed_slice(),
             gamma_shoup: gamma_shoup.into_boxed_slice(),
             theta_gamma_lo,
@@ -163,6 +239,11 @@ impl RnsScaler {
         }
     }
 
+    /// Returns the rounding mode of the scaler.
+    pub const fn rounding_mode(&self) -> RoundingMode {
+        self.rounding_mode
+    }
+
     // Let's define gamma = round(numerator * input / denominator)
     // and theta_gamma such that theta_gamma = numerator * input / denominator -
     // gamma. This function projects gamma in the RNS context, and scales
@@ -244,6 +325,18 @@ impl RnsScaler {
         debug_assert!(!out.is_empty());
         debug_assert!(starting_index + out.len() <= self.to.moduli_u64.len());
 
+        if self.rounding_mode != RoundingMode::Centered {
+            // The scaling of the non-centered lift is computed exactly.
+            let mut x = self.from.lift(rests) * &self.scaling_factor.numerator;
+            if self.rounding_mode == RoundingMode::Round {
+                x += &self.scaling_factor.denominator >> 1;
+            }
+            x /= &self.scaling_factor.denominator;
+            izip!(out.iter_mut(), &self.to.moduli_u64[starting_index..])
+                .for_each(|(out_i, qi)| *out_i = (&x % qi).to_u64().unwrap());
+            return;
+        }
+
         // First, let's compute the inner product of the rests with theta_omega.
         let mut sum_theta_garner = u256::ZERO;
         for (thetag_lo, thetag_hi, ri) in izip!(
@@ -344,7 +437,7 @@ impl RnsScaler {
 mod tests {
     use std::{error::Error, sync::Arc};
 
-    use super::RnsScaler;
+    use super::{RnsScaler, RnsScalerBuilder, RoundingMode};
     use crate::rns::{scaler::ScalingFactor, RnsContext};
     use fhe_util::catch_unwind;
     use ndarray::ArrayView1;
@@ -365,6 +458,42 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn rounding_modes() -> Result<(), Box<dyn Error>> {
+        let q = Arc::new(RnsContext::new(&[4u64, 4611686018326724609, 1153])?);
+        let r = Arc::new(RnsContext::new(&[4611686018309947393, 1009])?);
+        let mut rng = thread_rng();
+        assert!(RnsScalerBuilder::new(&q, &r)
+            .set_denominator(&BigUint::zero())
+            .build()
+            .is_err());
+
+        let n = BigUint::from(3u64);
+        let d = BigUint::from(1000u64);
+        let mut builder = RnsScalerBuilder::new(&q, &r);
+        builder.set_numerator(&n).set_denominator(&d);
+        assert_eq!(
+            builder.build()?,
+            RnsScaler::new(&q, &r, ScalingFactor::new(&n, &d))
+        );
+        let round = builder.set_rounding_mode(RoundingMode::Round).build()?;
+        let floor = builder.set_rounding_mode(RoundingMode::Floor).build()?;
+        assert_eq!(floor.rounding_mode(), RoundingMode::Floor);
+        for _ in 0..100 {
+            let x = q.project(&(BigUint::from(rng.next_u64()) * rng.next_u64() % q.modulus()));
+            let x_lift = q.lift(ArrayView1::from(&x));
+            assert_eq!(
+                round.scale_new((&x).into(), 2),
+                r.project(&((&x_lift * &n + (&d >> 1)) / &d))
+            );
+            assert_eq!(
+                floor.scale_new((&x).into(), 2),
+                r.project(&(&x_lift * &n / &d))
+            );
+        }
+        Ok(())
+    }
+
     #[test]
     fn scale_same_context() -> Result<(), Box<dyn Error>> {
         let ntests = 1000;
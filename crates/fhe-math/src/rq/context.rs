@@ -1,8 +1,22 @@
 use itertools::Itertools;
 use num_bigint::BigUint;
-use std::{fmt::Debug, sync::Arc};
+use std::{
+    collections::HashMap,
+    fmt::Debug,
+    sync::{Arc, Mutex, OnceLock, Weak},
+};
 
+use super::{convolution::Convolution, MIN_DEGREE};
 use crate::{ntt::NttOperator, rns::RnsContext, zq::Modulus, Error, Result};
+use fhe_util::inverse;
+
+/// Process-wide registry of the contexts created with [`Context::new_arc`],
+/// indexed by moduli and degree. The contexts are not kept alive by the
+/// registry, but are shared while they are in use.
+static REGISTRY: OnceLock<Mutex<HashMap<RegistryKey, Weak<Context>>>> = OnceLock::new();
+
+/// Moduli and degree of a context in the registry.
+type RegistryKey = (Box<[u64]>, usize);
 
 /// Struct that holds the context associated with elements in rq.
 #[derive(Default, Clone, PartialEq, Eq)]
@@ -10,7 +24,11 @@ pub struct Context {
     pub(crate) moduli: Box<[u64]>,
     pub(crate) q: Box<[Modulus]>,
     pub(crate) rns: Arc<RnsContext>,
-    pub(crate) ops: Box<[NttOperator]>,
+    /// The NTT operators of the moduli which support the NTT of size `degree`.
+    pub(crate) ops: Box<[Option<NttOperator>]>,
+    /// The convolutions of the other moduli, for which the Ntt representation
+    /// is the PowerBasis representation.
+    pub(crate) convolutions: Box<[Option<Convolution>]>,
     pub(crate) degree: usize,
     pub(crate) bitrev: Box<[usize]>,
     pub(crate) inv_last_qi_mod_qj: Box<[u64]>,
@@ -37,27 +55,31 @@ impl Debug for Context {
 impl Context {
     /// Creates a context from a list of moduli and a polynomial degree.
     ///
-    /// Returns an error if the moduli are not primes less than 62 bits which
-    /// supports the NTT of size `degree`.
+    /// Returns an error if the moduli are not coprime integers less than 62
+    /// bits. The moduli can be primes, or products of two primes supporting
+    /// the NTT of size `degree`. The multiplication modulo the moduli which do
+    /// not support the NTT of size `degree` falls back to a (slower)
+    /// negacyclic convolution.
     pub fn new(moduli: &[u64], degree: usize) -> Result<Self> {
-        if !degree.is_power_of_two() || degree < 8 {
-            Err(Error::Default(
-                "The degree is not a power of two larger or equal to 8".to_string(),
-            ))
+        if !degree.is_power_of_two() || degree < MIN_DEGREE {
+            Err(Error::InvalidDegree(degree))
         } else {
             let mut q = Vec::with_capacity(moduli.len());
             let rns = Arc::new(RnsContext::new(moduli)?);
             let mut ops = Vec::with_capacity(moduli.len());
+            let mut convolutions = Vec::with_capacity(moduli.len());
             for modulus in moduli {
                 let qi = Modulus::new(*modulus)?;
-                if let Some(op) = NttOperator::new(&qi, degree) {
-                    q.push(qi);
-                    ops.push(op);
+                let op = NttOperator::new(&qi, degree);
+                if op.is_some() {
+                    convolutions.push(None);
+                } else if let Some(convolution) = Convolution::new(&qi, degree) {
+                    convolutions.push(Some(convolution));
                 } else {
-                    return Err(Error::Default(
-                        "Impossible to construct a Ntt operator".to_string(),
-                    ));
+                    return Err(Error::UnsupportedModulus(*modulus));
                 }
+                q.push(qi);
+                ops.push(op);
             }
             let bitrev = (0..degree)
                 .map(|j| j.reverse_bits() >> (degree.leading_zeros() + 1))
@@ -67,13 +89,13 @@ impl Context {
             let mut inv_last_qi_mod_qj_shoup = vec![];
             let q_last = moduli.last().unwrap();
             for qi in &q[..q.len() - 1] {
-                let inv = qi.inv(qi.reduce(*q_last)).unwrap();
+                let inv = inverse(qi.reduce(*q_last), qi.modulus()).unwrap();
                 inv_last_qi_mod_qj.push(inv);
                 inv_last_qi_mod_qj_shoup.push(qi.shoup(inv));
             }
 
             let next_context = if moduli.len() >= 2 {
-                Some(Arc::new(Context::new(&moduli[..moduli.len() - 1], degree)?))
+                Some(Context::new_arc(&moduli[..moduli.len() - 1], degree)?)
             } else {
                 None
             };
@@ -83,6 +105,7 @@ impl Context {
                 q: q.into_boxed_slice(),
                 rns,
                 ops: ops.into_boxed_slice(),
+                convolutions: convolutions.into_boxed_slice(),
                 degree,
                 bitrev: bitrev.into_boxed_slice(),
                 inv_last_qi_mod_qj: inv_last_qi_mod_qj.into_boxed_slice(),
@@ -93,8 +116,31 @@ impl Context {
     }
 
     /// Creates a context in an `Arc`.
+    ///
+    /// The contexts are shared through a process-wide registry: if a context
+    /// with the same moduli and degree is alive, it is returned instead of
+    /// being recomputed. Since the chain of next contexts is created with this
+    /// function, contexts whose moduli share a prefix, such as the contexts at
+    /// the different levels of parameters, share their next contexts.
     pub fn new_arc(moduli: &[u64], degree: usize) -> Result<Arc<Self>> {
-        Self::new(moduli, degree).map(Arc::new)
+        let registry = REGISTRY.get_or_init(Default::default);
+        let key = (moduli.to_vec().into_boxed_slice(), degree);
+        if let Some(ctx) = registry.lock().unwrap().get(&key).and_then(Weak::upgrade) {
+            return Ok(ctx);
+        }
+
+        // The registry is not locked during the creation, which creates the
+        // next contexts recursively.
+        let ctx = Arc::new(Self::new(moduli, degree)?);
+        let mut registry = registry.lock().unwrap();
+        registry.retain(|_, ctx| ctx.strong_count() > 0);
+        match registry.get(&key).and_then(Weak::upgrade) {
+            Some(existing) => Ok(existing),
+            None => {
+                registry.insert(key, Arc::downgrade(&ctx));
+                Ok(ctx)
+            }
+        }
     }
 
     /// Returns the modulus as a BigUint.
@@ -112,6 +158,12 @@ impl Context {
         &self.q
     }
 
+    /// Returns whether the multiplication modulo some of the moduli uses a
+    /// negacyclic convolution instead of the NTT.
+    pub fn has_convolutions(&self) -> bool {
+        self.convolutions.iter().any(Option::is_some)
+    }
+
     /// Returns the number of iterations to switch to a children context.
     /// Returns an error if the context provided is not a child context.
     pub fn niterations_to(&self, context: &Arc<Context>) -> Result<usize> {
@@ -140,9 +192,7 @@ impl Context {
     /// Returns the context after `i` iterations.
     pub fn context_at_level(&self, i: usize) -> Result<Arc<Self>> {
         if i >= self.moduli.len() {
-            Err(Error::Default(
-                "No context at the specified level".to_string(),
-            ))
+            Err(Error::InvalidLevel(i))
         } else {
             let mut current_ctx = Arc::new(self.clone());
             for _ in 0..i {
@@ -174,18 +224,28 @@ mod tests {
             // modulus is = 1 modulo 2 * 8
             assert!(Context::new(&[*modulus], 16).is_ok());
 
-            if supports_ntt(*modulus, 128) {
-                assert!(Context::new(&[*modulus], 128).is_ok());
-            } else {
-                assert!(Context::new(&[*modulus], 128).is_err());
-            }
+            // Moduli which do not support the NTT fall back to a convolution
+            let ctx = Context::new(&[*modulus], 128).unwrap();
+            assert_eq!(ctx.has_convolutions(), !supports_ntt(*modulus, 128));
         }
 
         // All moduli in MODULI are = 1 modulo 2 * 8
-        assert!(Context::new(MODULI, 16).is_ok());
+        assert!(!Context::new(MODULI, 16).unwrap().has_convolutions());
+
+        // 1153 != 1 moduli 2 * 128
+        assert!(Context::new(MODULI, 128).unwrap().has_convolutions());
+
+        // The products of two primes supporting the NTT use the NTT
+        assert!(!Context::new(&[12289 * 40961, 1153], 16)
+            .unwrap()
+            .has_convolutions());
 
-        // This should fail since 1153 != 1 moduli 2 * 128
-        assert!(Context::new(MODULI, 128).is_err());
+        // The degree must be a power of two
+        assert!(Context::new(MODULI, 100).is_err());
+
+        // The degree must be at least 2
+        assert!(Context::new(MODULI, 2).is_ok());
+        assert!(Context::new(MODULI, 1).is_err());
     }
 
     #[test]
@@ -209,6 +269,24 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn registry() -> Result<(), Box<dyn Error>> {
+        let context = Context::new_arc(MODULI, 16)?;
+        assert!(Arc::ptr_eq(&context, &Context::new_arc(MODULI, 16)?));
+        assert!(!Arc::ptr_eq(&context, &Context::new_arc(MODULI, 32)?));
+
+        // The contexts with a prefix of the moduli share the chain of contexts.
+        let prefix = Context::new_arc(&MODULI[..3], 16)?;
+        assert!(Arc::ptr_eq(&prefix, &context.context_at_level(2)?));
+        let other = Context::new(MODULI, 16)?;
+        assert!(Arc::ptr_eq(
+            other.next_context.as_ref().unwrap(),
+            context.next_context.as_ref().unwrap()
+        ));
+
+        Ok(())
+    }
+
     #[test]
     fn niterations_to() -> Result<(), Box<dyn Error>> {
         // A context should have a children pointing to a context with one less modulus.
@@ -1,8 +1,13 @@
 //! Implementation of conversions from and to polynomials.
 
-use super::{traits::TryConvertFrom, Context, Poly, Representation};
+use super::{
+    traits::TryConvertFrom, variable_time_allowed, Context, Poly, Representation, MIN_DEGREE,
+};
 use crate::{
-    proto::rq::{Representation as RepresentationProto, Rq},
+    proto::{
+        rq::{Representation as RepresentationProto, Rq},
+        FORMAT_VERSION,
+    },
     Error, Result,
 };
 use itertools::{izip, Itertools};
@@ -15,7 +20,8 @@ impl From<&Poly> for Rq {
     fn from(p: &Poly) -> Self {
         assert!(!p.has_lazy_coefficients);
 
-        let mut q = p.clone();
+        // The polynomial may be secret, so the intermediate copies are wiped.
+        let mut q = Zeroizing::new(p.clone());
         if p.representation != Representation::PowerBasis {
             q.change_representation(Representation::PowerBasis);
         }
@@ -32,6 +38,7 @@ impl From<&Poly> for Rq {
                 proto.representation = RepresentationProto::Nttshoup as i32;
             }
         }
+        // The coefficients modulo qi are packed on ceil(log2(qi)) bits each.
         let mut serialization_length = 0;
         p.ctx
             .q
@@ -39,11 +46,14 @@ impl From<&Poly> for Rq {
             .for_each(|qi| serialization_length += qi.serialization_length(p.ctx.degree));
         let mut serialization = Vec::with_capacity(serialization_length);
 
-        izip!(q.coefficients.outer_iter(), p.ctx.q.iter())
-            .for_each(|(v, qi)| serialization.append(&mut qi.serialize_vec(v.as_slice().unwrap())));
+        izip!(q.coefficients.outer_iter(), p.ctx.q.iter()).for_each(|(v, qi)| {
+            serialization
+                .extend_from_slice(&Zeroizing::new(qi.serialize_vec(v.as_slice().unwrap())))
+        });
         proto.coefficients = serialization;
         proto.degree = p.ctx.degree as u32;
         proto.allow_variable_time = p.allow_variable_time_computations;
+        proto.version = FORMAT_VERSION;
         proto
     }
 }
@@ -59,6 +69,7 @@ impl TryConvertFrom<Vec<u64>> for Poly {
         R: Into<Option<Representation>>,
     {
         let repr = representation.into();
+        let len = v.len();
         match repr {
             Some(Representation::Ntt) => {
                 if let Ok(coefficients) = Array2::from_shape_vec((ctx.q.len(), ctx.degree), v) {
@@ -68,12 +79,14 @@ impl TryConvertFrom<Vec<u64>> for Poly {
                         allow_variable_time_computations: variable_time,
                         coefficients,
                         coefficients_shoup: None,
+                        cache: None,
                         has_lazy_coefficients: false,
                     })
                 } else {
-                    Err(Error::Default(
-                        "In Ntt representation, all coefficients must be specified".to_string(),
-                    ))
+                    Err(Error::InvalidLength {
+                        expected: ctx.q.len() * ctx.degree,
+                        found: len,
+                    })
                 }
             }
             Some(Representation::NttShoup) => {
@@ -84,15 +97,16 @@ impl TryConvertFrom<Vec<u64>> for Poly {
                         allow_variable_time_computations: variable_time,
                         coefficients,
                         coefficients_shoup: None,
+                        cache: None,
                         has_lazy_coefficients: false,
                     };
                     p.compute_coefficients_shoup();
                     Ok(p)
                 } else {
-                    Err(Error::Default(
-                        "In NttShoup representation, all coefficients must be specified"
-                            .to_string(),
-                    ))
+                    Err(Error::InvalidLength {
+                        expected: ctx.q.len() * ctx.degree,
+                        found: len,
+                    })
                 }
             }
             Some(Representation::PowerBasis) => {
@@ -105,11 +119,12 @@ impl TryConvertFrom<Vec<u64>> for Poly {
                         allow_variable_time_computations: variable_time,
                         coefficients,
                         coefficients_shoup: None,
+                        cache: None,
                         has_lazy_coefficients: false,
                     })
                 } else if v.len() <= ctx.degree {
                     let mut out = Self::zero(ctx, repr.unwrap());
-                    if variable_time {
+                    if variable_time_allowed(variable_time) {
                         unsafe {
                             izip!(out.coefficients.outer_iter_mut(), ctx.q.iter()).for_each(
                                 |(mut w, qi)| {
@@ -132,13 +147,13 @@ impl TryConvertFrom<Vec<u64>> for Poly {
                     }
                     Ok(out)
                 } else {
-                    Err(Error::Default("In PowerBasis representation, either all coefficients must be specified, or only coefficients up to the degree".to_string()))
+                    Err(Error::InvalidLength {
+                        expected: ctx.degree,
+                        found: v.len(),
+                    })
                 }
             }
-            None => Err(Error::Default(
-                "When converting from a vector, the representation needs to be specified"
-                    .to_string(),
-            )),
+            None => Err(Error::MissingRepresentation),
         }
     }
 }
@@ -153,28 +168,32 @@ impl TryConvertFrom<&Rq> for Poly {
     where
         R: Into<Option<Representation>>,
     {
+        if value.version > FORMAT_VERSION {
+            return Err(Error::UnsupportedVersion(value.version));
+        }
+
         let repr = value
             .representation
             .try_into()
-            .map_err(|_| Error::Default("Invalid representation".to_string()))?;
+            .map_err(|_| Error::Serialization("Invalid representation".to_string()))?;
         let representation_from_proto = match repr {
             RepresentationProto::Powerbasis => Representation::PowerBasis,
             RepresentationProto::Ntt => Representation::Ntt,
             RepresentationProto::Nttshoup => Representation::NttShoup,
-            _ => return Err(Error::Default("Unknown representation".to_string())),
+            _ => return Err(Error::Serialization("Unknown representation".to_string())),
         };
 
         let variable_time = variable_time || value.allow_variable_time;
 
         if let Some(r) = representation.into() as Option<Representation> {
             if r != representation_from_proto {
-                return Err(Error::Default("The representation asked for does not match the representation in the serialization".to_string()));
+                return Err(Error::IncorrectRepresentation(representation_from_proto, r));
             }
         }
 
         let degree = value.degree as usize;
-        if degree % 8 != 0 || degree < 8 {
-            return Err(Error::Default("Invalid degree".to_string()));
+        if !degree.is_power_of_two() || degree < MIN_DEGREE {
+            return Err(Error::InvalidDegree(degree));
         }
 
         let mut expected_nbytes = 0;
@@ -182,7 +201,10 @@ impl TryConvertFrom<&Rq> for Poly {
             .iter()
             .for_each(|qi| expected_nbytes += qi.serialization_length(degree));
         if value.coefficients.len() != expected_nbytes {
-            return Err(Error::Default("Invalid coefficients".to_string()));
+            return Err(Error::InvalidLength {
+                expected: expected_nbytes,
+                found: value.coefficients.len(),
+            });
         }
 
         let mut power_basis_coefficients = Vec::with_capacity(ctx.q.len() * ctx.degree);
@@ -190,8 +212,8 @@ impl TryConvertFrom<&Rq> for Poly {
         for i in 0..ctx.q.len() {
             let qi = &ctx.q[i];
             let size = qi.serialization_length(degree);
-            let mut v = qi.deserialize_vec(&value.coefficients[index..index + size]);
-            power_basis_coefficients.append(&mut v);
+            let v = Zeroizing::new(qi.deserialize_vec(&value.coefficients[index..index + size]));
+            power_basis_coefficients.extend_from_slice(&v[..degree]);
             index += size;
         }
 
@@ -217,16 +239,29 @@ impl TryConvertFrom<Array2<u64>> for Poly {
         R: Into<Option<Representation>>,
     {
         if a.shape() != [ctx.q.len(), ctx.degree] {
-            Err(Error::Default(
-                "The array of coefficient does not have the correct shape".to_string(),
-            ))
+            Err(Error::InvalidLength {
+                expected: ctx.q.len() * ctx.degree,
+                found: a.len(),
+            })
+        } else if izip!(a.outer_iter(), ctx.moduli.iter())
+            .any(|(ai, qi)| ai.iter().any(|aij| aij >= qi))
+        {
+            Err(Error::UnreducedCoefficients)
         } else if let Some(repr) = representation.into() {
+            // The coefficients are only copied when the array is not in standard
+            // layout, since the rows are accessed as slices.
+            let coefficients = if a.is_standard_layout() {
+                a
+            } else {
+                a.as_standard_layout().into_owned()
+            };
             let mut p = Self {
                 ctx: ctx.clone(),
                 representation: repr,
                 allow_variable_time_computations: variable_time,
-                coefficients: a,
+                coefficients,
                 coefficients_shoup: None,
+                cache: None,
                 has_lazy_coefficients: false,
             };
             if p.representation == Representation::NttShoup {
@@ -234,7 +269,7 @@ impl TryConvertFrom<Array2<u64>> for Poly {
             }
             Ok(p)
         } else {
-            Err(Error::Default("When converting from a 2-dimensional array, the representation needs to be specified".to_string()))
+            Err(Error::MissingRepresentation)
         }
     }
 }
@@ -263,19 +298,20 @@ impl<'a> TryConvertFrom<&'a [i64]> for Poly {
     where
         R: Into<Option<Representation>>,
     {
-        if representation.into() != Some(Representation::PowerBasis) {
-            Err(Error::Default(
-                "Converting signed integer require to import in PowerBasis representation"
-                    .to_string(),
+        let repr = representation.into().ok_or(Error::MissingRepresentation)?;
+        if repr != Representation::PowerBasis {
+            Err(Error::IncorrectRepresentation(
+                repr,
+                Representation::PowerBasis,
             ))
         } else if v.len() <= ctx.degree {
             let mut out = Self::zero(ctx, Representation::PowerBasis);
-            if variable_time {
+            if variable_time_allowed(variable_time) {
                 unsafe { out.allow_variable_time_computations() }
             }
             izip!(out.coefficients.outer_iter_mut(), ctx.q.iter()).for_each(|(mut w, qi)| {
                 let wi = w.as_slice_mut().unwrap();
-                if variable_time {
+                if variable_time_allowed(variable_time) {
                     unsafe { wi[..v.len()].copy_from_slice(&qi.reduce_vec_i64_vt(v)) }
                 } else {
                     wi[..v.len()].copy_from_slice(Zeroizing::new(qi.reduce_vec_i64(v)).as_ref());
@@ -283,7 +319,10 @@ impl<'a> TryConvertFrom<&'a [i64]> for Poly {
             });
             Ok(out)
         } else {
-            Err(Error::Default("In PowerBasis representation with signed integers, only `degree` coefficients can be specified".to_string()))
+            Err(Error::InvalidLength {
+                expected: ctx.degree,
+                found: v.len(),
+            })
         }
     }
 }
@@ -315,10 +354,10 @@ impl<'a> TryConvertFrom<&'a [BigUint]> for Poly {
         let repr = representation.into();
 
         if v.len() > ctx.degree {
-            Err(Error::Default(
-                "The slice contains too many big integers compared to the polynomial degree"
-                    .to_string(),
-            ))
+            Err(Error::InvalidLength {
+                expected: ctx.degree,
+                found: v.len(),
+            })
         } else if repr.is_some() {
             let mut coefficients = Array2::zeros((ctx.q.len(), ctx.degree));
 
@@ -332,6 +371,7 @@ impl<'a> TryConvertFrom<&'a [BigUint]> for Poly {
                 allow_variable_time_computations: variable_time,
                 coefficients,
                 coefficients_shoup: None,
+                cache: None,
                 has_lazy_coefficients: false,
             };
 
@@ -344,10 +384,7 @@ impl<'a> TryConvertFrom<&'a [BigUint]> for Poly {
                 }
             }
         } else {
-            Err(Error::Default(
-                "When converting from a vector, the representation needs to be specified"
-                    .to_string(),
-            ))
+            Err(Error::MissingRepresentation)
         }
     }
 }
@@ -429,6 +466,7 @@ mod tests {
         rq::{traits::TryConvertFrom, Context, Poly, Representation},
         Error as CrateError,
     };
+    use ndarray::Array2;
     use num_bigint::BigUint;
     use rand::thread_rng;
     use std::{error::Error, sync::Arc};
@@ -448,41 +486,62 @@ mod tests {
                 p
             );
             assert_eq!(
-				Poly::try_convert_from(&proto, &ctx, false, Representation::Ntt)
-					.expect_err("Should fail because of mismatched representations"),
-					CrateError::Default("The representation asked for does not match the representation in the serialization".to_string())
-			);
+                Poly::try_convert_from(&proto, &ctx, false, Representation::Ntt)
+                    .expect_err("Should fail because of mismatched representations"),
+                CrateError::IncorrectRepresentation(
+                    Representation::PowerBasis,
+                    Representation::Ntt
+                )
+            );
             assert_eq!(
-				Poly::try_convert_from(&proto, &ctx, false, Representation::NttShoup)
-					.expect_err("Should fail because of mismatched representations"),
-					CrateError::Default("The representation asked for does not match the representation in the serialization".to_string())
-			);
+                Poly::try_convert_from(&proto, &ctx, false, Representation::NttShoup)
+                    .expect_err("Should fail because of mismatched representations"),
+                CrateError::IncorrectRepresentation(
+                    Representation::PowerBasis,
+                    Representation::NttShoup
+                )
+            );
         }
 
         let ctx = Arc::new(Context::new(MODULI, 16)?);
         let p = Poly::random(&ctx, Representation::PowerBasis, &mut rng);
         let proto = Rq::from(&p);
+        assert_eq!(proto.coefficients.len(), (11 + 62 + 62) * 16 / 8);
+
+        // The serialization of small degrees is padded to a whole byte.
+        for degree in [2, 4] {
+            let ctx = Arc::new(Context::new(MODULI, degree)?);
+            let p = Poly::random(&ctx, Representation::Ntt, &mut rng);
+            let proto = Rq::from(&p);
+            assert_eq!(Poly::try_convert_from(&proto, &ctx, false, None)?, p);
+        }
         assert_eq!(Poly::try_convert_from(&proto, &ctx, false, None)?, p);
         assert_eq!(
             Poly::try_convert_from(&proto, &ctx, false, Representation::PowerBasis)?,
             p
         );
         assert_eq!(
-			Poly::try_convert_from(&proto, &ctx, false, Representation::Ntt)
-				.expect_err("Should fail because of mismatched representations"),
-				CrateError::Default("The representation asked for does not match the representation in the serialization".to_string())
-		);
+            Poly::try_convert_from(&proto, &ctx, false, Representation::Ntt)
+                .expect_err("Should fail because of mismatched representations"),
+            CrateError::IncorrectRepresentation(Representation::PowerBasis, Representation::Ntt)
+        );
         assert_eq!(
-			Poly::try_convert_from(&proto, &ctx, false, Representation::NttShoup)
-				.expect_err("Should fail because of mismatched representations"),
-				CrateError::Default("The representation asked for does not match the representation in the serialization".to_string())
-		);
+            Poly::try_convert_from(&proto, &ctx, false, Representation::NttShoup)
+                .expect_err("Should fail because of mismatched representations"),
+            CrateError::IncorrectRepresentation(
+                Representation::PowerBasis,
+                Representation::NttShoup
+            )
+        );
 
         let ctx = Arc::new(Context::new(&MODULI[0..1], 16)?);
         assert_eq!(
             Poly::try_convert_from(&proto, &ctx, false, None)
                 .expect_err("Should fail because of incorrect context"),
-            CrateError::Default("Invalid coefficients".to_string())
+            CrateError::InvalidLength {
+                expected: 22,
+                found: (11 + 62 + 62) * 16 / 8
+            }
         );
 
         Ok(())
@@ -678,4 +737,43 @@ mod tests {
         }
         Ok(())
     }
+
+    #[test]
+    fn array() -> Result<(), Box<dyn Error>> {
+        let mut rng = thread_rng();
+        let ctx = Arc::new(Context::new(MODULI, 16)?);
+        for representation in [
+            Representation::PowerBasis,
+            Representation::Ntt,
+            Representation::NttShoup,
+        ] {
+            let p = Poly::random(&ctx, representation.clone(), &mut rng);
+            let a = p.coefficients().to_owned();
+            let q = Poly::try_convert_from(a, &ctx, false, representation.clone())?;
+            assert_eq!(p, q);
+        }
+
+        // Arrays which are not in standard layout are accepted.
+        let p = Poly::random(&ctx, Representation::Ntt, &mut rng);
+        let mut a = Array2::zeros((ctx.degree, 3)).reversed_axes();
+        a.assign(&p.coefficients());
+        assert!(!a.is_standard_layout());
+        assert_eq!(
+            Poly::try_convert_from(a, &ctx, false, Representation::Ntt)?,
+            p
+        );
+
+        // Unreduced coefficients are rejected.
+        let mut a = p.coefficients().to_owned();
+        a[[0, 5]] = MODULI[0];
+        assert!(Poly::try_convert_from(a, &ctx, false, Representation::Ntt).is_err());
+
+        // The mutable view is refused in NttShoup representation.
+        let mut q = p.clone();
+        q.coefficients_mut()?[[1, 2]] = 0;
+        assert_eq!(q.coefficients()[[1, 2]], 0);
+        q.change_representation(Representation::NttShoup);
+        assert!(q.coefficients_mut().is_err());
+        Ok(())
+    }
 }
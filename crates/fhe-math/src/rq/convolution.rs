@@ -0,0 +1,143 @@
+//! Negacyclic convolution for moduli which do not support the NTT.
+
+use crate::{
+    ntt::NttOperator,
+    zq::{primes::generate_prime, Modulus},
+};
+use itertools::izip;
+
+/// Number of bits of the auxiliary primes.
+const AUXILIARY_PRIME_BITS: usize = 61;
+
+/// Multiplication of polynomials modulo `x^degree + 1` and a modulus `q` which
+/// does not support the NTT of size `degree`.
+///
+/// The product is computed over the integers using the NTT of size
+/// `2 * degree` modulo three auxiliary primes, whose product exceeds the
+/// coefficients of the product of two polynomials with coefficients in [0, q).
+/// The result is then reconstructed modulo q with Garner's algorithm, and
+/// reduced modulo `x^degree + 1`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Convolution {
+    q: Modulus,
+    degree: usize,
+    p: [Modulus; 3],
+    ops: [NttOperator; 3],
+    p0_inv_mod_p1: u64,
+    p0_inv_mod_p2: u64,
+    p1_inv_mod_p2: u64,
+    p0_mod_q: u64,
+    p0_p1_mod_q: u64,
+}
+
+impl Convolution {
+    /// Creates the convolution for the modulus `q` and the degree `degree`.
+    ///
+    /// Returns None if the auxiliary primes could not be generated.
+    pub(crate) fn new(q: &Modulus, degree: usize) -> Option<Self> {
+        let modulo = 4 * degree as u64;
+        let p0 = generate_prime(AUXILIARY_PRIME_BITS, modulo, 1 << AUXILIARY_PRIME_BITS)?;
+        let p1 = generate_prime(AUXILIARY_PRIME_BITS, modulo, p0)?;
+        let p2 = generate_prime(AUXILIARY_PRIME_BITS, modulo, p1)?;
+        let p = [
+            Modulus::new(p0).ok()?,
+            Modulus::new(p1).ok()?,
+            Modulus::new(p2).ok()?,
+        ];
+        let ops = [
+            NttOperator::new(&p[0], 2 * degree)?,
+            NttOperator::new(&p[1], 2 * degree)?,
+            NttOperator::new(&p[2], 2 * degree)?,
+        ];
+        Some(Self {
+            q: q.clone(),
+            degree,
+            p0_inv_mod_p1: p[1].inv(p[1].reduce(p0))?,
+            p0_inv_mod_p2: p[2].inv(p[2].reduce(p0))?,
+            p1_inv_mod_p2: p[2].inv(p[2].reduce(p1))?,
+            p0_mod_q: q.reduce(p0),
+            p0_p1_mod_q: q.mul(q.reduce(p0), q.reduce(p1)),
+            p,
+            ops,
+        })
+    }
+
+    /// Multiplies in place the polynomial `a` by the polynomial `b`, modulo
+    /// `x^degree + 1` and q, in constant time.
+    ///
+    /// Aborts if the lengths of `a` and `b` differ from the degree.
+    pub(crate) fn mul(&self, a: &mut [u64], b: &[u64]) {
+        assert_eq!(a.len(), self.degree);
+        assert_eq!(b.len(), self.degree);
+
+        // Product over the integers, modulo each auxiliary prime.
+        let products = izip!(self.p.iter(), self.ops.iter())
+            .map(|(pi, op)| {
+                let mut ai = vec![0u64; 2 * self.degree];
+                let mut bi = vec![0u64; 2 * self.degree];
+                izip!(ai.iter_mut(), a.iter()).for_each(|(aij, aj)| *aij = pi.reduce(*aj));
+                izip!(bi.iter_mut(), b.iter()).for_each(|(bij, bj)| *bij = pi.reduce(*bj));
+                op.forward(&mut ai);
+                op.forward(&mut bi);
+                pi.mul_vec(&mut ai, &bi);
+                op.backward(&mut ai);
+                ai
+            })
+            .collect::<Vec<_>>();
+
+        // Reconstruction modulo q, and reduction modulo x^degree + 1.
+        let coefficient = |j: usize| {
+            let (r0, r1, r2) = (products[0][j], products[1][j], products[2][j]);
+            let [_, p1, p2] = &self.p;
+            let x1 = p1.mul(p1.sub(r1, p1.reduce(r0)), self.p0_inv_mod_p1);
+            let x2 = p2.mul(p2.sub(r2, p2.reduce(r0)), self.p0_inv_mod_p2);
+            let x2 = p2.mul(p2.sub(x2, p2.reduce(x1)), self.p1_inv_mod_p2);
+            let x = self.q.add(
+                self.q.reduce(r0),
+                self.q.mul(self.q.reduce(x1), self.p0_mod_q),
+            );
+            self.q
+                .add(x, self.q.mul(self.q.reduce(x2), self.p0_p1_mod_q))
+        };
+        a.iter_mut()
+            .enumerate()
+            .for_each(|(j, aj)| *aj = self.q.sub(coefficient(j), coefficient(j + self.degree)));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Convolution;
+    use crate::zq::Modulus;
+    use rand::thread_rng;
+
+    #[test]
+    fn mul() {
+        let mut rng = thread_rng();
+        for p in [1153u64, 4611686018326724609, (1 << 62) - 57] {
+            let q = Modulus::new(p).unwrap();
+            for degree in [8, 128] {
+                let convolution = Convolution::new(&q, degree).unwrap();
+                let mut a = q.random_vec(degree, &mut rng);
+                let mut b = q.random_vec(degree, &mut rng);
+                (a[0], b[degree - 1]) = (p - 1, p - 1);
+
+                // Schoolbook multiplication modulo x^degree + 1.
+                let mut expected = vec![0u64; degree];
+                for i in 0..degree {
+                    for j in 0..degree {
+                        let c = q.mul(a[i], b[j]);
+                        if i + j < degree {
+                            expected[i + j] = q.add(expected[i + j], c);
+                        } else {
+                            expected[i + j - degree] = q.sub(expected[i + j - degree], c);
+                        }
+                    }
+                }
+
+                convolution.mul(&mut a, &b);
+                assert_eq!(a, expected);
+            }
+        }
+    }
+}
@@ -0,0 +1,219 @@
+#![warn(missing_docs, unused_imports)]
+
+//! Arithmetic in arbitrary cyclotomic rings.
+
+use super::convolution::Convolution;
+use crate::{zq::Modulus, Error, Result};
+
+/// Arithmetic in the cyclotomic ring ZZ_q\[x\] / (Phi_m(x)), where Phi_m is
+/// the m-th cyclotomic polynomial, for any m and not only powers of two.
+///
+/// The polynomials are represented by their coefficients, of length at most
+/// the degree phi(m) of the ring. The product is computed over the integers
+/// with a negacyclic convolution of a power-of-two size large enough to avoid
+/// the wrap-around, and then reduced modulo Phi_m.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CyclotomicRing {
+    m: usize,
+    q: Modulus,
+    /// The coefficients of Phi_m modulo q, from the constant coefficient to
+    /// the leading coefficient, which is 1.
+    phi: Vec<u64>,
+    /// The non-zero coefficients of Phi_m except the leading one, used for the
+    /// reduction.
+    phi_terms: Vec<(usize, u64)>,
+    convolution: Convolution,
+}
+
+impl CyclotomicRing {
+    /// Creates the ring ZZ_q\[x\] / (Phi_m(x)).
+    ///
+    /// Returns an error if m is zero, or if the product of two polynomials
+    /// cannot be computed for the modulus q.
+    pub fn new(m: usize, q: &Modulus) -> Result<Self> {
+        if m == 0 {
+            return Err(Error::InvalidCyclotomicIndex(m));
+        }
+
+        // Phi_m(x) = prod_{d | m} (x^d - 1)^mu(m / d).
+        let divisors = (1..=m).filter(|d| m % d == 0).collect::<Vec<_>>();
+        let mut phi = vec![1u64];
+        for d in divisors.iter().filter(|d| mobius(m / **d) == 1) {
+            // Multiplication by x^d - 1.
+            let mut product = vec![0u64; phi.len() + d];
+            for (i, c) in phi.iter().enumerate() {
+                product[i] = q.sub(product[i], *c);
+                product[i + d] = q.add(product[i + d], *c);
+            }
+            phi = product;
+        }
+        for d in divisors.iter().filter(|d| mobius(m / **d) == -1) {
+            // Exact division by x^d - 1: if p = r * (x^d - 1), then
+            // r_i = p_(i + d) + r_(i + d).
+            let mut quotient = vec![0u64; phi.len() - d];
+            for i in (0..quotient.len()).rev() {
+                let next = quotient.get(i + d).copied().unwrap_or(0);
+                quotient[i] = q.add(phi[i + d], next);
+            }
+            phi = quotient;
+        }
+        debug_assert_eq!(phi.last(), Some(&1));
+
+        let degree = phi.len() - 1;
+        let phi_terms = phi[..degree]
+            .iter()
+            .enumerate()
+            .filter(|(_, c)| **c != 0)
+            .map(|(i, c)| (i, *c))
+            .collect();
+        let convolution_size = (2 * degree).next_power_of_two();
+        let convolution =
+            Convolution::new(q, convolution_size).ok_or(Error::UnsupportedModulus(q.modulus()))?;
+
+        Ok(Self {
+            m,
+            q: q.clone(),
+            phi,
+            phi_terms,
+            convolution,
+        })
+    }
+
+    /// Returns the index m of the cyclotomic polynomial.
+    pub const fn m(&self) -> usize {
+        self.m
+    }
+
+    /// Returns the degree phi(m) of the ring.
+    pub fn degree(&self) -> usize {
+        self.phi.len() - 1
+    }
+
+    /// Returns the coefficients of the cyclotomic polynomial modulo q, from the
+    /// constant coefficient to the leading coefficient.
+    pub fn polynomial(&self) -> &[u64] {
+        &self.phi
+    }
+
+    /// Reduces a polynomial of any degree with coefficients in [0, q) modulo
+    /// the cyclotomic polynomial.
+    pub fn reduce(&self, a: &[u64]) -> Vec<u64> {
+        let degree = self.degree();
+        let mut r = a.to_vec();
+        for i in (degree..r.len()).rev() {
+            // x^i = -x^(i - degree) * (Phi_m(x) - x^degree).
+            let c = r[i];
+            for (j, phi_j) in &self.phi_terms {
+                let k = i - degree + j;
+                r[k] = self.q.sub(r[k], self.q.mul(c, *phi_j));
+            }
+        }
+        r.resize(degree, 0);
+        r
+    }
+
+    /// Multiplies two polynomials of the ring, in constant time.
+    ///
+    /// Aborts if the length of `a` or `b` exceeds the degree of the ring.
+    pub fn mul(&self, a: &[u64], b: &[u64]) -> Vec<u64> {
+        let degree = self.degree();
+        assert!(a.len() <= degree && b.len() <= degree);
+
+        let size = (2 * degree).next_power_of_two();
+        let mut a_padded = vec![0u64; size];
+        let mut b_padded = vec![0u64; size];
+        a_padded[..a.len()].copy_from_slice(a);
+        b_padded[..b.len()].copy_from_slice(b);
+        self.convolution.mul(&mut a_padded, &b_padded);
+        a_padded.truncate(2 * degree);
+        self.reduce(&a_padded)
+    }
+}
+
+/// Möbius function of a positive integer.
+fn mobius(mut n: usize) -> i8 {
+    let mut result = 1;
+    let mut p = 2;
+    while p * p <= n {
+        if n % p == 0 {
+            n /= p;
+            if n % p == 0 {
+                return 0;
+            }
+            result = -result;
+        }
+        p += 1;
+    }
+    if n > 1 {
+        result = -result;
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::CyclotomicRing;
+    use crate::zq::Modulus;
+    use rand::thread_rng;
+    use std::error::Error;
+
+    #[test]
+    fn polynomial() -> Result<(), Box<dyn Error>> {
+        let q = Modulus::new(1153)?;
+        let signed = |v: &[i64]| v.iter().map(|c| q.reduce_i64(*c)).collect::<Vec<_>>();
+
+        assert_eq!(CyclotomicRing::new(1, &q)?.polynomial(), signed(&[-1, 1]));
+        assert_eq!(
+            CyclotomicRing::new(9, &q)?.polynomial(),
+            signed(&[1, 0, 0, 1, 0, 0, 1])
+        );
+        assert_eq!(
+            CyclotomicRing::new(15, &q)?.polynomial(),
+            signed(&[1, -1, 0, 1, -1, 1, 0, -1, 1])
+        );
+        assert_eq!(
+            CyclotomicRing::new(16, &q)?.polynomial(),
+            signed(&[1, 0, 0, 0, 0, 0, 0, 0, 1])
+        );
+        assert_eq!(CyclotomicRing::new(105, &q)?.degree(), 48);
+        assert!(CyclotomicRing::new(0, &q).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn mul() -> Result<(), Box<dyn Error>> {
+        let mut rng = thread_rng();
+        for p in [1153u64, 4611686018326724609] {
+            let q = Modulus::new(p)?;
+            for m in [7, 12, 15, 27, 105] {
+                let ring = CyclotomicRing::new(m, &q)?;
+                let degree = ring.degree();
+
+                // x^m = 1 in the ring.
+                let mut x = vec![0u64; degree];
+                x[1] = 1;
+                let mut power = vec![0u64; degree];
+                power[0] = 1;
+                for _ in 0..m {
+                    power = ring.mul(&power, &x);
+                }
+                let mut one = vec![0u64; degree];
+                one[0] = 1;
+                assert_eq!(power, one);
+
+                // Schoolbook multiplication followed by the reduction.
+                let a = q.random_vec(degree, &mut rng);
+                let b = q.random_vec(degree, &mut rng);
+                let mut expected = vec![0u64; 2 * degree - 1];
+                for i in 0..degree {
+                    for j in 0..degree {
+                        expected[i + j] = q.add(expected[i + j], q.mul(a[i], b[j]));
+                    }
+                }
+                assert_eq!(ring.mul(&a, &b), ring.reduce(&expected));
+                assert_eq!(ring.mul(&a, &one), a);
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,132 @@
+#![warn(missing_docs, unused_imports)]
+
+//! Generation of arbitrary polynomials for fuzzing and property tests.
+//!
+//! The polynomials are sampled in contexts of small degree whose moduli are a
+//! prefix of [`MODULI`], so that they can be generated without exposing the
+//! private constructors of the polynomials.
+
+use super::{traits::TryConvertFrom, Context, Poly, Representation};
+use ndarray::Array2;
+use std::sync::Arc;
+
+/// Moduli of the contexts of the arbitrary polynomials, which support the NTT
+/// for all the [`DEGREES`].
+pub const MODULI: [u64; 3] = [1153, 4611686018326724609, 4611686018309947393];
+
+/// Degrees of the contexts of the arbitrary polynomials.
+pub const DEGREES: [usize; 4] = [8, 16, 32, 64];
+
+const REPRESENTATIONS: [Representation; 3] = [
+    Representation::PowerBasis,
+    Representation::Ntt,
+    Representation::NttShoup,
+];
+
+/// Returns the context with the first `num_moduli` moduli of [`MODULI`] and
+/// the degree `degree`.
+fn context(num_moduli: usize, degree: usize) -> Arc<Context> {
+    Context::new_arc(&MODULI[..num_moduli], degree).unwrap()
+}
+
+/// Builds a polynomial from arbitrary coefficients, which are reduced modulo
+/// the moduli of the context.
+fn poly(ctx: &Arc<Context>, representation: Representation, coefficients: Vec<u64>) -> Poly {
+    let mut coefficients =
+        Array2::from_shape_vec((ctx.moduli.len(), ctx.degree), coefficients).unwrap();
+    for (mut row, qi) in coefficients.outer_iter_mut().zip(ctx.moduli.iter()) {
+        row.iter_mut().for_each(|c| *c %= *qi);
+    }
+    Poly::try_convert_from(coefficients, ctx, false, representation).unwrap()
+}
+
+#[cfg(feature = "arbitrary")]
+impl<'a> arbitrary::Arbitrary<'a> for Representation {
+    fn arbitrary(u: &mut arbitrary::Unstructured<'a>) -> arbitrary::Result<Self> {
+        u.choose(&REPRESENTATIONS).cloned()
+    }
+}
+
+#[cfg(feature = "arbitrary")]
+impl<'a> arbitrary::Arbitrary<'a> for Poly {
+    fn arbitrary(u: &mut arbitrary::Unstructured<'a>) -> arbitrary::Result<Self> {
+        let num_moduli = u.int_in_range(1..=MODULI.len())?;
+        let degree = *u.choose(&DEGREES)?;
+        let representation = Representation::arbitrary(u)?;
+        let ctx = context(num_moduli, degree);
+        let coefficients = (0..num_moduli * degree)
+            .map(|_| u.arbitrary())
+            .collect::<arbitrary::Result<Vec<u64>>>()?;
+        Ok(poly(&ctx, representation, coefficients))
+    }
+}
+
+#[cfg(feature = "proptest")]
+impl proptest::arbitrary::Arbitrary for Representation {
+    type Parameters = ();
+    type Strategy = proptest::sample::Select<Representation>;
+
+    fn arbitrary_with(_: Self::Parameters) -> Self::Strategy {
+        proptest::sample::select(REPRESENTATIONS.to_vec())
+    }
+}
+
+#[cfg(feature = "proptest")]
+impl proptest::arbitrary::Arbitrary for Poly {
+    type Parameters = ();
+    type Strategy = proptest::strategy::BoxedStrategy<Poly>;
+
+    fn arbitrary_with(_: Self::Parameters) -> Self::Strategy {
+        use proptest::prelude::*;
+
+        (
+            1..=MODULI.len(),
+            proptest::sample::select(DEGREES.to_vec()),
+            any::<Representation>(),
+        )
+            .prop_flat_map(|(num_moduli, degree, representation)| {
+                proptest::collection::vec(any::<u64>(), num_moduli * degree).prop_map(
+                    move |coefficients| {
+                        let ctx = context(num_moduli, degree);
+                        poly(&ctx, representation.clone(), coefficients)
+                    },
+                )
+            })
+            .boxed()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[cfg(feature = "arbitrary")]
+    #[test]
+    fn arbitrary() -> Result<(), Box<dyn std::error::Error>> {
+        use crate::rq::Poly;
+        use arbitrary::{Arbitrary, Unstructured};
+        use rand::{thread_rng, RngCore};
+
+        let mut rng = thread_rng();
+        for _ in 0..100 {
+            let mut bytes = vec![0u8; 4096];
+            rng.fill_bytes(&mut bytes);
+            let p = Poly::arbitrary(&mut Unstructured::new(&bytes))?;
+            assert!(super::DEGREES.contains(&p.ctx().degree));
+            assert!(super::MODULI.starts_with(p.ctx().moduli()));
+        }
+        Ok(())
+    }
+
+    #[cfg(feature = "proptest")]
+    proptest::proptest! {
+        #[test]
+        fn serialization(p: crate::rq::Poly) {
+            use crate::{
+                proto::rq::Rq,
+                rq::{traits::TryConvertFrom, Poly},
+            };
+
+            let q = Poly::try_convert_from(&Rq::from(&p), p.ctx(), false, None).unwrap();
+            proptest::prop_assert_eq!(p, q);
+        }
+    }
+}
@@ -0,0 +1,295 @@
+//! Gadget decompositions of polynomials.
+//!
+//! A gadget over a context of modulus `Q` is a vector of factors `g_i` such
+//! that any polynomial `p` can be decomposed in digits `d_i` of small norm
+//! satisfying `p = sum_i d_i g_i mod Q`. Key switching, relinearization and
+//! external products multiply the digits of a polynomial with encryptions of
+//! the factors times a secret.
+
+use std::sync::Arc;
+
+use itertools::Itertools;
+use ndarray::{Array2, Axis};
+use num_bigint::BigUint;
+use num_traits::ToPrimitive;
+
+use super::{traits::TryConvertFrom, Context, Poly, Representation};
+use crate::{Error, Result};
+
+/// The kind of decomposition of a [`Gadget`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Decomposition {
+    /// The digits are the residues modulo each modulus.
+    Rns,
+    /// The digits are the digits of the coefficients in base `2^log_base`.
+    PowerOfTwo(usize),
+    /// The digits are the digits in base `2^log_base` of the residues modulo
+    /// each modulus.
+    Hybrid(usize),
+}
+
+/// A gadget decomposition of the polynomials of a context.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Gadget {
+    ctx: Arc<Context>,
+    decomposition: Decomposition,
+}
+
+impl Gadget {
+    /// Creates the RNS gadget, whose digits are the residues of the
+    /// coefficients modulo each modulus of the context, and whose factors are
+    /// the Garner coefficients of the context.
+    pub fn rns(ctx: &Arc<Context>) -> Self {
+        Self {
+            ctx: ctx.clone(),
+            decomposition: Decomposition::Rns,
+        }
+    }
+
+    /// Creates the gadget in base `2^log_base`, whose digits are the digits of
+    /// the coefficients in `[0, Q)`, and whose factors are the powers of the
+    /// base.
+    pub fn power_of_two(ctx: &Arc<Context>, log_base: usize) -> Result<Self> {
+        Self::check_log_base(log_base)?;
+        Ok(Self {
+            ctx: ctx.clone(),
+            decomposition: Decomposition::PowerOfTwo(log_base),
+        })
+    }
+
+    /// Creates the hybrid gadget, whose digits are the digits in base
+    /// `2^log_base` of the residues of the coefficients modulo each modulus,
+    /// and whose factors are the Garner coefficients times the powers of the
+    /// base.
+    pub fn hybrid(ctx: &Arc<Context>, log_base: usize) -> Result<Self> {
+        Self::check_log_base(log_base)?;
+        Ok(Self {
+            ctx: ctx.clone(),
+            decomposition: Decomposition::Hybrid(log_base),
+        })
+    }
+
+    fn check_log_base(log_base: usize) -> Result<()> {
+        if log_base == 0 || log_base > 62 {
+            Err(Error::InvalidGadgetBase(log_base))
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Returns the context of the decomposed polynomials.
+    pub fn ctx(&self) -> &Arc<Context> {
+        &self.ctx
+    }
+
+    /// Returns the logarithm of the base of the digits, or 0 for the RNS
+    /// gadget.
+    pub fn log_base(&self) -> usize {
+        match self.decomposition {
+            Decomposition::Rns => 0,
+            Decomposition::PowerOfTwo(log_base) | Decomposition::Hybrid(log_base) => log_base,
+        }
+    }
+
+    /// Returns the number of digits of the decomposition of a residue modulo
+    /// `qi` in base `2^log_base`.
+    fn num_digits_of(qi: u64, log_base: usize) -> usize {
+        (qi.ilog2() as usize + 1).div_ceil(log_base)
+    }
+
+    /// Returns the number of digits.
+    pub fn len(&self) -> usize {
+        match self.decomposition {
+            Decomposition::Rns => self.ctx.moduli.len(),
+            Decomposition::PowerOfTwo(log_base) => {
+                (self.ctx.modulus().bits() as usize).div_ceil(log_base)
+            }
+            Decomposition::Hybrid(log_base) => self
+                .ctx
+                .moduli
+                .iter()
+                .map(|qi| Self::num_digits_of(*qi, log_base))
+                .sum(),
+        }
+    }
+
+    /// Returns whether there are no digits, which is never the case.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the factors of the gadget, reduced modulo the modulus of the
+    /// context.
+    pub fn factors(&self) -> Vec<BigUint> {
+        let rns = &self.ctx.rns;
+        match self.decomposition {
+            Decomposition::Rns => (0..self.ctx.moduli.len())
+                .map(|i| rns.get_garner(i).unwrap().clone())
+                .collect(),
+            Decomposition::PowerOfTwo(log_base) => (0..self.len())
+                .map(|j| (BigUint::from(1u64) << (j * log_base)) % self.ctx.modulus())
+                .collect(),
+            Decomposition::Hybrid(log_base) => self
+                .ctx
+                .moduli
+                .iter()
+                .enumerate()
+                .flat_map(|(i, qi)| {
+                    (0..Self::num_digits_of(*qi, log_base)).map(move |j| {
+                        (rns.get_garner(i).unwrap() << (j * log_base)) % self.ctx.modulus()
+                    })
+                })
+                .collect(),
+        }
+    }
+
+    /// Decomposes the polynomial `p`, in PowerBasis representation in the
+    /// context of the gadget, into the coefficients of its digits.
+    pub fn decompose(&self, p: &Poly) -> Result<Vec<Vec<u64>>> {
+        if p.ctx != self.ctx {
+            return Err(Error::InvalidContext);
+        }
+        if p.representation != Representation::PowerBasis {
+            return Err(Error::IncorrectRepresentation(
+                p.representation.clone(),
+                Representation::PowerBasis,
+            ));
+        }
+
+        let split = |coefficients: &[u64], num_digits: usize, log_base: usize| {
+            let mask = (1u64 << log_base) - 1;
+            (0..num_digits)
+                .map(|j| {
+                    coefficients
+                        .iter()
+                        .map(|c| (c >> (j * log_base)) & mask)
+                        .collect_vec()
+                })
+                .collect_vec()
+        };
+
+        Ok(match self.decomposition {
+            Decomposition::Rns => p
+                .coefficients
+                .outer_iter()
+                .map(|residues| residues.to_vec())
+                .collect(),
+            Decomposition::PowerOfTwo(log_base) if self.ctx.moduli.len() == 1 => {
+                split(p.coefficients.as_slice().unwrap(), self.len(), log_base)
+            }
+            Decomposition::PowerOfTwo(log_base) => {
+                let mask = (BigUint::from(1u64) << log_base) - 1u64;
+                let mut digits = vec![vec![0u64; self.ctx.degree]; self.len()];
+                p.coefficients
+                    .axis_iter(Axis(1))
+                    .enumerate()
+                    .for_each(|(k, residues)| {
+                        let mut x = self.ctx.rns.lift(residues);
+                        digits.iter_mut().for_each(|digit| {
+                            digit[k] = (&x & &mask).to_u64().unwrap();
+                            x >>= log_base;
+                        })
+                    });
+                digits
+            }
+            Decomposition::Hybrid(log_base) => p
+                .coefficients
+                .outer_iter()
+                .zip(self.ctx.moduli.iter())
+                .flat_map(|(residues, qi)| {
+                    split(
+                        residues.as_slice().unwrap(),
+                        Self::num_digits_of(*qi, log_base),
+                        log_base,
+                    )
+                })
+                .collect(),
+        })
+    }
+
+    /// Recomposes the polynomial from the coefficients of its `digits`, as
+    /// returned by [`Gadget::decompose`]. The polynomial is returned in
+    /// PowerBasis representation.
+    pub fn recompose(&self, digits: &[Vec<u64>]) -> Result<Poly> {
+        if digits.len() != self.len() {
+            return Err(Error::InvalidLength {
+                expected: self.len(),
+                found: digits.len(),
+            });
+        }
+        let mut p = Poly::zero(&self.ctx, Representation::PowerBasis);
+        for (digit, factor) in digits.iter().zip(self.factors()) {
+            if digit.len() != self.ctx.degree {
+                return Err(Error::InvalidLength {
+                    expected: self.ctx.degree,
+                    found: digit.len(),
+                });
+            }
+            let mut coefficients = Array2::zeros((self.ctx.q.len(), self.ctx.degree));
+            coefficients
+                .outer_iter_mut()
+                .zip(self.ctx.q.iter())
+                .for_each(|(mut row, qi)| {
+                    row.iter_mut()
+                        .zip(digit.iter())
+                        .for_each(|(c, d)| *c = qi.reduce(*d))
+                });
+            let d =
+                Poly::try_convert_from(coefficients, &self.ctx, false, Representation::PowerBasis)?;
+            p += &(&d * &factor);
+        }
+        Ok(p)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{error::Error, sync::Arc};
+
+    use rand::thread_rng;
+
+    use super::Gadget;
+    use crate::rq::{Context, Poly, Representation};
+
+    static MODULI: &[u64; 3] = &[1153, 4611686018326724609, 4611686018309947393];
+
+    #[test]
+    fn decompose_recompose() -> Result<(), Box<dyn Error>> {
+        let mut rng = thread_rng();
+        for moduli in [&MODULI[..1], &MODULI[1..2], &MODULI[..]] {
+            let ctx = Arc::new(Context::new(moduli, 16)?);
+            let gadgets = [
+                Gadget::rns(&ctx),
+                Gadget::power_of_two(&ctx, 5)?,
+                Gadget::power_of_two(&ctx, 31)?,
+                Gadget::hybrid(&ctx, 5)?,
+                Gadget::hybrid(&ctx, 62)?,
+            ];
+            for gadget in gadgets {
+                assert_eq!(gadget.factors().len(), gadget.len());
+                for _ in 0..20 {
+                    let p = Poly::random(&ctx, Representation::PowerBasis, &mut rng);
+                    let digits = gadget.decompose(&p)?;
+                    assert_eq!(digits.len(), gadget.len());
+                    if gadget.log_base() != 0 {
+                        assert!(digits.iter().flatten().all(|d| *d < 1 << gadget.log_base()));
+                    }
+                    assert_eq!(gadget.recompose(&digits)?, p);
+                }
+                assert!(gadget.recompose(&[]).is_err());
+            }
+        }
+
+        let ctx = Arc::new(Context::new(MODULI, 16)?);
+        assert_eq!(Gadget::rns(&ctx).len(), 3);
+        assert_eq!(Gadget::hybrid(&ctx, 31)?.len(), 5);
+        assert!(Gadget::power_of_two(&ctx, 0).is_err());
+        assert!(Gadget::hybrid(&ctx, 63).is_err());
+        let p = Poly::random(&ctx, Representation::Ntt, &mut rng);
+        assert!(Gadget::rns(&ctx).decompose(&p).is_err());
+        let other = Arc::new(Context::new(&MODULI[..2], 16)?);
+        let p = Poly::random(&other, Representation::PowerBasis, &mut rng);
+        assert!(Gadget::rns(&ctx).decompose(&p).is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,157 @@
+//! Hoisted automorphisms.
+//!
+//! Key switching a polynomial after a substitution requires its decomposition
+//! in RNS digits. Since the substitution is a signed permutation of the
+//! coefficients, it commutes with the decomposition, so that a polynomial can
+//! be decomposed once with [`Poly::decompose_once`], and its digits substituted
+//! for many exponents with [`HoistedDecomposition::apply_automorphism_hoisted`].
+
+use std::sync::Arc;
+
+use itertools::izip;
+use ndarray::Array2;
+
+use super::{
+    dot_product, traits::TryConvertFrom, Context, Poly, Representation, SubstitutionExponent,
+};
+use crate::{Error, Result};
+
+/// The decomposition of a polynomial in RNS digits, where the i-th digit is the
+/// residue of the polynomial modulo the i-th modulus of its context, lifted to
+/// another context in Ntt representation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HoistedDecomposition {
+    digits: Vec<Poly>,
+}
+
+impl Poly {
+    /// Decomposes the polynomial in RNS digits lifted to the context `ctx`,
+    /// which must have the same degree.
+    ///
+    /// The sum of the digits multiplied by the Garner coefficients of the
+    /// context of the polynomial is the polynomial itself.
+    pub fn decompose_once(&self, ctx: &Arc<Context>) -> Result<HoistedDecomposition> {
+        if ctx.degree != self.ctx.degree {
+            return Err(Error::InvalidContext);
+        }
+        let mut p = self.clone();
+        p.change_representation(Representation::PowerBasis);
+        let digits = p
+            .coefficients
+            .outer_iter()
+            .map(|residues| {
+                let mut coefficients = Array2::zeros((ctx.q.len(), ctx.degree));
+                izip!(coefficients.outer_iter_mut(), ctx.q.iter()).for_each(|(mut row, qj)| {
+                    row.iter_mut()
+                        .zip(residues.iter())
+                        .for_each(|(c, r)| *c = qj.reduce(*r))
+                });
+                let mut digit = Poly::try_convert_from(
+                    coefficients,
+                    ctx,
+                    self.allow_variable_time_computations,
+                    Representation::PowerBasis,
+                )?;
+                digit.change_representation(Representation::Ntt);
+                Ok(digit)
+            })
+            .collect::<Result<Vec<_>>>()?;
+        Ok(HoistedDecomposition { digits })
+    }
+}
+
+impl HoistedDecomposition {
+    /// Returns the number of digits.
+    pub fn len(&self) -> usize {
+        self.digits.len()
+    }
+
+    /// Returns whether there are no digits, which is never the case.
+    pub fn is_empty(&self) -> bool {
+        self.digits.is_empty()
+    }
+
+    /// Returns the digits, in Ntt representation.
+    pub fn digits(&self) -> &[Poly] {
+        &self.digits
+    }
+
+    /// Returns the decomposition of the substitution of `x` by `x^i` in the
+    /// decomposed polynomial, without decomposing it again.
+    pub fn apply_automorphism_hoisted(&self, i: &SubstitutionExponent) -> Result<Self> {
+        let digits = self
+            .digits
+            .iter()
+            .map(|digit| digit.substitute(i))
+            .collect::<Result<Vec<_>>>()?;
+        Ok(Self { digits })
+    }
+
+    /// Computes the inner product of the digits with the polynomials of `key`,
+    /// which must be in Ntt or NttShoup representation in the context of the
+    /// digits.
+    pub fn inner_product(&self, key: &[Poly]) -> Result<Poly> {
+        if key.len() != self.digits.len()
+            || key.iter().any(|k| {
+                k.ctx != self.digits[0].ctx || k.representation == Representation::PowerBasis
+            })
+        {
+            return Err(Error::InvalidContext);
+        }
+        dot_product(self.digits.iter(), key.iter())
+    }
+
+    /// Returns the context of the digits.
+    pub fn ctx(&self) -> &Arc<Context> {
+        &self.digits[0].ctx
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{error::Error, sync::Arc};
+
+    use ndarray::{Array1, Array2};
+    use rand::thread_rng;
+
+    use crate::rq::{traits::TryConvertFrom, Context, Poly, Representation, SubstitutionExponent};
+
+    static MODULI: &[u64; 3] = &[1153, 4611686018326724609, 4611686018309947393];
+
+    #[test]
+    fn hoisted_automorphisms() -> Result<(), Box<dyn Error>> {
+        let mut rng = thread_rng();
+        let ctx = Arc::new(Context::new(MODULI, 16)?);
+        let p = Poly::random(&ctx, Representation::Ntt, &mut rng);
+        let decomposition = p.decompose_once(&ctx)?;
+        assert_eq!(decomposition.len(), MODULI.len());
+        assert_eq!(decomposition.ctx(), &ctx);
+
+        // The inner product with the Garner coefficients recomposes the
+        // polynomial, whatever the representatives of the digits.
+        let garner = (0..MODULI.len())
+            .map(|i| {
+                let mut coefficients = Array2::zeros((MODULI.len(), 16));
+                coefficients.column_mut(0).assign(&Array1::from(
+                    ctx.rns.project(ctx.rns.get_garner(i).unwrap()),
+                ));
+                let mut g =
+                    Poly::try_convert_from(coefficients, &ctx, false, Representation::PowerBasis)?;
+                g.change_representation(Representation::NttShoup);
+                Ok(g)
+            })
+            .collect::<crate::Result<Vec<_>>>()?;
+        assert_eq!(decomposition.inner_product(&garner)?, p);
+
+        for exponent in [1, 3, 5, 31] {
+            let element = SubstitutionExponent::new(&ctx, exponent)?;
+            let substituted = decomposition.apply_automorphism_hoisted(&element)?;
+            assert_eq!(substituted.inner_product(&garner)?, p.substitute(&element)?);
+        }
+
+        assert!(decomposition.inner_product(&garner[..2]).is_err());
+        let other = Arc::new(Context::new(MODULI, 32)?);
+        assert!(p.decompose_once(&other).is_err());
+        Ok(())
+    }
+}
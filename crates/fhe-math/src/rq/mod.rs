@@ -5,26 +5,70 @@
 
 mod context;
 mod convert;
+mod convolution;
+mod cyclotomic;
+mod hoisting;
+mod norm;
 mod ops;
+mod pool;
 mod serialize;
 
+#[cfg(any(feature = "arbitrary", feature = "proptest"))]
+pub mod fuzz;
+pub mod gadget;
 pub mod scaler;
 pub mod switcher;
 pub mod traits;
 pub use context::Context;
-pub use ops::dot_product;
+pub use cyclotomic::CyclotomicRing;
+pub use hoisting::HoistedDecomposition;
+pub use ops::{dot_product, mul_add_mul, sum_of_products};
+pub use pool::PolyPool;
 use sha2::{Digest, Sha256};
 
 use self::{scaler::Scaler, switcher::Switcher, traits::TryConvertFrom};
-use crate::{Error, Result};
-use fhe_util::sample_vec_cbd;
+use crate::{
+    ntt::{backward_batch, forward_batch, NttKernel},
+    Error, Result,
+};
+use fhe_util::{
+    inverse, is_prime, sample_vec_cbd, sample_vec_gaussian, sample_vec_ternary, MAX_CBD_VARIANCE,
+};
 use itertools::{izip, Itertools};
-use ndarray::{s, Array2, ArrayView2, Axis};
+use ndarray::{s, Array2, ArrayView2, ArrayViewMut2, Axis};
 use rand::{CryptoRng, RngCore, SeedableRng};
 use rand_chacha::ChaCha8Rng;
 use std::sync::Arc;
 use zeroize::{Zeroize, Zeroizing};
 
+/// Smallest polynomial degree supported by a [`Context`].
+pub const MIN_DEGREE: usize = 2;
+
+/// Distributions of the coefficients of the small polynomials generated by
+/// [`Poly::small`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum NoiseDistribution {
+    /// Centered binomial distribution of the given variance, which must belong
+    /// to [1, ..., `MAX_CBD_VARIANCE`]. The sampling runs in constant time.
+    Cbd(usize),
+    /// Rounded Gaussian distribution of the given standard deviation,
+    /// truncated at 6 standard deviations. The sampling does not run in
+    /// constant time.
+    Gaussian(f64),
+    /// Ternary distribution with exactly the given number of non-zero
+    /// coefficients, which must be at most the degree. The sampling does not
+    /// run in constant time.
+    Ternary(usize),
+}
+
+impl From<usize> for NoiseDistribution {
+    /// A bare variance denotes the centered binomial distribution of this
+    /// variance.
+    fn from(variance: usize) -> Self {
+        Self::Cbd(variance)
+    }
+}
+
 /// Possible representations of the underlying polynomial.
 #[derive(Default, Debug, Clone, PartialEq, Eq)]
 pub enum Representation {
@@ -40,7 +84,7 @@ pub enum Representation {
 }
 
 /// An exponent for a substitution.
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, PartialEq, Eq, Clone)]
 pub struct SubstitutionExponent {
     /// The value of the exponent.
     pub exponent: usize,
@@ -55,9 +99,7 @@ impl SubstitutionExponent {
     pub fn new(ctx: &Arc<Context>, exponent: usize) -> Result<Self> {
         let exponent = exponent % (2 * ctx.degree);
         if exponent & 1 == 0 {
-            return Err(Error::Default(
-                "The exponent should be odd modulo 2 * degree".to_string(),
-            ));
+            return Err(Error::InvalidExponent(exponent));
         }
         let mut power = (exponent - 1) / 2;
         let mask = ctx.degree - 1;
@@ -76,15 +118,75 @@ impl SubstitutionExponent {
     }
 }
 
+/// Returns whether the variable time computations are `allowed`, which they
+/// never are with the `constant-time` feature: the variable time code paths
+/// are then compiled out.
+#[inline(always)]
+const fn variable_time_allowed(allowed: bool) -> bool {
+    allowed && !cfg!(feature = "constant-time")
+}
+
 /// Struct that holds a polynomial for a specific context.
-#[derive(Default, Debug, Clone, PartialEq, Eq)]
+#[derive(Default, Debug)]
 pub struct Poly {
     ctx: Arc<Context>,
     representation: Representation,
     has_lazy_coefficients: bool,
     allow_variable_time_computations: bool,
     coefficients: Array2<u64>,
-    coefficients_shoup: Option<Array2<u64>>,
+    coefficients_shoup: Option<Arc<Array2<u64>>>,
+    cache: Option<Box<RepresentationCache>>,
+}
+
+/// Cache of the coefficients of a polynomial in its other representation
+/// (PowerBasis or Ntt).
+#[derive(Default, Debug, Clone)]
+struct RepresentationCache {
+    /// The representation of the cached coefficients, or None when they are
+    /// stale.
+    representation: Option<Representation>,
+    coefficients: Array2<u64>,
+}
+
+impl PartialEq for Poly {
+    fn eq(&self, other: &Self) -> bool {
+        // The cache does not change the value of the polynomial.
+        self.ctx == other.ctx
+            && self.representation == other.representation
+            && self.has_lazy_coefficients == other.has_lazy_coefficients
+            && self.allow_variable_time_computations == other.allow_variable_time_computations
+            && self.coefficients == other.coefficients
+            && self.coefficients_shoup == other.coefficients_shoup
+    }
+}
+
+impl Eq for Poly {}
+
+impl Clone for Poly {
+    fn clone(&self) -> Self {
+        Self {
+            ctx: self.ctx.clone(),
+            representation: self.representation.clone(),
+            has_lazy_coefficients: self.has_lazy_coefficients,
+            allow_variable_time_computations: self.allow_variable_time_computations,
+            coefficients: self.coefficients.clone(),
+            coefficients_shoup: self.coefficients_shoup.clone(),
+            cache: self.cache.clone(),
+        }
+    }
+
+    /// Reuses the allocation of `self` when the polynomials have the same
+    /// shape.
+    fn clone_from(&mut self, source: &Self) {
+        self.ctx.clone_from(&source.ctx);
+        self.representation.clone_from(&source.representation);
+        self.has_lazy_coefficients = source.has_lazy_coefficients;
+        self.allow_variable_time_computations = source.allow_variable_time_computations;
+        self.coefficients.clone_from(&source.coefficients);
+        self.coefficients_shoup
+            .clone_from(&source.coefficients_shoup);
+        self.cache.clone_from(&source.cache);
+    }
 }
 
 impl AsRef<Poly> for Poly {
@@ -109,15 +211,18 @@ impl Poly {
             has_lazy_coefficients: false,
             coefficients: Array2::zeros((ctx.q.len(), ctx.degree)),
             coefficients_shoup: if representation == Representation::NttShoup {
-                Some(Array2::zeros((ctx.q.len(), ctx.degree)))
+                Some(Arc::new(Array2::zeros((ctx.q.len(), ctx.degree))))
             } else {
                 None
             },
+            cache: None,
         }
     }
 
     /// Enable variable time computations when this polynomial is involved.
     ///
+    /// This has no effect when the `constant-time` feature is enabled.
+    ///
     /// # Safety
     ///
     /// By default, this is marked as unsafe, but is usually safe when only
@@ -126,6 +231,13 @@ impl Poly {
         self.allow_variable_time_computations = true
     }
 
+    /// Returns whether variable time computations are enabled for this
+    /// polynomial.
+    #[inline(always)]
+    const fn variable_time(&self) -> bool {
+        variable_time_allowed(self.allow_variable_time_computations)
+    }
+
     /// Disable variable time computations when this polynomial is involved.
     pub fn disallow_variable_time_computations(&mut self) {
         self.allow_variable_time_computations = false
@@ -138,12 +250,18 @@ impl Poly {
 
     /// Change the representation of the underlying polynomial.
     pub fn change_representation(&mut self, to: Representation) {
+        self.change_representation_with_kernel(to, NttKernel::Sequential)
+    }
+
+    /// Change the representation of the underlying polynomial, computing the
+    /// NTTs of all the residues with the kernel `kernel`.
+    pub fn change_representation_with_kernel(&mut self, to: Representation, kernel: NttKernel) {
         match self.representation {
             Representation::PowerBasis => {
                 match to {
-                    Representation::Ntt => self.ntt_forward(),
+                    Representation::Ntt => self.ntt_forward(kernel),
                     Representation::NttShoup => {
-                        self.ntt_forward();
+                        self.ntt_forward(kernel);
                         self.compute_coefficients_shoup();
                     }
                     Representation::PowerBasis => {} // no-op
@@ -151,25 +269,17 @@ impl Poly {
             }
             Representation::Ntt => {
                 match to {
-                    Representation::PowerBasis => self.ntt_backward(),
+                    Representation::PowerBasis => self.ntt_backward(kernel),
                     Representation::NttShoup => self.compute_coefficients_shoup(),
                     Representation::Ntt => {} // no-op
                 }
             }
             Representation::NttShoup => {
                 if to != Representation::NttShoup {
-                    // We are not sure whether this polynomial was sensitive or not,
-                    // so for security, we zeroize the Shoup coefficients.
-                    self.coefficients_shoup
-                        .as_mut()
-                        .unwrap()
-                        .as_slice_mut()
-                        .unwrap()
-                        .zeroize();
-                    self.coefficients_shoup = None
+                    self.drop_coefficients_shoup()
                 }
                 match to {
-                    Representation::PowerBasis => self.ntt_backward(),
+                    Representation::PowerBasis => self.ntt_backward(kernel),
                     Representation::Ntt => {}      // no-op
                     Representation::NttShoup => {} // no-op
                 }
@@ -179,6 +289,56 @@ impl Poly {
         self.representation = to;
     }
 
+    /// Keep the coefficients of the polynomial in both the PowerBasis and the
+    /// Ntt representations, so that changing back to the previous
+    /// representation does not recompute the NTT as long as the polynomial is
+    /// not modified. This doubles the memory used by the polynomial.
+    pub fn enable_representation_cache(&mut self) {
+        if self.cache.is_none() {
+            self.cache = Some(Box::default())
+        }
+    }
+
+    /// Disable the cache enabled by
+    /// [`Poly::enable_representation_cache`], and zeroize its content.
+    pub fn disable_representation_cache(&mut self) {
+        if let Some(mut cache) = self.cache.take() {
+            cache.coefficients.as_slice_mut().unwrap().zeroize();
+        }
+    }
+
+    /// When the cache holds the coefficients in the representation `to`,
+    /// swaps them with the current coefficients (in the representation `from`)
+    /// and returns true. Otherwise, stores the current coefficients in the
+    /// cache and returns false.
+    fn swap_with_cache(&mut self, to: Representation, from: Representation) -> bool {
+        if self.has_lazy_coefficients {
+            self.invalidate_cache();
+            return false;
+        }
+        match self.cache.as_deref_mut() {
+            None => false,
+            Some(cache) => {
+                let hit = cache.representation.as_ref() == Some(&to);
+                if hit {
+                    std::mem::swap(&mut self.coefficients, &mut cache.coefficients);
+                } else {
+                    cache.coefficients.clone_from(&self.coefficients);
+                }
+                cache.representation = Some(from);
+                hit
+            }
+        }
+    }
+
+    /// Marks the cached coefficients as stale; this must be called whenever the
+    /// coefficients are modified.
+    fn invalidate_cache(&mut self) {
+        if let Some(cache) = self.cache.as_deref_mut() {
+            cache.representation = None
+        }
+    }
+
     /// Compute the Shoup representation of the coefficients.
     fn compute_coefficients_shoup(&mut self) {
         let mut coefficients_shoup = Array2::zeros((self.ctx.q.len(), self.ctx.degree));
@@ -193,7 +353,19 @@ impl Poly {
                 .unwrap()
                 .copy_from_slice(&qi.shoup_vec(v.as_slice().unwrap()))
         });
-        self.coefficients_shoup = Some(coefficients_shoup)
+        self.coefficients_shoup = Some(Arc::new(coefficients_shoup))
+    }
+
+    /// Drop the Shoup representation of the coefficients. The Shoup
+    /// coefficients are shared between the clones of a polynomial, and are
+    /// zeroized when they are not used by another polynomial: we are not sure
+    /// whether this polynomial was sensitive or not.
+    fn drop_coefficients_shoup(&mut self) {
+        if let Some(mut coefficients_shoup) = self.coefficients_shoup.take() {
+            if let Some(coefficients_shoup) = Arc::get_mut(&mut coefficients_shoup) {
+                coefficients_shoup.as_slice_mut().unwrap().zeroize();
+            }
+        }
     }
 
     /// Override the internal representation to a given representation.
@@ -206,16 +378,11 @@ impl Poly {
     /// state. Similarly, if we override a representation which was NttShoup, we
     /// zeroize the existing Shoup coefficients.
     pub unsafe fn override_representation(&mut self, to: Representation) {
+        self.invalidate_cache();
         if to == Representation::NttShoup {
             self.compute_coefficients_shoup()
-        } else if self.coefficients_shoup.is_some() {
-            self.coefficients_shoup
-                .as_mut()
-                .unwrap()
-                .as_slice_mut()
-                .unwrap()
-                .zeroize();
-            self.coefficients_shoup = None
+        } else {
+            self.drop_coefficients_shoup()
         }
         self.representation = to;
     }
@@ -264,60 +431,111 @@ impl Poly {
     /// Generate a small polynomial and convert into the specified
     /// representation.
     ///
-    /// Returns an error if the variance does not belong to [1, ..., 16].
-    pub fn small<T: RngCore + CryptoRng>(
+    /// The coefficients are sampled from the distribution `distribution`,
+    /// which can be a [`NoiseDistribution`] or a bare variance, denoting the
+    /// centered binomial distribution sampled in constant time with
+    /// [`sample_vec_cbd`]. Returns an error if the variance does not belong to
+    /// [1, ..., `MAX_CBD_VARIANCE`], or if the parameters of the other
+    /// distributions are invalid.
+    pub fn small<T: RngCore + CryptoRng, D: Into<NoiseDistribution>>(
         ctx: &Arc<Context>,
         representation: Representation,
-        variance: usize,
+        distribution: D,
         rng: &mut T,
     ) -> Result<Self> {
-        if !(1..=16).contains(&variance) {
-            Err(Error::Default(
-                "The variance should be an integer between 1 and 16".to_string(),
-            ))
-        } else {
-            let coeffs = Zeroizing::new(
+        let distribution = distribution.into();
+        let coeffs = Zeroizing::new(match distribution {
+            NoiseDistribution::Cbd(variance) => {
+                if !(1..=MAX_CBD_VARIANCE).contains(&variance) {
+                    return Err(Error::InvalidVariance(variance));
+                }
                 sample_vec_cbd(ctx.degree, variance, rng)
-                    .map_err(|e| Error::Default(e.to_string()))?,
-            );
-            let mut p = Poly::try_convert_from(
-                coeffs.as_ref() as &[i64],
-                ctx,
-                false,
-                Representation::PowerBasis,
-            )?;
-            if representation != Representation::PowerBasis {
-                p.change_representation(representation);
+                    .map_err(|_| Error::InvalidVariance(variance))?
             }
-            Ok(p)
+            NoiseDistribution::Gaussian(sigma) => sample_vec_gaussian(ctx.degree, sigma, rng)
+                .map_err(|e| Error::InvalidDistribution(e.to_string()))?,
+            NoiseDistribution::Ternary(hamming_weight) => {
+                sample_vec_ternary(ctx.degree, hamming_weight, rng)
+                    .map_err(|e| Error::InvalidDistribution(e.to_string()))?
+            }
+        });
+        let mut p = Poly::try_convert_from(
+            coeffs.as_ref() as &[i64],
+            ctx,
+            false,
+            Representation::PowerBasis,
+        )?;
+        if representation != Representation::PowerBasis {
+            p.change_representation(representation);
         }
+        Ok(p)
     }
 
     /// Access the polynomial coefficients in RNS representation.
-    pub fn coefficients(&self) -> ArrayView2<u64> {
+    pub fn coefficients(&self) -> ArrayView2<'_, u64> {
         self.coefficients.view()
     }
 
+    /// Returns a mutable view of the coefficients in RNS representation, with
+    /// one row per modulus of the context. The coefficients of the i-th row
+    /// must remain reduced modulo the i-th modulus.
+    ///
+    /// Returns an error if the polynomial is in NttShoup representation, since
+    /// its Shoup coefficients would not be updated, or if it has lazy
+    /// coefficients, which are not reduced.
+    pub fn coefficients_mut(&mut self) -> Result<ArrayViewMut2<'_, u64>> {
+        if self.representation == Representation::NttShoup {
+            return Err(Error::IncorrectRepresentation(
+                Representation::NttShoup,
+                Representation::Ntt,
+            ));
+        }
+        if self.has_lazy_coefficients {
+            return Err(Error::LazyCoefficients);
+        }
+        self.invalidate_cache();
+        Ok(self.coefficients.view_mut())
+    }
+
+    /// Creates a polynomial from its coefficients in RNS representation, with
+    /// one row per modulus of the context, in the representation
+    /// `representation`.
+    ///
+    /// Returns an error if the array does not have one row of `degree`
+    /// coefficients per modulus, or if the coefficients of the i-th row are
+    /// not reduced modulo the i-th modulus.
+    pub fn from_raw_rns(
+        coefficients: Array2<u64>,
+        ctx: &Arc<Context>,
+        representation: Representation,
+    ) -> Result<Self> {
+        Poly::try_convert_from(coefficients, ctx, false, representation)
+    }
+
     /// Computes the forward Ntt on the coefficients
-    fn ntt_forward(&mut self) {
-        if self.allow_variable_time_computations {
-            izip!(self.coefficients.outer_iter_mut(), self.ctx.ops.iter())
-                .for_each(|(mut v, op)| unsafe { op.forward_vt(v.as_mut_ptr()) });
-        } else {
-            izip!(self.coefficients.outer_iter_mut(), self.ctx.ops.iter())
-                .for_each(|(mut v, op)| op.forward(v.as_slice_mut().unwrap()));
+    fn ntt_forward(&mut self, kernel: NttKernel) {
+        if self.swap_with_cache(Representation::Ntt, Representation::PowerBasis) {
+            return;
         }
+        forward_batch(
+            &self.ctx.ops,
+            self.coefficients.view_mut(),
+            variable_time_allowed(self.allow_variable_time_computations),
+            kernel,
+        )
     }
 
     /// Computes the backward Ntt on the coefficients
-    fn ntt_backward(&mut self) {
-        if self.allow_variable_time_computations {
-            izip!(self.coefficients.outer_iter_mut(), self.ctx.ops.iter())
-                .for_each(|(mut v, op)| unsafe { op.backward_vt(v.as_mut_ptr()) });
-        } else {
-            izip!(self.coefficients.outer_iter_mut(), self.ctx.ops.iter())
-                .for_each(|(mut v, op)| op.backward(v.as_slice_mut().unwrap()));
+    fn ntt_backward(&mut self, kernel: NttKernel) {
+        if self.swap_with_cache(Representation::PowerBasis, Representation::Ntt) {
+            return;
         }
+        backward_batch(
+            &self.ctx.ops,
+            self.coefficients.view_mut(),
+            variable_time_allowed(self.allow_variable_time_computations),
+            kernel,
+        )
     }
 
     /// Substitute x by x^i in a polynomial.
@@ -326,48 +544,55 @@ impl Poly {
     /// odd integer that is not a multiple of 2 * degree.
     pub fn substitute(&self, i: &SubstitutionExponent) -> Result<Poly> {
         let mut q = Poly::zero(&self.ctx, self.representation.clone());
-        if self.allow_variable_time_computations {
-            unsafe { q.allow_variable_time_computations() }
+        self.substitute_into(i, &mut q)?;
+        Ok(q)
+    }
+
+    /// Substitute x by x^i in a polynomial, and store the result in `out`,
+    /// reusing its allocation. In Ntt and NttShoup representation, the
+    /// substitution is a permutation of the coefficients, so that the
+    /// polynomial does not need to be converted to PowerBasis representation.
+    /// The exponent i must satisfy the same conditions as for
+    /// [`Poly::substitute`].
+    pub fn substitute_into(&self, i: &SubstitutionExponent, out: &mut Poly) -> Result<()> {
+        if self.ctx.has_convolutions() && self.representation != Representation::PowerBasis {
+            // The Ntt representation of the moduli without NTT is the PowerBasis
+            // representation, so the substitution is computed in PowerBasis.
+            let mut p = self.clone();
+            p.change_representation(Representation::PowerBasis);
+            p.substitute_into(i, out)?;
+            out.change_representation(self.representation.clone());
+            return Ok(());
         }
+
+        out.prepare_for(&self.ctx, self.representation.clone());
+        out.allow_variable_time_computations = self.allow_variable_time_computations;
+        let permute = |to: &mut Array2<u64>, from: &Array2<u64>| {
+            izip!(to.outer_iter_mut(), from.outer_iter()).for_each(|(mut to_row, from_row)| {
+                for (j, k) in izip!(self.ctx.bitrev.iter(), i.power_bitrev.iter()) {
+                    to_row[*j] = from_row[*k]
+                }
+            });
+        };
         match self.representation {
-            Representation::Ntt => {
-                izip!(
-                    q.coefficients.outer_iter_mut(),
-                    self.coefficients.outer_iter()
-                )
-                .for_each(|(mut q_row, p_row)| {
-                    for (j, k) in izip!(self.ctx.bitrev.iter(), i.power_bitrev.iter()) {
-                        q_row[*j] = p_row[*k]
-                    }
-                });
-            }
+            Representation::Ntt => permute(&mut out.coefficients, &self.coefficients),
             Representation::NttShoup => {
-                izip!(
-                    q.coefficients.outer_iter_mut(),
-                    self.coefficients.outer_iter()
-                )
-                .for_each(|(mut q_row, p_row)| {
-                    for (j, k) in izip!(self.ctx.bitrev.iter(), i.power_bitrev.iter()) {
-                        q_row[*j] = p_row[*k]
-                    }
-                });
-                izip!(
-                    q.coefficients_shoup.as_mut().unwrap().outer_iter_mut(),
-                    self.coefficients_shoup.as_ref().unwrap().outer_iter()
-                )
-                .for_each(|(mut q_row, p_row)| {
-                    for (j, k) in izip!(self.ctx.bitrev.iter(), i.power_bitrev.iter()) {
-                        q_row[*j] = p_row[*k]
-                    }
-                });
+                permute(&mut out.coefficients, &self.coefficients);
+                let mut coefficients_shoup = Array2::zeros(self.coefficients.dim());
+                permute(
+                    &mut coefficients_shoup,
+                    self.coefficients_shoup.as_ref().unwrap(),
+                );
+                out.coefficients_shoup = Some(Arc::new(coefficients_shoup));
             }
             Representation::PowerBasis => {
+                out.coefficients.fill(0);
                 let mut power = 0usize;
                 let mask = self.ctx.degree - 1;
                 for j in 0..self.ctx.degree {
                     izip!(
                         self.ctx.q.iter(),
-                        q.coefficients.slice_mut(s![.., power & mask]),
+                        out.coefficients.slice_mut(s![.., power & mask]),
                         self.coefficients.slice(s![.., j])
                     )
                     .for_each(|(qi, qij, pij)| {
@@ -382,7 +607,7 @@ impl Poly {
             }
         }
 
-        Ok(q)
+        Ok(())
     }
 
     /// Create a polynomial which can only be multiplied by a polynomial in
@@ -395,24 +620,62 @@ impl Poly {
         power_basis_coefficients: &[u64],
         ctx: &Arc<Context>,
     ) -> Self {
-        let mut coefficients = Array2::zeros((ctx.q.len(), ctx.degree));
-        izip!(coefficients.outer_iter_mut(), ctx.q.iter(), ctx.ops.iter()).for_each(
-            |(mut p, qi, op)| {
-                p.as_slice_mut()
-                    .unwrap()
-                    .clone_from_slice(power_basis_coefficients);
-                qi.lazy_reduce_vec(p.as_slice_mut().unwrap());
-                op.forward_vt_lazy(p.as_mut_ptr());
-            },
+        let mut p = Self::default();
+        p.set_constant_ntt_polynomial_with_lazy_coefficients_and_variable_time(
+            power_basis_coefficients,
+            ctx,
         );
-        Self {
-            ctx: ctx.clone(),
-            representation: Representation::Ntt,
-            allow_variable_time_computations: true,
-            coefficients,
-            coefficients_shoup: None,
-            has_lazy_coefficients: true,
+        p
+    }
+
+    /// Overwrites the polynomial with the constant polynomial created by
+    /// [`Poly::create_constant_ntt_polynomial_with_lazy_coefficients_and_variable_time`],
+    /// reusing its allocation when possible.
+    ///
+    /// # Safety
+    /// See [`Poly::create_constant_ntt_polynomial_with_lazy_coefficients_and_variable_time`].
+    pub unsafe fn set_constant_ntt_polynomial_with_lazy_coefficients_and_variable_time(
+        &mut self,
+        power_basis_coefficients: &[u64],
+        ctx: &Arc<Context>,
+    ) {
+        self.prepare_for(ctx, Representation::Ntt);
+        izip!(
+            self.coefficients.outer_iter_mut(),
+            ctx.q.iter(),
+            ctx.ops.iter()
+        )
+        .for_each(|(mut p, qi, op)| {
+            p.as_slice_mut()
+                .unwrap()
+                .clone_from_slice(power_basis_coefficients);
+            qi.lazy_reduce_vec(p.as_slice_mut().unwrap());
+            if let Some(op) = op {
+                if variable_time_allowed(true) {
+                    op.forward_vt_lazy(p.as_mut_ptr());
+                } else {
+                    op.forward(p.as_slice_mut().unwrap());
+                }
+            }
+        });
+        self.allow_variable_time_computations = true;
+        self.has_lazy_coefficients = true;
+    }
+
+    /// Prepares the polynomial to be overwritten in the context `ctx` and the
+    /// representation `representation`, reusing the allocation of the
+    /// coefficients when their shape is unchanged. The coefficients are left
+    /// unspecified.
+    fn prepare_for(&mut self, ctx: &Arc<Context>, representation: Representation) {
+        self.invalidate_cache();
+        if self.coefficients.dim() != (ctx.q.len(), ctx.degree) {
+            self.coefficients = Array2::zeros((ctx.q.len(), ctx.degree));
         }
+        self.drop_coefficients_shoup();
+        self.ctx.clone_from(ctx);
+        self.representation = representation;
+        self.has_lazy_coefficients = false;
+        self.allow_variable_time_computations = false;
     }
 
     /// Modulus switch down the polynomial by dividing and rounding each
@@ -422,6 +685,7 @@ impl Poly {
     /// Returns an error if there is no next context or if the representation
     /// is not PowerBasis.
     pub fn mod_switch_down_next(&mut self) -> Result<()> {
+        self.invalidate_cache();
         if self.ctx.next_context.is_none() {
             return Err(Error::NoMoreContext);
         }
@@ -441,10 +705,11 @@ impl Poly {
         let q_last_div_2 = q_last.modulus() / 2;
 
         // Add (q_last - 1) / 2 to change from flooring to rounding
+        let variable_time = self.variable_time();
         let (mut q_new_polys, mut q_last_poly) =
             self.coefficients.view_mut().split_at(Axis(0), q_len - 1);
 
-        if self.allow_variable_time_computations {
+        if variable_time {
             unsafe {
                 q_last_poly
                     .iter_mut()
@@ -497,7 +762,7 @@ impl Poly {
         }
 
         // Remove the last row, and update the context.
-        if !self.allow_variable_time_computations {
+        if !variable_time {
             q_last_poly.as_slice_mut().unwrap().zeroize();
         }
         self.coefficients.remove_index(Axis(0), q_len - 1);
@@ -520,6 +785,54 @@ impl Poly {
         Ok(())
     }
 
+    /// Modulo raise to a larger context, by lifting each coefficient to its
+    /// centered representative modulo the current modulus, and reducing it
+    /// modulo the moduli of `context`. This is the inverse of
+    /// [`Poly::mod_switch_down_to`] up to a multiple of the current modulus.
+    ///
+    /// Returns an error if the current context is not a child of the provided
+    /// context, or if the polynomial is not in PowerBasis representation.
+    pub fn mod_raise_to(&mut self, context: &Arc<Context>) -> Result<()> {
+        context.niterations_to(&self.ctx)?;
+        if self.representation != Representation::PowerBasis {
+            return Err(Error::IncorrectRepresentation(
+                self.representation.clone(),
+                Representation::PowerBasis,
+            ));
+        }
+        if &self.ctx == context {
+            return Ok(());
+        }
+        self.invalidate_cache();
+
+        // A negative coefficient x - q is represented by x + (Q - q) modulo the
+        // larger modulus Q.
+        let modulus = self.ctx.modulus();
+        let difference = context.modulus() - modulus;
+        let mut coefficients = Array2::zeros((context.q.len(), context.degree));
+        izip!(
+            coefficients.axis_iter_mut(Axis(1)),
+            self.coefficients.axis_iter(Axis(1))
+        )
+        .for_each(|(mut raised, residues)| {
+            let mut x = self.ctx.rns.lift(residues);
+            if &(&x << 1) > modulus {
+                x += &difference
+            }
+            raised
+                .iter_mut()
+                .zip(context.rns.project(&x))
+                .for_each(|(r, xi)| *r = xi);
+        });
+
+        if !self.allow_variable_time_computations {
+            self.coefficients.as_slice_mut().unwrap().zeroize();
+        }
+        self.coefficients = coefficients;
+        self.ctx = context.clone();
+        Ok(())
+    }
+
     /// Modulo switch to another context. The target context needs not to be
     /// related to the current context.
     pub fn mod_switch_to(&self, switcher: &Switcher) -> Result<Poly> {
@@ -531,6 +844,19 @@ impl Poly {
         scaler.scale(self)
     }
 
+    /// Scale a polynomial using a scaler, and write the result in `out`.
+    ///
+    /// The allocations of `out` and `buffer` are reused across calls; the
+    /// `buffer` is only used for polynomials in Ntt representation.
+    pub fn scale_into(
+        &self,
+        scaler: &Scaler,
+        out: &mut Poly,
+        buffer: &mut Array2<u64>,
+    ) -> Result<()> {
+        scaler.scale_into(self, out, buffer)
+    }
+
     /// Returns the context of the underlying polynomial
     pub fn ctx(&self) -> &Arc<Context> {
         &self.ctx
@@ -538,6 +864,7 @@ impl Poly {
 
     /// Multiplies a polynomial in PowerBasis representation by x^(-power).
     pub fn multiply_inverse_power_of_x(&mut self, power: usize) -> Result<()> {
+        self.invalidate_cache();
         if self.representation != Representation::PowerBasis {
             return Err(Error::IncorrectRepresentation(
                 self.representation.clone(),
@@ -565,28 +892,90 @@ impl Poly {
         });
         Ok(())
     }
+
+    /// Computes the inverse of a polynomial in Ntt or NttShoup representation,
+    /// by inverting each of its coefficients. The inverse is returned in Ntt
+    /// representation.
+    ///
+    /// Returns an error if the polynomial is in PowerBasis representation, if
+    /// a modulus of the context does not support the NTT, or if the
+    /// polynomial is not invertible, i.e. one of its Ntt coefficients is zero
+    /// or, for a modulus that is a product of two primes, a zero divisor.
+    pub fn inverse(&self) -> Result<Poly> {
+        if self.representation == Representation::PowerBasis {
+            return Err(Error::IncorrectRepresentation(
+                self.representation.clone(),
+                Representation::Ntt,
+            ));
+        }
+        if self.ctx.has_convolutions() {
+            let modulus = izip!(self.ctx.moduli.iter(), self.ctx.ops.iter())
+                .find(|(_, op)| op.is_none())
+                .map(|(qi, _)| *qi)
+                .unwrap_or_default();
+            return Err(Error::UnsupportedModulus(modulus));
+        }
+
+        let mut q = Poly::zero(&self.ctx, Representation::Ntt);
+        q.allow_variable_time_computations = self.allow_variable_time_computations;
+        let mut invertible = true;
+        izip!(
+            q.coefficients.outer_iter_mut(),
+            self.coefficients.outer_iter(),
+            self.ctx.q.iter()
+        )
+        .for_each(|(mut q_row, p_row, qi)| {
+            // The moduli supporting the NTT are either prime, or products of
+            // two primes for which the coefficients may be zero divisors.
+            let prime = is_prime(qi.modulus());
+            izip!(q_row.iter_mut(), p_row.iter()).for_each(|(qij, pij)| {
+                let pij = qi.reduce(*pij);
+                let inv = if prime {
+                    (pij != 0).then(|| qi.pow(pij, qi.modulus() - 2))
+                } else {
+                    inverse(pij, qi.modulus())
+                };
+                invertible &= inv.is_some();
+                *qij = inv.unwrap_or_default()
+            })
+        });
+        if invertible {
+            Ok(q)
+        } else {
+            Err(Error::NotInvertible)
+        }
+    }
 }
 
 impl Zeroize for Poly {
     fn zeroize(&mut self) {
         self.coefficients.as_slice_mut().unwrap().zeroize();
-        if let Some(s) = self.coefficients_shoup.as_mut() {
-            s.as_slice_mut().unwrap().zeroize();
+        if let Some(cache) = self.cache.as_deref_mut() {
+            cache.coefficients.as_slice_mut().unwrap().zeroize();
+            cache.representation = None;
         }
+        // The Shoup coefficients may be shared with other polynomials, so they
+        // are released instead of being modified.
+        self.coefficients_shoup = None;
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use super::{switcher::Switcher, Context, Poly, Representation};
-    use crate::{rq::SubstitutionExponent, zq::Modulus};
-    use fhe_util::variance;
-    use itertools::Itertools;
+    use super::{
+        switcher::Switcher, traits::TryConvertFrom, Context, NoiseDistribution, Poly,
+        Representation,
+    };
+    use crate::{ntt::NttKernel, rq::SubstitutionExponent, zq::Modulus};
+    use fhe_util::{variance, MAX_CBD_VARIANCE};
+    use itertools::{izip, Itertools};
+    use ndarray::Array2;
     use num_bigint::BigUint;
     use num_traits::{One, Zero};
     use rand::{thread_rng, Rng, SeedableRng};
     use rand_chacha::ChaCha8Rng;
     use std::{error::Error, sync::Arc};
+    use zeroize::Zeroize;
 
     // Moduli to be used in tests.
     const MODULI: &[u64; 5] = &[
@@ -767,6 +1156,9 @@ mod tests {
         q = -&p;
         assert!(q.allow_variable_time_computations);
 
+        // The variable time code paths are compiled out in constant-time mode.
+        assert_eq!(q.variable_time(), !cfg!(feature = "constant-time"));
+
         Ok(())
     }
 
@@ -799,8 +1191,21 @@ mod tests {
         assert!(p.coefficients_shoup.is_some());
         let q_ntt_shoup = p.clone();
 
+        // The Shoup coefficients are shared between clones, and are not
+        // zeroized while they are still in use.
+        assert!(Arc::ptr_eq(
+            p.coefficients_shoup.as_ref().unwrap(),
+            q_ntt_shoup.coefficients_shoup.as_ref().unwrap()
+        ));
+        let mut zeroized = p.clone();
+        zeroized.zeroize();
+        assert!(zeroized.coefficients_shoup.is_none());
+        assert_eq!(p, q_ntt_shoup);
         p.change_representation(Representation::PowerBasis);
         assert_eq!(p, q);
+        let mut expected_shoup = q_ntt_shoup.clone();
+        expected_shoup.compute_coefficients_shoup();
+        assert_eq!(q_ntt_shoup, expected_shoup);
 
         p.change_representation(Representation::NttShoup);
         assert_eq!(p, q_ntt_shoup);
@@ -811,6 +1216,53 @@ mod tests {
         p.change_representation(Representation::PowerBasis);
         assert_eq!(p, q);
 
+        for kernel in [NttKernel::Sequential, NttKernel::Threaded(3)] {
+            p.change_representation_with_kernel(Representation::NttShoup, kernel);
+            assert_eq!(p, q_ntt_shoup);
+            p.change_representation_with_kernel(Representation::PowerBasis, kernel);
+            assert_eq!(p, q);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn representation_cache() -> Result<(), Box<dyn Error>> {
+        let mut rng = thread_rng();
+        let ctx = Arc::new(Context::new(MODULI, 16)?);
+
+        let mut p = Poly::random(&ctx, Representation::PowerBasis, &mut rng);
+        let q = p.clone();
+        let mut q_ntt = q.clone();
+        q_ntt.change_representation(Representation::Ntt);
+
+        p.enable_representation_cache();
+        p.change_representation(Representation::Ntt);
+        assert_eq!(p, q_ntt);
+        p.change_representation(Representation::PowerBasis);
+        assert_eq!(p, q);
+        p.change_representation(Representation::NttShoup);
+        assert_eq!(p.coefficients, q_ntt.coefficients);
+        p.change_representation(Representation::PowerBasis);
+        assert_eq!(p, q);
+
+        // The cache is invalidated when the polynomial is modified.
+        p.change_representation(Representation::Ntt);
+        p += &q_ntt;
+        let expected = &q_ntt + &q_ntt;
+        assert_eq!(p, expected);
+        p.change_representation(Representation::PowerBasis);
+        let mut expected_power_basis = expected.clone();
+        expected_power_basis.change_representation(Representation::PowerBasis);
+        assert_eq!(p, expected_power_basis);
+        let mut p = -p;
+        p.change_representation(Representation::Ntt);
+        assert_eq!(p, -&expected);
+
+        p.disable_representation_cache();
+        assert!(p.cache.is_none());
+        p.change_representation(Representation::PowerBasis);
+        assert_eq!(p, -&expected_power_basis);
         Ok(())
     }
 
@@ -847,6 +1299,46 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn raw_rns() -> Result<(), Box<dyn Error>> {
+        let mut rng = thread_rng();
+        let ctx = Arc::new(Context::new(MODULI, 16)?);
+
+        let p = Poly::random(&ctx, Representation::Ntt, &mut rng);
+        let q = Poly::from_raw_rns(p.coefficients().to_owned(), &ctx, Representation::Ntt)?;
+        assert_eq!(p, q);
+        let q = Poly::from_raw_rns(p.coefficients().to_owned(), &ctx, Representation::NttShoup)?;
+        assert_eq!(q.coefficients(), p.coefficients());
+        assert!(q.coefficients_shoup.is_some());
+
+        assert_eq!(
+            Poly::from_raw_rns(Array2::zeros((2, 16)), &ctx, Representation::Ntt).unwrap_err(),
+            crate::Error::InvalidLength {
+                expected: MODULI.len() * 16,
+                found: 32
+            }
+        );
+        let mut a = Array2::zeros((MODULI.len(), 16));
+        a[[1, 3]] = MODULI[1];
+        assert_eq!(
+            Poly::from_raw_rns(a, &ctx, Representation::Ntt).unwrap_err(),
+            crate::Error::UnreducedCoefficients
+        );
+
+        // Negate the polynomial in place through the mutable view.
+        let mut q = p.clone();
+        izip!(q.coefficients_mut()?.outer_iter_mut(), ctx.q.iter())
+            .for_each(|(mut row, qi)| qi.neg_vec(row.as_slice_mut().unwrap()));
+        assert_eq!(q, -&p);
+
+        let mut q = Poly::random(&ctx, Representation::NttShoup, &mut rng);
+        assert_eq!(
+            q.coefficients_mut().unwrap_err(),
+            crate::Error::IncorrectRepresentation(Representation::NttShoup, Representation::Ntt)
+        );
+        Ok(())
+    }
+
     #[test]
     fn small() -> Result<(), Box<dyn Error>> {
         let mut rng = thread_rng();
@@ -856,18 +1348,20 @@ mod tests {
 
             let e = Poly::small(&ctx, Representation::PowerBasis, 0, &mut rng);
             assert!(e.is_err());
-            assert_eq!(
-                e.unwrap_err().to_string(),
-                "The variance should be an integer between 1 and 16"
+            assert_eq!(e.unwrap_err(), crate::Error::InvalidVariance(0));
+            let e = Poly::small(
+                &ctx,
+                Representation::PowerBasis,
+                MAX_CBD_VARIANCE + 1,
+                &mut rng,
             );
-            let e = Poly::small(&ctx, Representation::PowerBasis, 17, &mut rng);
             assert!(e.is_err());
             assert_eq!(
-                e.unwrap_err().to_string(),
-                "The variance should be an integer between 1 and 16"
+                e.unwrap_err(),
+                crate::Error::InvalidVariance(MAX_CBD_VARIANCE + 1)
             );
 
-            for i in 1..=16 {
+            for i in (1..=16).chain([100, 1000]) {
                 let p = Poly::small(&ctx, Representation::PowerBasis, i, &mut rng)?;
                 let coefficients = p.coefficients().to_slice().unwrap();
                 let v = unsafe { q.center_vec_vt(coefficients) };
@@ -885,6 +1379,55 @@ mod tests {
         assert!(v.iter().map(|vi| vi.abs()).max().unwrap() <= 32);
         assert_eq!(variance(&v).round(), 16.0);
 
+        // The other distributions.
+        let p = Poly::small(
+            &ctx,
+            Representation::Ntt,
+            NoiseDistribution::Cbd(16),
+            &mut rng,
+        )?;
+        assert_eq!(p.representation, Representation::Ntt);
+        let p = Poly::small(
+            &ctx,
+            Representation::PowerBasis,
+            NoiseDistribution::Gaussian(3.2),
+            &mut rng,
+        )?;
+        let v = unsafe { q.center_vec_vt(p.coefficients().to_slice().unwrap()) };
+        assert!(v.iter().map(|vi| vi.abs()).max().unwrap() <= 19);
+        assert!((variance(&v) - 3.2 * 3.2).abs() < 0.5);
+        let p = Poly::small(
+            &ctx,
+            Representation::PowerBasis,
+            NoiseDistribution::Ternary(64),
+            &mut rng,
+        )?;
+        let v = unsafe { q.center_vec_vt(p.coefficients().to_slice().unwrap()) };
+        assert_eq!(v.iter().filter(|vi| **vi != 0).count(), 64);
+        assert!(v.iter().all(|vi| vi.abs() <= 1));
+
+        let ctx = Arc::new(Context::new(MODULI, 16)?);
+        for distribution in [
+            NoiseDistribution::Gaussian(0.0),
+            NoiseDistribution::Gaussian(f64::NAN),
+            NoiseDistribution::Ternary(17),
+        ] {
+            assert!(matches!(
+                Poly::small(&ctx, Representation::PowerBasis, distribution, &mut rng),
+                Err(crate::Error::InvalidDistribution(_))
+            ));
+        }
+        assert_eq!(
+            Poly::small(
+                &ctx,
+                Representation::PowerBasis,
+                NoiseDistribution::Cbd(0),
+                &mut rng
+            )
+            .unwrap_err(),
+            crate::Error::InvalidVariance(0)
+        );
+
         Ok(())
     }
 
@@ -981,6 +1524,17 @@ mod tests {
                 .substitute(&SubstitutionExponent::new(&ctx, 11)?)?
         );
 
+        // The substitution can be stored in a polynomial of another representation.
+        let exponent = SubstitutionExponent::new(&ctx, 5)?;
+        let mut out = Poly::random(&ctx, Representation::NttShoup, &mut rng);
+        p.substitute_into(&exponent, &mut out)?;
+        assert_eq!(out, p.substitute(&exponent)?);
+        p_ntt_shoup.substitute_into(&exponent, &mut out)?;
+        assert_eq!(out, p_ntt_shoup.substitute(&exponent)?);
+        p_ntt.substitute_into(&exponent, &mut out)?;
+        out.change_representation(Representation::PowerBasis);
+        assert_eq!(out, p.substitute(&exponent)?);
+
         Ok(())
     }
 
@@ -1056,6 +1610,39 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn mod_raise_to() -> Result<(), Box<dyn Error>> {
+        let mut rng = thread_rng();
+        let ctx1 = Arc::new(Context::new(MODULI, 16)?);
+        let ctx2 = Arc::new(Context::new(&MODULI[..2], 16)?);
+
+        for _ in 0..100 {
+            let mut p = Poly::random(&ctx2, Representation::PowerBasis, &mut rng);
+            let reference = Vec::<BigUint>::from(&p);
+
+            p.mod_raise_to(&ctx1)?;
+
+            assert_eq!(p.ctx, ctx1);
+            assert_eq!(
+                Vec::<BigUint>::from(&p),
+                reference
+                    .iter()
+                    .map(|b| if b << 1 > *ctx2.modulus() {
+                        b + ctx1.modulus() - ctx2.modulus()
+                    } else {
+                        b.clone()
+                    })
+                    .collect_vec()
+            );
+        }
+
+        let mut p = Poly::random(&ctx1, Representation::PowerBasis, &mut rng);
+        assert!(p.mod_raise_to(&ctx2).is_err());
+        let mut p = Poly::random(&ctx2, Representation::Ntt, &mut rng);
+        assert!(p.mod_raise_to(&ctx1).is_err());
+        Ok(())
+    }
+
     #[test]
     fn mod_switch_to() -> Result<(), Box<dyn Error>> {
         let mut rng = thread_rng();
@@ -1115,4 +1702,71 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn inverse() -> Result<(), Box<dyn Error>> {
+        let mut rng = thread_rng();
+        // Random polynomials are invertible with overwhelming probability
+        // modulo the large moduli, but not modulo 1153.
+        let ctx = Arc::new(Context::new(&MODULI[1..], 16)?);
+        let mut one = Poly::try_convert_from(&[1u64], &ctx, false, Representation::PowerBasis)?;
+        one.change_representation(Representation::Ntt);
+
+        for representation in [Representation::Ntt, Representation::NttShoup] {
+            let p = Poly::random(&ctx, representation, &mut rng);
+            let p_inv = p.inverse()?;
+            assert_eq!(p_inv.representation, Representation::Ntt);
+            assert_eq!(&p_inv * &p, one);
+            assert_eq!(p_inv.inverse()?, {
+                let mut q = p.clone();
+                q.change_representation(Representation::Ntt);
+                q
+            });
+        }
+
+        let e = Poly::random(&ctx, Representation::PowerBasis, &mut rng).inverse();
+        assert_eq!(
+            e.unwrap_err(),
+            crate::Error::IncorrectRepresentation(Representation::PowerBasis, Representation::Ntt)
+        );
+        assert!(Poly::zero(&ctx, Representation::Ntt).inverse().is_err());
+        let mut p = Poly::random(&ctx, Representation::Ntt, &mut rng);
+        p.coefficients[[1, 3]] = 0;
+        assert!(p.inverse().is_err());
+
+        let ctx = Arc::new(Context::new(&[1153, 4611686018427387847], 16)?);
+        assert!(Poly::random(&ctx, Representation::Ntt, &mut rng)
+            .inverse()
+            .is_err());
+
+        // Modulo a product of two primes, the coefficients are inverted with
+        // the extended gcd, and the zero divisors are not invertible.
+        let composite = 12289 * 40961;
+        let ctx = Arc::new(Context::new(&[composite, 1153], 16)?);
+        let mut one = Poly::try_convert_from(&[1u64], &ctx, false, Representation::PowerBasis)?;
+        one.change_representation(Representation::Ntt);
+        for _ in 0..50 {
+            let mut p = Poly::random(&ctx, Representation::Ntt, &mut rng);
+            p.coefficients
+                .iter_mut()
+                .for_each(|pij| *pij = (*pij).max(1));
+            match p.inverse() {
+                Ok(p_inv) => assert_eq!(&p_inv * &p, one),
+                Err(e) => {
+                    assert_eq!(e, crate::Error::NotInvertible);
+                    assert!(p
+                        .coefficients
+                        .row(0)
+                        .iter()
+                        .any(|c| c % 12289 == 0 || c % 40961 == 0));
+                }
+            }
+        }
+        let mut p = Poly::random(&ctx, Representation::Ntt, &mut rng);
+        p.coefficients.iter_mut().for_each(|pij| *pij = 1);
+        p.coefficients[[0, 5]] = 12289 * 7;
+        assert_eq!(p.inverse().unwrap_err(), crate::Error::NotInvertible);
+
+        Ok(())
+    }
 }
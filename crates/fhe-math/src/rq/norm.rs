@@ -0,0 +1,175 @@
+#![warn(missing_docs, unused_imports)]
+
+//! Norms of polynomials.
+
+use super::{Poly, Representation};
+use num_bigint::BigUint;
+use num_traits::{ToPrimitive, Zero};
+use std::f64::consts::PI;
+
+impl Poly {
+    /// Returns the centered coefficients of the polynomial over the integers,
+    /// i.e. the coefficients in [-q/2, q/2) where q is the modulus of the
+    /// context, as pairs (absolute value, is negative).
+    fn centered_coefficients(&self) -> Vec<(BigUint, bool)> {
+        let mut p = self.clone();
+        p.change_representation(Representation::PowerBasis);
+        let modulus = self.ctx.modulus();
+        let half_modulus = modulus >> 1;
+        Vec::<BigUint>::from(&p)
+            .into_iter()
+            .map(|c| {
+                if c > half_modulus {
+                    (modulus - c, true)
+                } else {
+                    (c, false)
+                }
+            })
+            .collect()
+    }
+
+    /// Computes the infinity norm of the polynomial, i.e. the largest absolute
+    /// value of its coefficients centered in [-q/2, q/2), where q is the
+    /// modulus of the context.
+    ///
+    /// This function runs in variable time.
+    pub fn infinity_norm(&self) -> BigUint {
+        self.centered_coefficients()
+            .into_iter()
+            .map(|(c, _)| c)
+            .max()
+            .unwrap_or_else(BigUint::zero)
+    }
+
+    /// Estimates the infinity norm of the canonical embedding of the
+    /// polynomial, i.e. the largest absolute value of the polynomial with
+    /// centered coefficients evaluated at the primitive 2 * degree-th roots of
+    /// unity.
+    ///
+    /// The evaluations are computed with a Fast Fourier Transform over the
+    /// floating-point numbers, so the norm is only approximated to the
+    /// precision of a f64. This function runs in variable time.
+    pub fn canonical_norm(&self) -> f64 {
+        let degree = self.ctx.degree;
+
+        // Multiply the i-th coefficient by exp(i * pi * I / degree), so that
+        // the evaluations at the roots exp((2 * j + 1) * pi * I / degree) are
+        // the Discrete Fourier Transform of the twisted coefficients.
+        let mut values = self
+            .centered_coefficients()
+            .into_iter()
+            .enumerate()
+            .map(|(i, (c, is_negative))| {
+                let c = c.to_f64().unwrap();
+                let c = if is_negative { -c } else { c };
+                let angle = PI * i as f64 / degree as f64;
+                (c * angle.cos(), c * angle.sin())
+            })
+            .collect::<Vec<_>>();
+        fft(&mut values);
+
+        values
+            .iter()
+            .map(|(re, im)| re.hypot(*im))
+            .fold(0.0, f64::max)
+    }
+}
+
+/// Computes in place the Discrete Fourier Transform
+/// `a_j = sum_k a_k * exp(2 * pi * I * j * k / n)` of a vector of complex
+/// numbers, represented as pairs (real part, imaginary part), whose size n is
+/// a power of two.
+fn fft(a: &mut [(f64, f64)]) {
+    let n = a.len();
+    debug_assert!(n.is_power_of_two());
+    if n < 2 {
+        return;
+    }
+
+    for i in 0..n {
+        let j = i.reverse_bits() >> (n.leading_zeros() + 1);
+        if i < j {
+            a.swap(i, j)
+        }
+    }
+
+    let mut m = 2;
+    while m <= n {
+        let angle = 2.0 * PI / m as f64;
+        for start in (0..n).step_by(m) {
+            for k in 0..m / 2 {
+                let (w_re, w_im) = ((angle * k as f64).cos(), (angle * k as f64).sin());
+                let (x_re, x_im) = a[start + k];
+                let (y_re, y_im) = a[start + k + m / 2];
+                let (t_re, t_im) = (y_re * w_re - y_im * w_im, y_re * w_im + y_im * w_re);
+                a[start + k] = (x_re + t_re, x_im + t_im);
+                a[start + k + m / 2] = (x_re - t_re, x_im - t_im);
+            }
+        }
+        m <<= 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::rq::{traits::TryConvertFrom, Context, Poly, Representation};
+    use num_bigint::BigUint;
+    use num_traits::ToPrimitive;
+    use rand::thread_rng;
+    use std::{error::Error, f64::consts::PI, sync::Arc};
+
+    const MODULI: &[u64; 3] = &[1153, 4611686018326724609, 4611686018309947393];
+
+    #[test]
+    fn infinity_norm() -> Result<(), Box<dyn Error>> {
+        let ctx = Arc::new(Context::new(MODULI, 16)?);
+        assert_eq!(
+            Poly::zero(&ctx, Representation::Ntt).infinity_norm(),
+            BigUint::from(0u64)
+        );
+
+        let mut p =
+            Poly::try_convert_from(&[1i64, -5, 3], &ctx, false, Representation::PowerBasis)?;
+        assert_eq!(p.infinity_norm(), BigUint::from(5u64));
+        p.change_representation(Representation::NttShoup);
+        assert_eq!(p.infinity_norm(), BigUint::from(5u64));
+
+        let mut rng = thread_rng();
+        let p = Poly::random(&ctx, Representation::PowerBasis, &mut rng);
+        assert!(p.infinity_norm() <= ctx.modulus() >> 1);
+        Ok(())
+    }
+
+    #[test]
+    fn canonical_norm() -> Result<(), Box<dyn Error>> {
+        let ctx = Arc::new(Context::new(MODULI, 16)?);
+        let close = |a: f64, b: f64| (a - b).abs() <= 1e-9 * b.max(1.0);
+
+        assert_eq!(Poly::zero(&ctx, Representation::Ntt).canonical_norm(), 0.0);
+        let p = Poly::try_convert_from(&[-7i64], &ctx, false, Representation::PowerBasis)?;
+        assert!(close(p.canonical_norm(), 7.0));
+        let p = Poly::try_convert_from(&[0i64, 1], &ctx, false, Representation::PowerBasis)?;
+        assert!(close(p.canonical_norm(), 1.0));
+        // |1 + x| is maximal at the root exp(pi * I / 16).
+        let mut p = Poly::try_convert_from(&[1i64, 1], &ctx, false, Representation::PowerBasis)?;
+        p.change_representation(Representation::Ntt);
+        assert!(close(p.canonical_norm(), 2.0 * (PI / 32.0).cos()));
+
+        // The canonical norm is between the l2 and the l1 norms.
+        let mut rng = thread_rng();
+        for _ in 0..20 {
+            let p = Poly::small(&ctx, Representation::PowerBasis, 16, &mut rng)?;
+            let coefficients = p
+                .centered_coefficients()
+                .into_iter()
+                .map(|(c, _)| c.to_f64().unwrap())
+                .collect::<Vec<_>>();
+            let l1 = coefficients.iter().sum::<f64>();
+            let l2 = coefficients.iter().map(|c| c * c).sum::<f64>().sqrt();
+            let norm = p.canonical_norm();
+            assert!(l2 <= norm * (1.0 + 1e-9));
+            assert!(norm <= l1 * (1.0 + 1e-9));
+        }
+        Ok(())
+    }
+}
@@ -1,6 +1,6 @@
 //! Implementation of operations over polynomials.
 
-use super::{traits::TryConvertFrom, Poly, Representation};
+use super::{traits::TryConvertFrom, Context, Poly, Representation};
 use crate::{Error, Result};
 use itertools::{izip, Itertools};
 use ndarray::Array2;
@@ -9,10 +9,10 @@ use std::{
     cmp::min,
     ops::{Add, AddAssign, Mul, MulAssign, Neg, Sub, SubAssign},
 };
-use zeroize::Zeroize;
 
 impl AddAssign<&Poly> for Poly {
     fn add_assign(&mut self, p: &Poly) {
+        self.invalidate_cache();
         assert!(!self.has_lazy_coefficients && !p.has_lazy_coefficients);
         assert_ne!(
             self.representation,
@@ -25,7 +25,7 @@ impl AddAssign<&Poly> for Poly {
         );
         debug_assert_eq!(self.ctx, p.ctx, "Incompatible contexts");
         self.allow_variable_time_computations |= p.allow_variable_time_computations;
-        if self.allow_variable_time_computations {
+        if self.variable_time() {
             izip!(
                 self.coefficients.outer_iter_mut(),
                 p.coefficients.outer_iter(),
@@ -66,6 +66,7 @@ impl Add for Poly {
 
 impl SubAssign<&Poly> for Poly {
     fn sub_assign(&mut self, p: &Poly) {
+        self.invalidate_cache();
         assert!(!self.has_lazy_coefficients && !p.has_lazy_coefficients);
         assert_ne!(
             self.representation,
@@ -78,7 +79,7 @@ impl SubAssign<&Poly> for Poly {
         );
         debug_assert_eq!(self.ctx, p.ctx, "Incompatible contexts");
         self.allow_variable_time_computations |= p.allow_variable_time_computations;
-        if self.allow_variable_time_computations {
+        if self.variable_time() {
             izip!(
                 self.coefficients.outer_iter_mut(),
                 p.coefficients.outer_iter(),
@@ -111,6 +112,7 @@ impl Sub<&Poly> for &Poly {
 
 impl MulAssign<&Poly> for Poly {
     fn mul_assign(&mut self, p: &Poly) {
+        self.invalidate_cache();
         assert!(!p.has_lazy_coefficients);
         assert_ne!(
             self.representation,
@@ -132,9 +134,15 @@ impl MulAssign<&Poly> for Poly {
         debug_assert_eq!(self.ctx, p.ctx, "Incompatible contexts");
         self.allow_variable_time_computations |= p.allow_variable_time_computations;
 
+        if self.ctx.has_convolutions() && p.representation != Representation::PowerBasis {
+            mul_with_convolutions(&self.ctx, &mut self.coefficients, &p.coefficients);
+            self.has_lazy_coefficients = false;
+            return;
+        }
+
         match p.representation {
             Representation::Ntt => {
-                if self.allow_variable_time_computations {
+                if self.variable_time() {
                     unsafe {
                         izip!(
                             self.coefficients.outer_iter_mut(),
@@ -157,7 +165,7 @@ impl MulAssign<&Poly> for Poly {
                 }
             }
             Representation::NttShoup => {
-                if self.allow_variable_time_computations {
+                if self.variable_time() {
                     izip!(
                         self.coefficients.outer_iter_mut(),
                         p.coefficients.outer_iter(),
@@ -197,16 +205,19 @@ impl MulAssign<&Poly> for Poly {
 
 impl MulAssign<&BigUint> for Poly {
     fn mul_assign(&mut self, p: &BigUint) {
+        self.invalidate_cache();
         let v: Vec<BigUint> = vec![p.clone()];
         let mut q = Poly::try_convert_from(
             v.as_ref() as &[BigUint],
             &self.ctx,
-            self.allow_variable_time_computations,
+            self.variable_time(),
             self.representation.clone(),
         )
         .unwrap();
         q.change_representation(Representation::Ntt);
-        if self.allow_variable_time_computations {
+        if self.ctx.has_convolutions() {
+            mul_with_convolutions(&self.ctx, &mut self.coefficients, &q.coefficients);
+        } else if self.variable_time() {
             unsafe {
                 izip!(
                     self.coefficients.outer_iter_mut(),
@@ -238,12 +249,6 @@ impl Mul<&Poly> for &Poly {
                 // TODO: To test, and do the same thing for add, sub, and neg
                 let mut q = p.clone();
                 if q.representation == Representation::NttShoup {
-                    q.coefficients_shoup
-                        .as_mut()
-                        .unwrap()
-                        .as_slice_mut()
-                        .unwrap()
-                        .zeroize();
                     unsafe { q.override_representation(Representation::Ntt) }
                 }
                 q *= self;
@@ -258,6 +263,38 @@ impl Mul<&Poly> for &Poly {
     }
 }
 
+impl MulAssign<u64> for Poly {
+    fn mul_assign(&mut self, p: u64) {
+        self.invalidate_cache();
+        assert!(!self.has_lazy_coefficients);
+        assert_ne!(
+            self.representation,
+            Representation::NttShoup,
+            "Cannot multiply to a polynomial in NttShoup representation"
+        );
+        if self.variable_time() {
+            izip!(self.coefficients.outer_iter_mut(), self.ctx.q.iter()).for_each(
+                |(mut v1, qi)| unsafe {
+                    qi.scalar_mul_vec_vt(v1.as_slice_mut().unwrap(), qi.reduce(p))
+                },
+            );
+        } else {
+            izip!(self.coefficients.outer_iter_mut(), self.ctx.q.iter()).for_each(
+                |(mut v1, qi)| qi.scalar_mul_vec(v1.as_slice_mut().unwrap(), qi.reduce(p)),
+            );
+        }
+    }
+}
+
+impl Mul<u64> for &Poly {
+    type Output = Poly;
+    fn mul(self, p: u64) -> Poly {
+        let mut q = self.clone();
+        q *= p;
+        q
+    }
+}
+
 impl Mul<&BigUint> for &Poly {
     type Output = Poly;
     fn mul(self, p: &BigUint) -> Poly {
@@ -280,7 +317,8 @@ impl Neg for &Poly {
     fn neg(self) -> Poly {
         assert!(!self.has_lazy_coefficients);
         let mut out = self.clone();
-        if self.allow_variable_time_computations {
+        out.invalidate_cache();
+        if self.variable_time() {
             izip!(out.coefficients.outer_iter_mut(), out.ctx.q.iter())
                 .for_each(|(mut v1, qi)| unsafe { qi.neg_vec_vt(v1.as_slice_mut().unwrap()) });
         } else {
@@ -296,7 +334,8 @@ impl Neg for Poly {
 
     fn neg(mut self) -> Poly {
         assert!(!self.has_lazy_coefficients);
-        if self.allow_variable_time_computations {
+        self.invalidate_cache();
+        if self.variable_time() {
             izip!(self.coefficients.outer_iter_mut(), self.ctx.q.iter())
                 .for_each(|(mut v1, qi)| unsafe { qi.neg_vec_vt(v1.as_slice_mut().unwrap()) });
         } else {
@@ -307,6 +346,152 @@ impl Neg for Poly {
     }
 }
 
+impl Poly {
+    /// Computes `out = a + b`.
+    ///
+    /// The allocation of `out` is reused when it has the shape of `a`, so that
+    /// no memory is allocated when `out` is a workspace polynomial.
+    pub fn add_into(a: &Poly, b: &Poly, out: &mut Poly) {
+        out.clone_from(a);
+        *out += b;
+    }
+
+    /// Computes `out = a - b`.
+    ///
+    /// The allocation of `out` is reused when it has the shape of `a`.
+    pub fn sub_into(a: &Poly, b: &Poly, out: &mut Poly) {
+        out.clone_from(a);
+        *out -= b;
+    }
+
+    /// Computes `out = a * b`, with the same requirements on the
+    /// representations as the multiplication of `a` by `b`.
+    ///
+    /// The allocation of `out` is reused when it has the shape of `a`.
+    pub fn mul_into(a: &Poly, b: &Poly, out: &mut Poly) {
+        let (a, b) = if a.representation == Representation::NttShoup {
+            (b, a)
+        } else {
+            (a, b)
+        };
+        out.clone_from(a);
+        if out.representation == Representation::NttShoup {
+            unsafe { out.override_representation(Representation::Ntt) }
+        }
+        *out *= b;
+    }
+
+    /// Computes `self += a * b` in place, without intermediate polynomial.
+    ///
+    /// The polynomial `self` must be in Ntt representation. When one of `a`
+    /// and `b` is in NttShoup representation, the other one may have lazy
+    /// coefficients; otherwise, both must be in Ntt representation.
+    pub fn add_assign_product(&mut self, a: &Poly, b: &Poly) {
+        self.invalidate_cache();
+        assert!(!self.has_lazy_coefficients);
+        assert_eq!(
+            self.representation,
+            Representation::Ntt,
+            "Can only add a product to a polynomial in Ntt representation"
+        );
+        let (a, b) = if a.representation == Representation::NttShoup {
+            (b, a)
+        } else {
+            (a, b)
+        };
+        assert_ne!(
+            a.representation,
+            Representation::PowerBasis,
+            "Multiplication requires an Ntt representation."
+        );
+        assert!(!b.has_lazy_coefficients);
+        debug_assert_eq!(self.ctx, a.ctx, "Incompatible contexts");
+        debug_assert_eq!(self.ctx, b.ctx, "Incompatible contexts");
+        self.allow_variable_time_computations |=
+            a.allow_variable_time_computations | b.allow_variable_time_computations;
+        let variable_time = self.variable_time();
+
+        if self.ctx.has_convolutions() && b.representation != Representation::PowerBasis {
+            *self += &(a * b);
+            return;
+        }
+
+        match b.representation {
+            Representation::Ntt => {
+                assert!(
+                    !a.has_lazy_coefficients,
+                    "Can only multiply a polynomial with lazy coefficients by an NttShoup representation."
+                );
+                izip!(
+                    self.coefficients.outer_iter_mut(),
+                    a.coefficients.outer_iter(),
+                    b.coefficients.outer_iter(),
+                    self.ctx.q.iter()
+                )
+                .for_each(|(mut v, va, vb, qi)| {
+                    if variable_time {
+                        izip!(v.iter_mut(), va.iter(), vb.iter()).for_each(|(vj, aj, bj)| unsafe {
+                            *vj = qi.add_vt(*vj, qi.mul_vt(*aj, *bj))
+                        })
+                    } else {
+                        izip!(v.iter_mut(), va.iter(), vb.iter())
+                            .for_each(|(vj, aj, bj)| *vj = qi.add(*vj, qi.mul(*aj, *bj)))
+                    }
+                });
+            }
+            Representation::NttShoup => {
+                izip!(
+                    self.coefficients.outer_iter_mut(),
+                    a.coefficients.outer_iter(),
+                    b.coefficients.outer_iter(),
+                    b.coefficients_shoup.as_ref().unwrap().outer_iter(),
+                    self.ctx.q.iter()
+                )
+                .for_each(|(mut v, va, vb, vb_shoup, qi)| {
+                    if variable_time {
+                        izip!(v.iter_mut(), va.iter(), vb.iter(), vb_shoup.iter()).for_each(
+                            |(vj, aj, bj, bj_shoup)| unsafe {
+                                *vj = qi.add_vt(*vj, qi.mul_shoup_vt(*aj, *bj, *bj_shoup))
+                            },
+                        )
+                    } else {
+                        izip!(v.iter_mut(), va.iter(), vb.iter(), vb_shoup.iter()).for_each(
+                            |(vj, aj, bj, bj_shoup)| {
+                                *vj = qi.add(*vj, qi.mul_shoup(*aj, *bj, *bj_shoup))
+                            },
+                        )
+                    }
+                });
+            }
+            _ => {
+                panic!("Multiplication requires a multipliand in Ntt or NttShoup representation.")
+            }
+        }
+    }
+}
+
+/// Multiplies in place the coefficients `a` by the coefficients `b` in a
+/// context where some moduli do not support the NTT: the rows of these moduli
+/// are multiplied using their negacyclic convolution, and the other rows
+/// coefficient-wise. The coefficients of `a` may be lazy.
+fn mul_with_convolutions(ctx: &Context, a: &mut Array2<u64>, b: &Array2<u64>) {
+    izip!(
+        a.outer_iter_mut(),
+        b.outer_iter(),
+        ctx.q.iter(),
+        ctx.convolutions.iter()
+    )
+    .for_each(|(mut ai, bi, qi, convolution)| {
+        let ai = ai.as_slice_mut().unwrap();
+        if let Some(convolution) = convolution {
+            convolution.mul(ai, bi.as_slice().unwrap())
+        } else {
+            qi.reduce_vec(ai);
+            qi.mul_vec(ai, bi.as_slice().unwrap())
+        }
+    })
+}
+
 /// Computes the Fused-Mul-Add operation `out[i] += x[i] * y[i]`
 unsafe fn fma(out: &mut [u128], x: &[u64], y: &[u64]) {
     let n = out.len();
@@ -345,27 +530,48 @@ unsafe fn fma(out: &mut [u128], x: &[u64], y: &[u64]) {
     }
 }
 
-/// Compute the dot product between two iterators of polynomials.
-/// Returna an error if the iterator counts are 0, or if any of the polynomial
-/// is not in Ntt or NttShoup representation.
+/// Compute the dot product between two iterators of polynomials, in a single
+/// pass over the coefficients: the products are accumulated in 128 bits and
+/// only reduced when the accumulator could overflow.
+/// Returna an error if the iterator counts are 0, if the polynomials do not
+/// share the same context, if any of the polynomial is not in Ntt or NttShoup
+/// representation, or if both polynomials of a product have lazy
+/// coefficients.
 pub fn dot_product<'a, 'b, I, J>(p: I, q: J) -> Result<Poly>
 where
     I: Iterator<Item = &'a Poly> + Clone,
     J: Iterator<Item = &'b Poly> + Clone,
 {
-    debug_assert!(!p
-        .clone()
-        .any(|pi| pi.representation == Representation::PowerBasis));
-    debug_assert!(!q
-        .clone()
-        .any(|qi| qi.representation == Representation::PowerBasis));
-
     let count = min(p.clone().count(), q.clone().count());
     if count == 0 {
-        return Err(Error::Default("At least one iterator is empty".to_string()));
+        return Err(Error::EmptyInput);
     }
 
     let p_first = p.clone().next().unwrap();
+    let mut has_lazy_coefficients = false;
+    for (pi, qi) in izip!(p.clone(), q.clone()) {
+        if pi.ctx != p_first.ctx || qi.ctx != p_first.ctx {
+            return Err(Error::InvalidContext);
+        }
+        if pi.representation == Representation::PowerBasis
+            || qi.representation == Representation::PowerBasis
+        {
+            return Err(Error::IncorrectRepresentation(
+                Representation::PowerBasis,
+                Representation::Ntt,
+            ));
+        }
+        if pi.has_lazy_coefficients && qi.has_lazy_coefficients {
+            return Err(Error::LazyCoefficients);
+        }
+        has_lazy_coefficients |= pi.has_lazy_coefficients || qi.has_lazy_coefficients;
+    }
+
+    if p_first.ctx.has_convolutions() {
+        let mut out = Poly::zero(&p_first.ctx, Representation::Ntt);
+        izip!(p, q).for_each(|(pi, qi)| out.add_assign_product(pi, qi));
+        return Ok(out);
+    }
 
     // Initialize the accumulator
     let mut acc: Array2<u128> = Array2::zeros((p_first.ctx.q.len(), p_first.ctx.degree));
@@ -375,12 +581,14 @@ where
     let mut num_acc = vec![1u128; p_first.ctx.q.len()];
     let num_acc_ptr = num_acc.as_mut_ptr();
 
-    // Maximum number of products that can be accumulated
+    // Maximum number of products that can be accumulated; lazy coefficients
+    // are up to 4 times the modulus.
+    let lazy_bits = if has_lazy_coefficients { 2 } else { 0 };
     let max_acc = p_first
         .ctx
         .q
         .iter()
-        .map(|qi| 1u128 << (2 * qi.modulus().leading_zeros()))
+        .map(|qi| 1u128 << (2 * qi.modulus().leading_zeros() - lazy_bits))
         .collect_vec();
     let max_acc_ptr = max_acc.as_ptr();
 
@@ -403,7 +611,7 @@ where
                     let qj = &*q_ptr.offset(j);
                     *num_acc_ptr.offset(j) += 1;
                     if *num_acc_ptr.offset(j) == *max_acc_ptr.offset(j) {
-                        if p_first.allow_variable_time_computations {
+                        if p_first.variable_time() {
                             for i in j * degree..(j + 1) * degree {
                                 *acc_ptr.offset(i) = qj.reduce_u128_vt(*acc_ptr.offset(i)) as u128;
                             }
@@ -436,7 +644,7 @@ where
         p_first.ctx.q.iter()
     )
     .for_each(|(mut coeffsj, accj, m)| {
-        if p_first.allow_variable_time_computations {
+        if p_first.variable_time() {
             izip!(coeffsj.iter_mut(), accj.iter())
                 .for_each(|(cj, accjk)| *cj = unsafe { m.reduce_u128_vt(*accjk) });
         } else {
@@ -451,18 +659,36 @@ where
         allow_variable_time_computations: p_first.allow_variable_time_computations,
         coefficients: coeffs,
         coefficients_shoup: None,
+        cache: None,
         has_lazy_coefficients: false,
     })
 }
 
+/// Computes `sum_i a_i * b_i` for the pairs of polynomials `(a_i, b_i)` of
+/// `products` with [`dot_product`].
+pub fn sum_of_products<'a, I>(products: I) -> Result<Poly>
+where
+    I: IntoIterator<Item = (&'a Poly, &'a Poly)>,
+{
+    let (a, b): (Vec<_>, Vec<_>) = products.into_iter().unzip();
+    dot_product(a.into_iter(), b.into_iter())
+}
+
+/// Computes `a * b + c * d` with [`dot_product`], with a single reduction per
+/// coefficient.
+pub fn mul_add_mul(a: &Poly, b: &Poly, c: &Poly, d: &Poly) -> Result<Poly> {
+    dot_product([a, c].into_iter(), [b, d].into_iter())
+}
+
 #[cfg(test)]
 mod tests {
     use itertools::{izip, Itertools};
-    use rand::thread_rng;
+    use ndarray::Array2;
+    use rand::{thread_rng, RngCore};
 
-    use super::dot_product;
+    use super::{dot_product, mul_add_mul, sum_of_products};
     use crate::{
-        rq::{Context, Poly, Representation},
+        rq::{traits::TryConvertFrom, Context, Poly, Representation},
         zq::Modulus,
     };
     use std::{error::Error, sync::Arc};
@@ -618,6 +844,126 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn into() -> Result<(), Box<dyn Error>> {
+        let mut rng = thread_rng();
+        let ctx = Arc::new(Context::new(MODULI, 16)?);
+        let mut out = Poly::zero(&ctx, Representation::Ntt);
+        let ptr = out.coefficients().as_ptr();
+        for _ in 0..100 {
+            for variable_time in [false, true] {
+                let mut p = Poly::random(&ctx, Representation::Ntt, &mut rng);
+                let q = Poly::random(&ctx, Representation::Ntt, &mut rng);
+                let q_shoup = Poly::random(&ctx, Representation::NttShoup, &mut rng);
+                if variable_time {
+                    unsafe { p.allow_variable_time_computations() }
+                }
+
+                Poly::add_into(&p, &q, &mut out);
+                assert_eq!(out, &p + &q);
+                Poly::sub_into(&p, &q, &mut out);
+                assert_eq!(out, &p - &q);
+                Poly::mul_into(&p, &q, &mut out);
+                assert_eq!(out, &p * &q);
+                Poly::mul_into(&p, &q_shoup, &mut out);
+                assert_eq!(out, &p * &q_shoup);
+                Poly::mul_into(&q_shoup, &p, &mut out);
+                assert_eq!(out, &q_shoup * &p);
+
+                let mut r = q.clone();
+                r.add_assign_product(&p, &q);
+                assert_eq!(r, &q + &(&p * &q));
+                let mut r = q.clone();
+                r.add_assign_product(&q_shoup, &p);
+                assert_eq!(r, &q + &(&p * &q_shoup));
+
+                // The allocation of the output is reused.
+                assert_eq!(out.coefficients().as_ptr(), ptr);
+            }
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn mul_without_ntt() -> Result<(), Box<dyn Error>> {
+        let mut rng = thread_rng();
+        let n = 128;
+        // 1153 does not support the NTT of size 128.
+        let ctx = Arc::new(Context::new(&[1153, 4611686018326724609], n)?);
+        assert!(ctx.has_convolutions());
+        for _ in 0..10 {
+            let p = Poly::random(&ctx, Representation::PowerBasis, &mut rng);
+            let q = Poly::random(&ctx, Representation::PowerBasis, &mut rng);
+
+            // Schoolbook multiplication modulo x^n + 1.
+            let mut expected = Array2::zeros((2, n));
+            for (mut e, pi, qi, m) in izip!(
+                expected.outer_iter_mut(),
+                p.coefficients().outer_iter(),
+                q.coefficients().outer_iter(),
+                ctx.q.iter()
+            ) {
+                for i in 0..n {
+                    for j in 0..n {
+                        let c = m.mul(pi[i], qi[j]);
+                        if i + j < n {
+                            e[i + j] = m.add(e[i + j], c);
+                        } else {
+                            e[i + j - n] = m.sub(e[i + j - n], c);
+                        }
+                    }
+                }
+            }
+            let expected =
+                Poly::try_convert_from(expected, &ctx, false, Representation::PowerBasis)?;
+
+            let mut p_ntt = p.clone();
+            p_ntt.change_representation(Representation::Ntt);
+            let mut q_ntt = q.clone();
+            q_ntt.change_representation(Representation::Ntt);
+            let mut q_ntt_shoup = q.clone();
+            q_ntt_shoup.change_representation(Representation::NttShoup);
+
+            let mut r = &p_ntt * &q_ntt;
+            r.change_representation(Representation::PowerBasis);
+            assert_eq!(r, expected);
+
+            let mut r = &p_ntt * &q_ntt_shoup;
+            r.change_representation(Representation::PowerBasis);
+            assert_eq!(r, expected);
+
+            let mut r = dot_product([&p_ntt, &p_ntt].into_iter(), [&q_ntt, &q_ntt].into_iter())?;
+            r.change_representation(Representation::PowerBasis);
+            assert_eq!(r, &expected + &expected);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn mul_scalar() -> Result<(), Box<dyn Error>> {
+        let mut rng = thread_rng();
+        for _ in 0..100 {
+            let ctx = Arc::new(Context::new(MODULI, 16)?);
+            for representation in [Representation::PowerBasis, Representation::Ntt] {
+                let p = Poly::random(&ctx, representation.clone(), &mut rng);
+                let s = rng.next_u64();
+                let mut a = Vec::<u64>::from(&p);
+                for i in 0..MODULI.len() {
+                    let m = Modulus::new(MODULI[i]).unwrap();
+                    m.scalar_mul_vec(&mut a[i * 16..(i + 1) * 16], m.reduce(s))
+                }
+                let r = &p * s;
+                assert_eq!(r.representation, representation);
+                assert_eq!(Vec::<u64>::from(&r), a);
+
+                let mut r = p.clone();
+                r *= s;
+                assert_eq!(Vec::<u64>::from(&r), a);
+            }
+        }
+        Ok(())
+    }
+
     #[test]
     fn neg() -> Result<(), Box<dyn Error>> {
         let mut rng = thread_rng();
@@ -696,6 +1042,79 @@ mod tests {
                 assert_eq!(r, expected);
             }
         }
+
+        // Lazy coefficients are accepted on one side of the products.
+        let ctx = Arc::new(Context::new(MODULI, 16)?);
+        for len in [1, 3, 4, 5, 17] {
+            let p = (0..len)
+                .map(|_| unsafe {
+                    let coefficients = (0..16).map(|_| rng.next_u64() >> 2).collect_vec();
+                    Poly::create_constant_ntt_polynomial_with_lazy_coefficients_and_variable_time(
+                        &coefficients,
+                        &ctx,
+                    )
+                })
+                .collect_vec();
+            let q = (0..len)
+                .map(|_| Poly::random(&ctx, Representation::NttShoup, &mut rng))
+                .collect_vec();
+            let r = dot_product(p.iter(), q.iter())?;
+
+            let mut expected = Poly::zero(&ctx, Representation::Ntt);
+            izip!(&p, &q).for_each(|(pi, qi)| expected.add_assign_product(pi, qi));
+            assert_eq!(r.coefficients(), expected.coefficients());
+            assert_eq!(
+                dot_product(p.iter(), p.iter()).unwrap_err(),
+                crate::Error::LazyCoefficients
+            );
+        }
+        Ok(())
+    }
+    #[test]
+    fn fused_products() -> Result<(), Box<dyn Error>> {
+        let mut rng = thread_rng();
+        for moduli in [&MODULI[..1], MODULI, &[1009]] {
+            let ctx = Arc::new(Context::new(moduli, 16)?);
+            for len in [1, 2, 17, 100] {
+                let p = (0..len)
+                    .map(|_| Poly::random(&ctx, Representation::Ntt, &mut rng))
+                    .collect_vec();
+                let q = (0..len)
+                    .map(|_| Poly::random(&ctx, Representation::NttShoup, &mut rng))
+                    .collect_vec();
+                let r = sum_of_products(izip!(&p, &q))?;
+
+                let mut expected = Poly::zero(&ctx, Representation::Ntt);
+                izip!(&p, &q).for_each(|(pi, qi)| expected += &(pi * qi));
+                assert_eq!(r, expected);
+            }
+
+            let [a, b, c, d] = [(); 4].map(|_| Poly::random(&ctx, Representation::Ntt, &mut rng));
+            assert_eq!(mul_add_mul(&a, &b, &c, &d)?, &(&a * &b) + &(&c * &d));
+
+            let mut e = a.clone();
+            e.change_representation(Representation::PowerBasis);
+            assert_eq!(
+                mul_add_mul(&a, &b, &c, &e).unwrap_err(),
+                crate::Error::IncorrectRepresentation(
+                    Representation::PowerBasis,
+                    Representation::Ntt
+                )
+            );
+        }
+
+        let ctx = Arc::new(Context::new(MODULI, 16)?);
+        let other_ctx = Arc::new(Context::new(&MODULI[..1], 16)?);
+        let a = Poly::random(&ctx, Representation::Ntt, &mut rng);
+        let b = Poly::random(&other_ctx, Representation::Ntt, &mut rng);
+        assert_eq!(
+            sum_of_products([(&a, &a), (&b, &b)]).unwrap_err(),
+            crate::Error::InvalidContext
+        );
+        assert_eq!(
+            sum_of_products(std::iter::empty()).unwrap_err(),
+            crate::Error::EmptyInput
+        );
         Ok(())
     }
 }
@@ -0,0 +1,172 @@
+#![warn(missing_docs, unused_imports)]
+
+//! Pool of polynomial buffers.
+
+use super::{Context, Poly, Representation};
+use ndarray::Array2;
+use std::{collections::HashMap, sync::Arc, sync::Mutex};
+use zeroize::Zeroize;
+
+/// Default maximum number of buffers kept for each shape.
+const DEFAULT_MAX_BUFFERS_PER_SHAPE: usize = 64;
+
+/// Buffers indexed by their shape.
+type Buffers = HashMap<(usize, usize), Vec<Array2<u64>>>;
+
+/// Pool recycling the coefficient buffers of polynomials.
+///
+/// Buffers are grouped by shape (number of moduli × degree), so that a
+/// polynomial returned to the pool with [`PolyPool::recycle`] provides the
+/// storage of the next polynomial of the same shape created with
+/// [`PolyPool::zero`]. Recycled buffers are zeroized before being stored.
+///
+/// Pools are independent from each other: using one pool per thread (or per
+/// NUMA node) keeps the buffers close to the memory where they were first
+/// allocated.
+#[derive(Debug)]
+pub struct PolyPool {
+    buffers: Mutex<Buffers>,
+    max_buffers_per_shape: usize,
+}
+
+impl Default for PolyPool {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PolyPool {
+    /// Creates an empty pool.
+    pub fn new() -> Self {
+        Self::with_max_buffers_per_shape(DEFAULT_MAX_BUFFERS_PER_SHAPE)
+    }
+
+    /// Creates an empty pool which keeps at most `max_buffers_per_shape`
+    /// buffers of each shape; additional recycled buffers are freed.
+    pub fn with_max_buffers_per_shape(max_buffers_per_shape: usize) -> Self {
+        Self {
+            buffers: Mutex::new(HashMap::new()),
+            max_buffers_per_shape,
+        }
+    }
+
+    /// Creates a polynomial with all coefficients equal to 0, using buffers
+    /// from the pool when available.
+    pub fn zero(&self, ctx: &Arc<Context>, representation: Representation) -> Poly {
+        let shape = (ctx.q.len(), ctx.degree);
+        let coefficients_shoup = if representation == Representation::NttShoup {
+            Some(Arc::new(self.take(shape)))
+        } else {
+            None
+        };
+        Poly {
+            ctx: ctx.clone(),
+            representation,
+            has_lazy_coefficients: false,
+            allow_variable_time_computations: false,
+            coefficients: self.take(shape),
+            coefficients_shoup,
+            cache: None,
+        }
+    }
+
+    /// Returns the buffers of a polynomial to the pool.
+    pub fn recycle(&self, p: Poly) {
+        let Poly {
+            coefficients,
+            coefficients_shoup,
+            ..
+        } = p;
+        self.store(coefficients);
+        if let Some(coefficients_shoup) = coefficients_shoup.and_then(|s| Arc::try_unwrap(s).ok()) {
+            self.store(coefficients_shoup);
+        }
+    }
+
+    /// Returns the number of buffers held by the pool.
+    pub fn len(&self) -> usize {
+        self.buffers.lock().unwrap().values().map(Vec::len).sum()
+    }
+
+    /// Returns whether the pool holds no buffer.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Frees all the buffers held by the pool.
+    pub fn clear(&self) {
+        self.buffers.lock().unwrap().clear()
+    }
+
+    fn take(&self, shape: (usize, usize)) -> Array2<u64> {
+        self.buffers
+            .lock()
+            .unwrap()
+            .get_mut(&shape)
+            .and_then(Vec::pop)
+            .unwrap_or_else(|| Array2::zeros(shape))
+    }
+
+    fn store(&self, mut buffer: Array2<u64>) {
+        if buffer.is_empty() || !buffer.is_standard_layout() {
+            return;
+        }
+        buffer.as_slice_mut().unwrap().zeroize();
+        let mut buffers = self.buffers.lock().unwrap();
+        let shape_buffers = buffers.entry(buffer.dim()).or_default();
+        if shape_buffers.len() < self.max_buffers_per_shape {
+            shape_buffers.push(buffer)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::PolyPool;
+    use crate::rq::{Context, Poly, Representation};
+    use rand::thread_rng;
+    use std::{error::Error, sync::Arc};
+
+    #[test]
+    fn recycle() -> Result<(), Box<dyn Error>> {
+        let mut rng = thread_rng();
+        let ctx = Arc::new(Context::new(
+            &[4611686018326724609, 4611686018309947393],
+            16,
+        )?);
+        let other_ctx = Arc::new(Context::new(&[4611686018326724609], 16)?);
+        let pool = PolyPool::with_max_buffers_per_shape(2);
+        assert!(pool.is_empty());
+
+        let p = Poly::random(&ctx, Representation::Ntt, &mut rng);
+        let ptr = p.coefficients().as_ptr();
+        pool.recycle(p);
+        assert_eq!(pool.len(), 1);
+
+        // A polynomial of another shape does not use the buffer.
+        let q = pool.zero(&other_ctx, Representation::PowerBasis);
+        assert_eq!(q, Poly::zero(&other_ctx, Representation::PowerBasis));
+        assert_eq!(pool.len(), 1);
+
+        // The buffer is reused, and zeroed.
+        let q = pool.zero(&ctx, Representation::PowerBasis);
+        assert_eq!(q.coefficients().as_ptr(), ptr);
+        assert_eq!(q, Poly::zero(&ctx, Representation::PowerBasis));
+        assert!(pool.is_empty());
+
+        // Polynomials in NttShoup representation use two buffers.
+        pool.recycle(q);
+        pool.recycle(Poly::random(&ctx, Representation::NttShoup, &mut rng));
+        assert_eq!(pool.len(), 2);
+        let q = pool.zero(&ctx, Representation::NttShoup);
+        assert_eq!(q, Poly::zero(&ctx, Representation::NttShoup));
+        assert!(pool.is_empty());
+
+        pool.recycle(q);
+        pool.recycle(Poly::zero(&ctx, Representation::Ntt));
+        assert_eq!(pool.len(), 2);
+        pool.clear();
+        assert!(pool.is_empty());
+        Ok(())
+    }
+}
@@ -4,6 +4,7 @@
 
 use super::{Context, Poly, Representation};
 use crate::{
+    ntt::{backward_batch, forward_batch, NttKernel},
     rns::{RnsScaler, ScalingFactor},
     Error, Result,
 };
@@ -24,7 +25,7 @@ impl Scaler {
     /// Create a scaler from a context `from` to a context `to`.
     pub fn new(from: &Arc<Context>, to: &Arc<Context>, factor: ScalingFactor) -> Result<Self> {
         if from.degree != to.degree {
-            return Err(Error::Default("Incompatible degrees".to_string()));
+            return Err(Error::IncompatibleDegrees(from.degree, to.degree));
         }
 
         let mut number_common_moduli = 0;
@@ -50,17 +51,31 @@ impl Scaler {
 
     /// Scale a polynomial
     pub(crate) fn scale(&self, p: &Poly) -> Result<Poly> {
+        let mut out = Poly::default();
+        self.scale_into(p, &mut out, &mut Array2::default((0, 0)))?;
+        Ok(out)
+    }
+
+    /// Scale a polynomial into `out`, reusing its allocation when it already
+    /// has the shape of the output context. The `buffer` holds the PowerBasis
+    /// coefficients of polynomials in Ntt representation.
+    pub(crate) fn scale_into(
+        &self,
+        p: &Poly,
+        out: &mut Poly,
+        buffer: &mut Array2<u64>,
+    ) -> Result<()> {
         if p.ctx.as_ref() != self.from.as_ref() {
-            Err(Error::Default(
-                "The input polynomial does not have the correct context".to_string(),
-            ))
+            Err(Error::InvalidContext)
         } else {
             let mut representation = p.representation.clone();
             if representation == Representation::NttShoup {
                 representation = Representation::Ntt;
             }
 
-            let mut new_coefficients = Array2::<u64>::zeros((self.to.q.len(), self.to.degree));
+            out.prepare_for(&self.to, representation.clone());
+            out.allow_variable_time_computations = p.allow_variable_time_computations;
+            let new_coefficients = &mut out.coefficients;
 
             if self.number_common_moduli > 0 {
                 new_coefficients
@@ -81,55 +96,36 @@ impl Scaler {
                             .scale(column, new_column, self.number_common_moduli)
                     });
                 } else if self.number_common_moduli < self.to.q.len() {
-                    let mut p_coefficients_powerbasis = p.coefficients.clone();
+                    buffer.clone_from(&p.coefficients);
                     // Backward NTT
-                    if p.allow_variable_time_computations {
-                        izip!(p_coefficients_powerbasis.outer_iter_mut(), p.ctx.ops.iter())
-                            .for_each(|(mut v, op)| unsafe { op.backward_vt(v.as_mut_ptr()) });
-                    } else {
-                        izip!(p_coefficients_powerbasis.outer_iter_mut(), p.ctx.ops.iter())
-                            .for_each(|(mut v, op)| op.backward(v.as_slice_mut().unwrap()));
-                    }
+                    backward_batch(
+                        &p.ctx.ops,
+                        buffer.view_mut(),
+                        p.variable_time(),
+                        NttKernel::Sequential,
+                    );
                     // Conversion
                     izip!(
                         new_coefficients
                             .slice_mut(s![self.number_common_moduli.., ..])
                             .axis_iter_mut(Axis(1)),
-                        p_coefficients_powerbasis.axis_iter(Axis(1))
+                        buffer.axis_iter(Axis(1))
                     )
                     .for_each(|(new_column, column)| {
                         self.scaler
                             .scale(column, new_column, self.number_common_moduli)
                     });
                     // Forward NTT on the second half
-                    if p.allow_variable_time_computations {
-                        izip!(
-                            new_coefficients
-                                .slice_mut(s![self.number_common_moduli.., ..])
-                                .outer_iter_mut(),
-                            &self.to.ops[self.number_common_moduli..]
-                        )
-                        .for_each(|(mut v, op)| unsafe { op.forward_vt(v.as_mut_ptr()) });
-                    } else {
-                        izip!(
-                            new_coefficients
-                                .slice_mut(s![self.number_common_moduli.., ..])
-                                .outer_iter_mut(),
-                            &self.to.ops[self.number_common_moduli..]
-                        )
-                        .for_each(|(mut v, op)| op.forward(v.as_slice_mut().unwrap()));
-                    }
+                    forward_batch(
+                        &self.to.ops[self.number_common_moduli..],
+                        new_coefficients.slice_mut(s![self.number_common_moduli.., ..]),
+                        p.variable_time(),
+                        NttKernel::Sequential,
+                    );
                 }
             }
 
-            Ok(Poly {
-                ctx: self.to.clone(),
-                representation,
-                allow_variable_time_computations: p.allow_variable_time_computations,
-                coefficients: new_coefficients,
-                coefficients_shoup: None,
-                has_lazy_coefficients: false,
-            })
+            Ok(())
         }
     }
 }
@@ -139,6 +135,7 @@ mod tests {
     use super::{Scaler, ScalingFactor};
     use crate::rq::{Context, Poly, Representation};
     use itertools::Itertools;
+    use ndarray::Array2;
     use num_bigint::BigUint;
     use num_traits::{One, Zero};
     use rand::thread_rng;
@@ -163,6 +160,8 @@ mod tests {
         let ntests = 100;
         let from = Arc::new(Context::new(Q, 16)?);
         let to = Arc::new(Context::new(P, 16)?);
+        let mut out = Poly::default();
+        let mut buffer = Array2::default((0, 0));
 
         for numerator in &[1u64, 2, 3, 100, 1000, 4611686018326724610] {
             for denominator in &[1u64, 2, 3, 4, 100, 101, 1000, 1001, 4611686018326724610] {
@@ -201,6 +200,8 @@ mod tests {
 
                     poly.change_representation(Representation::Ntt);
                     let mut scaled_poly = scaler.scale(&poly)?;
+                    scaler.scale_into(&poly, &mut out, &mut buffer)?;
+                    assert_eq!(out, scaled_poly);
                     scaled_poly.change_representation(Representation::PowerBasis);
                     let scaled_biguint = Vec::<BigUint>::from(&scaled_poly);
                     assert_eq!(expected, scaled_biguint);
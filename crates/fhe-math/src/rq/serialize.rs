@@ -18,7 +18,7 @@ impl DeserializeWithContext for Poly {
     type Context = Context;
 
     fn from_bytes(bytes: &[u8], ctx: &Arc<Context>) -> Result<Self, Self::Error> {
-        let rq: Rq = Message::decode(bytes).map_err(|e| Error::Serialization(e.to_string()))?;
+        let rq: Rq = Message::decode(bytes).map_err(Error::Decoding)?;
         Poly::try_convert_from(&rq, ctx, false, None)
     }
 }
@@ -28,8 +28,10 @@ mod tests {
     use std::{error::Error, sync::Arc};
 
     use fhe_traits::{DeserializeWithContext, Serialize};
+    use prost::Message;
     use rand::thread_rng;
 
+    use crate::proto::{rq::Rq, FORMAT_VERSION};
     use crate::rq::{Context, Poly, Representation};
 
     const Q: &[u64; 3] = &[
@@ -62,4 +64,29 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn versions() -> Result<(), Box<dyn Error>> {
+        let mut rng = thread_rng();
+        let ctx = Arc::new(Context::new(Q, 16)?);
+        let p = Poly::random(&ctx, Representation::Ntt, &mut rng);
+
+        // The messages of older releases have no version field.
+        let mut rq = Rq::from(&p);
+        assert_eq!(rq.version, FORMAT_VERSION);
+        rq.version = 0;
+        assert_eq!(p, Poly::from_bytes(&rq.encode_to_vec(), &ctx)?);
+
+        // The fields added by newer releases are ignored.
+        let mut bytes = p.to_bytes();
+        bytes.extend_from_slice(&[0xa0, 0x06, 0x07]);
+        assert_eq!(p, Poly::from_bytes(&bytes, &ctx)?);
+
+        rq.version = FORMAT_VERSION + 1;
+        assert_eq!(
+            Poly::from_bytes(&rq.encode_to_vec(), &ctx),
+            Err(crate::Error::UnsupportedVersion(FORMAT_VERSION + 1))
+        );
+        Ok(())
+    }
 }
@@ -2,7 +2,14 @@
 
 //! Ring operations for moduli up to 62 bits.
 
+mod modulus128;
+mod montgomery;
 pub mod primes;
+mod simd;
+
+pub use self::modulus128::{Modulus128, MAX_MODULUS128_BITS};
+use self::montgomery::MontgomeryConstants;
+pub use self::montgomery::ReductionBackend;
 
 use crate::errors::{Error, Result};
 use fhe_util::{is_prime, transcode_from_bytes, transcode_to_bytes};
@@ -11,6 +18,14 @@ use num_bigint::BigUint;
 use num_traits::cast::ToPrimitive;
 use rand::{distributions::Uniform, CryptoRng, Rng, RngCore};
 
+/// Maximum number of bits of a modulus.
+///
+/// The lazy reductions keep intermediate values below `4 * p` (in the NTT
+/// butterflies and the modulus switching), and the vectorized additions
+/// compare sums of two reduced values as signed integers: both require
+/// `4 * p < 2^64`, i.e. moduli of at most 62 bits.
+pub const MAX_MODULUS_BITS: usize = 62;
+
 /// cond ? on_true : on_false
 const fn const_time_cond_select(on_true: u64, on_false: u64, cond: bool) -> u64 {
     let mask = -(cond as i64) as u64;
@@ -19,15 +34,24 @@ const fn const_time_cond_select(on_true: u64, on_false: u64, cond: bool) -> u64
 }
 
 /// Structure encapsulating an integer modulus up to 62 bits.
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone)]
 pub struct Modulus {
     pub(crate) p: u64,
-    nbits: usize,
     barrett_hi: u64,
     barrett_lo: u64,
     leading_zeros: u32,
     pub(crate) supports_opt: bool,
     distribution: Uniform<u64>,
+    backend: ReductionBackend,
+    montgomery: MontgomeryConstants,
+}
+
+// The other members are derived from the value of the modulus and from the
+// reduction backend.
+impl PartialEq for Modulus {
+    fn eq(&self, other: &Self) -> bool {
+        self.p == other.p && self.backend == other.backend
+    }
 }
 
 // We need to declare Eq manually because of the `Uniform` member.
@@ -36,19 +60,22 @@ impl Eq for Modulus {}
 impl Modulus {
     /// Create a modulus from an integer of at most 62 bits.
     pub fn new(p: u64) -> Result<Self> {
-        if p < 2 || (p >> 62) != 0 {
+        if p < 2 || (p >> MAX_MODULUS_BITS) != 0 {
             Err(Error::InvalidModulus(p))
         } else {
             let barrett = ((BigUint::from(1u64) << 128usize) / p).to_u128().unwrap(); // 2^128 / p
-            Ok(Self {
+            let mut q = Self {
                 p,
-                nbits: 64 - p.leading_zeros() as usize,
                 barrett_hi: (barrett >> 64) as u64,
                 barrett_lo: barrett as u64,
                 leading_zeros: p.leading_zeros(),
                 supports_opt: primes::supports_opt(p),
                 distribution: Uniform::from(0..p),
-            })
+                backend: ReductionBackend::Barrett,
+                montgomery: MontgomeryConstants::default(),
+            };
+            q.montgomery = MontgomeryConstants::new(&q);
+            Ok(q)
         }
     }
 
@@ -106,7 +133,7 @@ impl Modulus {
     /// # Safety
     /// This function is not constant time and its timing may reveal information
     /// about the values being multiplied.
-    const unsafe fn mul_vt(&self, a: u64, b: u64) -> u64 {
+    pub(crate) const unsafe fn mul_vt(&self, a: u64, b: u64) -> u64 {
         debug_assert!(a < self.p && b < self.p);
         Self::reduce1_vt(self.lazy_reduce_u128((a as u128) * (b as u128)), self.p)
     }
@@ -175,7 +202,7 @@ impl Modulus {
     /// # Safety
     /// This function is not constant time and its timing may reveal information
     /// about the values being multiplied.
-    const unsafe fn mul_shoup_vt(&self, a: u64, b: u64, b_shoup: u64) -> u64 {
+    pub(crate) const unsafe fn mul_shoup_vt(&self, a: u64, b: u64, b_shoup: u64) -> u64 {
         Self::reduce1_vt(self.lazy_mul_shoup(a, b, b_shoup), self.p)
     }
 
@@ -202,7 +229,8 @@ impl Modulus {
     pub fn add_vec(&self, a: &mut [u64], b: &[u64]) {
         debug_assert_eq!(a.len(), b.len());
 
-        izip!(a.iter_mut(), b.iter()).for_each(|(ai, bi)| *ai = self.add(*ai, *bi));
+        let done = simd::add_vec(a, b, self.p);
+        izip!(a[done..].iter_mut(), b[done..].iter()).for_each(|(ai, bi)| *ai = self.add(*ai, *bi));
     }
 
     /// Modular addition of vectors in place in variable time.
@@ -216,6 +244,13 @@ impl Modulus {
         let n = a.len();
         debug_assert_eq!(n, b.len());
 
+        let done = simd::add_vec(a, b, self.p);
+        if done > 0 {
+            izip!(a[done..].iter_mut(), b[done..].iter())
+                .for_each(|(ai, bi)| *ai = self.add_vt(*ai, *bi));
+            return;
+        }
+
         let p = self.p;
         macro_rules! add_at {
             ($idx:expr) => {
@@ -255,7 +290,8 @@ impl Modulus {
     pub fn sub_vec(&self, a: &mut [u64], b: &[u64]) {
         debug_assert_eq!(a.len(), b.len());
 
-        izip!(a.iter_mut(), b.iter()).for_each(|(ai, bi)| *ai = self.sub(*ai, *bi));
+        let done = simd::sub_vec(a, b, self.p);
+        izip!(a[done..].iter_mut(), b[done..].iter()).for_each(|(ai, bi)| *ai = self.sub(*ai, *bi));
     }
 
     /// Modular subtraction of vectors in place in variable time.
@@ -269,6 +305,13 @@ impl Modulus {
         let n = a.len();
         debug_assert_eq!(n, b.len());
 
+        let done = simd::sub_vec(a, b, self.p);
+        if done > 0 {
+            izip!(a[done..].iter_mut(), b[done..].iter())
+                .for_each(|(ai, bi)| *ai = self.sub_vt(*ai, *bi));
+            return;
+        }
+
         let p = self.p;
         macro_rules! sub_at {
             ($idx:expr) => {
@@ -369,8 +412,13 @@ impl Modulus {
         debug_assert_eq!(a.len(), b_shoup.len());
         debug_assert_eq!(&b_shoup, &self.shoup_vec(b));
 
-        izip!(a.iter_mut(), b.iter(), b_shoup.iter())
-            .for_each(|(ai, bi, bi_shoup)| *ai = self.mul_shoup(*ai, *bi, *bi_shoup));
+        let done = simd::mul_shoup_vec(a, b, b_shoup, self.p);
+        izip!(
+            a[done..].iter_mut(),
+            b[done..].iter(),
+            b_shoup[done..].iter()
+        )
+        .for_each(|(ai, bi, bi_shoup)| *ai = self.mul_shoup(*ai, *bi, *bi_shoup));
     }
 
     /// Shoup modular multiplication of vectors in place in variable time.
@@ -385,13 +433,31 @@ impl Modulus {
         debug_assert_eq!(a.len(), b_shoup.len());
         debug_assert_eq!(&b_shoup, &self.shoup_vec(b));
 
-        izip!(a.iter_mut(), b.iter(), b_shoup.iter())
-            .for_each(|(ai, bi, bi_shoup)| *ai = self.mul_shoup_vt(*ai, *bi, *bi_shoup));
+        let done = simd::mul_shoup_vec(a, b, b_shoup, self.p);
+        izip!(
+            a[done..].iter_mut(),
+            b[done..].iter(),
+            b_shoup[done..].iter()
+        )
+        .for_each(|(ai, bi, bi_shoup)| *ai = self.mul_shoup_vt(*ai, *bi, *bi_shoup));
     }
 
     /// Reduce a vector in place in constant time.
     pub fn reduce_vec(&self, a: &mut [u64]) {
-        a.iter_mut().for_each(|ai| *ai = self.reduce(*ai));
+        let done = simd::reduce_vec(a, self);
+        a[done..].iter_mut().for_each(|ai| *ai = self.reduce(*ai));
+    }
+
+    /// Center a value modulo p as i64 in constant time.
+    const fn center(&self, a: u64) -> i64 {
+        debug_assert!(a < self.p);
+
+        (a as i64) - (const_time_cond_select(self.p, 0, a >= self.p >> 1) as i64)
+    }
+
+    /// Center a vector in constant time.
+    pub fn center_vec(&self, a: &[u64]) -> Vec<i64> {
+        a.iter().map(|ai| self.center(*ai)).collect_vec()
     }
 
     /// Center a value modulo p as i64 in variable time.
@@ -425,11 +491,14 @@ impl Modulus {
     /// This function is not constant time and its timing may reveal information
     /// about the values being reduced.
     pub unsafe fn reduce_vec_vt(&self, a: &mut [u64]) {
-        a.iter_mut().for_each(|ai| *ai = self.reduce_vt(*ai));
+        let done = simd::reduce_vec(a, self);
+        a[done..]
+            .iter_mut()
+            .for_each(|ai| *ai = self.reduce_vt(*ai));
     }
 
     /// Modular reduction of a i64 in constant time.
-    const fn reduce_i64(&self, a: i64) -> u64 {
+    pub const fn reduce_i64(&self, a: i64) -> u64 {
         self.reduce_u128((((self.p as i128) << 64) + (a as i128)) as u128)
     }
 
@@ -474,7 +543,8 @@ impl Modulus {
     ///
     /// Aborts if any of the values in the vector is >= p in debug mode.
     pub fn neg_vec(&self, a: &mut [u64]) {
-        izip!(a.iter_mut()).for_each(|ai| *ai = self.neg(*ai));
+        let done = simd::neg_vec(a, self.p);
+        izip!(a[done..].iter_mut()).for_each(|ai| *ai = self.neg(*ai));
     }
 
     /// Modular negation of a vector in place in variable time.
@@ -484,7 +554,8 @@ impl Modulus {
     /// This function is not constant time and its timing may reveal information
     /// about the values being negated.
     pub unsafe fn neg_vec_vt(&self, a: &mut [u64]) {
-        izip!(a.iter_mut()).for_each(|ai| *ai = self.neg_vt(*ai));
+        let done = simd::neg_vec(a, self.p);
+        izip!(a[done..].iter_mut()).for_each(|ai| *ai = self.neg_vt(*ai));
     }
 
     /// Modular exponentiation in variable time.
@@ -525,6 +596,70 @@ impl Modulus {
         }
     }
 
+    /// Modular exponentiation of vectors in place in variable time.
+    ///
+    /// Aborts if any of the values of a is >= p or n >= p in debug mode.
+    pub fn pow_vec(&self, a: &mut [u64], n: u64) {
+        a.iter_mut().for_each(|ai| *ai = self.pow(*ai, n))
+    }
+
+    /// Modular inversion of vectors in variable time, with Montgomery's batch
+    /// inversion which costs a single inversion and 3 multiplications per
+    /// value.
+    ///
+    /// Returns None if p is not prime or any of the values of a is 0.
+    /// Aborts if any of the values of a is >= p in debug mode.
+    pub fn inv_vec(&self, a: &[u64]) -> std::option::Option<Vec<u64>> {
+        if a.contains(&0) {
+            return None;
+        }
+
+        // The prefix products a_0 * ... * a_{i - 1}.
+        let mut prefixes = Vec::with_capacity(a.len());
+        let mut product = 1;
+        for ai in a {
+            debug_assert!(*ai < self.p);
+            prefixes.push(product);
+            product = self.mul(product, *ai);
+        }
+
+        let mut inverse = self.inv(product)?;
+        let mut out = vec![0; a.len()];
+        for i in (0..a.len()).rev() {
+            out[i] = self.mul(inverse, prefixes[i]);
+            inverse = self.mul(inverse, a[i]);
+        }
+        Some(out)
+    }
+
+    /// Modular fused multiplication and addition of vectors in place in
+    /// constant time, which computes a + b * c.
+    ///
+    /// Aborts if a, b and c differ in size, and if any of their values is >= p
+    /// in debug mode.
+    pub fn mul_add_vec(&self, a: &mut [u64], b: &[u64], c: &[u64]) {
+        debug_assert_eq!(a.len(), b.len());
+        debug_assert_eq!(a.len(), c.len());
+
+        izip!(a.iter_mut(), b.iter(), c.iter()).for_each(|(ai, bi, ci)| {
+            debug_assert!(*ai < self.p && *bi < self.p && *ci < self.p);
+            *ai = self.reduce_u128((*ai as u128) + (*bi as u128) * (*ci as u128))
+        });
+    }
+
+    /// Modular fused scalar multiplication and addition of vectors in place in
+    /// constant time, which computes a + b * c.
+    ///
+    /// Aborts if a and b differ in size, and if any of their values is >= p in
+    /// debug mode.
+    pub fn scalar_mul_add_vec(&self, a: &mut [u64], b: &[u64], c: u64) {
+        debug_assert_eq!(a.len(), b.len());
+
+        let c_shoup = self.shoup(c);
+        izip!(a.iter_mut(), b.iter())
+            .for_each(|(ai, bi)| *ai = self.add(*ai, self.mul_shoup(*bi, c, c_shoup)));
+    }
+
     /// Modular reduction of a u128 in constant time.
     pub const fn reduce_u128(&self, a: u128) -> u64 {
         Self::reduce1(self.lazy_reduce_u128(a), self.p)
@@ -697,23 +832,22 @@ impl Modulus {
     }
 
     /// Length of the serialization of a vector of size `size`.
-    ///
-    /// Panics if the size is not a multiple of 8.
     pub const fn serialization_length(&self, size: usize) -> usize {
-        assert!(size % 8 == 0);
         let p_nbits = 64 - (self.p - 1).leading_zeros() as usize;
-        p_nbits * size / 8
+        (p_nbits * size).div_ceil(8)
     }
 
-    /// Serialize a vector of elements of length a multiple of 8.
-    ///
-    /// Panics if the length of the vector is not a multiple of 8.
+    /// Serialize a vector of elements. When the serialization does not fill
+    /// the last byte, it is padded with zeros.
     pub fn serialize_vec(&self, a: &[u64]) -> Vec<u8> {
         let p_nbits = 64 - (self.p - 1).leading_zeros() as usize;
         transcode_to_bytes(a, p_nbits)
     }
 
     /// Deserialize a vector of bytes into a vector of elements mod p.
+    ///
+    /// The padding of the last byte may be deserialized into a trailing zero
+    /// element, which should be dropped by the caller knowing the size.
     pub fn deserialize_vec(&self, b: &[u8]) -> Vec<u64> {
         let p_nbits = 64 - (self.p - 1).leading_zeros() as usize;
         transcode_from_bytes(b, p_nbits)
@@ -722,7 +856,7 @@ impl Modulus {
 
 #[cfg(test)]
 mod tests {
-    use super::{primes, Modulus};
+    use super::{primes, Modulus, MAX_MODULUS_BITS};
     use fhe_util::catch_unwind;
     use itertools::{izip, Itertools};
     use proptest::collection::vec as prop_vec;
@@ -748,7 +882,7 @@ mod tests {
         #[test]
         fn constructor(p: u64) {
             // 63 and 64-bit integers do not work.
-            prop_assert!(Modulus::new(p | (1u64 << 62)).is_err());
+            prop_assert!(Modulus::new(p | (1u64 << MAX_MODULUS_BITS)).is_err());
             prop_assert!(Modulus::new(p | (1u64 << 63)).is_err());
 
             // p = 0 & 1 do not work.
@@ -1091,4 +1225,47 @@ mod tests {
             }
         }
     }
+
+    #[test]
+    fn batch_ops() {
+        let mut rng = rand::thread_rng();
+
+        for p in [2u64, 3, 17, 1987, 4611686018326724609] {
+            let q = Modulus::new(p).unwrap();
+
+            let a = (0..100).map(|_| 1 + rng.next_u64() % (p - 1)).collect_vec();
+            let inverses = q.inv_vec(&a).unwrap();
+            assert_eq!(
+                inverses,
+                a.iter().map(|ai| q.inv(*ai).unwrap()).collect_vec()
+            );
+            assert_eq!(q.inv_vec(&[]), Some(vec![]));
+            assert!(q.inv_vec(&[1, 0]).is_none());
+
+            let n = rng.next_u64() % p;
+            let mut powers = a.clone();
+            q.pow_vec(&mut powers, n);
+            assert_eq!(powers, a.iter().map(|ai| q.pow(*ai, n)).collect_vec());
+
+            let b = q.random_vec(100, &mut rng);
+            let c = q.random_vec(100, &mut rng);
+            let mut d = a.clone();
+            q.mul_add_vec(&mut d, &b, &c);
+            let expected = izip!(&a, &b, &c)
+                .map(|(ai, bi, ci)| q.add(*ai, q.mul(*bi, *ci)))
+                .collect_vec();
+            assert_eq!(d, expected);
+
+            let mut d = a.clone();
+            q.scalar_mul_add_vec(&mut d, &b, c[0]);
+            let expected = izip!(&a, &b)
+                .map(|(ai, bi)| q.add(*ai, q.mul(*bi, c[0])))
+                .collect_vec();
+            assert_eq!(d, expected);
+        }
+
+        // The inversion requires a prime modulus.
+        let q = Modulus::new(1155).unwrap();
+        assert!(q.inv_vec(&[1, 2]).is_none());
+    }
 }
@@ -0,0 +1,305 @@
+//! Ring operations for moduli up to 126 bits.
+//!
+//! [`Modulus128`] reduces the 256-bit products of two residues with a Barrett
+//! reduction, so that values which do not fit a [`Modulus`](super::Modulus),
+//! e.g. the product of two RNS moduli, can be accumulated exactly without
+//! falling back to `BigUint`.
+
+use ethnum::U256;
+use itertools::{izip, Itertools};
+use num_bigint::BigUint;
+use num_traits::ToPrimitive;
+use rand::{distributions::Uniform, CryptoRng, Rng, RngCore};
+
+use crate::errors::{Error, Result};
+
+/// Maximum number of bits of a [`Modulus128`].
+///
+/// The Barrett reduction leaves values below `3 * p`, which must fit a u128.
+pub const MAX_MODULUS128_BITS: usize = 126;
+
+/// Structure encapsulating an integer modulus up to 126 bits.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Modulus128 {
+    p: u128,
+    nbits: usize,
+    // floor(2^(2 * max(nbits, 64)) / p), so that all the u128 can be reduced.
+    barrett: u128,
+    // The shifts of the Barrett reduction, which add up to
+    // 2 * max(nbits, 64).
+    shift_in: u32,
+    shift_out: u32,
+    // 2^64 mod p.
+    two_64: u128,
+    distribution: Uniform<u128>,
+}
+
+// We need to declare Eq manually because of the `Uniform` member.
+impl Eq for Modulus128 {}
+
+/// cond ? on_true : on_false
+const fn const_time_cond_select(on_true: u128, on_false: u128, cond: bool) -> u128 {
+    let mask = (cond as u128).wrapping_neg();
+    ((on_true ^ on_false) & mask) ^ on_false
+}
+
+impl Modulus128 {
+    /// Create a modulus from an integer of at most 126 bits.
+    pub fn new(p: u128) -> Result<Self> {
+        if p < 2 || (p >> MAX_MODULUS128_BITS) != 0 {
+            return Err(Error::InvalidModulus128(p));
+        }
+        let nbits = 128 - p.leading_zeros() as usize;
+        let width = 2 * nbits.max(64);
+        let barrett = ((BigUint::from(1u64) << width) / p).to_u128().unwrap();
+        let shift_in = nbits as u32 - 1;
+        Ok(Self {
+            p,
+            nbits,
+            barrett,
+            shift_in,
+            shift_out: width as u32 - shift_in,
+            two_64: (1u128 << 64) % p,
+            distribution: Uniform::from(0..p),
+        })
+    }
+
+    /// Returns the value of the modulus.
+    pub const fn modulus(&self) -> u128 {
+        self.p
+    }
+
+    /// Returns the number of bits of the modulus.
+    pub const fn bits(&self) -> usize {
+        self.nbits
+    }
+
+    /// Returns a - p if a >= p, and a otherwise, in constant time.
+    const fn reduce1(&self, a: u128) -> u128 {
+        let (r, borrow) = a.overflowing_sub(self.p);
+        const_time_cond_select(a, r, borrow)
+    }
+
+    /// Modular reduction of a 256-bit integer below 2^(2 * max(nbits, 64)),
+    /// e.g. the product of two values < p, in constant time.
+    fn reduce_u256(&self, a: U256) -> u128 {
+        // The estimation of the quotient is smaller than the quotient by at
+        // most 2, and the remainder fits in a u128.
+        let q = ((a >> self.shift_in) * U256::from(self.barrett)) >> self.shift_out;
+        let r = a.as_u128().wrapping_sub(q.as_u128().wrapping_mul(self.p));
+        self.reduce1(self.reduce1(r))
+    }
+
+    /// Modular reduction of a u128 in constant time.
+    pub fn reduce(&self, a: u128) -> u128 {
+        self.reduce_u256(U256::from(a))
+    }
+
+    /// Modular reduction of an arbitrary large integer, in variable time.
+    pub fn reduce_biguint(&self, a: &BigUint) -> u128 {
+        a.iter_u64_digits().rev().fold(0, |r, limb| {
+            self.add(self.mul(r, self.two_64), self.reduce(limb as u128))
+        })
+    }
+
+    /// Performs the modular addition of a and b in constant time.
+    ///
+    /// Aborts if a >= p or b >= p in debug mode.
+    pub const fn add(&self, a: u128, b: u128) -> u128 {
+        debug_assert!(a < self.p && b < self.p);
+        self.reduce1(a + b)
+    }
+
+    /// Performs the modular subtraction of a and b in constant time.
+    ///
+    /// Aborts if a >= p or b >= p in debug mode.
+    pub const fn sub(&self, a: u128, b: u128) -> u128 {
+        debug_assert!(a < self.p && b < self.p);
+        self.reduce1(a + self.p - b)
+    }
+
+    /// Performs the modular negation of a in constant time.
+    ///
+    /// Aborts if a >= p in debug mode.
+    pub const fn neg(&self, a: u128) -> u128 {
+        debug_assert!(a < self.p);
+        self.reduce1(self.p - a)
+    }
+
+    /// Performs the modular multiplication of a and b in constant time.
+    ///
+    /// Aborts if a >= p or b >= p in debug mode.
+    pub fn mul(&self, a: u128, b: u128) -> u128 {
+        debug_assert!(a < self.p && b < self.p);
+        self.reduce_u256(U256::from(a) * U256::from(b))
+    }
+
+    /// Modular exponentiation in variable time.
+    ///
+    /// Aborts if a >= p in debug mode.
+    pub fn pow(&self, a: u128, n: u128) -> u128 {
+        debug_assert!(a < self.p);
+
+        let mut r = 1;
+        for i in (0..128 - n.leading_zeros()).rev() {
+            r = self.mul(r, r);
+            if (n >> i) & 1 == 1 {
+                r = self.mul(r, a);
+            }
+        }
+        r
+    }
+
+    /// Modular inversion in variable time, by Fermat's little theorem.
+    ///
+    /// Returns None if a = 0, or if p is not prime and the result is not the
+    /// inverse of a.
+    /// Aborts if a >= p in debug mode.
+    pub fn inv(&self, a: u128) -> std::option::Option<u128> {
+        let r = self.pow(a, self.p - 2);
+        (self.mul(a, r) == 1).then_some(r)
+    }
+
+    /// Modular addition of vectors in place in constant time.
+    ///
+    /// Aborts if a and b differ in size, and if any of their values is >= p in
+    /// debug mode.
+    pub fn add_vec(&self, a: &mut [u128], b: &[u128]) {
+        debug_assert_eq!(a.len(), b.len());
+        izip!(a.iter_mut(), b.iter()).for_each(|(ai, bi)| *ai = self.add(*ai, *bi));
+    }
+
+    /// Modular subtraction of vectors in place in constant time.
+    ///
+    /// Aborts if a and b differ in size, and if any of their values is >= p in
+    /// debug mode.
+    pub fn sub_vec(&self, a: &mut [u128], b: &[u128]) {
+        debug_assert_eq!(a.len(), b.len());
+        izip!(a.iter_mut(), b.iter()).for_each(|(ai, bi)| *ai = self.sub(*ai, *bi));
+    }
+
+    /// Modular multiplication of vectors in place in constant time.
+    ///
+    /// Aborts if a and b differ in size, and if any of their values is >= p in
+    /// debug mode.
+    pub fn mul_vec(&self, a: &mut [u128], b: &[u128]) {
+        debug_assert_eq!(a.len(), b.len());
+        izip!(a.iter_mut(), b.iter()).for_each(|(ai, bi)| *ai = self.mul(*ai, *bi));
+    }
+
+    /// Modular scalar multiplication of vectors in place in constant time.
+    ///
+    /// Aborts if b or any of the values of a is >= p in debug mode.
+    pub fn scalar_mul_vec(&self, a: &mut [u128], b: u128) {
+        a.iter_mut().for_each(|ai| *ai = self.mul(*ai, b));
+    }
+
+    /// Modular negation of a vector in place in constant time.
+    ///
+    /// Aborts if any of the values in the vector is >= p in debug mode.
+    pub fn neg_vec(&self, a: &mut [u128]) {
+        a.iter_mut().for_each(|ai| *ai = self.neg(*ai));
+    }
+
+    /// Modular reduction of a vector in place in constant time.
+    pub fn reduce_vec(&self, a: &mut [u128]) {
+        a.iter_mut().for_each(|ai| *ai = self.reduce(*ai));
+    }
+
+    /// Returns the sum of the products of the values of a and b modulo p, in
+    /// constant time.
+    ///
+    /// Aborts if a and b differ in size, and if any of their values is >= p in
+    /// debug mode.
+    pub fn dot_product(&self, a: &[u128], b: &[u128]) -> u128 {
+        debug_assert_eq!(a.len(), b.len());
+        izip!(a, b).fold(0, |acc, (ai, bi)| self.add(acc, self.mul(*ai, *bi)))
+    }
+
+    /// Returns a random vector.
+    pub fn random_vec<R: RngCore + CryptoRng>(&self, size: usize, rng: &mut R) -> Vec<u128> {
+        rng.sample_iter(self.distribution).take(size).collect_vec()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Modulus128, MAX_MODULUS128_BITS};
+    use num_bigint::BigUint;
+    use rand::{thread_rng, Rng};
+
+    #[test]
+    fn arithmetic() {
+        let mut rng = thread_rng();
+        assert!(Modulus128::new(0).is_err());
+        assert!(Modulus128::new(1).is_err());
+        assert!(Modulus128::new(1 << MAX_MODULUS128_BITS).is_err());
+
+        // 2^127 - 1 is too large; 2^89 - 1 and 2^107 - 1 are Mersenne primes.
+        for p in [
+            2u128,
+            3,
+            1987,
+            4611686018326724609,
+            (1 << 89) - 1,
+            (1 << 107) - 1,
+            (1 << MAX_MODULUS128_BITS) - 1,
+        ] {
+            let q = Modulus128::new(p).unwrap();
+            assert_eq!(q.modulus(), p);
+            let pb = BigUint::from(p);
+
+            let mut values = vec![0, 1, p - 1];
+            values.extend(q.random_vec(20, &mut rng));
+            for a in values.iter() {
+                for b in values.iter() {
+                    let (ab, bb) = (BigUint::from(*a), BigUint::from(*b));
+                    assert_eq!(BigUint::from(q.add(*a, *b)), (&ab + &bb) % &pb);
+                    assert_eq!(BigUint::from(q.sub(*a, *b)), (&ab + &pb - &bb) % &pb);
+                    assert_eq!(BigUint::from(q.mul(*a, *b)), (&ab * &bb) % &pb);
+                }
+                assert_eq!(q.add(*a, q.neg(*a)), 0);
+            }
+
+            for a in [0u128, u128::MAX, rng.gen()] {
+                assert_eq!(BigUint::from(q.reduce(a)), BigUint::from(a) % &pb);
+            }
+            let large = BigUint::from(rng.gen::<u128>()) * rng.gen::<u128>() * rng.gen::<u128>();
+            assert_eq!(BigUint::from(q.reduce_biguint(&large)), &large % &pb);
+
+            // The dot product is exact.
+            let a = q.random_vec(50, &mut rng);
+            let b = q.random_vec(50, &mut rng);
+            let expected = a
+                .iter()
+                .zip(&b)
+                .map(|(ai, bi)| BigUint::from(*ai) * *bi)
+                .sum::<BigUint>();
+            assert_eq!(BigUint::from(q.dot_product(&a, &b)), expected % &pb);
+            let mut c = a.clone();
+            q.mul_vec(&mut c, &b);
+            q.add_vec(&mut c, &a);
+            q.sub_vec(&mut c, &a);
+            q.neg_vec(&mut c);
+            q.neg_vec(&mut c);
+            let mut d = b.clone();
+            q.scalar_mul_vec(&mut d, 1);
+            q.mul_vec(&mut d, &a);
+            assert_eq!(c, d);
+        }
+
+        for p in [3u128, 1987, (1 << 89) - 1, (1 << 107) - 1] {
+            let q = Modulus128::new(p).unwrap();
+            let a = 1 + rng.gen::<u128>() % (p - 1);
+            assert_eq!(q.mul(a, q.inv(a).unwrap()), 1);
+            assert!(q.inv(0).is_none());
+            assert_eq!(q.pow(a, 0), 1);
+            assert_eq!(q.pow(a, p - 1), 1);
+        }
+        let q = Modulus128::new(1 << 100).unwrap();
+        assert!(q.inv(2).is_none());
+        let mut a = [u128::MAX, 1 << 100];
+        q.reduce_vec(&mut a);
+        assert_eq!(a, [(1 << 100) - 1, 0]);
+    }
+}
@@ -0,0 +1,213 @@
+//! Montgomery multiplication.
+//!
+//! A value `a` is represented in the Montgomery domain by `a * R mod p`, where
+//! `R = 2^64`, so that the product of two values in the Montgomery domain only
+//! requires a Montgomery reduction of their 128-bit product.
+//!
+//! The addition, subtraction and negation are the same in both domains, so
+//! that with the [`ReductionBackend::Montgomery`] backend, the vectors can be
+//! converted into the Montgomery domain once with
+//! [`Modulus::to_montgomery_vec`], multiplied with
+//! [`Modulus::mul_montgomery_vec`], added and subtracted with the vector
+//! operations of [`Modulus`], and converted back at the end with
+//! [`Modulus::from_montgomery_vec`]. The other operations of [`Modulus`],
+//! including [`Modulus::mul_vec`], always operate on canonical values,
+//! whatever the backend.
+//!
+//! The backend is selected explicitly with [`Modulus::new_with_backend`]; the
+//! moduli of an [`rq::Context`](crate::rq::Context) use the Barrett backend.
+
+use super::Modulus;
+use crate::{Error, Result};
+use itertools::izip;
+
+/// Reduction backend of a modulus.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum ReductionBackend {
+    /// Barrett reduction, or the optimized reduction for special primes.
+    #[default]
+    Barrett,
+    /// Montgomery reduction; only available for odd moduli. Enables the
+    /// operations in the Montgomery domain.
+    Montgomery,
+}
+
+/// Constants used by the Montgomery multiplication.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct MontgomeryConstants {
+    /// -p^(-1) mod 2^64.
+    p_neg_inv: u64,
+    /// R^2 mod p.
+    r2: u64,
+}
+
+impl MontgomeryConstants {
+    /// Compute the constants for an odd modulus.
+    pub(crate) fn new(q: &Modulus) -> Self {
+        if q.p & 1 == 0 {
+            return Self::default();
+        }
+        // Newton iteration, doubling the number of correct low bits each time.
+        let mut inv = q.p;
+        for _ in 0..5 {
+            inv = inv.wrapping_mul(2u64.wrapping_sub(q.p.wrapping_mul(inv)));
+        }
+        let r = q.reduce_u128(1u128 << 64);
+        Self {
+            p_neg_inv: inv.wrapping_neg(),
+            r2: q.mul(r, r),
+        }
+    }
+}
+
+impl Modulus {
+    /// Create a modulus from an integer of at most 62 bits, which uses the
+    /// reduction backend `backend`.
+    ///
+    /// Returns an error if the Montgomery backend is selected for an even
+    /// modulus.
+    pub fn new_with_backend(p: u64, backend: ReductionBackend) -> Result<Self> {
+        let mut q = Self::new(p)?;
+        if backend == ReductionBackend::Montgomery && p & 1 == 0 {
+            return Err(Error::UnsupportedModulus(p));
+        }
+        q.backend = backend;
+        Ok(q)
+    }
+
+    /// Returns the reduction backend of the modulus.
+    pub const fn backend(&self) -> ReductionBackend {
+        self.backend
+    }
+
+    /// Montgomery reduction of a < p * 2^64, returning a * 2^(-64) mod p in
+    /// constant time.
+    const fn montgomery_reduce(&self, a: u128) -> u64 {
+        debug_assert!(matches!(self.backend, ReductionBackend::Montgomery));
+        debug_assert!(a < (self.p as u128) << 64);
+
+        let m = (a as u64).wrapping_mul(self.montgomery.p_neg_inv);
+        let t = ((a + (m as u128) * (self.p as u128)) >> 64) as u64;
+        Self::reduce1(t, self.p)
+    }
+
+    /// Converts a value into the Montgomery domain.
+    ///
+    /// Aborts if the modulus does not use the Montgomery backend or if a >= p in debug mode.
+    pub const fn to_montgomery(&self, a: u64) -> u64 {
+        debug_assert!(a < self.p);
+        self.montgomery_reduce((a as u128) * (self.montgomery.r2 as u128))
+    }
+
+    /// Converts a value out of the Montgomery domain.
+    ///
+    /// Aborts if the modulus does not use the Montgomery backend or if a >= p in debug mode.
+    pub const fn from_montgomery(&self, a: u64) -> u64 {
+        debug_assert!(a < self.p);
+        self.montgomery_reduce(a as u128)
+    }
+
+    /// Montgomery multiplication of a and b in constant time, which computes
+    /// a * b * 2^(-64) mod p. When a and b are in the Montgomery domain, so is
+    /// the result.
+    ///
+    /// Aborts if the modulus does not use the Montgomery backend or if a >= p or b >= p in debug mode.
+    pub const fn mul_montgomery(&self, a: u64, b: u64) -> u64 {
+        debug_assert!(a < self.p && b < self.p);
+        self.montgomery_reduce((a as u128) * (b as u128))
+    }
+
+    /// Montgomery multiplication of vectors in place in constant time.
+    ///
+    /// Aborts if a and b differ in size, if the modulus does not use the
+    /// Montgomery backend, and if any of their values is >= p in debug mode.
+    pub fn mul_montgomery_vec(&self, a: &mut [u64], b: &[u64]) {
+        debug_assert_eq!(a.len(), b.len());
+
+        izip!(a.iter_mut(), b.iter()).for_each(|(ai, bi)| *ai = self.mul_montgomery(*ai, *bi));
+    }
+
+    /// Converts a vector into the Montgomery domain in place.
+    ///
+    /// Aborts if the modulus does not use the Montgomery backend or if any of the values is >= p in debug
+    /// mode.
+    pub fn to_montgomery_vec(&self, a: &mut [u64]) {
+        a.iter_mut().for_each(|ai| *ai = self.to_montgomery(*ai))
+    }
+
+    /// Converts a vector out of the Montgomery domain in place.
+    ///
+    /// Aborts if the modulus does not use the Montgomery backend or if any of the values is >= p in debug
+    /// mode.
+    pub fn from_montgomery_vec(&self, a: &mut [u64]) {
+        a.iter_mut().for_each(|ai| *ai = self.from_montgomery(*ai))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ReductionBackend;
+    use crate::zq::Modulus;
+    use rand::thread_rng;
+
+    #[test]
+    fn montgomery() {
+        let mut rng = thread_rng();
+        for p in [3u64, 1153, 4611686018326724609, (1 << 62) - 57] {
+            let q = Modulus::new_with_backend(p, ReductionBackend::Montgomery).unwrap();
+            let barrett = Modulus::new(p).unwrap();
+            assert_eq!(q.backend(), ReductionBackend::Montgomery);
+            assert_eq!(barrett.backend(), ReductionBackend::Barrett);
+
+            let mut a = q.random_vec(64, &mut rng);
+            let mut b = q.random_vec(64, &mut rng);
+            (a[0], b[0]) = (p - 1, p - 1);
+            (a[1], b[1]) = (0, p - 1);
+            for (ai, bi) in a.iter().zip(b.iter()) {
+                assert_eq!(q.from_montgomery(q.to_montgomery(*ai)), *ai);
+                assert_eq!(
+                    q.from_montgomery(q.mul_montgomery(q.to_montgomery(*ai), q.to_montgomery(*bi))),
+                    q.mul(*ai, *bi)
+                );
+            }
+
+            // The vectors stay in the Montgomery domain across the operations.
+            let mut expected = a.clone();
+            barrett.mul_vec(&mut expected, &b);
+            barrett.add_vec(&mut expected, &b);
+            barrett.mul_vec(&mut expected, &a);
+            let (mut a_m, mut b_m) = (a.clone(), b.clone());
+            q.to_montgomery_vec(&mut a_m);
+            q.to_montgomery_vec(&mut b_m);
+            let mut c = a_m.clone();
+            q.mul_montgomery_vec(&mut c, &b_m);
+            q.add_vec(&mut c, &b_m);
+            q.mul_montgomery_vec(&mut c, &a_m);
+            q.from_montgomery_vec(&mut c);
+            assert_eq!(c, expected);
+
+            // The other vector operations are canonical for every backend.
+            let mut c = a.clone();
+            q.mul_vec(&mut c, &b);
+            q.add_vec(&mut c, &b);
+            q.mul_vec(&mut c, &a);
+            assert_eq!(c, expected);
+            let mut c = a.clone();
+            unsafe {
+                q.mul_vec_vt(&mut c, &b);
+                q.add_vec_vt(&mut c, &b);
+                q.mul_vec_vt(&mut c, &a);
+            }
+            assert_eq!(c, expected);
+
+            assert_ne!(q, barrett);
+            assert_eq!(
+                q,
+                Modulus::new_with_backend(p, ReductionBackend::Montgomery).unwrap()
+            );
+        }
+
+        assert!(Modulus::new_with_backend(1024, ReductionBackend::Montgomery).is_err());
+        assert!(Modulus::new_with_backend(1024, ReductionBackend::Barrett).is_ok());
+    }
+}
@@ -1,5 +1,6 @@
 //! Optimized primes generated as in the NFLlib library.
 
+use super::{Modulus, MAX_MODULUS_BITS};
 use fhe_util::is_prime;
 use num_bigint::BigUint;
 
@@ -23,10 +24,10 @@ pub fn supports_opt(p: u64) -> bool {
 }
 
 /// Generate a `num_bits`-bit prime, congruent to 1 mod `modulo`, strictly
-/// smaller than `upper_bound`. Note that `num_bits` must belong to (10..=62),
-/// and upper_bound must be <= 1 << num_bits.
+/// smaller than `upper_bound`. Note that `num_bits` must belong to
+/// (10..=[`MAX_MODULUS_BITS`]), and upper_bound must be <= 1 << num_bits.
 pub fn generate_prime(num_bits: usize, modulo: u64, upper_bound: u64) -> Option<u64> {
-    if !(10..=62).contains(&num_bits) {
+    if !(10..=MAX_MODULUS_BITS).contains(&num_bits) {
         None
     } else {
         debug_assert!(
@@ -56,10 +57,120 @@ pub fn generate_prime(num_bits: usize, modulo: u64, upper_bound: u64) -> Option<
     }
 }
 
+/// Returns whether p is a prime supporting the Number Theoretic Transform of
+/// size `n`, i.e., whether p is congruent to 1 modulo `2 * n`. Returns false
+/// if `n` is not a power of two.
+pub fn is_ntt_friendly(p: u64, n: usize) -> bool {
+    n.is_power_of_two()
+        && (n as u64).leading_zeros() > 0
+        && p % ((n as u64) << 1) == 1
+        && is_prime(p)
+}
+
+/// Returns a primitive `m`-th root of unity modulo the prime p, where `m` is a
+/// power of two, such as `2 * n` for the negacyclic NTT of size `n`. The root
+/// is the smallest one found when raising 2, 3, ... to the power
+/// `(p - 1) / m`, so the output is deterministic.
+///
+/// Returns None if `m` is not a power of two >= 2, if p is not a prime of at
+/// most [`MAX_MODULUS_BITS`] bits, or if p is not congruent to 1 modulo `m`.
+pub fn primitive_root_of_unity(m: usize, p: u64) -> Option<u64> {
+    if m < 2 || !m.is_power_of_two() || p % m as u64 != 1 || !is_prime(p) {
+        return None;
+    }
+    let q = Modulus::new(p).ok()?;
+    let lambda = (p - 1) / m as u64;
+    // Since m is a power of two, r is a primitive m-th root of unity if and
+    // only if r^(m / 2) = -1 mod p.
+    (2..p)
+        .map(|x| q.pow(x, lambda))
+        .find(|r| q.pow(*r, (m / 2) as u64) == p - 1)
+}
+
+/// Iterator over the `num_bits`-bit primes congruent to 1 modulo a given
+/// integer, in decreasing order. See [`primes_congruent_to_one`] and
+/// [`ntt_primes`].
+#[derive(Debug, Clone)]
+pub struct PrimeSearch {
+    candidate: Option<u64>,
+    modulo: u64,
+    lower_bound: u64,
+}
+
+impl Iterator for PrimeSearch {
+    type Item = u64;
+
+    fn next(&mut self) -> Option<u64> {
+        while let Some(candidate) = self.candidate.filter(|c| *c >= self.lower_bound) {
+            self.candidate = candidate.checked_sub(self.modulo);
+            if is_prime(candidate) {
+                return Some(candidate);
+            }
+        }
+        self.candidate = None;
+        None
+    }
+}
+
+/// Returns an iterator over the `num_bits`-bit primes congruent to 1 modulo
+/// `modulo`, in decreasing order starting from the largest one. The iterator
+/// is empty if `num_bits` does not belong to (2..=[`MAX_MODULUS_BITS`]) or if
+/// `modulo` is 0.
+pub fn primes_congruent_to_one(num_bits: usize, modulo: u64) -> PrimeSearch {
+    if !(2..=MAX_MODULUS_BITS).contains(&num_bits) || modulo == 0 {
+        return PrimeSearch {
+            candidate: None,
+            modulo: 1,
+            lower_bound: 0,
+        };
+    }
+    let k_max = ((1u64 << num_bits) - 2) / modulo;
+    PrimeSearch {
+        candidate: Some(k_max * modulo + 1),
+        modulo,
+        lower_bound: 1u64 << (num_bits - 1),
+    }
+}
+
+/// Returns an iterator over the `num_bits`-bit primes supporting the NTT of
+/// size `degree`, i.e., congruent to 1 modulo `2 * degree`, in decreasing
+/// order starting from the largest one.
+pub fn ntt_primes(num_bits: usize, degree: usize) -> PrimeSearch {
+    primes_congruent_to_one(num_bits, 2 * degree as u64)
+}
+
+/// Generate `count` distinct `num_bits`-bit primes congruent to 1 modulo
+/// `2 * degree`, which therefore support the NTT of size `degree`, in
+/// decreasing order starting from the largest one. Returns None if
+/// `num_bits` does not belong to (10..=[`MAX_MODULUS_BITS`]) or if there are
+/// not enough such primes.
+pub fn generate_ntt_primes(num_bits: usize, degree: usize, count: usize) -> Option<Vec<u64>> {
+    if !(10..=MAX_MODULUS_BITS).contains(&num_bits) {
+        return None;
+    }
+
+    let primes = ntt_primes(num_bits, degree).take(count).collect::<Vec<_>>();
+    (primes.len() == count).then_some(primes)
+}
+
+/// Generate the largest `num_bits`-bit prime congruent to 1 modulo
+/// `2 * degree`, which can be used as a plaintext modulus supporting the SIMD
+/// encoding of polynomials of degree `degree`. Contrary to the ciphertext
+/// moduli, `num_bits` can be smaller than 10 bits, but must be at most
+/// [`MAX_MODULUS_BITS`]. Returns None if there is no such prime.
+pub fn generate_plaintext_prime(num_bits: usize, degree: usize) -> Option<u64> {
+    ntt_primes(num_bits, degree).next()
+}
+
 #[cfg(test)]
 mod tests {
-    use super::generate_prime;
+    use super::{
+        generate_ntt_primes, generate_plaintext_prime, generate_prime, is_ntt_friendly,
+        primes_congruent_to_one, primitive_root_of_unity,
+    };
+    use crate::zq::Modulus;
     use fhe_util::catch_unwind;
+    use fhe_util::is_prime;
 
     // Verifies that the same moduli as in the NFLlib library are generated.
     // <https://github.com/quarkslab/NFLlib/blob/master/include/nfl/params.hpp>
@@ -100,6 +211,88 @@ mod tests {
         )
     }
 
+    #[test]
+    fn ntt_primes() {
+        let primes = generate_ntt_primes(62, 1048576, 20).unwrap();
+        let mut generated = vec![];
+        let mut upper_bound = u64::MAX >> 2;
+        while generated.len() != 20 {
+            upper_bound = generate_prime(62, 2 * 1048576, upper_bound).unwrap();
+            generated.push(upper_bound);
+        }
+        assert_eq!(primes, generated);
+
+        for (num_bits, degree, count) in [(10, 16, 3), (30, 4096, 8), (62, 2, 5)] {
+            let primes = generate_ntt_primes(num_bits, degree, count).unwrap();
+            assert_eq!(primes.len(), count);
+            assert!(primes.windows(2).all(|w| w[0] > w[1]));
+            for p in primes {
+                assert!(is_prime(p));
+                assert_eq!(p % (2 * degree as u64), 1);
+                assert_eq!(64 - p.leading_zeros() as usize, num_bits);
+            }
+        }
+
+        assert_eq!(generate_ntt_primes(20, 1024, 0), Some(vec![]));
+        assert!(generate_ntt_primes(9, 16, 1).is_none());
+        assert!(generate_ntt_primes(63, 16, 1).is_none());
+        // There is no prime of 11 bits congruent to 1 modulo 256.
+        assert!(generate_ntt_primes(11, 128, 1).is_none());
+    }
+
+    #[test]
+    fn plaintext_prime() {
+        assert_eq!(generate_plaintext_prime(5, 8), Some(17));
+        assert_eq!(generate_plaintext_prime(11, 16), Some(2017));
+        assert_eq!(
+            generate_plaintext_prime(20, 4096),
+            generate_prime(20, 8192, 1 << 20)
+        );
+        assert!(generate_plaintext_prime(4, 8).is_none());
+        assert!(generate_plaintext_prime(1, 1).is_none());
+        assert!(generate_plaintext_prime(63, 1).is_none());
+    }
+
+    #[test]
+    fn number_theory() {
+        assert!(is_ntt_friendly(12289, 2048));
+        assert!(!is_ntt_friendly(12289, 4096));
+        assert!(!is_ntt_friendly(12289, 100));
+        assert!(!is_ntt_friendly(12289, 0));
+        assert!(!is_ntt_friendly(12289 * 40961, 16));
+
+        for (m, p) in [(2, 3), (16, 17), (4096, 12289), (2048, 4611686018326724609)] {
+            let root = primitive_root_of_unity(m, p).unwrap();
+            let q = Modulus::new(p).unwrap();
+            assert_eq!(q.pow(root, m as u64), 1);
+            assert_eq!(q.pow(root, m as u64 / 2), p - 1);
+            assert_eq!(primitive_root_of_unity(m, p), Some(root));
+        }
+        assert!(primitive_root_of_unity(8192, 12289).is_none());
+        assert!(primitive_root_of_unity(12, 37).is_none());
+        assert!(primitive_root_of_unity(16, 17 * 97).is_none());
+        assert!(primitive_root_of_unity(1, 17).is_none());
+
+        assert_eq!(
+            primes_congruent_to_one(10, 128).collect::<Vec<_>>(),
+            vec![769, 641]
+        );
+        assert_eq!(
+            super::ntt_primes(62, 1048576).take(20).collect::<Vec<_>>(),
+            generate_ntt_primes(62, 1048576, 20).unwrap()
+        );
+        assert_eq!(
+            super::ntt_primes(5, 1).collect::<Vec<_>>(),
+            vec![31, 29, 23, 19, 17]
+        );
+        assert_eq!(
+            primes_congruent_to_one(2, 1).collect::<Vec<_>>(),
+            vec![3, 2]
+        );
+        assert_eq!(primes_congruent_to_one(11, 0).count(), 0);
+        assert_eq!(primes_congruent_to_one(63, 2).count(), 0);
+    }
+
     #[test]
     fn upper_bound() {
         debug_assert!(catch_unwind(|| generate_prime(62, 2 * 1048576, (1 << 62) + 1)).is_err());
@@ -0,0 +1,264 @@
+//! Vectorized modular operations.
+//!
+//! The functions process the longest prefix of the vectors supported by the
+//! vector instructions available at runtime, and return its length; the
+//! remaining elements are left to the scalar code. The operations only use
+//! masks, and are therefore constant time. AVX2 only multiplies 32-bit lanes,
+//! so the 64-bit products are assembled from four 32-bit products. This is
+//! faster for the Shoup multiplications and the reductions of u64, but not for
+//! the Barrett reductions of the full 128-bit products, so that
+//! [`Modulus::mul_vec`] remains scalar.
+
+use crate::zq::Modulus;
+
+/// Modular addition of the prefix of two vectors in place.
+pub(crate) fn add_vec(a: &mut [u64], b: &[u64], p: u64) -> usize {
+    debug_assert_eq!(a.len(), b.len());
+    #[cfg(target_arch = "x86_64")]
+    if is_x86_feature_detected!("avx2") {
+        return unsafe { avx2::add_vec(a, b, p) };
+    }
+    0
+}
+
+/// Modular subtraction of the prefix of two vectors in place.
+pub(crate) fn sub_vec(a: &mut [u64], b: &[u64], p: u64) -> usize {
+    debug_assert_eq!(a.len(), b.len());
+    #[cfg(target_arch = "x86_64")]
+    if is_x86_feature_detected!("avx2") {
+        return unsafe { avx2::sub_vec(a, b, p) };
+    }
+    0
+}
+
+/// Modular negation of the prefix of a vector in place.
+pub(crate) fn neg_vec(a: &mut [u64], p: u64) -> usize {
+    #[cfg(target_arch = "x86_64")]
+    if is_x86_feature_detected!("avx2") {
+        return unsafe { avx2::neg_vec(a, p) };
+    }
+    0
+}
+
+/// Shoup modular multiplication of the prefix of two vectors in place.
+pub(crate) fn mul_shoup_vec(a: &mut [u64], b: &[u64], b_shoup: &[u64], p: u64) -> usize {
+    debug_assert_eq!(a.len(), b.len());
+    debug_assert_eq!(a.len(), b_shoup.len());
+    #[cfg(target_arch = "x86_64")]
+    if is_x86_feature_detected!("avx2") {
+        return unsafe { avx2::mul_shoup_vec(a, b, b_shoup, p) };
+    }
+    0
+}
+
+/// Modular reduction of the prefix of a vector in place.
+pub(crate) fn reduce_vec(a: &mut [u64], q: &Modulus) -> usize {
+    #[cfg(target_arch = "x86_64")]
+    if is_x86_feature_detected!("avx2") {
+        return unsafe { avx2::reduce_vec(a, q) };
+    }
+    0
+}
+
+#[cfg(target_arch = "x86_64")]
+mod avx2 {
+    use super::Modulus;
+    use std::arch::x86_64::*;
+
+    // The moduli have at most 62 bits, so the sums of two reduced values fit in
+    // 63 bits and the signed comparisons are correct.
+
+    #[target_feature(enable = "avx2")]
+    pub(super) unsafe fn add_vec(a: &mut [u64], b: &[u64], p: u64) -> usize {
+        let n = a.len() & !3;
+        let vp = _mm256_set1_epi64x(p as i64);
+        let vp_minus_one = _mm256_set1_epi64x(p as i64 - 1);
+        for i in (0..n).step_by(4) {
+            let va = _mm256_loadu_si256(a.as_ptr().add(i) as *const __m256i);
+            let vb = _mm256_loadu_si256(b.as_ptr().add(i) as *const __m256i);
+            let sum = _mm256_add_epi64(va, vb);
+            let mask = _mm256_cmpgt_epi64(sum, vp_minus_one);
+            let r = _mm256_sub_epi64(sum, _mm256_and_si256(mask, vp));
+            _mm256_storeu_si256(a.as_mut_ptr().add(i) as *mut __m256i, r);
+        }
+        n
+    }
+
+    #[target_feature(enable = "avx2")]
+    pub(super) unsafe fn sub_vec(a: &mut [u64], b: &[u64], p: u64) -> usize {
+        let n = a.len() & !3;
+        let vp = _mm256_set1_epi64x(p as i64);
+        for i in (0..n).step_by(4) {
+            let va = _mm256_loadu_si256(a.as_ptr().add(i) as *const __m256i);
+            let vb = _mm256_loadu_si256(b.as_ptr().add(i) as *const __m256i);
+            let diff = _mm256_sub_epi64(va, vb);
+            let mask = _mm256_cmpgt_epi64(vb, va);
+            let r = _mm256_add_epi64(diff, _mm256_and_si256(mask, vp));
+            _mm256_storeu_si256(a.as_mut_ptr().add(i) as *mut __m256i, r);
+        }
+        n
+    }
+
+    #[target_feature(enable = "avx2")]
+    pub(super) unsafe fn neg_vec(a: &mut [u64], p: u64) -> usize {
+        let n = a.len() & !3;
+        let vp = _mm256_set1_epi64x(p as i64);
+        let zero = _mm256_setzero_si256();
+        for i in (0..n).step_by(4) {
+            let va = _mm256_loadu_si256(a.as_ptr().add(i) as *const __m256i);
+            let mask = _mm256_cmpeq_epi64(va, zero);
+            let r = _mm256_andnot_si256(mask, _mm256_sub_epi64(vp, va));
+            _mm256_storeu_si256(a.as_mut_ptr().add(i) as *mut __m256i, r);
+        }
+        n
+    }
+    /// Returns the high and low 64 bits of the products of the lanes.
+    #[inline]
+    #[target_feature(enable = "avx2")]
+    unsafe fn mul_wide(a: __m256i, b: __m256i) -> (__m256i, __m256i) {
+        let lo32 = _mm256_set1_epi64x(0xffffffff);
+        let a_hi = _mm256_srli_epi64(a, 32);
+        let b_hi = _mm256_srli_epi64(b, 32);
+        let ll = _mm256_mul_epu32(a, b);
+        let lh = _mm256_mul_epu32(a, b_hi);
+        let hl = _mm256_mul_epu32(a_hi, b);
+        let hh = _mm256_mul_epu32(a_hi, b_hi);
+        let mid = _mm256_add_epi64(
+            _mm256_srli_epi64(ll, 32),
+            _mm256_add_epi64(_mm256_and_si256(lh, lo32), _mm256_and_si256(hl, lo32)),
+        );
+        let lo = _mm256_or_si256(_mm256_and_si256(ll, lo32), _mm256_slli_epi64(mid, 32));
+        let hi = _mm256_add_epi64(
+            _mm256_add_epi64(hh, _mm256_srli_epi64(mid, 32)),
+            _mm256_add_epi64(_mm256_srli_epi64(lh, 32), _mm256_srli_epi64(hl, 32)),
+        );
+        (hi, lo)
+    }
+
+    /// Returns the low 64 bits of the products of the lanes.
+    #[inline]
+    #[target_feature(enable = "avx2")]
+    unsafe fn mul_lo(a: __m256i, b: __m256i) -> __m256i {
+        let cross = _mm256_add_epi64(
+            _mm256_mul_epu32(a, _mm256_srli_epi64(b, 32)),
+            _mm256_mul_epu32(_mm256_srli_epi64(a, 32), b),
+        );
+        _mm256_add_epi64(_mm256_mul_epu32(a, b), _mm256_slli_epi64(cross, 32))
+    }
+
+    /// Returns the sums of the lanes, and the carries as all-ones masks.
+    #[inline]
+    #[target_feature(enable = "avx2")]
+    unsafe fn add_carry(a: __m256i, b: __m256i) -> (__m256i, __m256i) {
+        let sign = _mm256_set1_epi64x(i64::MIN);
+        let sum = _mm256_add_epi64(a, b);
+        let carry = _mm256_cmpgt_epi64(_mm256_xor_si256(a, sign), _mm256_xor_si256(sum, sign));
+        (sum, carry)
+    }
+
+    /// Reduces lanes in the interval [0, 2 * p).
+    #[inline]
+    #[target_feature(enable = "avx2")]
+    unsafe fn reduce1(a: __m256i, vp: __m256i, vp_minus_one: __m256i) -> __m256i {
+        let mask = _mm256_cmpgt_epi64(a, vp_minus_one);
+        _mm256_sub_epi64(a, _mm256_and_si256(mask, vp))
+    }
+
+    #[target_feature(enable = "avx2")]
+    pub(super) unsafe fn mul_shoup_vec(a: &mut [u64], b: &[u64], b_shoup: &[u64], p: u64) -> usize {
+        let n = a.len() & !3;
+        let vp = _mm256_set1_epi64x(p as i64);
+        let vp_minus_one = _mm256_set1_epi64x(p as i64 - 1);
+        for i in (0..n).step_by(4) {
+            let va = _mm256_loadu_si256(a.as_ptr().add(i) as *const __m256i);
+            let vb = _mm256_loadu_si256(b.as_ptr().add(i) as *const __m256i);
+            let vb_shoup = _mm256_loadu_si256(b_shoup.as_ptr().add(i) as *const __m256i);
+            let (quotient, _) = mul_wide(va, vb_shoup);
+            let r = _mm256_sub_epi64(mul_lo(va, vb), mul_lo(quotient, vp));
+            let r = reduce1(r, vp, vp_minus_one);
+            _mm256_storeu_si256(a.as_mut_ptr().add(i) as *mut __m256i, r);
+        }
+        n
+    }
+
+    #[target_feature(enable = "avx2")]
+    pub(super) unsafe fn reduce_vec(a: &mut [u64], q: &Modulus) -> usize {
+        let n = a.len() & !3;
+        let b_hi = _mm256_set1_epi64x(q.barrett_hi as i64);
+        let b_lo = _mm256_set1_epi64x(q.barrett_lo as i64);
+        let vp = _mm256_set1_epi64x(q.p as i64);
+        let vp_minus_one = _mm256_set1_epi64x(q.p as i64 - 1);
+        for i in (0..n).step_by(4) {
+            let va = _mm256_loadu_si256(a.as_ptr().add(i) as *const __m256i);
+            // Follows `Modulus::lazy_reduce`.
+            let (p_lo_lo, _) = mul_wide(va, b_lo);
+            let (p_lo_hi_hi, p_lo_hi_lo) = mul_wide(va, b_hi);
+            let (_, carry) = add_carry(p_lo_hi_lo, p_lo_lo);
+            let quotient = _mm256_sub_epi64(p_lo_hi_hi, carry);
+            let r = _mm256_sub_epi64(va, mul_lo(quotient, vp));
+            let r = reduce1(r, vp, vp_minus_one);
+            _mm256_storeu_si256(a.as_mut_ptr().add(i) as *mut __m256i, r);
+        }
+        n
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::zq::Modulus;
+    use rand::{thread_rng, RngCore};
+
+    #[test]
+    fn matches_scalar() {
+        let mut rng = thread_rng();
+        for p in [2u64, 3, 1153, 4611686018326724609, (1 << 62) - 57] {
+            let q = Modulus::new(p).unwrap();
+            for size in [0, 1, 3, 4, 7, 16, 33] {
+                let mut a = q.random_vec(size, &mut rng);
+                let mut b = q.random_vec(size, &mut rng);
+                if size > 2 {
+                    // Edge cases.
+                    (a[0], b[0]) = (p - 1, p - 1);
+                    (a[1], b[1]) = (0, p - 1);
+                    (a[2], b[2]) = (0, 0);
+                }
+
+                let mut c = a.clone();
+                let done = super::add_vec(&mut c, &b, p);
+                assert!(done <= size);
+                for i in 0..done {
+                    assert_eq!(c[i], q.add(a[i], b[i]));
+                }
+
+                let mut c = a.clone();
+                let done = super::sub_vec(&mut c, &b, p);
+                for i in 0..done {
+                    assert_eq!(c[i], q.sub(a[i], b[i]));
+                }
+
+                let mut c = a.clone();
+                let done = super::neg_vec(&mut c, p);
+                for i in 0..done {
+                    assert_eq!(c[i], q.neg(a[i]));
+                }
+
+                let mut c = a.clone();
+                let b_shoup = q.shoup_vec(&b);
+                let done = super::mul_shoup_vec(&mut c, &b, &b_shoup, p);
+                for i in 0..done {
+                    assert_eq!(c[i], q.mul_shoup(a[i], b[i], b_shoup[i]));
+                }
+
+                let mut c = (0..size).map(|_| rng.next_u64()).collect::<Vec<_>>();
+                if size > 2 {
+                    (c[0], c[1], c[2]) = (u64::MAX, p, 0);
+                }
+                let d = c.clone();
+                let done = super::reduce_vec(&mut c, &q);
+                for i in 0..done {
+                    assert_eq!(c[i], d[i] % p);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,23 @@
+import assert from 'node:assert/strict'
+import { createRequire } from 'node:module'
+import test from 'node:test'
+
+const { BfvParameters, Ciphertext, Plaintext, PublicKey, SecretKey } = createRequire(import.meta.url)('../index.js')
+
+test('encrypt, serialize and decrypt', () => {
+  const params = BfvParameters.default(2, 16)
+  const sk = new SecretKey(params)
+  const pk = PublicKey.fromBytes(new PublicKey(sk).toBytes(), params)
+
+  const values = BigUint64Array.from({ length: 16 }, (_, i) => BigInt(i))
+  const ct = pk.encrypt(Plaintext.encode(values, params, 'simd'))
+  const received = Ciphertext.fromBytes(ct.toBytes(), params)
+  const t = params.plaintextModulus
+  assert.deepEqual(
+    sk.decrypt(received.add(received)).decode('simd'),
+    values.map((v) => (2n * v) % t),
+  )
+
+  assert.throws(() => Ciphertext.fromBytes(Buffer.from([1]), params))
+  assert.throws(() => Plaintext.encode(values, params, 'unknown'))
+})
@@ -0,0 +1,219 @@
+// The `missing_docs` lint is not enabled, since the glue generated by the
+// `napi` macros is undocumented.
+#![warn(unused_imports)]
+
+//! Node.js bindings for the BFV encryption scheme of the `fhe` crate.
+//!
+//! The errors of the library are thrown as JavaScript errors, the slot vectors
+//! are `BigUint64Array`s, the serializations are `Buffer`s, and the randomness
+//! is drawn from the thread-local cryptographically secure generator.
+
+use std::sync::Arc;
+
+use fhe::{bfv, entropy};
+use fhe_traits::{
+    DeserializeParametrized, FheDecoder, FheDecrypter, FheEncoder, FheEncrypter, Serialize,
+};
+use napi::bindgen_prelude::{BigInt, BigUint64Array, Buffer};
+use napi::{Error, Result};
+use napi_derive::napi;
+
+fn to_napi_err(e: fhe::Error) -> Error {
+    Error::from_reason(e.to_string())
+}
+
+/// Returns the encoding named `name` at level `level`.
+fn encoding(name: &str, level: usize) -> Result<bfv::Encoding> {
+    match name {
+        "poly" => Ok(bfv::Encoding::poly_at_level(level)),
+        "simd" => Ok(bfv::Encoding::simd_at_level(level)),
+        _ => Err(Error::from_reason(format!(
+            "Unknown encoding {name}, expected \"poly\" or \"simd\""
+        ))),
+    }
+}
+
+/// Parameters for the BFV encryption scheme.
+#[napi]
+pub struct BfvParameters(Arc<bfv::BfvParameters>);
+
+#[napi]
+impl BfvParameters {
+    /// Creates parameters with ciphertext moduli of the given sizes in bits.
+    #[napi(constructor)]
+    pub fn new(degree: u32, plaintext_modulus: BigInt, moduli_sizes: Vec<u32>) -> Result<Self> {
+        let (_, plaintext_modulus, lossless) = plaintext_modulus.get_u64();
+        if !lossless {
+            return Err(Error::from_reason("The plaintext modulus exceeds 64 bits"));
+        }
+        let moduli_sizes = moduli_sizes.iter().map(|s| *s as usize).collect::<Vec<_>>();
+        bfv::BfvParametersBuilder::new()
+            .set_degree(degree as usize)
+            .set_plaintext_modulus(plaintext_modulus)
+            .set_moduli_sizes(&moduli_sizes)
+            .build_arc()
+            .map(Self)
+            .map_err(to_napi_err)
+    }
+
+    /// Returns default parameters for tests and prototypes, which are not
+    /// secure.
+    #[napi(factory)]
+    pub fn default(num_moduli: u32, degree: u32) -> Result<Self> {
+        bfv::BfvParametersBuilder::new()
+            .set_degree(degree as usize)
+            .set_plaintext_modulus(1153)
+            .set_moduli_sizes(&vec![62; num_moduli as usize])
+            .set_minimum_security(0)
+            .build_arc()
+            .map(Self)
+            .map_err(to_napi_err)
+    }
+
+    /// The degree of the polynomials.
+    #[napi(getter)]
+    pub fn degree(&self) -> u32 {
+        self.0.degree() as u32
+    }
+
+    /// The plaintext modulus.
+    #[napi(getter)]
+    pub fn plaintext_modulus(&self) -> BigInt {
+        BigInt::from(self.0.plaintext())
+    }
+}
+
+/// Plaintext for the BFV encryption scheme.
+#[napi]
+pub struct Plaintext(bfv::Plaintext);
+
+#[napi]
+impl Plaintext {
+    /// Encodes a vector of integers with the encoding `"poly"` or `"simd"`.
+    #[napi(factory)]
+    pub fn encode(
+        values: BigUint64Array,
+        parameters: &BfvParameters,
+        encoding_name: String,
+        level: Option<u32>,
+    ) -> Result<Self> {
+        let encoding = encoding(&encoding_name, level.unwrap_or_default() as usize)?;
+        bfv::Plaintext::try_encode(&values[..], encoding, &parameters.0)
+            .map(Self)
+            .map_err(to_napi_err)
+    }
+
+    /// Decodes the plaintext with the encoding `"poly"` or `"simd"`.
+    #[napi]
+    pub fn decode(&self, encoding_name: String) -> Result<BigUint64Array> {
+        Vec::<u64>::try_decode(&self.0, encoding(&encoding_name, self.0.level())?)
+            .map(BigUint64Array::new)
+            .map_err(to_napi_err)
+    }
+}
+
+/// Ciphertext for the BFV encryption scheme.
+#[napi]
+pub struct Ciphertext(bfv::Ciphertext);
+
+#[napi]
+impl Ciphertext {
+    /// Deserializes a ciphertext with the given parameters.
+    #[napi(factory)]
+    pub fn from_bytes(bytes: Buffer, parameters: &BfvParameters) -> Result<Self> {
+        bfv::Ciphertext::from_bytes(&bytes, &parameters.0)
+            .map(Self)
+            .map_err(to_napi_err)
+    }
+
+    /// Serializes the ciphertext.
+    #[napi]
+    pub fn to_bytes(&self) -> Buffer {
+        self.0.to_bytes().into()
+    }
+
+    /// The level of the ciphertext.
+    #[napi(getter)]
+    pub fn level(&self) -> u32 {
+        self.0.level() as u32
+    }
+
+    /// Adds two ciphertexts.
+    #[napi]
+    pub fn add(&self, other: &Ciphertext) -> Ciphertext {
+        Ciphertext(&self.0 + &other.0)
+    }
+
+    /// Multiplies the ciphertext by a plaintext.
+    #[napi]
+    pub fn multiply_plain(&self, pt: &Plaintext) -> Ciphertext {
+        Ciphertext(&self.0 * &pt.0)
+    }
+}
+
+/// Secret key for the BFV encryption scheme.
+#[napi]
+pub struct SecretKey(bfv::SecretKey);
+
+#[napi]
+impl SecretKey {
+    /// Generates a random secret key.
+    #[napi(constructor)]
+    pub fn new(parameters: &BfvParameters) -> Self {
+        Self(bfv::SecretKey::random(&parameters.0, &mut entropy::rng()))
+    }
+
+    /// Encrypts a plaintext.
+    #[napi]
+    pub fn encrypt(&self, pt: &Plaintext) -> Result<Ciphertext> {
+        self.0
+            .try_encrypt(&pt.0, &mut entropy::rng())
+            .map(Ciphertext)
+            .map_err(to_napi_err)
+    }
+
+    /// Decrypts a ciphertext.
+    #[napi]
+    pub fn decrypt(&self, ct: &Ciphertext) -> Result<Plaintext> {
+        self.0
+            .try_decrypt(&ct.0)
+            .map(Plaintext)
+            .map_err(to_napi_err)
+    }
+}
+
+/// Public key for the BFV encryption scheme.
+#[napi]
+pub struct PublicKey(bfv::PublicKey);
+
+#[napi]
+impl PublicKey {
+    /// Generates a public key from a secret key.
+    #[napi(constructor)]
+    pub fn new(sk: &SecretKey) -> Self {
+        Self(bfv::PublicKey::new(&sk.0, &mut entropy::rng()))
+    }
+
+    /// Deserializes a public key with the given parameters.
+    #[napi(factory)]
+    pub fn from_bytes(bytes: Buffer, parameters: &BfvParameters) -> Result<Self> {
+        bfv::PublicKey::from_bytes(&bytes, &parameters.0)
+            .map(Self)
+            .map_err(to_napi_err)
+    }
+
+    /// Serializes the public key.
+    #[napi]
+    pub fn to_bytes(&self) -> Buffer {
+        self.0.to_bytes().into()
+    }
+
+    /// Encrypts a plaintext.
+    #[napi]
+    pub fn encrypt(&self, pt: &Plaintext) -> Result<Ciphertext> {
+        self.0
+            .try_encrypt(&pt.0, &mut entropy::rng())
+            .map(Ciphertext)
+            .map_err(to_napi_err)
+    }
+}
@@ -0,0 +1,360 @@
+#![warn(missing_docs, unused_imports)]
+
+//! Python bindings for the BFV encryption scheme of the `fhe` crate.
+//!
+//! The errors of the library are raised as `ValueError`, and the randomness
+//! is drawn from the thread-local cryptographically secure generator.
+
+use std::sync::Arc;
+
+use fhe::{bfv, entropy};
+use fhe_traits::{
+    DeserializeParametrized, FheDecoder, FheDecrypter, FheEncoder, FheEncrypter, Serialize,
+};
+use numpy::{IntoPyArray, PyArray1, PyReadonlyArray1};
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+use pyo3::types::PyBytes;
+
+fn to_py_err(e: fhe::Error) -> PyErr {
+    PyValueError::new_err(e.to_string())
+}
+
+/// Returns the encoding named `name` at level `level`.
+fn encoding(name: &str, level: usize) -> PyResult<bfv::Encoding> {
+    match name {
+        "poly" => Ok(bfv::Encoding::poly_at_level(level)),
+        "simd" => Ok(bfv::Encoding::simd_at_level(level)),
+        _ => Err(PyValueError::new_err(format!(
+            "Unknown encoding {name}, expected \"poly\" or \"simd\""
+        ))),
+    }
+}
+
+/// Parameters for the BFV encryption scheme.
+#[pyclass(module = "fhers")]
+struct BfvParameters(Arc<bfv::BfvParameters>);
+
+#[pymethods]
+impl BfvParameters {
+    /// Creates parameters with ciphertext moduli of the given sizes in bits.
+    #[new]
+    fn new(degree: usize, plaintext_modulus: u64, moduli_sizes: Vec<usize>) -> PyResult<Self> {
+        bfv::BfvParametersBuilder::new()
+            .set_degree(degree)
+            .set_plaintext_modulus(plaintext_modulus)
+            .set_moduli_sizes(&moduli_sizes)
+            .build_arc()
+            .map(Self)
+            .map_err(to_py_err)
+    }
+
+    /// Returns default parameters for tests and prototypes, which are not
+    /// secure.
+    #[staticmethod]
+    fn default(num_moduli: usize, degree: usize) -> Self {
+        Self(bfv::BfvParameters::default_arc(num_moduli, degree))
+    }
+
+    /// The degree of the polynomials.
+    #[getter]
+    fn degree(&self) -> usize {
+        self.0.degree()
+    }
+
+    /// The plaintext modulus.
+    #[getter]
+    fn plaintext_modulus(&self) -> u64 {
+        self.0.plaintext()
+    }
+
+    /// The ciphertext moduli.
+    #[getter]
+    fn moduli(&self) -> Vec<u64> {
+        self.0.moduli().to_vec()
+    }
+
+    /// The maximum level of the ciphertexts.
+    #[getter]
+    fn max_level(&self) -> usize {
+        self.0.max_level()
+    }
+}
+
+/// Plaintext for the BFV encryption scheme.
+#[pyclass(module = "fhers")]
+struct Plaintext(bfv::Plaintext);
+
+#[pymethods]
+impl Plaintext {
+    /// Encodes a vector of unsigned integers with the encoding `"poly"` or
+    /// `"simd"`.
+    #[staticmethod]
+    #[pyo3(signature = (values, parameters, encoding_name = "simd", level = 0))]
+    fn encode(
+        values: PyReadonlyArray1<'_, u64>,
+        parameters: &BfvParameters,
+        encoding_name: &str,
+        level: usize,
+    ) -> PyResult<Self> {
+        bfv::Plaintext::try_encode(
+            values.as_slice()?,
+            encoding(encoding_name, level)?,
+            &parameters.0,
+        )
+        .map(Self)
+        .map_err(to_py_err)
+    }
+
+    /// Encodes a vector of signed integers with the encoding `"poly"` or
+    /// `"simd"`.
+    #[staticmethod]
+    #[pyo3(signature = (values, parameters, encoding_name = "simd", level = 0))]
+    fn encode_signed(
+        values: PyReadonlyArray1<'_, i64>,
+        parameters: &BfvParameters,
+        encoding_name: &str,
+        level: usize,
+    ) -> PyResult<Self> {
+        bfv::Plaintext::try_encode(
+            values.as_slice()?,
+            encoding(encoding_name, level)?,
+            &parameters.0,
+        )
+        .map(Self)
+        .map_err(to_py_err)
+    }
+
+    /// Decodes the plaintext as a vector of unsigned integers.
+    #[pyo3(signature = (encoding_name = "simd"))]
+    fn decode<'py>(&self, py: Python<'py>, encoding_name: &str) -> PyResult<&'py PyArray1<u64>> {
+        Vec::<u64>::try_decode(&self.0, encoding(encoding_name, self.0.level())?)
+            .map(|v| v.into_pyarray(py))
+            .map_err(to_py_err)
+    }
+
+    /// Decodes the plaintext as a vector of signed integers.
+    #[pyo3(signature = (encoding_name = "simd"))]
+    fn decode_signed<'py>(
+        &self,
+        py: Python<'py>,
+        encoding_name: &str,
+    ) -> PyResult<&'py PyArray1<i64>> {
+        Vec::<i64>::try_decode(&self.0, encoding(encoding_name, self.0.level())?)
+            .map(|v| v.into_pyarray(py))
+            .map_err(to_py_err)
+    }
+}
+
+/// Ciphertext for the BFV encryption scheme.
+#[pyclass(module = "fhers")]
+struct Ciphertext(bfv::Ciphertext);
+
+#[pymethods]
+impl Ciphertext {
+    /// Deserializes a ciphertext with the given parameters.
+    #[staticmethod]
+    fn from_bytes(bytes: &[u8], parameters: &BfvParameters) -> PyResult<Self> {
+        bfv::Ciphertext::from_bytes(bytes, &parameters.0)
+            .map(Self)
+            .map_err(to_py_err)
+    }
+
+    /// Serializes the ciphertext.
+    fn to_bytes<'py>(&self, py: Python<'py>) -> &'py PyBytes {
+        PyBytes::new(py, &self.0.to_bytes())
+    }
+
+    /// The level of the ciphertext.
+    #[getter]
+    fn level(&self) -> usize {
+        self.0.level()
+    }
+
+    /// Multiplies the ciphertext by a plaintext.
+    fn multiply_plain(&self, pt: &Plaintext) -> Self {
+        Self(&self.0 * &pt.0)
+    }
+
+    fn __add__(&self, other: &Self) -> Self {
+        Self(&self.0 + &other.0)
+    }
+
+    fn __sub__(&self, other: &Self) -> Self {
+        Self(&self.0 - &other.0)
+    }
+
+    fn __mul__(&self, other: &Self) -> Self {
+        Self(&self.0 * &other.0)
+    }
+
+    fn __neg__(&self) -> Self {
+        Self(-&self.0)
+    }
+}
+
+/// Secret key for the BFV encryption scheme.
+#[pyclass(module = "fhers")]
+struct SecretKey(bfv::SecretKey);
+
+#[pymethods]
+impl SecretKey {
+    /// Generates a random secret key.
+    #[new]
+    fn new(parameters: &BfvParameters) -> Self {
+        Self(bfv::SecretKey::random(&parameters.0, &mut entropy::rng()))
+    }
+
+    /// Encrypts a plaintext.
+    fn encrypt(&self, pt: &Plaintext) -> PyResult<Ciphertext> {
+        self.0
+            .try_encrypt(&pt.0, &mut entropy::rng())
+            .map(Ciphertext)
+            .map_err(to_py_err)
+    }
+
+    /// Decrypts a ciphertext.
+    fn decrypt(&self, ct: &Ciphertext) -> PyResult<Plaintext> {
+        self.0.try_decrypt(&ct.0).map(Plaintext).map_err(to_py_err)
+    }
+}
+
+/// Public key for the BFV encryption scheme.
+#[pyclass(module = "fhers")]
+struct PublicKey(bfv::PublicKey);
+
+#[pymethods]
+impl PublicKey {
+    /// Generates a public key from a secret key.
+    #[new]
+    fn new(sk: &SecretKey) -> Self {
+        Self(bfv::PublicKey::new(&sk.0, &mut entropy::rng()))
+    }
+
+    /// Deserializes a public key with the given parameters.
+    #[staticmethod]
+    fn from_bytes(bytes: &[u8], parameters: &BfvParameters) -> PyResult<Self> {
+        bfv::PublicKey::from_bytes(bytes, &parameters.0)
+            .map(Self)
+            .map_err(to_py_err)
+    }
+
+    /// Serializes the public key.
+    fn to_bytes<'py>(&self, py: Python<'py>) -> &'py PyBytes {
+        PyBytes::new(py, &self.0.to_bytes())
+    }
+
+    /// Encrypts a plaintext.
+    fn encrypt(&self, pt: &Plaintext) -> PyResult<Ciphertext> {
+        self.0
+            .try_encrypt(&pt.0, &mut entropy::rng())
+            .map(Ciphertext)
+            .map_err(to_py_err)
+    }
+}
+
+/// Relinearization key for the BFV encryption scheme.
+#[pyclass(module = "fhers")]
+struct RelinearizationKey(bfv::RelinearizationKey);
+
+#[pymethods]
+impl RelinearizationKey {
+    /// Generates a relinearization key from a secret key.
+    #[new]
+    fn new(sk: &SecretKey) -> PyResult<Self> {
+        bfv::RelinearizationKey::new(&sk.0, &mut entropy::rng())
+            .map(Self)
+            .map_err(to_py_err)
+    }
+
+    /// Relinearizes a ciphertext of three polynomials.
+    fn relinearize(&self, ct: &Ciphertext) -> PyResult<Ciphertext> {
+        let mut ct = ct.0.clone();
+        self.0.relinearizes(&mut ct).map_err(to_py_err)?;
+        Ok(Ciphertext(ct))
+    }
+}
+
+/// Evaluation key for the BFV encryption scheme.
+#[pyclass(module = "fhers")]
+struct EvaluationKey(bfv::EvaluationKey);
+
+#[pymethods]
+impl EvaluationKey {
+    /// Generates an evaluation key from a secret key, enabling the inner sum,
+    /// the rotation of the rows, and the rotations of the columns by the
+    /// given amounts.
+    #[new]
+    #[pyo3(signature = (sk, inner_sum = false, row_rotation = false, column_rotations = vec![]))]
+    fn new(
+        sk: &SecretKey,
+        inner_sum: bool,
+        row_rotation: bool,
+        column_rotations: Vec<usize>,
+    ) -> PyResult<Self> {
+        let mut builder = bfv::EvaluationKeyBuilder::new(&sk.0).map_err(to_py_err)?;
+        if inner_sum {
+            builder.enable_inner_sum().map_err(to_py_err)?;
+        }
+        if row_rotation {
+            builder.enable_row_rotation().map_err(to_py_err)?;
+        }
+        for i in column_rotations {
+            builder.enable_column_rotation(i).map_err(to_py_err)?;
+        }
+        builder
+            .build(&mut entropy::rng())
+            .map(Self)
+            .map_err(to_py_err)
+    }
+
+    /// Deserializes an evaluation key with the given parameters.
+    #[staticmethod]
+    fn from_bytes(bytes: &[u8], parameters: &BfvParameters) -> PyResult<Self> {
+        bfv::EvaluationKey::from_bytes(bytes, &parameters.0)
+            .map(Self)
+            .map_err(to_py_err)
+    }
+
+    /// Serializes the evaluation key.
+    fn to_bytes<'py>(&self, py: Python<'py>) -> &'py PyBytes {
+        PyBytes::new(py, &self.0.to_bytes())
+    }
+
+    /// Computes the sum of the slots, replicated in every slot.
+    fn inner_sum(&self, ct: &Ciphertext) -> PyResult<Ciphertext> {
+        self.0
+            .computes_inner_sum(&ct.0)
+            .map(Ciphertext)
+            .map_err(to_py_err)
+    }
+
+    /// Swaps the two rows of slots.
+    fn rotate_rows(&self, ct: &Ciphertext) -> PyResult<Ciphertext> {
+        self.0
+            .rotates_rows(&ct.0)
+            .map(Ciphertext)
+            .map_err(to_py_err)
+    }
+
+    /// Rotates the columns of slots by `i`.
+    fn rotate_columns(&self, ct: &Ciphertext, i: usize) -> PyResult<Ciphertext> {
+        self.0
+            .rotates_columns_by(&ct.0, i)
+            .map(Ciphertext)
+            .map_err(to_py_err)
+    }
+}
+
+/// Python bindings for the BFV encryption scheme of the `fhe` crate.
+#[pymodule]
+fn fhers(_py: Python<'_>, m: &PyModule) -> PyResult<()> {
+    m.add_class::<BfvParameters>()?;
+    m.add_class::<Plaintext>()?;
+    m.add_class::<Ciphertext>()?;
+    m.add_class::<SecretKey>()?;
+    m.add_class::<PublicKey>()?;
+    m.add_class::<RelinearizationKey>()?;
+    m.add_class::<EvaluationKey>()?;
+    Ok(())
+}
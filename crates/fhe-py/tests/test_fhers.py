@@ -0,0 +1,47 @@
+import unittest
+
+import numpy as np
+
+import fhers
+
+
+class TestFhers(unittest.TestCase):
+    def setUp(self):
+        self.params = fhers.BfvParameters.default(2, 16)
+        self.sk = fhers.SecretKey(self.params)
+        self.t = self.params.plaintext_modulus
+
+    def encrypt(self, values):
+        pt = fhers.Plaintext.encode(np.array(values, dtype=np.uint64), self.params)
+        return fhers.PublicKey(self.sk).encrypt(pt)
+
+    def decrypt(self, ct):
+        return self.sk.decrypt(ct).decode()
+
+    def test_arithmetic(self):
+        a = np.arange(16, dtype=np.uint64)
+        b = np.arange(16, 32, dtype=np.uint64)
+        ct_a, ct_b = self.encrypt(a), self.encrypt(b)
+        rk = fhers.RelinearizationKey(self.sk)
+        np.testing.assert_array_equal(self.decrypt(ct_a + ct_b), (a + b) % self.t)
+        np.testing.assert_array_equal(
+            self.decrypt(rk.relinearize(ct_a * ct_b)), (a * b) % self.t
+        )
+
+    def test_evaluation_key(self):
+        a = np.arange(16, dtype=np.uint64)
+        ek = fhers.EvaluationKey(self.sk, inner_sum=True, column_rotations=[1])
+        total = self.decrypt(ek.inner_sum(self.encrypt(a)))
+        np.testing.assert_array_equal(total, np.full(16, a.sum() % self.t))
+        ek = fhers.EvaluationKey.from_bytes(ek.to_bytes(), self.params)
+        self.decrypt(ek.rotate_columns(self.encrypt(a), 1))
+
+    def test_errors(self):
+        with self.assertRaises(ValueError):
+            fhers.Plaintext.encode(np.zeros(17, dtype=np.uint64), self.params)
+        with self.assertRaises(ValueError):
+            fhers.Ciphertext.from_bytes(b"\x01", self.params)
+
+
+if __name__ == "__main__":
+    unittest.main()
@@ -0,0 +1,98 @@
+//! Asynchronous variants of the encryption and decryption traits, for keys
+//! held by a remote service such as a key management service, a hardware
+//! security module or a threshold-decryption network.
+
+use std::{future::Future, pin::Pin};
+
+use rand::rngs::OsRng;
+
+use crate::{FheCiphertext, FheDecrypter, FheEncrypter, FheParametrized, FhePlaintext};
+
+/// A boxed future which can be sent across threads.
+pub type BoxFuture<'a, T> = Pin<Box<dyn Future<Output = T> + Send + 'a>>;
+
+/// Encrypt a plaintext into a ciphertext asynchronously.
+///
+/// The implementor is responsible for the randomness of the encryption.
+pub trait AsyncFheEncrypter<
+    P: FhePlaintext<Parameters = Self::Parameters>,
+    C: FheCiphertext<Parameters = Self::Parameters>,
+>: FheParametrized
+{
+    /// The type of error returned.
+    type Error;
+
+    /// Try to encrypt an [`FhePlaintext`] into an [`FheCiphertext`].
+    fn try_encrypt_async<'a>(&'a self, pt: &'a P) -> BoxFuture<'a, Result<C, Self::Error>>;
+}
+
+/// Decrypt a ciphertext into a plaintext asynchronously.
+pub trait AsyncFheDecrypter<
+    P: FhePlaintext<Parameters = Self::Parameters>,
+    C: FheCiphertext<Parameters = Self::Parameters>,
+>: FheParametrized
+{
+    /// The type of error returned.
+    type Error;
+
+    /// Try to decrypt an [`FheCiphertext`] into an [`FhePlaintext`].
+    fn try_decrypt_async<'a>(&'a self, ct: &'a C) -> BoxFuture<'a, Result<P, Self::Error>>;
+}
+
+/// Adapter implementing the asynchronous traits for a local implementation of
+/// [`FheEncrypter`] or [`FheDecrypter`], so that the same code can use local
+/// and remote keys.
+///
+/// The operations run to completion when called, and return ready futures.
+/// The encryption draws its randomness from [`OsRng`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AsyncAdapter<T>(T);
+
+impl<T> AsyncAdapter<T> {
+    /// Wraps a local implementation.
+    pub const fn new(inner: T) -> Self {
+        Self(inner)
+    }
+
+    /// Returns a reference to the local implementation.
+    pub const fn inner(&self) -> &T {
+        &self.0
+    }
+
+    /// Returns the local implementation.
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl<T: FheParametrized> FheParametrized for AsyncAdapter<T> {
+    type Parameters = T::Parameters;
+}
+
+impl<P, C, T> AsyncFheEncrypter<P, C> for AsyncAdapter<T>
+where
+    P: FhePlaintext<Parameters = T::Parameters>,
+    C: FheCiphertext<Parameters = T::Parameters> + Send + 'static,
+    T: FheEncrypter<P, C>,
+    T::Error: Send + 'static,
+{
+    type Error = T::Error;
+
+    fn try_encrypt_async<'a>(&'a self, pt: &'a P) -> BoxFuture<'a, Result<C, Self::Error>> {
+        Box::pin(std::future::ready(self.0.try_encrypt(pt, &mut OsRng)))
+    }
+}
+
+impl<P, C, T> AsyncFheDecrypter<P, C> for AsyncAdapter<T>
+where
+    P: FhePlaintext<Parameters = T::Parameters> + Send + 'static,
+    C: FheCiphertext<Parameters = T::Parameters>,
+    T: FheDecrypter<P, C>,
+    T::Error: Send + 'static,
+{
+    type Error = T::Error;
+
+    fn try_decrypt_async<'a>(&'a self, ct: &'a C) -> BoxFuture<'a, Result<P, Self::Error>> {
+        Box::pin(std::future::ready(self.0.try_decrypt(ct)))
+    }
+}
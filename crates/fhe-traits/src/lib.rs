@@ -8,6 +8,11 @@ use std::sync::Arc;
 
 use rand::{CryptoRng, RngCore};
 
+#[cfg(feature = "async")]
+mod asynchronous;
+#[cfg(feature = "async")]
+pub use asynchronous::{AsyncAdapter, AsyncFheDecrypter, AsyncFheEncrypter, BoxFuture};
+
 /// The homomorphic encryption parameters.
 pub trait FheParameters {}
 
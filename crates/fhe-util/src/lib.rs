@@ -7,7 +7,7 @@
 #[cfg(test)]
 extern crate proptest;
 
-use rand::{CryptoRng, RngCore};
+use rand::{CryptoRng, Rng, RngCore};
 
 use num_bigint_dig::{prime::probably_prime, BigUint, ModInverse};
 use num_traits::{cast::ToPrimitive, PrimInt};
@@ -30,43 +30,136 @@ pub fn is_prime(p: u64) -> bool {
     probably_prime(&BigUint::from(p), 0)
 }
 
+/// The largest variance supported by [`sample_vec_cbd`].
+pub const MAX_CBD_VARIANCE: usize = 1 << 16;
+
 /// Sample a vector of independent centered binomial distributions of a given
-/// variance. Returns an error if the variance is strictly larger than 16.
+/// variance. Returns an error if the variance is not between 1 and
+/// [`MAX_CBD_VARIANCE`].
+///
+/// Each sample is the difference of the number of ones in two sequences of
+/// `2 * variance` random bits. The sampling runs in constant time: the
+/// random bits are only combined with masks and branch-free population
+/// counts, and the sequence of operations only depends on `vector_size` and
+/// `variance`. It must be used for all secret and error polynomials.
 pub fn sample_vec_cbd<R: RngCore + CryptoRng>(
     vector_size: usize,
     variance: usize,
     rng: &mut R,
 ) -> Result<Vec<i64>, &'static str> {
-    if !(1..=16).contains(&variance) {
-        return Err("The variance should be between 1 and 16");
+    if !(1..=MAX_CBD_VARIANCE).contains(&variance) {
+        return Err("The variance should be between 1 and 65536");
     }
 
-    let mut out = Vec::with_capacity(vector_size);
+    let mut pool = BitPool {
+        rng,
+        bits: 0,
+        size: 0,
+    };
+    let mut count_ones = |mut nbits: usize| {
+        let mut count = 0u64;
+        while nbits > 0 {
+            let n = nbits.min(64);
+            count += popcount(pool.take(n));
+            nbits -= n;
+        }
+        count as i64
+    };
 
-    let number_bits = 4 * variance;
-    let mask_add = ((u64::MAX >> (64 - number_bits)) >> (2 * variance)) as u128;
-    let mask_sub = mask_add << (2 * variance);
+    let out = (0..vector_size)
+        .map(|_| {
+            let positive = count_ones(2 * variance);
+            positive - count_ones(2 * variance)
+        })
+        .collect();
+    Ok(out)
+}
 
-    let mut current_pool = 0u128;
-    let mut current_pool_nbits = 0;
+/// Sample a vector of independent rounded Gaussian distributions of standard
+/// deviation `sigma`, truncated to `[-6 * sigma, 6 * sigma]`. Returns an
+/// error if `sigma` is not a positive finite number.
+///
+/// The samples are generated with the Box-Muller transform and a rejection of
+/// the tail; contrary to [`sample_vec_cbd`], this sampling does not run in
+/// constant time.
+pub fn sample_vec_gaussian<R: RngCore + CryptoRng>(
+    vector_size: usize,
+    sigma: f64,
+    rng: &mut R,
+) -> Result<Vec<i64>, &'static str> {
+    if !sigma.is_finite() || sigma <= 0.0 {
+        return Err("The standard deviation should be a positive number");
+    }
 
-    for _ in 0..vector_size {
-        if current_pool_nbits < number_bits {
-            current_pool |= (rng.next_u64() as u128) << current_pool_nbits;
-            current_pool_nbits += 64;
+    let bound = (6.0 * sigma).floor();
+    let mut sample = || loop {
+        // u1 is in (0, 1] so that its logarithm is finite.
+        let u1 = 1.0 - rng.gen::<f64>();
+        let u2 = rng.gen::<f64>();
+        let z = (-2.0 * u1.ln()).sqrt() * (2.0 * std::f64::consts::PI * u2).cos();
+        let x = (z * sigma).round();
+        if x.abs() <= bound {
+            return x as i64;
         }
-        debug_assert!(current_pool_nbits >= number_bits);
-        out.push(
-            ((current_pool & mask_add).count_ones() as i64)
-                - ((current_pool & mask_sub).count_ones() as i64),
-        );
-        current_pool >>= number_bits;
-        current_pool_nbits -= number_bits;
+    };
+    Ok((0..vector_size).map(|_| sample()).collect())
+}
+
+/// Sample a ternary vector with exactly `hamming_weight` non-zero
+/// coefficients, at uniformly random positions and with uniformly random
+/// signs. Returns an error if `hamming_weight` is larger than `vector_size`.
+///
+/// This sampling does not run in constant time.
+pub fn sample_vec_ternary<R: RngCore + CryptoRng>(
+    vector_size: usize,
+    hamming_weight: usize,
+    rng: &mut R,
+) -> Result<Vec<i64>, &'static str> {
+    if hamming_weight > vector_size {
+        return Err("The Hamming weight should be at most the vector size");
     }
 
+    // Partial Fisher-Yates shuffle of the positions.
+    let mut positions = (0..vector_size).collect::<Vec<_>>();
+    let mut out = vec![0i64; vector_size];
+    for i in 0..hamming_weight {
+        let j = rng.gen_range(i..vector_size);
+        positions.swap(i, j);
+        out[positions[i]] = if rng.gen::<bool>() { 1 } else { -1 };
+    }
     Ok(out)
 }
 
+/// A buffer of random bits.
+struct BitPool<'a, R: RngCore> {
+    rng: &'a mut R,
+    bits: u128,
+    size: usize,
+}
+
+impl<R: RngCore> BitPool<'_, R> {
+    /// Returns the next `n` random bits, for `1 <= n <= 64`.
+    fn take(&mut self, n: usize) -> u64 {
+        debug_assert!((1..=64).contains(&n));
+        if self.size < n {
+            self.bits |= (self.rng.next_u64() as u128) << self.size;
+            self.size += 64;
+        }
+        let bits = (self.bits as u64) & (u64::MAX >> (64 - n));
+        self.bits >>= n;
+        self.size -= n;
+        bits
+    }
+}
+
+/// Counts the number of ones of `x` without branches nor table lookups.
+const fn popcount(x: u64) -> u64 {
+    let x = x - ((x >> 1) & 0x5555555555555555);
+    let x = (x & 0x3333333333333333) + ((x >> 2) & 0x3333333333333333);
+    let x = (x + (x >> 4)) & 0x0f0f0f0f0f0f0f0f;
+    x.wrapping_mul(0x0101010101010101) >> 56
+}
+
 /// Transcodes a vector of u64 of `nbits`-bit numbers into a vector of bytes.
 pub fn transcode_to_bytes(a: &[u64], nbits: usize) -> Vec<u8> {
     assert!(0 < nbits && nbits <= 64);
@@ -199,8 +292,8 @@ mod tests {
     use crate::variance;
 
     use super::{
-        inverse, is_prime, sample_vec_cbd, transcode_bidirectional, transcode_from_bytes,
-        transcode_to_bytes,
+        inverse, is_prime, popcount, sample_vec_cbd, sample_vec_gaussian, sample_vec_ternary,
+        transcode_bidirectional, transcode_from_bytes, transcode_to_bytes, MAX_CBD_VARIANCE,
     };
 
     #[test]
@@ -223,9 +316,9 @@ mod tests {
     #[test]
     fn sample_cbd() {
         assert!(sample_vec_cbd(10, 0, &mut thread_rng()).is_err());
-        assert!(sample_vec_cbd(10, 17, &mut thread_rng()).is_err());
+        assert!(sample_vec_cbd(10, MAX_CBD_VARIANCE + 1, &mut thread_rng()).is_err());
 
-        for var in 1..=16 {
+        for var in [1, 2, 10, 16, 17, 31, 32, 33, 100, 1000] {
             for size in 0..=100 {
                 let v = sample_vec_cbd(size, var, &mut thread_rng()).unwrap();
                 assert_eq!(v.len(), size);
@@ -235,10 +328,49 @@ mod tests {
             let v = sample_vec_cbd(100000, var, &mut thread_rng()).unwrap();
             assert!(v.iter().map(|vi| vi.abs()).max().unwrap() <= 2 * var as i64);
 
-            // Verifies that the variance is correct. We could probably refine the bound
-            // but for now, we will just check that the rounded value is equal to the
-            // variance.
-            assert!(variance(&v).round() == (var as f64));
+            // Verifies that the variance is correct, up to 5%.
+            assert!((variance(&v) / var as f64 - 1.0).abs() < 0.05);
+        }
+
+        let mut rng = thread_rng();
+        for x in [0, 1, u64::MAX, 0x8000000000000001] {
+            assert_eq!(popcount(x), x.count_ones() as u64);
+        }
+        for _ in 0..1000 {
+            let x = rng.next_u64();
+            assert_eq!(popcount(x), x.count_ones() as u64);
+        }
+    }
+
+    #[test]
+    fn sample_gaussian() {
+        for sigma in [0.0, -1.0, f64::NAN, f64::INFINITY] {
+            assert!(sample_vec_gaussian(10, sigma, &mut thread_rng()).is_err());
+        }
+
+        for sigma in [0.5, 3.2, 10.0, 100.0] {
+            let v = sample_vec_gaussian(100000, sigma, &mut thread_rng()).unwrap();
+            assert_eq!(v.len(), 100000);
+            assert!(v.iter().map(|vi| vi.abs()).max().unwrap() as f64 <= 6.0 * sigma);
+
+            // Verifies that the variance is correct, up to 5%, accounting for the
+            // rounding.
+            let expected = sigma * sigma + 1.0 / 12.0;
+            assert!((variance(&v) / expected - 1.0).abs() < 0.05);
+        }
+    }
+
+    #[test]
+    fn sample_ternary() {
+        assert!(sample_vec_ternary(10, 11, &mut thread_rng()).is_err());
+
+        for size in [0, 1, 16, 1024] {
+            for h in [0, size / 2, size] {
+                let v = sample_vec_ternary(size, h, &mut thread_rng()).unwrap();
+                assert_eq!(v.len(), size);
+                assert_eq!(v.iter().filter(|vi| **vi != 0).count(), h);
+                assert!(v.iter().all(|vi| vi.abs() <= 1));
+            }
         }
     }
 
@@ -2,6 +2,9 @@ use std::io::Result;
 
 fn main() -> Result<()> {
     // Generate the proto files.
-    // prost_build::compile_protos(&["src/proto/bfv.proto"], &["src/proto"])?;
+    // prost_build::compile_protos(&["src/proto/bfv.proto", "src/proto/service.proto"], &["src/proto"])?;
+    // The server and client of the service are generated with
+    // tonic_build::configure().compile(&["src/proto/service.proto"], &["src/proto"])?,
+    // and gated behind the `grpc` feature.
     Ok(())
 }
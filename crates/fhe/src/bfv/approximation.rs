@@ -0,0 +1,200 @@
+//! Polynomial interpolation of functions over the plaintext space.
+//!
+//! Every function from `Z_t` to `Z_t`, for a prime plaintext modulus `t`, is a
+//! polynomial of degree at most `t - 1`. These helpers compute the
+//! coefficients of such polynomials, in increasing degree, ready for
+//! [`Multiplicator::evaluate_polynomial`](crate::bfv::Multiplicator::evaluate_polynomial).
+//! When the inputs are known to lie in a range `[a, b)`, interpolating the
+//! function on this range only yields a polynomial of degree `b - a - 1`,
+//! which is much cheaper to evaluate. The cost of the evaluation is estimated
+//! by [`estimate_cost`].
+
+use std::ops::Range;
+
+use crate::bfv::{noise, BfvParameters};
+use crate::{Error, Result};
+
+/// The cost of evaluating a polynomial with
+/// [`Multiplicator::evaluate_polynomial`](crate::bfv::Multiplicator::evaluate_polynomial).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PolynomialCost {
+    /// The degree of the polynomial.
+    pub degree: usize,
+    /// The multiplicative depth of the evaluation.
+    pub depth: usize,
+    /// The number of ciphertext multiplications.
+    pub multiplications: usize,
+    /// The heuristic estimate of the noise of the result, in bits, for an
+    /// input freshly encrypted with the public key.
+    pub noise: f64,
+    /// The noise capacity of the ciphertexts at level 0, in bits.
+    pub capacity: f64,
+}
+
+impl PolynomialCost {
+    /// Returns whether the result is expected to decrypt correctly.
+    pub fn fits(&self) -> bool {
+        self.noise < self.capacity
+    }
+}
+
+/// Returns the coefficients of the polynomial of degree at most `t - 1` equal
+/// to `f` on `Z_t`.
+///
+/// Returns an error if the plaintext modulus is not prime. The interpolation
+/// takes `O(t^2)` operations.
+pub fn interpolate<F>(par: &BfvParameters, f: F) -> Result<Vec<u64>>
+where
+    F: Fn(u64) -> u64,
+{
+    interpolate_range(par, 0..par.plaintext(), f)
+}
+
+/// Returns the coefficients of the polynomial of degree at most
+/// `range.len() - 1` equal to `f` on `range`. The values of `f` are reduced
+/// modulo the plaintext modulus, and the polynomial has arbitrary values
+/// outside of `range`.
+///
+/// Returns an error if the plaintext modulus is not prime, or if the range is
+/// empty or not included in `[0, t)`.
+pub fn interpolate_range<F>(par: &BfvParameters, range: Range<u64>, f: F) -> Result<Vec<u64>>
+where
+    F: Fn(u64) -> u64,
+{
+    let t = &par.plaintext;
+    if range.is_empty() || range.end > t.modulus() {
+        return Err(Error::UnspecifiedInput(format!(
+            "The range must be a non-empty subrange of [0, {})",
+            t.modulus()
+        )));
+    }
+    let inverses = t
+        .inv_vec(&(1..range.end - range.start).collect::<Vec<_>>())
+        .ok_or(Error::UnsupportedOperation(
+            "Interpolation requires a prime plaintext modulus".to_string(),
+        ))?;
+
+    // Divided differences of the consecutive points, whose differences of
+    // index j are equal to j.
+    let mut newton = range.clone().map(|x| t.reduce(f(x))).collect::<Vec<_>>();
+    for (j, inverse) in (1..newton.len()).zip(&inverses) {
+        for i in (j..newton.len()).rev() {
+            newton[i] = t.mul(t.sub(newton[i], newton[i - 1]), *inverse);
+        }
+    }
+
+    // Conversion from the Newton basis, prod_{i < j} (x - range.start - i), to
+    // the monomial basis with Horner's method.
+    let mut coefficients = vec![*newton.last().unwrap()];
+    for (j, c) in newton.iter().enumerate().rev().skip(1) {
+        let root = t.reduce(range.start + j as u64);
+        coefficients.insert(0, 0);
+        for k in 0..coefficients.len() - 1 {
+            let product = t.mul(coefficients[k + 1], root);
+            coefficients[k] = t.sub(coefficients[k], product);
+        }
+        coefficients[0] = t.add(coefficients[0], *c);
+    }
+
+    while coefficients.len() > 2 && coefficients.last() == Some(&0) {
+        coefficients.pop();
+    }
+    coefficients.resize(coefficients.len().max(2), 0);
+    Ok(coefficients)
+}
+
+/// Estimates the cost of evaluating the polynomial with coefficients
+/// `coefficients`, in increasing degree, on a ciphertext freshly encrypted
+/// with the public key.
+pub fn estimate_cost(par: &BfvParameters, coefficients: &[u64]) -> PolynomialCost {
+    let degree = coefficients.len().max(2) - 1;
+
+    // The powers are computed as in `evaluate_polynomial`.
+    let mut powers = vec![Some(noise::fresh_public_key(par))];
+    for k in 2..=degree {
+        let power = noise::mul(par, powers[k / 2 - 1], powers[k - k / 2 - 1]);
+        powers.push(power);
+    }
+    let estimate = powers
+        .iter()
+        .zip(&coefficients[1..])
+        .map(|(power, c)| noise::mul_scalar(par, *power, *c))
+        .reduce(noise::add)
+        .flatten();
+
+    PolynomialCost {
+        degree,
+        depth: degree.next_power_of_two().ilog2() as usize,
+        multiplications: degree - 1,
+        noise: noise::add_plaintext(estimate).unwrap_or_default(),
+        capacity: noise::capacity(par, 0),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{estimate_cost, interpolate, interpolate_range};
+    use crate::bfv::{
+        BfvParameters, BfvParametersBuilder, Encoding, Multiplicator, Plaintext, PublicKey,
+        RelinearizationKey, SecretKey,
+    };
+    use fhe_traits::{FheDecoder, FheDecrypter, FheEncoder, FheEncrypter};
+    use rand::thread_rng;
+    use std::error::Error;
+
+    #[test]
+    fn interpolation() -> Result<(), Box<dyn Error>> {
+        let mut rng = thread_rng();
+        let par = BfvParameters::default_arc(3, 16);
+        let t = par.plaintext.clone();
+        let evaluate = |coefficients: &[u64], x: u64| {
+            coefficients
+                .iter()
+                .rev()
+                .fold(0, |acc, c| t.add(t.mul(acc, x), *c))
+        };
+
+        let f = |x: u64| (x * x) % 7 + 3;
+        let coefficients = interpolate(&par, f)?;
+        assert!(coefficients.len() <= t.modulus() as usize);
+        assert!((0..t.modulus()).all(|x| evaluate(&coefficients, x) == f(x)));
+
+        // A polynomial of degree 2 is recovered exactly.
+        let square = interpolate_range(&par, 100..120, |x| t.mul(x, x))?;
+        assert_eq!(square, [0, 0, 1]);
+        assert_eq!(interpolate_range(&par, 5..6, |_| 42)?, [42, 0]);
+
+        let range = 3..11;
+        let coefficients = interpolate_range(&par, range.clone(), f)?;
+        assert_eq!(coefficients.len(), 8);
+        assert!(range.clone().all(|x| evaluate(&coefficients, x) == f(x)));
+
+        let cost = estimate_cost(&par, &coefficients);
+        assert_eq!((cost.degree, cost.depth, cost.multiplications), (7, 3, 6));
+        assert!(cost.fits());
+
+        let sk = SecretKey::random(&par, &mut rng);
+        let pk = PublicKey::new(&sk, &mut rng);
+        let rk = RelinearizationKey::new(&sk, &mut rng)?;
+        let v = (0..par.degree() as u64)
+            .map(|i| 3 + i % 8)
+            .collect::<Vec<_>>();
+        let pt = Plaintext::try_encode(&v, Encoding::simd(), &par)?;
+        let ct = pk.try_encrypt(&pt, &mut rng)?;
+        let ct = Multiplicator::default(&rk)?.evaluate_polynomial(&ct, &coefficients)?;
+        let pt = sk.try_decrypt(&ct)?;
+        let expected = v.iter().map(|x| f(*x)).collect::<Vec<_>>();
+        assert_eq!(Vec::<u64>::try_decode(&pt, Encoding::simd())?, expected);
+
+        assert!(interpolate_range(&par, 3..3, f).is_err());
+        assert!(interpolate_range(&par, 0..t.modulus() + 1, f).is_err());
+        let par = BfvParametersBuilder::new()
+            .set_degree(16)
+            .set_plaintext_modulus(1024)
+            .set_moduli_sizes(&[62])
+            .set_minimum_security(0)
+            .build()?;
+        assert!(interpolate_range(&par, 0..4, f).is_err());
+        Ok(())
+    }
+}
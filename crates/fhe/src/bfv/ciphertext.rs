@@ -1,19 +1,23 @@
 //! Ciphertext type in the BFV encryption scheme.
 
-use crate::bfv::{parameters::BfvParameters, traits::TryConvertFrom};
-use crate::proto::bfv::Ciphertext as CiphertextProto;
+use crate::bfv::integrity::{self, TAG_SIZE};
+use crate::bfv::{noise, parameters::BfvParameters, traits::TryConvertFrom, Encoding, Plaintext};
+use crate::proto::{bfv::Ciphertext as CiphertextProto, check_version, FORMAT_VERSION};
 use crate::{Error, Result};
-use fhe_math::rq::{Poly, Representation};
+use fhe_math::rq::{traits::TryConvertFrom as TryConvertFromPoly, Poly, Representation};
 use fhe_traits::{
-    DeserializeParametrized, DeserializeWithContext, FheCiphertext, FheParametrized, Serialize,
+    DeserializeParametrized, DeserializeWithContext, FheCiphertext, FheEncoder, FheParametrized,
+    Serialize,
 };
+use fhe_util::inverse;
+use num_bigint::BigUint;
 use prost::Message;
-use rand::SeedableRng;
+use rand::{CryptoRng, RngCore, SeedableRng};
 use rand_chacha::ChaCha8Rng;
 use std::sync::Arc;
 
 /// A ciphertext encrypting a plaintext.
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone)]
 pub struct Ciphertext {
     /// The parameters of the underlying BFV encryption scheme.
     pub(crate) par: Arc<BfvParameters>,
@@ -26,11 +30,43 @@ pub struct Ciphertext {
 
     /// The ciphertext level
     pub(crate) level: usize,
+
+    /// Heuristic estimate of the noise in bits, if known.
+    pub(crate) noise: Option<f64>,
+
+    /// The metadata attached by the application, if any.
+    pub(crate) metadata: Option<Metadata>,
+
+    /// The identifier of the key under which the ciphertext is encrypted, if
+    /// known.
+    pub(crate) key_id: Option<u64>,
+}
+
+/// Opaque metadata attached to a ciphertext, with its authentication tag.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Metadata {
+    data: Vec<u8>,
+    tag: [u8; TAG_SIZE],
 }
 
+// The noise estimate is not part of the ciphertext itself.
+impl PartialEq for Ciphertext {
+    fn eq(&self, other: &Self) -> bool {
+        self.par == other.par
+            && self.seed == other.seed
+            && self.c == other.c
+            && self.level == other.level
+            && self.metadata == other.metadata
+            && self.key_id == other.key_id
+    }
+}
+
+impl Eq for Ciphertext {}
+
 impl Ciphertext {
     /// Modulo switch the ciphertext to the last level.
     pub fn mod_switch_to_last_level(&mut self) -> Result<()> {
+        self.noise = noise::mod_switch(&self.par, self.level, self.par.max_level(), self.noise);
         self.level = self.par.max_level();
         let last_ctx = self.par.ctx_at_level(self.level)?;
         self.seed = None;
@@ -53,11 +89,91 @@ impl Ciphertext {
                 ci.mod_switch_down_next()?;
                 ci.change_representation(Representation::Ntt);
             }
+            self.noise = noise::mod_switch(&self.par, self.level, self.level + 1, self.noise);
             self.level += 1
         }
         Ok(())
     }
 
+    /// Modulo raise the ciphertext to the level `level`, which must be at most
+    /// the level of the ciphertext.
+    ///
+    /// The polynomials are lifted to their centered representatives modulo
+    /// the current modulus `q`, so that the raised ciphertext decrypts to
+    /// `Δ m + e + q I` modulo the larger modulus, where `Δ` is the scaling
+    /// factor at the current level and `I` is a polynomial of small norm. This
+    /// is not a valid encryption of `m` at the larger modulus until the
+    /// multiple of `q` is removed, as in the first step of bootstrapping,
+    /// hence its noise estimate is dropped.
+    pub fn mod_raise(&mut self, level: usize) -> Result<()> {
+        if level > self.level {
+            return Err(Error::InvalidLevel {
+                level,
+                max_level: self.level,
+            });
+        }
+        if level == self.level {
+            return Ok(());
+        }
+        let ctx = self.par.ctx_at_level(level)?;
+        self.seed = None;
+        for ci in self.c.iter_mut() {
+            ci.change_representation(Representation::PowerBasis);
+            ci.mod_raise_to(ctx)?;
+            ci.change_representation(Representation::Ntt);
+        }
+        self.level = level;
+        self.noise = None;
+        Ok(())
+    }
+
+    /// Switches the ciphertext to other parameters, without decrypting it.
+    ///
+    /// The parameters `par` must have the same degree and plaintext modulus as
+    /// the parameters of the ciphertext, and one of their contexts must have
+    /// moduli forming a prefix of the moduli of the ciphertext, for example
+    /// when the chains of moduli share a prefix or only differ in their
+    /// number of levels. The ciphertext is then switched down to the largest
+    /// such context, and it remains decryptable by the same secret key.
+    pub fn switch_parameters(&self, par: &Arc<BfvParameters>) -> Result<Ciphertext> {
+        if par.degree() != self.par.degree() || par.plaintext() != self.par.plaintext() {
+            return Err(Error::ParameterMismatch {
+                expected: self.par.fingerprint(),
+                found: par.fingerprint(),
+            });
+        }
+
+        let moduli = self.c[0].ctx().moduli();
+        let level = (0..=par.max_level())
+            .find(|level| moduli.starts_with(par.ctx[*level].moduli()))
+            .ok_or(Error::ParameterMismatch {
+                expected: self.par.fingerprint(),
+                found: par.fingerprint(),
+            })?;
+        let ctx = par.ctx_at_level(level)?;
+
+        let mut c = self.c.clone();
+        let mut seed = self.seed;
+        if self.c[0].ctx() != ctx {
+            seed = None;
+            for ci in c.iter_mut() {
+                ci.change_representation(Representation::PowerBasis);
+                ci.mod_switch_down_to(ctx)?;
+                ci.change_representation(Representation::Ntt);
+            }
+        }
+
+        Ok(Ciphertext {
+            par: par.clone(),
+            seed,
+            c,
+            level,
+            noise: noise::switch_parameters(&self.par, self.level, par, level, self.noise),
+            metadata: None,
+            key_id: self.key_id,
+        })
+    }
+
     /// Create a ciphertext from a vector of polynomials.
     /// A ciphertext must contain at least two polynomials, and all polynomials
     /// must be in Ntt representation and with the same context.
@@ -87,6 +203,9 @@ impl Ciphertext {
             seed: None,
             c,
             level,
+            noise: None,
+            metadata: None,
+            key_id: None,
         })
     }
 
@@ -94,6 +213,238 @@ impl Ciphertext {
     pub fn get(&self, i: usize) -> Option<&Poly> {
         self.c.get(i)
     }
+
+    /// Returns the level of the ciphertext.
+    pub const fn level(&self) -> usize {
+        self.level
+    }
+
+    /// Returns the degree of the ciphertext, i.e., its number of polynomials
+    /// minus one. Freshly encrypted and relinearized ciphertexts have degree 1.
+    pub fn degree(&self) -> usize {
+        self.c.len().saturating_sub(1)
+    }
+
+    /// Returns the size in bytes of the serialized ciphertext.
+    pub fn size_bytes(&self) -> usize {
+        CiphertextProto::from(self).encoded_len()
+    }
+
+    /// Returns whether the ciphertext is seeded, i.e., whether its last
+    /// polynomial is compressed into a seed when serialized.
+    pub const fn is_seeded(&self) -> bool {
+        self.seed.is_some()
+    }
+
+    /// Returns the parameters of the ciphertext.
+    pub const fn parameters(&self) -> &Arc<BfvParameters> {
+        &self.par
+    }
+
+    /// Returns the identifier of the key under which the ciphertext is
+    /// encrypted, as returned by [`SecretKey::key_id`](crate::bfv::SecretKey::key_id),
+    /// or `None` if it is unknown, for example for ciphertexts created with
+    /// [`Ciphertext::new`].
+    ///
+    /// The identifier is set when encrypting, propagated by the homomorphic
+    /// operations, and serialized with the ciphertext. Operating on
+    /// ciphertexts of different identifiers, or decrypting a ciphertext with a
+    /// secret key of another identifier, fails with [`Error::KeyMismatch`].
+    pub const fn key_id(&self) -> Option<u64> {
+        self.key_id
+    }
+
+    /// Returns the identifier of the key of the result of an operation between
+    /// `self` and `other`, or an error if their identifiers differ.
+    pub(crate) fn merge_key_id(&self, other: &Ciphertext) -> Result<Option<u64>> {
+        other.merge_key_id_with(self.key_id)
+    }
+
+    /// Returns the identifier of the key of the result of an operation between
+    /// `self` and a ciphertext of identifier `key_id`, or an error if the
+    /// identifiers differ.
+    pub(crate) fn merge_key_id_with(&self, key_id: Option<u64>) -> Result<Option<u64>> {
+        match (key_id, self.key_id) {
+            (Some(expected), Some(found)) if expected != found => {
+                Err(Error::KeyMismatch { expected, found })
+            }
+            (key_id, self_key_id) => Ok(key_id.or(self_key_id)),
+        }
+    }
+
+    /// Attaches the opaque `metadata` to the ciphertext, e.g., a record
+    /// identifier or a schema version, replacing any previous metadata. The
+    /// metadata is serialized with the ciphertext, and authenticated together
+    /// with it by an HMAC-SHA256 under `key`, which should be a uniformly
+    /// random key of at least 32 bytes.
+    ///
+    /// The metadata is bound to the ciphertext, so it no longer verifies once
+    /// the ciphertext is modified by homomorphic operations: the metadata of
+    /// their results should be cleared or set again.
+    pub fn set_metadata(&mut self, metadata: &[u8], key: &[u8]) {
+        self.metadata = None;
+        let tag = integrity::tag(&self.authenticated_bytes(metadata), Some(key));
+        self.metadata = Some(Metadata {
+            data: metadata.to_vec(),
+            tag,
+        });
+    }
+
+    /// Returns the metadata attached to the ciphertext with
+    /// [`Ciphertext::set_metadata`], or `None` if there is none.
+    ///
+    /// Returns [`Error::IntegrityCheckFailed`] if the metadata was not attached
+    /// to this ciphertext under `key`.
+    pub fn metadata(&self, key: &[u8]) -> Result<Option<&[u8]>> {
+        let Some(metadata) = &self.metadata else {
+            return Ok(None);
+        };
+        let bytes = self.authenticated_bytes(&metadata.data);
+        integrity::verify(&bytes, Some(key), &metadata.tag)?;
+        Ok(Some(&metadata.data))
+    }
+
+    /// Returns whether metadata is attached to the ciphertext.
+    pub const fn has_metadata(&self) -> bool {
+        self.metadata.is_some()
+    }
+
+    /// Removes the metadata attached to the ciphertext.
+    pub fn clear_metadata(&mut self) {
+        self.metadata = None
+    }
+
+    /// Returns the bytes authenticated by the tag of the metadata `data`: its
+    /// length, the metadata, and the serialization of the ciphertext without
+    /// metadata and format version.
+    fn authenticated_bytes(&self, data: &[u8]) -> Vec<u8> {
+        let mut proto = CiphertextProto::from(self);
+        proto.metadata.clear();
+        proto.metadata_tag.clear();
+        proto.version = 0;
+        let mut bytes = (data.len() as u64).to_be_bytes().to_vec();
+        bytes.extend_from_slice(data);
+        proto.encode(&mut bytes).unwrap();
+        bytes
+    }
+
+    /// Returns a heuristic estimate of the noise in the ciphertext, in bits.
+    ///
+    /// The estimate is computed without the secret key, from the operations
+    /// that produced the ciphertext, and is comparable to the value returned
+    /// by [`SecretKey::measure_noise`](crate::bfv::SecretKey::measure_noise).
+    /// Returns `None` when the noise is unknown, for example for ciphertexts
+    /// deserialized after being operated on.
+    pub fn noise_estimate(&self) -> Option<usize> {
+        self.noise.map(|noise| noise.ceil().max(0.0) as usize)
+    }
+
+    /// Returns a heuristic estimate of the remaining noise budget in bits, i.e.,
+    /// of the noise that can still be added before decryption fails.
+    ///
+    /// Returns `None` when the noise is unknown.
+    pub fn noise_budget_estimate(&self) -> Option<usize> {
+        let capacity = self.noise_capacity();
+        self.noise
+            .map(|noise| (capacity - noise).floor().max(0.0) as usize)
+    }
+
+    /// Returns an error if the heuristic estimate of the noise exceeds the
+    /// noise budget, i.e., if the ciphertext would likely decrypt incorrectly.
+    ///
+    /// Succeeds when the noise is unknown.
+    pub fn check_noise_budget(&self) -> Result<()> {
+        let capacity = self.noise_capacity();
+        match self.noise {
+            Some(noise) if noise > capacity => Err(Error::NoiseBudgetExceeded {
+                noise: noise.ceil() as usize,
+                capacity: capacity.floor().max(0.0) as usize,
+            }),
+            _ => Ok(()),
+        }
+    }
+
+    /// Adds a uniform noise `statistical_bits` bits larger than the estimated
+    /// noise of the ciphertext, so that the noise statistically hides the
+    /// operations which produced the ciphertext.
+    ///
+    /// Returns an error if the noise is unknown or if the flooded noise
+    /// exceeds the noise budget.
+    pub(crate) fn flood_noise<R: RngCore + CryptoRng>(
+        &mut self,
+        statistical_bits: usize,
+        rng: &mut R,
+    ) -> Result<()> {
+        let noise = self.noise.ok_or(Error::UnspecifiedInput(
+            "The noise of the ciphertext is unknown".to_string(),
+        ))?;
+        let bits = noise.ceil() as usize + statistical_bits;
+        if bits as f64 > self.noise_capacity() {
+            return Err(Error::NoiseBudgetExceeded {
+                noise: bits,
+                capacity: self.noise_capacity().floor().max(0.0) as usize,
+            });
+        }
+
+        // Sample the coefficients uniformly in [-2^bits, 2^bits).
+        let ctx = self.c[0].ctx().clone();
+        let q = ctx.modulus();
+        let offset = q - (BigUint::from(1u64) << bits);
+        let mut bytes = vec![0u8; (bits + 1).div_ceil(8)];
+        let coefficients = (0..self.par.degree())
+            .map(|_| {
+                rng.fill_bytes(&mut bytes);
+                let u = BigUint::from_bytes_le(&bytes) % (BigUint::from(1u64) << (bits + 1));
+                (u + &offset) % q
+            })
+            .collect::<Vec<_>>();
+        let mut e = Poly::try_convert_from(
+            coefficients.as_slice(),
+            &ctx,
+            false,
+            Representation::PowerBasis,
+        )?;
+        e.change_representation(Representation::Ntt);
+
+        self.c[0] += &e;
+        self.seed = None;
+        self.noise = Some(bits as f64 + 1.0);
+        Ok(())
+    }
+
+    /// Maximum noise, in bits, for which the ciphertext decrypts correctly.
+    pub(crate) fn noise_capacity(&self) -> f64 {
+        noise::capacity(&self.par, self.level)
+    }
+
+    /// Divide the ciphertext by a constant, i.e. multiply it by the inverse of
+    /// `c` modulo the plaintext modulus.
+    ///
+    /// Returns an error if `c` is not invertible modulo the plaintext modulus.
+    pub fn div_by_constant(&self, c: u64) -> Result<Ciphertext> {
+        let t = self.par.plaintext.modulus();
+        let c_inv = inverse(self.par.plaintext.reduce(c), t).ok_or(Error::NotInvertible(c, t))?;
+        Ok(self * c_inv)
+    }
+
+    /// Divide each slot of the ciphertext by the corresponding value of `c`,
+    /// i.e. multiply it by a plaintext encoding the inverses of the values in
+    /// `c` modulo the plaintext modulus using the SIMD encoding.
+    ///
+    /// Returns an error if one of the values is not invertible modulo the
+    /// plaintext modulus, or if the parameters do not support the SIMD
+    /// encoding.
+    pub fn div_by_vector(&self, c: &[u64]) -> Result<Ciphertext> {
+        let t = self.par.plaintext.modulus();
+        let c_inv = c
+            .iter()
+            .map(|ci| {
+                inverse(self.par.plaintext.reduce(*ci), t).ok_or(Error::NotInvertible(*ci, t))
+            })
+            .collect::<Result<Vec<u64>>>()?;
+        let pt = Plaintext::try_encode(&c_inv, Encoding::simd_at_level(self.level), &self.par)?;
+        Ok(self * &pt)
+    }
 }
 
 impl FheCiphertext for Ciphertext {}
@@ -104,11 +455,23 @@ impl FheParametrized for Ciphertext {
 
 impl Serialize for Ciphertext {
     fn to_bytes(&self) -> Vec<u8> {
-        CiphertextProto::from(self).encode_to_vec()
+        let bytes = CiphertextProto::from(self).encode_to_vec();
+        #[cfg(feature = "tracing")]
+        tracing::debug!(
+            level = self.level,
+            size = self.c.len(),
+            bytes = bytes.len(),
+            "serialized ciphertext"
+        );
+        bytes
     }
 }
 
 impl DeserializeParametrized for Ciphertext {
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "debug", skip_all, fields(bytes = bytes.len()), err)
+    )]
     fn from_bytes(bytes: &[u8], par: &Arc<BfvParameters>) -> Result<Self> {
         if let Ok(ctp) = Message::decode(bytes) {
             Ciphertext::try_convert_from(&ctp, par)
@@ -128,6 +491,9 @@ impl Ciphertext {
             seed: None,
             c: Default::default(),
             level: 0,
+            noise: None,
+            metadata: None,
+            key_id: None,
         }
     }
 }
@@ -136,15 +502,24 @@ impl Ciphertext {
 impl From<&Ciphertext> for CiphertextProto {
     fn from(ct: &Ciphertext) -> Self {
         let mut proto = CiphertextProto::default();
-        for i in 0..ct.c.len() - 1 {
-            proto.c.push(ct.c[i].to_bytes())
-        }
-        if let Some(seed) = ct.seed {
-            proto.seed = seed.to_vec()
-        } else {
-            proto.c.push(ct.c[ct.c.len() - 1].to_bytes())
+        if let Some((last, others)) = ct.c.split_last() {
+            for ci in others {
+                proto.c.push(ci.to_bytes())
+            }
+            if let Some(seed) = ct.seed {
+                proto.seed = seed.to_vec()
+            } else {
+                proto.c.push(last.to_bytes())
+            }
         }
         proto.level = ct.level as u32;
+        proto.fingerprint = ct.par.fingerprint();
+        proto.version = FORMAT_VERSION;
+        if let Some(metadata) = &ct.metadata {
+            proto.metadata = metadata.data.clone();
+            proto.metadata_tag = metadata.tag.to_vec();
+        }
+        proto.key_id = ct.key_id.unwrap_or_default();
         proto
     }
 }
@@ -152,11 +527,17 @@ impl From<&Ciphertext> for CiphertextProto {
 impl TryConvertFrom<&CiphertextProto> for Ciphertext {
     fn try_convert_from(value: &CiphertextProto, par: &Arc<BfvParameters>) -> Result<Self> {
         if value.c.is_empty() || (value.c.len() == 1 && value.seed.is_empty()) {
-            return Err(Error::DefaultError("Not enough polynomials".to_string()));
+            return Err(Error::SerializationError);
         }
 
+        check_version(value.version)?;
+        par.check_serialized_fingerprint(value.fingerprint)?;
+
         if value.level as usize > par.max_level() {
-            return Err(Error::DefaultError("Invalid level".to_string()));
+            return Err(Error::InvalidLevel {
+                level: value.level as usize,
+                max_level: par.max_level(),
+            });
         }
 
         let ctx = par.ctx_at_level(value.level as usize)?;
@@ -181,11 +562,33 @@ impl TryConvertFrom<&CiphertextProto> for Ciphertext {
             c.push(c1)
         }
 
+        // A seeded ciphertext is a fresh encryption under the secret key.
+        let noise = seed.map(|_| noise::fresh_secret_key(par));
+
+        let metadata = if value.metadata_tag.is_empty() {
+            if !value.metadata.is_empty() {
+                return Err(Error::SerializationError);
+            }
+            None
+        } else {
+            Some(Metadata {
+                data: value.metadata.clone(),
+                tag: value
+                    .metadata_tag
+                    .as_slice()
+                    .try_into()
+                    .map_err(|_| Error::SerializationError)?,
+            })
+        };
+
         Ok(Ciphertext {
             par: par.clone(),
             seed,
             c,
             level: value.level as usize,
+            noise,
+            metadata,
+            key_id: (value.key_id != 0).then_some(value.key_id),
         })
     }
 }
@@ -193,12 +596,18 @@ impl TryConvertFrom<&CiphertextProto> for Ciphertext {
 #[cfg(test)]
 mod tests {
     use crate::bfv::{
-        traits::TryConvertFrom, BfvParameters, Ciphertext, Encoding, Plaintext, SecretKey,
+        traits::TryConvertFrom, BfvParameters, BfvParametersBuilder, Ciphertext, Encoding,
+        Plaintext, PublicKey, SecretKey,
     };
-    use crate::proto::bfv::Ciphertext as CiphertextProto;
-    use fhe_traits::FheDecrypter;
+    use crate::proto::{bfv::Ciphertext as CiphertextProto, FORMAT_VERSION};
+    use fhe_math::rq::{traits::TryConvertFrom as TryConvertFromPoly, Poly, Representation};
     use fhe_traits::{DeserializeParametrized, FheEncoder, FheEncrypter, Serialize};
-    use rand::thread_rng;
+    use fhe_traits::{FheDecoder, FheDecrypter};
+    use itertools::{izip, Itertools};
+    use num_bigint::{BigInt, BigUint};
+    use num_traits::{Signed, Zero};
+    use prost::Message;
+    use rand::{thread_rng, RngCore};
     use std::error::Error;
 
     #[test]
@@ -207,6 +616,7 @@ mod tests {
         for params in [
             BfvParameters::default_arc(1, 16),
             BfvParameters::default_arc(6, 16),
+            BfvParameters::default_arc(2, 2),
         ] {
             let sk = SecretKey::random(&params, &mut rng);
             let v = params.plaintext.random_vec(params.degree(), &mut rng);
@@ -228,6 +638,7 @@ mod tests {
         for params in [
             BfvParameters::default_arc(1, 16),
             BfvParameters::default_arc(6, 16),
+            BfvParameters::default_arc(2, 2),
         ] {
             let sk = SecretKey::random(&params, &mut rng);
             let v = params.plaintext.random_vec(params.degree(), &mut rng);
@@ -235,10 +646,59 @@ mod tests {
             let ct: Ciphertext = sk.try_encrypt(&pt, &mut rng)?;
             let ct_bytes = ct.to_bytes();
             assert_eq!(ct, Ciphertext::from_bytes(&ct_bytes, &params)?);
+
+            // The ciphertext cannot be used with other parameters.
+            let other = BfvParameters::default_arc(3, 16);
+            let mismatch = crate::Error::ParameterMismatch {
+                expected: other.fingerprint(),
+                found: params.fingerprint(),
+            };
+            assert_eq!(
+                Ciphertext::from_bytes(&ct_bytes, &other).unwrap_err(),
+                mismatch
+            );
+            assert_eq!(
+                SecretKey::random(&other, &mut rng)
+                    .try_decrypt(&ct)
+                    .unwrap_err(),
+                mismatch
+            );
         }
         Ok(())
     }
 
+    #[test]
+    fn schema_evolution() -> Result<(), Box<dyn Error>> {
+        let mut rng = thread_rng();
+        let params = BfvParameters::default_arc(2, 16);
+        let sk = SecretKey::random(&params, &mut rng);
+        let pt = Plaintext::try_encode(&[1u64, 2, 3], Encoding::poly(), &params)?;
+        let ct: Ciphertext = sk.try_encrypt(&pt, &mut rng)?;
+        let ct = &ct * &ct;
+
+        // The messages of older releases have no version field.
+        let mut proto = CiphertextProto::from(&ct);
+        assert_eq!(proto.version, FORMAT_VERSION);
+        proto.version = 0;
+        let legacy = proto.encode_to_vec();
+        assert!(legacy.len() < ct.to_bytes().len());
+        assert_eq!(ct, Ciphertext::from_bytes(&legacy, &params)?);
+
+        // The fields added by newer releases are ignored.
+        let mut extended = ct.to_bytes();
+        extended.extend_from_slice(&[0xa0, 0x06, 0x07]);
+        assert_eq!(ct, Ciphertext::from_bytes(&extended, &params)?);
+
+        // The messages of an unsupported version are rejected.
+        let mut proto = CiphertextProto::from(&ct);
+        proto.version = FORMAT_VERSION + 1;
+        assert_eq!(
+            Ciphertext::from_bytes(&proto.encode_to_vec(), &params).unwrap_err(),
+            crate::Error::UnsupportedVersion(FORMAT_VERSION + 1)
+        );
+        Ok(())
+    }
+
     #[test]
     fn new() -> Result<(), Box<dyn Error>> {
         let mut rng = thread_rng();
@@ -256,10 +716,10 @@ mod tests {
             let c1 = ct3.get(1).unwrap();
             let c2 = ct3.get(2).unwrap();
 
-            assert_eq!(
-                ct3,
-                Ciphertext::new(vec![c0.clone(), c1.clone(), c2.clone()], &params)?
-            );
+            let mut ct3_new = Ciphertext::new(vec![c0.clone(), c1.clone(), c2.clone()], &params)?;
+            assert_eq!(ct3_new.key_id(), None);
+            ct3_new.key_id = ct3.key_id;
+            assert_eq!(ct3, ct3_new);
             assert_eq!(ct3.level, 0);
 
             ct3.mod_switch_to_last_level()?;
@@ -267,10 +727,10 @@ mod tests {
             let c0 = ct3.get(0).unwrap();
             let c1 = ct3.get(1).unwrap();
             let c2 = ct3.get(2).unwrap();
-            assert_eq!(
-                ct3,
-                Ciphertext::new(vec![c0.clone(), c1.clone(), c2.clone()], &params)?
-            );
+            let mut ct3_new = Ciphertext::new(vec![c0.clone(), c1.clone(), c2.clone()], &params)?;
+            assert_eq!(ct3_new.key_id(), None);
+            ct3_new.key_id = ct3.key_id;
+            assert_eq!(ct3, ct3_new);
             assert_eq!(ct3.level, params.max_level());
         }
 
@@ -299,4 +759,338 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn mod_raise() -> Result<(), Box<dyn Error>> {
+        let mut rng = thread_rng();
+        let params = BfvParameters::default_arc(3, 16);
+        let sk = SecretKey::random(&params, &mut rng);
+        let v = params.plaintext.random_vec(params.degree(), &mut rng);
+        let pt = Plaintext::try_encode(&v, Encoding::simd(), &params)?;
+        let mut ct: Ciphertext = sk.try_encrypt(&pt, &mut rng)?;
+        ct.mod_switch_to_last_level()?;
+        assert!(ct.clone().mod_raise(params.max_level() + 1).is_err());
+
+        // Computes c0 + c1 * s with centered coefficients.
+        let phase = |ct: &Ciphertext| -> crate::Result<Vec<BigInt>> {
+            let ctx = ct.c[0].ctx();
+            let mut s =
+                Poly::try_convert_from(sk.coeffs.as_ref(), ctx, false, Representation::PowerBasis)?;
+            s.change_representation(Representation::Ntt);
+            let mut p = &ct.c[0] + &(&ct.c[1] * &s);
+            p.change_representation(Representation::PowerBasis);
+            let modulus = BigInt::from(ctx.modulus().clone());
+            Ok(Vec::<BigUint>::from(&p)
+                .into_iter()
+                .map(|x| {
+                    let x = BigInt::from(x);
+                    if &x << 1 > modulus {
+                        x - &modulus
+                    } else {
+                        x
+                    }
+                })
+                .collect())
+        };
+
+        let q = BigInt::from(params.ctx_at_level(ct.level)?.modulus().clone());
+        let expected = phase(&ct)?;
+        let mut raised = ct.clone();
+        raised.mod_raise(0)?;
+        assert_eq!(raised.level, 0);
+        assert_eq!(raised.c[0].ctx(), params.ctx_at_level(0)?);
+        assert!(raised.noise.is_none());
+        for (x, y) in izip!(phase(&raised)?, expected) {
+            let difference = x - y;
+            assert!((&difference % &q).is_zero());
+            assert!((difference / &q).abs() <= BigInt::from(params.degree()));
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn switch_parameters() -> Result<(), Box<dyn Error>> {
+        let mut rng = thread_rng();
+        let moduli = [
+            4611686018326724609,
+            4611686018309947393,
+            4611686018282684417,
+            4611686018257518593,
+        ];
+        let params = |moduli: &[u64], plaintext: u64| {
+            BfvParametersBuilder::new()
+                .set_degree(16)
+                .set_plaintext_modulus(plaintext)
+                .set_moduli(moduli)
+                .set_minimum_security(0)
+                .build_arc()
+        };
+        let par = params(&moduli[..3], 1153)?;
+        let sk = SecretKey::random(&par, &mut rng);
+        let v = par.plaintext.random_vec(par.degree(), &mut rng);
+        let pt = Plaintext::try_encode(&v, Encoding::simd(), &par)?;
+        let ct: Ciphertext = sk.try_encrypt(&pt, &mut rng)?;
+
+        // Switch to a shorter chain, to the same chain with an additional
+        // level, and to the last level of a longer chain.
+        for (other_moduli, expected_level) in [
+            (&moduli[..2], 0),
+            (&moduli[..1], 0),
+            (&moduli[..3], 0),
+            (&moduli[..4], 1),
+        ] {
+            let other = params(other_moduli, 1153)?;
+            let ct2 = ct.switch_parameters(&other)?;
+            assert_eq!(ct2.parameters(), &other);
+            assert_eq!(ct2.level(), expected_level);
+            let sk2 = SecretKey::new(sk.coeffs.to_vec(), &other);
+            let pt2 = sk2.try_decrypt(&ct2)?;
+            assert_eq!(Vec::<u64>::try_decode(&pt2, Encoding::simd())?, v);
+        }
+
+        // The ciphertext cannot be switched to unrelated parameters.
+        assert!(ct.switch_parameters(&params(&moduli[1..3], 1153)?).is_err());
+        assert!(ct.switch_parameters(&params(&moduli[..3], 1217)?).is_err());
+        assert!(ct
+            .switch_parameters(&BfvParameters::default_arc(3, 32))
+            .is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn div_by_constant() -> Result<(), Box<dyn Error>> {
+        let mut rng = thread_rng();
+        for params in [
+            BfvParameters::default_arc(1, 16),
+            BfvParameters::default_arc(6, 16),
+        ] {
+            let t = &params.plaintext;
+            let sk = SecretKey::random(&params, &mut rng);
+            let v = t.random_vec(params.degree(), &mut rng);
+            let pt = Plaintext::try_encode(&v, Encoding::simd(), &params)?;
+            let ct: Ciphertext = sk.try_encrypt(&pt, &mut rng)?;
+
+            // Dividing `c * v` by `c` recovers `v`.
+            for c in [1, 2, 17, t.modulus() - 1, t.modulus() + 5] {
+                let ct_c = &ct * c;
+                let ct_div = ct_c.div_by_constant(c)?;
+                let decrypted = sk.try_decrypt(&ct_div)?;
+                assert_eq!(Vec::<u64>::try_decode(&decrypted, Encoding::simd())?, v);
+            }
+
+            assert_eq!(
+                ct.div_by_constant(0).unwrap_err(),
+                crate::Error::NotInvertible(0, t.modulus())
+            );
+            assert_eq!(
+                ct.div_by_constant(2 * t.modulus()).unwrap_err(),
+                crate::Error::NotInvertible(2 * t.modulus(), t.modulus())
+            );
+        }
+
+        // With a plaintext modulus which is not prime.
+        let params = BfvParametersBuilder::new()
+            .set_degree(16)
+            .set_plaintext_modulus(1 << 10)
+            .set_moduli_sizes(&[62])
+            .set_minimum_security(0)
+            .build_arc()?;
+        let sk = SecretKey::random(&params, &mut rng);
+        let v = params.plaintext.random_vec(params.degree(), &mut rng);
+        let pt = Plaintext::try_encode(&v, Encoding::poly(), &params)?;
+        let ct: Ciphertext = sk.try_encrypt(&pt, &mut rng)?;
+        let ct_div = (&ct * 3u64).div_by_constant(3)?;
+        let decrypted = sk.try_decrypt(&ct_div)?;
+        assert_eq!(Vec::<u64>::try_decode(&decrypted, Encoding::poly())?, v);
+        assert_eq!(
+            ct.div_by_constant(4).unwrap_err(),
+            crate::Error::NotInvertible(4, 1 << 10)
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn div_by_vector() -> Result<(), Box<dyn Error>> {
+        let mut rng = thread_rng();
+        for params in [
+            BfvParameters::default_arc(1, 16),
+            BfvParameters::default_arc(6, 16),
+        ] {
+            let t = &params.plaintext;
+            let sk = SecretKey::random(&params, &mut rng);
+            for level in [0, params.max_level()] {
+                let v = t.random_vec(params.degree(), &mut rng);
+                let c = (0..params.degree())
+                    .map(|_| 1 + (rng.next_u64() % (t.modulus() - 1)))
+                    .collect_vec();
+                let mut vc = v.clone();
+                t.mul_vec(&mut vc, &c);
+
+                let pt = Plaintext::try_encode(&vc, Encoding::simd_at_level(level), &params)?;
+                let ct: Ciphertext = sk.try_encrypt(&pt, &mut rng)?;
+                let ct_div = ct.div_by_vector(&c)?;
+                assert_eq!(ct_div.level, level);
+                let decrypted = sk.try_decrypt(&ct_div)?;
+                assert_eq!(Vec::<u64>::try_decode(&decrypted, Encoding::simd())?, v);
+
+                let mut c_invalid = c.clone();
+                c_invalid[3] = 0;
+                assert_eq!(
+                    ct.div_by_vector(&c_invalid).unwrap_err(),
+                    crate::Error::NotInvertible(0, t.modulus())
+                );
+            }
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn introspection() -> Result<(), Box<dyn Error>> {
+        let mut rng = thread_rng();
+        for params in [
+            BfvParameters::default_arc(1, 16),
+            BfvParameters::default_arc(6, 16),
+        ] {
+            let sk = SecretKey::random(&params, &mut rng);
+            let v = params.plaintext.random_vec(params.degree(), &mut rng);
+            let pt = Plaintext::try_encode(&v, Encoding::simd(), &params)?;
+            let ct: Ciphertext = sk.try_encrypt(&pt, &mut rng)?;
+
+            assert_eq!(ct.level(), 0);
+            assert_eq!(ct.degree(), 1);
+            assert!(ct.is_seeded());
+            assert_eq!(ct.parameters(), &params);
+            assert_eq!(ct.size_bytes(), ct.to_bytes().len());
+            assert!(ct.noise_estimate().is_some());
+            assert!(ct.noise_budget_estimate().unwrap() > 0);
+
+            // A seeded ciphertext is fresh, so its noise is still known after
+            // deserialization.
+            let ct_deserialized = Ciphertext::from_bytes(&ct.to_bytes(), &params)?;
+            assert_eq!(ct_deserialized.noise_estimate(), ct.noise_estimate());
+
+            let mut ct2 = &ct * &ct;
+            assert_eq!(ct2.degree(), 2);
+            assert!(!ct2.is_seeded());
+            assert_eq!(ct2.size_bytes(), ct2.to_bytes().len());
+            assert!(ct2.noise_estimate().unwrap() > ct.noise_estimate().unwrap());
+            assert!(ct2.noise_budget_estimate().unwrap() < ct.noise_budget_estimate().unwrap());
+
+            // The noise of other ciphertexts is unknown after deserialization.
+            let ct2_deserialized = Ciphertext::from_bytes(&ct2.to_bytes(), &params)?;
+            assert_eq!(ct2_deserialized, ct2);
+            assert!(ct2_deserialized.noise_estimate().is_none());
+            assert!(ct2_deserialized.noise_budget_estimate().is_none());
+
+            ct2.mod_switch_to_last_level()?;
+            assert_eq!(ct2.level(), params.max_level());
+            assert!(ct2.noise_estimate().is_some());
+
+            let zero = Ciphertext::zero(&params);
+            assert_eq!(zero.degree(), 0);
+            assert!(!zero.is_seeded());
+            assert!(zero.noise_estimate().is_none());
+            assert_eq!(zero.size_bytes(), zero.to_bytes().len());
+            assert!(Ciphertext::from_bytes(&zero.to_bytes(), &params).is_err());
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn metadata() -> Result<(), Box<dyn Error>> {
+        let mut rng = thread_rng();
+        let params = BfvParameters::default_arc(2, 16);
+        let sk = SecretKey::random(&params, &mut rng);
+        let pt = Plaintext::try_encode(&[1u64, 2, 3], Encoding::poly(), &params)?;
+        let mut key = [0u8; 32];
+        rng.fill_bytes(&mut key);
+
+        let mut ct: Ciphertext = sk.try_encrypt(&pt, &mut rng)?;
+        assert!(!ct.has_metadata());
+        assert_eq!(ct.metadata(&key)?, None);
+        ct.set_metadata(b"record 42, schema 3", &key);
+        assert!(ct.has_metadata());
+        assert_eq!(ct.metadata(&key)?, Some(&b"record 42, schema 3"[..]));
+        assert_eq!(ct.size_bytes(), ct.to_bytes().len());
+        assert!(ct.metadata(&[0u8; 32]).is_err());
+
+        // The metadata round-trips through serialization.
+        let ct2 = Ciphertext::from_bytes(&ct.to_bytes(), &params)?;
+        assert_eq!(ct2, ct);
+        assert_eq!(ct2.metadata(&key)?, ct.metadata(&key)?);
+
+        // The metadata is bound to the ciphertext.
+        let other: Ciphertext = sk.try_encrypt(&pt, &mut rng)?;
+        let mut proto = CiphertextProto::from(&other);
+        let tampered = CiphertextProto::from(&ct);
+        proto.metadata = tampered.metadata.clone();
+        proto.metadata_tag = tampered.metadata_tag.clone();
+        let swapped = Ciphertext::try_convert_from(&proto, &params)?;
+        assert!(matches!(
+            swapped.metadata(&key),
+            Err(crate::Error::IntegrityCheckFailed)
+        ));
+        let mut proto = tampered.clone();
+        proto.metadata[0] ^= 1;
+        let modified = Ciphertext::try_convert_from(&proto, &params)?;
+        assert!(modified.metadata(&key).is_err());
+        let mut sum = &ct + &other;
+        assert!(sum.metadata(&key).is_err());
+        sum.clear_metadata();
+        assert_eq!(sum.metadata(&key)?, None);
+
+        // Empty metadata is still authenticated, and malformed tags are rejected.
+        ct.set_metadata(&[], &key);
+        let ct2 = Ciphertext::from_bytes(&ct.to_bytes(), &params)?;
+        assert_eq!(ct2.metadata(&key)?, Some(&[][..]));
+        let mut proto = tampered;
+        proto.metadata_tag.pop();
+        assert!(Ciphertext::try_convert_from(&proto, &params).is_err());
+        proto.metadata_tag.clear();
+        assert!(Ciphertext::try_convert_from(&proto, &params).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn key_id() -> Result<(), Box<dyn Error>> {
+        let mut rng = thread_rng();
+        let params = BfvParameters::default_arc(2, 16);
+        let sk = SecretKey::random(&params, &mut rng);
+        let pk = PublicKey::new(&sk, &mut rng);
+        let pt = Plaintext::try_encode(&[1u64, 2, 3], Encoding::poly(), &params)?;
+
+        // Fresh ciphertexts, and the results of operations, are tagged.
+        let ct: Ciphertext = sk.try_encrypt(&pt, &mut rng)?;
+        let ct_pk: Ciphertext = pk.try_encrypt(&pt, &mut rng)?;
+        assert_eq!(ct.key_id(), Some(sk.key_id()));
+        assert_eq!(ct_pk.key_id(), Some(sk.key_id()));
+        assert_eq!(ct.try_mul(&ct_pk)?.key_id(), Some(sk.key_id()));
+        assert_eq!((-&ct).key_id(), Some(sk.key_id()));
+        let ct2 = Ciphertext::from_bytes(&ct.to_bytes(), &params)?;
+        assert_eq!(ct2.key_id(), Some(sk.key_id()));
+
+        // Operands and secret keys of different identifiers are rejected.
+        let other_sk = SecretKey::random(&params, &mut rng);
+        assert_ne!(other_sk.key_id(), sk.key_id());
+        let other: Ciphertext = other_sk.try_encrypt(&pt, &mut rng)?;
+        let mismatch = crate::Error::KeyMismatch {
+            expected: sk.key_id(),
+            found: other_sk.key_id(),
+        };
+        assert_eq!(ct.try_add(&other).unwrap_err(), mismatch);
+        assert_eq!(ct.try_mul(&other).unwrap_err(), mismatch);
+        assert_eq!(sk.try_decrypt(&other).unwrap_err(), mismatch);
+
+        // Untagged ciphertexts can be operated on with tagged ones.
+        let untagged = Ciphertext::new(ct.c.clone(), &params)?;
+        assert_eq!(untagged.key_id(), None);
+        assert_eq!(
+            Ciphertext::from_bytes(&untagged.to_bytes(), &params)?.key_id(),
+            None
+        );
+        assert_eq!(untagged.try_add(&other)?.key_id(), Some(other_sk.key_id()));
+        assert_eq!(sk.try_decrypt(&untagged)?, pt);
+        Ok(())
+    }
 }
@@ -0,0 +1,263 @@
+//! Composition of parameters with coprime plaintext moduli by the Chinese
+//! remainder theorem.
+//!
+//! [`CrtBfv`] runs the same circuit under `k` parameter sets whose plaintext
+//! moduli `t_1, ..., t_k` are pairwise coprime. A value modulo
+//! `T = t_1 * ... * t_k` is encoded as its residues modulo each `t_i`, the
+//! circuit is evaluated independently on each instance, and the decrypted
+//! residues are recombined modulo `T`. This gives exact arithmetic on large
+//! integers, as long as the results of the circuit are smaller than `T`, at
+//! the cost of `k` evaluations with a small plaintext modulus.
+
+use std::sync::Arc;
+
+use fhe_traits::{FheDecoder, FheDecrypter, FheEncoder, FheEncrypter};
+use fhe_util::inverse;
+use itertools::{izip, Itertools};
+use num_bigint::BigUint;
+use num_traits::ToPrimitive;
+use rand::{CryptoRng, RngCore};
+
+use crate::bfv::{BfvParameters, Ciphertext, Encoding, Plaintext};
+use crate::{Error, Result};
+
+/// Parameter sets with pairwise coprime plaintext moduli, composed by the
+/// Chinese remainder theorem.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CrtBfv {
+    pars: Vec<Arc<BfvParameters>>,
+    modulus: BigUint,
+    // The coefficients `(T / t_i) * ((T / t_i)^-1 mod t_i)` of the
+    // recombination.
+    coefficients: Vec<BigUint>,
+}
+
+impl CrtBfv {
+    /// Composes the parameter sets `pars`, which must have the same degree and
+    /// pairwise coprime plaintext moduli.
+    pub fn new(pars: &[Arc<BfvParameters>]) -> Result<Self> {
+        let Some(first) = pars.first() else {
+            return Err(Error::TooFewValues(0, 1));
+        };
+        if let Some(par) = pars.iter().find(|par| par.degree() != first.degree()) {
+            return Err(Error::UnspecifiedInput(format!(
+                "The parameters have different degrees {} and {}",
+                first.degree(),
+                par.degree()
+            )));
+        }
+
+        let modulus = pars
+            .iter()
+            .map(|par| BigUint::from(par.plaintext()))
+            .product::<BigUint>();
+        let coefficients = pars
+            .iter()
+            .map(|par| {
+                let t = par.plaintext();
+                let quotient = &modulus / t;
+                let residue = (&quotient % t).to_u64().unwrap();
+                inverse(residue, t)
+                    .map(|inverse| quotient * inverse)
+                    .ok_or_else(|| {
+                        Error::UnspecifiedInput(
+                            "The plaintext moduli must be pairwise coprime".to_string(),
+                        )
+                    })
+            })
+            .collect::<Result<Vec<_>>>()?;
+        Ok(Self {
+            pars: pars.to_vec(),
+            modulus,
+            coefficients,
+        })
+    }
+
+    /// Returns the composed parameter sets.
+    pub fn parameters(&self) -> &[Arc<BfvParameters>] {
+        &self.pars
+    }
+
+    /// Returns the effective plaintext modulus, i.e., the product of the
+    /// plaintext moduli.
+    pub fn plaintext_modulus(&self) -> &BigUint {
+        &self.modulus
+    }
+
+    /// Encodes the `values`, reduced modulo the effective plaintext modulus,
+    /// into one plaintext per parameter set.
+    pub fn encode(&self, values: &[BigUint], encoding: Encoding) -> Result<Vec<Plaintext>> {
+        self.pars
+            .iter()
+            .map(|par| {
+                let residues = values
+                    .iter()
+                    .map(|v| (v % par.plaintext()).to_u64().unwrap())
+                    .collect_vec();
+                Plaintext::try_encode(&residues, encoding.clone(), par)
+            })
+            .collect()
+    }
+
+    /// Decodes the plaintexts `pts`, one per parameter set, into values modulo
+    /// the effective plaintext modulus.
+    pub fn decode(&self, pts: &[Plaintext], encoding: Encoding) -> Result<Vec<BigUint>> {
+        if pts.len() != self.pars.len() {
+            return Err(Error::UnspecifiedInput(format!(
+                "Expected {} plaintexts, found {}",
+                self.pars.len(),
+                pts.len()
+            )));
+        }
+        let mut values = vec![BigUint::default(); self.pars[0].degree()];
+        for (par, pt, coefficient) in izip!(&self.pars, pts, &self.coefficients) {
+            par.check_fingerprint(&pt.par)?;
+            let residues = Vec::<u64>::try_decode(pt, encoding.clone())?;
+            values
+                .iter_mut()
+                .zip(residues)
+                .for_each(|(v, r)| *v += coefficient * r);
+        }
+        values.iter_mut().for_each(|v| *v %= &self.modulus);
+        Ok(values)
+    }
+
+    /// Encodes and encrypts the `values` with the `keys`, one per parameter
+    /// set, into one ciphertext per parameter set.
+    pub fn encrypt<E, R>(
+        &self,
+        keys: &[E],
+        values: &[BigUint],
+        encoding: Encoding,
+        rng: &mut R,
+    ) -> Result<Vec<Ciphertext>>
+    where
+        E: FheEncrypter<Plaintext, Ciphertext, Parameters = BfvParameters, Error = Error>,
+        R: RngCore + CryptoRng,
+    {
+        self.check_count(keys.len())?;
+        self.encode(values, encoding)?
+            .iter()
+            .zip(keys)
+            .map(|(pt, key)| key.try_encrypt(pt, rng))
+            .collect()
+    }
+
+    /// Runs the circuit `f` on each parameter set, given its index, and
+    /// returns the ciphertexts it outputs.
+    pub fn evaluate<F>(&self, mut f: F) -> Result<Vec<Ciphertext>>
+    where
+        F: FnMut(usize, &Arc<BfvParameters>) -> Result<Ciphertext>,
+    {
+        self.pars
+            .iter()
+            .enumerate()
+            .map(|(i, par)| {
+                let ct = f(i, par)?;
+                par.check_fingerprint(&ct.par)?;
+                Ok(ct)
+            })
+            .collect()
+    }
+
+    /// Decrypts the ciphertexts `cts` with the `keys`, one per parameter set,
+    /// and decodes them into values modulo the effective plaintext modulus.
+    pub fn decrypt<D>(
+        &self,
+        keys: &[D],
+        cts: &[Ciphertext],
+        encoding: Encoding,
+    ) -> Result<Vec<BigUint>>
+    where
+        D: FheDecrypter<Plaintext, Ciphertext, Parameters = BfvParameters, Error = Error>,
+    {
+        self.check_count(keys.len())?;
+        let pts = keys
+            .iter()
+            .zip(cts)
+            .map(|(key, ct)| key.try_decrypt(ct))
+            .collect::<Result<Vec<_>>>()?;
+        self.decode(&pts, encoding)
+    }
+
+    /// Checks that there is one key per parameter set.
+    fn check_count(&self, count: usize) -> Result<()> {
+        if count != self.pars.len() {
+            Err(Error::UnspecifiedInput(format!(
+                "Expected {} keys, found {count}",
+                self.pars.len()
+            )))
+        } else {
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::CrtBfv;
+    use crate::bfv::{
+        BfvParametersBuilder, Encoding, Multiplicator, RelinearizationKey, SecretKey,
+    };
+    use num_bigint::BigUint;
+    use rand::{thread_rng, Rng};
+    use std::error::Error;
+
+    #[test]
+    fn crt_bfv() -> Result<(), Box<dyn Error>> {
+        let mut rng = thread_rng();
+        let pars = [65537u64, 114689, 147457]
+            .iter()
+            .map(|t| {
+                BfvParametersBuilder::new()
+                    .set_degree(16)
+                    .set_plaintext_modulus(*t)
+                    .set_moduli_sizes(&[62; 3])
+                    .set_minimum_security(0)
+                    .build_arc()
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let crt = CrtBfv::new(&pars)?;
+        assert_eq!(
+            crt.plaintext_modulus(),
+            &(BigUint::from(65537u64) * 114689u64 * 147457u64)
+        );
+
+        let sks = pars
+            .iter()
+            .map(|par| SecretKey::random(par, &mut rng))
+            .collect::<Vec<_>>();
+        let multiplicators = sks
+            .iter()
+            .map(|sk| Multiplicator::default(&RelinearizationKey::new(sk, &mut rng)?))
+            .collect::<crate::Result<Vec<_>>>()?;
+
+        // Products of 25-bit integers exceed each plaintext modulus.
+        let a = (0..16)
+            .map(|_| BigUint::from(rng.gen_range(0..1u64 << 25)))
+            .collect::<Vec<_>>();
+        let b = (0..16)
+            .map(|_| BigUint::from(rng.gen_range(0..1u64 << 25)))
+            .collect::<Vec<_>>();
+        let ct_a = crt.encrypt(&sks, &a, Encoding::simd(), &mut rng)?;
+        let ct_b = crt.encrypt(&sks, &b, Encoding::simd(), &mut rng)?;
+        let ct = crt.evaluate(|i, _| {
+            let product = multiplicators[i].multiply(&ct_a[i], &ct_b[i])?;
+            Ok(&product + &ct_a[i])
+        })?;
+        let expected = a.iter().zip(&b).map(|(a, b)| a * b + a).collect::<Vec<_>>();
+        assert_eq!(crt.decrypt(&sks, &ct, Encoding::simd())?, expected);
+
+        assert!(crt.decrypt(&sks[..2], &ct, Encoding::simd()).is_err());
+        assert!(CrtBfv::new(&[]).is_err());
+        assert!(CrtBfv::new(&[pars[0].clone(), pars[0].clone()]).is_err());
+        let other = BfvParametersBuilder::new()
+            .set_degree(32)
+            .set_plaintext_modulus(7)
+            .set_moduli_sizes(&[62])
+            .set_minimum_security(0)
+            .build_arc()?;
+        assert!(CrtBfv::new(&[pars[0].clone(), other]).is_err());
+        Ok(())
+    }
+}
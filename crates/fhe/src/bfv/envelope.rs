@@ -0,0 +1,256 @@
+//! Textual envelopes of serialized ciphertexts and public keys.
+//!
+//! An [`Envelope`] is written as `<type>.<version>.<fingerprint>.<payload>`,
+//! where the type is `ct` for a ciphertext or `pk` for a public key, the
+//! version is the serialization format version, the fingerprint is the
+//! fingerprint of the parameters in hexadecimal, and the payload is the
+//! serialization in unpadded URL-safe base64. This representation only uses
+//! URL-safe characters, and can be embedded in JSON documents, configuration
+//! files or QR codes.
+
+use std::fmt::Display;
+use std::str::FromStr;
+use std::sync::Arc;
+
+use fhe_traits::{DeserializeParametrized, Serialize};
+
+use crate::bfv::{inspect_bytes, BfvParameters, Ciphertext, ObjectType, PublicKey};
+use crate::proto::{check_version, FORMAT_VERSION};
+use crate::{Error, Result};
+
+/// The alphabet of the URL-safe base64 encoding.
+const BASE64: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_";
+
+/// A textual envelope of a serialized [`Ciphertext`] or [`PublicKey`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Envelope {
+    object_type: ObjectType,
+    version: u32,
+    fingerprint: u64,
+    payload: Vec<u8>,
+}
+
+impl Envelope {
+    /// Returns the type of the enveloped object.
+    pub fn object_type(&self) -> ObjectType {
+        self.object_type
+    }
+
+    /// Returns the serialization format version of the payload.
+    pub fn version(&self) -> u32 {
+        self.version
+    }
+
+    /// Returns the fingerprint of the parameters of the enveloped object.
+    pub fn fingerprint(&self) -> u64 {
+        self.fingerprint
+    }
+
+    /// Returns the serialization of the enveloped object.
+    pub fn payload(&self) -> &[u8] {
+        &self.payload
+    }
+
+    fn check(&self, object_type: ObjectType, par: &Arc<BfvParameters>) -> Result<()> {
+        if self.object_type != object_type {
+            return Err(Error::UnspecifiedInput(format!(
+                "Expected an envelope of a {object_type:?}, found a {:?}",
+                self.object_type
+            )));
+        }
+        par.check_serialized_fingerprint(self.fingerprint)
+    }
+
+    /// Deserializes the enveloped [`Ciphertext`] with the parameters `par`.
+    pub fn to_ciphertext(&self, par: &Arc<BfvParameters>) -> Result<Ciphertext> {
+        self.check(ObjectType::Ciphertext, par)?;
+        Ciphertext::from_bytes(&self.payload, par)
+    }
+
+    /// Deserializes the enveloped [`PublicKey`] with the parameters `par`.
+    pub fn to_public_key(&self, par: &Arc<BfvParameters>) -> Result<PublicKey> {
+        self.check(ObjectType::PublicKey, par)?;
+        PublicKey::from_bytes(&self.payload, par)
+    }
+}
+
+impl From<&Ciphertext> for Envelope {
+    fn from(ct: &Ciphertext) -> Self {
+        Self {
+            object_type: ObjectType::Ciphertext,
+            version: FORMAT_VERSION,
+            fingerprint: ct.par.fingerprint(),
+            payload: ct.to_bytes(),
+        }
+    }
+}
+
+impl From<&PublicKey> for Envelope {
+    fn from(pk: &PublicKey) -> Self {
+        Self {
+            object_type: ObjectType::PublicKey,
+            version: FORMAT_VERSION,
+            fingerprint: pk.par.fingerprint(),
+            payload: pk.to_bytes(),
+        }
+    }
+}
+
+impl Display for Envelope {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let object_type = match self.object_type {
+            ObjectType::Ciphertext => "ct",
+            ObjectType::PublicKey => "pk",
+            _ => unreachable!(),
+        };
+        write!(
+            f,
+            "{object_type}.{}.{:016x}.{}",
+            self.version,
+            self.fingerprint,
+            to_base64(&self.payload)
+        )
+    }
+}
+
+impl FromStr for Envelope {
+    type Err = Error;
+
+    /// Parses an envelope, and checks that its header matches its payload.
+    fn from_str(s: &str) -> Result<Self> {
+        let fields = s.trim().split('.').collect::<Vec<_>>();
+        let [object_type, version, fingerprint, payload] = fields[..] else {
+            return Err(Error::SerializationError);
+        };
+        let object_type = match object_type {
+            "ct" => ObjectType::Ciphertext,
+            "pk" => ObjectType::PublicKey,
+            _ => return Err(Error::SerializationError),
+        };
+        let version = version.parse().map_err(|_| Error::SerializationError)?;
+        check_version(version)?;
+        let fingerprint =
+            u64::from_str_radix(fingerprint, 16).map_err(|_| Error::SerializationError)?;
+        let payload = from_base64(payload)?;
+
+        let info = inspect_bytes(&payload)?;
+        if info.object_type != object_type
+            || info.version != version
+            || info.fingerprint != Some(fingerprint)
+        {
+            return Err(Error::SerializationError);
+        }
+
+        Ok(Self {
+            object_type,
+            version,
+            fingerprint,
+            payload,
+        })
+    }
+}
+
+impl Display for Ciphertext {
+    /// Formats the ciphertext as an [`Envelope`].
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        Envelope::from(self).fmt(f)
+    }
+}
+
+impl Display for PublicKey {
+    /// Formats the public key as an [`Envelope`].
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        Envelope::from(self).fmt(f)
+    }
+}
+
+/// Encodes bytes in unpadded URL-safe base64.
+fn to_base64(bytes: &[u8]) -> String {
+    let mut s = String::with_capacity((4 * bytes.len()).div_ceil(3));
+    for chunk in bytes.chunks(3) {
+        let mut block = [0u8; 3];
+        block[..chunk.len()].copy_from_slice(chunk);
+        let n = u32::from_be_bytes([0, block[0], block[1], block[2]]);
+        (0..=chunk.len()).for_each(|i| s.push(BASE64[(n >> (18 - 6 * i)) as usize & 63] as char));
+    }
+    s
+}
+
+/// Decodes bytes from unpadded URL-safe base64.
+fn from_base64(s: &str) -> Result<Vec<u8>> {
+    if s.len() % 4 == 1 {
+        return Err(Error::SerializationError);
+    }
+    let mut bytes = Vec::with_capacity(3 * s.len() / 4);
+    for chunk in s.as_bytes().chunks(4) {
+        let mut n = 0u32;
+        for (i, c) in chunk.iter().enumerate() {
+            let digit = BASE64
+                .iter()
+                .position(|b| b == c)
+                .ok_or(Error::SerializationError)?;
+            n |= (digit as u32) << (18 - 6 * i);
+        }
+        let block = n.to_be_bytes();
+        let len = chunk.len() - 1;
+        // Reject the encodings whose unused bits are not zero.
+        if block[1 + len..].iter().any(|b| *b != 0) {
+            return Err(Error::SerializationError);
+        }
+        bytes.extend_from_slice(&block[1..1 + len]);
+    }
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{from_base64, to_base64, Envelope};
+    use crate::bfv::{
+        BfvParameters, Ciphertext, Encoding, ObjectType, Plaintext, PublicKey, SecretKey,
+    };
+    use fhe_traits::{FheDecoder, FheDecrypter, FheEncoder, FheEncrypter};
+    use rand::{thread_rng, RngCore};
+    use std::error::Error;
+
+    #[test]
+    fn envelopes() -> Result<(), Box<dyn Error>> {
+        let mut rng = thread_rng();
+        assert_eq!(to_base64(b"fhe.rs!"), "ZmhlLnJzIQ");
+        for len in 0..10 {
+            let mut bytes = vec![0u8; len];
+            rng.fill_bytes(&mut bytes);
+            assert_eq!(from_base64(&to_base64(&bytes))?, bytes);
+        }
+        assert!(from_base64("ZmhlLnJzIR").is_err());
+        assert!(from_base64("ZmhlL").is_err());
+        assert!(from_base64("Zmhl+nJz").is_err());
+
+        let par = BfvParameters::default_arc(2, 16);
+        let sk = SecretKey::random(&par, &mut rng);
+        let pk = PublicKey::new(&sk, &mut rng);
+        let pt = Plaintext::try_encode(&[1u64, 2, 3], Encoding::poly(), &par)?;
+        let ct: Ciphertext = pk.try_encrypt(&pt, &mut rng)?;
+
+        let s = ct.to_string();
+        assert!(s.starts_with(&format!("ct.1.{:016x}.", par.fingerprint())));
+        let envelope: Envelope = s.parse()?;
+        assert_eq!(envelope.object_type(), ObjectType::Ciphertext);
+        let pt2 = sk.try_decrypt(&envelope.to_ciphertext(&par)?)?;
+        assert_eq!(
+            Vec::<u64>::try_decode(&pt2, Encoding::poly())?,
+            Vec::<u64>::try_decode(&pt, Encoding::poly())?
+        );
+        assert!(envelope.to_public_key(&par).is_err());
+        let other = BfvParameters::default_arc(2, 32);
+        assert!(envelope.to_ciphertext(&other).is_err());
+
+        let envelope: Envelope = pk.to_string().parse()?;
+        assert_eq!(envelope.to_public_key(&par)?, pk);
+
+        assert!(s.replacen("ct.", "pk.", 1).parse::<Envelope>().is_err());
+        assert!(s.replacen(".1.", ".9.", 1).parse::<Envelope>().is_err());
+        assert!(s[..s.len() - 4].parse::<Envelope>().is_err());
+        assert!("ct.1.0".parse::<Envelope>().is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,230 @@
+//! Generation of arbitrary values and of serialized corpora for fuzzing and
+//! property tests.
+//!
+//! The arbitrary plaintexts are generated with the [`parameters`] of this
+//! module, which must be used to operate on them.
+
+use crate::bfv::{
+    BfvParameters, BfvParametersBuilder, Ciphertext, Encoding, EvaluationKeyBuilder, Plaintext,
+    PublicKey, RGSWCiphertext, RelinearizationKey, SecretKey,
+};
+use crate::Result;
+use fhe_traits::{FheEncoder, FheEncrypter, Serialize};
+use rand::{CryptoRng, RngCore};
+use std::sync::{Arc, OnceLock};
+
+/// Returns the parameters of the arbitrary plaintexts, of degree 16 and
+/// plaintext modulus 1153, with 1 to 3 moduli of 62 bits.
+pub fn parameters() -> &'static [Arc<BfvParameters>] {
+    static PARAMETERS: OnceLock<Vec<Arc<BfvParameters>>> = OnceLock::new();
+    PARAMETERS.get_or_init(|| {
+        (1..=3)
+            .map(|num_moduli| {
+                BfvParametersBuilder::new()
+                    .set_degree(16)
+                    .set_plaintext_modulus(1153)
+                    .set_moduli_sizes(&vec![62; num_moduli])
+                    .set_minimum_security(0)
+                    .build_arc()
+                    .unwrap()
+            })
+            .collect()
+    })
+}
+
+/// Encodes arbitrary values, which are reduced modulo the plaintext modulus.
+fn plaintext(par: &Arc<BfvParameters>, simd: bool, level: usize, values: &[u64]) -> Plaintext {
+    let encoding = if simd {
+        Encoding::simd_at_level(level)
+    } else {
+        Encoding::poly_at_level(level)
+    };
+    let values = values
+        .iter()
+        .map(|v| v % par.plaintext())
+        .collect::<Vec<_>>();
+    Plaintext::try_encode(&values, encoding, par).unwrap()
+}
+
+/// Returns a corpus of valid serializations of the objects of the library
+/// for the parameters `par`, labelled by the type of the object, to seed the
+/// fuzzing of the deserialization.
+///
+/// The corpus contains the parameters, a compressed and an uncompressed
+/// ciphertext, a ciphertext of size 3, a public key and a RGSW ciphertext, as
+/// well as a relinearization key and an evaluation key when the parameters
+/// support key switching.
+pub fn serialized_corpus<R: RngCore + CryptoRng>(
+    par: &Arc<BfvParameters>,
+    rng: &mut R,
+) -> Result<Vec<(&'static str, Vec<u8>)>> {
+    let sk = SecretKey::random(par, rng);
+    let v = par.plaintext.random_vec(par.degree(), rng);
+    let pt = plaintext(par, par.op.is_some(), 0, &v);
+    let ct: Ciphertext = sk.try_encrypt(&pt, rng)?;
+    let rgsw: RGSWCiphertext = sk.try_encrypt(&pt, rng)?;
+
+    let mut corpus = vec![
+        ("parameters", par.to_bytes()),
+        ("ciphertext", ct.to_bytes()),
+        ("ciphertext", (&ct + &ct).to_bytes()),
+        ("ciphertext", (&ct * &ct).to_bytes()),
+        ("public_key", PublicKey::new(&sk, rng).to_bytes()),
+        ("rgsw_ciphertext", rgsw.to_bytes()),
+    ];
+    if par.moduli().len() > 1 {
+        corpus.push((
+            "relinearization_key",
+            RelinearizationKey::new(&sk, rng)?.to_bytes(),
+        ));
+        corpus.push((
+            "evaluation_key",
+            EvaluationKeyBuilder::new(&sk)?
+                .enable_row_rotation()?
+                .build(rng)?
+                .to_bytes(),
+        ));
+    }
+    Ok(corpus)
+}
+
+#[cfg(feature = "arbitrary")]
+impl<'a> arbitrary::Arbitrary<'a> for Encoding {
+    fn arbitrary(u: &mut arbitrary::Unstructured<'a>) -> arbitrary::Result<Self> {
+        let level = u.int_in_range(0..=3)?;
+        Ok(if u.arbitrary()? {
+            Encoding::simd_at_level(level)
+        } else {
+            Encoding::poly_at_level(level)
+        })
+    }
+}
+
+#[cfg(feature = "arbitrary")]
+impl<'a> arbitrary::Arbitrary<'a> for Plaintext {
+    fn arbitrary(u: &mut arbitrary::Unstructured<'a>) -> arbitrary::Result<Self> {
+        let par = u.choose(parameters())?;
+        let simd = u.arbitrary()?;
+        let level = u.int_in_range(0..=par.max_level())?;
+        let values = (0..par.degree())
+            .map(|_| u.arbitrary())
+            .collect::<arbitrary::Result<Vec<u64>>>()?;
+        Ok(plaintext(par, simd, level, &values))
+    }
+}
+
+#[cfg(feature = "proptest")]
+impl proptest::arbitrary::Arbitrary for Encoding {
+    type Parameters = ();
+    type Strategy = proptest::strategy::BoxedStrategy<Encoding>;
+
+    fn arbitrary_with(_: Self::Parameters) -> Self::Strategy {
+        use proptest::prelude::*;
+
+        (any::<bool>(), 0..=3usize)
+            .prop_map(|(simd, level)| {
+                if simd {
+                    Encoding::simd_at_level(level)
+                } else {
+                    Encoding::poly_at_level(level)
+                }
+            })
+            .boxed()
+    }
+}
+
+#[cfg(feature = "proptest")]
+impl proptest::arbitrary::Arbitrary for Plaintext {
+    type Parameters = ();
+    type Strategy = proptest::strategy::BoxedStrategy<Plaintext>;
+
+    fn arbitrary_with(_: Self::Parameters) -> Self::Strategy {
+        use proptest::prelude::*;
+
+        proptest::sample::select(parameters())
+            .prop_flat_map(|par| {
+                let values = proptest::collection::vec(any::<u64>(), par.degree());
+                (
+                    Just(par.clone()),
+                    any::<bool>(),
+                    0..=par.max_level(),
+                    values,
+                )
+            })
+            .prop_map(|(par, simd, level, values)| plaintext(&par, simd, level, &values))
+            .boxed()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parameters, serialized_corpus};
+    use crate::bfv::{Ciphertext, EvaluationKey, PublicKey, RGSWCiphertext, RelinearizationKey};
+    use fhe_traits::DeserializeParametrized;
+    use rand::thread_rng;
+    use std::error::Error;
+
+    #[test]
+    fn corpus() -> Result<(), Box<dyn Error>> {
+        let mut rng = thread_rng();
+        for par in parameters() {
+            let corpus = serialized_corpus(par, &mut rng)?;
+            assert_eq!(corpus.len(), if par.moduli().len() > 1 { 8 } else { 6 });
+            for (name, bytes) in corpus {
+                match name {
+                    "parameters" => {
+                        crate::bfv::BfvParameters::try_deserialize_insecure(&bytes)?;
+                    }
+                    "ciphertext" => {
+                        Ciphertext::from_bytes(&bytes, par)?;
+                    }
+                    "public_key" => {
+                        PublicKey::from_bytes(&bytes, par)?;
+                    }
+                    "rgsw_ciphertext" => {
+                        RGSWCiphertext::from_bytes(&bytes, par)?;
+                    }
+                    "relinearization_key" => {
+                        RelinearizationKey::from_bytes(&bytes, par)?;
+                    }
+                    "evaluation_key" => {
+                        EvaluationKey::from_bytes(&bytes, par)?;
+                    }
+                    _ => panic!("Unexpected entry {name}"),
+                }
+            }
+        }
+        Ok(())
+    }
+
+    #[cfg(feature = "arbitrary")]
+    #[test]
+    fn arbitrary() -> Result<(), Box<dyn Error>> {
+        use crate::bfv::Plaintext;
+        use arbitrary::{Arbitrary, Unstructured};
+        use rand::RngCore;
+
+        let mut rng = thread_rng();
+        for _ in 0..100 {
+            let mut bytes = vec![0u8; 1024];
+            rng.fill_bytes(&mut bytes);
+            let pt = Plaintext::arbitrary(&mut Unstructured::new(&bytes))?;
+            assert!(parameters().contains(&pt.par));
+            assert!(pt.level() <= pt.par.max_level());
+        }
+        Ok(())
+    }
+
+    #[cfg(feature = "proptest")]
+    proptest::proptest! {
+        #[test]
+        fn encode_decode(pt: crate::bfv::Plaintext) {
+            use fhe_traits::{FheDecoder, FheEncoder};
+
+            let encoding = pt.encoding.clone().unwrap();
+            let values = Vec::<u64>::try_decode(&pt, encoding.clone()).unwrap();
+            let pt2 = crate::bfv::Plaintext::try_encode(&values, encoding, &pt.par).unwrap();
+            proptest::prop_assert_eq!(pt, pt2);
+        }
+    }
+}
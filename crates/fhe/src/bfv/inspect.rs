@@ -0,0 +1,246 @@
+//! Inspection of serialized objects.
+//!
+//! [`inspect_bytes`] reports the type, format version, parameters fingerprint
+//! and level of a serialized ciphertext or key by scanning the top-level
+//! fields of its message, without decoding its polynomials, so that the
+//! serializations can be routed and validated cheaply.
+
+use std::collections::BTreeMap;
+
+use prost::encoding::{decode_key, decode_varint, WireType};
+
+use crate::{Error, Result};
+
+/// The type of a serialized object.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ObjectType {
+    /// A [`Ciphertext`](crate::bfv::Ciphertext).
+    Ciphertext,
+    /// A [`PublicKey`](crate::bfv::PublicKey).
+    PublicKey,
+    /// A [`SecretKey`](crate::bfv::SecretKey).
+    SecretKey,
+    /// A [`RelinearizationKey`](crate::bfv::RelinearizationKey).
+    RelinearizationKey,
+    /// An [`EvaluationKey`](crate::bfv::EvaluationKey).
+    EvaluationKey,
+    /// An [`RGSWCiphertext`](crate::bfv::RGSWCiphertext).
+    RgswCiphertext,
+}
+
+/// The information about a serialized object returned by [`inspect_bytes`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ObjectInfo {
+    /// The type of the object.
+    pub object_type: ObjectType,
+    /// The version of the serialization format, 0 for older releases.
+    pub version: u32,
+    /// The fingerprint of the parameters of the object, if any. An evaluation
+    /// key without Galois keys does not record its parameters.
+    pub fingerprint: Option<u64>,
+    /// The level of the object, i.e., the level of the ciphertexts for the
+    /// keys, if any.
+    pub level: Option<usize>,
+    /// The size of the serialization, in bytes.
+    pub size: usize,
+}
+
+/// The top-level fields of a message, indexed by their tags. Only the first
+/// value of the repeated fields is kept.
+#[derive(Debug, Default)]
+struct Fields<'a> {
+    varint: BTreeMap<u32, u64>,
+    fixed64: BTreeMap<u32, u64>,
+    length_delimited: BTreeMap<u32, &'a [u8]>,
+}
+
+impl<'a> Fields<'a> {
+    fn scan(mut bytes: &'a [u8]) -> Result<Self> {
+        let mut fields = Fields::default();
+        while !bytes.is_empty() {
+            let (tag, wire_type) = decode_key(&mut bytes).map_err(|_| Error::SerializationError)?;
+            match wire_type {
+                WireType::Varint => {
+                    let value = decode_varint(&mut bytes).map_err(|_| Error::SerializationError)?;
+                    fields.varint.entry(tag).or_insert(value);
+                }
+                WireType::SixtyFourBit => {
+                    if bytes.len() < 8 {
+                        return Err(Error::SerializationError);
+                    }
+                    let (value, rest) = bytes.split_at(8);
+                    let value = u64::from_le_bytes(value.try_into().unwrap());
+                    fields.fixed64.entry(tag).or_insert(value);
+                    bytes = rest;
+                }
+                WireType::ThirtyTwoBit => {
+                    bytes = bytes.get(4..).ok_or(Error::SerializationError)?;
+                }
+                WireType::LengthDelimited => {
+                    let len = decode_varint(&mut bytes).map_err(|_| Error::SerializationError)?;
+                    let len = usize::try_from(len).map_err(|_| Error::SerializationError)?;
+                    if len > bytes.len() {
+                        return Err(Error::SerializationError);
+                    }
+                    let (value, rest) = bytes.split_at(len);
+                    fields.length_delimited.entry(tag).or_insert(value);
+                    bytes = rest;
+                }
+                WireType::StartGroup | WireType::EndGroup => {
+                    return Err(Error::SerializationError);
+                }
+            }
+        }
+        Ok(fields)
+    }
+
+    fn version(&self, tag: u32) -> u32 {
+        self.varint.get(&tag).copied().unwrap_or_default() as u32
+    }
+
+    fn level(&self, tag: u32) -> Option<usize> {
+        Some(self.varint.get(&tag).copied().unwrap_or_default() as usize)
+    }
+
+    /// Interprets the fields as a ciphertext, identified by its fingerprint.
+    fn ciphertext(&self) -> Option<(u32, Option<u64>, Option<usize>)> {
+        let fingerprint = *self.fixed64.get(&4)?;
+        Some((self.version(5), Some(fingerprint), self.level(3)))
+    }
+
+    /// Interprets the fields as a key switching key, identified by its
+    /// fingerprint.
+    fn key_switching_key(&self) -> Option<(u32, Option<u64>, Option<usize>)> {
+        let fingerprint = *self.fixed64.get(&7)?;
+        Some((self.version(8), Some(fingerprint), self.level(4)))
+    }
+}
+
+/// Inspects the serialization of a ciphertext or a key, without deserializing
+/// it.
+///
+/// The type of the object is inferred from the layout of its message, which
+/// is not authenticated: the object must still be deserialized with the
+/// parameters matching its fingerprint before being used.
+pub fn inspect_bytes(bytes: &[u8]) -> Result<ObjectInfo> {
+    let fields = Fields::scan(bytes)?;
+    let nested = |tag: u32| -> Result<Option<Fields>> {
+        fields
+            .length_delimited
+            .get(&tag)
+            .map(|bytes| Fields::scan(bytes))
+            .transpose()
+    };
+
+    let (object_type, (version, fingerprint, level)) = if let Some(info) = fields.ciphertext() {
+        (ObjectType::Ciphertext, info)
+    } else if let Some(fingerprint) = fields.fixed64.get(&2) {
+        (
+            ObjectType::SecretKey,
+            (fields.version(3), Some(*fingerprint), None),
+        )
+    } else if let Some(inner) = nested(1)? {
+        if let Some(info) = inner.ciphertext() {
+            (ObjectType::PublicKey, info)
+        } else if let Some(info) = inner.key_switching_key() {
+            if nested(2)?.is_some_and(|inner| inner.key_switching_key().is_some()) {
+                (ObjectType::RgswCiphertext, info)
+            } else if fields.varint.is_empty() && fields.length_delimited.len() == 1 {
+                (ObjectType::RelinearizationKey, info)
+            } else {
+                return Err(Error::SerializationError);
+            }
+        } else {
+            return Err(Error::SerializationError);
+        }
+    } else if !bytes.is_empty()
+        && fields.fixed64.is_empty()
+        && fields.varint.keys().all(|tag| [3, 4, 6].contains(tag))
+        && fields
+            .length_delimited
+            .keys()
+            .all(|tag| [2, 5].contains(tag))
+    {
+        // The parameters of an evaluation key are recorded in its Galois keys.
+        let fingerprint = match nested(2)? {
+            Some(gk) => {
+                let ksk = gk
+                    .length_delimited
+                    .get(&1)
+                    .map(|bytes| Fields::scan(bytes))
+                    .transpose()?
+                    .and_then(|ksk| ksk.key_switching_key())
+                    .ok_or(Error::SerializationError)?;
+                ksk.1
+            }
+            None => None,
+        };
+        (
+            ObjectType::EvaluationKey,
+            (fields.version(6), fingerprint, fields.level(3)),
+        )
+    } else {
+        return Err(Error::SerializationError);
+    };
+
+    Ok(ObjectInfo {
+        object_type,
+        version,
+        fingerprint,
+        level,
+        size: bytes.len(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{inspect_bytes, ObjectType};
+    use crate::bfv::{
+        BfvParameters, Ciphertext, Encoding, EvaluationKeyBuilder, Plaintext, PublicKey,
+        RGSWCiphertext, RelinearizationKey, SecretKey,
+    };
+    use crate::proto::FORMAT_VERSION;
+    use fhe_traits::{FheEncoder, FheEncrypter, Serialize};
+    use rand::thread_rng;
+    use std::error::Error;
+
+    #[test]
+    fn inspect() -> Result<(), Box<dyn Error>> {
+        let mut rng = thread_rng();
+        let par = BfvParameters::default_arc(3, 16);
+        let sk = SecretKey::random(&par, &mut rng);
+        let pt = Plaintext::try_encode(&[1u64, 2, 3], Encoding::poly_at_level(1), &par)?;
+        let ct: Ciphertext = sk.try_encrypt(&pt, &mut rng)?;
+        let pk = PublicKey::new(&sk, &mut rng);
+        let rk = RelinearizationKey::new_leveled(&sk, 1, 0, &mut rng)?;
+        let ek = EvaluationKeyBuilder::new_leveled(&sk, 1, 0)?
+            .enable_inner_sum()?
+            .build(&mut rng)?;
+        let empty_ek = EvaluationKeyBuilder::new_leveled(&sk, 1, 0)?.build(&mut rng)?;
+        let rgsw: RGSWCiphertext = sk.try_encrypt(&pt, &mut rng)?;
+
+        for (bytes, object_type, level) in [
+            (ct.to_bytes().to_vec(), ObjectType::Ciphertext, Some(1)),
+            (pk.to_bytes(), ObjectType::PublicKey, Some(0)),
+            (sk.to_bytes().to_vec(), ObjectType::SecretKey, None),
+            (rk.to_bytes(), ObjectType::RelinearizationKey, Some(1)),
+            (ek.to_bytes(), ObjectType::EvaluationKey, Some(1)),
+            (rgsw.to_bytes(), ObjectType::RgswCiphertext, Some(1)),
+        ] {
+            let info = inspect_bytes(&bytes)?;
+            assert_eq!(info.object_type, object_type);
+            assert_eq!(info.version, FORMAT_VERSION);
+            assert_eq!(info.fingerprint, Some(par.fingerprint()));
+            assert_eq!(info.level, level);
+            assert_eq!(info.size, bytes.len());
+            assert!(inspect_bytes(&bytes[..bytes.len() - 1]).is_err());
+        }
+
+        let info = inspect_bytes(&empty_ek.to_bytes())?;
+        assert_eq!(info.object_type, ObjectType::EvaluationKey);
+        assert_eq!(info.fingerprint, None);
+        assert!(inspect_bytes(&[]).is_err());
+        assert!(inspect_bytes(&par.to_bytes()).is_err());
+        Ok(())
+    }
+}
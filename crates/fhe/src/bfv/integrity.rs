@@ -0,0 +1,133 @@
+//! Integrity tags on serializations.
+//!
+//! [`seal`] appends to a serialization either its SHA-256 checksum, which
+//! detects transport corruption, or its HMAC-SHA256 under a key shared by the
+//! sender and the receiver, which also detects tampering. [`open`] verifies
+//! the tag before the serialization is deserialized.
+
+use sha2::{Digest, Sha256};
+use zeroize::Zeroizing;
+
+use crate::{Error, Result};
+
+/// The size of the integrity tags, in bytes.
+pub const TAG_SIZE: usize = 32;
+
+/// The size of the blocks of SHA-256, in bytes.
+const BLOCK_SIZE: usize = 64;
+
+/// Computes the tag of `bytes`: its HMAC-SHA256 under `key` if any, or its
+/// SHA-256 digest otherwise.
+pub(crate) fn tag(bytes: &[u8], key: Option<&[u8]>) -> [u8; TAG_SIZE] {
+    let Some(key) = key else {
+        return Sha256::digest(bytes).into();
+    };
+
+    let mut block = Zeroizing::new([0u8; BLOCK_SIZE]);
+    if key.len() > BLOCK_SIZE {
+        block[..TAG_SIZE].copy_from_slice(&Sha256::digest(key));
+    } else {
+        block[..key.len()].copy_from_slice(key);
+    }
+    let pad = |byte: u8| {
+        let mut pad = Zeroizing::new(*block);
+        pad.iter_mut().for_each(|b| *b ^= byte);
+        pad
+    };
+    let inner = Sha256::new()
+        .chain_update(pad(0x36).as_slice())
+        .chain_update(bytes)
+        .finalize();
+    Sha256::new()
+        .chain_update(pad(0x5c).as_slice())
+        .chain_update(inner)
+        .finalize()
+        .into()
+}
+
+/// Appends an integrity tag to the serialization `bytes`.
+///
+/// When `key` is `None`, the tag is the SHA-256 checksum of the
+/// serialization, which anyone can recompute: it only detects accidental
+/// corruption. Otherwise, the tag is the HMAC-SHA256 of the serialization
+/// under `key`, which should be a uniformly random key of at least 32 bytes.
+pub fn seal(bytes: &[u8], key: Option<&[u8]>) -> Vec<u8> {
+    let mut sealed = Vec::with_capacity(bytes.len() + TAG_SIZE);
+    sealed.extend_from_slice(bytes);
+    sealed.extend_from_slice(&tag(bytes, key));
+    sealed
+}
+
+/// Verifies the integrity tag of a serialization sealed with [`seal`] using
+/// the same `key`, and returns the serialization without its tag.
+///
+/// The comparison of the tags runs in constant time.
+pub fn open<'a>(sealed: &'a [u8], key: Option<&[u8]>) -> Result<&'a [u8]> {
+    if sealed.len() < TAG_SIZE {
+        return Err(Error::IntegrityCheckFailed);
+    }
+    let (bytes, expected) = sealed.split_at(sealed.len() - TAG_SIZE);
+    verify(bytes, key, expected)?;
+    Ok(bytes)
+}
+
+/// Verifies in constant time that `expected` is the tag of `bytes`.
+pub(crate) fn verify(bytes: &[u8], key: Option<&[u8]>, expected: &[u8]) -> Result<()> {
+    let difference = tag(bytes, key)
+        .iter()
+        .zip(expected)
+        .fold(0u8, |acc, (a, b)| acc | (a ^ b));
+    if difference == 0 && expected.len() == TAG_SIZE {
+        Ok(())
+    } else {
+        Err(Error::IntegrityCheckFailed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{open, seal, tag, TAG_SIZE};
+    use crate::bfv::{BfvParameters, Ciphertext, Encoding, Plaintext, SecretKey};
+    use crate::Error as FheError;
+    use fhe_traits::{DeserializeParametrized, FheEncoder, FheEncrypter, Serialize};
+    use rand::{thread_rng, RngCore};
+    use std::error::Error;
+
+    #[test]
+    fn seal_open() -> Result<(), Box<dyn Error>> {
+        // Test case 2 of RFC 4231.
+        let mac = tag(b"what do ya want for nothing?", Some(b"Jefe"));
+        let expected = "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843";
+        assert_eq!(
+            mac.iter().map(|b| format!("{b:02x}")).collect::<String>(),
+            expected
+        );
+
+        let mut rng = thread_rng();
+        let par = BfvParameters::default_arc(2, 16);
+        let sk = SecretKey::random(&par, &mut rng);
+        let pt = Plaintext::try_encode(&[1u64, 2, 3], Encoding::poly(), &par)?;
+        let ct: Ciphertext = sk.try_encrypt(&pt, &mut rng)?;
+        let bytes = ct.to_bytes();
+        let mut key = [0u8; 32];
+        rng.fill_bytes(&mut key);
+
+        for key in [None, Some(&key[..])] {
+            let mut sealed = seal(&bytes, key);
+            assert_eq!(sealed.len(), bytes.len() + TAG_SIZE);
+            assert_eq!(Ciphertext::from_bytes(open(&sealed, key)?, &par)?, ct);
+
+            sealed[3] ^= 1;
+            assert!(matches!(
+                open(&sealed, key),
+                Err(FheError::IntegrityCheckFailed)
+            ));
+        }
+
+        let sealed = seal(&bytes, Some(&key));
+        assert!(open(&sealed, None).is_err());
+        assert!(open(&sealed, Some(&[0u8; 32])).is_err());
+        assert!(open(&sealed[..TAG_SIZE - 1], Some(&key)).is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,393 @@
+//! Known-answer tests for the BFV primitives.
+//!
+//! A [`TestVector`] records, for a primitive and a seed, the coefficients of
+//! the polynomials of the ciphertext output by the primitive and the decrypted
+//! values. All the randomness is derived from the seed, so that the vectors
+//! are deterministic and can be used to check alternative implementations, or
+//! refactors of this library, bit-for-bit. The vectors do not depend on the
+//! serialization format of the ciphertexts, nor on their metadata.
+//!
+//! The vectors can be written and read in a textual format, one vector per
+//! line, with the primitive, the fingerprint of the parameters, the seed and
+//! the coefficients in hexadecimal, and the decrypted values separated by
+//! commas.
+
+use crate::bfv::{
+    towers, BfvParameters, Ciphertext, Encoding, EvaluationKeyBuilder, Multiplicator, Plaintext,
+    PublicKey, RelinearizationKey, SecretKey,
+};
+use crate::{Error, Result};
+use fhe_traits::{FheDecoder, FheDecrypter, FheEncoder, FheEncrypter};
+use rand::SeedableRng;
+use rand_chacha::ChaCha8Rng;
+use std::{fmt::Display, str::FromStr, sync::Arc};
+
+/// A primitive covered by the known-answer tests.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Primitive {
+    /// Encryption with the secret key.
+    SecretKeyEncryption,
+    /// Encryption with the public key.
+    PublicKeyEncryption,
+    /// Addition of two ciphertexts.
+    Addition,
+    /// Multiplication of two ciphertexts followed by a relinearization.
+    Multiplication,
+    /// Rotation of the rows.
+    RowRotation,
+    /// Rotation of the columns by one.
+    ColumnRotation,
+    /// Modulus switching to the next level.
+    ModulusSwitching,
+}
+
+impl Primitive {
+    /// All the primitives covered by the known-answer tests.
+    pub const ALL: [Primitive; 7] = [
+        Primitive::SecretKeyEncryption,
+        Primitive::PublicKeyEncryption,
+        Primitive::Addition,
+        Primitive::Multiplication,
+        Primitive::RowRotation,
+        Primitive::ColumnRotation,
+        Primitive::ModulusSwitching,
+    ];
+
+    const fn name(&self) -> &'static str {
+        match self {
+            Primitive::SecretKeyEncryption => "sk-encrypt",
+            Primitive::PublicKeyEncryption => "pk-encrypt",
+            Primitive::Addition => "add",
+            Primitive::Multiplication => "mul",
+            Primitive::RowRotation => "rotate-rows",
+            Primitive::ColumnRotation => "rotate-columns",
+            Primitive::ModulusSwitching => "mod-switch",
+        }
+    }
+}
+
+impl Display for Primitive {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for Primitive {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|primitive| primitive.name() == s)
+            .ok_or(Error::SerializationError)
+    }
+}
+
+/// A known-answer test vector.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TestVector {
+    /// The primitive.
+    pub primitive: Primitive,
+    /// The fingerprint of the parameters.
+    pub fingerprint: u64,
+    /// The seed from which all the randomness is derived.
+    pub seed: [u8; 32],
+    /// The coefficients of the polynomials of the ciphertext output by the
+    /// primitive, in coefficient representation, polynomial by polynomial
+    /// and modulus by modulus.
+    pub coefficients: Vec<u64>,
+    /// The values decrypted from the ciphertext.
+    pub decrypted: Vec<u64>,
+}
+
+impl TestVector {
+    /// Generates the test vector of a primitive for the parameters `par` and
+    /// the seed `seed`.
+    ///
+    /// The values are encoded with the SIMD encoding when the parameters
+    /// support it, and with the polynomial encoding otherwise. Returns an
+    /// error if the parameters do not support the primitive, for example
+    /// the multiplication with a single modulus.
+    pub fn generate(
+        par: &Arc<BfvParameters>,
+        primitive: Primitive,
+        seed: [u8; 32],
+    ) -> Result<Self> {
+        let mut rng = ChaCha8Rng::from_seed(seed);
+        let encoding = if par.op.is_some() {
+            Encoding::simd()
+        } else {
+            Encoding::poly()
+        };
+
+        let sk = SecretKey::random(par, &mut rng);
+        let v1 = par.plaintext.random_vec(par.degree(), &mut rng);
+        let v2 = par.plaintext.random_vec(par.degree(), &mut rng);
+        let pt1 = Plaintext::try_encode(&v1, encoding.clone(), par)?;
+        let pt2 = Plaintext::try_encode(&v2, encoding.clone(), par)?;
+
+        let ct = match primitive {
+            Primitive::SecretKeyEncryption => sk.try_encrypt(&pt1, &mut rng)?,
+            Primitive::PublicKeyEncryption => {
+                PublicKey::new(&sk, &mut rng).try_encrypt(&pt1, &mut rng)?
+            }
+            Primitive::Addition => {
+                let ct1: Ciphertext = sk.try_encrypt(&pt1, &mut rng)?;
+                let ct2: Ciphertext = sk.try_encrypt(&pt2, &mut rng)?;
+                &ct1 + &ct2
+            }
+            Primitive::Multiplication => {
+                let ct1 = sk.try_encrypt(&pt1, &mut rng)?;
+                let ct2 = sk.try_encrypt(&pt2, &mut rng)?;
+                let rk = RelinearizationKey::new(&sk, &mut rng)?;
+                Multiplicator::default(&rk)?.multiply(&ct1, &ct2)?
+            }
+            Primitive::RowRotation => {
+                let ct = sk.try_encrypt(&pt1, &mut rng)?;
+                EvaluationKeyBuilder::new(&sk)?
+                    .enable_row_rotation()?
+                    .build(&mut rng)?
+                    .rotates_rows(&ct)?
+            }
+            Primitive::ColumnRotation => {
+                let ct = sk.try_encrypt(&pt1, &mut rng)?;
+                EvaluationKeyBuilder::new(&sk)?
+                    .enable_column_rotation(1)?
+                    .build(&mut rng)?
+                    .rotates_columns_by(&ct, 1)?
+            }
+            Primitive::ModulusSwitching => {
+                let mut ct: Ciphertext = sk.try_encrypt(&pt1, &mut rng)?;
+                ct.mod_switch_to_next_level()?;
+                ct
+            }
+        };
+
+        let pt = sk.try_decrypt(&ct)?;
+        let decrypted = Vec::<u64>::try_decode(&pt, encoding)?;
+        Ok(Self {
+            primitive,
+            fingerprint: par.fingerprint(),
+            seed,
+            coefficients: coefficients(&ct),
+            decrypted,
+        })
+    }
+
+    /// Verifies the test vector with the parameters `par`, by generating it
+    /// again and comparing the coefficients and the decrypted values.
+    pub fn verify(&self, par: &Arc<BfvParameters>) -> Result<()> {
+        par.check_serialized_fingerprint(self.fingerprint)?;
+        let expected = Self::generate(par, self.primitive, self.seed)?;
+        if expected.coefficients != self.coefficients {
+            Err(Error::KnownAnswerTestFailed(format!(
+                "the coefficients of {} differ",
+                self.primitive
+            )))
+        } else if expected.decrypted != self.decrypted {
+            Err(Error::KnownAnswerTestFailed(format!(
+                "the decrypted values of {} differ",
+                self.primitive
+            )))
+        } else {
+            Ok(())
+        }
+    }
+}
+
+impl Display for TestVector {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} {:016x} {} {} {}",
+            self.primitive,
+            self.fingerprint,
+            to_hex(&self.seed),
+            to_hex(&to_bytes(&self.coefficients)),
+            self.decrypted
+                .iter()
+                .map(|v| v.to_string())
+                .collect::<Vec<_>>()
+                .join(",")
+        )
+    }
+}
+
+impl FromStr for TestVector {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let fields = s.split_whitespace().collect::<Vec<_>>();
+        let [primitive, fingerprint, seed, coefficients, decrypted] = fields[..] else {
+            return Err(Error::SerializationError);
+        };
+        Ok(Self {
+            primitive: primitive.parse()?,
+            fingerprint: u64::from_str_radix(fingerprint, 16)
+                .map_err(|_| Error::SerializationError)?,
+            seed: from_hex(seed)?
+                .try_into()
+                .map_err(|_| Error::SerializationError)?,
+            coefficients: from_bytes(&from_hex(coefficients)?)?,
+            decrypted: decrypted
+                .split(',')
+                .map(|v| v.parse().map_err(|_| Error::SerializationError))
+                .collect::<Result<_>>()?,
+        })
+    }
+}
+
+/// Returns the coefficients of the polynomials of a ciphertext in coefficient
+/// representation, polynomial by polynomial and modulus by modulus.
+fn coefficients(ct: &Ciphertext) -> Vec<u64> {
+    towers::to_towers(ct)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .collect()
+}
+
+/// Encodes coefficients in little-endian bytes.
+pub(crate) fn to_bytes(coefficients: &[u64]) -> Vec<u8> {
+    coefficients.iter().flat_map(|c| c.to_le_bytes()).collect()
+}
+
+/// Decodes coefficients from little-endian bytes.
+fn from_bytes(bytes: &[u8]) -> Result<Vec<u64>> {
+    if bytes.len() % 8 != 0 {
+        return Err(Error::SerializationError);
+    }
+    Ok(bytes
+        .chunks_exact(8)
+        .map(|c| u64::from_le_bytes(c.try_into().unwrap()))
+        .collect())
+}
+
+/// Encodes bytes in lowercase hexadecimal.
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+/// Decodes bytes from hexadecimal.
+fn from_hex(s: &str) -> Result<Vec<u8>> {
+    if s.len() % 2 != 0 {
+        return Err(Error::SerializationError);
+    }
+    (0..s.len())
+        .step_by(2)
+        .map(|i| {
+            s.get(i..i + 2)
+                .and_then(|b| u8::from_str_radix(b, 16).ok())
+                .ok_or(Error::SerializationError)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{coefficients, to_bytes, Primitive, TestVector};
+    use crate::bfv::{BfvParameters, Ciphertext, Encoding, Plaintext, SecretKey};
+    use crate::Error;
+    use fhe_traits::{DeserializeParametrized, FheEncoder, FheEncrypter, Serialize};
+    use rand::thread_rng;
+    use sha2::{Digest, Sha256};
+    use std::error::Error as StdError;
+
+    #[test]
+    fn generate_and_verify() -> Result<(), Box<dyn StdError>> {
+        let par = BfvParameters::default_arc(3, 16);
+        for primitive in Primitive::ALL {
+            let vector = TestVector::generate(&par, primitive, [primitive as u8; 32])?;
+            assert_eq!(
+                vector,
+                TestVector::generate(&par, primitive, [primitive as u8; 32])?
+            );
+            vector.verify(&par)?;
+
+            let parsed: TestVector = vector.to_string().parse()?;
+            assert_eq!(parsed, vector);
+
+            let mut tampered = vector.clone();
+            tampered.coefficients[0] ^= 1;
+            assert!(matches!(
+                tampered.verify(&par),
+                Err(Error::KnownAnswerTestFailed(_))
+            ));
+            let mut tampered = vector.clone();
+            tampered.decrypted[0] ^= 1;
+            assert!(matches!(
+                tampered.verify(&par),
+                Err(Error::KnownAnswerTestFailed(_))
+            ));
+
+            let other = BfvParameters::default_arc(2, 16);
+            assert!(matches!(
+                vector.verify(&other),
+                Err(Error::ParameterMismatch { .. })
+            ));
+        }
+
+        assert!(TestVector::generate(
+            &BfvParameters::default_arc(1, 16),
+            Primitive::Multiplication,
+            [0; 32]
+        )
+        .is_err());
+        assert!("sk-encrypt 00".parse::<TestVector>().is_err());
+        let line = TestVector::generate(&par, Primitive::Addition, [0; 32])?.to_string();
+        let mut fields = line.split(' ').collect::<Vec<_>>();
+        fields[3] = "00";
+        assert!(fields.join(" ").parse::<TestVector>().is_err());
+        assert!("unknown".parse::<Primitive>().is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn known_answers() -> Result<(), Box<dyn StdError>> {
+        // The SHA-256 digests of the coefficients of the ciphertexts, which only
+        // change when the sampling or the arithmetic of the library changes.
+        let par = BfvParameters::default_arc(3, 16);
+        let digests = Primitive::ALL
+            .into_iter()
+            .map(|primitive| {
+                let vector = TestVector::generate(&par, primitive, [42; 32])?;
+                let digest = Sha256::digest(to_bytes(&vector.coefficients));
+                Ok(digest[..8].iter().map(|b| format!("{b:02x}")).collect())
+            })
+            .collect::<Result<Vec<String>, Error>>()?;
+        assert_eq!(digests, KNOWN_DIGESTS);
+        Ok(())
+    }
+
+    #[test]
+    fn independent_of_serialization_and_metadata() -> Result<(), Box<dyn StdError>> {
+        let mut rng = thread_rng();
+        let par = BfvParameters::default_arc(3, 16);
+        let sk = SecretKey::random(&par, &mut rng);
+        let v = par.plaintext.random_vec(par.degree(), &mut rng);
+        let pt = Plaintext::try_encode(&v, Encoding::simd(), &par)?;
+        let ct: Ciphertext = sk.try_encrypt(&pt, &mut rng)?;
+
+        // The serialization includes a format version, which must not change the
+        // known answers.
+        let ct2 = Ciphertext::from_bytes(&ct.to_bytes(), &par)?;
+        assert_eq!(coefficients(&ct2), coefficients(&ct));
+
+        // Neither must the identifier of the key, which is only metadata.
+        assert!(ct.key_id.is_some());
+        let mut ct3 = ct.clone();
+        ct3.key_id = None;
+        assert_eq!(coefficients(&ct3), coefficients(&ct));
+        Ok(())
+    }
+
+    const KNOWN_DIGESTS: [&str; 7] = [
+        "4ab9e22a4ed312f6",
+        "55334ab8f783a29f",
+        "9a0a0fd5fb7fc3c7",
+        "b1a183cd1321f361",
+        "3cb03ddd1d2b96f5",
+        "827ae2b22dfe4ce9",
+        "59b35c753b19a654",
+    ];
+}
@@ -1,17 +1,29 @@
 //! Leveled evaluation keys for the BFV encryption scheme.
 
-use crate::bfv::{keys::GaloisKey, traits::TryConvertFrom, BfvParameters, Ciphertext, SecretKey};
-use crate::proto::bfv::{EvaluationKey as EvaluationKeyProto, GaloisKey as GaloisKeyProto};
+use crate::bfv::{
+    keys::{GaloisKey, KeySwitchingKey},
+    profiler::{Profiler, Stopwatch},
+    traits::TryConvertFrom,
+    BfvParameters, Ciphertext, SecretKey, SecretKeyOperations,
+};
+use crate::proto::{
+    bfv::{EvaluationKey as EvaluationKeyProto, GaloisKey as GaloisKeyProto},
+    check_version, FORMAT_VERSION,
+};
 use crate::{Error, Result};
 use fhe_math::rq::{traits::TryConvertFrom as TryConvertFromPoly, Poly, Representation};
 use fhe_math::zq::Modulus;
 use fhe_traits::{DeserializeParametrized, FheParametrized, Serialize};
 use prost::Message;
-use rand::{CryptoRng, RngCore};
+use rand::{CryptoRng, Rng, RngCore, SeedableRng};
+use rand_chacha::ChaCha8Rng;
 use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
 use zeroize::{Zeroize, ZeroizeOnDrop};
 
+#[cfg(feature = "noise-oracle")]
+use crate::bfv::NoiseOracle;
+
 /// Evaluation key for the BFV encryption scheme.
 ///
 /// An evaluation key enables one or several of the following operations:
@@ -21,7 +33,7 @@ use zeroize::{Zeroize, ZeroizeOnDrop};
 /// - inner sum
 #[derive(Debug, PartialEq, Eq)]
 pub struct EvaluationKey {
-    par: Arc<BfvParameters>,
+    pub(crate) par: Arc<BfvParameters>,
 
     ciphertext_level: usize,
     evaluation_key_level: usize,
@@ -34,9 +46,41 @@ pub struct EvaluationKey {
 
     /// Monomials used in expansion
     monomials: Vec<Poly>,
+
+    /// The seed from which the seeds of the Galois keys are derived, if any
+    master_seed: Option<<ChaCha8Rng as SeedableRng>::Seed>,
+
+    /// Oracle measuring the noise after every operation
+    #[cfg(feature = "noise-oracle")]
+    oracle: Option<NoiseOracle>,
+
+    /// Profiler recording the operations
+    profiler: Option<Profiler>,
 }
 
 impl EvaluationKey {
+    /// Attaches a [`NoiseOracle`] which measures the noise of the ciphertexts
+    /// after every operation.
+    #[cfg(feature = "noise-oracle")]
+    pub fn set_noise_oracle(&mut self, oracle: &NoiseOracle) {
+        self.oracle = Some(oracle.clone())
+    }
+
+    /// Attaches a [`Profiler`] which records every operation.
+    pub fn set_profiler(&mut self, profiler: &Profiler) {
+        self.profiler = Some(profiler.clone())
+    }
+
+    /// Measures the noise of a ciphertext resulting from an operation with the
+    /// noise oracle, if any.
+    #[inline]
+    fn observe(&self, _operation: &'static str, _ct: &Ciphertext) {
+        #[cfg(feature = "noise-oracle")]
+        if let Some(oracle) = self.oracle.as_ref() {
+            oracle.observe(_operation, _ct)
+        }
+    }
+
     /// Reports whether the evaluation key enables to compute an homomorphic
     /// inner sums.
     pub fn supports_inner_sum(&self) -> bool {
@@ -54,10 +98,11 @@ impl EvaluationKey {
     /// Computes the homomorphic inner sum.
     pub fn computes_inner_sum(&self, ct: &Ciphertext) -> Result<Ciphertext> {
         if !self.supports_inner_sum() {
-            Err(Error::DefaultError(
+            Err(Error::UnsupportedOperation(
                 "This key does not support the inner sum functionality".to_string(),
             ))
         } else {
+            let mut stopwatch = Stopwatch::start(self.profiler.as_ref());
             let mut out = ct.clone();
 
             let mut i = 1;
@@ -73,6 +118,8 @@ impl EvaluationKey {
             let gk = self.gk.get(&(self.par.degree() * 2 - 1)).unwrap();
             out += &gk.relinearize(&out)?;
 
+            stopwatch.lap("computes_inner_sum", &out.c, out.noise);
+            self.observe("computes_inner_sum", &out);
             Ok(out)
         }
     }
@@ -86,12 +133,16 @@ impl EvaluationKey {
     /// Homomorphically rotate the rows of the plaintext
     pub fn rotates_rows(&self, ct: &Ciphertext) -> Result<Ciphertext> {
         if !self.supports_row_rotation() {
-            Err(Error::DefaultError(
+            Err(Error::UnsupportedOperation(
                 "This key does not support the row rotation functionality".to_string(),
             ))
         } else {
+            let mut stopwatch = Stopwatch::start(self.profiler.as_ref());
             let gk = self.gk.get(&(self.par.degree() * 2 - 1)).unwrap();
-            gk.relinearize(ct)
+            let out = gk.relinearize(ct)?;
+            stopwatch.lap("rotates_rows", &out.c, out.noise);
+            self.observe("rotates_rows", &out);
+            Ok(out)
         }
     }
 
@@ -108,18 +159,112 @@ impl EvaluationKey {
     /// Homomorphically rotate the columns of the plaintext
     pub fn rotates_columns_by(&self, ct: &Ciphertext, i: usize) -> Result<Ciphertext> {
         if !self.supports_column_rotation_by(i) {
-            Err(Error::DefaultError(
+            Err(Error::UnsupportedOperation(
                 "This key does not support rotating the columns by this index".to_string(),
             ))
         } else {
+            let mut stopwatch = Stopwatch::start(self.profiler.as_ref());
             let gk = self
                 .gk
                 .get(self.rot_to_gk_exponent.get(&i).unwrap())
                 .unwrap();
-            gk.relinearize(ct)
+            let out = gk.relinearize(ct)?;
+            stopwatch.lap("rotates_columns_by", &out.c, out.noise);
+            self.observe("rotates_columns_by", &out);
+            Ok(out)
         }
     }
 
+    /// Homomorphically rotate the columns of the plaintext by each of the
+    /// `indices`.
+    ///
+    /// The decomposition of the ciphertext needed by the key switching is
+    /// computed once and shared between the rotations, which is faster than
+    /// calling [`EvaluationKey::rotates_columns_by`] for each index.
+    pub fn rotates_columns_by_many(
+        &self,
+        ct: &Ciphertext,
+        indices: &[usize],
+    ) -> Result<Vec<Ciphertext>> {
+        let gks = indices
+            .iter()
+            .map(|i| {
+                if self.supports_column_rotation_by(*i) {
+                    Ok(&self.gk[&self.rot_to_gk_exponent[i]])
+                } else {
+                    Err(Error::UnsupportedOperation(
+                        "This key does not support rotating the columns by this index".to_string(),
+                    ))
+                }
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let Some(first) = gks.first() else {
+            return Ok(vec![]);
+        };
+        if first.ksk.log_base != 0 || ct.c.len() != 2 {
+            return indices
+                .iter()
+                .map(|i| self.rotates_columns_by(ct, *i))
+                .collect();
+        }
+
+        self.par.check_fingerprint(&ct.par)?;
+        let mut stopwatch = Stopwatch::start(self.profiler.as_ref());
+        let c1_digits = ct.c[1].decompose_once(&first.ksk.ctx_ksk)?;
+        let outs = gks
+            .iter()
+            .map(|gk| {
+                let out = gk.relinearize_hoisted(ct, &c1_digits)?;
+                self.observe("rotates_columns_by", &out);
+                Ok(out)
+            })
+            .collect::<Result<Vec<_>>>()?;
+        stopwatch.lap_ciphertexts("rotates_columns_by_many", &outs);
+        Ok(outs)
+    }
+
+    /// Derives the key rotating the columns by `i` from the key rotating the
+    /// columns by 1, without the secret key, and adds it to the evaluation key.
+    ///
+    /// The derived key is obtained by composing up to `2 * log2(i)` Galois
+    /// keys, which increases its noise accordingly. This requires the
+    /// evaluation key to be at the level of the ciphertexts.
+    pub fn derive_column_rotation(&mut self, i: usize) -> Result<()> {
+        let exponent = *self
+            .rot_to_gk_exponent
+            .get(&i)
+            .ok_or_else(|| Error::UnspecifiedInput(format!("Invalid column index {i}")))?;
+        if self.gk.contains_key(&exponent) {
+            return Ok(());
+        }
+        let mut power = self
+            .gk
+            .get(&self.rot_to_gk_exponent[&1])
+            .ok_or_else(|| {
+                Error::UnsupportedOperation(
+                    "This key does not support column rotation by 1".to_string(),
+                )
+            })?
+            .clone();
+        let mut key = None::<GaloisKey>;
+        let mut j = i;
+        loop {
+            if j & 1 == 1 {
+                key = Some(match key {
+                    Some(key) => key.compose(&power)?,
+                    None => power.clone(),
+                });
+            }
+            j >>= 1;
+            if j == 0 {
+                break;
+            }
+            power = power.compose(&power)?;
+        }
+        self.gk.insert(exponent, key.unwrap());
+        Ok(())
+    }
+
     /// Reports whether the evaluation key supports oblivious expansion.
     pub fn supports_expansion(&self, level: usize) -> bool {
         if level == 0 {
@@ -142,12 +287,14 @@ impl EvaluationKey {
     pub fn expands(&self, ct: &Ciphertext, size: usize) -> Result<Vec<Ciphertext>> {
         let level = size.next_power_of_two().ilog2() as usize;
         if ct.c.len() != 2 {
-            Err(Error::DefaultError(
-                "The ciphertext is not of size 2".to_string(),
-            ))
+            Err(Error::CiphertextSizeMismatch {
+                expected: 2,
+                found: ct.c.len(),
+            })
         } else if level == 0 {
             Ok(vec![ct.clone()])
         } else if self.supports_expansion(level) {
+            let mut stopwatch = Stopwatch::start(self.profiler.as_ref());
             let mut out = vec![Ciphertext::zero(&ct.par); 1 << level];
             out[0] = ct.clone();
 
@@ -167,14 +314,111 @@ impl EvaluationKey {
                 }
             }
             out.truncate(size);
+            stopwatch.lap_ciphertexts("expands", &out);
+            out.iter().for_each(|ct| self.observe("expands", ct));
             Ok(out)
         } else {
-            Err(Error::DefaultError(
+            Err(Error::UnsupportedOperation(
                 "This key does not support expansion at this level".to_string(),
             ))
         }
     }
 
+    /// Returns the level of the ciphertexts the evaluation key applies to.
+    pub fn ciphertext_level(&self) -> usize {
+        self.ciphertext_level
+    }
+
+    /// Returns the Galois elements of the Galois keys of the evaluation key,
+    /// in increasing order. The row rotation uses the element
+    /// `2 * degree - 1`, and the column rotations use the elements returned
+    /// by [`EvaluationKey::column_rotation_galois_element`].
+    pub fn galois_elements(&self) -> Vec<usize> {
+        let mut elements = self.gk.keys().copied().collect::<Vec<_>>();
+        elements.sort_unstable();
+        elements
+    }
+
+    /// Returns the Galois element of the Galois key rotating the columns by
+    /// `i`, which must be in `[1, degree / 2)`.
+    pub fn column_rotation_galois_element(par: &Arc<BfvParameters>, i: usize) -> Result<usize> {
+        Self::construct_rot_to_gk_exponent(par)
+            .get(&i)
+            .copied()
+            .ok_or_else(|| Error::UnspecifiedInput(format!("Invalid column index {i}")))
+    }
+
+    /// Splits the evaluation key into one evaluation key per Galois element,
+    /// in increasing order of the elements. The parts are serialized and
+    /// loaded independently, so that only the Galois keys needed by a
+    /// computation are fetched, and are recombined with
+    /// [`EvaluationKey::merge`].
+    ///
+    /// The parts keep the master seed of the evaluation key, from which the
+    /// seeds of their Galois keys are derived instead of being serialized.
+    pub fn split(&self) -> Vec<EvaluationKey> {
+        self.galois_elements()
+            .into_iter()
+            .map(|element| {
+                self.with_galois_keys(HashMap::from([(element, self.gk[&element].clone())]))
+            })
+            .collect()
+    }
+
+    /// Merges evaluation keys with the same parameters and levels, such as
+    /// parts of an evaluation key obtained with [`EvaluationKey::split`], into
+    /// an evaluation key holding all their Galois keys.
+    ///
+    /// Returns an error if there are no keys, or if the keys have different
+    /// parameters or levels.
+    pub fn merge(keys: &[EvaluationKey]) -> Result<EvaluationKey> {
+        let Some(first) = keys.first() else {
+            return Err(Error::TooFewValues(0, 1));
+        };
+        let mut gk = HashMap::new();
+        let mut master_seed = first.master_seed;
+        for key in keys {
+            first.par.check_fingerprint(&key.par)?;
+            if key.ciphertext_level != first.ciphertext_level {
+                return Err(Error::LevelMismatch {
+                    expected: first.ciphertext_level,
+                    found: key.ciphertext_level,
+                });
+            }
+            if key.evaluation_key_level != first.evaluation_key_level {
+                return Err(Error::LevelMismatch {
+                    expected: first.evaluation_key_level,
+                    found: key.evaluation_key_level,
+                });
+            }
+            // The master seed is only kept when it is common to all the keys.
+            if key.master_seed != master_seed {
+                master_seed = None
+            }
+            gk.extend(key.gk.iter().map(|(e, k)| (*e, k.clone())));
+        }
+        let mut merged = first.with_galois_keys(gk);
+        merged.master_seed = master_seed;
+        Ok(merged)
+    }
+
+    /// Returns an evaluation key with the same parameters, levels and master
+    /// seed, holding the Galois keys `gk`.
+    fn with_galois_keys(&self, gk: HashMap<usize, GaloisKey>) -> Self {
+        EvaluationKey {
+            par: self.par.clone(),
+            ciphertext_level: self.ciphertext_level,
+            evaluation_key_level: self.evaluation_key_level,
+            gk,
+            rot_to_gk_exponent: self.rot_to_gk_exponent.clone(),
+            monomials: self.monomials.clone(),
+            master_seed: self.master_seed,
+            #[cfg(feature = "noise-oracle")]
+            oracle: None,
+            profiler: None,
+        }
+    }
+
     fn construct_rot_to_gk_exponent(par: &Arc<BfvParameters>) -> HashMap<usize, usize> {
         let mut m = HashMap::new();
         let q = Modulus::new(2 * par.degree() as u64).unwrap();
@@ -192,27 +436,39 @@ impl FheParametrized for EvaluationKey {
 
 impl Serialize for EvaluationKey {
     fn to_bytes(&self) -> Vec<u8> {
-        EvaluationKeyProto::from(self).encode_to_vec()
+        let bytes = EvaluationKeyProto::from(self).encode_to_vec();
+        #[cfg(feature = "tracing")]
+        tracing::debug!(
+            galois_keys = self.gk.len(),
+            bytes = bytes.len(),
+            "serialized evaluation key"
+        );
+        bytes
     }
 }
 
 impl DeserializeParametrized for EvaluationKey {
     type Error = Error;
 
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "debug", skip_all, fields(bytes = bytes.len()), err)
+    )]
     fn from_bytes(bytes: &[u8], par: &Arc<Self::Parameters>) -> Result<Self> {
         let gkp = Message::decode(bytes);
         if let Ok(gkp) = gkp {
             EvaluationKey::try_convert_from(&gkp, par)
         } else {
-            Err(Error::DefaultError("Invalid serialization".to_string()))
+            Err(Error::SerializationError)
         }
     }
 }
 
-/// Builder for a leveled evaluation key from the secret key.
+/// Builder for a leveled evaluation key from the secret key, or from any
+/// holder of the secret key.
 #[derive(Debug)]
-pub struct EvaluationKeyBuilder {
-    sk: SecretKey,
+pub struct EvaluationKeyBuilder<K: SecretKeyOperations = SecretKey> {
+    sk: K,
     ciphertext_level: usize,
     evaluation_key_level: usize,
     inner_sum: bool,
@@ -222,17 +478,17 @@ pub struct EvaluationKeyBuilder {
     rot_to_gk_exponent: HashMap<usize, usize>,
 }
 
-impl Zeroize for EvaluationKeyBuilder {
+impl<K: SecretKeyOperations + Zeroize> Zeroize for EvaluationKeyBuilder<K> {
     fn zeroize(&mut self) {
         self.sk.zeroize()
     }
 }
 
-impl ZeroizeOnDrop for EvaluationKeyBuilder {}
+impl<K: SecretKeyOperations + ZeroizeOnDrop> ZeroizeOnDrop for EvaluationKeyBuilder<K> {}
 
-impl EvaluationKeyBuilder {
+impl<K: SecretKeyOperations + Clone> EvaluationKeyBuilder<K> {
     /// Creates a new builder from the [`SecretKey`].
-    pub fn new(sk: &SecretKey) -> Result<Self> {
+    pub fn new(sk: &K) -> Result<Self> {
         Ok(Self {
             sk: sk.clone(),
             ciphertext_level: 0,
@@ -241,7 +497,7 @@ impl EvaluationKeyBuilder {
             row_rotation: false,
             expansion_level: 0,
             column_rotation: HashSet::new(),
-            rot_to_gk_exponent: EvaluationKey::construct_rot_to_gk_exponent(&sk.par),
+            rot_to_gk_exponent: EvaluationKey::construct_rot_to_gk_exponent(sk.parameters()),
         })
     }
 
@@ -251,12 +507,21 @@ impl EvaluationKeyBuilder {
     /// than the ciphertext level, or if the ciphertext level is larger than the
     /// maximum level supported by these parameters.
     pub fn new_leveled(
-        sk: &SecretKey,
+        sk: &K,
         ciphertext_level: usize,
         evaluation_key_level: usize,
     ) -> Result<Self> {
-        if ciphertext_level < evaluation_key_level || ciphertext_level > sk.par.max_level() {
-            return Err(Error::DefaultError("Unexpected levels".to_string()));
+        if ciphertext_level > sk.parameters().max_level() {
+            return Err(Error::InvalidLevel {
+                level: ciphertext_level,
+                max_level: sk.parameters().max_level(),
+            });
+        }
+        if ciphertext_level < evaluation_key_level {
+            return Err(Error::InvalidLevel {
+                level: evaluation_key_level,
+                max_level: ciphertext_level,
+            });
         }
 
         Ok(Self {
@@ -267,15 +532,16 @@ impl EvaluationKeyBuilder {
             row_rotation: false,
             expansion_level: 0,
             column_rotation: HashSet::new(),
-            rot_to_gk_exponent: EvaluationKey::construct_rot_to_gk_exponent(&sk.par),
+            rot_to_gk_exponent: EvaluationKey::construct_rot_to_gk_exponent(sk.parameters()),
         })
     }
 
     /// Allow expansion by this evaluation key.
     #[allow(unused_must_use)]
     pub fn enable_expansion(&mut self, level: usize) -> Result<&mut Self> {
-        if level >= 64 - self.sk.par.degree().leading_zeros() as usize {
-            Err(Error::DefaultError("Invalid level 2".to_string()))
+        let max_level = self.sk.parameters().degree().ilog2() as usize;
+        if level > max_level {
+            Err(Error::InvalidLevel { level, max_level })
         } else {
             self.expansion_level = level;
             Ok(self)
@@ -304,17 +570,34 @@ impl EvaluationKeyBuilder {
             self.column_rotation.insert(*exp);
             Ok(self)
         } else {
-            Err(Error::DefaultError("Invalid column index".to_string()))
+            Err(Error::UnspecifiedInput(format!("Invalid column index {i}")))
         }
     }
 
     /// Build an [`EvaluationKey`] with the specified attributes.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            level = "debug",
+            skip_all,
+            fields(
+                ciphertext_level = self.ciphertext_level,
+                evaluation_key_level = self.evaluation_key_level,
+            ),
+            err
+        )
+    )]
     pub fn build<R: RngCore + CryptoRng>(&mut self, rng: &mut R) -> Result<EvaluationKey> {
+        let par = self.sk.parameters().clone();
         let mut ek = EvaluationKey {
             gk: HashMap::default(),
-            par: self.sk.par.clone(),
+            par: par.clone(),
             rot_to_gk_exponent: self.rot_to_gk_exponent.clone(),
-            monomials: Vec::with_capacity(self.sk.par.degree().ilog2() as usize),
+            monomials: Vec::with_capacity(par.degree().ilog2() as usize),
+            master_seed: None,
+            #[cfg(feature = "noise-oracle")]
+            oracle: None,
+            profiler: None,
             ciphertext_level: self.ciphertext_level,
             evaluation_key_level: self.evaluation_key_level,
         };
@@ -322,27 +605,27 @@ impl EvaluationKeyBuilder {
         let mut indices = self.column_rotation.clone();
 
         if self.row_rotation {
-            indices.insert(self.sk.par.degree() * 2 - 1);
+            indices.insert(par.degree() * 2 - 1);
         }
 
         if self.inner_sum {
             // Add the required indices to the set of indices
-            indices.insert(self.sk.par.degree() * 2 - 1);
+            indices.insert(par.degree() * 2 - 1);
             let mut i = 1;
-            while i < self.sk.par.degree() / 2 {
+            while i < par.degree() / 2 {
                 indices.insert(*ek.rot_to_gk_exponent.get(&i).unwrap());
                 i *= 2
             }
         }
 
         for l in 0..self.expansion_level {
-            indices.insert((self.sk.par.degree() >> l) + 1);
+            indices.insert((par.degree() >> l) + 1);
         }
 
-        let ciphertext_ctx = self.sk.par.ctx_at_level(self.ciphertext_level)?;
-        for l in 0..self.sk.par.degree().ilog2() {
-            let mut monomial = vec![0i64; self.sk.par.degree()];
-            monomial[self.sk.par.degree() - (1 << l)] = -1;
+        let ciphertext_ctx = par.ctx_at_level(self.ciphertext_level)?;
+        for l in 0..par.degree().ilog2() {
+            let mut monomial = vec![0i64; par.degree()];
+            monomial[par.degree() - (1 << l)] = -1;
             let mut monomial = Poly::try_convert_from(
                 &monomial,
                 ciphertext_ctx,
@@ -354,18 +637,24 @@ impl EvaluationKeyBuilder {
             ek.monomials.push(monomial);
         }
 
+        // The seeds of the Galois keys are derived from a master seed, so that
+        // only the master seed needs to be serialized.
+        let mut master_seed = <ChaCha8Rng as SeedableRng>::Seed::default();
+        rng.fill(&mut master_seed);
         for index in indices {
             ek.gk.insert(
                 index,
-                GaloisKey::new(
+                GaloisKey::new_with_seed(
                     &self.sk,
                     index,
                     self.ciphertext_level,
                     self.evaluation_key_level,
+                    KeySwitchingKey::derive_seed(&master_seed, index as u64),
                     rng,
                 )?,
             );
         }
+        ek.master_seed = Some(master_seed);
 
         Ok(ek)
     }
@@ -375,28 +664,60 @@ impl From<&EvaluationKey> for EvaluationKeyProto {
     fn from(ek: &EvaluationKey) -> Self {
         let mut proto = EvaluationKeyProto::default();
         for (_, gk) in ek.gk.iter() {
-            proto.gk.push(GaloisKeyProto::from(gk))
+            let mut gkp = GaloisKeyProto::from(gk);
+            // Omit the seeds that can be derived from the master seed.
+            if let (Some(master_seed), Some(ksk)) = (ek.master_seed.as_ref(), gkp.ksk.as_mut()) {
+                let seed = KeySwitchingKey::derive_seed(master_seed, gkp.exponent as u64);
+                if ksk.seed == seed {
+                    ksk.seed.clear()
+                }
+            }
+            proto.gk.push(gkp)
+        }
+        if let Some(master_seed) = ek.master_seed.as_ref() {
+            proto.master_seed = master_seed.to_vec();
         }
         proto.ciphertext_level = ek.ciphertext_level as u32;
         proto.evaluation_key_level = ek.evaluation_key_level as u32;
+        proto.version = FORMAT_VERSION;
         proto
     }
 }
 
 impl TryConvertFrom<&EvaluationKeyProto> for EvaluationKey {
     fn try_convert_from(value: &EvaluationKeyProto, par: &Arc<BfvParameters>) -> Result<Self> {
+        check_version(value.version)?;
+        let master_seed = if value.master_seed.is_empty() {
+            None
+        } else {
+            Some(
+                <ChaCha8Rng as SeedableRng>::Seed::try_from(value.master_seed.clone())
+                    .map_err(|_| Error::SerializationError)?,
+            )
+        };
+
         let mut gk = HashMap::new();
         for gkp in &value.gk {
-            let key = GaloisKey::try_convert_from(gkp, par)?;
+            let key = match (master_seed.as_ref(), gkp.ksk.as_ref()) {
+                (Some(master_seed), Some(ksk)) if ksk.seed.is_empty() && ksk.c1.is_empty() => {
+                    let mut gkp = gkp.clone();
+                    gkp.ksk.as_mut().unwrap().seed =
+                        KeySwitchingKey::derive_seed(master_seed, gkp.exponent as u64).to_vec();
+                    GaloisKey::try_convert_from(&gkp, par)?
+                }
+                _ => GaloisKey::try_convert_from(gkp, par)?,
+            };
             if key.ksk.ciphertext_level != value.ciphertext_level as usize {
-                return Err(Error::DefaultError(
-                    "Galois key has incorrect ciphertext level".to_string(),
-                ));
+                return Err(Error::LevelMismatch {
+                    expected: value.ciphertext_level as usize,
+                    found: key.ksk.ciphertext_level,
+                });
             }
             if key.ksk.ksk_level != value.evaluation_key_level as usize {
-                return Err(Error::DefaultError(
-                    "Galois key has incorrect evaluation key level".to_string(),
-                ));
+                return Err(Error::LevelMismatch {
+                    expected: value.evaluation_key_level as usize,
+                    found: key.ksk.ksk_level,
+                });
             }
             gk.insert(key.element.exponent, key);
         }
@@ -422,6 +743,10 @@ impl TryConvertFrom<&EvaluationKeyProto> for EvaluationKey {
             par: par.clone(),
             rot_to_gk_exponent: EvaluationKey::construct_rot_to_gk_exponent(par),
             monomials,
+            master_seed,
+            #[cfg(feature = "noise-oracle")]
+            oracle: None,
+            profiler: None,
             ciphertext_level: value.ciphertext_level as usize,
             evaluation_key_level: value.evaluation_key_level as usize,
         })
@@ -507,7 +832,10 @@ mod tests {
         assert!(e.is_err());
         assert_eq!(
             e.unwrap_err(),
-            crate::Error::DefaultError("Unexpected levels".to_string())
+            crate::Error::InvalidLevel {
+                level: 1,
+                max_level: 0
+            }
         );
 
         Ok(())
@@ -519,6 +847,8 @@ mod tests {
         for params in [
             BfvParameters::default_arc(6, 16),
             BfvParameters::default_arc(5, 16),
+            BfvParameters::default_arc(2, 4),
+            BfvParameters::default_arc(2, 2),
         ] {
             for _ in 0..25 {
                 for ciphertext_level in 0..=params.max_level() {
@@ -563,6 +893,8 @@ mod tests {
         for params in [
             BfvParameters::default_arc(6, 16),
             BfvParameters::default_arc(5, 16),
+            BfvParameters::default_arc(2, 4),
+            BfvParameters::default_arc(2, 2),
         ] {
             for _ in 0..50 {
                 for ciphertext_level in 0..=params.max_level() {
@@ -608,6 +940,8 @@ mod tests {
         for params in [
             BfvParameters::default_arc(6, 16),
             BfvParameters::default_arc(5, 16),
+            BfvParameters::default_arc(2, 4),
+            BfvParameters::default_arc(2, 2),
         ] {
             let row_size = params.degree() >> 1;
             for _ in 0..50 {
@@ -657,6 +991,91 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn hoisted_column_rotations() -> Result<(), Box<dyn Error>> {
+        let mut rng = thread_rng();
+        for params in [
+            BfvParameters::default_arc(6, 16),
+            BfvParameters::default_arc(2, 4),
+        ] {
+            let indices = (1..params.degree() >> 1).collect::<Vec<_>>();
+            for ciphertext_level in 0..=params.max_level() {
+                for evaluation_key_level in 0..=min(params.max_level(), ciphertext_level) {
+                    let sk = SecretKey::random(&params, &mut rng);
+                    let mut builder = EvaluationKeyBuilder::new_leveled(
+                        &sk,
+                        ciphertext_level,
+                        evaluation_key_level,
+                    )?;
+                    for i in &indices {
+                        builder.enable_column_rotation(*i)?;
+                    }
+                    let ek = builder.build(&mut rng)?;
+
+                    let v = params.plaintext.random_vec(params.degree(), &mut rng);
+                    let encoding = Encoding::simd_at_level(ciphertext_level);
+                    let pt = Plaintext::try_encode(&v, encoding.clone(), &params)?;
+                    let ct = sk.try_encrypt(&pt, &mut rng)?;
+
+                    let rotated = ek.rotates_columns_by_many(&ct, &indices)?;
+                    assert_eq!(rotated.len(), indices.len());
+                    for (i, ct2) in izip!(&indices, &rotated) {
+                        let expected = sk.try_decrypt(&ek.rotates_columns_by(&ct, *i)?)?;
+                        let pt = sk.try_decrypt(ct2)?;
+                        assert_eq!(
+                            Vec::<u64>::try_decode(&pt, encoding.clone())?,
+                            Vec::<u64>::try_decode(&expected, encoding.clone())?
+                        );
+                    }
+                    assert!(ek
+                        .rotates_columns_by_many(&ct, &[params.degree() >> 1])
+                        .is_err());
+                }
+            }
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn derived_column_rotations() -> Result<(), Box<dyn Error>> {
+        let mut rng = thread_rng();
+        let params = BfvParameters::default_arc(6, 16);
+        let row_size = params.degree() >> 1;
+        let sk = SecretKey::random(&params, &mut rng);
+        let mut ek = EvaluationKeyBuilder::new(&sk)?
+            .enable_column_rotation(1)?
+            .build(&mut rng)?;
+
+        let v = params.plaintext.random_vec(params.degree(), &mut rng);
+        let pt = Plaintext::try_encode(&v, Encoding::simd(), &params)?;
+        let ct = sk.try_encrypt(&pt, &mut rng)?;
+        for i in 1..row_size {
+            assert_eq!(ek.supports_column_rotation_by(i), i == 1);
+            ek.derive_column_rotation(i)?;
+            assert!(ek.supports_column_rotation_by(i));
+
+            let mut expected = vec![0u64; params.degree()];
+            expected[..row_size - i].copy_from_slice(&v[i..row_size]);
+            expected[row_size - i..row_size].copy_from_slice(&v[..i]);
+            expected[row_size..2 * row_size - i].copy_from_slice(&v[row_size + i..]);
+            expected[2 * row_size - i..].copy_from_slice(&v[row_size..row_size + i]);
+
+            let pt = sk.try_decrypt(&ek.rotates_columns_by(&ct, i)?)?;
+            assert_eq!(Vec::<u64>::try_decode(&pt, Encoding::simd())?, expected);
+        }
+
+        // The derived keys are serialized with their random polynomials.
+        let bytes = ek.to_bytes();
+        assert_eq!(ek, EvaluationKey::from_bytes(&bytes, &params)?);
+
+        let mut ek = EvaluationKeyBuilder::new(&sk)?
+            .enable_row_rotation()?
+            .build(&mut rng)?;
+        assert!(ek.derive_column_rotation(2).is_err());
+        assert!(ek.derive_column_rotation(row_size).is_err());
+        Ok(())
+    }
+
     #[test]
     fn expansion() -> Result<(), Box<dyn Error>> {
         let mut rng = thread_rng();
@@ -711,6 +1130,61 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn split_and_merge() -> Result<(), Box<dyn Error>> {
+        let mut rng = thread_rng();
+        let params = BfvParameters::default_arc(3, 16);
+        let sk = SecretKey::random(&params, &mut rng);
+        let ek = EvaluationKeyBuilder::new_leveled(&sk, 1, 0)?
+            .enable_row_rotation()?
+            .enable_column_rotation(1)?
+            .enable_column_rotation(3)?
+            .build(&mut rng)?;
+        let element = EvaluationKey::column_rotation_galois_element(&params, 3)?;
+        assert!(ek.galois_elements().contains(&element));
+        assert!(EvaluationKey::column_rotation_galois_element(&params, 8).is_err());
+        assert_eq!(ek.ciphertext_level(), 1);
+
+        let parts = ek.split();
+        assert_eq!(parts.len(), 3);
+        let chunks = parts.iter().map(|part| part.to_bytes()).collect::<Vec<_>>();
+        assert!(chunks.iter().map(|c| c.len()).sum::<usize>() < ek.to_bytes().len() + 3 * 64);
+
+        // Only the chunk rotating the columns by 3 is loaded.
+        let index = ek
+            .galois_elements()
+            .iter()
+            .position(|e| *e == element)
+            .unwrap();
+        let partial = EvaluationKey::merge(&[EvaluationKey::from_bytes(&chunks[index], &params)?])?;
+        assert!(partial.supports_column_rotation_by(3));
+        assert!(!partial.supports_column_rotation_by(1));
+        assert!(!partial.supports_row_rotation());
+
+        let v = params.plaintext.random_vec(params.degree(), &mut rng);
+        let pt = Plaintext::try_encode(&v, Encoding::simd_at_level(1), &params)?;
+        let ct = sk.try_encrypt(&pt, &mut rng)?;
+        let expected = ek.rotates_columns_by(&ct, 3)?;
+        let pt = sk.try_decrypt(&partial.rotates_columns_by(&ct, 3)?)?;
+        assert_eq!(
+            Vec::<u64>::try_decode(&pt, Encoding::simd_at_level(1))?,
+            Vec::<u64>::try_decode(&sk.try_decrypt(&expected)?, Encoding::simd_at_level(1))?
+        );
+
+        let loaded = chunks
+            .iter()
+            .map(|chunk| EvaluationKey::from_bytes(chunk, &params))
+            .collect::<crate::Result<Vec<_>>>()?;
+        assert_eq!(EvaluationKey::merge(&loaded)?, ek);
+
+        let other = EvaluationKeyBuilder::new_leveled(&sk, 0, 0)?
+            .enable_row_rotation()?
+            .build(&mut rng)?;
+        assert!(EvaluationKey::merge(&[partial, other]).is_err());
+        assert!(EvaluationKey::merge(&[]).is_err());
+        Ok(())
+    }
+
     #[test]
     fn proto_conversion() -> Result<(), Box<dyn Error>> {
         let mut rng = thread_rng();
@@ -793,6 +1267,11 @@ mod tests {
                     .build(&mut rng)?;
                 let bytes = ek.to_bytes();
                 assert_eq!(ek, EvaluationKey::from_bytes(&bytes, &params)?);
+
+                // The seeds of the Galois keys are derived from the master seed.
+                let mut ek = ek;
+                ek.master_seed = None;
+                assert!(bytes.len() < ek.to_bytes().len());
             }
         }
         Ok(())
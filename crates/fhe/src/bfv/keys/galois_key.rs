@@ -1,57 +1,67 @@
 //! Galois keys for the BFV encryption scheme
 
 use super::key_switching_key::KeySwitchingKey;
-use crate::bfv::{traits::TryConvertFrom, BfvParameters, Ciphertext, SecretKey};
+use crate::bfv::{
+    noise, traits::TryConvertFrom, BfvParameters, Ciphertext, SecretKeyMessage, SecretKeyOperations,
+};
 use crate::proto::bfv::{GaloisKey as GaloisKeyProto, KeySwitchingKey as KeySwitchingKeyProto};
 use crate::{Error, Result};
-use fhe_math::rq::{
-    switcher::Switcher, traits::TryConvertFrom as TryConvertFromPoly, Poly, Representation,
-    SubstitutionExponent,
-};
-use rand::{CryptoRng, RngCore};
+use fhe_math::rq::{HoistedDecomposition, Poly, Representation, SubstitutionExponent};
+use rand::{CryptoRng, Rng, RngCore, SeedableRng};
+use rand_chacha::ChaCha8Rng;
 use std::sync::Arc;
-use zeroize::Zeroizing;
 
 /// Galois key for the BFV encryption scheme.
 /// A Galois key is a special type of key switching key,
 /// which switch from `s(x^i)` to `s(x)` where `s(x)` is the secret key.
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, PartialEq, Eq, Clone)]
 pub struct GaloisKey {
     pub(crate) element: SubstitutionExponent,
     pub(crate) ksk: KeySwitchingKey,
 }
 
 impl GaloisKey {
-    /// Generate a [`GaloisKey`] from a [`SecretKey`].
-    pub fn new<R: RngCore + CryptoRng>(
-        sk: &SecretKey,
+    /// Generate a [`GaloisKey`] from a [`SecretKey`](crate::bfv::SecretKey), or from any holder of
+    /// the secret key.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "debug", skip(sk, rng), err)
+    )]
+    pub fn new<K: SecretKeyOperations + ?Sized, R: RngCore + CryptoRng>(
+        sk: &K,
+        exponent: usize,
+        ciphertext_level: usize,
+        galois_key_level: usize,
+        rng: &mut R,
+    ) -> Result<Self> {
+        let mut seed = <ChaCha8Rng as SeedableRng>::Seed::default();
+        rng.fill(&mut seed);
+        Self::new_with_seed(sk, exponent, ciphertext_level, galois_key_level, seed, rng)
+    }
+
+    /// Generate a [`GaloisKey`] whose random polynomials are generated from
+    /// `seed`.
+    pub(crate) fn new_with_seed<K: SecretKeyOperations + ?Sized, R: RngCore + CryptoRng>(
+        sk: &K,
         exponent: usize,
         ciphertext_level: usize,
         galois_key_level: usize,
+        seed: <ChaCha8Rng as SeedableRng>::Seed,
         rng: &mut R,
     ) -> Result<Self> {
-        let ctx_galois_key = sk.par.ctx_at_level(galois_key_level)?;
-        let ctx_ciphertext = sk.par.ctx_at_level(ciphertext_level)?;
+        let par = sk.parameters();
+        par.ctx_at_level(galois_key_level)?;
+        let ctx_ciphertext = par.ctx_at_level(ciphertext_level)?;
 
         let ciphertext_exponent =
             SubstitutionExponent::new(ctx_ciphertext, exponent).map_err(Error::MathError)?;
 
-        let switcher_up = Switcher::new(ctx_ciphertext, ctx_galois_key)?;
-        let s = Zeroizing::new(Poly::try_convert_from(
-            sk.coeffs.as_ref(),
-            ctx_ciphertext,
-            false,
-            Representation::PowerBasis,
-        )?);
-        let s_sub = Zeroizing::new(s.substitute(&ciphertext_exponent)?);
-        let mut s_sub_switched_up = Zeroizing::new(s_sub.mod_switch_to(&switcher_up)?);
-        s_sub_switched_up.change_representation(Representation::PowerBasis);
-
-        let ksk = KeySwitchingKey::new(
+        let ksk = KeySwitchingKey::new_with_seed(
             sk,
-            &s_sub_switched_up,
+            SecretKeyMessage::Substitution(exponent),
             ciphertext_level,
             galois_key_level,
+            seed,
             rng,
         )?;
 
@@ -61,15 +71,102 @@ impl GaloisKey {
         })
     }
 
+    /// Derives the Galois key for the product of the exponents of `self` and
+    /// `other`, without the secret key.
+    ///
+    /// The substitution of `self` by the exponent of `other` is a key
+    /// switching key from `s(x^other)`, which is switched to `s(x)` using
+    /// `other`. The keys must be for ciphertexts at the level of the keys, and
+    /// use the RNS decomposition of the ciphertext modulus. The noise of the
+    /// derived key is the sum of the noises of the two keys, which is not
+    /// reflected in the noise estimates.
+    pub(crate) fn compose(&self, other: &GaloisKey) -> Result<GaloisKey> {
+        let ksk = &self.ksk;
+        self.ksk.par.check_fingerprint(&other.ksk.par)?;
+        if ksk.ciphertext_level != ksk.ksk_level
+            || ksk.log_base != 0
+            || other.ksk.ciphertext_level != ksk.ciphertext_level
+            || other.ksk.ksk_level != ksk.ksk_level
+        {
+            return Err(Error::UnsupportedOperation(
+                "Galois keys can only be composed at the level of the ciphertexts".to_string(),
+            ));
+        }
+
+        let mut c0 = Vec::with_capacity(ksk.c0.len());
+        let mut c1 = Vec::with_capacity(ksk.c1.len());
+        for (c0_i, c1_i) in ksk.c0.iter().zip(ksk.c1.iter()) {
+            let mut a = c1_i.substitute(&other.element)?;
+            a.change_representation(Representation::PowerBasis);
+            let (d0, mut d1) = other.ksk.key_switch(&a)?;
+
+            let mut b = c0_i.substitute(&other.element)?;
+            b.change_representation(Representation::Ntt);
+            b += &d0;
+            b.change_representation(Representation::NttShoup);
+            d1.change_representation(Representation::NttShoup);
+            c0.push(b);
+            c1.push(d1);
+        }
+
+        let element = SubstitutionExponent::new(
+            &ksk.ctx_ciphertext,
+            self.element.exponent * other.element.exponent,
+        )
+        .map_err(Error::MathError)?;
+        Ok(GaloisKey {
+            element,
+            ksk: KeySwitchingKey {
+                par: ksk.par.clone(),
+                seed: None,
+                c0: c0.into_boxed_slice(),
+                c1: c1.into_boxed_slice(),
+                ciphertext_level: ksk.ciphertext_level,
+                ctx_ciphertext: ksk.ctx_ciphertext.clone(),
+                ksk_level: ksk.ksk_level,
+                ctx_ksk: ksk.ctx_ksk.clone(),
+                log_base: 0,
+            },
+        })
+    }
+
     /// Relinearize a [`Ciphertext`] using the [`GaloisKey`]
     pub fn relinearize(&self, ct: &Ciphertext) -> Result<Ciphertext> {
-        // assert_eq!(ct.par, self.ksk.par);
+        self.ksk.par.check_fingerprint(&ct.par)?;
         assert_eq!(ct.c.len(), 2);
 
         let mut c2 = ct.c[1].substitute(&self.element)?;
         c2.change_representation(Representation::PowerBasis);
-        let (mut c0, mut c1) = self.ksk.key_switch(&c2)?;
+        let (c0, c1) = self.ksk.key_switch(&c2)?;
+        self.finish(ct, c0, c1)
+    }
+
+    /// Relinearize a [`Ciphertext`] using the [`GaloisKey`], given the
+    /// decomposition `c1_digits` of its second polynomial in the context of
+    /// the key, so that the decomposition is shared between several keys.
+    ///
+    /// The key must use the RNS decomposition of the ciphertext modulus.
+    pub(crate) fn relinearize_hoisted(
+        &self,
+        ct: &Ciphertext,
+        c1_digits: &HoistedDecomposition,
+    ) -> Result<Ciphertext> {
+        self.ksk.par.check_fingerprint(&ct.par)?;
+        assert_eq!(ct.c.len(), 2);
+        assert_eq!(self.ksk.log_base, 0);
+        if ct.c[1].ctx() != &self.ksk.ctx_ciphertext || c1_digits.ctx() != &self.ksk.ctx_ksk {
+            return Err(Error::MathError(fhe_math::Error::InvalidContext));
+        }
+
+        let digits = c1_digits.apply_automorphism_hoisted(&self.element)?;
+        let c0 = digits.inner_product(&self.ksk.c0)?;
+        let c1 = digits.inner_product(&self.ksk.c1)?;
+        self.finish(ct, c0, c1)
+    }
 
+    /// Completes the relinearization of `ct` from the key switched
+    /// substitution `(c0, c1)` of its second polynomial.
+    fn finish(&self, ct: &Ciphertext, mut c0: Poly, mut c1: Poly) -> Result<Ciphertext> {
         if c0.ctx() != ct.c[0].ctx() {
             c0.change_representation(Representation::PowerBasis);
             c1.change_representation(Representation::PowerBasis);
@@ -79,13 +176,18 @@ impl GaloisKey {
             c1.change_representation(Representation::Ntt);
         }
 
-        c0 += &ct.c[0].substitute(&self.element)?;
+        let mut c2 = Poly::zero(ct.c[0].ctx(), Representation::Ntt);
+        ct.c[0].substitute_into(&self.element, &mut c2)?;
+        c0 += &c2;
 
         Ok(Ciphertext {
             par: ct.par.clone(),
             seed: None,
             c: vec![c0, c1],
             level: self.ksk.ciphertext_level,
+            noise: noise::after_key_switch(&self.ksk, ct.noise),
+            metadata: None,
+            key_id: ct.key_id,
         })
     }
 }
@@ -101,8 +203,8 @@ impl From<&GaloisKey> for GaloisKeyProto {
 
 impl TryConvertFrom<&GaloisKeyProto> for GaloisKey {
     fn try_convert_from(value: &GaloisKeyProto, par: &Arc<BfvParameters>) -> Result<Self> {
-        if value.ksk.is_some() {
-            let ksk = KeySwitchingKey::try_convert_from(value.ksk.as_ref().unwrap(), par)?;
+        if let Some(ksk) = value.ksk.as_ref() {
+            let ksk = KeySwitchingKey::try_convert_from(ksk, par)?;
 
             let ctx = par.ctx_at_level(ksk.ciphertext_level)?;
             let element = SubstitutionExponent::new(ctx, value.exponent as usize)
@@ -110,7 +212,7 @@ impl TryConvertFrom<&GaloisKeyProto> for GaloisKey {
 
             Ok(GaloisKey { element, ksk })
         } else {
-            Err(Error::DefaultError("Invalid serialization".to_string()))
+            Err(Error::SerializationError)
         }
     }
 }
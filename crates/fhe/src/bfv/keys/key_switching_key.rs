@@ -1,21 +1,18 @@
 //! Key-switching keys for the BFV encryption scheme
 
-use crate::bfv::{traits::TryConvertFrom as BfvTryConvertFrom, BfvParameters, SecretKey};
-use crate::proto::bfv::KeySwitchingKey as KeySwitchingKeyProto;
-use crate::{Error, Result};
-use fhe_math::rq::traits::TryConvertFrom;
-use fhe_math::rq::Context;
-use fhe_math::{
-    rns::RnsContext,
-    rq::{Poly, Representation},
+use crate::bfv::{
+    traits::TryConvertFrom as BfvTryConvertFrom, BfvParameters, OpScratch, SecretKey,
+    SecretKeyMessage, SecretKeyOperations,
 };
+use crate::proto::{bfv::KeySwitchingKey as KeySwitchingKeyProto, check_version, FORMAT_VERSION};
+use crate::{Error, Result};
+use fhe_math::rq::{dot_product, gadget::Gadget, Context, Poly, Representation};
 use fhe_traits::{DeserializeWithContext, Serialize};
-use itertools::{izip, Itertools};
+use itertools::izip;
 use num_bigint::BigUint;
 use rand::{CryptoRng, Rng, RngCore, SeedableRng};
 use rand_chacha::ChaCha8Rng;
 use std::sync::Arc;
-use zeroize::Zeroizing;
 
 /// Key switching key for the BFV encryption scheme.
 #[derive(Debug, PartialEq, Eq, Clone)]
@@ -54,55 +51,139 @@ impl KeySwitchingKey {
         ksk_level: usize,
         rng: &mut R,
     ) -> Result<Self> {
-        let ctx_ksk = sk.par.ctx_at_level(ksk_level)?;
-        let ctx_ciphertext = sk.par.ctx_at_level(ciphertext_level)?;
-
-        if from.ctx() != ctx_ksk {
-            return Err(Error::DefaultError(
-                "Incorrect context for polynomial from".to_string(),
-            ));
-        }
+        let mut seed = <ChaCha8Rng as SeedableRng>::Seed::default();
+        rng.fill(&mut seed);
+        Self::generate(
+            &sk.par,
+            ciphertext_level,
+            ksk_level,
+            seed,
+            rng,
+            |c1, scales, rng| sk.key_samples_from(c1, Some(from), scales, rng),
+        )
+    }
 
+    /// Generate a [`KeySwitchingKey`] to the secret key held by `sk` from the
+    /// secret-dependent `message`.
+    pub fn new_with_operations<K: SecretKeyOperations + ?Sized, R: RngCore + CryptoRng>(
+        sk: &K,
+        message: SecretKeyMessage,
+        ciphertext_level: usize,
+        ksk_level: usize,
+        rng: &mut R,
+    ) -> Result<Self> {
         let mut seed = <ChaCha8Rng as SeedableRng>::Seed::default();
         rng.fill(&mut seed);
+        Self::new_with_seed(sk, message, ciphertext_level, ksk_level, seed, rng)
+    }
+
+    /// Generate a [`KeySwitchingKey`] to the secret key held by `sk` from the
+    /// secret-dependent `message`, whose c1's are generated from `seed`.
+    pub(crate) fn new_with_seed<K: SecretKeyOperations + ?Sized, R: RngCore + CryptoRng>(
+        sk: &K,
+        message: SecretKeyMessage,
+        ciphertext_level: usize,
+        ksk_level: usize,
+        seed: <ChaCha8Rng as SeedableRng>::Seed,
+        rng: &mut R,
+    ) -> Result<Self> {
+        Self::generate(
+            sk.parameters(),
+            ciphertext_level,
+            ksk_level,
+            seed,
+            rng,
+            |c1, scales, rng| sk.key_samples(c1, message, ciphertext_level, scales, rng),
+        )
+    }
+
+    /// Generate a [`KeySwitchingKey`] whose c0's are computed by `sample` from
+    /// the c1's and the scaling factors of the message.
+    fn generate<R, F>(
+        par: &Arc<BfvParameters>,
+        ciphertext_level: usize,
+        ksk_level: usize,
+        seed: <ChaCha8Rng as SeedableRng>::Seed,
+        rng: &mut R,
+        sample: F,
+    ) -> Result<Self>
+    where
+        R: RngCore + CryptoRng,
+        F: FnOnce(&[Poly], &[BigUint], &mut R) -> Result<Vec<Poly>>,
+    {
+        let ctx_ksk = par.ctx_at_level(ksk_level)?;
+        let ctx_ciphertext = par.ctx_at_level(ciphertext_level)?;
+
+        let gadget = Self::default_gadget(ctx_ciphertext, ctx_ksk)?;
+        let c1 = Self::generate_c1(ctx_ksk, seed, gadget.len());
+
+        let mut c0 = sample(&c1, &gadget.factors(), rng)?;
+        if c0.len() != c1.len() {
+            return Err(Error::CiphertextSizeMismatch {
+                expected: c1.len(),
+                found: c0.len(),
+            });
+        }
+        for b in c0.iter_mut() {
+            if b.ctx() != ctx_ksk {
+                return Err(Error::MathError(fhe_math::Error::InvalidContext));
+            }
+            // It is now safe to enable variable time computations.
+            unsafe { b.allow_variable_time_computations() }
+            b.change_representation(Representation::NttShoup);
+        }
 
+        Ok(Self {
+            par: par.clone(),
+            seed: Some(seed),
+            c0: c0.into_boxed_slice(),
+            c1: c1.into_boxed_slice(),
+            ciphertext_level,
+            ctx_ciphertext: ctx_ciphertext.clone(),
+            ksk_level,
+            ctx_ksk: ctx_ksk.clone(),
+            log_base: gadget.log_base(),
+        })
+    }
+
+    /// Returns the gadget of a new key switching key from polynomials in
+    /// `ctx_ciphertext` to polynomials in `ctx_ksk`.
+    pub(crate) fn default_gadget(
+        ctx_ciphertext: &Arc<Context>,
+        ctx_ksk: &Arc<Context>,
+    ) -> Result<Gadget> {
+        // For level with only one modulus, we decompose in base 2^log_base
         if ctx_ksk.moduli().len() == 1 {
             let modulus = ctx_ksk.moduli().first().unwrap();
             let log_modulus = modulus.next_power_of_two().ilog2() as usize;
-            let log_base = log_modulus / 2;
-
-            let c1 = Self::generate_c1(ctx_ksk, seed, (log_modulus + log_base - 1) / log_base);
-            let c0 = Self::generate_c0_decomposition(sk, from, &c1, rng, log_base)?;
-
-            Ok(Self {
-                par: sk.par.clone(),
-                seed: Some(seed),
-                c0: c0.into_boxed_slice(),
-                c1: c1.into_boxed_slice(),
-                ciphertext_level,
-                ctx_ciphertext: ctx_ciphertext.clone(),
-                ksk_level,
-                ctx_ksk: ctx_ksk.clone(),
-                log_base,
-            })
+            Ok(Gadget::power_of_two(ctx_ciphertext, log_modulus / 2)?)
         } else {
-            let c1 = Self::generate_c1(ctx_ksk, seed, ctx_ciphertext.moduli().len());
-            let c0 = Self::generate_c0(sk, from, &c1, rng)?;
-
-            Ok(Self {
-                par: sk.par.clone(),
-                seed: Some(seed),
-                c0: c0.into_boxed_slice(),
-                c1: c1.into_boxed_slice(),
-                ciphertext_level,
-                ctx_ciphertext: ctx_ciphertext.clone(),
-                ksk_level,
-                ctx_ksk: ctx_ksk.clone(),
-                log_base: 0,
-            })
+            Ok(Gadget::rns(ctx_ciphertext))
         }
     }
 
+    /// Returns the gadget decomposing the polynomials to key switch.
+    pub(crate) fn gadget(&self) -> Result<Gadget> {
+        if self.log_base != 0 {
+            Ok(Gadget::power_of_two(&self.ctx_ciphertext, self.log_base)?)
+        } else {
+            Ok(Gadget::rns(&self.ctx_ciphertext))
+        }
+    }
+
+    /// Derives the seed of the key of index `index` in a set of keys generated
+    /// from a single `master_seed`.
+    pub(crate) fn derive_seed(
+        master_seed: &<ChaCha8Rng as SeedableRng>::Seed,
+        index: u64,
+    ) -> <ChaCha8Rng as SeedableRng>::Seed {
+        let mut rng = ChaCha8Rng::from_seed(*master_seed);
+        rng.set_stream(index);
+        let mut seed = <ChaCha8Rng as SeedableRng>::Seed::default();
+        rng.fill(&mut seed);
+        seed
+    }
+
     /// Generate the c1's from the seed
     fn generate_c1(
         ctx: &Arc<Context>,
@@ -121,190 +202,53 @@ impl KeySwitchingKey {
         c1
     }
 
-    /// Generate the c0's from the c1's and the secret key
-    fn generate_c0<R: RngCore + CryptoRng>(
-        sk: &SecretKey,
-        from: &Poly,
-        c1: &[Poly],
-        rng: &mut R,
-    ) -> Result<Vec<Poly>> {
-        if c1.is_empty() {
-            return Err(Error::DefaultError("Empty number of c1's".to_string()));
-        }
-        if from.representation() != &Representation::PowerBasis {
-            return Err(Error::DefaultError(
-                "Unexpected representation for from".to_string(),
-            ));
-        }
-
-        let size = c1.len();
-
-        let mut s = Zeroizing::new(Poly::try_convert_from(
-            sk.coeffs.as_ref(),
-            c1[0].ctx(),
-            false,
-            Representation::PowerBasis,
-        )?);
-        s.change_representation(Representation::Ntt);
-
-        let rns = RnsContext::new(&sk.par.moduli[..size])?;
-        let c0 = c1
-            .iter()
-            .enumerate()
-            .map(|(i, c1i)| {
-                let mut a_s = Zeroizing::new(c1i.clone());
-                a_s.disallow_variable_time_computations();
-                a_s.change_representation(Representation::Ntt);
-                *a_s.as_mut() *= s.as_ref();
-                a_s.change_representation(Representation::PowerBasis);
-
-                let mut b =
-                    Poly::small(a_s.ctx(), Representation::PowerBasis, sk.par.variance, rng)?;
-                b -= &a_s;
-
-                let gi = rns.get_garner(i).unwrap();
-                let g_i_from = Zeroizing::new(gi * from);
-                b += &g_i_from;
-
-                // It is now safe to enable variable time computations.
-                unsafe { b.allow_variable_time_computations() }
-                b.change_representation(Representation::NttShoup);
-                Ok(b)
-            })
-            .collect::<Result<Vec<Poly>>>()?;
-
-        Ok(c0)
-    }
-
-    /// Generate the c0's from the c1's and the secret key
-    fn generate_c0_decomposition<R: RngCore + CryptoRng>(
-        sk: &SecretKey,
-        from: &Poly,
-        c1: &[Poly],
-        rng: &mut R,
-        log_base: usize,
-    ) -> Result<Vec<Poly>> {
-        if c1.is_empty() {
-            return Err(Error::DefaultError("Empty number of c1's".to_string()));
-        }
-
-        if from.representation() != &Representation::PowerBasis {
-            return Err(Error::DefaultError(
-                "Unexpected representation for from".to_string(),
-            ));
-        }
-
-        let mut s = Zeroizing::new(Poly::try_convert_from(
-            sk.coeffs.as_ref(),
-            c1[0].ctx(),
-            false,
-            Representation::PowerBasis,
-        )?);
-        s.change_representation(Representation::Ntt);
-
-        let c0 = c1
-            .iter()
-            .enumerate()
-            .map(|(i, c1i)| {
-                let mut a_s = Zeroizing::new(c1i.clone());
-                a_s.disallow_variable_time_computations();
-                a_s.change_representation(Representation::Ntt);
-                *a_s.as_mut() *= s.as_ref();
-                a_s.change_representation(Representation::PowerBasis);
-
-                let mut b =
-                    Poly::small(a_s.ctx(), Representation::PowerBasis, sk.par.variance, rng)?;
-                b -= &a_s;
-
-                let power = BigUint::from(1u64 << (i * log_base));
-                b += &(from * &power);
-
-                // It is now safe to enable variable time computations.
-                unsafe { b.allow_variable_time_computations() }
-                b.change_representation(Representation::NttShoup);
-                Ok(b)
-            })
-            .collect::<Result<Vec<Poly>>>()?;
-
-        Ok(c0)
-    }
-
     /// Key switch a polynomial.
     pub fn key_switch(&self, p: &Poly) -> Result<(Poly, Poly)> {
-        if self.log_base != 0 {
-            return self.key_switch_decomposition(p);
-        }
-
-        if p.ctx().as_ref() != self.ctx_ciphertext.as_ref() {
-            return Err(Error::DefaultError(
-                "The input polynomial does not have the correct context.".to_string(),
-            ));
-        }
-        if p.representation() != &Representation::PowerBasis {
-            return Err(Error::DefaultError("Incorrect representation".to_string()));
-        }
-
-        let mut c0 = Poly::zero(&self.ctx_ksk, Representation::Ntt);
-        let mut c1 = Poly::zero(&self.ctx_ksk, Representation::Ntt);
-        for (c2_i_coefficients, c0_i, c1_i) in izip!(
-            p.coefficients().outer_iter(),
-            self.c0.iter(),
-            self.c1.iter()
-        ) {
-            let mut c2_i = unsafe {
-                Poly::create_constant_ntt_polynomial_with_lazy_coefficients_and_variable_time(
-                    c2_i_coefficients.as_slice().unwrap(),
-                    &self.ctx_ksk,
-                )
-            };
-            c0 += &(&c2_i * c0_i);
-            c2_i *= c1_i;
-            c1 += &c2_i;
-        }
+        let mut scratch = OpScratch::default();
+        self.key_switch_with_scratch(p, &mut scratch)?;
+        let [c0, c1] = std::mem::take(&mut scratch.key_switched);
         Ok((c0, c1))
     }
 
-    /// Key switch a polynomial.
-    fn key_switch_decomposition(&self, p: &Poly) -> Result<(Poly, Poly)> {
-        if p.ctx().as_ref() != self.ctx_ciphertext.as_ref() {
-            return Err(Error::DefaultError(
-                "The input polynomial does not have the correct context.".to_string(),
-            ));
-        }
-        if p.representation() != &Representation::PowerBasis {
-            return Err(Error::DefaultError("Incorrect representation".to_string()));
-        }
-
-        let log_modulus = p
-            .ctx()
-            .moduli()
-            .first()
-            .unwrap()
-            .next_power_of_two()
-            .ilog2() as usize;
-
-        let mut coefficients = p.coefficients().to_slice().unwrap().to_vec();
-        let mut c2i = vec![];
-        let mask = (1u64 << self.log_base) - 1;
-        (0..(log_modulus + self.log_base - 1) / self.log_base).for_each(|_| {
-            c2i.push(coefficients.iter().map(|c| c & mask).collect_vec());
-            coefficients.iter_mut().for_each(|c| *c >>= self.log_base);
-        });
+    /// Key switch a polynomial using the temporary polynomials of `scratch`.
+    /// The result is stored in `scratch.key_switched`.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            level = "trace",
+            skip_all,
+            fields(
+                ciphertext_level = self.ciphertext_level,
+                ksk_level = self.ksk_level,
+                log_base = self.log_base,
+            ),
+            err
+        )
+    )]
+    pub(crate) fn key_switch_with_scratch(&self, p: &Poly, scratch: &mut OpScratch) -> Result<()> {
+        let digits = self.gadget()?.decompose(p)?;
+        self.accumulate(&digits, scratch)
+    }
 
-        let mut c0 = Poly::zero(&self.ctx_ksk, Representation::Ntt);
-        let mut c1 = Poly::zero(&self.ctx_ksk, Representation::Ntt);
-        for (c2_i_coefficients, c0_i, c1_i) in izip!(c2i.iter(), self.c0.iter(), self.c1.iter()) {
-            let mut c2_i = unsafe {
-                Poly::create_constant_ntt_polynomial_with_lazy_coefficients_and_variable_time(
-                    c2_i_coefficients.as_slice(),
+    /// Accumulates the products of the digits with the key switching key
+    /// into `scratch.key_switched`, with one dot product for each of c0 and
+    /// c1.
+    fn accumulate(&self, digits: &[Vec<u64>], scratch: &mut OpScratch) -> Result<()> {
+        let count = digits.len().min(self.c0.len());
+        scratch.digits.resize_with(count, Poly::default);
+        for (digit, c2_i_coefficients) in izip!(scratch.digits.iter_mut(), digits) {
+            unsafe {
+                digit.set_constant_ntt_polynomial_with_lazy_coefficients_and_variable_time(
+                    c2_i_coefficients,
                     &self.ctx_ksk,
                 )
             };
-            c0 += &(&c2_i * c0_i);
-            c2_i *= c1_i;
-            c1 += &c2_i;
         }
-        Ok((c0, c1))
+        scratch.key_switched = [
+            dot_product(scratch.digits.iter(), self.c0.iter())?,
+            dot_product(scratch.digits.iter(), self.c1.iter())?,
+        ];
+        Ok(())
     }
 }
 
@@ -326,50 +270,53 @@ impl From<&KeySwitchingKey> for KeySwitchingKeyProto {
         ksk.ciphertext_level = value.ciphertext_level as u32;
         ksk.ksk_level = value.ksk_level as u32;
         ksk.log_base = value.log_base as u32;
+        ksk.fingerprint = value.par.fingerprint();
+        ksk.version = FORMAT_VERSION;
         ksk
     }
 }
 
 impl BfvTryConvertFrom<&KeySwitchingKeyProto> for KeySwitchingKey {
     fn try_convert_from(value: &KeySwitchingKeyProto, par: &Arc<BfvParameters>) -> Result<Self> {
+        check_version(value.version)?;
+        par.check_serialized_fingerprint(value.fingerprint)?;
+
         let ciphertext_level = value.ciphertext_level as usize;
         let ksk_level = value.ksk_level as usize;
         let ctx_ksk = par.ctx_at_level(ksk_level)?;
         let ctx_ciphertext = par.ctx_at_level(ciphertext_level)?;
 
-        let c0_size: usize;
         let log_base = value.log_base as usize;
-        if log_base != 0 {
+        let gadget = if log_base != 0 {
             if ksk_level != par.max_level() || ciphertext_level != par.max_level() {
-                return Err(Error::DefaultError(
-                    "A decomposition size is specified but the levels are not maximal".to_string(),
-                ));
-            } else {
-                let log_modulus: usize =
-                    par.moduli().first().unwrap().next_power_of_two().ilog2() as usize;
-                c0_size = (log_modulus + log_base - 1) / log_base;
+                // A decomposition is only used when the levels are maximal.
+                return Err(Error::LevelMismatch {
+                    expected: par.max_level(),
+                    found: ksk_level.min(ciphertext_level),
+                });
             }
+            Gadget::power_of_two(ctx_ciphertext, log_base)?
         } else {
-            c0_size = ctx_ciphertext.moduli().len();
-        }
+            Gadget::rns(ctx_ciphertext)
+        };
+        let c0_size = gadget.len();
 
         if value.c0.len() != c0_size {
-            return Err(Error::DefaultError(
-                "Incorrect number of values in c0".to_string(),
-            ));
+            return Err(Error::SerializationError);
         }
 
         let seed = if value.seed.is_empty() {
             if value.c1.len() != c0_size {
-                return Err(Error::DefaultError(
-                    "Incorrect number of values in c1".to_string(),
-                ));
+                return Err(Error::SerializationError);
             }
             None
         } else {
             let unwrapped = <ChaCha8Rng as SeedableRng>::Seed::try_from(value.seed.clone());
             if unwrapped.is_err() {
-                return Err(Error::DefaultError("Invalid seed".to_string()));
+                return Err(Error::MathError(fhe_math::Error::InvalidSeedSize(
+                    value.seed.len(),
+                    <ChaCha8Rng as SeedableRng>::Seed::default().len(),
+                )));
             }
             Some(unwrapped.unwrap())
         };
@@ -410,11 +357,7 @@ mod tests {
         keys::key_switching_key::KeySwitchingKey, traits::TryConvertFrom, BfvParameters, SecretKey,
     };
     use crate::proto::bfv::KeySwitchingKey as KeySwitchingKeyProto;
-    use fhe_math::{
-        rns::RnsContext,
-        rq::{traits::TryConvertFrom as TryConvertFromPoly, Poly, Representation},
-    };
-    use num_bigint::BigUint;
+    use fhe_math::rq::{traits::TryConvertFrom as TryConvertFromPoly, Poly, Representation};
     use rand::thread_rng;
     use std::error::Error;
 
@@ -480,10 +423,7 @@ mod tests {
                 let mut c3 = &input * &p;
                 c3.change_representation(Representation::PowerBasis);
 
-                let rns = RnsContext::new(&params.moduli)?;
-                Vec::<BigUint>::from(&(&c2 - &c3)).iter().for_each(|b| {
-                    assert!(std::cmp::min(b.bits(), (rns.modulus() - b).bits()) <= 70)
-                });
+                assert!((&c2 - &c3).infinity_norm().bits() <= 70);
             }
         }
         Ok(())
@@ -518,13 +458,7 @@ mod tests {
                 let mut c3 = &input * &p;
                 c3.change_representation(Representation::PowerBasis);
 
-                let rns = RnsContext::new(ctx.moduli())?;
-                Vec::<BigUint>::from(&(&c2 - &c3)).iter().for_each(|b| {
-                    assert!(
-                        std::cmp::min(b.bits(), (rns.modulus() - b).bits())
-                            <= (rns.modulus().bits() / 2) + 10
-                    )
-                });
+                assert!((&c2 - &c3).infinity_norm().bits() <= (ctx.modulus().bits() / 2) + 10);
             }
         }
         Ok(())
@@ -1,14 +1,24 @@
 mod evaluation_key;
 mod galois_key;
 mod key_switching_key;
+mod operations;
+mod provider;
 mod public_key;
+mod reencryption_key;
 mod relinearization_key;
+mod ring_switching_key;
 mod secret_key;
+mod store;
 
 pub use evaluation_key::{EvaluationKey, EvaluationKeyBuilder};
 pub use galois_key::GaloisKey;
+pub use operations::{SecretKeyMessage, SecretKeyOperations};
+pub use provider::{GaloisKeyProvider, LazyEvaluationKey};
 pub use public_key::PublicKey;
+pub use reencryption_key::ReencryptionKey;
 pub use relinearization_key::RelinearizationKey;
+pub use ring_switching_key::RingSwitchingKey;
 pub use secret_key::SecretKey;
+pub use store::{FileKeyStore, KeyStore, KeyType, MemoryKeyStore, StorableKey};
 
 pub(crate) use key_switching_key::KeySwitchingKey;
@@ -0,0 +1,159 @@
+//! Operations requiring the secret key, which can be delegated to an external
+//! device such as a hardware security module or a trusted execution
+//! environment.
+
+use crate::bfv::{BfvParameters, Ciphertext, Plaintext};
+use crate::Result;
+use fhe_math::rq::Poly;
+use num_bigint::BigUint;
+use rand::{CryptoRng, RngCore};
+use std::sync::Arc;
+
+/// The secret-dependent message of a key sample.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SecretKeyMessage {
+    /// No message, as in a public key.
+    Zero,
+    /// The square `s^2` of the secret key, as in a relinearization key.
+    SecretSquared,
+    /// The image `s(x^exponent)` of the secret key by a substitution, as in a
+    /// Galois key.
+    Substitution(usize),
+}
+
+/// The operations requiring the secret key.
+///
+/// This trait allows the coefficients of the secret key to stay in an external
+/// device, while the public key, the relinearization key and the evaluation
+/// keys are assembled locally from the key samples computed by the device. A
+/// [`SecretKey`](crate::bfv::SecretKey) implements these operations itself.
+pub trait SecretKeyOperations {
+    /// Returns the parameters of the secret key.
+    fn parameters(&self) -> &Arc<BfvParameters>;
+
+    /// Computes the key samples `b_i = -a_i * s + e_i + scale_i * m`, in power
+    /// basis representation, where `s` is the secret key, the `e_i` are fresh
+    /// errors and the message `m` is computed from the secret key at level
+    /// `ciphertext_level` before being switched to the context of the
+    /// polynomials `a_i`.
+    ///
+    /// The implementor may draw the errors from its own source of randomness
+    /// instead of `rng`.
+    fn key_samples<R: RngCore + CryptoRng>(
+        &self,
+        a: &[Poly],
+        message: SecretKeyMessage,
+        ciphertext_level: usize,
+        scales: &[BigUint],
+        rng: &mut R,
+    ) -> Result<Vec<Poly>>;
+
+    /// Decrypts a [`Ciphertext`].
+    fn decrypt(&self, ct: &Ciphertext) -> Result<Plaintext>;
+
+    /// Returns the identifier of the secret key, if known, with which the
+    /// ciphertexts encrypted under the keys it generates are tagged.
+    fn key_id(&self) -> Option<u64> {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{SecretKeyMessage, SecretKeyOperations};
+    use crate::bfv::{
+        BfvParameters, Ciphertext, Encoding, EvaluationKeyBuilder, Plaintext, PublicKey,
+        RelinearizationKey, SecretKey,
+    };
+    use crate::{Error, Result};
+    use fhe_math::rq::Poly;
+    use fhe_traits::{FheDecoder, FheDecrypter, FheEncoder, FheEncrypter};
+    use num_bigint::BigUint;
+    use rand::{thread_rng, CryptoRng, RngCore};
+    use std::{cell::Cell, error::Error as StdError, rc::Rc, sync::Arc};
+
+    /// A device holding the secret key, which counts the operations.
+    #[derive(Clone)]
+    struct Device {
+        sk: SecretKey,
+        operations: Rc<Cell<usize>>,
+        available: bool,
+    }
+
+    impl SecretKeyOperations for Device {
+        fn parameters(&self) -> &Arc<BfvParameters> {
+            self.sk.parameters()
+        }
+
+        fn key_samples<R: RngCore + CryptoRng>(
+            &self,
+            a: &[Poly],
+            message: SecretKeyMessage,
+            ciphertext_level: usize,
+            scales: &[BigUint],
+            rng: &mut R,
+        ) -> Result<Vec<Poly>> {
+            if !self.available {
+                return Err(Error::UnsupportedOperation("Unavailable".to_string()));
+            }
+            self.operations.set(self.operations.get() + 1);
+            self.sk
+                .key_samples(a, message, ciphertext_level, scales, rng)
+        }
+
+        fn decrypt(&self, ct: &Ciphertext) -> Result<Plaintext> {
+            self.operations.set(self.operations.get() + 1);
+            self.sk.decrypt(ct)
+        }
+    }
+
+    #[test]
+    fn delegated_keys() -> std::result::Result<(), Box<dyn StdError>> {
+        let mut rng = thread_rng();
+        let par = BfvParameters::default_arc(3, 16);
+        let sk = SecretKey::random(&par, &mut rng);
+        let mut device = Device {
+            sk: sk.clone(),
+            operations: Rc::new(Cell::new(0)),
+            available: true,
+        };
+
+        let pk = PublicKey::new_with_operations(&device, &mut rng)?;
+        let rk = RelinearizationKey::new(&device, &mut rng)?;
+        let ek = EvaluationKeyBuilder::new(&device)?
+            .enable_column_rotation(1)?
+            .build(&mut rng)?;
+        assert_eq!(device.operations.get(), 3);
+
+        let v = par.plaintext.random_vec(par.degree(), &mut rng);
+        let pt = Plaintext::try_encode(&v, Encoding::simd(), &par)?;
+        let ct: Ciphertext = pk.try_encrypt(&pt, &mut rng)?;
+        let pt2 = device.decrypt(&ct)?;
+        assert_eq!(Vec::<u64>::try_decode(&pt2, Encoding::simd())?, v);
+        assert_eq!(device.operations.get(), 4);
+
+        let mut ct2 = &ct * &ct;
+        rk.relinearizes(&mut ct2)?;
+        let mut expected = v.clone();
+        par.plaintext.mul_vec(&mut expected, &v);
+        let pt2 = sk.try_decrypt(&ct2)?;
+        assert_eq!(Vec::<u64>::try_decode(&pt2, Encoding::simd())?, expected);
+
+        let ct3 = ek.rotates_columns_by(&ct, 1)?;
+        let pt3 = sk.try_decrypt(&ct3)?;
+        let row_size = par.degree() >> 1;
+        let expected = [
+            &v[1..row_size],
+            &v[..1],
+            &v[row_size + 1..],
+            &v[row_size..=row_size],
+        ]
+        .concat();
+        assert_eq!(Vec::<u64>::try_decode(&pt3, Encoding::simd())?, expected);
+
+        device.available = false;
+        assert!(PublicKey::new_with_operations(&device, &mut rng).is_err());
+        assert!(RelinearizationKey::new(&device, &mut rng).is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,242 @@
+//! On-demand loading of the Galois keys of an evaluation key.
+
+use std::collections::HashSet;
+use std::sync::{Arc, RwLock};
+
+use crate::bfv::{BfvParameters, Ciphertext, EvaluationKey};
+use crate::{Error, Result};
+
+/// A source of Galois keys, e.g. the parts of an evaluation key obtained with
+/// [`EvaluationKey::split`] and stored on disk or in an object store.
+pub trait GaloisKeyProvider {
+    /// Returns an evaluation key for ciphertexts at level `ciphertext_level`
+    /// holding the Galois key of the Galois element `galois_element`, or
+    /// `None` if the provider does not have this key.
+    fn fetch(
+        &self,
+        ciphertext_level: usize,
+        galois_element: usize,
+    ) -> Result<Option<EvaluationKey>>;
+}
+
+impl<F> GaloisKeyProvider for F
+where
+    F: Fn(usize, usize) -> Result<Option<EvaluationKey>>,
+{
+    fn fetch(
+        &self,
+        ciphertext_level: usize,
+        galois_element: usize,
+    ) -> Result<Option<EvaluationKey>> {
+        self(ciphertext_level, galois_element)
+    }
+}
+
+/// An evaluation key whose Galois keys are fetched from a
+/// [`GaloisKeyProvider`] the first time an operation needs them, and cached
+/// afterwards.
+pub struct LazyEvaluationKey<P: GaloisKeyProvider> {
+    par: Arc<BfvParameters>,
+    ciphertext_level: usize,
+    provider: P,
+    cache: RwLock<Option<EvaluationKey>>,
+}
+
+impl<P: GaloisKeyProvider> LazyEvaluationKey<P> {
+    /// Creates an evaluation key for ciphertexts at level `ciphertext_level`,
+    /// without any Galois key loaded.
+    pub fn new(par: &Arc<BfvParameters>, ciphertext_level: usize, provider: P) -> Result<Self> {
+        par.ctx_at_level(ciphertext_level)?;
+        Ok(Self {
+            par: par.clone(),
+            ciphertext_level,
+            provider,
+            cache: RwLock::new(None),
+        })
+    }
+
+    /// Returns the Galois elements of the Galois keys loaded so far, in
+    /// increasing order.
+    pub fn loaded_galois_elements(&self) -> Vec<usize> {
+        self.cache
+            .read()
+            .unwrap()
+            .as_ref()
+            .map_or_else(Vec::new, |ek| ek.galois_elements())
+    }
+
+    /// Drops the Galois keys loaded so far.
+    pub fn clear(&self) {
+        *self.cache.write().unwrap() = None
+    }
+
+    /// Homomorphically rotate the rows of the plaintext.
+    pub fn rotates_rows(&self, ct: &Ciphertext) -> Result<Ciphertext> {
+        self.with_galois_keys(&[2 * self.par.degree() - 1], |ek| ek.rotates_rows(ct))
+    }
+
+    /// Homomorphically rotate the columns of the plaintext.
+    pub fn rotates_columns_by(&self, ct: &Ciphertext, i: usize) -> Result<Ciphertext> {
+        let element = EvaluationKey::column_rotation_galois_element(&self.par, i)?;
+        self.with_galois_keys(&[element], |ek| ek.rotates_columns_by(ct, i))
+    }
+
+    /// Homomorphically rotate the columns of the plaintext by each of the
+    /// `indices`, as [`EvaluationKey::rotates_columns_by_many`].
+    pub fn rotates_columns_by_many(
+        &self,
+        ct: &Ciphertext,
+        indices: &[usize],
+    ) -> Result<Vec<Ciphertext>> {
+        let elements = indices
+            .iter()
+            .map(|i| EvaluationKey::column_rotation_galois_element(&self.par, *i))
+            .collect::<Result<Vec<_>>>()?;
+        self.with_galois_keys(&elements, |ek| ek.rotates_columns_by_many(ct, indices))
+    }
+
+    /// Computes the homomorphic inner sum.
+    pub fn computes_inner_sum(&self, ct: &Ciphertext) -> Result<Ciphertext> {
+        let mut elements = vec![2 * self.par.degree() - 1];
+        let mut i = 1;
+        while i < self.par.degree() / 2 {
+            elements.push(EvaluationKey::column_rotation_galois_element(&self.par, i)?);
+            i *= 2
+        }
+        self.with_galois_keys(&elements, |ek| ek.computes_inner_sum(ct))
+    }
+
+    /// Loads the Galois keys of the `elements` which are not cached yet, and
+    /// runs `f` on the cached evaluation key.
+    fn with_galois_keys<T, F>(&self, elements: &[usize], f: F) -> Result<T>
+    where
+        F: FnOnce(&EvaluationKey) -> Result<T>,
+    {
+        let loaded = self
+            .loaded_galois_elements()
+            .into_iter()
+            .collect::<HashSet<_>>();
+        let missing = elements
+            .iter()
+            .filter(|e| !loaded.contains(e))
+            .collect::<HashSet<_>>();
+        if !missing.is_empty() {
+            let mut parts = missing
+                .into_iter()
+                .map(|element| self.fetch(*element))
+                .collect::<Result<Vec<_>>>()?;
+            let mut cache = self.cache.write().unwrap();
+            if let Some(ek) = cache.take() {
+                parts.push(ek);
+            }
+            *cache = Some(EvaluationKey::merge(&parts)?);
+        }
+
+        let cache = self.cache.read().unwrap();
+        f(cache.as_ref().unwrap())
+    }
+
+    /// Fetches and checks the Galois key of the element `element`.
+    fn fetch(&self, element: usize) -> Result<EvaluationKey> {
+        let ek = self
+            .provider
+            .fetch(self.ciphertext_level, element)?
+            .ok_or_else(|| {
+                Error::UnsupportedOperation(format!(
+                    "The Galois key of the element {element} is not available"
+                ))
+            })?;
+        self.par.check_fingerprint(&ek.par)?;
+        if ek.ciphertext_level() != self.ciphertext_level {
+            return Err(Error::LevelMismatch {
+                expected: self.ciphertext_level,
+                found: ek.ciphertext_level(),
+            });
+        }
+        if !ek.galois_elements().contains(&element) {
+            return Err(Error::UnspecifiedInput(format!(
+                "The provider did not return the Galois key of the element {element}"
+            )));
+        }
+        Ok(ek)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::LazyEvaluationKey;
+    use crate::bfv::{
+        BfvParameters, Ciphertext, Encoding, EvaluationKey, EvaluationKeyBuilder, Plaintext,
+        SecretKey,
+    };
+    use fhe_traits::{
+        DeserializeParametrized, FheDecoder, FheDecrypter, FheEncoder, FheEncrypter, Serialize,
+    };
+    use rand::thread_rng;
+    use std::cell::Cell;
+    use std::collections::HashMap;
+    use std::error::Error;
+
+    #[test]
+    fn lazy_evaluation_key() -> Result<(), Box<dyn Error>> {
+        let mut rng = thread_rng();
+        let par = BfvParameters::default_arc(3, 16);
+        let sk = SecretKey::random(&par, &mut rng);
+        let ek = EvaluationKeyBuilder::new_leveled(&sk, 0, 0)?
+            .enable_inner_sum()?
+            .enable_column_rotation(3)?
+            .build(&mut rng)?;
+
+        // The parts of the evaluation key are stored serialized.
+        let chunks = ek
+            .galois_elements()
+            .into_iter()
+            .zip(ek.split())
+            .map(|(element, part)| (element, part.to_bytes()))
+            .collect::<HashMap<_, _>>();
+        let fetches = Cell::new(0);
+        let provider = |level: usize, element: usize| {
+            fetches.set(fetches.get() + 1);
+            assert_eq!(level, 0);
+            chunks
+                .get(&element)
+                .map(|bytes| EvaluationKey::from_bytes(bytes, &par))
+                .transpose()
+        };
+        let lazy = LazyEvaluationKey::new(&par, 0, provider)?;
+        assert!(lazy.loaded_galois_elements().is_empty());
+
+        let v = par.plaintext.random_vec(par.degree(), &mut rng);
+        let pt = Plaintext::try_encode(&v, Encoding::simd(), &par)?;
+        let ct = sk.try_encrypt(&pt, &mut rng)?;
+        let decrypt = |ct: &Ciphertext| -> Result<Vec<u64>, Box<dyn Error>> {
+            Ok(Vec::<u64>::try_decode(
+                &sk.try_decrypt(ct)?,
+                Encoding::simd(),
+            )?)
+        };
+
+        let rotated = lazy.rotates_columns_by(&ct, 3)?;
+        assert_eq!(
+            decrypt(&rotated)?,
+            decrypt(&ek.rotates_columns_by(&ct, 3)?)?
+        );
+        assert_eq!(lazy.loaded_galois_elements().len(), 1);
+        assert_eq!(fetches.get(), 1);
+        lazy.rotates_columns_by_many(&ct, &[3, 3])?;
+        assert_eq!(fetches.get(), 1);
+
+        let sum = lazy.computes_inner_sum(&ct)?;
+        assert_eq!(decrypt(&sum)?, decrypt(&ek.computes_inner_sum(&ct)?)?);
+        assert_eq!(lazy.loaded_galois_elements(), ek.galois_elements());
+        let rows = lazy.rotates_rows(&ct)?;
+        assert_eq!(decrypt(&rows)?, decrypt(&ek.rotates_rows(&ct)?)?);
+        assert_eq!(fetches.get(), chunks.len());
+
+        // The rotation by 5 is not available.
+        assert!(lazy.rotates_columns_by(&ct, 5).is_err());
+        lazy.clear();
+        assert!(lazy.loaded_galois_elements().is_empty());
+        Ok(())
+    }
+}
@@ -1,13 +1,20 @@
 //! Public keys for the BFV encryption scheme
 
 use crate::bfv::traits::TryConvertFrom;
-use crate::bfv::{BfvParameters, Ciphertext, Encoding, Plaintext};
+use crate::bfv::{
+    noise, BfvParameters, Ciphertext, Encoding, Plaintext, SecretKeyMessage, SecretKeyOperations,
+};
 use crate::proto::bfv::{Ciphertext as CiphertextProto, PublicKey as PublicKeyProto};
 use crate::{Error, Result};
 use fhe_math::rq::{Poly, Representation};
 use fhe_traits::{DeserializeParametrized, FheEncrypter, FheParametrized, Serialize};
+use num_bigint::BigUint;
+use num_traits::One;
 use prost::Message;
-use rand::{CryptoRng, RngCore};
+use rand::{CryptoRng, Rng, RngCore, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use std::collections::btree_map::Entry;
+use std::collections::BTreeMap;
 use std::sync::Arc;
 use zeroize::Zeroizing;
 
@@ -22,6 +29,10 @@ pub struct PublicKey {
 
 impl PublicKey {
     /// Generate a new [`PublicKey`] from a [`SecretKey`].
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "debug", skip_all, fields(degree = sk.par.degree()))
+    )]
     pub fn new<R: RngCore + CryptoRng>(sk: &SecretKey, rng: &mut R) -> Self {
         let zero = Plaintext::zero(Encoding::poly(), &sk.par).unwrap();
         let mut c: Ciphertext = sk.try_encrypt(&zero, rng).unwrap();
@@ -34,6 +45,54 @@ impl PublicKey {
             c,
         }
     }
+
+    /// Generate a new [`PublicKey`] from the secret key held by `sk`, which
+    /// only computes the key sample.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "debug", skip_all, fields(degree = sk.parameters().degree()), err)
+    )]
+    pub fn new_with_operations<K: SecretKeyOperations + ?Sized, R: RngCore + CryptoRng>(
+        sk: &K,
+        rng: &mut R,
+    ) -> Result<Self> {
+        let par = sk.parameters();
+        let ctx = par.ctx_at_level(0)?;
+
+        let mut seed = <ChaCha8Rng as SeedableRng>::Seed::default();
+        rng.fill(&mut seed);
+        let a = Poly::random_from_seed(ctx, Representation::Ntt, seed);
+        let mut b = sk
+            .key_samples(
+                std::slice::from_ref(&a),
+                SecretKeyMessage::Zero,
+                0,
+                &[BigUint::one()],
+                rng,
+            )?
+            .pop()
+            .ok_or(Error::TooFewValues(0, 1))?;
+        if b.ctx() != ctx {
+            return Err(Error::MathError(fhe_math::Error::InvalidContext));
+        }
+        // The polynomials of a public key should not allow for variable time
+        // computation.
+        b.disallow_variable_time_computations();
+        b.change_representation(Representation::Ntt);
+
+        Ok(Self {
+            par: par.clone(),
+            c: Ciphertext {
+                par: par.clone(),
+                seed: Some(seed),
+                c: vec![b, a],
+                level: 0,
+                noise: Some(noise::fresh_secret_key(par)),
+                metadata: None,
+                key_id: sk.key_id(),
+            },
+        })
+    }
 }
 
 impl FheParametrized for PublicKey {
@@ -43,17 +102,81 @@ impl FheParametrized for PublicKey {
 impl FheEncrypter<Plaintext, Ciphertext> for PublicKey {
     type Error = Error;
 
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "debug", skip_all, fields(level = pt.level), err)
+    )]
     fn try_encrypt<R: RngCore + CryptoRng>(
         &self,
         pt: &Plaintext,
         rng: &mut R,
     ) -> Result<Ciphertext> {
+        self.par.check_fingerprint(&pt.par)?;
+        self.encrypt_with(&self.key_at_level(pt.level)?, pt, rng)
+    }
+}
+
+impl PublicKey {
+    /// Encrypts the plaintexts `pts`, e.g., for the ingestion of large batches
+    /// of records.
+    ///
+    /// The randomness of the encryption of `pts[i]` is drawn from a ChaCha8
+    /// stream indexed by `i` under a single seed sampled from `rng`, so that
+    /// the encryptions are independent of each other. The public key is
+    /// switched once to each level of the plaintexts. The plaintexts are
+    /// encrypted on the CPU, on the threads of the rayon thread pool with the
+    /// `parallel` feature.
+    pub fn try_encrypt_many<R: RngCore + CryptoRng>(
+        &self,
+        pts: &[Plaintext],
+        rng: &mut R,
+    ) -> Result<Vec<Ciphertext>> {
+        let mut keys = BTreeMap::new();
+        for pt in pts {
+            self.par.check_fingerprint(&pt.par)?;
+            if let Entry::Vacant(entry) = keys.entry(pt.level) {
+                entry.insert(self.key_at_level(pt.level)?);
+            }
+        }
+
+        let mut seed = Zeroizing::new(<ChaCha8Rng as SeedableRng>::Seed::default());
+        rng.fill(seed.as_mut());
+        let encrypt = |(i, pt): (usize, &Plaintext)| {
+            let mut rng = ChaCha8Rng::from_seed(*seed);
+            rng.set_stream(i as u64);
+            self.encrypt_with(&keys[&pt.level], pt, &mut rng)
+        };
+
+        #[cfg(feature = "parallel")]
+        {
+            use rayon::prelude::*;
+            pts.par_iter().enumerate().map(encrypt).collect()
+        }
+        #[cfg(not(feature = "parallel"))]
+        {
+            pts.iter().enumerate().map(encrypt).collect()
+        }
+    }
+
+    /// Returns the ciphertext of the public key switched to the level
+    /// `level`.
+    fn key_at_level(&self, level: usize) -> Result<Ciphertext> {
         let mut ct = self.c.clone();
-        while ct.level != pt.level {
+        while ct.level != level {
             ct.mod_switch_to_next_level()?;
         }
+        Ok(ct)
+    }
 
-        let ctx = self.par.ctx_at_level(ct.level)?;
+    /// Encrypts the plaintext `pt` with the ciphertext `key` of the public key
+    /// at the level of the plaintext.
+    fn encrypt_with<R: RngCore + CryptoRng>(
+        &self,
+        key: &Ciphertext,
+        pt: &Plaintext,
+        rng: &mut R,
+    ) -> Result<Ciphertext> {
+        let ctx = self.par.ctx_at_level(key.level)?;
         let u = Zeroizing::new(Poly::small(
             ctx,
             Representation::Ntt,
@@ -74,10 +197,10 @@ impl FheEncrypter<Plaintext, Ciphertext> for PublicKey {
         )?);
 
         let m = Zeroizing::new(pt.to_poly());
-        let mut c0 = u.as_ref() * &ct.c[0];
+        let mut c0 = u.as_ref() * &key.c[0];
         c0 += &e1;
         c0 += &m;
-        let mut c1 = u.as_ref() * &ct.c[1];
+        let mut c1 = u.as_ref() * &key.c[1];
         c1 += &e2;
 
         // It is now safe to enable variable time computations.
@@ -90,7 +213,10 @@ impl FheEncrypter<Plaintext, Ciphertext> for PublicKey {
             par: self.par.clone(),
             seed: None,
             c: vec![c0, c1],
-            level: ct.level,
+            level: key.level,
+            noise: Some(noise::fresh_public_key(&self.par)),
+            metadata: None,
+            key_id: key.key_id,
         })
     }
 }
@@ -105,18 +231,25 @@ impl From<&PublicKey> for PublicKeyProto {
 
 impl Serialize for PublicKey {
     fn to_bytes(&self) -> Vec<u8> {
-        PublicKeyProto::from(self).encode_to_vec()
+        let bytes = PublicKeyProto::from(self).encode_to_vec();
+        #[cfg(feature = "tracing")]
+        tracing::debug!(bytes = bytes.len(), "serialized public key");
+        bytes
     }
 }
 
 impl DeserializeParametrized for PublicKey {
     type Error = Error;
 
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "debug", skip_all, fields(bytes = bytes.len()), err)
+    )]
     fn from_bytes(bytes: &[u8], par: &Arc<Self::Parameters>) -> Result<Self> {
         let proto: PublicKeyProto =
             Message::decode(bytes).map_err(|_| Error::SerializationError)?;
-        if proto.c.is_some() {
-            let mut c = Ciphertext::try_convert_from(&proto.c.unwrap(), par)?;
+        if let Some(c) = proto.c {
+            let mut c = Ciphertext::try_convert_from(&c, par)?;
             if c.level != 0 {
                 Err(Error::SerializationError)
             } else {
@@ -186,6 +319,38 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn encrypt_many() -> Result<(), Box<dyn Error>> {
+        let mut rng = thread_rng();
+        let params = BfvParameters::default_arc(6, 16);
+        let sk = SecretKey::random(&params, &mut rng);
+        let pk = PublicKey::new(&sk, &mut rng);
+
+        let pts = (0..10)
+            .map(|i| {
+                Plaintext::try_encode(
+                    &params.plaintext.random_vec(params.degree(), &mut rng),
+                    Encoding::poly_at_level(i % 3),
+                    &params,
+                )
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let cts = pk.try_encrypt_many(&pts, &mut rng)?;
+        assert_eq!(cts.len(), pts.len());
+        for (ct, pt) in cts.iter().zip(&pts) {
+            assert_eq!(ct.level(), pt.level());
+            assert_eq!(&sk.try_decrypt(ct)?, pt);
+        }
+        // The encryptions use independent randomness.
+        assert_ne!(cts[0].c[1], cts[3].c[1]);
+
+        assert!(pk.try_encrypt_many(&[], &mut rng)?.is_empty());
+        let other = BfvParameters::default_arc(1, 16);
+        let pt = Plaintext::zero(Encoding::poly(), &other)?;
+        assert!(pk.try_encrypt_many(&[pt], &mut rng).is_err());
+        Ok(())
+    }
+
     #[test]
     fn test_serialize() -> Result<(), Box<dyn Error>> {
         let mut rng = thread_rng();
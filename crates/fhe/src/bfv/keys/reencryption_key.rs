@@ -0,0 +1,259 @@
+//! Re-encryption keys for the rotation of the secret key.
+
+use std::io::{Read, Write};
+use std::sync::Arc;
+
+use super::key_switching_key::KeySwitchingKey;
+use crate::bfv::{noise, BfvParameters, Ciphertext, SecretKey};
+use crate::{Error, Result};
+use fhe_math::rq::{traits::TryConvertFrom, Poly, Representation};
+use fhe_traits::{DeserializeParametrized, Serialize};
+use rand::{CryptoRng, RngCore};
+use zeroize::Zeroizing;
+
+/// The size of the length prefix of the frames of a stream, in bytes.
+const PREFIX_SIZE: usize = 4;
+
+/// Re-encryption key for the BFV encryption scheme.
+///
+/// A re-encryption key is a key switching key from an old secret key to a new
+/// secret key with the same parameters. It converts the ciphertexts encrypted
+/// under the old secret key into ciphertexts of the same plaintexts under the
+/// new secret key, e.g. to rotate the secret key periodically without
+/// decrypting the stored ciphertexts. The noise of the converted ciphertexts
+/// includes the noise of a key switching.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct ReencryptionKey {
+    ksk: KeySwitchingKey,
+}
+
+impl ReencryptionKey {
+    /// Generate a [`ReencryptionKey`] from the [`SecretKey`] `from` to the
+    /// [`SecretKey`] `to`.
+    pub fn new<R: RngCore + CryptoRng>(
+        from: &SecretKey,
+        to: &SecretKey,
+        rng: &mut R,
+    ) -> Result<Self> {
+        Self::new_leveled(from, to, 0, 0, rng)
+    }
+
+    /// Generate a [`ReencryptionKey`] from the [`SecretKey`] `from` to the
+    /// [`SecretKey`] `to`, for ciphertexts at level `ciphertext_level` using
+    /// keys at level `key_level`.
+    pub fn new_leveled<R: RngCore + CryptoRng>(
+        from: &SecretKey,
+        to: &SecretKey,
+        ciphertext_level: usize,
+        key_level: usize,
+        rng: &mut R,
+    ) -> Result<Self> {
+        from.par.check_fingerprint(&to.par)?;
+        let ctx_key = to.par.ctx_at_level(key_level)?;
+        if ctx_key.moduli().len() == 1 {
+            return Err(Error::UnsupportedOperation(
+                "These parameters do not support key switching".to_string(),
+            ));
+        }
+        let s = Zeroizing::new(Poly::try_convert_from(
+            from.coeffs.as_ref(),
+            ctx_key,
+            false,
+            Representation::PowerBasis,
+        )?);
+        let ksk = KeySwitchingKey::new(to, &s, ciphertext_level, key_level, rng)?;
+        Ok(Self { ksk })
+    }
+
+    /// Returns the parameters of the key.
+    pub fn parameters(&self) -> &Arc<BfvParameters> {
+        &self.ksk.par
+    }
+
+    /// Re-encrypts the [`Ciphertext`] `ct` under the new secret key.
+    pub fn reencrypts(&self, ct: &Ciphertext) -> Result<Ciphertext> {
+        self.ksk.par.check_fingerprint(&ct.par)?;
+        if ct.c.len() != 2 {
+            return Err(Error::CiphertextSizeMismatch {
+                expected: 2,
+                found: ct.c.len(),
+            });
+        }
+        if ct.level != self.ksk.ciphertext_level {
+            return Err(Error::LevelMismatch {
+                expected: self.ksk.ciphertext_level,
+                found: ct.level,
+            });
+        }
+
+        let mut c1 = ct.c[1].clone();
+        c1.change_representation(Representation::PowerBasis);
+        let (mut d0, mut d1) = self.ksk.key_switch(&c1)?;
+        let ctx = ct.c[0].ctx();
+        if d0.ctx() != ctx {
+            d0.change_representation(Representation::PowerBasis);
+            d1.change_representation(Representation::PowerBasis);
+            d0.mod_switch_down_to(ctx)?;
+            d1.mod_switch_down_to(ctx)?;
+        }
+        d0.change_representation(Representation::Ntt);
+        d1.change_representation(Representation::Ntt);
+        d0 += &ct.c[0];
+
+        Ok(Ciphertext {
+            par: ct.par.clone(),
+            seed: None,
+            c: vec![d0, d1],
+            level: ct.level,
+            noise: noise::after_key_switch(&self.ksk, ct.noise),
+            metadata: None,
+            // The ciphertext is now encrypted under another key.
+            key_id: None,
+        })
+    }
+
+    /// Re-encrypts the ciphertexts read from `reader` and writes them to
+    /// `writer`, one at a time, calling `progress` with the number of
+    /// ciphertexts re-encrypted so far after each of them. Returns the number
+    /// of re-encrypted ciphertexts.
+    ///
+    /// Each ciphertext of the streams is serialized and prefixed by its length
+    /// as a 4-byte big-endian integer, which is the framing of the
+    /// `FrameCodec` of the `net` feature. Returns an error if a frame is
+    /// truncated or larger than a ciphertext at level 0, or if the streams
+    /// fail.
+    pub fn reencrypts_stream<R, W, F>(
+        &self,
+        reader: &mut R,
+        writer: &mut W,
+        mut progress: F,
+    ) -> Result<usize>
+    where
+        R: Read,
+        W: Write,
+        F: FnMut(usize),
+    {
+        let par = &self.ksk.par;
+        let max_length = par.ciphertext_size_bytes(0, 1)?;
+        let mut count = 0;
+        while let Some(frame) = read_frame(reader, max_length)? {
+            let ct = self.reencrypts(&Ciphertext::from_bytes(&frame, par)?)?;
+            let bytes = ct.to_bytes();
+            writer
+                .write_all(&(bytes.len() as u32).to_be_bytes())
+                .and_then(|_| writer.write_all(&bytes))
+                .map_err(|e| Error::IoError(e.to_string()))?;
+            count += 1;
+            progress(count);
+        }
+        writer.flush().map_err(|e| Error::IoError(e.to_string()))?;
+        Ok(count)
+    }
+}
+
+/// Reads a frame of at most `max_length` bytes from `reader`, or returns
+/// `None` at the end of the stream.
+fn read_frame<R: Read>(reader: &mut R, max_length: usize) -> Result<Option<Vec<u8>>> {
+    let mut prefix = [0u8; PREFIX_SIZE];
+    let mut read = 0;
+    while read < PREFIX_SIZE {
+        match reader.read(&mut prefix[read..]) {
+            Ok(0) if read == 0 => return Ok(None),
+            Ok(0) => return Err(Error::IoError("Truncated frame".to_string())),
+            Ok(n) => read += n,
+            Err(e) if e.kind() == std::io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(Error::IoError(e.to_string())),
+        }
+    }
+    let length = u32::from_be_bytes(prefix) as usize;
+    if length > max_length {
+        return Err(Error::TooManyValues(length, max_length));
+    }
+    let mut frame = vec![0u8; length];
+    reader
+        .read_exact(&mut frame)
+        .map_err(|e| Error::IoError(e.to_string()))?;
+    Ok(Some(frame))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ReencryptionKey;
+    use crate::bfv::{BfvParameters, Ciphertext, Encoding, Plaintext, PublicKey, SecretKey};
+    use fhe_traits::{
+        DeserializeParametrized, FheDecoder, FheDecrypter, FheEncoder, FheEncrypter, Serialize,
+    };
+    use rand::thread_rng;
+    use std::error::Error;
+
+    #[test]
+    fn reencryption() -> Result<(), Box<dyn Error>> {
+        let mut rng = thread_rng();
+        let par = BfvParameters::default_arc(3, 16);
+        let old_sk = SecretKey::random(&par, &mut rng);
+        let new_sk = SecretKey::random(&par, &mut rng);
+        let pk = PublicKey::new(&old_sk, &mut rng);
+
+        for level in 0..par.max_level() {
+            let rk = ReencryptionKey::new_leveled(&old_sk, &new_sk, level, level, &mut rng)?;
+            let encoding = Encoding::simd_at_level(level);
+            let v = par.plaintext.random_vec(par.degree(), &mut rng);
+            let pt = Plaintext::try_encode(&v, encoding.clone(), &par)?;
+            let ct: Ciphertext = pk.try_encrypt(&pt, &mut rng)?;
+            let ct2 = rk.reencrypts(&ct)?;
+            assert_eq!(ct2.level, level);
+            let pt2 = new_sk.try_decrypt(&ct2)?;
+            assert_eq!(Vec::<u64>::try_decode(&pt2, encoding)?, v);
+        }
+
+        // A stream of ciphertexts, some encrypted with the secret key.
+        let rk = ReencryptionKey::new(&old_sk, &new_sk, &mut rng)?;
+        let values = (0..5)
+            .map(|_| par.plaintext.random_vec(par.degree(), &mut rng))
+            .collect::<Vec<_>>();
+        let mut input = vec![];
+        for (i, v) in values.iter().enumerate() {
+            let pt = Plaintext::try_encode(v, Encoding::simd(), &par)?;
+            let ct: Ciphertext = if i % 2 == 0 {
+                old_sk.try_encrypt(&pt, &mut rng)?
+            } else {
+                pk.try_encrypt(&pt, &mut rng)?
+            };
+            let bytes = ct.to_bytes();
+            input.extend_from_slice(&(bytes.len() as u32).to_be_bytes());
+            input.extend_from_slice(&bytes);
+        }
+        let mut output = vec![];
+        let mut reported = vec![];
+        let count =
+            rk.reencrypts_stream(&mut input.as_slice(), &mut output, |n| reported.push(n))?;
+        assert_eq!(count, values.len());
+        assert_eq!(reported, [1, 2, 3, 4, 5]);
+
+        let mut output = output.as_slice();
+        for v in &values {
+            let length = u32::from_be_bytes(output[..4].try_into()?) as usize;
+            let ct = Ciphertext::from_bytes(&output[4..4 + length], &par)?;
+            output = &output[4 + length..];
+            let pt = new_sk.try_decrypt(&ct)?;
+            assert_eq!(&Vec::<u64>::try_decode(&pt, Encoding::simd())?, v);
+        }
+        assert!(output.is_empty());
+
+        // Truncated and oversized frames are rejected.
+        let mut sink = vec![];
+        assert!(rk
+            .reencrypts_stream(&mut &input[..input.len() - 1], &mut sink, |_| {})
+            .is_err());
+        assert!(rk
+            .reencrypts_stream(&mut &[0xffu8; 8][..], &mut sink, |_| {})
+            .is_err());
+        assert_eq!(rk.reencrypts_stream(&mut &[][..], &mut sink, |_| {})?, 0);
+
+        let other = BfvParameters::default_arc(2, 16);
+        assert!(
+            ReencryptionKey::new(&old_sk, &SecretKey::random(&other, &mut rng), &mut rng).is_err()
+        );
+        Ok(())
+    }
+}
@@ -3,18 +3,18 @@
 use std::sync::Arc;
 
 use super::key_switching_key::KeySwitchingKey;
-use crate::bfv::{traits::TryConvertFrom, BfvParameters, Ciphertext, SecretKey};
+use crate::bfv::{
+    noise, traits::TryConvertFrom, BfvParameters, Ciphertext, OpScratch, SecretKeyMessage,
+    SecretKeyOperations,
+};
 use crate::proto::bfv::{
     KeySwitchingKey as KeySwitchingKeyProto, RelinearizationKey as RelinearizationKeyProto,
 };
 use crate::{Error, Result};
-use fhe_math::rq::{
-    switcher::Switcher, traits::TryConvertFrom as TryConvertFromPoly, Poly, Representation,
-};
+use fhe_math::rq::Representation;
 use fhe_traits::{DeserializeParametrized, FheParametrized, Serialize};
 use prost::Message;
 use rand::{CryptoRng, RngCore};
-use zeroize::Zeroizing;
 
 /// Relinearization key for the BFV encryption scheme.
 /// A relinearization key is a special type of key switching key,
@@ -25,14 +25,21 @@ pub struct RelinearizationKey {
 }
 
 impl RelinearizationKey {
-    /// Generate a [`RelinearizationKey`] from a [`SecretKey`].
-    pub fn new<R: RngCore + CryptoRng>(sk: &SecretKey, rng: &mut R) -> Result<Self> {
+    /// Generate a [`RelinearizationKey`] from a
+    /// [`SecretKey`](crate::bfv::SecretKey), or from any
+    /// holder of the secret key.
+    pub fn new<K: SecretKeyOperations + ?Sized, R: RngCore + CryptoRng>(
+        sk: &K,
+        rng: &mut R,
+    ) -> Result<Self> {
         Self::new_leveled_internal(sk, 0, 0, rng)
     }
 
-    /// Generate a [`RelinearizationKey`] from a [`SecretKey`].
-    pub fn new_leveled<R: RngCore + CryptoRng>(
-        sk: &SecretKey,
+    /// Generate a [`RelinearizationKey`] from a
+    /// [`SecretKey`](crate::bfv::SecretKey), or from any
+    /// holder of the secret key.
+    pub fn new_leveled<K: SecretKeyOperations + ?Sized, R: RngCore + CryptoRng>(
+        sk: &K,
         ciphertext_level: usize,
         key_level: usize,
         rng: &mut R,
@@ -40,72 +47,116 @@ impl RelinearizationKey {
         Self::new_leveled_internal(sk, ciphertext_level, key_level, rng)
     }
 
-    fn new_leveled_internal<R: RngCore + CryptoRng>(
-        sk: &SecretKey,
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "debug", skip(sk, rng), err)
+    )]
+    fn new_leveled_internal<K: SecretKeyOperations + ?Sized, R: RngCore + CryptoRng>(
+        sk: &K,
         ciphertext_level: usize,
         key_level: usize,
         rng: &mut R,
     ) -> Result<Self> {
-        let ctx_relin_key = sk.par.ctx_at_level(key_level)?;
-        let ctx_ciphertext = sk.par.ctx_at_level(ciphertext_level)?;
+        let par = sk.parameters();
+        let ctx_relin_key = par.ctx_at_level(key_level)?;
+        par.ctx_at_level(ciphertext_level)?;
 
         if ctx_relin_key.moduli().len() == 1 {
-            return Err(Error::DefaultError(
+            return Err(Error::UnsupportedOperation(
                 "These parameters do not support key switching".to_string(),
             ));
         }
 
-        let mut s = Zeroizing::new(Poly::try_convert_from(
-            sk.coeffs.as_ref(),
-            ctx_ciphertext,
-            false,
-            Representation::PowerBasis,
-        )?);
-        s.change_representation(Representation::Ntt);
-        let mut s2 = Zeroizing::new(s.as_ref() * s.as_ref());
-        s2.change_representation(Representation::PowerBasis);
-        let switcher_up = Switcher::new(ctx_ciphertext, ctx_relin_key)?;
-        let s2_switched_up = Zeroizing::new(s2.mod_switch_to(&switcher_up)?);
-        let ksk = KeySwitchingKey::new(sk, &s2_switched_up, ciphertext_level, key_level, rng)?;
+        let ksk = KeySwitchingKey::new_with_operations(
+            sk,
+            SecretKeyMessage::SecretSquared,
+            ciphertext_level,
+            key_level,
+            rng,
+        )?;
         Ok(Self { ksk })
     }
 
     /// Relinearize an "extended" ciphertext (c0, c1, c2) into a [`Ciphertext`]
     pub fn relinearizes(&self, ct: &mut Ciphertext) -> Result<()> {
-        if ct.c.len() != 3 {
-            Err(Error::DefaultError(
-                "Only supports relinearization of ciphertext with 3 parts".to_string(),
-            ))
-        } else if ct.level != self.ksk.ciphertext_level {
-            Err(Error::DefaultError(
-                "Ciphertext has incorrect level".to_string(),
-            ))
-        } else {
-            let mut c2 = ct.c[2].clone();
-            c2.change_representation(Representation::PowerBasis);
+        self.relinearizes_with_scratch(ct, &mut OpScratch::default())
+    }
 
-            #[allow(unused_mut)]
-            let (mut c0, mut c1) = self.relinearizes_poly(&c2)?;
+    /// Relinearize an "extended" ciphertext (c0, c1, c2) into a [`Ciphertext`],
+    /// reusing the temporary polynomials of `scratch`.
+    pub fn relinearizes_with_scratch(
+        &self,
+        ct: &mut Ciphertext,
+        scratch: &mut OpScratch,
+    ) -> Result<()> {
+        self.check(ct)?;
+        let mut c2 = std::mem::take(&mut scratch.products[0]);
+        c2.clone_from(&ct.c[2]);
+        c2.change_representation(Representation::PowerBasis);
+        let key_switched = self.ksk.key_switch_with_scratch(&c2, scratch);
+        scratch.products[0] = c2;
+        key_switched?;
+
+        let [c0, c1] = &mut scratch.key_switched;
+        if c0.ctx() != ct.c[0].ctx() {
+            c0.change_representation(Representation::PowerBasis);
+            c1.change_representation(Representation::PowerBasis);
+            c0.mod_switch_down_to(ct.c[0].ctx())?;
+            c1.mod_switch_down_to(ct.c[1].ctx())?;
+            c0.change_representation(Representation::Ntt);
+            c1.change_representation(Representation::Ntt);
+        }
 
-            if c0.ctx() != ct.c[0].ctx() {
-                c0.change_representation(Representation::PowerBasis);
-                c1.change_representation(Representation::PowerBasis);
-                c0.mod_switch_down_to(ct.c[0].ctx())?;
-                c1.mod_switch_down_to(ct.c[1].ctx())?;
-                c0.change_representation(Representation::Ntt);
-                c1.change_representation(Representation::Ntt);
-            }
+        ct.c[0] += &*c0;
+        ct.c[1] += &*c1;
+        ct.c.truncate(2);
+        ct.noise = noise::after_key_switch(&self.ksk, ct.noise);
+        Ok(())
+    }
 
-            ct.c[0] += &c0;
-            ct.c[1] += &c1;
-            ct.c.truncate(2);
-            Ok(())
+    /// Relinearizes the "extended" ciphertexts `cts` in place, reusing the
+    /// temporary polynomials across the ciphertexts. With the `parallel`
+    /// feature, the ciphertexts are relinearized on the threads of the rayon
+    /// thread pool.
+    ///
+    /// Returns an error, without modifying any ciphertext, if one of them
+    /// cannot be relinearized.
+    pub fn relinearizes_many(&self, cts: &mut [Ciphertext]) -> Result<()> {
+        cts.iter().try_for_each(|ct| self.check(ct))?;
+
+        #[cfg(feature = "parallel")]
+        {
+            use rayon::prelude::*;
+            cts.par_iter_mut()
+                .try_for_each_init(OpScratch::default, |scratch, ct| {
+                    self.relinearizes_with_scratch(ct, scratch)
+                })
+        }
+        #[cfg(not(feature = "parallel"))]
+        {
+            let mut scratch = OpScratch::default();
+            cts.iter_mut()
+                .try_for_each(|ct| self.relinearizes_with_scratch(ct, &mut scratch))
         }
     }
 
-    /// Relinearize using polynomials.
-    pub(crate) fn relinearizes_poly(&self, c2: &Poly) -> Result<(Poly, Poly)> {
-        self.ksk.key_switch(c2)
+    /// Checks that the ciphertext `ct` is an "extended" ciphertext at the
+    /// level of the key.
+    fn check(&self, ct: &Ciphertext) -> Result<()> {
+        self.ksk.par.check_fingerprint(&ct.par)?;
+        if ct.c.len() != 3 {
+            Err(Error::CiphertextSizeMismatch {
+                expected: 3,
+                found: ct.c.len(),
+            })
+        } else if ct.level != self.ksk.ciphertext_level {
+            Err(Error::LevelMismatch {
+                expected: self.ksk.ciphertext_level,
+                found: ct.level,
+            })
+        } else {
+            Ok(())
+        }
     }
 }
 
@@ -119,19 +170,22 @@ impl From<&RelinearizationKey> for RelinearizationKeyProto {
 
 impl TryConvertFrom<&RelinearizationKeyProto> for RelinearizationKey {
     fn try_convert_from(value: &RelinearizationKeyProto, par: &Arc<BfvParameters>) -> Result<Self> {
-        if value.ksk.is_some() {
+        if let Some(ksk) = value.ksk.as_ref() {
             Ok(RelinearizationKey {
-                ksk: KeySwitchingKey::try_convert_from(value.ksk.as_ref().unwrap(), par)?,
+                ksk: KeySwitchingKey::try_convert_from(ksk, par)?,
             })
         } else {
-            Err(Error::DefaultError("Invalid serialization".to_string()))
+            Err(Error::SerializationError)
         }
     }
 }
 
 impl Serialize for RelinearizationKey {
     fn to_bytes(&self) -> Vec<u8> {
-        RelinearizationKeyProto::from(self).encode_to_vec()
+        let bytes = RelinearizationKeyProto::from(self).encode_to_vec();
+        #[cfg(feature = "tracing")]
+        tracing::debug!(bytes = bytes.len(), "serialized relinearization key");
+        bytes
     }
 }
 
@@ -142,12 +196,16 @@ impl FheParametrized for RelinearizationKey {
 impl DeserializeParametrized for RelinearizationKey {
     type Error = Error;
 
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "debug", skip_all, fields(bytes = bytes.len()), err)
+    )]
     fn from_bytes(bytes: &[u8], par: &Arc<Self::Parameters>) -> Result<Self> {
         let rk = Message::decode(bytes);
         if let Ok(rk) = rk {
             RelinearizationKey::try_convert_from(&rk, par)
         } else {
-            Err(Error::DefaultError("Invalid serialization".to_string()))
+            Err(Error::SerializationError)
         }
     }
 }
@@ -155,10 +213,12 @@ impl DeserializeParametrized for RelinearizationKey {
 #[cfg(test)]
 mod tests {
     use super::RelinearizationKey;
-    use crate::bfv::{traits::TryConvertFrom, BfvParameters, Ciphertext, Encoding, SecretKey};
+    use crate::bfv::{
+        traits::TryConvertFrom, BfvParameters, Ciphertext, Encoding, Plaintext, SecretKey,
+    };
     use crate::proto::bfv::RelinearizationKey as RelinearizationKeyProto;
     use fhe_math::rq::{traits::TryConvertFrom as TryConvertFromPoly, Poly, Representation};
-    use fhe_traits::{FheDecoder, FheDecrypter};
+    use fhe_traits::{FheDecoder, FheDecrypter, FheEncoder, FheEncrypter};
     use rand::thread_rng;
     use std::error::Error;
 
@@ -197,7 +257,7 @@ mod tests {
 
                 // Check that the relinearization by polynomials works the same way
                 c2.change_representation(Representation::PowerBasis);
-                let (mut c0r, mut c1r) = rk.relinearizes_poly(&c2)?;
+                let (mut c0r, mut c1r) = rk.ksk.key_switch(&c2)?;
                 c0r.change_representation(Representation::PowerBasis);
                 c0r.mod_switch_down_to(c0.ctx())?;
                 c1r.change_representation(Representation::PowerBasis);
@@ -216,6 +276,46 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn relinearization_many() -> Result<(), Box<dyn Error>> {
+        let mut rng = thread_rng();
+        let params = BfvParameters::default_arc(3, 16);
+        let sk = SecretKey::random(&params, &mut rng);
+        let rk = RelinearizationKey::new(&sk, &mut rng)?;
+        let values = (0..8)
+            .map(|_| params.plaintext.random_vec(params.degree(), &mut rng))
+            .collect::<Vec<_>>();
+        let cts = values
+            .iter()
+            .map(|v| {
+                let pt = Plaintext::try_encode(v, Encoding::simd(), &params)?;
+                sk.try_encrypt(&pt, &mut rng)
+            })
+            .collect::<crate::Result<Vec<Ciphertext>>>()?;
+        let mut products = cts.iter().map(|ct| ct * ct).collect::<Vec<_>>();
+
+        let mut expected = products.clone();
+        for ct in expected.iter_mut() {
+            rk.relinearizes(ct)?;
+        }
+        rk.relinearizes_many(&mut products)?;
+        assert_eq!(products, expected);
+        for (ct, v) in products.iter().zip(&values) {
+            let pt = sk.try_decrypt(ct)?;
+            let mut square = v.clone();
+            params.plaintext.mul_vec(&mut square, v);
+            assert_eq!(Vec::<u64>::try_decode(&pt, Encoding::simd())?, square);
+        }
+
+        // The ciphertexts are left unchanged when one of them is invalid.
+        let mut mixed = vec![&cts[0] * &cts[0], cts[1].clone()];
+        let copy = mixed.clone();
+        assert!(rk.relinearizes_many(&mut mixed).is_err());
+        assert_eq!(mixed, copy);
+        rk.relinearizes_many(&mut [])?;
+        Ok(())
+    }
+
     #[test]
     fn relinearization_leveled() -> Result<(), Box<dyn Error>> {
         let mut rng = thread_rng();
@@ -258,7 +358,7 @@ mod tests {
 
                         // Check that the relinearization by polynomials works the same way
                         c2.change_representation(Representation::PowerBasis);
-                        let (mut c0r, mut c1r) = rk.relinearizes_poly(&c2)?;
+                        let (mut c0r, mut c1r) = rk.ksk.key_switch(&c2)?;
                         c0r.change_representation(Representation::PowerBasis);
                         c0r.mod_switch_down_to(c0.ctx())?;
                         c1r.change_representation(Representation::PowerBasis);
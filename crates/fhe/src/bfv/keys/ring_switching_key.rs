@@ -0,0 +1,220 @@
+//! Ring switching keys for the BFV encryption scheme.
+
+use std::sync::Arc;
+
+use super::key_switching_key::KeySwitchingKey;
+use crate::bfv::{noise, BfvParameters, Ciphertext, SecretKey};
+use crate::{Error, Result};
+use fhe_math::rq::{traits::TryConvertFrom, Poly, Representation};
+use ndarray::{s, Array2};
+use rand::{CryptoRng, RngCore};
+use zeroize::Zeroizing;
+
+/// Ring switching key for the BFV encryption scheme.
+///
+/// A ring switching key switches a ciphertext in the ring of degree `2N`
+/// encrypted under a secret key `s(x)` to a ciphertext in the ring of degree
+/// `N` encrypted under another secret key. Writing `y = x^2`, the secret key is
+/// `s(x) = s_e(y) + x s_o(y)`, and the key consists of the key switching keys
+/// from `s_e` and from `y s_o` to the secret key in the smaller ring.
+///
+/// The ciphertext moduli and the plaintext modulus of the two sets of
+/// parameters must be the same. Only the messages of the subring, i.e., the
+/// polynomials in `x^2` when using [`Encoding::poly`](crate::bfv::Encoding),
+/// are preserved: the coefficient `2i` of the plaintext becomes the
+/// coefficient `i` of the switched plaintext.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct RingSwitchingKey {
+    from_par: Arc<BfvParameters>,
+    /// Key switching key from the even part of the secret key.
+    ksk_even: KeySwitchingKey,
+    /// Key switching key from `y` times the odd part of the secret key.
+    ksk_odd: KeySwitchingKey,
+}
+
+impl RingSwitchingKey {
+    /// Generate a [`RingSwitchingKey`] from the [`SecretKey`] `from` to the
+    /// [`SecretKey`] `to`, whose degree is half of the degree of `from`.
+    pub fn new<R: RngCore + CryptoRng>(
+        from: &SecretKey,
+        to: &SecretKey,
+        rng: &mut R,
+    ) -> Result<Self> {
+        Self::new_leveled(from, to, 0, 0, rng)
+    }
+
+    /// Generate a [`RingSwitchingKey`] from the [`SecretKey`] `from` to the
+    /// [`SecretKey`] `to`, for ciphertexts at level `ciphertext_level` using
+    /// keys at level `key_level`.
+    pub fn new_leveled<R: RngCore + CryptoRng>(
+        from: &SecretKey,
+        to: &SecretKey,
+        ciphertext_level: usize,
+        key_level: usize,
+        rng: &mut R,
+    ) -> Result<Self> {
+        let (from_par, to_par) = (&from.par, &to.par);
+        if from_par.degree() != 2 * to_par.degree()
+            || from_par.moduli() != to_par.moduli()
+            || from_par.plaintext() != to_par.plaintext()
+        {
+            return Err(Error::UnsupportedOperation(
+                "The parameters do not support ring switching".to_string(),
+            ));
+        }
+        let ctx_key = to_par.ctx_at_level(key_level)?;
+        if ctx_key.moduli().len() == 1 {
+            return Err(Error::UnsupportedOperation(
+                "These parameters do not support key switching".to_string(),
+            ));
+        }
+
+        let n = to_par.degree();
+        let mut even = Zeroizing::new(vec![0i64; n]);
+        let mut odd = Zeroizing::new(vec![0i64; n]);
+        for i in 0..n {
+            even[i] = from.coeffs[2 * i];
+            odd[i] = from.coeffs[2 * i + 1];
+        }
+        // Multiplication by y, where y^n = -1.
+        odd.rotate_right(1);
+        odd[0] = -odd[0];
+
+        let ksk = |coeffs: &[i64], rng: &mut R| -> Result<KeySwitchingKey> {
+            let s = Zeroizing::new(Poly::try_convert_from(
+                coeffs,
+                ctx_key,
+                false,
+                Representation::PowerBasis,
+            )?);
+            KeySwitchingKey::new(to, &s, ciphertext_level, key_level, rng)
+        };
+        let ksk_even = ksk(&even, rng)?;
+        let ksk_odd = ksk(&odd, rng)?;
+
+        Ok(Self {
+            from_par: from_par.clone(),
+            ksk_even,
+            ksk_odd,
+        })
+    }
+
+    /// Switches the [`Ciphertext`] `ct` to the ring of degree `N`.
+    ///
+    /// The resulting ciphertext encrypts the even coefficients of the
+    /// plaintext of `ct`, and its noise includes the noise of two key
+    /// switchings.
+    pub fn switches(&self, ct: &Ciphertext) -> Result<Ciphertext> {
+        self.from_par.check_fingerprint(&ct.par)?;
+        if ct.c.len() != 2 {
+            return Err(Error::CiphertextSizeMismatch {
+                expected: 2,
+                found: ct.c.len(),
+            });
+        }
+        if ct.level != self.ksk_even.ciphertext_level {
+            return Err(Error::LevelMismatch {
+                expected: self.ksk_even.ciphertext_level,
+                found: ct.level,
+            });
+        }
+
+        let ctx = &self.ksk_even.ctx_ciphertext;
+        let [c0_even, c1_even, c1_odd] = {
+            let mut c0 = ct.c[0].clone();
+            let mut c1 = ct.c[1].clone();
+            c0.change_representation(Representation::PowerBasis);
+            c1.change_representation(Representation::PowerBasis);
+            let split = |p: &Poly, offset: usize| -> Result<Poly> {
+                let coefficients: Array2<u64> =
+                    p.coefficients().slice(s![.., offset..;2]).to_owned();
+                Ok(Poly::try_convert_from(
+                    coefficients,
+                    ctx,
+                    false,
+                    Representation::PowerBasis,
+                )?)
+            };
+            [split(&c0, 0)?, split(&c1, 0)?, split(&c1, 1)?]
+        };
+
+        let (mut d0, mut d1) = self.ksk_even.key_switch(&c1_even)?;
+        let (e0, e1) = self.ksk_odd.key_switch(&c1_odd)?;
+        d0 += &e0;
+        d1 += &e1;
+        if d0.ctx() != ctx {
+            d0.change_representation(Representation::PowerBasis);
+            d1.change_representation(Representation::PowerBasis);
+            d0.mod_switch_down_to(ctx)?;
+            d1.mod_switch_down_to(ctx)?;
+        }
+        d0.change_representation(Representation::Ntt);
+        d1.change_representation(Representation::Ntt);
+
+        let mut c0 = c0_even;
+        c0.change_representation(Representation::Ntt);
+        c0 += &d0;
+
+        Ok(Ciphertext {
+            par: self.ksk_even.par.clone(),
+            seed: None,
+            c: vec![c0, d1],
+            level: ct.level,
+            noise: noise::after_key_switch(
+                &self.ksk_odd,
+                noise::after_key_switch(&self.ksk_even, ct.noise),
+            ),
+            metadata: None,
+            // The ciphertext is now encrypted under another key.
+            key_id: None,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::RingSwitchingKey;
+    use crate::bfv::{BfvParameters, BfvParametersBuilder, Encoding, Plaintext, SecretKey};
+    use fhe_traits::{FheDecoder, FheDecrypter, FheEncoder, FheEncrypter};
+    use rand::thread_rng;
+    use std::error::Error;
+
+    #[test]
+    fn ring_switching() -> Result<(), Box<dyn Error>> {
+        let mut rng = thread_rng();
+        let par = BfvParameters::default_arc(3, 16);
+        let small_par = BfvParametersBuilder::new()
+            .set_degree(8)
+            .set_plaintext_modulus(par.plaintext())
+            .set_moduli(par.moduli())
+            .set_minimum_security(0)
+            .build_arc()?;
+        let sk = SecretKey::random(&par, &mut rng);
+        let small_sk = SecretKey::random(&small_par, &mut rng);
+
+        for level in 0..par.max_level() {
+            let rsk = RingSwitchingKey::new_leveled(&sk, &small_sk, level, level, &mut rng)?;
+            for _ in 0..20 {
+                let v = par.plaintext.random_vec(par.degree(), &mut rng);
+                let pt = Plaintext::try_encode(&v, Encoding::poly_at_level(level), &par)?;
+                let ct = sk.try_encrypt(&pt, &mut rng)?;
+
+                let switched = rsk.switches(&ct)?;
+                assert_eq!(switched.level, level);
+                let pt = small_sk.try_decrypt(&switched)?;
+                let expected = v.iter().step_by(2).copied().collect::<Vec<_>>();
+                assert_eq!(
+                    Vec::<u64>::try_decode(&pt, Encoding::poly_at_level(level))?,
+                    expected
+                );
+            }
+        }
+
+        let rsk = RingSwitchingKey::new(&sk, &small_sk, &mut rng)?;
+        let ct = small_sk.try_encrypt(&Plaintext::zero(Encoding::poly(), &small_par)?, &mut rng)?;
+        assert!(rsk.switches(&ct).is_err());
+        assert!(RingSwitchingKey::new(&small_sk, &sk, &mut rng).is_err());
+        assert!(RingSwitchingKey::new(&sk, &sk, &mut rng).is_err());
+        Ok(())
+    }
+}
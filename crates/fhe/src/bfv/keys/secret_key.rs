@@ -1,17 +1,25 @@
 //! Secret keys for the BFV encryption scheme
 
-use crate::bfv::{BfvParameters, Ciphertext, Plaintext};
+use crate::bfv::{
+    noise, BfvParameters, Ciphertext, Plaintext, SecretKeyMessage, SecretKeyOperations,
+};
+use crate::proto::{bfv::SecretKey as SecretKeyProto, check_version, FORMAT_VERSION};
 use crate::{Error, Result};
 use fhe_math::{
-    rq::{traits::TryConvertFrom, Poly, Representation},
+    rq::{
+        switcher::Switcher, traits::TryConvertFrom, Context, Poly, Representation,
+        SubstitutionExponent,
+    },
     zq::Modulus,
 };
 use fhe_traits::{FheDecrypter, FheEncrypter, FheParametrized};
 use fhe_util::sample_vec_cbd;
-use itertools::Itertools;
+use itertools::{izip, Itertools};
 use num_bigint::BigUint;
-use rand::{thread_rng, CryptoRng, Rng, RngCore, SeedableRng};
+use prost::Message;
+use rand::{CryptoRng, Rng, RngCore, SeedableRng};
 use rand_chacha::ChaCha8Rng;
+use sha2::{Digest, Sha256};
 use std::sync::Arc;
 use zeroize::{Zeroize, ZeroizeOnDrop, Zeroizing};
 
@@ -20,6 +28,7 @@ use zeroize::{Zeroize, ZeroizeOnDrop, Zeroizing};
 pub struct SecretKey {
     pub(crate) par: Arc<BfvParameters>,
     pub(crate) coeffs: Box<[i64]>,
+    key_id: u64,
 }
 
 impl Zeroize for SecretKey {
@@ -32,6 +41,10 @@ impl ZeroizeOnDrop for SecretKey {}
 
 impl SecretKey {
     /// Generate a random [`SecretKey`].
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "debug", skip_all, fields(degree = par.degree()))
+    )]
     pub fn random<R: RngCore + CryptoRng>(par: &Arc<BfvParameters>, rng: &mut R) -> Self {
         let s_coefficients = sample_vec_cbd(par.degree(), par.variance, rng).unwrap();
         Self::new(s_coefficients, par)
@@ -41,10 +54,100 @@ impl SecretKey {
     pub(crate) fn new(coeffs: Vec<i64>, par: &Arc<BfvParameters>) -> Self {
         Self {
             par: par.clone(),
+            key_id: Self::compute_key_id(&coeffs),
             coeffs: coeffs.into_boxed_slice(),
         }
     }
 
+    /// Returns the identifier of the secret key, which tags the ciphertexts
+    /// encrypted under this key or its public keys.
+    ///
+    /// The identifier is the truncation of a SHA-256 commitment to the
+    /// coefficients of the key, and is never 0. It does not depend on the
+    /// parameters, so that it is preserved by
+    /// [`Ciphertext::switch_parameters`].
+    pub const fn key_id(&self) -> u64 {
+        self.key_id
+    }
+
+    /// Computes the identifier of the secret key of coefficients `coeffs`.
+    fn compute_key_id(coeffs: &[i64]) -> u64 {
+        let mut hasher = Sha256::new();
+        hasher.update(b"fhe.rs bfv key identifier");
+        coeffs
+            .iter()
+            .for_each(|c| hasher.update(Zeroizing::new(c.to_le_bytes()).as_ref()));
+        let digest = hasher.finalize();
+        let key_id = u64::from_be_bytes(digest[..8].try_into().unwrap());
+        key_id.max(1)
+    }
+
+    /// Returns an error if the ciphertext `ct` is tagged with the identifier of
+    /// another key.
+    pub(crate) fn check_key_id(&self, ct: &Ciphertext) -> Result<()> {
+        match ct.key_id {
+            Some(found) if found != self.key_id => Err(Error::KeyMismatch {
+                expected: self.key_id,
+                found,
+            }),
+            _ => Ok(()),
+        }
+    }
+
+    /// Serializes the secret key.
+    ///
+    /// The serialization is wiped from memory when the returned bytes are
+    /// dropped, as are the intermediate buffers.
+    pub fn to_bytes(&self) -> Zeroizing<Vec<u8>> {
+        let mut proto = SecretKeyProto {
+            coeffs: self.coeffs.to_vec(),
+            fingerprint: self.par.fingerprint(),
+            version: FORMAT_VERSION,
+        };
+        let bytes = Zeroizing::new(proto.encode_to_vec());
+        proto.coeffs.zeroize();
+        #[cfg(feature = "tracing")]
+        tracing::debug!(bytes = bytes.len(), "serialized secret key");
+        bytes
+    }
+
+    /// Deserializes a secret key for the parameters `par`.
+    ///
+    /// The intermediate buffers are wiped from memory.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "debug", skip_all, fields(bytes = bytes.len()), err)
+    )]
+    pub fn from_bytes(bytes: &[u8], par: &Arc<BfvParameters>) -> Result<Self> {
+        // Reserving the coefficients avoids leaving copies of them in memory
+        // when the vector grows during the decoding.
+        let mut proto = SecretKeyProto {
+            coeffs: Vec::with_capacity(par.degree()),
+            fingerprint: 0,
+            version: 0,
+        };
+        let decoded = proto.merge(bytes);
+        let sk = decoded
+            .map_err(|_| Error::SerializationError)
+            .and_then(|_| check_version(proto.version))
+            .and_then(|_| par.check_serialized_fingerprint(proto.fingerprint))
+            .and_then(|_| {
+                // The coefficients are bounded by twice the variance.
+                let bound = 2 * par.variance as i64;
+                let out_of_bound = proto
+                    .coeffs
+                    .iter()
+                    .fold(false, |acc, c| acc | (c.abs() > bound));
+                if proto.coeffs.len() != par.degree() || out_of_bound {
+                    Err(Error::SerializationError)
+                } else {
+                    Ok(Self::new(proto.coeffs.to_vec(), par))
+                }
+            });
+        proto.coeffs.zeroize();
+        sk
+    }
+
     /// Measure the noise in a [`Ciphertext`].
     ///
     /// # Safety
@@ -79,16 +182,7 @@ impl SecretKey {
         *c.as_mut() -= &m;
         c.change_representation(Representation::PowerBasis);
 
-        let ciphertext_modulus = ct.c[0].ctx().modulus();
-        let mut noise = 0usize;
-        for coeff in Vec::<BigUint>::from(c.as_ref()) {
-            noise = std::cmp::max(
-                noise,
-                std::cmp::min(coeff.bits(), (ciphertext_modulus - &coeff).bits()) as usize,
-            )
-        }
-
-        Ok(noise)
+        Ok(c.infinity_norm().bits() as usize)
     }
 
     pub(crate) fn encrypt_poly<R: RngCore + CryptoRng>(
@@ -101,7 +195,7 @@ impl SecretKey {
         let level = self.par.level_of_ctx(p.ctx())?;
 
         let mut seed = <ChaCha8Rng as SeedableRng>::Seed::default();
-        thread_rng().fill(&mut seed);
+        rng.fill(&mut seed);
 
         // Let's create a secret key with the ciphertext context
         let mut s = Zeroizing::new(Poly::try_convert_from(
@@ -131,6 +225,9 @@ impl SecretKey {
             seed: Some(seed),
             c: vec![b, a],
             level,
+            noise: Some(noise::fresh_secret_key(&self.par)),
+            metadata: None,
+            key_id: Some(self.key_id),
         })
     }
 }
@@ -142,12 +239,16 @@ impl FheParametrized for SecretKey {
 impl FheEncrypter<Plaintext, Ciphertext> for SecretKey {
     type Error = Error;
 
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "debug", skip_all, fields(level = pt.level), err)
+    )]
     fn try_encrypt<R: RngCore + CryptoRng>(
         &self,
         pt: &Plaintext,
         rng: &mut R,
     ) -> Result<Ciphertext> {
-        assert_eq!(self.par, pt.par);
+        self.par.check_fingerprint(&pt.par)?;
         let m = Zeroizing::new(pt.to_poly());
         self.encrypt_poly(m.as_ref(), rng)
     }
@@ -156,64 +257,236 @@ impl FheEncrypter<Plaintext, Ciphertext> for SecretKey {
 impl FheDecrypter<Plaintext, Ciphertext> for SecretKey {
     type Error = Error;
 
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            level = "debug",
+            skip_all,
+            fields(level = ct.level, size = ct.c.len()),
+            err
+        )
+    )]
     fn try_decrypt(&self, ct: &Ciphertext) -> Result<Plaintext> {
-        if self.par != ct.par {
-            Err(Error::DefaultError(
-                "Incompatible BFV parameters".to_string(),
-            ))
-        } else {
-            // Let's create a secret key with the ciphertext context
-            let mut s = Zeroizing::new(Poly::try_convert_from(
-                self.coeffs.as_ref(),
-                ct.c[0].ctx(),
-                false,
-                Representation::PowerBasis,
-            )?);
-            s.change_representation(Representation::Ntt);
-            let mut si = s.clone();
-
-            let mut c = Zeroizing::new(ct.c[0].clone());
-            c.disallow_variable_time_computations();
+        self.par.check_fingerprint(&ct.par)?;
+        self.check_key_id(ct)?;
+        // Let's create a secret key with the ciphertext context
+        let mut s = Zeroizing::new(Poly::try_convert_from(
+            self.coeffs.as_ref(),
+            ct.c[0].ctx(),
+            false,
+            Representation::PowerBasis,
+        )?);
+        s.change_representation(Representation::Ntt);
+        let mut si = s.clone();
 
-            // Compute the phase c0 + c1*s + c2*s^2 + ... where the secret power
-            // s^k is computed on-the-fly
-            for i in 1..ct.c.len() {
-                let mut cis = Zeroizing::new(ct.c[i].clone());
-                cis.disallow_variable_time_computations();
-                *cis.as_mut() *= si.as_ref();
-                *c.as_mut() += &cis;
-                if i + 1 < ct.c.len() {
-                    *si.as_mut() *= s.as_ref();
+        let mut c = Zeroizing::new(ct.c[0].clone());
+        c.disallow_variable_time_computations();
+
+        // Compute the phase c0 + c1*s + c2*s^2 + ... where the secret power
+        // s^k is computed on-the-fly
+        for i in 1..ct.c.len() {
+            let mut cis = Zeroizing::new(ct.c[i].clone());
+            cis.disallow_variable_time_computations();
+            *cis.as_mut() *= si.as_ref();
+            *c.as_mut() += &cis;
+            if i + 1 < ct.c.len() {
+                *si.as_mut() *= s.as_ref();
+            }
+        }
+        c.change_representation(Representation::PowerBasis);
+
+        let w = self.scale_phase(&c, ct.level)?;
+
+        let pt = Plaintext::new(&self.par, w.into_boxed_slice(), None, ct.level);
+
+        Ok(pt)
+    }
+}
+
+impl SecretKey {
+    /// Computes the key samples `-a_i * s + e_i + scales_i * from`, where
+    /// `from` is an optional polynomial in the context of the `a_i`.
+    pub(crate) fn key_samples_from<R: RngCore + CryptoRng>(
+        &self,
+        a: &[Poly],
+        from: Option<&Poly>,
+        scales: &[BigUint],
+        rng: &mut R,
+    ) -> Result<Vec<Poly>> {
+        if a.is_empty() {
+            return Err(Error::TooFewValues(0, 1));
+        }
+        if a.len() != scales.len() {
+            return Err(Error::CiphertextSizeMismatch {
+                expected: a.len(),
+                found: scales.len(),
+            });
+        }
+        let ctx = a[0].ctx();
+        if a.iter().any(|ai| ai.ctx() != ctx) || from.is_some_and(|from| from.ctx() != ctx) {
+            return Err(Error::MathError(fhe_math::Error::InvalidContext));
+        }
+        if let Some(from) = from {
+            if from.representation() != &Representation::PowerBasis {
+                return Err(Error::MathError(fhe_math::Error::IncorrectRepresentation(
+                    from.representation().clone(),
+                    Representation::PowerBasis,
+                )));
+            }
+        }
+
+        let mut s = Zeroizing::new(Poly::try_convert_from(
+            self.coeffs.as_ref(),
+            ctx,
+            false,
+            Representation::PowerBasis,
+        )?);
+        s.change_representation(Representation::Ntt);
+
+        izip!(a, scales)
+            .map(|(ai, scale)| {
+                let mut a_s = Zeroizing::new(ai.clone());
+                a_s.disallow_variable_time_computations();
+                a_s.change_representation(Representation::Ntt);
+                *a_s.as_mut() *= s.as_ref();
+                a_s.change_representation(Representation::PowerBasis);
+
+                let mut b = Poly::small(ctx, Representation::PowerBasis, self.par.variance, rng)?;
+                b -= &a_s;
+                if let Some(from) = from {
+                    b += &Zeroizing::new(from * scale);
                 }
+                Ok(b)
+            })
+            .collect()
+    }
+
+    /// Computes the secret-dependent `message` at level `ciphertext_level`,
+    /// switched to the context `ctx`, in power basis representation.
+    fn message(
+        &self,
+        message: SecretKeyMessage,
+        ciphertext_level: usize,
+        ctx: &Arc<Context>,
+    ) -> Result<Option<Zeroizing<Poly>>> {
+        let ctx_ciphertext = self.par.ctx_at_level(ciphertext_level)?;
+        let s = Zeroizing::new(Poly::try_convert_from(
+            self.coeffs.as_ref(),
+            ctx_ciphertext,
+            false,
+            Representation::PowerBasis,
+        )?);
+        let mut m = match message {
+            SecretKeyMessage::Zero => return Ok(None),
+            SecretKeyMessage::SecretSquared => {
+                let mut s = s;
+                s.change_representation(Representation::Ntt);
+                let mut s2 = Zeroizing::new(s.as_ref() * s.as_ref());
+                s2.change_representation(Representation::PowerBasis);
+                s2
             }
-            c.change_representation(Representation::PowerBasis);
-
-            let d = Zeroizing::new(c.scale(&self.par.scalers[ct.level])?);
-
-            // TODO: Can we handle plaintext moduli that are BigUint?
-            let v = Zeroizing::new(
-                Vec::<u64>::from(d.as_ref())
-                    .iter_mut()
-                    .map(|vi| *vi + self.par.plaintext.modulus())
-                    .collect_vec(),
-            );
-            let mut w = v[..self.par.degree()].to_vec();
-            let q = Modulus::new(self.par.moduli[0]).map_err(Error::MathError)?;
-            q.reduce_vec(&mut w);
-            self.par.plaintext.reduce_vec(&mut w);
-
-            let mut poly =
-                Poly::try_convert_from(&w, ct.c[0].ctx(), false, Representation::PowerBasis)?;
-            poly.change_representation(Representation::Ntt);
-
-            let pt = Plaintext {
-                par: self.par.clone(),
-                value: w.into_boxed_slice(),
-                encoding: None,
-                poly_ntt: poly,
-                level: ct.level,
-            };
+            SecretKeyMessage::Substitution(exponent) => {
+                let exponent = SubstitutionExponent::new(ctx_ciphertext, exponent)?;
+                Zeroizing::new(s.substitute(&exponent)?)
+            }
+        };
+        let switcher_up = Switcher::new(ctx_ciphertext, ctx)?;
+        m = Zeroizing::new(m.mod_switch_to(&switcher_up)?);
+        m.change_representation(Representation::PowerBasis);
+        Ok(Some(m))
+    }
+}
+
+impl SecretKeyOperations for SecretKey {
+    fn parameters(&self) -> &Arc<BfvParameters> {
+        &self.par
+    }
+
+    fn key_samples<R: RngCore + CryptoRng>(
+        &self,
+        a: &[Poly],
+        message: SecretKeyMessage,
+        ciphertext_level: usize,
+        scales: &[BigUint],
+        rng: &mut R,
+    ) -> Result<Vec<Poly>> {
+        let ctx = a.first().ok_or(Error::TooFewValues(0, 1))?.ctx().clone();
+        let m = self.message(message, ciphertext_level, &ctx)?;
+        self.key_samples_from(a, m.as_deref(), scales, rng)
+    }
+
+    fn decrypt(&self, ct: &Ciphertext) -> Result<Plaintext> {
+        self.try_decrypt(ct)
+    }
+
+    fn key_id(&self) -> Option<u64> {
+        Some(self.key_id)
+    }
+}
+
+impl SecretKey {
+    /// Scales the phase `c` of a ciphertext at level `level`, in PowerBasis
+    /// representation, into the plaintext values.
+    fn scale_phase(&self, c: &Poly, level: usize) -> Result<Vec<u64>> {
+        let d = Zeroizing::new(c.scale(&self.par.scalers[level])?);
+
+        // TODO: Can we handle plaintext moduli that are BigUint?
+        let v = Zeroizing::new(
+            Vec::<u64>::from(d.as_ref())
+                .iter_mut()
+                .map(|vi| *vi + self.par.plaintext.modulus())
+                .collect_vec(),
+        );
+        let mut w = v[..self.par.degree()].to_vec();
+        let q = Modulus::new(self.par.moduli[0]).map_err(Error::MathError)?;
+        q.reduce_vec(&mut w);
+        self.par.plaintext.reduce_vec(&mut w);
+        Ok(w)
+    }
+
+    /// Decrypts a [`Ciphertext`] and verifies the decryption before releasing
+    /// the plaintext, as a countermeasure against fault injections.
+    ///
+    /// The phase of the ciphertext is computed a second time with Horner's
+    /// method, independently of [`FheDecrypter::try_decrypt`], and the two
+    /// decryptions are compared in constant time. Returns
+    /// [`Error::DecryptionVerificationFailed`] if they differ.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            level = "debug",
+            skip_all,
+            fields(level = ct.level, size = ct.c.len()),
+            err
+        )
+    )]
+    pub fn try_decrypt_verified(&self, ct: &Ciphertext) -> Result<Plaintext> {
+        let mut pt = self.try_decrypt(ct)?;
+
+        let mut s = Zeroizing::new(Poly::try_convert_from(
+            self.coeffs.as_ref(),
+            ct.c[0].ctx(),
+            false,
+            Representation::PowerBasis,
+        )?);
+        s.change_representation(Representation::Ntt);
+
+        // Compute the phase c0 + s * (c1 + s * (c2 + ...)).
+        let mut c = Zeroizing::new(ct.c[ct.c.len() - 1].clone());
+        c.disallow_variable_time_computations();
+        for ci in ct.c.iter().rev().skip(1) {
+            *c.as_mut() *= s.as_ref();
+            *c.as_mut() += ci;
+            c.disallow_variable_time_computations();
+        }
+        c.change_representation(Representation::PowerBasis);
+        let w = Zeroizing::new(self.scale_phase(&c, ct.level)?);
 
+        let difference = izip!(pt.value.iter(), w.iter()).fold(0, |acc, (a, b)| acc | (a ^ b));
+        if difference != 0 || pt.value.len() != w.len() {
+            pt.zeroize();
+            Err(Error::DecryptionVerificationFailed)
+        } else {
             Ok(pt)
         }
     }
@@ -267,4 +540,83 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn decrypt_verified() -> Result<(), Box<dyn Error>> {
+        let mut rng = thread_rng();
+        let params = BfvParameters::default_arc(6, 16);
+        let sk = SecretKey::random(&params, &mut rng);
+        for level in 0..params.max_level() {
+            let pt = Plaintext::try_encode(
+                &params.plaintext.random_vec(params.degree(), &mut rng),
+                Encoding::poly_at_level(level),
+                &params,
+            )?;
+            let ct = sk.try_encrypt(&pt, &mut rng)?;
+            assert_eq!(sk.try_decrypt_verified(&ct)?, pt);
+
+            // Ciphertexts of size 3 use the square of the secret key.
+            let ct3 = &ct * &ct;
+            assert_eq!(sk.try_decrypt_verified(&ct3)?, sk.try_decrypt(&ct3)?);
+        }
+        Ok(())
+    }
+
+    #[cfg(feature = "async")]
+    #[test]
+    fn async_adapter() -> Result<(), Box<dyn Error>> {
+        use fhe_traits::{AsyncAdapter, AsyncFheDecrypter, AsyncFheEncrypter};
+        use std::{
+            future::Future,
+            sync::Arc,
+            task::{Context, Poll, Wake, Waker},
+        };
+
+        struct NoopWaker;
+        impl Wake for NoopWaker {
+            fn wake(self: Arc<Self>) {}
+        }
+        fn poll_ready<T>(mut future: impl Future<Output = T> + Unpin) -> T {
+            let waker = Waker::from(Arc::new(NoopWaker));
+            match std::pin::Pin::new(&mut future).poll(&mut Context::from_waker(&waker)) {
+                Poll::Ready(value) => value,
+                Poll::Pending => panic!("The adapter should return ready futures"),
+            }
+        }
+
+        let mut rng = thread_rng();
+        let params = BfvParameters::default_arc(1, 16);
+        let sk = AsyncAdapter::new(SecretKey::random(&params, &mut rng));
+        let pt = Plaintext::try_encode(
+            &params.plaintext.random_vec(params.degree(), &mut rng),
+            Encoding::poly(),
+            &params,
+        )?;
+        let ct: crate::bfv::Ciphertext = poll_ready(sk.try_encrypt_async(&pt))?;
+        let pt2: Plaintext = poll_ready(sk.try_decrypt_async(&ct))?;
+        assert_eq!(pt2, pt);
+        assert_eq!(sk.inner().try_decrypt(&ct)?, pt);
+        Ok(())
+    }
+
+    #[test]
+    fn serialize() -> Result<(), Box<dyn Error>> {
+        let mut rng = thread_rng();
+        for params in [
+            BfvParameters::default_arc(1, 16),
+            BfvParameters::default_arc(6, 16),
+        ] {
+            let sk = SecretKey::random(&params, &mut rng);
+            let bytes = sk.to_bytes();
+            assert_eq!(SecretKey::from_bytes(&bytes, &params)?, sk);
+
+            assert!(SecretKey::from_bytes(&bytes[..bytes.len() / 2], &params).is_err());
+            let other = BfvParameters::default_arc(2, 16);
+            assert!(matches!(
+                SecretKey::from_bytes(&bytes, &other),
+                Err(crate::Error::ParameterMismatch { .. })
+            ));
+        }
+        Ok(())
+    }
 }
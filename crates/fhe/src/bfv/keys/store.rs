@@ -0,0 +1,471 @@
+//! Storage of the keys, indexed by the fingerprint of their parameters, the
+//! identifier of the secret key they belong to, and their type.
+
+use crate::bfv::{BfvParameters, EvaluationKey, PublicKey, RelinearizationKey, SecretKey};
+use crate::{Error, Result};
+use fhe_traits::{DeserializeParametrized, Serialize};
+use std::{
+    collections::BTreeMap,
+    fmt::Display,
+    fs,
+    io::Write,
+    path::{Path, PathBuf},
+    str::FromStr,
+    sync::Arc,
+};
+use zeroize::Zeroizing;
+
+/// The type of a stored key.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum KeyType {
+    /// A [`SecretKey`].
+    Secret,
+    /// A [`PublicKey`].
+    Public,
+    /// A [`RelinearizationKey`].
+    Relinearization,
+    /// An [`EvaluationKey`].
+    Evaluation,
+}
+
+impl KeyType {
+    /// All the key types.
+    pub const ALL: [KeyType; 4] = [
+        KeyType::Secret,
+        KeyType::Public,
+        KeyType::Relinearization,
+        KeyType::Evaluation,
+    ];
+
+    const fn name(&self) -> &'static str {
+        match self {
+            KeyType::Secret => "secret_key",
+            KeyType::Public => "public_key",
+            KeyType::Relinearization => "relinearization_key",
+            KeyType::Evaluation => "evaluation_key",
+        }
+    }
+}
+
+impl Display for KeyType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for KeyType {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|key_type| key_type.name() == s)
+            .ok_or_else(|| Error::KeyStoreError(format!("Unknown key type {s}")))
+    }
+}
+
+/// A key which can be stored in a [`KeyStore`], in the serialization format
+/// of the library.
+pub trait StorableKey: Sized {
+    /// The type of the key.
+    const KEY_TYPE: KeyType;
+
+    /// Returns the fingerprint of the parameters of the key.
+    fn fingerprint(&self) -> u64;
+
+    /// Returns the identifier of the secret key the key belongs to, as
+    /// returned by [`SecretKey::key_id`], when the key records it.
+    fn key_id(&self) -> Option<u64> {
+        None
+    }
+
+    /// Serializes the key.
+    fn to_stored_bytes(&self) -> Zeroizing<Vec<u8>>;
+
+    /// Deserializes a key for the parameters `par`.
+    fn from_stored_bytes(bytes: &[u8], par: &Arc<BfvParameters>) -> Result<Self>;
+}
+
+impl StorableKey for SecretKey {
+    const KEY_TYPE: KeyType = KeyType::Secret;
+
+    fn fingerprint(&self) -> u64 {
+        self.par.fingerprint()
+    }
+
+    fn key_id(&self) -> Option<u64> {
+        Some(SecretKey::key_id(self))
+    }
+
+    fn to_stored_bytes(&self) -> Zeroizing<Vec<u8>> {
+        self.to_bytes()
+    }
+
+    fn from_stored_bytes(bytes: &[u8], par: &Arc<BfvParameters>) -> Result<Self> {
+        Self::from_bytes(bytes, par)
+    }
+}
+
+impl StorableKey for PublicKey {
+    const KEY_TYPE: KeyType = KeyType::Public;
+
+    fn fingerprint(&self) -> u64 {
+        self.par.fingerprint()
+    }
+
+    fn key_id(&self) -> Option<u64> {
+        self.c.key_id
+    }
+
+    fn to_stored_bytes(&self) -> Zeroizing<Vec<u8>> {
+        Zeroizing::new(self.to_bytes())
+    }
+
+    fn from_stored_bytes(bytes: &[u8], par: &Arc<BfvParameters>) -> Result<Self> {
+        Self::from_bytes(bytes, par)
+    }
+}
+
+impl StorableKey for RelinearizationKey {
+    const KEY_TYPE: KeyType = KeyType::Relinearization;
+
+    fn fingerprint(&self) -> u64 {
+        self.ksk.par.fingerprint()
+    }
+
+    fn to_stored_bytes(&self) -> Zeroizing<Vec<u8>> {
+        Zeroizing::new(self.to_bytes())
+    }
+
+    fn from_stored_bytes(bytes: &[u8], par: &Arc<BfvParameters>) -> Result<Self> {
+        Self::from_bytes(bytes, par)
+    }
+}
+
+impl StorableKey for EvaluationKey {
+    const KEY_TYPE: KeyType = KeyType::Evaluation;
+
+    fn fingerprint(&self) -> u64 {
+        self.par.fingerprint()
+    }
+
+    fn to_stored_bytes(&self) -> Zeroizing<Vec<u8>> {
+        Zeroizing::new(self.to_bytes())
+    }
+
+    fn from_stored_bytes(bytes: &[u8], par: &Arc<BfvParameters>) -> Result<Self> {
+        Self::from_bytes(bytes, par)
+    }
+}
+
+/// A store of serialized keys, indexed by the fingerprint of their parameters,
+/// the identifier of the secret key they belong to, and their type, so that
+/// the keys of several clients sharing the same parameters are kept apart.
+///
+/// The identifier of a client is the [`SecretKey::key_id`] of its secret key,
+/// which is also the [`Ciphertext::key_id`](crate::bfv::Ciphertext::key_id) of
+/// its ciphertexts.
+pub trait KeyStore {
+    /// Stores the serialized key `bytes`, replacing any key with the same
+    /// fingerprint, key identifier and type.
+    fn put(&mut self, fingerprint: u64, key_id: u64, key_type: KeyType, bytes: &[u8])
+        -> Result<()>;
+
+    /// Returns the serialized key with the given fingerprint, key identifier
+    /// and type, if any.
+    fn get(
+        &self,
+        fingerprint: u64,
+        key_id: u64,
+        key_type: KeyType,
+    ) -> Result<Option<Zeroizing<Vec<u8>>>>;
+
+    /// Lists the fingerprints, key identifiers and types of the stored keys,
+    /// in increasing order.
+    fn list(&self) -> Result<Vec<(u64, u64, KeyType)>>;
+
+    /// Stores a key belonging to the secret key of identifier `key_id`.
+    ///
+    /// Returns an error if the key records the identifier of another secret
+    /// key.
+    fn put_key<K: StorableKey>(&mut self, key_id: u64, key: &K) -> Result<()>
+    where
+        Self: Sized,
+    {
+        if key.key_id().is_some_and(|id| id != key_id) {
+            return Err(Error::KeyStoreError(format!(
+                "The {} does not belong to the secret key {key_id:016x}",
+                K::KEY_TYPE
+            )));
+        }
+        self.put(
+            key.fingerprint(),
+            key_id,
+            K::KEY_TYPE,
+            &key.to_stored_bytes(),
+        )
+    }
+
+    /// Returns the key of type `K` for the parameters `par` belonging to the
+    /// secret key of identifier `key_id`, if any.
+    fn get_key<K: StorableKey>(&self, key_id: u64, par: &Arc<BfvParameters>) -> Result<Option<K>>
+    where
+        Self: Sized,
+    {
+        self.get(par.fingerprint(), key_id, K::KEY_TYPE)?
+            .map(|bytes| K::from_stored_bytes(&bytes, par))
+            .transpose()
+    }
+}
+
+/// A [`KeyStore`] holding the keys in memory, which are wiped when they are
+/// replaced or when the store is dropped.
+#[derive(Debug, Default)]
+pub struct MemoryKeyStore {
+    keys: BTreeMap<(u64, u64, KeyType), Zeroizing<Vec<u8>>>,
+}
+
+impl MemoryKeyStore {
+    /// Creates an empty store.
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl KeyStore for MemoryKeyStore {
+    fn put(
+        &mut self,
+        fingerprint: u64,
+        key_id: u64,
+        key_type: KeyType,
+        bytes: &[u8],
+    ) -> Result<()> {
+        self.keys.insert(
+            (fingerprint, key_id, key_type),
+            Zeroizing::new(bytes.to_vec()),
+        );
+        Ok(())
+    }
+
+    fn get(
+        &self,
+        fingerprint: u64,
+        key_id: u64,
+        key_type: KeyType,
+    ) -> Result<Option<Zeroizing<Vec<u8>>>> {
+        Ok(self.keys.get(&(fingerprint, key_id, key_type)).cloned())
+    }
+
+    fn list(&self) -> Result<Vec<(u64, u64, KeyType)>> {
+        Ok(self.keys.keys().copied().collect())
+    }
+}
+
+/// A [`KeyStore`] storing the keys in a directory, with one subdirectory per
+/// fingerprint containing one subdirectory per key identifier, both in
+/// hexadecimal, which contains one file per key type, e.g.
+/// `0123456789abcdef/fedcba9876543210/public_key.bin`.
+///
+/// The keys are written to a temporary file which is then renamed, so that a
+/// key is never partially written. On Unix, the secret keys are only readable
+/// by their owner.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileKeyStore {
+    root: PathBuf,
+}
+
+impl FileKeyStore {
+    /// Opens the store in the directory `root`, which is created if needed.
+    pub fn new<P: AsRef<Path>>(root: P) -> Result<Self> {
+        let root = root.as_ref().to_path_buf();
+        fs::create_dir_all(&root).map_err(|e| io_error(&root, e))?;
+        Ok(Self { root })
+    }
+
+    /// Returns the directory of the store.
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    fn path(&self, fingerprint: u64, key_id: u64, key_type: KeyType) -> PathBuf {
+        self.root
+            .join(format!("{fingerprint:016x}"))
+            .join(format!("{key_id:016x}"))
+            .join(format!("{key_type}.bin"))
+    }
+
+    /// Returns the subdirectories of `dir` named by an integer in hexadecimal.
+    fn hex_subdirectories(dir: &Path) -> Result<Vec<(u64, PathBuf)>> {
+        let mut subdirectories = vec![];
+        for entry in fs::read_dir(dir).map_err(|e| io_error(dir, e))? {
+            let entry = entry.map_err(|e| io_error(dir, e))?;
+            if let Some(value) = entry
+                .file_name()
+                .to_str()
+                .and_then(|name| u64::from_str_radix(name, 16).ok())
+                .filter(|_| entry.path().is_dir())
+            {
+                subdirectories.push((value, entry.path()))
+            }
+        }
+        Ok(subdirectories)
+    }
+}
+
+fn io_error(path: &Path, e: std::io::Error) -> Error {
+    Error::KeyStoreError(format!("{}: {e}", path.display()))
+}
+
+impl KeyStore for FileKeyStore {
+    fn put(
+        &mut self,
+        fingerprint: u64,
+        key_id: u64,
+        key_type: KeyType,
+        bytes: &[u8],
+    ) -> Result<()> {
+        let path = self.path(fingerprint, key_id, key_type);
+        let dir = path.parent().unwrap();
+        fs::create_dir_all(dir).map_err(|e| io_error(dir, e))?;
+
+        let tmp = path.with_extension("tmp");
+        let mut options = fs::OpenOptions::new();
+        options.write(true).create(true).truncate(true);
+        #[cfg(unix)]
+        if key_type == KeyType::Secret {
+            use std::os::unix::fs::OpenOptionsExt;
+            options.mode(0o600);
+        }
+        let mut file = options.open(&tmp).map_err(|e| io_error(&tmp, e))?;
+        file.write_all(bytes)
+            .and_then(|_| file.sync_all())
+            .map_err(|e| io_error(&tmp, e))?;
+        fs::rename(&tmp, &path).map_err(|e| io_error(&path, e))
+    }
+
+    fn get(
+        &self,
+        fingerprint: u64,
+        key_id: u64,
+        key_type: KeyType,
+    ) -> Result<Option<Zeroizing<Vec<u8>>>> {
+        let path = self.path(fingerprint, key_id, key_type);
+        match fs::read(&path) {
+            Ok(bytes) => Ok(Some(Zeroizing::new(bytes))),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(io_error(&path, e)),
+        }
+    }
+
+    fn list(&self) -> Result<Vec<(u64, u64, KeyType)>> {
+        let mut keys = vec![];
+        for (fingerprint, dir) in Self::hex_subdirectories(&self.root)? {
+            for (key_id, _) in Self::hex_subdirectories(&dir)? {
+                for key_type in KeyType::ALL {
+                    if self.path(fingerprint, key_id, key_type).is_file() {
+                        keys.push((fingerprint, key_id, key_type))
+                    }
+                }
+            }
+        }
+        keys.sort();
+        Ok(keys)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{FileKeyStore, KeyStore, KeyType, MemoryKeyStore};
+    use crate::bfv::{
+        BfvParameters, EvaluationKey, EvaluationKeyBuilder, PublicKey, RelinearizationKey,
+        SecretKey,
+    };
+    use rand::{thread_rng, RngCore};
+    use std::error::Error;
+
+    fn put_and_get<S: KeyStore>(store: &mut S) -> Result<(), Box<dyn Error>> {
+        let mut rng = thread_rng();
+        let par = BfvParameters::default_arc(2, 16);
+        let other = BfvParameters::default_arc(3, 16);
+        let sk = SecretKey::random(&par, &mut rng);
+        let pk = PublicKey::new(&sk, &mut rng);
+        let rk = RelinearizationKey::new(&sk, &mut rng)?;
+        let ek = EvaluationKeyBuilder::new(&sk)?
+            .enable_row_rotation()?
+            .build(&mut rng)?;
+        let id = sk.key_id();
+
+        assert!(store.list()?.is_empty());
+        assert!(store.get_key::<SecretKey>(id, &par)?.is_none());
+
+        store.put_key(id, &sk)?;
+        store.put_key(id, &pk)?;
+        store.put_key(id, &rk)?;
+        store.put_key(id, &ek)?;
+        let other_sk = SecretKey::random(&other, &mut rng);
+        store.put_key(other_sk.key_id(), &PublicKey::new(&other_sk, &mut rng))?;
+
+        assert_eq!(store.get_key::<SecretKey>(id, &par)?, Some(sk.clone()));
+        assert_eq!(store.get_key::<PublicKey>(id, &par)?, Some(pk.clone()));
+        assert_eq!(store.get_key::<RelinearizationKey>(id, &par)?, Some(rk));
+        assert_eq!(store.get_key::<EvaluationKey>(id, &par)?, Some(ek));
+        assert!(store.get_key::<SecretKey>(id, &other)?.is_none());
+
+        // The keys of another client with the same parameters are kept apart.
+        let sk2 = SecretKey::random(&par, &mut rng);
+        let pk2 = PublicKey::new(&sk2, &mut rng);
+        store.put_key(sk2.key_id(), &pk2)?;
+        assert_eq!(store.get_key::<PublicKey>(id, &par)?, Some(pk.clone()));
+        assert_eq!(store.get_key::<PublicKey>(sk2.key_id(), &par)?, Some(pk2));
+        assert!(store.get_key::<SecretKey>(sk2.key_id(), &par)?.is_none());
+
+        // The keys recording the identifier of another secret key are rejected.
+        assert!(store.put_key(sk2.key_id(), &pk).is_err());
+        assert!(store.put_key(sk2.key_id(), &sk).is_err());
+
+        let mut expected = vec![
+            (par.fingerprint(), id, KeyType::Secret),
+            (par.fingerprint(), id, KeyType::Public),
+            (par.fingerprint(), id, KeyType::Relinearization),
+            (par.fingerprint(), id, KeyType::Evaluation),
+            (par.fingerprint(), sk2.key_id(), KeyType::Public),
+            (other.fingerprint(), other_sk.key_id(), KeyType::Public),
+        ];
+        expected.sort();
+        assert_eq!(store.list()?, expected);
+
+        // Keys are replaced.
+        let pk3 = PublicKey::new(&sk, &mut rng);
+        store.put_key(id, &pk3)?;
+        assert_eq!(store.get_key::<PublicKey>(id, &par)?, Some(pk3));
+        assert_eq!(store.list()?, expected);
+
+        // Corrupted keys are rejected.
+        store.put(par.fingerprint(), id, KeyType::Public, &[1, 2, 3])?;
+        assert!(store.get_key::<PublicKey>(id, &par).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn memory() -> Result<(), Box<dyn Error>> {
+        put_and_get(&mut MemoryKeyStore::new())
+    }
+
+    #[test]
+    fn file() -> Result<(), Box<dyn Error>> {
+        let root =
+            std::env::temp_dir().join(format!("fhe-key-store-{:016x}", thread_rng().next_u64()));
+        let mut store = FileKeyStore::new(&root)?;
+        let result = put_and_get(&mut store);
+        std::fs::remove_dir_all(&root)?;
+        result
+    }
+
+    #[test]
+    fn key_type() {
+        for key_type in KeyType::ALL {
+            assert_eq!(key_type.to_string().parse::<KeyType>(), Ok(key_type));
+        }
+        assert!("unknown".parse::<KeyType>().is_err());
+    }
+}
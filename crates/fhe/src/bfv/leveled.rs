@@ -0,0 +1,254 @@
+//! Compile-time tracking of the levels of ciphertexts and plaintexts.
+//!
+//! [`Leveled<T, L>`] wraps a ciphertext or a plaintext whose level `L` is part
+//! of its type. The level is checked once when the value is wrapped, and the
+//! operations on wrapped values only accept operands at the same level and
+//! return values at the level determined by their types, so that circuits with
+//! a static structure cannot mix levels:
+//!
+//! ```compile_fail
+//! # use fhe::bfv::{leveled::Leveled, Ciphertext};
+//! fn add(a: &Leveled<Ciphertext, 0>, b: &Leveled<Ciphertext, 1>) -> Leveled<Ciphertext, 0> {
+//!     a + b
+//! }
+//! ```
+//!
+//! Switching to a lower level than the current one is also rejected at
+//! compile time, since the levels only increase along a circuit.
+
+use std::ops::{Add, Mul, Neg, Sub};
+
+use crate::bfv::{Ciphertext, Multiplicator, Plaintext};
+use crate::{Error, Result};
+
+/// A value of type `T` at the level `L`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Leveled<T, const L: usize> {
+    inner: T,
+}
+
+/// Compile-time assertion that the level `M` is at least the level `L`.
+struct LevelOrder<const L: usize, const M: usize>;
+
+impl<const L: usize, const M: usize> LevelOrder<L, M> {
+    const CHECK: () = assert!(L <= M, "Cannot switch to a lower level");
+}
+
+impl<T, const L: usize> Leveled<T, L> {
+    /// Returns the level of the value.
+    pub const fn level(&self) -> usize {
+        L
+    }
+
+    /// Returns a reference to the underlying value.
+    pub const fn inner(&self) -> &T {
+        &self.inner
+    }
+
+    /// Returns the underlying value.
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+}
+
+impl<const L: usize> Leveled<Ciphertext, L> {
+    /// Wraps the ciphertext `ct`, which must be at level `L`.
+    pub fn new(ct: Ciphertext) -> Result<Self> {
+        if ct.level() != L {
+            return Err(Error::LevelMismatch {
+                expected: L,
+                found: ct.level(),
+            });
+        }
+        Ok(Self { inner: ct })
+    }
+
+    /// Modulo switch the ciphertext down to the level `M`, which must be at
+    /// least `L` and at most the maximum level of the parameters.
+    pub fn mod_switch_to<const M: usize>(self) -> Result<Leveled<Ciphertext, M>> {
+        let () = LevelOrder::<L, M>::CHECK;
+        Ok(Leveled {
+            inner: mod_switch_down_to(self.inner, M)?,
+        })
+    }
+
+    /// Multiply the ciphertext by `rhs` with the `multiplicator`, and modulo
+    /// switch the product down to the level `M`.
+    ///
+    /// The multiplicator must operate at level `L`; the level `M` must be at
+    /// least the level of its products, i.e., `L + 1` when it switches moduli
+    /// after the multiplication and `L` otherwise.
+    pub fn multiply<const M: usize>(
+        &self,
+        rhs: &Self,
+        multiplicator: &Multiplicator,
+    ) -> Result<Leveled<Ciphertext, M>> {
+        let () = LevelOrder::<L, M>::CHECK;
+        let product = multiplicator.multiply(&self.inner, &rhs.inner)?;
+        if product.level() > M {
+            return Err(Error::LevelMismatch {
+                expected: M,
+                found: product.level(),
+            });
+        }
+        Ok(Leveled {
+            inner: mod_switch_down_to(product, M)?,
+        })
+    }
+}
+
+/// Modulo switch the ciphertext `ct` down to the level `level`, which must be
+/// at least its level.
+fn mod_switch_down_to(mut ct: Ciphertext, level: usize) -> Result<Ciphertext> {
+    let max_level = ct.par.max_level();
+    if level > max_level {
+        return Err(Error::InvalidLevel { level, max_level });
+    }
+    while ct.level() < level {
+        ct.mod_switch_to_next_level()?
+    }
+    Ok(ct)
+}
+
+impl<const L: usize> Leveled<Plaintext, L> {
+    /// Wraps the plaintext `pt`, which must be at level `L`.
+    pub fn new(pt: Plaintext) -> Result<Self> {
+        if pt.level() != L {
+            return Err(Error::LevelMismatch {
+                expected: L,
+                found: pt.level(),
+            });
+        }
+        Ok(Self { inner: pt })
+    }
+}
+
+impl<const L: usize> Add<&Leveled<Ciphertext, L>> for &Leveled<Ciphertext, L> {
+    type Output = Leveled<Ciphertext, L>;
+
+    fn add(self, rhs: &Leveled<Ciphertext, L>) -> Leveled<Ciphertext, L> {
+        Leveled {
+            inner: &self.inner + &rhs.inner,
+        }
+    }
+}
+
+impl<const L: usize> Add<&Leveled<Plaintext, L>> for &Leveled<Ciphertext, L> {
+    type Output = Leveled<Ciphertext, L>;
+
+    fn add(self, rhs: &Leveled<Plaintext, L>) -> Leveled<Ciphertext, L> {
+        Leveled {
+            inner: &self.inner + &rhs.inner,
+        }
+    }
+}
+
+impl<const L: usize> Sub<&Leveled<Ciphertext, L>> for &Leveled<Ciphertext, L> {
+    type Output = Leveled<Ciphertext, L>;
+
+    fn sub(self, rhs: &Leveled<Ciphertext, L>) -> Leveled<Ciphertext, L> {
+        Leveled {
+            inner: &self.inner - &rhs.inner,
+        }
+    }
+}
+
+impl<const L: usize> Sub<&Leveled<Plaintext, L>> for &Leveled<Ciphertext, L> {
+    type Output = Leveled<Ciphertext, L>;
+
+    fn sub(self, rhs: &Leveled<Plaintext, L>) -> Leveled<Ciphertext, L> {
+        Leveled {
+            inner: &self.inner - &rhs.inner,
+        }
+    }
+}
+
+impl<const L: usize> Mul<&Leveled<Plaintext, L>> for &Leveled<Ciphertext, L> {
+    type Output = Leveled<Ciphertext, L>;
+
+    fn mul(self, rhs: &Leveled<Plaintext, L>) -> Leveled<Ciphertext, L> {
+        Leveled {
+            inner: &self.inner * &rhs.inner,
+        }
+    }
+}
+
+impl<const L: usize> Neg for &Leveled<Ciphertext, L> {
+    type Output = Leveled<Ciphertext, L>;
+
+    fn neg(self) -> Leveled<Ciphertext, L> {
+        Leveled {
+            inner: -&self.inner,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Leveled;
+    use crate::bfv::{
+        BfvParameters, Ciphertext, Encoding, Multiplicator, Plaintext, RelinearizationKey,
+        SecretKey,
+    };
+    use fhe_traits::{FheDecoder, FheDecrypter, FheEncoder, FheEncrypter};
+    use rand::thread_rng;
+    use std::error::Error;
+
+    #[test]
+    fn leveled() -> Result<(), Box<dyn Error>> {
+        let mut rng = thread_rng();
+        let par = BfvParameters::default_arc(4, 16);
+        let sk = SecretKey::random(&par, &mut rng);
+        let rk = RelinearizationKey::new(&sk, &mut rng)?;
+        let mut multiplicator = Multiplicator::default(&rk)?;
+        multiplicator.enable_mod_switching()?;
+        let t = &par.plaintext;
+
+        let a = par.plaintext.random_vec(par.degree(), &mut rng);
+        let b = par.plaintext.random_vec(par.degree(), &mut rng);
+        let encrypt = |v: &[u64]| -> Result<Leveled<Ciphertext, 0>, Box<dyn Error>> {
+            let pt = Plaintext::try_encode(v, Encoding::simd(), &par)?;
+            Ok(Leveled::<Ciphertext, 0>::new(
+                sk.try_encrypt(&pt, &mut thread_rng())?,
+            )?)
+        };
+        let ct_a = encrypt(&a)?;
+        let ct_b = encrypt(&b)?;
+
+        // (a + b) * (a - b) * b at level 2.
+        let sum = &ct_a + &ct_b;
+        let difference = &ct_a - &ct_b;
+        let product: Leveled<Ciphertext, 1> = sum.multiply(&difference, &multiplicator)?;
+        assert_eq!(product.level(), 1);
+        let pt_b = Leveled::<Plaintext, 2>::new(Plaintext::try_encode(
+            &b,
+            Encoding::simd_at_level(2),
+            &par,
+        )?)?;
+        let result = &product.mod_switch_to::<2>()? * &pt_b;
+        let result = -&result;
+        assert_eq!(result.inner().level(), 2);
+
+        let expected = a
+            .iter()
+            .zip(&b)
+            .map(|(a, b)| t.neg(t.mul(t.mul(t.add(*a, *b), t.sub(*a, *b)), *b)))
+            .collect::<Vec<_>>();
+        let pt = sk.try_decrypt(result.inner())?;
+        assert_eq!(
+            Vec::<u64>::try_decode(&pt, Encoding::simd_at_level(2))?,
+            expected
+        );
+
+        // The product can be switched further down, but not kept at level 0.
+        let squared: Leveled<Ciphertext, 3> = ct_a.multiply(&ct_a, &multiplicator)?;
+        assert_eq!(squared.into_inner().level(), 3);
+        assert!(ct_a.multiply::<0>(&ct_a, &multiplicator).is_err());
+
+        // The levels are checked when wrapping the values.
+        assert!(Leveled::<Ciphertext, 1>::new(ct_b.into_inner()).is_err());
+        assert!(Leveled::<Plaintext, 0>::new(pt_b.into_inner()).is_err());
+        assert!(ct_a.mod_switch_to::<4>().is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,281 @@
+//! Homomorphic linear transforms of the slots of ciphertexts.
+//!
+//! In the SIMD encoding, the slots form a matrix of 2 rows, and the evaluation
+//! key rotates the columns and swaps the rows. A linear map of the slots is
+//! decomposed into generalized diagonals: the diagonal of index `k` multiplies
+//! the ciphertext and the ciphertext with swapped rows, whose columns are
+//! rotated by `k`. The diagonals are evaluated with the baby-step giant-step
+//! algorithm, where the rotations of the baby steps are hoisted.
+
+use std::sync::Arc;
+
+use fhe_traits::FheEncoder;
+use itertools::Itertools;
+
+use crate::bfv::{
+    dot_product_scalar, BfvParameters, Ciphertext, Encoding, EvaluationKey, Plaintext,
+};
+use crate::{Error, Result};
+
+/// A product of a plaintext with a baby step of a ciphertext.
+struct Term {
+    baby_step: usize,
+    swapped: bool,
+    pt: Plaintext,
+}
+
+/// A matrix acting on the slots of ciphertexts, encoded for the baby-step
+/// giant-step evaluation of [`apply_linear_transform`].
+pub struct PlaintextMatrix {
+    par: Arc<BfvParameters>,
+    level: usize,
+    baby_steps: usize,
+    giant_steps: Vec<Vec<Term>>,
+}
+
+impl PlaintextMatrix {
+    /// Encodes the matrix whose rows are `rows`, reduced modulo the plaintext
+    /// modulus, for ciphertexts at level `level`. The slot `i` of the result
+    /// of [`apply_linear_transform`] is the sum of `rows[i][j]` times the
+    /// slot `j` of the input.
+    ///
+    /// Returns an error if the matrix is not a square matrix of dimension the
+    /// degree, or if the parameters do not support the SIMD encoding.
+    pub fn new(rows: &[Vec<u64>], level: usize, par: &Arc<BfvParameters>) -> Result<Self> {
+        let degree = par.degree();
+        if rows.len() != degree || rows.iter().any(|row| row.len() != degree) {
+            return Err(Error::UnspecifiedInput(format!(
+                "The matrix must have {degree} rows and columns"
+            )));
+        }
+        par.ctx_at_level(level)?;
+
+        let row_size = degree >> 1;
+        let baby_steps = 1 << row_size.ilog2().div_ceil(2);
+        let giant_steps = (0..row_size.div_ceil(baby_steps))
+            .map(|g| {
+                let mut terms = vec![];
+                for baby_step in 0..baby_steps.min(row_size - g * baby_steps) {
+                    let k = g * baby_steps + baby_step;
+                    for swapped in [false, true] {
+                        // Slot (r, c) of the result depends on the slot
+                        // (r, c + k) of the input, or (1 - r, c + k) with the
+                        // rows swapped. The diagonal is rotated by -g * b to
+                        // apply the giant step after the product.
+                        let diagonal = (0..degree)
+                            .map(|i| {
+                                let (r, c) = (i / row_size, i % row_size);
+                                let c = (c + row_size - g * baby_steps % row_size) % row_size;
+                                let r_in = if swapped { 1 - r } else { r };
+                                let j = r_in * row_size + (c + k) % row_size;
+                                par.plaintext.reduce(rows[r * row_size + c][j])
+                            })
+                            .collect_vec();
+                        if diagonal.iter().any(|d| *d != 0) {
+                            let encoding = Encoding::simd_at_level(level);
+                            terms.push(Term {
+                                baby_step,
+                                swapped,
+                                pt: Plaintext::try_encode(&diagonal, encoding, par)?,
+                            });
+                        }
+                    }
+                }
+                Ok(terms)
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(Self {
+            par: par.clone(),
+            level,
+            baby_steps,
+            giant_steps,
+        })
+    }
+
+    /// Returns the level of the ciphertexts this matrix applies to.
+    pub fn level(&self) -> usize {
+        self.level
+    }
+
+    /// Returns the indices of the column rotations that the evaluation key
+    /// must enable to apply this matrix.
+    pub fn column_rotations(&self) -> Vec<usize> {
+        let baby_steps = self
+            .giant_steps
+            .iter()
+            .flatten()
+            .map(|term| term.baby_step)
+            .filter(|j| *j > 0);
+        let giant_steps = self
+            .giant_steps
+            .iter()
+            .enumerate()
+            .filter(|(g, terms)| *g > 0 && !terms.is_empty())
+            .map(|(g, _)| g * self.baby_steps);
+        baby_steps.chain(giant_steps).sorted().dedup().collect()
+    }
+
+    /// Returns whether the evaluation key must enable the row rotation to
+    /// apply this matrix.
+    pub fn row_rotation(&self) -> bool {
+        self.giant_steps.iter().flatten().any(|term| term.swapped)
+    }
+}
+
+/// Multiplies the slots of the ciphertext `ct` by the matrix `matrix`, with
+/// the evaluation key `ek` enabling the rotations listed by
+/// [`PlaintextMatrix::column_rotations`] and [`PlaintextMatrix::row_rotation`].
+///
+/// The baby steps rotate `ct` and its row swap by `1, ..., b - 1` with hoisted
+/// key switchings, and each non-zero giant step rotates the sum of the
+/// products of the baby steps with the diagonals by a multiple of `b`, where
+/// `b` is about the square root of half the degree.
+pub fn apply_linear_transform(
+    ct: &Ciphertext,
+    matrix: &PlaintextMatrix,
+    ek: &EvaluationKey,
+) -> Result<Ciphertext> {
+    matrix.par.check_fingerprint(&ct.par)?;
+    if ct.level != matrix.level {
+        return Err(Error::LevelMismatch {
+            expected: matrix.level,
+            found: ct.level,
+        });
+    }
+
+    let indices = matrix
+        .giant_steps
+        .iter()
+        .flatten()
+        .map(|term| term.baby_step)
+        .filter(|j| *j > 0)
+        .sorted()
+        .dedup()
+        .collect_vec();
+    let baby_steps = |ct: &Ciphertext| -> Result<Vec<Option<Ciphertext>>> {
+        let mut out = vec![None; matrix.baby_steps];
+        out[0] = Some(ct.clone());
+        for (j, ct_j) in indices
+            .iter()
+            .zip(ek.rotates_columns_by_many(ct, &indices)?)
+        {
+            out[*j] = Some(ct_j)
+        }
+        Ok(out)
+    };
+    let rotated = baby_steps(ct)?;
+    let rotated_swapped = if matrix.row_rotation() {
+        baby_steps(&ek.rotates_rows(ct)?)?
+    } else {
+        vec![]
+    };
+
+    let mut out: Option<Ciphertext> = None;
+    for (g, terms) in matrix.giant_steps.iter().enumerate() {
+        if terms.is_empty() {
+            continue;
+        }
+        let cts = terms.iter().map(|term| {
+            let rotated = if term.swapped {
+                &rotated_swapped
+            } else {
+                &rotated
+            };
+            rotated[term.baby_step].as_ref().unwrap()
+        });
+        let mut sum = dot_product_scalar(cts, terms.iter().map(|term| &term.pt))?;
+        if g > 0 {
+            sum = ek.rotates_columns_by(&sum, g * matrix.baby_steps)?;
+        }
+        out = Some(match out {
+            Some(out) => &out + &sum,
+            None => sum,
+        });
+    }
+    match out {
+        Some(out) => Ok(out),
+        None => Ok(ct * &Plaintext::zero(Encoding::simd_at_level(ct.level), &ct.par)?),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{apply_linear_transform, PlaintextMatrix};
+    use crate::bfv::{BfvParameters, Encoding, EvaluationKeyBuilder, Plaintext, SecretKey};
+    use fhe_traits::{FheDecoder, FheDecrypter, FheEncoder, FheEncrypter};
+    use rand::{thread_rng, Rng};
+    use std::error::Error;
+
+    #[test]
+    fn linear_transform() -> Result<(), Box<dyn Error>> {
+        let mut rng = thread_rng();
+        for par in [
+            BfvParameters::default_arc(2, 8),
+            BfvParameters::default_arc(2, 16),
+            BfvParameters::default_arc(3, 64),
+        ] {
+            let degree = par.degree();
+            let t = par.plaintext();
+            let sk = SecretKey::random(&par, &mut rng);
+            let v = par.plaintext.random_vec(degree, &mut rng);
+
+            // A random matrix, a permutation matrix and the zero matrix. The
+            // key switching noise exhausts a single 62-bit modulus, so the
+            // matrices are applied at levels keeping at least two moduli.
+            let random = (0..degree)
+                .map(|_| par.plaintext.random_vec(degree, &mut rng))
+                .collect::<Vec<_>>();
+            let shift = rng.gen_range(0..degree);
+            let permutation = (0..degree)
+                .map(|i| {
+                    (0..degree)
+                        .map(|j| ((i + shift) % degree == j) as u64)
+                        .collect()
+                })
+                .collect::<Vec<Vec<u64>>>();
+            let zero = vec![vec![0u64; degree]; degree];
+
+            let level = par.max_level() - 1;
+            for (rows, level) in [(random, 0), (permutation, level), (zero, level)] {
+                let matrix = PlaintextMatrix::new(&rows, level, &par)?;
+                let mut builder = EvaluationKeyBuilder::new_leveled(&sk, level, level)?;
+                if matrix.row_rotation() {
+                    builder.enable_row_rotation()?;
+                }
+                for i in matrix.column_rotations() {
+                    builder.enable_column_rotation(i)?;
+                }
+                let ek = builder.build(&mut rng)?;
+
+                let encoding = Encoding::simd_at_level(level);
+                let pt = Plaintext::try_encode(&v, encoding.clone(), &par)?;
+                let ct = sk.try_encrypt(&pt, &mut rng)?;
+                let ct2 = apply_linear_transform(&ct, &matrix, &ek)?;
+                let expected = rows
+                    .iter()
+                    .map(|row| {
+                        row.iter().zip(&v).fold(0u128, |acc, (a, b)| {
+                            (acc + *a as u128 * *b as u128) % t as u128
+                        }) as u64
+                    })
+                    .collect::<Vec<_>>();
+                // Check the noise first, so that a noise failure shows up as such.
+                let q_bits = par.moduli_sizes()[..par.moduli().len() - level]
+                    .iter()
+                    .sum::<usize>();
+                let t_bits = 64 - t.leading_zeros() as usize;
+                let noise = unsafe { sk.measure_noise(&ct2)? };
+                println!("Noise: {noise} bits, budget: {} bits", q_bits - t_bits - 1);
+                assert!(noise < q_bits - t_bits - 1);
+                let pt2 = sk.try_decrypt(&ct2)?;
+                assert_eq!(Vec::<u64>::try_decode(&pt2, encoding)?, expected);
+            }
+        }
+
+        let par = BfvParameters::default_arc(2, 16);
+        assert!(PlaintextMatrix::new(&[vec![0u64; 16]], 0, &par).is_err());
+        assert!(PlaintextMatrix::new(&vec![vec![0u64; 16]; 16], 2, &par).is_err());
+        Ok(())
+    }
+}
@@ -4,20 +4,55 @@
 
 mod ciphertext;
 mod encoding;
+mod envelope;
+mod inspect;
 mod keys;
+mod linear_transform;
+mod noise;
+#[cfg(feature = "noise-oracle")]
+mod noise_oracle;
 mod ops;
 mod parameters;
 mod plaintext;
 mod plaintext_vec;
+mod profiler;
 mod rgsw_ciphertext;
+mod security;
+mod size;
+mod slot_transforms;
 
+pub mod approximation;
+pub mod crt;
+#[cfg(any(feature = "arbitrary", feature = "proptest"))]
+pub mod fuzz;
+pub mod integrity;
+pub mod kat;
+pub mod leveled;
+pub mod ole;
+pub mod prf;
+pub mod psi;
+pub mod shuffle;
+pub mod sorting;
+pub mod towers;
 pub mod traits;
+pub mod transciphering;
 pub use ciphertext::Ciphertext;
 pub use encoding::Encoding;
+pub use envelope::Envelope;
+pub use inspect::{inspect_bytes, ObjectInfo, ObjectType};
 pub(crate) use keys::KeySwitchingKey;
-pub use keys::{EvaluationKey, EvaluationKeyBuilder, PublicKey, RelinearizationKey, SecretKey};
-pub use ops::{dot_product_scalar, Multiplicator};
-pub use parameters::{BfvParameters, BfvParametersBuilder};
+pub use keys::{
+    EvaluationKey, EvaluationKeyBuilder, FileKeyStore, GaloisKeyProvider, KeyStore, KeyType,
+    LazyEvaluationKey, MemoryKeyStore, PublicKey, ReencryptionKey, RelinearizationKey,
+    RingSwitchingKey, SecretKey, SecretKeyMessage, SecretKeyOperations, StorableKey,
+};
+pub use linear_transform::{apply_linear_transform, PlaintextMatrix};
+#[cfg(feature = "noise-oracle")]
+pub use noise_oracle::{NoiseOracle, NoiseRecord};
+pub use ops::{dot_product_scalar, Multiplicator, OpScratch};
+pub use parameters::{BfvParameters, BfvParametersBuilder, MultiplicationStrategy};
 pub use plaintext::Plaintext;
 pub use plaintext_vec::PlaintextVec;
+pub use profiler::{OperationStats, ProfileReport, Profiler};
 pub use rgsw_ciphertext::RGSWCiphertext;
+pub use slot_transforms::SlotTransforms;
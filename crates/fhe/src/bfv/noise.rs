@@ -0,0 +1,218 @@
+//! Heuristic estimation of the noise in ciphertexts.
+//!
+//! The estimates are computed from the parameters and the operations performed
+//! on the ciphertexts, and do not require the secret key. They approximate the
+//! infinity norm of the noise (as measured by
+//! [`SecretKey::measure_noise`](crate::bfv::SecretKey::measure_noise)) using
+//! average-case heuristics, and are expressed in bits.
+
+use crate::bfv::{BfvParameters, KeySwitchingKey};
+
+/// Number of standard deviations used to bound a sum of random values.
+const TAIL: f64 = 6.0;
+
+/// Returns log2(2^a + 2^b).
+fn log2_add(a: f64, b: f64) -> f64 {
+    let (hi, lo) = if a >= b { (a, b) } else { (b, a) };
+    hi + (1.0 + (lo - hi).exp2()).log2()
+}
+
+/// Standard deviation of the error and secret distributions.
+fn sigma(par: &BfvParameters) -> f64 {
+    (par.variance as f64).sqrt()
+}
+
+/// Number of bits of the ciphertext modulus at a given level.
+pub(crate) fn log2_modulus(par: &BfvParameters, level: usize) -> f64 {
+    par.ctx[level]
+        .moduli()
+        .iter()
+        .map(|qi| (*qi as f64).log2())
+        .sum()
+}
+
+/// Noise capacity at a given level, i.e., the largest noise which can be
+/// decrypted correctly.
+pub(crate) fn capacity(par: &BfvParameters, level: usize) -> f64 {
+    log2_modulus(par, level) - (par.plaintext.modulus() as f64).log2() - 1.0
+}
+
+/// Noise of a fresh encryption under the secret key.
+pub(crate) fn fresh_secret_key(par: &BfvParameters) -> f64 {
+    (TAIL * sigma(par)).log2()
+}
+
+/// Noise of a fresh encryption under the public key.
+pub(crate) fn fresh_public_key(par: &BfvParameters) -> f64 {
+    let n = par.degree() as f64;
+    let sigma = sigma(par);
+    (TAIL * sigma * (1.0 + 2.0 * n.sqrt() * sigma)).log2()
+}
+
+/// Noise of the sum of two ciphertexts.
+pub(crate) fn add(a: Option<f64>, b: Option<f64>) -> Option<f64> {
+    Some(log2_add(a?, b?))
+}
+
+/// Noise after adding a plaintext, which only adds its rounding error.
+pub(crate) fn add_plaintext(a: Option<f64>) -> Option<f64> {
+    Some(log2_add(a?, 0.0))
+}
+
+/// Noise after multiplying by a scalar in [0, t).
+pub(crate) fn mul_scalar(par: &BfvParameters, a: Option<f64>, scalar: u64) -> Option<f64> {
+    let t = par.plaintext.modulus();
+    let centered = scalar.min(t - scalar).max(1);
+    Some(a? + (centered as f64).log2())
+}
+
+/// Noise after multiplying by an arbitrary plaintext.
+pub(crate) fn mul_plaintext(par: &BfvParameters, a: Option<f64>) -> Option<f64> {
+    let n = par.degree() as f64;
+    Some(a? + (n.sqrt() * par.plaintext.modulus() as f64).log2())
+}
+
+/// Noise of the (unrelinearized) product of two ciphertexts.
+pub(crate) fn mul(par: &BfvParameters, a: Option<f64>, b: Option<f64>) -> Option<f64> {
+    let n = par.degree() as f64;
+    let t = par.plaintext.modulus() as f64;
+    let sigma = sigma(par);
+    let (a, b) = (a?, b?);
+    // The noises are multiplied by the quotients of the decryption by the
+    // ciphertext modulus, which have a size comparable to the secret key.
+    let tensor = log2_add(a, b) + (t * n * sigma / 2.0).log2();
+    let rounding = (t * n * sigma * sigma).log2();
+    Some(log2_add(tensor, rounding))
+}
+
+/// Noise added by a key switching with the key switching key `ksk`.
+pub(crate) fn key_switch(ksk: &KeySwitchingKey) -> f64 {
+    let par = &ksk.par;
+    let n = par.degree() as f64;
+    let (ndigits, log2_digit) = if ksk.log_base != 0 {
+        (ksk.c0.len() as f64, ksk.log_base as f64)
+    } else {
+        let moduli = ksk.ctx_ciphertext.moduli();
+        let log2_digit = moduli
+            .iter()
+            .map(|qi| (*qi as f64).log2())
+            .fold(0.0, f64::max);
+        (moduli.len() as f64, log2_digit)
+    };
+    // Each digit is uniform, and is multiplied by an error polynomial.
+    let noise = (TAIL * (ndigits * n / 12.0).sqrt() * sigma(par)).log2() + log2_digit;
+    if ksk.ksk_level != ksk.ciphertext_level {
+        mod_switch_bits(par, ksk.ksk_level, ksk.ciphertext_level, noise)
+    } else {
+        noise
+    }
+}
+
+/// Noise after key switching.
+pub(crate) fn after_key_switch(ksk: &KeySwitchingKey, a: Option<f64>) -> Option<f64> {
+    Some(log2_add(a?, key_switch(ksk)))
+}
+
+fn mod_switch_bits(par: &BfvParameters, from: usize, to: usize, a: f64) -> f64 {
+    let n = par.degree() as f64;
+    let scaled = a - log2_modulus(par, from) + log2_modulus(par, to);
+    // Rounding error of the ciphertext coefficients.
+    let rounding = ((3.0 * n).sqrt() * sigma(par)).log2();
+    log2_add(scaled, rounding)
+}
+
+/// Noise after switching from level `from` to level `to`.
+pub(crate) fn mod_switch(
+    par: &BfvParameters,
+    from: usize,
+    to: usize,
+    a: Option<f64>,
+) -> Option<f64> {
+    if from == to {
+        a
+    } else {
+        Some(mod_switch_bits(par, from, to, a?))
+    }
+}
+
+/// Noise after switching from level `from_level` of the parameters `from` to
+/// level `to_level` of the parameters `to`.
+pub(crate) fn switch_parameters(
+    from: &BfvParameters,
+    from_level: usize,
+    to: &BfvParameters,
+    to_level: usize,
+    a: Option<f64>,
+) -> Option<f64> {
+    let log2_from = log2_modulus(from, from_level);
+    let log2_to = log2_modulus(to, to_level);
+    if log2_from == log2_to {
+        a
+    } else {
+        let n = from.degree() as f64;
+        let scaled = a? - log2_from + log2_to;
+        let rounding = ((3.0 * n).sqrt() * sigma(from)).log2();
+        Some(log2_add(scaled, rounding))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::bfv::{
+        BfvParameters, Ciphertext, Encoding, EvaluationKeyBuilder, Multiplicator, Plaintext,
+        PublicKey, RelinearizationKey, SecretKey,
+    };
+    use fhe_traits::{FheEncoder, FheEncrypter};
+    use rand::thread_rng;
+    use std::error::Error;
+
+    #[test]
+    fn estimates_follow_measured_noise() -> Result<(), Box<dyn Error>> {
+        let mut rng = thread_rng();
+        for par in [
+            BfvParameters::default_arc(3, 16),
+            BfvParameters::default_arc(6, 16),
+            BfvParameters::default_parameters_128(20)[2].clone(),
+        ] {
+            let sk = SecretKey::random(&par, &mut rng);
+            let pk = PublicKey::new(&sk, &mut rng);
+            let rk = RelinearizationKey::new(&sk, &mut rng)?;
+            let ek = EvaluationKeyBuilder::new(&sk)?
+                .enable_inner_sum()?
+                .build(&mut rng)?;
+            let multiplicator = Multiplicator::default(&rk)?;
+
+            let v = par.plaintext.random_vec(par.degree(), &mut rng);
+            let pt = Plaintext::try_encode(&v, Encoding::simd(), &par)?;
+            let check = |ct: &Ciphertext| {
+                let measured = unsafe { sk.measure_noise(ct).unwrap() } as isize;
+                let estimate = ct.noise_estimate().unwrap() as isize;
+                println!("Noise: measured {measured}, estimated {estimate}");
+                // The estimates are conservative, in particular for long sums.
+                assert!(estimate >= measured - 3 && estimate <= measured + 14);
+            };
+
+            let ct_sk: Ciphertext = sk.try_encrypt(&pt, &mut rng)?;
+            check(&ct_sk);
+            let ct_pk: Ciphertext = pk.try_encrypt(&pt, &mut rng)?;
+            check(&ct_pk);
+            check(&(&ct_sk + &ct_pk));
+            check(&(&ct_sk - &pt));
+            check(&(&ct_sk * &pt));
+            check(&(&ct_sk * 1000u64));
+
+            let mut ct = &ct_sk * &ct_pk;
+            check(&ct);
+            rk.relinearizes(&mut ct)?;
+            check(&ct);
+            let ct = multiplicator.multiply(&ct, &ct)?;
+            check(&ct);
+            check(&ek.computes_inner_sum(&ct)?);
+
+            let mut ct = ct.clone();
+            ct.mod_switch_to_last_level()?;
+            check(&ct);
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,147 @@
+//! Measurement of the noise of the ciphertexts after every operation, for
+//! debugging purposes.
+
+use crate::bfv::{Ciphertext, SecretKey};
+use std::sync::{Arc, Mutex};
+
+/// Noise measured by a [`NoiseOracle`] after an operation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NoiseRecord {
+    /// The name of the operation.
+    pub operation: &'static str,
+    /// The level of the resulting ciphertext.
+    pub level: usize,
+    /// The noise measured with the secret key, in bits, or `None` if the
+    /// ciphertext could not be decrypted.
+    pub measured: Option<usize>,
+    /// The heuristic estimate of the noise, in bits, if known.
+    pub estimated: Option<usize>,
+}
+
+/// An oracle which holds a [`SecretKey`] to measure and log the actual noise
+/// of the ciphertexts after every operation, for example to validate the
+/// heuristic noise estimates or to find the operation after which a circuit
+/// fails to decrypt.
+///
+/// The oracle can be attached to a
+/// [`Multiplicator`](crate::bfv::Multiplicator) or to an
+/// [`EvaluationKey`](crate::bfv::EvaluationKey). The measurements are emitted
+/// as `tracing` events when the `tracing` feature is enabled, printed on the
+/// standard error otherwise, and recorded in the oracle. Clones of an oracle
+/// share their records.
+///
+/// The oracle defeats the purpose of the encryption, and must never be used
+/// in production.
+#[derive(Debug, Clone)]
+pub struct NoiseOracle {
+    sk: SecretKey,
+    records: Arc<Mutex<Vec<NoiseRecord>>>,
+}
+
+impl NoiseOracle {
+    /// Creates an oracle measuring the noise with the secret key `sk`.
+    pub fn new(sk: &SecretKey) -> Self {
+        Self {
+            sk: sk.clone(),
+            records: Arc::new(Mutex::new(vec![])),
+        }
+    }
+
+    /// Measures and logs the noise of the ciphertext `ct` resulting from the
+    /// operation `operation`.
+    pub fn observe(&self, operation: &'static str, ct: &Ciphertext) {
+        // Safety: the measurement runs in variable time, which is acceptable
+        // for an oracle which already knows the secret key.
+        let measured = unsafe { self.sk.measure_noise(ct) }.ok();
+        let record = NoiseRecord {
+            operation,
+            level: ct.level,
+            measured,
+            estimated: ct.noise_estimate(),
+        };
+
+        #[cfg(feature = "tracing")]
+        tracing::debug!(
+            operation = record.operation,
+            level = record.level,
+            measured = record.measured,
+            estimated = record.estimated,
+            "measured noise"
+        );
+        #[cfg(not(feature = "tracing"))]
+        eprintln!(
+            "{}: level {}, measured noise {:?} bits, estimated noise {:?} bits",
+            record.operation, record.level, record.measured, record.estimated
+        );
+
+        self.records.lock().unwrap().push(record);
+    }
+
+    /// Returns the noise recorded so far, in the order of the operations.
+    pub fn records(&self) -> Vec<NoiseRecord> {
+        self.records.lock().unwrap().clone()
+    }
+
+    /// Clears the noise recorded so far.
+    pub fn clear(&self) {
+        self.records.lock().unwrap().clear()
+    }
+}
+
+impl PartialEq for NoiseOracle {
+    fn eq(&self, other: &Self) -> bool {
+        self.sk == other.sk && Arc::ptr_eq(&self.records, &other.records)
+    }
+}
+
+impl Eq for NoiseOracle {}
+
+#[cfg(test)]
+mod tests {
+    use super::NoiseOracle;
+    use crate::bfv::{
+        BfvParameters, Encoding, EvaluationKeyBuilder, Multiplicator, Plaintext,
+        RelinearizationKey, SecretKey,
+    };
+    use fhe_traits::{FheEncoder, FheEncrypter};
+    use rand::thread_rng;
+    use std::error::Error;
+
+    #[test]
+    fn observe() -> Result<(), Box<dyn Error>> {
+        let mut rng = thread_rng();
+        let params = BfvParameters::default_arc(3, 16);
+        let sk = SecretKey::random(&params, &mut rng);
+        let oracle = NoiseOracle::new(&sk);
+
+        let v = params.plaintext.random_vec(params.degree(), &mut rng);
+        let pt = Plaintext::try_encode(&v, Encoding::simd(), &params)?;
+        let ct = sk.try_encrypt(&pt, &mut rng)?;
+
+        let rk = RelinearizationKey::new(&sk, &mut rng)?;
+        let mut multiplicator = Multiplicator::default(&rk)?;
+        multiplicator.set_noise_oracle(&oracle);
+        let ct2 = multiplicator.multiply(&ct, &ct)?;
+
+        let mut ek = EvaluationKeyBuilder::new(&sk)?
+            .enable_row_rotation()?
+            .build(&mut rng)?;
+        ek.set_noise_oracle(&oracle);
+        ek.rotates_rows(&ct2)?;
+
+        let records = oracle.records();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].operation, "multiply");
+        assert_eq!(records[1].operation, "rotates_rows");
+        for record in &records {
+            assert_eq!(record.level, 0);
+            assert!(record.measured.is_some());
+            assert!(record.estimated.is_some());
+        }
+        assert!(records[0].measured > unsafe { sk.measure_noise(&ct).ok() });
+
+        oracle.clear();
+        assert!(oracle.records().is_empty());
+        Ok(())
+    }
+}
@@ -0,0 +1,288 @@
+//! Oblivious linear evaluation and Beaver triple generation.
+//!
+//! In an oblivious linear evaluation, a receiver holding a vector `x` and a
+//! sender holding vectors `a` and `b` interact so that the receiver learns
+//! `a * x + b` (slot-wise, modulo the plaintext modulus) and nothing else, and
+//! the sender learns nothing. The receiver encrypts `x` with [`ole_request`],
+//! the sender evaluates the affine function with [`ole_response`], and the
+//! receiver decrypts the result with [`ole_finish`].
+//!
+//! The responses are re-randomized with an encryption of zero under the public
+//! key of the receiver, and their noise is flooded with [`FLOODING_BITS`] bits
+//! of statistical security, so that they do not reveal the inputs of the
+//! sender. The receiver is assumed to follow the protocol, i.e., to send fresh
+//! encryptions.
+//!
+//! Batches of [`BeaverTripleShares`] are generated between a first party
+//! holding the secret key, using a [`BeaverTripleGenerator`], and a second
+//! party calling [`beaver_triple_response`].
+
+use std::sync::Arc;
+
+use fhe_traits::{FheDecoder, FheDecrypter, FheEncoder, FheEncrypter};
+use rand::{CryptoRng, RngCore};
+use zeroize::{Zeroize, ZeroizeOnDrop};
+
+use crate::bfv::{noise, BfvParameters, Ciphertext, Encoding, Plaintext, PublicKey, SecretKey};
+use crate::{Error, Result};
+
+/// Statistical security, in bits, of the noise flooding of the responses.
+pub const FLOODING_BITS: usize = 40;
+
+/// Encrypts the input `x` of the receiver of an oblivious linear evaluation.
+pub fn ole_request<R: RngCore + CryptoRng>(
+    sk: &SecretKey,
+    x: &[u64],
+    rng: &mut R,
+) -> Result<Ciphertext> {
+    let pt = Plaintext::try_encode(x, Encoding::simd(), &sk.par)?;
+    sk.try_encrypt(&pt, rng)
+}
+
+/// Evaluates `a * x + b` on the encrypted `request` of the receiver, whose
+/// public key is `pk`.
+pub fn ole_response<R: RngCore + CryptoRng>(
+    pk: &PublicKey,
+    request: &Ciphertext,
+    a: &[u64],
+    b: &[u64],
+    rng: &mut R,
+) -> Result<Ciphertext> {
+    respond(pk, &[(request, a)], b, rng)
+}
+
+/// Decrypts the `response` of the sender, to obtain `a * x + b`.
+pub fn ole_finish(sk: &SecretKey, response: &Ciphertext) -> Result<Vec<u64>> {
+    let pt = sk.try_decrypt(response)?;
+    Vec::<u64>::try_decode(&pt, Encoding::simd_at_level(response.level))
+}
+
+/// Evaluates the sum of the `terms` `ct_i * a_i` and of `b`, and hides the
+/// inputs of the sender in the resulting ciphertext.
+fn respond<R: RngCore + CryptoRng>(
+    pk: &PublicKey,
+    terms: &[(&Ciphertext, &[u64])],
+    b: &[u64],
+    rng: &mut R,
+) -> Result<Ciphertext> {
+    let par = &pk.par;
+    let mut response = pk.try_encrypt(&Plaintext::zero(Encoding::simd(), par)?, rng)?;
+    for (request, a) in terms {
+        par.check_fingerprint(&request.par)?;
+        if request.level != 0 || request.c.len() != 2 {
+            return Err(Error::UnspecifiedInput(
+                "The request is not a fresh encryption".to_string(),
+            ));
+        }
+        // The noise of a request is not known to the sender, which assumes
+        // that it is a fresh encryption.
+        let mut request = (*request).clone();
+        request.noise = Some(noise::fresh_public_key(par));
+
+        let a = Plaintext::try_encode(*a, Encoding::simd(), par)?;
+        response += &(&request * &a);
+    }
+    response += &Plaintext::try_encode(b, Encoding::simd(), par)?;
+    response.mod_switch_to_last_level()?;
+    response.flood_noise(FLOODING_BITS, rng)?;
+    Ok(response)
+}
+
+/// The additive shares of a party in a batch of Beaver triples `(a, b, c)`
+/// with `c = a * b`, slot-wise modulo the plaintext modulus.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BeaverTripleShares {
+    /// The shares of `a`.
+    pub a: Vec<u64>,
+    /// The shares of `b`.
+    pub b: Vec<u64>,
+    /// The shares of `c`.
+    pub c: Vec<u64>,
+}
+
+impl Zeroize for BeaverTripleShares {
+    fn zeroize(&mut self) {
+        self.a.zeroize();
+        self.b.zeroize();
+        self.c.zeroize();
+    }
+}
+
+impl ZeroizeOnDrop for BeaverTripleShares {}
+
+impl Drop for BeaverTripleShares {
+    fn drop(&mut self) {
+        self.zeroize()
+    }
+}
+
+impl BeaverTripleShares {
+    /// Samples uniform shares of `a` and `b`.
+    fn random<R: RngCore + CryptoRng>(par: &BfvParameters, rng: &mut R) -> Self {
+        Self {
+            a: par.plaintext.random_vec(par.degree(), rng),
+            b: par.plaintext.random_vec(par.degree(), rng),
+            c: vec![],
+        }
+    }
+
+    /// Returns the number of triples.
+    pub fn len(&self) -> usize {
+        self.a.len()
+    }
+
+    /// Returns whether there are no triples.
+    pub fn is_empty(&self) -> bool {
+        self.a.is_empty()
+    }
+}
+
+/// Generates a batch of `degree` Beaver triples for the party holding the
+/// secret key.
+#[derive(Debug)]
+pub struct BeaverTripleGenerator {
+    par: Arc<BfvParameters>,
+    shares: BeaverTripleShares,
+}
+
+impl BeaverTripleGenerator {
+    /// Samples the shares of `a` and `b` of this party.
+    pub fn new<R: RngCore + CryptoRng>(par: &Arc<BfvParameters>, rng: &mut R) -> Self {
+        Self {
+            par: par.clone(),
+            shares: BeaverTripleShares::random(par, rng),
+        }
+    }
+
+    /// Encrypts the shares of `a` and `b` of this party, to be sent to the
+    /// other party.
+    pub fn request<R: RngCore + CryptoRng>(
+        &self,
+        sk: &SecretKey,
+        rng: &mut R,
+    ) -> Result<Vec<Ciphertext>> {
+        self.par.check_fingerprint(&sk.par)?;
+        Ok(vec![
+            ole_request(sk, &self.shares.a, rng)?,
+            ole_request(sk, &self.shares.b, rng)?,
+        ])
+    }
+
+    /// Completes the shares of this party from the `response` of the other
+    /// party.
+    pub fn finish(mut self, sk: &SecretKey, response: &Ciphertext) -> Result<BeaverTripleShares> {
+        self.par.check_fingerprint(&sk.par)?;
+        let t = &self.par.plaintext;
+        // c_0 = a_0 * b_0 + (a_0 * b_1 + a_1 * b_0 + r)
+        let mut c = ole_finish(sk, response)?;
+        let mut ab = self.shares.a.clone();
+        t.mul_vec(&mut ab, &self.shares.b);
+        t.add_vec(&mut c, &ab);
+        ab.zeroize();
+        self.shares.c = c;
+        Ok(self.shares)
+    }
+}
+
+/// Answers the `request` of the party holding the secret key, whose public key
+/// is `pk`, and returns the response along with the shares of this party.
+pub fn beaver_triple_response<R: RngCore + CryptoRng>(
+    pk: &PublicKey,
+    request: &[Ciphertext],
+    rng: &mut R,
+) -> Result<(Ciphertext, BeaverTripleShares)> {
+    if request.len() != 2 {
+        return Err(Error::CiphertextSizeMismatch {
+            expected: 2,
+            found: request.len(),
+        });
+    }
+    let par = &pk.par;
+    let t = &par.plaintext;
+    let mut shares = BeaverTripleShares::random(par, rng);
+    let r = t.random_vec(par.degree(), rng);
+
+    // The other party learns a_0 * b_1 + b_0 * a_1 + r.
+    let response = respond(
+        pk,
+        &[(&request[0], &shares.b), (&request[1], &shares.a)],
+        &r,
+        rng,
+    )?;
+
+    // c_1 = a_1 * b_1 - r
+    let mut c = shares.a.clone();
+    t.mul_vec(&mut c, &shares.b);
+    t.sub_vec(&mut c, &r);
+    shares.c = c;
+    Ok((response, shares))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        beaver_triple_response, ole_finish, ole_request, ole_response, BeaverTripleGenerator,
+    };
+    use crate::bfv::{BfvParameters, PublicKey, SecretKey};
+    use rand::thread_rng;
+    use std::error::Error;
+
+    #[test]
+    fn ole() -> Result<(), Box<dyn Error>> {
+        let mut rng = thread_rng();
+        let par = BfvParameters::default_arc(3, 16);
+        let t = &par.plaintext;
+        let sk = SecretKey::random(&par, &mut rng);
+        let pk = PublicKey::new(&sk, &mut rng);
+
+        let x = t.random_vec(par.degree(), &mut rng);
+        let a = t.random_vec(par.degree(), &mut rng);
+        let b = t.random_vec(par.degree(), &mut rng);
+
+        let request = ole_request(&sk, &x, &mut rng)?;
+        let response = ole_response(&pk, &request, &a, &b, &mut rng)?;
+        assert_eq!(response.level, par.max_level());
+        assert!(response.noise_estimate().unwrap() > super::FLOODING_BITS);
+
+        let mut expected = a.clone();
+        t.mul_vec(&mut expected, &x);
+        t.add_vec(&mut expected, &b);
+        assert_eq!(ole_finish(&sk, &response)?, expected);
+
+        // The noise cannot be flooded with a single modulus.
+        let par = BfvParameters::default_arc(1, 16);
+        let sk = SecretKey::random(&par, &mut rng);
+        let pk = PublicKey::new(&sk, &mut rng);
+        let request = ole_request(&sk, &x, &mut rng)?;
+        assert!(ole_response(&pk, &request, &a, &b, &mut rng).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn beaver_triples() -> Result<(), Box<dyn Error>> {
+        let mut rng = thread_rng();
+        let par = BfvParameters::default_arc(3, 16);
+        let t = &par.plaintext;
+        let sk = SecretKey::random(&par, &mut rng);
+        let pk = PublicKey::new(&sk, &mut rng);
+
+        let generator = BeaverTripleGenerator::new(&par, &mut rng);
+        let request = generator.request(&sk, &mut rng)?;
+        let (response, shares_1) = beaver_triple_response(&pk, &request, &mut rng)?;
+        let shares_0 = generator.finish(&sk, &response)?;
+        assert_eq!(shares_0.len(), par.degree());
+        assert_eq!(shares_1.len(), par.degree());
+
+        let mut a = shares_0.a.clone();
+        t.add_vec(&mut a, &shares_1.a);
+        let mut b = shares_0.b.clone();
+        t.add_vec(&mut b, &shares_1.b);
+        let mut c = shares_0.c.clone();
+        t.add_vec(&mut c, &shares_1.c);
+        t.mul_vec(&mut a, &b);
+        assert_eq!(a, c);
+
+        assert!(beaver_triple_response(&pk, &request[..1], &mut rng).is_err());
+        Ok(())
+    }
+}
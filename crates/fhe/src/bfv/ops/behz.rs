@@ -0,0 +1,299 @@
+//! RNS multiplication following the BEHZ variant of
+//! <https://eprint.iacr.org/2016/510>, where the ciphertexts are extended to an
+//! auxiliary basis using fast base conversions and a small Montgomery
+//! reduction, and scaled back using the Shenoy-Kumaresan base conversion.
+
+use crate::{Error, ParametersError, Result};
+use fhe_math::{
+    rns::BaseConverter,
+    rq::{traits::TryConvertFrom, Context, Poly, Representation},
+    zq::{primes::generate_prime, Modulus},
+};
+use itertools::{izip, Itertools};
+use ndarray::{s, Array2, Axis};
+use num_bigint::BigUint;
+use num_traits::One;
+use std::sync::Arc;
+
+/// Precomputed values to perform the BEHZ multiplication at a given level.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct BehzParameters {
+    /// Context of the ciphertexts.
+    q_ctx: Arc<Context>,
+    /// Context of the auxiliary basis Bsk = B U {m_sk}.
+    bsk_ctx: Arc<Context>,
+    plaintext: u64,
+
+    // Extension from q to Bsk using a small Montgomery reduction.
+    q_to_bsk_m_tilde: BaseConverter,
+    m_tilde: Modulus,
+    neg_inv_q_mod_m_tilde: u64,
+    q_mod_bsk: Box<[u64]>,
+    inv_m_tilde_mod_bsk: Box<[u64]>,
+
+    // Fast floor from q U Bsk to Bsk.
+    q_to_bsk: BaseConverter,
+    inv_q_mod_bsk: Box<[u64]>,
+
+    // Shenoy-Kumaresan conversion from Bsk to q.
+    b_to_q_msk: BaseConverter,
+    inv_b_mod_msk: u64,
+    b_mod_q: Box<[u64]>,
+}
+
+impl BehzParameters {
+    /// Create the BEHZ parameters for the context `q_ctx` of polynomials of
+    /// degree `degree` and a plaintext modulus `plaintext`.
+    pub(crate) fn new(q_ctx: &Arc<Context>, degree: usize, plaintext: u64) -> Result<Self> {
+        let q = q_ctx.moduli_operators();
+        let q_modulus = q_ctx.modulus();
+
+        // The auxiliary basis B must be large enough to hold t * (c * d) / q,
+        // where c and d are in [0, q) and the product has up to `degree` terms.
+        // We add a margin to account for the tensoring of larger ciphertexts.
+        let b_size = q_modulus.bits() as usize
+            + degree.ilog2() as usize
+            + (64 - plaintext.leading_zeros() as usize)
+            + 8;
+        let mut b = vec![];
+        let mut b_product = BigUint::one();
+        let mut upper_bound = 1 << 62;
+        while (b_product.bits() as usize) <= b_size {
+            upper_bound = generate_prime(62, 2 * degree as u64, upper_bound).ok_or(
+                Error::ParametersError(ParametersError::NotEnoughPrimes(62, degree)),
+            )?;
+            if !q_ctx.moduli().contains(&upper_bound) {
+                b.push(upper_bound);
+                b_product *= upper_bound;
+            }
+        }
+        let mut m_sk = generate_prime(62, 2 * degree as u64, upper_bound);
+        while let Some(m) = m_sk.filter(|m| q_ctx.moduli().contains(m)) {
+            m_sk = generate_prime(62, 2 * degree as u64, m);
+        }
+        let m_sk = m_sk.ok_or(Error::ParametersError(ParametersError::NotEnoughPrimes(
+            62, degree,
+        )))?;
+
+        // The modulus m_tilde only needs to be coprime with q and larger than
+        // twice the number of moduli in q.
+        let mut m_tilde_value = generate_prime(32, 2, 1 << 32);
+        while let Some(m) = m_tilde_value.filter(|m| q_ctx.moduli().contains(m)) {
+            m_tilde_value = generate_prime(32, 2, m);
+        }
+        let m_tilde_value = m_tilde_value.ok_or(Error::ParametersError(
+            ParametersError::NotEnoughPrimes(32, degree),
+        ))?;
+        let m_tilde = Modulus::new(m_tilde_value)?;
+
+        let mut bsk_moduli = b.clone();
+        bsk_moduli.push(m_sk);
+        let bsk_ctx = Context::new_arc(&bsk_moduli, degree)?;
+        let bsk = bsk_ctx.moduli_operators();
+        let b_ops = &bsk[..b.len()];
+        let m_sk = &bsk[b.len()];
+
+        let mut bsk_m_tilde = bsk.to_vec();
+        bsk_m_tilde.push(m_tilde.clone());
+        let q_to_bsk_m_tilde = BaseConverter::new(q, &bsk_m_tilde, m_tilde_value)?;
+        let q_to_bsk = BaseConverter::new(q, bsk, 1)?;
+        let mut q_msk = q.to_vec();
+        q_msk.push(m_sk.clone());
+        let b_to_q_msk = BaseConverter::new(b_ops, &q_msk, 1)?;
+
+        let neg_inv_q_mod_m_tilde = m_tilde.neg(inv(q_modulus, &m_tilde)?);
+
+        let q_mod_bsk = bsk
+            .iter()
+            .map(|m| rem(q_modulus, m.modulus()))
+            .collect_vec();
+        let inv_m_tilde_mod_bsk = bsk
+            .iter()
+            .map(|m| inv(&BigUint::from(m_tilde_value), m))
+            .collect::<Result<Vec<_>>>()?;
+        let inv_q_mod_bsk = bsk
+            .iter()
+            .map(|m| inv(q_modulus, m))
+            .collect::<Result<Vec<_>>>()?;
+
+        let inv_b_mod_msk = inv(&b_product, m_sk)?;
+        let b_mod_q = q
+            .iter()
+            .map(|qi| rem(&b_product, qi.modulus()))
+            .collect_vec();
+
+        Ok(Self {
+            q_ctx: q_ctx.clone(),
+            bsk_ctx,
+            plaintext,
+            q_to_bsk_m_tilde,
+            m_tilde,
+            neg_inv_q_mod_m_tilde,
+            q_mod_bsk: q_mod_bsk.into_boxed_slice(),
+            inv_m_tilde_mod_bsk: inv_m_tilde_mod_bsk.into_boxed_slice(),
+            q_to_bsk,
+            inv_q_mod_bsk: inv_q_mod_bsk.into_boxed_slice(),
+            b_to_q_msk,
+            inv_b_mod_msk,
+            b_mod_q: b_mod_q.into_boxed_slice(),
+        })
+    }
+
+    /// Extend a polynomial in q to the basis Bsk; both outputs are in Ntt
+    /// representation.
+    fn extend(&self, p: &Poly) -> Result<(Poly, Poly)> {
+        let mut p_q = p.clone();
+        p_q.change_representation(Representation::PowerBasis);
+
+        // Fast base conversion of m_tilde * p to Bsk U {m_tilde}.
+        let z = self.q_to_bsk_m_tilde.convert(p_q.coefficients());
+
+        // Small Montgomery reduction to remove the multiple of q introduced
+        // by the fast base conversion.
+        let bsk_len = self.q_mod_bsk.len();
+        let m_tilde_half = self.m_tilde.modulus() >> 1;
+        let mut p_bsk = Array2::<u64>::zeros((bsk_len, z.ncols()));
+        izip!(p_bsk.axis_iter_mut(Axis(1)), z.axis_iter(Axis(1))).for_each(|(mut out, z_k)| {
+            let r = self.m_tilde.mul(z_k[bsk_len], self.neg_inv_q_mod_m_tilde);
+            let r_is_negative = r > m_tilde_half;
+            for (out_j, z_jk, m, q_mod_m, inv_m_tilde) in izip!(
+                out.iter_mut(),
+                z_k.iter(),
+                self.bsk_ctx.moduli_operators(),
+                self.q_mod_bsk.iter(),
+                self.inv_m_tilde_mod_bsk.iter()
+            ) {
+                let q_r = if r_is_negative {
+                    m.neg(m.mul(*q_mod_m, m.reduce(self.m_tilde.modulus() - r)))
+                } else {
+                    m.mul(*q_mod_m, m.reduce(r))
+                };
+                *out_j = m.mul(m.add(*z_jk, q_r), *inv_m_tilde);
+            }
+        });
+
+        let mut p_bsk =
+            Poly::try_convert_from(p_bsk, &self.bsk_ctx, false, Representation::PowerBasis)?;
+        p_q.change_representation(Representation::Ntt);
+        p_bsk.change_representation(Representation::Ntt);
+        Ok((p_q, p_bsk))
+    }
+
+    /// Compute round(t / q * x) in q from x given in q U Bsk, in PowerBasis
+    /// representation.
+    fn scale_down(&self, x_q: &mut Poly, x_bsk: &mut Poly) -> Result<Poly> {
+        x_q.change_representation(Representation::PowerBasis);
+        x_bsk.change_representation(Representation::PowerBasis);
+
+        let mut t_x_q = x_q.coefficients().to_owned();
+        izip!(t_x_q.outer_iter_mut(), self.q_ctx.moduli_operators()).for_each(|(mut v, qi)| {
+            let t = qi.reduce(self.plaintext);
+            let t_shoup = qi.shoup(t);
+            v.iter_mut()
+                .for_each(|vi| *vi = qi.mul_shoup(*vi, t, t_shoup))
+        });
+        let mut t_x_bsk = x_bsk.coefficients().to_owned();
+        izip!(t_x_bsk.outer_iter_mut(), self.bsk_ctx.moduli_operators()).for_each(|(mut v, m)| {
+            let t = m.reduce(self.plaintext);
+            let t_shoup = m.shoup(t);
+            v.iter_mut()
+                .for_each(|vi| *vi = m.mul_shoup(*vi, t, t_shoup))
+        });
+
+        // Fast floor: (t * x - FastBConv(t * x mod q)) / q in Bsk.
+        let conv = self.q_to_bsk.convert(t_x_q.view());
+        izip!(
+            t_x_bsk.outer_iter_mut(),
+            conv.outer_iter(),
+            self.bsk_ctx.moduli_operators(),
+            self.inv_q_mod_bsk.iter()
+        )
+        .for_each(|(mut v, c, m, inv_q)| {
+            let inv_q_shoup = m.shoup(*inv_q);
+            izip!(v.iter_mut(), c.iter())
+                .for_each(|(vi, ci)| *vi = m.mul_shoup(m.sub(*vi, *ci), *inv_q, inv_q_shoup))
+        });
+
+        // Shenoy-Kumaresan conversion from Bsk to q.
+        let q_len = self.b_mod_q.len();
+        let b_len = t_x_bsk.nrows() - 1;
+        let (y_b, y_msk) = t_x_bsk.view().split_at(Axis(0), b_len);
+        let mut y_q_msk = self.b_to_q_msk.convert(y_b);
+        let m_sk = &self.bsk_ctx.moduli_operators()[b_len];
+        let m_sk_half = m_sk.modulus() >> 1;
+        let (mut y_q, y_conv_msk) = y_q_msk.view_mut().split_at(Axis(0), q_len);
+        izip!(y_q.axis_iter_mut(Axis(1)), y_conv_msk.iter(), y_msk.iter()).for_each(
+            |(mut y_q_k, conv_msk_k, msk_k)| {
+                let alpha = m_sk.mul(m_sk.sub(*conv_msk_k, *msk_k), self.inv_b_mod_msk);
+                let alpha_is_negative = alpha > m_sk_half;
+                for (y, qi, b_mod_qi) in izip!(
+                    y_q_k.iter_mut(),
+                    self.q_ctx.moduli_operators(),
+                    self.b_mod_q.iter()
+                ) {
+                    if alpha_is_negative {
+                        let a = qi.reduce(m_sk.modulus() - alpha);
+                        *y = qi.add(*y, qi.mul(a, *b_mod_qi));
+                    } else {
+                        let a = qi.reduce(alpha);
+                        *y = qi.sub(*y, qi.mul(a, *b_mod_qi));
+                    }
+                }
+            },
+        );
+
+        let y_q = y_q_msk.slice_move(s![..q_len, ..]);
+        Ok(Poly::try_convert_from(
+            y_q,
+            &self.q_ctx,
+            false,
+            Representation::PowerBasis,
+        )?)
+    }
+
+    /// Tensor two ciphertexts (given as vectors of polynomials in Ntt
+    /// representation) and scale the result by t / q. The output polynomials
+    /// are in PowerBasis representation.
+    pub(crate) fn tensor(&self, lhs: &[Poly], rhs: &[Poly]) -> Result<Vec<Poly>> {
+        if lhs.is_empty() || rhs.is_empty() {
+            return Err(Error::TooFewValues(0, 1));
+        }
+        let lhs_ext = lhs
+            .iter()
+            .map(|p| self.extend(p))
+            .collect::<Result<Vec<_>>>()?;
+        let rhs_ext = if std::ptr::eq(lhs, rhs) {
+            lhs_ext.clone()
+        } else {
+            rhs.iter()
+                .map(|p| self.extend(p))
+                .collect::<Result<Vec<_>>>()?
+        };
+
+        let size = lhs_ext.len() + rhs_ext.len() - 1;
+        let mut c_q = vec![Poly::zero(&self.q_ctx, Representation::Ntt); size];
+        let mut c_bsk = vec![Poly::zero(&self.bsk_ctx, Representation::Ntt); size];
+        for (i, (l_q, l_bsk)) in lhs_ext.iter().enumerate() {
+            for (j, (r_q, r_bsk)) in rhs_ext.iter().enumerate() {
+                c_q[i + j] += &(l_q * r_q);
+                c_bsk[i + j] += &(l_bsk * r_bsk);
+            }
+        }
+
+        izip!(c_q.iter_mut(), c_bsk.iter_mut())
+            .map(|(x_q, x_bsk)| self.scale_down(x_q, x_bsk))
+            .collect()
+    }
+}
+
+/// Returns x mod m.
+fn rem(x: &BigUint, m: u64) -> u64 {
+    (x % m).iter_u64_digits().next().unwrap_or_default()
+}
+
+/// Returns the inverse of x mod m, or an error if x is not invertible.
+fn inv(x: &BigUint, m: &Modulus) -> Result<u64> {
+    let x_mod_m = rem(x, m.modulus());
+    m.inv(x_mod_m)
+        .ok_or(Error::NotInvertible(x_mod_m, m.modulus()))
+}
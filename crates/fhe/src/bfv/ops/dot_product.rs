@@ -5,7 +5,7 @@ use itertools::{izip, Itertools};
 use ndarray::{Array, Array2};
 
 use crate::{
-    bfv::{Ciphertext, Plaintext},
+    bfv::{noise, Ciphertext, Plaintext},
     Error, Result,
 };
 
@@ -58,24 +58,29 @@ where
 {
     let count = min(ct.clone().count(), pt.clone().count());
     if count == 0 {
-        return Err(Error::DefaultError(
-            "At least one iterator is empty".to_string(),
-        ));
+        return Err(Error::TooFewValues(0, 1));
     }
     let ct_first = ct.clone().next().unwrap();
     let ctx = ct_first.c[0].ctx();
 
-    if izip!(ct.clone(), pt.clone()).any(|(cti, pti)| {
-        cti.par != ct_first.par || pti.par != ct_first.par || cti.c.len() != ct_first.c.len()
-    }) {
-        return Err(Error::DefaultError("Mismatched parameters".to_string()));
+    let mut key_id = ct_first.key_id;
+    for (cti, pti) in izip!(ct.clone(), pt.clone()) {
+        ct_first.par.check_fingerprint(&cti.par)?;
+        ct_first.par.check_fingerprint(&pti.par)?;
+        key_id = cti.merge_key_id_with(key_id)?;
     }
-    if ct.clone().any(|cti| cti.c.len() != ct_first.c.len()) {
-        return Err(Error::DefaultError(
-            "Mismatched number of parts in the ciphertexts".to_string(),
-        ));
+    if let Some(cti) = ct.clone().find(|cti| cti.c.len() != ct_first.c.len()) {
+        return Err(Error::CiphertextSizeMismatch {
+            expected: ct_first.c.len(),
+            found: cti.c.len(),
+        });
     }
 
+    let noise = izip!(ct.clone(), pt.clone())
+        .map(|(cti, _)| noise::mul_plaintext(&ct_first.par, cti.noise))
+        .reduce(noise::add)
+        .unwrap();
+
     let max_acc = ctx
         .moduli()
         .iter()
@@ -83,14 +88,14 @@ where
         .collect_vec();
     let min_of_max = max_acc.iter().min().unwrap();
 
-    if count as u128 > *min_of_max {
-        // Too many ciphertexts for the optimized method, instead, we call
-        // `poly_dot_product`.
+    if count as u128 > *min_of_max || ctx.has_convolutions() {
+        // Too many ciphertexts for the optimized method, or moduli without
+        // NTT, instead, we call `poly_dot_product`.
         let c = (0..ct_first.c.len())
             .map(|i| {
                 poly_dot_product(
                     ct.clone().map(|cti| unsafe { cti.c.get_unchecked(i) }),
-                    pt.clone().map(|pti| &pti.poly_ntt),
+                    pt.clone().map(|pti| pti.poly_ntt()),
                 )
                 .map_err(Error::MathError)
             })
@@ -101,11 +106,14 @@ where
             seed: None,
             c,
             level: ct_first.level,
+            noise,
+            metadata: None,
+            key_id,
         })
     } else {
         let mut acc = Array::zeros((ct_first.c.len(), ctx.moduli().len(), ct_first.par.degree()));
         for (ciphertext, plaintext) in izip!(ct, pt) {
-            let pt_coefficients = plaintext.poly_ntt.coefficients();
+            let pt_coefficients = plaintext.poly_ntt().coefficients();
             for (mut acci, ci) in izip!(acc.outer_iter_mut(), ciphertext.c.iter()) {
                 let ci_coefficients = ci.coefficients();
                 for (mut accij, cij, pij) in izip!(
@@ -134,7 +142,11 @@ where
                 ctx.moduli_operators()
             ) {
                 for (outij_coeff, accij_coeff) in izip!(outij.iter_mut(), accij.iter()) {
-                    unsafe { *outij_coeff = q.reduce_u128_vt(*accij_coeff) }
+                    *outij_coeff = if cfg!(feature = "constant-time") {
+                        q.reduce_u128(*accij_coeff)
+                    } else {
+                        unsafe { q.reduce_u128_vt(*accij_coeff) }
+                    }
                 }
             }
             c.push(Poly::try_convert_from(
@@ -150,6 +162,9 @@ where
             seed: None,
             c,
             level: ct_first.level,
+            noise,
+            metadata: None,
+            key_id,
         })
     }
 }
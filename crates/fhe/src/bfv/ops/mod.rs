@@ -1,12 +1,18 @@
 //! Operations over ciphertexts
 
+mod behz;
+pub(crate) use behz::BehzParameters;
+
 mod dot_product;
 pub use dot_product::dot_product_scalar;
 
 mod mul;
 pub use mul::Multiplicator;
 
-use super::{Ciphertext, Plaintext};
+mod scratch;
+pub use scratch::OpScratch;
+
+use super::{noise, BfvParameters, Ciphertext, MultiplicationStrategy, Plaintext};
 use crate::{Error, Result};
 use fhe_math::rq::{Poly, Representation};
 use itertools::{izip, Itertools};
@@ -31,7 +37,9 @@ impl AddAssign<&Ciphertext> for Ciphertext {
         } else if !rhs.c.is_empty() {
             assert_eq!(self.level, rhs.level);
             assert_eq!(self.c.len(), rhs.c.len());
+            self.key_id = self.merge_key_id(rhs).unwrap();
             izip!(&mut self.c, &rhs.c).for_each(|(c1i, c2i)| *c1i += c2i);
+            self.noise = noise::add(self.noise, rhs.noise);
             self.seed = None
         }
     }
@@ -63,10 +71,48 @@ impl AddAssign<&Plaintext> for Ciphertext {
 
         let poly = rhs.to_poly();
         self.c[0] += &poly;
+        self.noise = noise::add_plaintext(self.noise);
+        self.seed = None
+    }
+}
+
+impl AddAssign<u64> for Ciphertext {
+    fn add_assign(&mut self, rhs: u64) {
+        assert!(!self.c.is_empty());
+
+        let poly = scalar_to_poly(&self.par, rhs, self.level);
+        self.c[0] += &poly;
+        self.noise = noise::add_plaintext(self.noise);
         self.seed = None
     }
 }
 
+impl AddAssign<i64> for Ciphertext {
+    fn add_assign(&mut self, rhs: i64) {
+        *self += self.par.plaintext.reduce_i64(rhs)
+    }
+}
+
+impl Add<u64> for &Ciphertext {
+    type Output = Ciphertext;
+
+    fn add(self, rhs: u64) -> Ciphertext {
+        let mut self_clone = self.clone();
+        self_clone += rhs;
+        self_clone
+    }
+}
+
+impl Add<i64> for &Ciphertext {
+    type Output = Ciphertext;
+
+    fn add(self, rhs: i64) -> Ciphertext {
+        let mut self_clone = self.clone();
+        self_clone += rhs;
+        self_clone
+    }
+}
+
 impl Sub<&Ciphertext> for &Ciphertext {
     type Output = Ciphertext;
 
@@ -86,7 +132,9 @@ impl SubAssign<&Ciphertext> for Ciphertext {
         } else if !rhs.c.is_empty() {
             assert_eq!(self.level, rhs.level);
             assert_eq!(self.c.len(), rhs.c.len());
+            self.key_id = self.merge_key_id(rhs).unwrap();
             izip!(&mut self.c, &rhs.c).for_each(|(c1i, c2i)| *c1i -= c2i);
+            self.noise = noise::add(self.noise, rhs.noise);
             self.seed = None
         }
     }
@@ -118,10 +166,48 @@ impl SubAssign<&Plaintext> for Ciphertext {
 
         let poly = rhs.to_poly();
         self.c[0] -= &poly;
+        self.noise = noise::add_plaintext(self.noise);
         self.seed = None
     }
 }
 
+impl SubAssign<u64> for Ciphertext {
+    fn sub_assign(&mut self, rhs: u64) {
+        assert!(!self.c.is_empty());
+
+        let poly = scalar_to_poly(&self.par, rhs, self.level);
+        self.c[0] -= &poly;
+        self.noise = noise::add_plaintext(self.noise);
+        self.seed = None
+    }
+}
+
+impl SubAssign<i64> for Ciphertext {
+    fn sub_assign(&mut self, rhs: i64) {
+        *self -= self.par.plaintext.reduce_i64(rhs)
+    }
+}
+
+impl Sub<u64> for &Ciphertext {
+    type Output = Ciphertext;
+
+    fn sub(self, rhs: u64) -> Ciphertext {
+        let mut self_clone = self.clone();
+        self_clone -= rhs;
+        self_clone
+    }
+}
+
+impl Sub<i64> for &Ciphertext {
+    type Output = Ciphertext;
+
+    fn sub(self, rhs: i64) -> Ciphertext {
+        let mut self_clone = self.clone();
+        self_clone -= rhs;
+        self_clone
+    }
+}
+
 impl Neg for &Ciphertext {
     type Output = Ciphertext;
 
@@ -132,6 +218,9 @@ impl Neg for &Ciphertext {
             seed: None,
             c,
             level: self.level,
+            noise: self.noise,
+            metadata: None,
+            key_id: self.key_id,
         }
     }
 }
@@ -151,7 +240,8 @@ impl MulAssign<&Plaintext> for Ciphertext {
         assert_eq!(self.par, rhs.par);
         if !self.c.is_empty() {
             assert_eq!(self.level, rhs.level);
-            self.c.iter_mut().for_each(|ci| *ci *= &rhs.poly_ntt);
+            self.c.iter_mut().for_each(|ci| *ci *= rhs.poly_ntt());
+            self.noise = noise::mul_plaintext(&self.par, self.noise);
         }
         self.seed = None
     }
@@ -167,14 +257,73 @@ impl Mul<&Plaintext> for &Ciphertext {
     }
 }
 
+impl MulAssign<u64> for Ciphertext {
+    fn mul_assign(&mut self, rhs: u64) {
+        let rhs = self.par.plaintext.reduce(rhs);
+        // Multiply by the centered representative of the scalar, so that the
+        // noise grows by at most a factor t / 2.
+        let t = self.par.plaintext.modulus();
+        if rhs > t / 2 {
+            self.c.iter_mut().for_each(|ci| {
+                *ci *= t - rhs;
+                *ci = -&*ci
+            });
+        } else {
+            self.c.iter_mut().for_each(|ci| *ci *= rhs);
+        }
+        self.noise = noise::mul_scalar(&self.par, self.noise, rhs);
+        self.seed = None
+    }
+}
+
+impl MulAssign<i64> for Ciphertext {
+    fn mul_assign(&mut self, rhs: i64) {
+        *self *= self.par.plaintext.reduce_i64(rhs)
+    }
+}
+
+impl Mul<u64> for &Ciphertext {
+    type Output = Ciphertext;
+
+    fn mul(self, rhs: u64) -> Ciphertext {
+        let mut self_clone = self.clone();
+        self_clone *= rhs;
+        self_clone
+    }
+}
+
+impl Mul<i64> for &Ciphertext {
+    type Output = Ciphertext;
+
+    fn mul(self, rhs: i64) -> Ciphertext {
+        let mut self_clone = self.clone();
+        self_clone *= rhs;
+        self_clone
+    }
+}
+
 impl Mul<&Ciphertext> for &Ciphertext {
     type Output = Ciphertext;
 
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            level = "debug",
+            skip_all,
+            fields(level = self.level, size = self.c.len())
+        )
+    )]
     fn mul(self, rhs: &Ciphertext) -> Ciphertext {
         if self.c.is_empty() {
             return self.clone();
         }
 
+        if self.par.multiplication_strategy == MultiplicationStrategy::Behz {
+            return self.mul_behz(rhs).unwrap();
+        }
+
+        let key_id = self.merge_key_id(rhs).unwrap();
+
         if rhs == self {
             // Squaring operation
             let mp = &self.par.mul_params[self.level];
@@ -218,6 +367,9 @@ impl Mul<&Ciphertext> for &Ciphertext {
                 seed: None,
                 c,
                 level: rhs.level,
+                noise: noise::mul(&self.par, self.noise, rhs.noise),
+                metadata: None,
+                key_id,
             }
         } else {
             assert_eq!(self.par, rhs.par);
@@ -271,20 +423,151 @@ impl Mul<&Ciphertext> for &Ciphertext {
                 seed: None,
                 c,
                 level: rhs.level,
+                noise: noise::mul(&self.par, self.noise, rhs.noise),
+                metadata: None,
+                key_id,
             }
         }
     }
 }
 
+impl Ciphertext {
+    /// Checks that `self` and `rhs` can be operated on together.
+    fn check_operands(&self, rhs: &Ciphertext) -> Result<()> {
+        self.par.check_fingerprint(&rhs.par)?;
+        if self.level != rhs.level {
+            return Err(Error::LevelMismatch {
+                expected: self.level,
+                found: rhs.level,
+            });
+        }
+        self.merge_key_id(rhs)?;
+        Ok(())
+    }
+
+    /// Adds two ciphertexts, returning an error instead of panicking when
+    /// they are incompatible, and [`Error::NoiseBudgetExceeded`] when the
+    /// heuristic estimate of the noise of the sum exceeds its noise budget.
+    ///
+    /// Users who track the noise themselves can use the `+` operator, which
+    /// does not check the noise budget.
+    pub fn try_add(&self, rhs: &Ciphertext) -> Result<Ciphertext> {
+        self.check_operands(rhs)?;
+        if self.c.len() != rhs.c.len() {
+            return Err(Error::CiphertextSizeMismatch {
+                expected: self.c.len(),
+                found: rhs.c.len(),
+            });
+        }
+        let sum = self + rhs;
+        sum.check_noise_budget()?;
+        Ok(sum)
+    }
+
+    /// Multiplies two ciphertexts without relinearization, returning an error
+    /// instead of panicking when they are incompatible, and
+    /// [`Error::NoiseBudgetExceeded`] when the heuristic estimate of the noise
+    /// of the product exceeds its noise budget.
+    ///
+    /// Users who track the noise themselves can use the `*` operator, which
+    /// does not check the noise budget.
+    pub fn try_mul(&self, rhs: &Ciphertext) -> Result<Ciphertext> {
+        self.check_operands(rhs)?;
+        let product = if self.par.multiplication_strategy == MultiplicationStrategy::Behz
+            && !self.c.is_empty()
+        {
+            self.mul_behz(rhs)?
+        } else {
+            self * rhs
+        };
+        product.check_noise_budget()?;
+        Ok(product)
+    }
+
+    /// Multiplies two ciphertexts without relinearization using the BEHZ
+    /// strategy.
+    fn mul_behz(&self, rhs: &Ciphertext) -> Result<Ciphertext> {
+        self.check_operands(rhs)?;
+        let key_id = self.merge_key_id(rhs)?;
+
+        let mut c = self.par.behz_params[self.level].tensor(&self.c, &rhs.c)?;
+        c.iter_mut()
+            .for_each(|ci| ci.change_representation(Representation::Ntt));
+
+        Ok(Ciphertext {
+            par: self.par.clone(),
+            seed: None,
+            c,
+            level: rhs.level,
+            noise: noise::mul(&self.par, self.noise, rhs.noise),
+            metadata: None,
+            key_id,
+        })
+    }
+}
+
+/// Returns the encoding of the constant `value` at a given level, i.e. the
+/// polynomial `round(q / t) * value` in Ntt representation, without going
+/// through a [`Plaintext`].
+fn scalar_to_poly(par: &BfvParameters, value: u64, level: usize) -> Poly {
+    let value = par
+        .plaintext
+        .mul(par.plaintext.reduce(value), par.q_mod_t[level]);
+    let mut poly = par.delta[level].clone();
+    poly.change_representation(Representation::Ntt);
+    poly.disallow_variable_time_computations();
+    poly *= value;
+    poly
+}
+
 #[cfg(test)]
 mod tests {
     use crate::bfv::{
         encoding::EncodingEnum, BfvParameters, Ciphertext, Encoding, Plaintext, SecretKey,
     };
     use fhe_traits::{FheDecoder, FheDecrypter, FheEncoder, FheEncrypter};
-    use rand::{rngs::OsRng, thread_rng};
+    use rand::{rngs::OsRng, thread_rng, RngCore};
     use std::error::Error;
 
+    #[test]
+    fn try_add_try_mul() -> Result<(), Box<dyn Error>> {
+        let mut rng = thread_rng();
+        let params = BfvParameters::default_arc(6, 16);
+        let sk = SecretKey::random(&params, &mut rng);
+        let a = params.plaintext.random_vec(params.degree(), &mut rng);
+        let b = params.plaintext.random_vec(params.degree(), &mut rng);
+        let pt_a = Plaintext::try_encode(&a, Encoding::simd(), &params)?;
+        let pt_b = Plaintext::try_encode(&b, Encoding::simd(), &params)?;
+        let ct_a: Ciphertext = sk.try_encrypt(&pt_a, &mut rng)?;
+        let ct_b: Ciphertext = sk.try_encrypt(&pt_b, &mut rng)?;
+
+        assert_eq!(ct_a.try_add(&ct_b)?, &ct_a + &ct_b);
+        assert_eq!(ct_a.try_mul(&ct_b)?, &ct_a * &ct_b);
+
+        let ct_c: Ciphertext = sk.try_encrypt(
+            &Plaintext::try_encode(&a, Encoding::simd_at_level(1), &params)?,
+            &mut rng,
+        )?;
+        assert!(matches!(
+            ct_a.try_add(&ct_c),
+            Err(crate::Error::LevelMismatch { .. })
+        ));
+        assert!(matches!(
+            ct_a.try_add(&(&ct_a * &ct_b)),
+            Err(crate::Error::CiphertextSizeMismatch { .. })
+        ));
+        let other = BfvParameters::default_arc(2, 16);
+        let ct_d: Ciphertext = SecretKey::random(&other, &mut rng).try_encrypt(
+            &Plaintext::try_encode(&[1u64], Encoding::poly(), &other)?,
+            &mut rng,
+        )?;
+        assert!(matches!(
+            ct_a.try_mul(&ct_d),
+            Err(crate::Error::ParameterMismatch { .. })
+        ));
+        Ok(())
+    }
+
     #[test]
     fn add() -> Result<(), Box<dyn Error>> {
         let mut rng = thread_rng();
@@ -559,6 +842,106 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn scalar_constants() -> Result<(), Box<dyn Error>> {
+        let mut rng = thread_rng();
+
+        for params in [
+            BfvParameters::default_arc(1, 16),
+            BfvParameters::default_arc(6, 16),
+        ] {
+            let t = &params.plaintext;
+            let sk = SecretKey::random(&params, &mut rng);
+            for _ in 0..20 {
+                let a = t.random_vec(params.degree(), &mut rng);
+                let k = rng.next_u64();
+                let k_signed = -((rng.next_u64() >> 2) as i64);
+                let k_t = t.reduce(k);
+                let k_signed_t = t.reduce_i64(k_signed);
+
+                for level in [0, params.max_level()] {
+                    for encoding in [
+                        Encoding::poly_at_level(level),
+                        Encoding::simd_at_level(level),
+                    ] {
+                        // Constants only modify the constant coefficient with the polynomial
+                        // encoding, and every slot with the SIMD encoding.
+                        let n = if encoding.encoding == EncodingEnum::Poly {
+                            1
+                        } else {
+                            params.degree()
+                        };
+
+                        let pt_a = Plaintext::try_encode(&a, encoding.clone(), &params)?;
+                        let ct_a: Ciphertext = sk.try_encrypt(&pt_a, &mut rng)?;
+                        assert_eq!(ct_a.level, level);
+
+                        let mut expected = a.clone();
+                        expected[..n]
+                            .iter_mut()
+                            .for_each(|ai| *ai = t.add(*ai, k_t));
+                        let mut ct = &ct_a + k;
+                        assert_eq!(
+                            Vec::<u64>::try_decode(&sk.try_decrypt(&ct)?, encoding.clone())?,
+                            expected
+                        );
+                        ct -= k;
+                        assert_eq!(
+                            Vec::<u64>::try_decode(&sk.try_decrypt(&ct)?, encoding.clone())?,
+                            a
+                        );
+
+                        let mut expected = a.clone();
+                        expected[..n]
+                            .iter_mut()
+                            .for_each(|ai| *ai = t.sub(*ai, k_signed_t));
+                        let mut ct = &ct_a - k_signed;
+                        assert_eq!(
+                            Vec::<u64>::try_decode(&sk.try_decrypt(&ct)?, encoding.clone())?,
+                            expected
+                        );
+                        ct += k_signed;
+                        assert_eq!(
+                            Vec::<u64>::try_decode(&sk.try_decrypt(&ct)?, encoding.clone())?,
+                            a
+                        );
+
+                        let mut expected = a.clone();
+                        t.scalar_mul_vec(&mut expected, k_t);
+                        let mut ct = &ct_a * k;
+                        assert_eq!(
+                            Vec::<u64>::try_decode(&sk.try_decrypt(&ct)?, encoding.clone())?,
+                            expected
+                        );
+                        t.scalar_mul_vec(&mut expected, k_signed_t);
+                        ct *= k_signed;
+                        assert_eq!(
+                            Vec::<u64>::try_decode(&sk.try_decrypt(&ct)?, encoding.clone())?,
+                            expected
+                        );
+
+                        let mut expected = a.clone();
+                        t.scalar_mul_vec(&mut expected, 3);
+                        assert_eq!(
+                            Vec::<u64>::try_decode(
+                                &sk.try_decrypt(&(&ct_a * 3u64))?,
+                                encoding.clone()
+                            )?,
+                            expected
+                        );
+
+                        // Scalars are multiplied by their centered representative.
+                        let ct = &ct_a * -1i64;
+                        assert_eq!(ct, -&ct_a);
+                        assert_eq!(ct.noise_estimate(), ct_a.noise_estimate());
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+
     #[test]
     fn mul() -> Result<(), Box<dyn Error>> {
         let mut rng = thread_rng();
@@ -8,10 +8,18 @@ use fhe_math::{
 use num_bigint::BigUint;
 
 use crate::{
-    bfv::{keys::RelinearizationKey, BfvParameters, Ciphertext},
+    bfv::{
+        keys::RelinearizationKey,
+        noise,
+        profiler::{Profiler, Stopwatch},
+        BfvParameters, Ciphertext, MultiplicationStrategy, OpScratch,
+    },
     Error, Result,
 };
 
+#[cfg(feature = "noise-oracle")]
+use crate::bfv::NoiseOracle;
+
 /// Multiplicator that implements a strategy for multiplying. In particular, the
 /// following information can be specified:
 /// - Whether `lhs` must be scaled;
@@ -19,6 +27,9 @@ use crate::{
 /// - The basis at which the multiplication will occur;
 /// - The scaling factor after multiplication;
 /// - Whether relinearization should be used.
+///
+/// The default multiplicator follows the [`MultiplicationStrategy`] of the
+/// parameters, while custom multiplicators always use the HPS strategy.
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Multiplicator {
     par: Arc<BfvParameters>,
@@ -30,6 +41,15 @@ pub struct Multiplicator {
     rk: Option<RelinearizationKey>,
     mod_switch: bool,
     level: usize,
+    strategy: MultiplicationStrategy,
+    // Whether the noise estimates can be propagated, which is only the case
+    // with the default scaling factors.
+    tracks_noise: bool,
+    // Whether to return an error when the noise budget is exceeded.
+    checks_noise_budget: bool,
+    #[cfg(feature = "noise-oracle")]
+    oracle: Option<NoiseOracle>,
+    profiler: Option<Profiler>,
 }
 
 impl Multiplicator {
@@ -81,7 +101,7 @@ impl Multiplicator {
         par: &Arc<BfvParameters>,
     ) -> Result<Self> {
         let base_ctx = par.ctx_at_level(level)?;
-        let mul_ctx = Arc::new(Context::new(extended_basis, par.degree())?);
+        let mul_ctx = Context::new_arc(extended_basis, par.degree())?;
         let extender_lhs = Scaler::new(base_ctx, &mul_ctx, lhs_scaling_factor)?;
         let extender_rhs = Scaler::new(base_ctx, &mul_ctx, rhs_scaling_factor)?;
         let down_scaler = Scaler::new(&mul_ctx, base_ctx, post_mul_scaling_factor)?;
@@ -95,6 +115,12 @@ impl Multiplicator {
             rk: None,
             mod_switch: false,
             level,
+            strategy: MultiplicationStrategy::Hps,
+            tracks_noise: false,
+            checks_noise_budget: false,
+            #[cfg(feature = "noise-oracle")]
+            oracle: None,
+            profiler: None,
         })
     }
 
@@ -129,6 +155,8 @@ impl Multiplicator {
             &rk.ksk.par,
         )?;
 
+        multiplicator.strategy = rk.ksk.par.multiplication_strategy();
+        multiplicator.tracks_noise = true;
         multiplicator.enable_relinearization(rk)?;
         Ok(multiplicator)
     }
@@ -137,9 +165,10 @@ impl Multiplicator {
     pub fn enable_relinearization(&mut self, rk: &RelinearizationKey) -> Result<()> {
         let rk_ctx = self.par.ctx_at_level(rk.ksk.ciphertext_level)?;
         if rk_ctx != &self.base_ctx {
-            return Err(Error::DefaultError(
-                "Invalid relinearization key context".to_string(),
-            ));
+            return Err(Error::LevelMismatch {
+                expected: self.level,
+                found: rk.ksk.ciphertext_level,
+            });
         }
         self.rk = Some(rk.clone());
         Ok(())
@@ -149,7 +178,7 @@ impl Multiplicator {
     /// applicable).
     pub fn enable_mod_switching(&mut self) -> Result<()> {
         if self.par.ctx_at_level(self.par.max_level())? == &self.base_ctx {
-            Err(Error::DefaultError(
+            Err(Error::UnsupportedOperation(
                 "Cannot modulo switch as this is already the last level".to_string(),
             ))
         } else {
@@ -158,50 +187,132 @@ impl Multiplicator {
         }
     }
 
-    /// Multiply two ciphertexts using the defined multiplication strategy.
-    pub fn multiply(&self, lhs: &Ciphertext, rhs: &Ciphertext) -> Result<Ciphertext> {
-        if lhs.par != self.par || rhs.par != self.par {
-            return Err(Error::DefaultError(
-                "Ciphertexts do not have the same parameters".to_string(),
-            ));
-        }
-        if lhs.level != self.level || rhs.level != self.level {
-            return Err(Error::DefaultError(
-                "Ciphertexts are not at expected level".to_string(),
-            ));
-        }
-        if lhs.c.len() != 2 || rhs.c.len() != 2 {
-            return Err(Error::DefaultError(
-                "Multiplication can only be performed on ciphertexts of size 2".to_string(),
-            ));
+    /// Enable the check of the noise budget after multiplication, so that
+    /// [`Multiplicator::multiply`] returns [`Error::NoiseBudgetExceeded`] when
+    /// the heuristic estimate of the noise of the product exceeds its noise
+    /// budget.
+    ///
+    /// The estimate is pessimistic, so valid multiplications may be rejected
+    /// close to the end of the noise budget.
+    pub fn enable_noise_budget_check(&mut self) {
+        self.checks_noise_budget = true
+    }
+
+    /// Attaches a [`NoiseOracle`] which measures the noise of the ciphertexts
+    /// after every multiplication.
+    #[cfg(feature = "noise-oracle")]
+    pub fn set_noise_oracle(&mut self, oracle: &NoiseOracle) {
+        self.oracle = Some(oracle.clone())
+    }
+
+    /// Attaches a [`Profiler`] which records the steps of every
+    /// multiplication.
+    pub fn set_profiler(&mut self, profiler: &Profiler) {
+        self.profiler = Some(profiler.clone())
+    }
+
+    /// Measures the noise of a ciphertext resulting from an operation with the
+    /// noise oracle, if any.
+    #[inline]
+    fn observe(&self, _operation: &'static str, _ct: &Ciphertext) {
+        #[cfg(feature = "noise-oracle")]
+        if let Some(oracle) = self.oracle.as_ref() {
+            oracle.observe(_operation, _ct)
         }
+    }
 
-        // Extend
-        let c00 = lhs.c[0].scale(&self.extender_lhs)?;
-        let c01 = lhs.c[1].scale(&self.extender_lhs)?;
-        let c10 = rhs.c[0].scale(&self.extender_rhs)?;
-        let c11 = rhs.c[1].scale(&self.extender_rhs)?;
+    /// Multiply two ciphertexts using the defined multiplication strategy.
+    pub fn multiply(&self, lhs: &Ciphertext, rhs: &Ciphertext) -> Result<Ciphertext> {
+        self.multiply_with_scratch(lhs, rhs, &mut OpScratch::default())
+    }
 
-        // Multiply
-        let mut c0 = &c00 * &c10;
-        let mut c1 = &c00 * &c11;
-        c1 += &(&c01 * &c10);
-        let mut c2 = &c01 * &c11;
-        c0.change_representation(Representation::PowerBasis);
-        c1.change_representation(Representation::PowerBasis);
-        c2.change_representation(Representation::PowerBasis);
+    /// Multiply two ciphertexts using the defined multiplication strategy,
+    /// reusing the temporary polynomials of `scratch`.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            level = "debug",
+            skip_all,
+            fields(
+                level = self.level,
+                relinearize = self.rk.is_some(),
+                mod_switch = self.mod_switch,
+            ),
+            err
+        )
+    )]
+    pub fn multiply_with_scratch(
+        &self,
+        lhs: &Ciphertext,
+        rhs: &Ciphertext,
+        scratch: &mut OpScratch,
+    ) -> Result<Ciphertext> {
+        self.par.check_fingerprint(&lhs.par)?;
+        self.par.check_fingerprint(&rhs.par)?;
+        let key_id = lhs.merge_key_id(rhs)?;
+        for ct in [lhs, rhs] {
+            if ct.level != self.level {
+                return Err(Error::LevelMismatch {
+                    expected: self.level,
+                    found: ct.level,
+                });
+            }
+            if ct.c.len() != 2 {
+                return Err(Error::CiphertextSizeMismatch {
+                    expected: 2,
+                    found: ct.c.len(),
+                });
+            }
+        }
 
-        // Scale
-        let c0 = c0.scale(&self.down_scaler)?;
-        let c1 = c1.scale(&self.down_scaler)?;
-        let c2 = c2.scale(&self.down_scaler)?;
+        let mut stopwatch = Stopwatch::start(self.profiler.as_ref());
+        let mut c = match self.strategy {
+            MultiplicationStrategy::Hps => {
+                // Extend
+                let [c00, c01, c10, c11] = &mut scratch.extended;
+                lhs.c[0].scale_into(&self.extender_lhs, c00, &mut scratch.buffer)?;
+                lhs.c[1].scale_into(&self.extender_lhs, c01, &mut scratch.buffer)?;
+                rhs.c[0].scale_into(&self.extender_rhs, c10, &mut scratch.buffer)?;
+                rhs.c[1].scale_into(&self.extender_rhs, c11, &mut scratch.buffer)?;
+
+                // Multiply
+                let [c0, c1, c2] = &mut scratch.products;
+                c0.clone_from(c00);
+                *c0 *= &*c10;
+                c1.clone_from(c00);
+                *c1 *= &*c11;
+                c2.clone_from(c01);
+                *c2 *= &*c11;
+                *c01 *= &*c10;
+                *c1 += &*c01;
+                c0.change_representation(Representation::PowerBasis);
+                c1.change_representation(Representation::PowerBasis);
+                c2.change_representation(Representation::PowerBasis);
+
+                // Scale
+                let c0 = c0.scale(&self.down_scaler)?;
+                let c1 = c1.scale(&self.down_scaler)?;
+                let c2 = c2.scale(&self.down_scaler)?;
+
+                vec![c0, c1, c2]
+            }
+            MultiplicationStrategy::Behz => {
+                self.par.behz_params[self.level].tensor(&lhs.c, &rhs.c)?
+            }
+        };
 
-        let mut c = vec![c0, c1, c2];
+        let mut noise = if self.tracks_noise {
+            noise::mul(&self.par, lhs.noise, rhs.noise)
+        } else {
+            None
+        };
+        stopwatch.lap("multiply", &c, noise);
 
         // Relinearize
         if let Some(rk) = self.rk.as_ref() {
-            #[allow(unused_mut)]
-            let (mut c0r, mut c1r) = rk.relinearizes_poly(&c[2])?;
+            noise = noise::after_key_switch(&rk.ksk, noise);
+            rk.ksk.key_switch_with_scratch(&c[2], scratch)?;
+            let [c0r, c1r] = &mut scratch.key_switched;
 
             if c0r.ctx() != c[0].ctx() {
                 c0r.change_representation(Representation::PowerBasis);
@@ -213,9 +324,10 @@ impl Multiplicator {
                 c[1].change_representation(Representation::Ntt);
             }
 
-            c[0] += &c0r;
-            c[1] += &c1r;
+            c[0] += &*c0r;
+            c[1] += &*c1r;
             c.truncate(2);
+            stopwatch.lap("relinearize", &c, noise);
         }
 
         // We construct a ciphertext, but it may not have the right representation for
@@ -225,29 +337,71 @@ impl Multiplicator {
             seed: None,
             c,
             level: self.level,
+            noise,
+            metadata: None,
+            key_id,
         };
 
         if self.mod_switch {
             c.mod_switch_to_next_level()?;
+            stopwatch.lap("mod_switch", &c.c, c.noise);
         } else {
             c.c.iter_mut()
                 .for_each(|p| p.change_representation(Representation::Ntt));
+            stopwatch.extend("multiply");
         }
 
+        self.observe("multiply", &c);
+        if self.checks_noise_budget {
+            c.check_noise_budget()?;
+        }
         Ok(c)
     }
+
+    /// Evaluates the polynomial with the given `coefficients`, in increasing
+    /// degree, on each slot of `x`. The coefficients of a polynomial equal to
+    /// a function can be computed with the helpers of the
+    /// [`approximation`](crate::bfv::approximation) module.
+    ///
+    /// The powers of `x` are computed with a multiplicative depth of
+    /// `ceil(log2(degree))`. Returns an error if there are fewer than 2
+    /// coefficients.
+    pub fn evaluate_polynomial(&self, x: &Ciphertext, coefficients: &[u64]) -> Result<Ciphertext> {
+        if coefficients.len() < 2 {
+            return Err(Error::TooFewValues(coefficients.len(), 2));
+        }
+
+        // powers[i] encrypts x^(i + 1)
+        let degree = coefficients.len() - 1;
+        let mut powers = Vec::with_capacity(degree);
+        powers.push(x.clone());
+        for k in 2..=degree {
+            let power = self.multiply(&powers[k / 2 - 1], &powers[k - k / 2 - 1])?;
+            powers.push(power);
+        }
+
+        let mut result = &powers[0] * coefficients[1];
+        for (power, coefficient) in powers[1..].iter().zip(&coefficients[2..]) {
+            result += &(power * *coefficient);
+        }
+        result += coefficients[0];
+        Ok(result)
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use crate::bfv::{
-        BfvParameters, Ciphertext, Encoding, Plaintext, RelinearizationKey, SecretKey,
+        BfvParameters, BfvParametersBuilder, Ciphertext, Encoding, MultiplicationStrategy,
+        OpScratch, Plaintext, RelinearizationKey, SecretKey,
     };
     use fhe_math::{
         rns::{RnsContext, ScalingFactor},
         zq::primes::generate_prime,
     };
-    use fhe_traits::{FheDecoder, FheDecrypter, FheEncoder, FheEncrypter};
+    use fhe_traits::{
+        DeserializeParametrized, FheDecoder, FheDecrypter, FheEncoder, FheEncrypter, Serialize,
+    };
     use num_bigint::BigUint;
     use rand::{rngs::OsRng, thread_rng};
     use std::error::Error;
@@ -287,6 +441,79 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn mul_without_ntt() -> Result<(), Box<dyn Error>> {
+        let mut rng = thread_rng();
+        // The ciphertext moduli do not support the NTT of size 16.
+        let par = BfvParametersBuilder::new()
+            .set_degree(16)
+            .set_plaintext_modulus(1153)
+            .set_moduli(&[4611686018427387847, 2305843009213693951])
+            .set_minimum_security(0)
+            .build_arc()?;
+        let values = par.plaintext.random_vec(par.degree(), &mut rng);
+        let mut expected = values.clone();
+        par.plaintext.mul_vec(&mut expected, &values);
+
+        let sk = SecretKey::random(&par, &mut OsRng);
+        let rk = RelinearizationKey::new(&sk, &mut rng)?;
+        let pt = Plaintext::try_encode(&values, Encoding::simd(), &par)?;
+        let ct1: Ciphertext = sk.try_encrypt(&pt, &mut rng)?;
+        let ct2: Ciphertext = sk.try_encrypt(&pt, &mut rng)?;
+
+        let multiplicator = Multiplicator::default(&rk)?;
+        let ct3 = multiplicator.multiply(&ct1, &ct2)?;
+        let pt = sk.try_decrypt(&ct3)?;
+        assert_eq!(Vec::<u64>::try_decode(&pt, Encoding::simd())?, expected);
+        Ok(())
+    }
+
+    #[test]
+    fn mul_with_scratch() -> Result<(), Box<dyn Error>> {
+        let mut rng = thread_rng();
+        // The same workspace is reused across parameters of different sizes.
+        let mut scratch = OpScratch::new();
+        for par in [
+            BfvParameters::default_arc(2, 16),
+            BfvParameters::default_arc(6, 16),
+            BfvParameters::default_arc(3, 16),
+            BfvParameters::default_arc(3, 4),
+        ] {
+            let values = par.plaintext.random_vec(par.degree(), &mut rng);
+            let mut expected = values.clone();
+            par.plaintext.mul_vec(&mut expected, &values);
+
+            let sk = SecretKey::random(&par, &mut OsRng);
+            let rk = RelinearizationKey::new(&sk, &mut rng)?;
+            let pt = Plaintext::try_encode(&values, Encoding::simd(), &par)?;
+            let ct1: Ciphertext = sk.try_encrypt(&pt, &mut rng)?;
+            let ct2: Ciphertext = sk.try_encrypt(&pt, &mut rng)?;
+
+            let mut multiplicator = Multiplicator::default(&rk)?;
+            for _ in 0..2 {
+                let ct3 = multiplicator.multiply_with_scratch(&ct1, &ct2, &mut scratch)?;
+                assert_eq!(ct3, multiplicator.multiply(&ct1, &ct2)?);
+                let pt = sk.try_decrypt(&ct3)?;
+                assert_eq!(Vec::<u64>::try_decode(&pt, Encoding::simd())?, expected);
+            }
+
+            multiplicator.enable_mod_switching()?;
+            let ct3 = multiplicator.multiply_with_scratch(&ct1, &ct2, &mut scratch)?;
+            assert_eq!(ct3, multiplicator.multiply(&ct1, &ct2)?);
+            let pt = sk.try_decrypt(&ct3)?;
+            assert_eq!(Vec::<u64>::try_decode(&pt, Encoding::simd())?, expected);
+
+            let mut ct3 = &ct1 * &ct2;
+            let mut ct3_scratch = ct3.clone();
+            rk.relinearizes(&mut ct3)?;
+            rk.relinearizes_with_scratch(&mut ct3_scratch, &mut scratch)?;
+            assert_eq!(ct3, ct3_scratch);
+            let pt = sk.try_decrypt(&ct3_scratch)?;
+            assert_eq!(Vec::<u64>::try_decode(&pt, Encoding::simd())?, expected);
+        }
+        Ok(())
+    }
+
     #[test]
     fn mul_at_level() -> Result<(), Box<dyn Error>> {
         let mut rng = thread_rng();
@@ -357,6 +584,89 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn mul_behz_matches_hps() -> Result<(), Box<dyn Error>> {
+        let mut rng = thread_rng();
+        for (plaintext, moduli_sizes) in [
+            (1153, vec![62usize; 3]),
+            (1153, vec![50, 55, 60, 62]),
+            (65537, vec![40, 40, 40, 40, 40]),
+        ] {
+            let mut builder = BfvParametersBuilder::new();
+            builder
+                .set_degree(16)
+                .set_plaintext_modulus(plaintext)
+                .set_moduli_sizes(&moduli_sizes)
+                .set_minimum_security(0);
+            let par_hps = builder.build_arc()?;
+            let par_behz = builder
+                .set_multiplication_strategy(MultiplicationStrategy::Behz)
+                .build_arc()?;
+            assert_eq!(par_hps.moduli(), par_behz.moduli());
+
+            for _ in 0..10 {
+                let v1 = par_hps.plaintext.random_vec(par_hps.degree(), &mut rng);
+                let v2 = par_hps.plaintext.random_vec(par_hps.degree(), &mut rng);
+                let mut expected = v1.clone();
+                par_hps.plaintext.mul_vec(&mut expected, &v2);
+
+                // Use the same secret key and the same input ciphertexts for both
+                // strategies.
+                let sk_hps = SecretKey::random(&par_hps, &mut OsRng);
+                let sk_behz = SecretKey::new(sk_hps.coeffs.to_vec(), &par_behz);
+                let pt1 = Plaintext::try_encode(&v1, Encoding::simd(), &par_hps)?;
+                let pt2 = Plaintext::try_encode(&v2, Encoding::simd(), &par_hps)?;
+                let ct1_hps: Ciphertext = sk_hps.try_encrypt(&pt1, &mut rng)?;
+                let ct2_hps: Ciphertext = sk_hps.try_encrypt(&pt2, &mut rng)?;
+                let ct1_behz = Ciphertext::from_bytes(&ct1_hps.to_bytes(), &par_behz)?;
+                let ct2_behz = Ciphertext::from_bytes(&ct2_hps.to_bytes(), &par_behz)?;
+
+                let rk_hps = RelinearizationKey::new(&sk_hps, &mut rng)?;
+                let rk_behz = RelinearizationKey::new(&sk_behz, &mut rng)?;
+                let mut multiplicator_hps = Multiplicator::default(&rk_hps)?;
+                let mut multiplicator_behz = Multiplicator::default(&rk_behz)?;
+
+                for mod_switching in [false, true] {
+                    if mod_switching {
+                        multiplicator_hps.enable_mod_switching()?;
+                        multiplicator_behz.enable_mod_switching()?;
+                    }
+                    let ct3_hps = multiplicator_hps.multiply(&ct1_hps, &ct2_hps)?;
+                    let ct3_behz = multiplicator_behz.multiply(&ct1_behz, &ct2_behz)?;
+                    assert_eq!(ct3_hps.level, ct3_behz.level);
+                    println!(
+                        "Noise: HPS {}, BEHZ {}",
+                        unsafe { sk_hps.measure_noise(&ct3_hps)? },
+                        unsafe { sk_behz.measure_noise(&ct3_behz)? }
+                    );
+
+                    let pt_hps = sk_hps.try_decrypt(&ct3_hps)?;
+                    let pt_behz = sk_behz.try_decrypt(&ct3_behz)?;
+                    let d_hps = Vec::<u64>::try_decode(&pt_hps, Encoding::simd())?;
+                    let d_behz = Vec::<u64>::try_decode(&pt_behz, Encoding::simd())?;
+                    assert_eq!(d_hps, expected);
+                    assert_eq!(d_behz, d_hps);
+                }
+
+                // Without relinearization, the operator must agree as well.
+                let ct3_behz = &ct1_behz * &ct2_behz;
+                assert_eq!(ct3_behz.c.len(), 3);
+                let pt = sk_behz.try_decrypt(&ct3_behz)?;
+                assert_eq!(Vec::<u64>::try_decode(&pt, Encoding::simd())?, expected);
+
+                // The errors of the BEHZ multiplication are returned.
+                let mut ct4_behz = ct2_behz.clone();
+                ct4_behz.mod_switch_to_next_level()?;
+                assert!(matches!(
+                    ct1_behz.try_mul(&ct4_behz),
+                    Err(crate::Error::LevelMismatch { .. })
+                ));
+                assert!(ct1_behz.try_mul(&Ciphertext::zero(&par_behz)).is_err());
+            }
+        }
+        Ok(())
+    }
+
     #[test]
     fn different_mul_strategy() -> Result<(), Box<dyn Error>> {
         // Implement the second multiplication strategy from <https://eprint.iacr.org/2021/204>
@@ -407,4 +717,44 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn noise_budget_exceeded() -> Result<(), Box<dyn Error>> {
+        let mut rng = thread_rng();
+        let par = BfvParameters::default_arc(2, 16);
+        let sk = SecretKey::random(&par, &mut rng);
+        let rk = RelinearizationKey::new(&sk, &mut rng)?;
+        let pt = Plaintext::try_encode(&[2u64], Encoding::poly(), &par)?;
+        let ct: Ciphertext = sk.try_encrypt(&pt, &mut rng)?;
+
+        // Square until the noise budget is exhausted.
+        let mut multiplicator = Multiplicator::default(&rk)?;
+        multiplicator.enable_noise_budget_check();
+        let mut ct2 = ct.clone();
+        let mut error = None;
+        for _ in 0..10 {
+            match multiplicator.multiply(&ct2, &ct2) {
+                Ok(product) => ct2 = product,
+                Err(e) => {
+                    error = Some(e);
+                    break;
+                }
+            }
+        }
+        assert!(matches!(
+            error,
+            Some(crate::Error::NoiseBudgetExceeded { .. })
+        ));
+        assert!(ct2.check_noise_budget().is_ok());
+        assert!(ct2.try_mul(&ct2).is_err());
+
+        // The check is disabled by default.
+        let multiplicator = Multiplicator::default(&rk)?;
+        let ct3 = multiplicator.multiply(&ct2, &ct2)?;
+        assert!(matches!(
+            ct3.check_noise_budget(),
+            Err(crate::Error::NoiseBudgetExceeded { .. })
+        ));
+        Ok(())
+    }
 }
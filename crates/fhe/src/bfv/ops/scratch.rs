@@ -0,0 +1,35 @@
+//! Reusable workspace for homomorphic operations.
+
+use fhe_math::rq::Poly;
+use ndarray::Array2;
+
+/// Workspace holding the temporary polynomials of the multiplication and of
+/// the key switching.
+///
+/// An [`OpScratch`] is typically allocated once per thread and passed to
+/// [`Multiplicator::multiply_with_scratch`](crate::bfv::Multiplicator::multiply_with_scratch)
+/// or [`RelinearizationKey::relinearizes_with_scratch`](crate::bfv::RelinearizationKey::relinearizes_with_scratch):
+/// the allocations of the temporary polynomials are then reused across
+/// operations instead of being performed at every call. A workspace can be
+/// used with any parameters; it adapts its buffers on first use.
+#[derive(Debug, Default)]
+pub struct OpScratch {
+    /// The ciphertexts extended to the multiplication basis.
+    pub(crate) extended: [Poly; 4],
+    /// The products of the extended polynomials.
+    pub(crate) products: [Poly; 3],
+    /// Buffer for the coefficients of polynomials converted out of the Ntt
+    /// representation.
+    pub(crate) buffer: Array2<u64>,
+    /// The digits of the key switching decomposition.
+    pub(crate) digits: Vec<Poly>,
+    /// The output of the key switching.
+    pub(crate) key_switched: [Poly; 2],
+}
+
+impl OpScratch {
+    /// Creates an empty workspace.
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
@@ -1,22 +1,47 @@
 //! Create parameters for the BFV encryption scheme
 
-use crate::proto::bfv::Parameters;
+use crate::bfv::{
+    ops::BehzParameters,
+    security::{estimate_security, max_log_q, max_secure_log_q},
+};
+use crate::proto::bfv::{MultiplicationStrategy as MultiplicationStrategyProto, Parameters};
 use crate::{Error, ParametersError, Result};
 use fhe_math::{
     ntt::NttOperator,
     rns::{RnsContext, ScalingFactor},
-    rq::{scaler::Scaler, traits::TryConvertFrom, Context, Poly, Representation},
-    zq::{primes::generate_prime, Modulus},
+    rq::{scaler::Scaler, traits::TryConvertFrom, Context, Poly, Representation, MIN_DEGREE},
+    zq::{
+        primes::{generate_ntt_primes, generate_plaintext_prime, generate_prime},
+        Modulus, MAX_MODULUS_BITS,
+    },
 };
 use fhe_traits::{Deserialize, FheParameters, Serialize};
+use fhe_util::{is_prime, MAX_CBD_VARIANCE};
 use itertools::Itertools;
 use num_bigint::BigUint;
 use num_traits::ToPrimitive;
 use prost::Message;
+use sha2::{Digest, Sha256};
 use std::collections::HashMap;
 use std::fmt::Debug;
 use std::sync::Arc;
 
+/// Strategy used to multiply two ciphertexts in the RNS representation.
+///
+/// The two strategies have different performance and noise growth trade-offs
+/// depending on the shape of the chain of moduli.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum MultiplicationStrategy {
+    /// The multiplication of Halevi, Polyakov and Shoup
+    /// (<https://eprint.iacr.org/2018/117>), with the improvements of
+    /// <https://eprint.iacr.org/2021/204>.
+    #[default]
+    Hps,
+    /// The multiplication of Bajard, Eynard, Hasan and Zucca
+    /// (<https://eprint.iacr.org/2016/510>).
+    Behz,
+}
+
 /// Parameters for the BFV encryption scheme.
 #[derive(PartialEq, Eq)]
 pub struct BfvParameters {
@@ -57,10 +82,24 @@ pub struct BfvParameters {
     /// Plaintext Modulus
     pub(crate) plaintext: Modulus,
 
+    /// Strategy for the multiplications
+    pub(crate) multiplication_strategy: MultiplicationStrategy,
+
     // Parameters for the multiplications
     pub(crate) mul_params: Box<[MultiplicationParameters]>,
 
+    // Parameters for the BEHZ multiplications, if selected
+    pub(crate) behz_params: Box<[BehzParameters]>,
+
+    /// Slot `i` of the SIMD encoding is the coefficient
+    /// `matrix_reps_index_map[i]` of the plaintext polynomial in NTT form.
     pub(crate) matrix_reps_index_map: Box<[usize]>,
+
+    /// The inverse permutation of `matrix_reps_index_map`.
+    pub(crate) matrix_reps_inverse_index_map: Box<[usize]>,
+
+    /// Fingerprint of the parameters
+    fingerprint: u64,
 }
 
 impl Debug for BfvParameters {
@@ -69,6 +108,8 @@ impl Debug for BfvParameters {
             .field("polynomial_degree", &self.polynomial_degree)
             .field("plaintext_modulus", &self.plaintext_modulus)
             .field("moduli", &self.moduli)
+            .field("multiplication_strategy", &self.multiplication_strategy)
+            .field("fingerprint", &format_args!("{:016x}", self.fingerprint))
             // .field("moduli_sizes", &self.moduli_sizes)
             // .field("variance", &self.variance)
             // .field("ctx", &self.ctx)
@@ -108,6 +149,76 @@ impl BfvParameters {
         self.plaintext_modulus
     }
 
+    /// Returns the strategy used to multiply ciphertexts.
+    pub const fn multiplication_strategy(&self) -> MultiplicationStrategy {
+        self.multiplication_strategy
+    }
+
+    /// Returns an estimation of the bits of classical security of these
+    /// parameters, from the degree, the size of the ciphertext modulus and the
+    /// variance of the secret and the error, based on the tables of the
+    /// <https://homomorphicencryption.org> standard.
+    pub fn security_level(&self) -> usize {
+        estimate_security(
+            self.polynomial_degree,
+            self.ctx[0].modulus().bits() as usize,
+            self.variance,
+        )
+    }
+
+    /// Returns a stable fingerprint of these parameters, computed with SHA-256
+    /// over the degree, the ciphertext moduli, the plaintext modulus and the
+    /// variance of the secret and error distributions.
+    ///
+    /// Two parameters with the same fingerprint produce compatible keys and
+    /// ciphertexts, and the fingerprint does not change across versions of
+    /// the library or platforms.
+    pub const fn fingerprint(&self) -> u64 {
+        self.fingerprint
+    }
+
+    /// Returns an error if the `other` parameters do not have the same
+    /// fingerprint as these parameters.
+    pub(crate) fn check_fingerprint(&self, other: &Self) -> Result<()> {
+        if self.fingerprint != other.fingerprint {
+            Err(Error::ParameterMismatch {
+                expected: self.fingerprint,
+                found: other.fingerprint,
+            })
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Returns an error if the fingerprint found in a serialization is neither
+    /// the fingerprint of these parameters nor 0, which denotes a serialization
+    /// without fingerprint.
+    pub(crate) fn check_serialized_fingerprint(&self, fingerprint: u64) -> Result<()> {
+        if fingerprint != 0 && fingerprint != self.fingerprint {
+            Err(Error::ParameterMismatch {
+                expected: self.fingerprint,
+                found: fingerprint,
+            })
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Computes the fingerprint of parameters.
+    fn compute_fingerprint(degree: usize, moduli: &[u64], plaintext: u64, variance: usize) -> u64 {
+        let mut hasher = Sha256::new();
+        hasher.update(b"fhe.rs BFV parameters");
+        hasher.update((degree as u64).to_le_bytes());
+        hasher.update((moduli.len() as u64).to_le_bytes());
+        for modulus in moduli {
+            hasher.update(modulus.to_le_bytes());
+        }
+        hasher.update(plaintext.to_le_bytes());
+        hasher.update((variance as u64).to_le_bytes());
+        let hash = hasher.finalize();
+        u64::from_le_bytes(hash[..8].try_into().unwrap())
+    }
+
     /// Returns the maximum level allowed by these parameters.
     pub fn max_level(&self) -> usize {
         self.moduli.len() - 1
@@ -115,9 +226,10 @@ impl BfvParameters {
 
     /// Returns the context corresponding to the level.
     pub(crate) fn ctx_at_level(&self, level: usize) -> Result<&Arc<Context>> {
-        self.ctx
-            .get(level)
-            .ok_or_else(|| Error::DefaultError("No context".to_string()))
+        self.ctx.get(level).ok_or(Error::InvalidLevel {
+            level,
+            max_level: self.max_level(),
+        })
     }
 
     /// Returns the level of a given context
@@ -184,14 +296,10 @@ impl BfvParameters {
 
         for n in n_and_qs.keys().sorted() {
             let moduli = n_and_qs.get(n).unwrap();
-            if let Some(plaintext_modulus) = generate_prime(
-                plaintext_nbits,
-                2 * *n as u64,
-                u64::MAX >> (64 - plaintext_nbits),
-            ) {
+            if let Some(plaintext_modulus) = generate_plaintext_prime(plaintext_nbits, *n) {
                 params.push(
                     BfvParametersBuilder::new()
-                        .set_degree(*n as usize)
+                        .set_degree(*n)
                         .set_plaintext_modulus(plaintext_modulus)
                         .set_moduli(moduli)
                         .build_arc()
@@ -203,20 +311,87 @@ impl BfvParameters {
         params
     }
 
+    /// Returns a [`BfvParametersBuilder`] preset with a degree and a chain of
+    /// ciphertext moduli providing 128 bits of classical security according
+    /// to the tables of the <https://homomorphicencryption.org> standard.
+    ///
+    /// The largest ciphertext modulus allowed by the standard for this degree
+    /// is split into at least `depth_hint + 1` moduli of about the same size,
+    /// so that a ciphertext can be switched to a smaller modulus after each of
+    /// `depth_hint` multiplications. Only the plaintext modulus remains to be
+    /// set before building the parameters:
+    ///
+    /// ```
+    /// # use fhe::bfv::BfvParameters;
+    /// let parameters = BfvParameters::standard_128_classic(4096, 2)?
+    ///     .set_plaintext_modulus(1032193)
+    ///     .build_arc()?;
+    /// assert!(parameters.security_level() >= 128);
+    /// # Ok::<(), fhe::Error>(())
+    /// ```
+    ///
+    /// Returns an error if the standard does not define parameters for this
+    /// degree, which must be a power of two between 1024 and 32768, or if there
+    /// are not enough primes of the size of the moduli for `depth_hint`.
+    pub fn standard_128_classic(degree: usize, depth_hint: usize) -> Result<BfvParametersBuilder> {
+        Self::standard(128, degree, depth_hint)
+    }
+
+    /// Returns a [`BfvParametersBuilder`] preset with a degree and a chain of
+    /// ciphertext moduli providing 192 bits of classical security. See
+    /// [`BfvParameters::standard_128_classic`].
+    pub fn standard_192_classic(degree: usize, depth_hint: usize) -> Result<BfvParametersBuilder> {
+        Self::standard(192, degree, depth_hint)
+    }
+
+    /// Returns a [`BfvParametersBuilder`] preset with a degree and a chain of
+    /// ciphertext moduli providing 256 bits of classical security. See
+    /// [`BfvParameters::standard_128_classic`].
+    pub fn standard_256_classic(degree: usize, depth_hint: usize) -> Result<BfvParametersBuilder> {
+        Self::standard(256, degree, depth_hint)
+    }
+
+    /// Preset of the standard for `security` bits of classical security.
+    fn standard(security: usize, degree: usize, depth_hint: usize) -> Result<BfvParametersBuilder> {
+        let log_q = max_log_q(degree, security).ok_or(Error::ParametersError(
+            ParametersError::NoStandardParameters(degree, security),
+        ))?;
+
+        // Split the modulus in moduli of about the same size.
+        let num_moduli = (depth_hint + 1).max(log_q.div_ceil(MAX_MODULUS_BITS));
+        let moduli_sizes = (0..num_moduli)
+            .map(|i| log_q / num_moduli + usize::from(i < log_q % num_moduli))
+            .collect_vec();
+        let moduli = BfvParametersBuilder::generate_moduli(&moduli_sizes, degree)?;
+
+        let mut builder = BfvParametersBuilder::new();
+        builder
+            .set_degree(degree)
+            .set_moduli(&moduli)
+            .set_minimum_security(security);
+        Ok(builder)
+    }
+
+    /// Returns default parameters for tests.
     #[cfg(test)]
     pub fn default_arc(num_moduli: usize, degree: usize) -> Arc<Self> {
-        if !degree.is_power_of_two() || degree < 8 {
+        if !degree.is_power_of_two() || degree < MIN_DEGREE {
             panic!("Invalid degree");
         }
         BfvParametersBuilder::new()
             .set_degree(degree)
             .set_plaintext_modulus(1153)
             .set_moduli_sizes(&vec![62usize; num_moduli])
+            .set_minimum_security(0)
             .build_arc()
             .unwrap()
     }
 }
 
+/// Default minimum bits of security of the parameters built by a
+/// [`BfvParametersBuilder`].
+const DEFAULT_MINIMUM_SECURITY: usize = 128;
+
 /// Builder for parameters for the Bfv encryption scheme.
 #[derive(Debug)]
 pub struct BfvParametersBuilder {
@@ -225,6 +400,10 @@ pub struct BfvParametersBuilder {
     variance: usize,
     ciphertext_moduli: Vec<u64>,
     ciphertext_moduli_sizes: Vec<usize>,
+    multiplication_strategy: MultiplicationStrategy,
+    minimum_security: usize,
+    simd_required: bool,
+    drop_order: Vec<usize>,
 }
 
 impl BfvParametersBuilder {
@@ -237,11 +416,15 @@ impl BfvParametersBuilder {
             variance: 10,
             ciphertext_moduli: Default::default(),
             ciphertext_moduli_sizes: Default::default(),
+            multiplication_strategy: Default::default(),
+            minimum_security: DEFAULT_MINIMUM_SECURITY,
+            simd_required: false,
+            drop_order: Default::default(),
         }
     }
 
     /// Sets the polynomial degree. Returns an error if the degree is not
-    /// a power of two larger or equal to 8.
+    /// a power of two larger or equal to [`MIN_DEGREE`].
     pub fn set_degree(&mut self, degree: usize) -> &mut Self {
         self.degree = degree;
         self
@@ -254,7 +437,8 @@ impl BfvParametersBuilder {
         self
     }
 
-    /// Sets the sizes of the ciphertext moduli.
+    /// Sets the sizes of the ciphertext moduli, between 10 and
+    /// [`MAX_MODULUS_BITS`] bits.
     /// Only one of `set_moduli_sizes` and `set_moduli`
     /// can be specified.
     pub fn set_moduli_sizes(&mut self, sizes: &[usize]) -> &mut Self {
@@ -270,38 +454,95 @@ impl BfvParametersBuilder {
         self
     }
 
-    /// Sets the error variance. Returns an error if the variance is not between
-    /// one and sixteen.
+    /// Sets the variance of the secret and error distributions. Building the
+    /// parameters returns an error if the variance is not between one and
+    /// [`MAX_CBD_VARIANCE`](fhe_util::MAX_CBD_VARIANCE).
     pub fn set_variance(&mut self, variance: usize) -> &mut Self {
         self.variance = variance;
         self
     }
 
+    /// Sets the strategy used to multiply ciphertexts. Defaults to
+    /// [`MultiplicationStrategy::Hps`].
+    pub fn set_multiplication_strategy(&mut self, strategy: MultiplicationStrategy) -> &mut Self {
+        self.multiplication_strategy = strategy;
+        self
+    }
+
+    /// Sets the minimum bits of security, as estimated by
+    /// [`BfvParameters::security_level`], below which the parameters are
+    /// refused. Defaults to 128 bits; setting it to 0 allows insecure
+    /// parameters, for example for tests.
+    pub fn set_minimum_security(&mut self, bits: usize) -> &mut Self {
+        self.minimum_security = bits;
+        self
+    }
+
+    /// Sets whether the plaintext modulus must support the SIMD encoding, in
+    /// which case building the parameters fails if the plaintext modulus is
+    /// not a prime congruent to 1 modulo twice the degree. Defaults to false.
+    pub fn set_simd_required(&mut self, required: bool) -> &mut Self {
+        self.simd_required = required;
+        self
+    }
+
+    /// Sets the order in which the ciphertext moduli are dropped when switching
+    /// to the next levels, as indices in the list of moduli, or of moduli
+    /// sizes: the modulus `order[0]` is dropped first, then `order[1]`, etc.
+    /// The moduli which are not listed are dropped afterwards, starting from
+    /// the last one. Defaults to an empty order, so that the moduli are
+    /// dropped starting from the last one.
+    ///
+    /// Since the contexts of the successive levels drop the last modulus, the
+    /// moduli of the parameters are reordered accordingly, so that
+    /// [`BfvParameters::moduli`] lists the moduli not in the order, followed
+    /// by the moduli of the order from the last dropped to the first dropped.
+    /// Building the parameters returns an error if an index is out of range or
+    /// repeated.
+    pub fn set_drop_order(&mut self, order: &[usize]) -> &mut Self {
+        self.drop_order = order.to_owned();
+        self
+    }
+
+    /// Reorders the moduli so that the successive levels drop the moduli in
+    /// the order `drop_order`.
+    fn apply_drop_order(moduli: Vec<u64>, drop_order: &[usize]) -> Result<Vec<u64>> {
+        for (i, j) in drop_order.iter().enumerate() {
+            if *j >= moduli.len() || drop_order[..i].contains(j) {
+                return Err(Error::ParametersError(ParametersError::InvalidDropOrder(
+                    *j,
+                    moduli.len(),
+                )));
+            }
+        }
+        let mut reordered = moduli
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| !drop_order.contains(i))
+            .map(|(_, m)| *m)
+            .collect_vec();
+        reordered.extend(drop_order.iter().rev().map(|j| moduli[*j]));
+        Ok(reordered)
+    }
+
     /// Generate ciphertext moduli with the specified sizes
     fn generate_moduli(moduli_sizes: &[usize], degree: usize) -> Result<Vec<u64>> {
         let mut moduli = vec![];
-        for size in moduli_sizes {
-            if *size > 62 || *size < 10 {
+        for (i, size) in moduli_sizes.iter().enumerate() {
+            if *size > MAX_MODULUS_BITS || *size < 10 {
                 return Err(Error::ParametersError(ParametersError::InvalidModulusSize(
-                    *size, 10, 62,
+                    *size,
+                    10,
+                    MAX_MODULUS_BITS,
                 )));
             }
 
-            let mut upper_bound = 1 << size;
-            loop {
-                if let Some(prime) = generate_prime(*size, 2 * degree as u64, upper_bound) {
-                    if !moduli.contains(&prime) {
-                        moduli.push(prime);
-                        break;
-                    } else {
-                        upper_bound = prime;
-                    }
-                } else {
-                    return Err(Error::ParametersError(ParametersError::NotEnoughPrimes(
-                        *size, degree,
-                    )));
-                }
-            }
+            // The moduli of the same size are the successive primes of this size.
+            let count = moduli_sizes[..=i].iter().filter(|s| *s == size).count();
+            let primes = generate_ntt_primes(*size, degree, count).ok_or(
+                Error::ParametersError(ParametersError::NotEnoughPrimes(*size, degree)),
+            )?;
+            moduli.push(primes[count - 1]);
         }
 
         Ok(moduli)
@@ -315,12 +556,20 @@ impl BfvParametersBuilder {
     /// Build a new `BfvParameters`.
     pub fn build(&self) -> Result<BfvParameters> {
         // Check that the degree is a power of 2 (and large enough).
-        if self.degree < 8 || !self.degree.is_power_of_two() {
+        if self.degree < MIN_DEGREE || !self.degree.is_power_of_two() {
             return Err(Error::ParametersError(ParametersError::InvalidDegree(
                 self.degree,
             )));
         }
 
+        // Check that the secret and errors can be sampled with this variance.
+        if !(1..=MAX_CBD_VARIANCE).contains(&self.variance) {
+            return Err(Error::ParametersError(ParametersError::InvalidVariance(
+                self.variance,
+                MAX_CBD_VARIANCE,
+            )));
+        }
+
         // This checks that the plaintext modulus is valid.
         // TODO: Check bound on the plaintext modulus.
         let plaintext_modulus = Modulus::new(self.plaintext).map_err(|e| {
@@ -346,6 +595,52 @@ impl BfvParametersBuilder {
         if !self.ciphertext_moduli_sizes.is_empty() {
             moduli = Self::generate_moduli(&self.ciphertext_moduli_sizes, self.degree)?
         }
+        let moduli = Self::apply_drop_order(moduli, &self.drop_order)?;
+
+        // Check that the moduli are distinct primes, and that the plaintext
+        // modulus is invertible modulo each of them.
+        for (i, modulus) in moduli.iter().enumerate() {
+            if moduli[..i].contains(modulus) {
+                return Err(Error::ParametersError(
+                    ParametersError::DuplicateCiphertextModulus(*modulus),
+                ));
+            }
+            Modulus::new(*modulus)?;
+            if !is_prime(*modulus) {
+                let suggestion = nearest_ntt_prime(*modulus, self.degree, &moduli).ok_or(
+                    Error::ParametersError(ParametersError::NotEnoughPrimes(
+                        64 - modulus.leading_zeros() as usize,
+                        self.degree,
+                    )),
+                )?;
+                return Err(Error::ParametersError(
+                    ParametersError::InvalidCiphertextModulus(*modulus, suggestion),
+                ));
+            }
+            if self.plaintext % modulus == 0 {
+                return Err(Error::ParametersError(
+                    ParametersError::PlaintextNotCoprime(self.plaintext, *modulus),
+                ));
+            }
+        }
+
+        // Check that the plaintext modulus supports the SIMD encoding if needed.
+        let op = NttOperator::new(&plaintext_modulus, self.degree);
+        if self.simd_required && op.is_none() {
+            let suggestion = nearest_ntt_prime(self.plaintext, self.degree, &[]).ok_or(
+                Error::ParametersError(ParametersError::InvalidPlaintext(format!(
+                    "No plaintext modulus supports the SIMD encoding for degree {}",
+                    self.degree
+                ))),
+            )?;
+            return Err(Error::ParametersError(
+                ParametersError::PlaintextNotSimdFriendly(
+                    self.plaintext,
+                    2 * self.degree as u64,
+                    suggestion,
+                ),
+            ));
+        }
 
         // Recomputes the moduli sizes
         let moduli_sizes = moduli
@@ -353,18 +648,33 @@ impl BfvParametersBuilder {
             .map(|m| 64 - m.leading_zeros() as usize)
             .collect_vec();
 
-        // Create n+1 moduli of 62 bits for multiplication.
+        // Check that the parameters are secure enough.
+        let log_q = moduli
+            .iter()
+            .fold(BigUint::from(1u64), |acc, m| acc * m)
+            .bits() as usize;
+        let security = estimate_security(self.degree, log_q, self.variance);
+        if security < self.minimum_security {
+            return Err(Error::ParametersError(
+                ParametersError::InsufficientSecurity(
+                    security,
+                    self.minimum_security,
+                    max_secure_log_q(self.degree, self.minimum_security, self.variance),
+                ),
+            ));
+        }
+
+        // Create n+1 moduli of the maximum size for multiplication.
         let mut extended_basis = Vec::with_capacity(moduli.len() + 1);
-        let mut upper_bound = 1 << 62;
+        let mut upper_bound = 1 << MAX_MODULUS_BITS;
         while extended_basis.len() != moduli.len() + 1 {
-            upper_bound = generate_prime(62, 2 * self.degree as u64, upper_bound).unwrap();
+            upper_bound =
+                generate_prime(MAX_MODULUS_BITS, 2 * self.degree as u64, upper_bound).unwrap();
             if !extended_basis.contains(&upper_bound) && !moduli.contains(&upper_bound) {
                 extended_basis.push(upper_bound)
             }
         }
 
-        let op = NttOperator::new(&plaintext_modulus, self.degree);
-
         let plaintext_ctx = Context::new_arc(&moduli[..1], self.degree)?;
 
         let mut delta_rests = vec![];
@@ -378,6 +688,7 @@ impl BfvParametersBuilder {
         let mut q_mod_t = Vec::with_capacity(moduli.len());
         let mut scalers = Vec::with_capacity(moduli.len());
         let mut mul_params = Vec::with_capacity(moduli.len());
+        let mut behz_params = vec![];
         for i in 0..moduli.len() {
             let rns = RnsContext::new(&moduli[..moduli.len() - i])?;
             let ctx_i = Context::new_arc(&moduli[..moduli.len() - i], self.degree)?;
@@ -417,6 +728,10 @@ impl BfvParametersBuilder {
                 ScalingFactor::new(&BigUint::from(plaintext_modulus.modulus()), ctx_i.modulus()),
             )?);
 
+            if self.multiplication_strategy == MultiplicationStrategy::Behz {
+                behz_params.push(BehzParameters::new(&ctx_i, self.degree, self.plaintext)?);
+            }
+
             ctx.push(ctx_i);
         }
 
@@ -436,6 +751,13 @@ impl BfvParametersBuilder {
             pos *= gen;
             pos &= m - 1;
         }
+        let mut matrix_reps_inverse_index_map = vec![0usize; self.degree];
+        for (i, j) in matrix_reps_index_map.iter().enumerate() {
+            matrix_reps_inverse_index_map[*j] = i;
+        }
+
+        let fingerprint =
+            BfvParameters::compute_fingerprint(self.degree, &moduli, self.plaintext, self.variance);
 
         Ok(BfvParameters {
             polynomial_degree: self.degree,
@@ -449,12 +771,37 @@ impl BfvParametersBuilder {
             q_mod_t: q_mod_t.into_boxed_slice(),
             scalers: scalers.into_boxed_slice(),
             plaintext: plaintext_modulus,
+            multiplication_strategy: self.multiplication_strategy,
             mul_params: mul_params.into_boxed_slice(),
+            behz_params: behz_params.into_boxed_slice(),
             matrix_reps_index_map: matrix_reps_index_map.into_boxed_slice(),
+            matrix_reps_inverse_index_map: matrix_reps_inverse_index_map.into_boxed_slice(),
+            fingerprint,
         })
     }
 }
 
+/// Returns the prime congruent to 1 modulo `2 * degree` nearest to `value`,
+/// which is not in `exclude`, or None if there is no such prime of at most
+/// [`MAX_MODULUS_BITS`] bits.
+fn nearest_ntt_prime(value: u64, degree: usize, exclude: &[u64]) -> Option<u64> {
+    let modulo = 2 * degree as u64;
+    let is_candidate = |p: &u64| is_prime(*p) && !exclude.contains(p);
+    let below = (1..=value / modulo)
+        .rev()
+        .map(|k| k * modulo + 1)
+        .find(is_candidate);
+    let above = (value / modulo + 1..)
+        .map(|k| k * modulo + 1)
+        .take_while(|p| *p >> MAX_MODULUS_BITS == 0)
+        .find(is_candidate);
+    match (below, above) {
+        (Some(b), Some(a)) if value.abs_diff(a) < value.abs_diff(b) => Some(a),
+        (Some(b), _) => Some(b),
+        (None, a) => a,
+    }
+}
+
 impl Serialize for BfvParameters {
     fn to_bytes(&self) -> Vec<u8> {
         Parameters {
@@ -462,22 +809,52 @@ impl Serialize for BfvParameters {
             plaintext: self.plaintext_modulus,
             moduli: self.moduli.to_vec(),
             variance: self.variance as u32,
+            multiplication_strategy: match self.multiplication_strategy {
+                MultiplicationStrategy::Hps => MultiplicationStrategyProto::Hps,
+                MultiplicationStrategy::Behz => MultiplicationStrategyProto::Behz,
+            } as i32,
         }
         .encode_to_vec()
     }
 }
 
 impl Deserialize for BfvParameters {
+    /// Deserializes parameters, which are refused if they provide less than
+    /// the default minimum of 128 bits of security, as the parameters built by
+    /// a [`BfvParametersBuilder`]; see
+    /// [`BfvParameters::try_deserialize_insecure`] for insecure parameters.
     fn try_deserialize(bytes: &[u8]) -> Result<Self> {
+        Self::try_deserialize_with_minimum_security(bytes, DEFAULT_MINIMUM_SECURITY)
+    }
+    type Error = Error;
+}
+
+impl BfvParameters {
+    /// Deserializes parameters without checking their security.
+    ///
+    /// The parameters must come from a trusted source, since parameters
+    /// chosen by an attacker may provide no security at all.
+    pub fn try_deserialize_insecure(bytes: &[u8]) -> Result<Self> {
+        Self::try_deserialize_with_minimum_security(bytes, 0)
+    }
+
+    fn try_deserialize_with_minimum_security(bytes: &[u8], bits: usize) -> Result<Self> {
         let params: Parameters = Message::decode(bytes).map_err(|_| Error::SerializationError)?;
+        let multiplication_strategy =
+            match MultiplicationStrategyProto::try_from(params.multiplication_strategy) {
+                Ok(MultiplicationStrategyProto::Hps) => MultiplicationStrategy::Hps,
+                Ok(MultiplicationStrategyProto::Behz) => MultiplicationStrategy::Behz,
+                Err(_) => return Err(Error::SerializationError),
+            };
         BfvParametersBuilder::new()
             .set_degree(params.degree as usize)
             .set_plaintext_modulus(params.plaintext)
             .set_moduli(&params.moduli)
             .set_variance(params.variance as usize)
+            .set_multiplication_strategy(multiplication_strategy)
+            .set_minimum_security(bits)
             .build()
     }
-    type Error = Error;
 }
 
 /// Multiplication parameters
@@ -507,9 +884,13 @@ impl MultiplicationParameters {
 
 #[cfg(test)]
 mod tests {
-    use super::{BfvParameters, BfvParametersBuilder};
-    use fhe_traits::{Deserialize, Serialize};
-    use std::error::Error;
+    use super::{BfvParameters, BfvParametersBuilder, MultiplicationStrategy};
+    use crate::bfv::{Ciphertext, Encoding, Plaintext, SecretKey};
+    use crate::ParametersError;
+    use fhe_traits::{Deserialize, FheDecrypter, FheEncoder, FheEncrypter, Serialize};
+    use fhe_util::MAX_CBD_VARIANCE;
+    use rand::thread_rng;
+    use std::{error::Error, sync::Arc};
 
     // TODO: To fix when errors handling is fixed.
     // #[test]
@@ -609,12 +990,194 @@ mod tests {
         assert_eq!(params.degree(), 16);
     }
 
+    #[test]
+    fn security() -> Result<(), Box<dyn Error>> {
+        for params in BfvParameters::default_parameters_128(20) {
+            assert!(params.security_level() >= 128);
+        }
+
+        let mut builder = BfvParametersBuilder::new();
+        builder
+            .set_degree(16)
+            .set_plaintext_modulus(1153)
+            .set_moduli_sizes(&[62]);
+        let e = builder.build();
+        assert!(matches!(
+            e.unwrap_err(),
+            crate::Error::ParametersError(ParametersError::InsufficientSecurity(_, 128, 0))
+        ));
+        let params = builder.set_minimum_security(0).build()?;
+        assert!(params.security_level() < 128);
+
+        let params = builder
+            .set_degree(4096)
+            .set_moduli_sizes(&[36, 36, 37])
+            .set_minimum_security(192)
+            .build();
+        assert!(params.is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn validation() -> Result<(), Box<dyn Error>> {
+        let error = |builder: &BfvParametersBuilder| match builder.build() {
+            Err(crate::Error::ParametersError(e)) => Some(e),
+            _ => None,
+        };
+        let mut builder = BfvParametersBuilder::new();
+        builder
+            .set_degree(16)
+            .set_plaintext_modulus(2)
+            .set_minimum_security(0);
+
+        // 1155 = 3 * 5 * 7 * 11, and 1153 and 1217 are the nearest primes
+        // congruent to 1 modulo 32.
+        builder.set_moduli(&[1155]);
+        assert_eq!(
+            error(&builder),
+            Some(ParametersError::InvalidCiphertextModulus(1155, 1153))
+        );
+        builder.set_moduli(&[1153, 1155]);
+        assert_eq!(
+            error(&builder),
+            Some(ParametersError::InvalidCiphertextModulus(1155, 1217))
+        );
+        builder.set_moduli(&[4611686018326724609, 4611686018326724609]);
+        assert_eq!(
+            error(&builder),
+            Some(ParametersError::DuplicateCiphertextModulus(
+                4611686018326724609
+            ))
+        );
+        builder.set_plaintext_modulus(2 * 1153).set_moduli(&[1153]);
+        assert_eq!(
+            error(&builder),
+            Some(ParametersError::PlaintextNotCoprime(2 * 1153, 1153))
+        );
+
+        // 1155 is not prime, and 1123 is prime but not congruent to 1 modulo 32.
+        builder.set_moduli(&[4611686018326724609]);
+        for (plaintext, suggestion) in [(1155, 1153), (1123, 1153), (2, 97)] {
+            builder.set_plaintext_modulus(plaintext);
+            assert!(builder.build().is_ok());
+            builder.set_simd_required(true);
+            assert_eq!(
+                error(&builder),
+                Some(ParametersError::PlaintextNotSimdFriendly(
+                    plaintext, 32, suggestion
+                ))
+            );
+            builder.set_simd_required(false);
+        }
+        builder.set_plaintext_modulus(1153).set_simd_required(true);
+        assert!(builder.build().is_ok());
+
+        // The variance must be supported by the sampler of the errors.
+        for variance in [0, MAX_CBD_VARIANCE + 1] {
+            builder.set_variance(variance);
+            assert_eq!(
+                error(&builder),
+                Some(ParametersError::InvalidVariance(variance, MAX_CBD_VARIANCE))
+            );
+        }
+        assert!(builder.set_variance(100).build().is_ok());
+        builder.set_variance(10);
+
+        // The suggested ciphertext modulus provides the minimum security.
+        builder
+            .set_degree(4096)
+            .set_moduli(&[4611686018326724609, 4611686018309947393])
+            .set_simd_required(false)
+            .set_minimum_security(128);
+        assert_eq!(
+            error(&builder),
+            Some(ParametersError::InsufficientSecurity(112, 128, 109))
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn matrix_reps_index_maps() {
+        for degree in [16, 2048] {
+            let par = BfvParameters::default_arc(1, degree);
+            for (i, j) in par.matrix_reps_index_map.iter().enumerate() {
+                assert_eq!(par.matrix_reps_inverse_index_map[*j], i);
+            }
+        }
+    }
+
+    #[test]
+    fn fingerprint() -> Result<(), Box<dyn Error>> {
+        let mut builder = BfvParametersBuilder::new();
+        builder
+            .set_degree(16)
+            .set_plaintext_modulus(1153)
+            .set_moduli(&[4611686018326724609, 4611686018309947393])
+            .set_minimum_security(0);
+        let params = builder.build()?;
+        // The fingerprint is stable across versions and platforms.
+        assert_eq!(params.fingerprint(), 0x43d888ea46c1b0df);
+        assert_eq!(params.fingerprint(), builder.build()?.fingerprint());
+        assert_eq!(
+            params.fingerprint(),
+            builder
+                .set_multiplication_strategy(MultiplicationStrategy::Behz)
+                .build()?
+                .fingerprint()
+        );
+        assert_eq!(
+            params.fingerprint(),
+            BfvParameters::try_deserialize_insecure(&params.to_bytes())?.fingerprint()
+        );
+
+        for other in [
+            builder.set_plaintext_modulus(1217).build()?,
+            builder
+                .set_plaintext_modulus(1153)
+                .set_variance(2)
+                .build()?,
+            builder
+                .set_variance(10)
+                .set_moduli(&[4611686018309947393, 4611686018326724609])
+                .build()?,
+            builder.set_degree(32).build()?,
+        ] {
+            assert_ne!(params.fingerprint(), other.fingerprint());
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn standard() -> Result<(), Box<dyn Error>> {
+        for (degree, depth_hint) in [(1024, 0), (4096, 2), (8192, 3), (32768, 10)] {
+            for (security, builder) in [
+                (128, BfvParameters::standard_128_classic(degree, depth_hint)),
+                (192, BfvParameters::standard_192_classic(degree, depth_hint)),
+                (256, BfvParameters::standard_256_classic(degree, depth_hint)),
+            ] {
+                let params = builder?.set_plaintext_modulus(1153).build()?;
+                assert_eq!(params.degree(), degree);
+                assert!(params.moduli().len() > depth_hint);
+                assert!(params.security_level() >= security);
+            }
+        }
+
+        assert_eq!(
+            BfvParameters::standard_128_classic(16, 1).unwrap_err(),
+            crate::Error::ParametersError(ParametersError::NoStandardParameters(16, 128))
+        );
+        assert!(BfvParameters::standard_128_classic(1024, 2).is_err());
+        assert!(BfvParameters::standard_256_classic(8192, 5).is_err());
+        Ok(())
+    }
+
     #[test]
     fn ciphertext_moduli() -> Result<(), Box<dyn Error>> {
         let params = BfvParametersBuilder::new()
             .set_degree(16)
             .set_plaintext_modulus(2)
             .set_moduli_sizes(&[62, 62, 62, 61, 60, 11])
+            .set_minimum_security(0)
             .build()?;
         assert_eq!(
             params.moduli.to_vec(),
@@ -639,12 +1202,75 @@ mod tests {
                 1152921504606845473,
                 2017,
             ])
+            .set_minimum_security(0)
             .build()?;
         assert_eq!(params.moduli_sizes.to_vec(), &[62, 62, 62, 61, 60, 11]);
 
         Ok(())
     }
 
+    #[test]
+    fn drop_order() -> Result<(), Box<dyn Error>> {
+        let moduli = [
+            4611686018427387617,
+            4611686018427387329,
+            2305843009213693921,
+            1152921504606845473,
+        ];
+        let params = BfvParametersBuilder::new()
+            .set_degree(16)
+            .set_plaintext_modulus(2)
+            .set_moduli(&moduli)
+            .set_drop_order(&[0, 2])
+            .set_minimum_security(0)
+            .build()?;
+        assert_eq!(
+            params.moduli.to_vec(),
+            &[moduli[1], moduli[3], moduli[2], moduli[0]]
+        );
+        assert_eq!(params.moduli_sizes.to_vec(), &[62, 60, 61, 62]);
+        assert_eq!(
+            params.ctx_at_level(1)?.moduli(),
+            &[moduli[1], moduli[3], moduli[2]]
+        );
+        assert_eq!(params.ctx_at_level(2)?.moduli(), &[moduli[1], moduli[3]]);
+        assert_eq!(params.ctx_at_level(3)?.moduli(), &[moduli[1]]);
+
+        let params = Arc::new(params);
+        let mut rng = thread_rng();
+        let sk = SecretKey::random(&params, &mut rng);
+        let v = params.plaintext.random_vec(params.degree(), &mut rng);
+        let pt = Plaintext::try_encode(&v, Encoding::poly(), &params)?;
+        let mut ct: Ciphertext = sk.try_encrypt(&pt, &mut rng)?;
+        ct.mod_switch_to_last_level()?;
+        assert_eq!(sk.try_decrypt(&ct)?.value, pt.value);
+
+        let params = BfvParametersBuilder::new()
+            .set_degree(16)
+            .set_plaintext_modulus(2)
+            .set_moduli_sizes(&[62, 61, 60])
+            .set_drop_order(&[1, 0, 2])
+            .set_minimum_security(0)
+            .build()?;
+        assert_eq!(params.moduli_sizes.to_vec(), &[60, 62, 61]);
+
+        for order in [&[0, 4][..], &[1, 1]] {
+            let e = BfvParametersBuilder::new()
+                .set_degree(16)
+                .set_plaintext_modulus(2)
+                .set_moduli(&moduli)
+                .set_drop_order(order)
+                .set_minimum_security(0)
+                .build();
+            assert_eq!(
+                e.unwrap_err(),
+                crate::Error::ParametersError(ParametersError::InvalidDropOrder(order[1], 4))
+            );
+        }
+
+        Ok(())
+    }
+
     #[test]
     fn serialize() -> Result<(), Box<dyn Error>> {
         let params = BfvParametersBuilder::new()
@@ -652,9 +1278,32 @@ mod tests {
             .set_plaintext_modulus(2)
             .set_moduli_sizes(&[62, 62, 62, 61, 60, 11])
             .set_variance(4)
+            .set_minimum_security(0)
             .build()?;
         let bytes = params.to_bytes();
-        assert_eq!(BfvParameters::try_deserialize(&bytes)?, params);
+        assert_eq!(BfvParameters::try_deserialize_insecure(&bytes)?, params);
+
+        // The security of the deserialized parameters is checked by default.
+        assert!(BfvParameters::try_deserialize(&bytes).is_err());
+        let secure = BfvParameters::standard_128_classic(4096, 2)?
+            .set_plaintext_modulus(1032193)
+            .build()?;
+        assert_eq!(BfvParameters::try_deserialize(&secure.to_bytes())?, secure);
+
+        let params = BfvParametersBuilder::new()
+            .set_degree(16)
+            .set_plaintext_modulus(2)
+            .set_moduli_sizes(&[62, 62, 62, 61, 60, 11])
+            .set_multiplication_strategy(MultiplicationStrategy::Behz)
+            .set_minimum_security(0)
+            .build()?;
+        let bytes = params.to_bytes();
+        let deserialized = BfvParameters::try_deserialize_insecure(&bytes)?;
+        assert_eq!(
+            deserialized.multiplication_strategy(),
+            MultiplicationStrategy::Behz
+        );
+        assert_eq!(deserialized, params);
         Ok(())
     }
 }
@@ -5,7 +5,7 @@ use crate::{
 };
 use fhe_math::rq::{traits::TryConvertFrom, Context, Poly, Representation};
 use fhe_traits::{FheDecoder, FheEncoder, FheParametrized, FhePlaintext};
-use std::sync::Arc;
+use std::sync::{Arc, OnceLock};
 use zeroize::{Zeroize, ZeroizeOnDrop, Zeroizing};
 
 use super::encoding::EncodingEnum;
@@ -19,8 +19,9 @@ pub struct Plaintext {
     pub(crate) value: Box<[u64]>,
     /// The encoding of the plaintext, if known
     pub(crate) encoding: Option<Encoding>,
-    /// The plaintext as a polynomial.
-    pub(crate) poly_ntt: Poly,
+    /// The plaintext as a polynomial in NTT representation, computed when
+    /// first needed.
+    pub(crate) poly_ntt: OnceLock<Poly>,
     /// The level of the plaintext
     pub(crate) level: usize,
 }
@@ -39,11 +40,42 @@ impl ZeroizeOnDrop for Plaintext {}
 impl Zeroize for Plaintext {
     fn zeroize(&mut self) {
         self.value.zeroize();
-        self.poly_ntt.zeroize();
+        if let Some(poly) = self.poly_ntt.get_mut() {
+            poly.zeroize();
+        }
     }
 }
 
 impl Plaintext {
+    /// Creates a plaintext from its value, whose polynomial in NTT
+    /// representation is computed when first needed.
+    pub(crate) fn new(
+        par: &Arc<BfvParameters>,
+        value: Box<[u64]>,
+        encoding: Option<Encoding>,
+        level: usize,
+    ) -> Self {
+        Self {
+            par: par.clone(),
+            value,
+            encoding,
+            poly_ntt: OnceLock::new(),
+            level,
+        }
+    }
+
+    /// Returns the plaintext as a polynomial in NTT representation.
+    pub(crate) fn poly_ntt(&self) -> &Poly {
+        self.poly_ntt.get_or_init(|| {
+            let ctx = self.par.ctx_at_level(self.level).unwrap();
+            let mut poly =
+                Poly::try_convert_from(self.value.as_ref(), ctx, false, Representation::PowerBasis)
+                    .unwrap();
+            poly.change_representation(Representation::Ntt);
+            poly
+        })
+    }
+
     pub(crate) fn to_poly(&self) -> Poly {
         let mut m_v = Zeroizing::new(self.value.clone());
         self.par
@@ -60,21 +92,14 @@ impl Plaintext {
     /// Generate a zero plaintext.
     pub fn zero(encoding: Encoding, par: &Arc<BfvParameters>) -> Result<Self> {
         let level = encoding.level;
-        let ctx = par.ctx_at_level(level)?;
-        let value = vec![0u64; par.degree()];
-        let poly_ntt = Poly::zero(ctx, Representation::Ntt);
-        Ok(Self {
-            par: par.clone(),
-            value: value.into_boxed_slice(),
-            encoding: Some(encoding),
-            poly_ntt,
-            level,
-        })
+        par.ctx_at_level(level)?;
+        let value = vec![0u64; par.degree()].into_boxed_slice();
+        Ok(Self::new(par, value, Some(encoding), level))
     }
 
     /// Returns the level of this plaintext.
     pub fn level(&self) -> usize {
-        self.par.level_of_ctx(self.poly_ntt.ctx()).unwrap()
+        self.level
     }
 }
 
@@ -86,8 +111,8 @@ impl PartialEq for Plaintext {
     fn eq(&self, other: &Self) -> bool {
         let mut eq = self.par == other.par;
         eq &= self.value == other.value;
-        if self.encoding.is_some() && other.encoding.is_some() {
-            eq &= self.encoding.as_ref().unwrap() == other.encoding.as_ref().unwrap()
+        if let (Some(e1), Some(e2)) = (self.encoding.as_ref(), other.encoding.as_ref()) {
+            eq &= e1 == e2
         }
         eq
     }
@@ -108,11 +133,9 @@ impl TryConvertFrom<&Plaintext> for Poly {
             != pt
                 .par
                 .ctx_at_level(pt.level())
-                .map_err(|e| fhe_math::Error::Default(e.to_string()))?
+                .map_err(|_| fhe_math::Error::InvalidLevel(pt.level()))?
         {
-            Err(fhe_math::Error::Default(
-                "Incompatible contexts".to_string(),
-            ))
+            Err(fhe_math::Error::InvalidContext)
         } else {
             Poly::try_convert_from(
                 pt.value.as_ref(),
@@ -197,10 +220,7 @@ impl FheDecoder<Plaintext> for Vec<u64> {
             EncodingEnum::Simd => {
                 if let Some(op) = &pt.par.op {
                     op.forward(&mut w);
-                    let mut w_reordered = w.clone();
-                    for i in 0..pt.par.degree() {
-                        w_reordered[i] = w[pt.par.matrix_reps_index_map[i]]
-                    }
+                    let w_reordered = pt.par.matrix_reps_index_map.iter().map(|j| w[*j]).collect();
                     w.zeroize();
                     Ok(w_reordered)
                 } else {
@@ -219,7 +239,11 @@ impl FheDecoder<Plaintext> for Vec<i64> {
         E: Into<Option<Encoding>>,
     {
         let v = Vec::<u64>::try_decode(pt, encoding)?;
-        Ok(unsafe { pt.par.plaintext.center_vec_vt(&v) })
+        if cfg!(feature = "constant-time") {
+            Ok(pt.par.plaintext.center_vec(&v))
+        } else {
+            Ok(unsafe { pt.par.plaintext.center_vec_vt(&v) })
+        }
     }
 
     type Error = Error;
@@ -259,6 +283,7 @@ mod tests {
             .set_degree(16)
             .set_plaintext_modulus(2)
             .set_moduli(&[4611686018326724609])
+            .set_minimum_security(0)
             .build_arc()?;
 
         let a = params.plaintext.random_vec(params.degree(), &mut rng);
@@ -359,9 +384,10 @@ mod tests {
         let plaintext = Plaintext::zero(Encoding::poly(), &params)?;
 
         assert_eq!(plaintext.value, Box::<[u64]>::from([0u64; 16]));
+        assert!(plaintext.poly_ntt.get().is_none());
         assert_eq!(
-            plaintext.poly_ntt,
-            Poly::zero(&params.ctx[0], Representation::Ntt)
+            plaintext.poly_ntt(),
+            &Poly::zero(&params.ctx[0], Representation::Ntt)
         );
 
         Ok(())
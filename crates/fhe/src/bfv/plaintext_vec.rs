@@ -1,6 +1,5 @@
 use std::{cmp::min, sync::Arc};
 
-use fhe_math::rq::{traits::TryConvertFrom, Poly, Representation};
 use fhe_traits::{FheEncoder, FheEncoderVariableTime, FheParametrized, FhePlaintext};
 use zeroize::{Zeroize, ZeroizeOnDrop};
 
@@ -45,7 +44,7 @@ impl FheEncoderVariableTime<&[u64]> for PlaintextVec {
         if encoding.encoding == EncodingEnum::Simd && par.op.is_none() {
             return Err(Error::EncodingNotSupported(EncodingEnum::Simd.to_string()));
         }
-        let ctx = par.ctx_at_level(encoding.level)?;
+        par.ctx_at_level(encoding.level)?;
         let num_plaintexts = value.len().div_ceil(par.degree());
 
         Ok(PlaintextVec(
@@ -56,27 +55,28 @@ impl FheEncoderVariableTime<&[u64]> for PlaintextVec {
                     match encoding.encoding {
                         EncodingEnum::Poly => v[..slice.len()].copy_from_slice(slice),
                         EncodingEnum::Simd => {
-                            for i in 0..slice.len() {
-                                v[par.matrix_reps_index_map[i]] = slice[i];
+                            for (vj, i) in
+                                v.iter_mut().zip(par.matrix_reps_inverse_index_map.iter())
+                            {
+                                *vj = slice.get(*i).copied().unwrap_or_default();
+                            }
+                            let op = par.op.as_ref().ok_or(Error::EncodingNotSupported(
+                                EncodingEnum::Simd.to_string(),
+                            ))?;
+                            if cfg!(feature = "constant-time") {
+                                op.backward(&mut v)
+                            } else {
+                                op.backward_vt(v.as_mut_ptr())
                             }
-                            par.op
-                                .as_ref()
-                                .ok_or(Error::DefaultError("No Ntt operator".to_string()))?
-                                .backward_vt(v.as_mut_ptr());
                         }
                     };
 
-                    let mut poly =
-                        Poly::try_convert_from(&v, ctx, true, Representation::PowerBasis)?;
-                    poly.change_representation(Representation::Ntt);
-
-                    Ok(Plaintext {
-                        par: par.clone(),
-                        value: v.into_boxed_slice(),
-                        encoding: Some(encoding.clone()),
-                        poly_ntt: poly,
-                        level: encoding.level,
-                    })
+                    Ok(Plaintext::new(
+                        par,
+                        v.into_boxed_slice(),
+                        Some(encoding.clone()),
+                        encoding.level,
+                    ))
                 })
                 .collect::<Result<Vec<Plaintext>>>()?,
         ))
@@ -92,7 +92,7 @@ impl FheEncoder<&[u64]> for PlaintextVec {
         if encoding.encoding == EncodingEnum::Simd && par.op.is_none() {
             return Err(Error::EncodingNotSupported(EncodingEnum::Simd.to_string()));
         }
-        let ctx = par.ctx_at_level(encoding.level)?;
+        par.ctx_at_level(encoding.level)?;
         let num_plaintexts = value.len().div_ceil(par.degree());
 
         Ok(PlaintextVec(
@@ -103,27 +103,24 @@ impl FheEncoder<&[u64]> for PlaintextVec {
                     match encoding.encoding {
                         EncodingEnum::Poly => v[..slice.len()].copy_from_slice(slice),
                         EncodingEnum::Simd => {
-                            for i in 0..slice.len() {
-                                v[par.matrix_reps_index_map[i]] = slice[i];
+                            for (vj, i) in
+                                v.iter_mut().zip(par.matrix_reps_inverse_index_map.iter())
+                            {
+                                *vj = slice.get(*i).copied().unwrap_or_default();
                             }
                             par.op
                                 .as_ref()
-                                .ok_or(Error::DefaultError("No Ntt operator".to_string()))?
+                                .ok_or(Error::EncodingNotSupported(EncodingEnum::Simd.to_string()))?
                                 .backward(&mut v);
                         }
                     };
 
-                    let mut poly =
-                        Poly::try_convert_from(&v, ctx, false, Representation::PowerBasis)?;
-                    poly.change_representation(Representation::Ntt);
-
-                    Ok(Plaintext {
-                        par: par.clone(),
-                        value: v.into_boxed_slice(),
-                        encoding: Some(encoding.clone()),
-                        poly_ntt: poly,
-                        level: encoding.level,
-                    })
+                    Ok(Plaintext::new(
+                        par,
+                        v.into_boxed_slice(),
+                        Some(encoding.clone()),
+                        encoding.level,
+                    ))
                 })
                 .collect::<Result<Vec<Plaintext>>>()?,
         ))
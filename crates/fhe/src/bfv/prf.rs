@@ -0,0 +1,215 @@
+//! Homomorphic evaluation of the Legendre pseudorandom function.
+//!
+//! The Legendre PRF (Damgård, 1988) with keys `k_0, ..., k_{m-1}` modulo a
+//! prime `t` maps an input `x` to the `m` bits indicating whether `x + k_i` is
+//! a non-residue modulo `t`. The Legendre symbol is `(x + k_i)^((t - 1) / 2)`,
+//! so the PRF is evaluated on encrypted inputs with a multiplicative depth of
+//! `ceil(log2(t - 1))`, which makes it suitable for oblivious PRF protocols:
+//! the client encrypts its inputs in the slots of a ciphertext, the server
+//! holding the keys evaluates [`LegendrePrf::evaluate_encrypted`], and the
+//! client decrypts the outputs with [`LegendrePrf::decode`].
+//!
+//! The noise of the responses depends on the keys, which the server should
+//! hide, e.g., by adding a fresh encryption of zero with flooding noise. The
+//! security of the Legendre PRF requires a large prime `t`.
+
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+use fhe_traits::FheDecoder;
+use fhe_util::is_prime;
+use itertools::Itertools;
+use rand::{CryptoRng, RngCore};
+use zeroize::Zeroizing;
+
+use crate::bfv::psi::{ceil_log2, power};
+use crate::bfv::{BfvParameters, Ciphertext, Encoding, Multiplicator, Plaintext};
+use crate::{Error, Result};
+
+/// The largest number of keys, i.e., of output bits.
+pub const MAX_OUTPUT_BITS: usize = 64;
+
+/// The Legendre PRF with keys modulo the plaintext modulus.
+#[derive(Clone, PartialEq, Eq)]
+pub struct LegendrePrf {
+    par: Arc<BfvParameters>,
+    keys: Zeroizing<Vec<u64>>,
+}
+
+impl LegendrePrf {
+    /// Creates the PRF with the keys `keys`, reduced modulo the plaintext
+    /// modulus, whose outputs have one bit per key.
+    ///
+    /// Returns an error if the plaintext modulus is not an odd prime, or if
+    /// there are no keys or more than [`MAX_OUTPUT_BITS`] keys.
+    pub fn new(par: &Arc<BfvParameters>, keys: &[u64]) -> Result<Self> {
+        if par.plaintext() == 2 || !is_prime(par.plaintext()) {
+            return Err(Error::UnsupportedOperation(
+                "The Legendre PRF requires an odd prime plaintext modulus".to_string(),
+            ));
+        }
+        if keys.is_empty() {
+            return Err(Error::TooFewValues(0, 1));
+        }
+        if keys.len() > MAX_OUTPUT_BITS {
+            return Err(Error::TooManyValues(keys.len(), MAX_OUTPUT_BITS));
+        }
+        Ok(Self {
+            par: par.clone(),
+            keys: Zeroizing::new(keys.iter().map(|k| par.plaintext.reduce(*k)).collect()),
+        })
+    }
+
+    /// Creates the PRF with `bits` random keys.
+    pub fn random<R: RngCore + CryptoRng>(
+        par: &Arc<BfvParameters>,
+        bits: usize,
+        rng: &mut R,
+    ) -> Result<Self> {
+        Self::new(par, &Zeroizing::new(par.plaintext.random_vec(bits, rng)))
+    }
+
+    /// Returns the number of bits of the outputs.
+    pub fn output_bits(&self) -> usize {
+        self.keys.len()
+    }
+
+    /// Returns the multiplicative depth of
+    /// [`LegendrePrf::evaluate_encrypted`].
+    pub fn depth(&self) -> usize {
+        ceil_log2(self.par.plaintext() as usize - 1)
+    }
+
+    /// Evaluates the PRF on `x`. The bit `i` of the output is 1 if `x + k_i`
+    /// is a non-residue modulo the plaintext modulus, and 0 otherwise.
+    pub fn evaluate(&self, x: u64) -> u64 {
+        let t = &self.par.plaintext;
+        let x = t.reduce(x);
+        self.keys
+            .iter()
+            .enumerate()
+            .map(|(i, k)| {
+                let symbol = t.pow(t.add(x, *k), (t.modulus() - 1) / 2);
+                ((symbol == t.modulus() - 1) as u64) << i
+            })
+            .sum()
+    }
+
+    /// Evaluates the PRF on each slot of the ciphertext `ct` in the SIMD
+    /// encoding, and returns one ciphertext per bit of the outputs, whose
+    /// slots are 0 or 1.
+    ///
+    /// The bit of `y = x + k_i` is `(y^(t - 1) - y^((t - 1) / 2)) / 2`, whose
+    /// powers are computed by the `multiplicator` with a multiplicative depth
+    /// of [`LegendrePrf::depth`].
+    pub fn evaluate_encrypted(
+        &self,
+        ct: &Ciphertext,
+        multiplicator: &Multiplicator,
+    ) -> Result<Vec<Ciphertext>> {
+        self.par.check_fingerprint(&ct.par)?;
+        let t = &self.par.plaintext;
+        let exponent = (t.modulus() - 1) as usize;
+        let half = t.inv(2).unwrap();
+        self.keys
+            .iter()
+            .map(|k| {
+                let mut powers = BTreeMap::from([(1, ct + *k)]);
+                let square = power(multiplicator, &mut powers, exponent)?;
+                let symbol = power(multiplicator, &mut powers, exponent / 2)?;
+                Ok(&(&square - &symbol) * half)
+            })
+            .collect()
+    }
+
+    /// Decodes the decrypted `responses` of
+    /// [`LegendrePrf::evaluate_encrypted`] into the outputs of the PRF for
+    /// each slot.
+    pub fn decode(responses: &[Plaintext]) -> Result<Vec<u64>> {
+        let Some(first) = responses.first() else {
+            return Err(Error::TooFewValues(0, 1));
+        };
+        if responses.len() > MAX_OUTPUT_BITS {
+            return Err(Error::TooManyValues(responses.len(), MAX_OUTPUT_BITS));
+        }
+        let mut outputs = vec![0u64; first.par.degree()];
+        for (i, response) in responses.iter().enumerate() {
+            let bits = Vec::<u64>::try_decode(response, Encoding::simd_at_level(response.level))?;
+            if let Some(bit) = bits.iter().find(|bit| **bit > 1) {
+                return Err(Error::UnspecifiedInput(format!("Invalid bit {bit}")));
+            }
+            outputs
+                .iter_mut()
+                .zip_eq(bits)
+                .for_each(|(o, b)| *o |= b << i);
+        }
+        Ok(outputs)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::LegendrePrf;
+    use crate::bfv::{
+        BfvParameters, BfvParametersBuilder, Encoding, Multiplicator, Plaintext,
+        RelinearizationKey, SecretKey,
+    };
+    use fhe_traits::{FheDecrypter, FheEncoder, FheEncrypter};
+    use rand::thread_rng;
+    use std::error::Error;
+
+    #[test]
+    fn legendre_prf() -> Result<(), Box<dyn Error>> {
+        let mut rng = thread_rng();
+        let par = BfvParametersBuilder::new()
+            .set_degree(16)
+            .set_plaintext_modulus(97)
+            .set_moduli_sizes(&[62; 5])
+            .set_minimum_security(0)
+            .build_arc()?;
+        let sk = SecretKey::random(&par, &mut rng);
+        let rk = RelinearizationKey::new(&sk, &mut rng)?;
+        let multiplicator = Multiplicator::default(&rk)?;
+
+        // The residues modulo 97 are the squares.
+        let prf = LegendrePrf::new(&par, &[0, 3])?;
+        let squares = (0..97u64).map(|x| x * x % 97).collect::<Vec<_>>();
+        for x in 1..97 {
+            let expected =
+                (!squares.contains(&x) as u64) | (!squares.contains(&((x + 3) % 97)) as u64) << 1;
+            assert_eq!(prf.evaluate(x), expected);
+        }
+        assert_eq!(prf.evaluate(0), 0);
+        assert_eq!(prf.depth(), 7);
+
+        let prf = LegendrePrf::random(&par, 3, &mut rng)?;
+        assert_eq!(prf.output_bits(), 3);
+        let x = par.plaintext.random_vec(par.degree(), &mut rng);
+        let pt = Plaintext::try_encode(&x, Encoding::simd(), &par)?;
+        let ct = sk.try_encrypt(&pt, &mut rng)?;
+        let responses = prf
+            .evaluate_encrypted(&ct, &multiplicator)?
+            .iter()
+            .map(|ct| sk.try_decrypt(ct))
+            .collect::<Result<Vec<_>, _>>()?;
+        let outputs = LegendrePrf::decode(&responses)?;
+        assert_eq!(
+            outputs,
+            x.iter().map(|x| prf.evaluate(*x)).collect::<Vec<_>>()
+        );
+
+        assert!(LegendrePrf::new(&par, &[]).is_err());
+        assert!(LegendrePrf::new(&par, &[0; 65]).is_err());
+        assert!(LegendrePrf::decode(&[]).is_err());
+        let par = BfvParameters::default_arc(1, 16);
+        assert!(LegendrePrf::new(&par, &[1]).is_ok());
+        let par = BfvParametersBuilder::new()
+            .set_degree(16)
+            .set_plaintext_modulus(1024)
+            .set_moduli_sizes(&[62])
+            .set_minimum_security(0)
+            .build_arc()?;
+        assert!(LegendrePrf::new(&par, &[1]).is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,279 @@
+//! Profiling of the homomorphic operations.
+
+use std::collections::BTreeMap;
+use std::fmt::Display;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use fhe_math::rq::Poly;
+
+use crate::bfv::Ciphertext;
+use crate::Result;
+
+/// Statistics of an operation recorded by a [`Profiler`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct OperationStats {
+    /// The number of times the operation was performed.
+    pub count: usize,
+    /// The total time spent in the operation.
+    pub total_time: Duration,
+    /// The longest time spent in a single operation.
+    pub max_time: Duration,
+    /// The total size of the resulting ciphertexts, in bytes, as the size of
+    /// their polynomials in memory.
+    pub total_bytes: usize,
+    /// The largest heuristic estimate of the noise of the resulting
+    /// ciphertexts, in bits, if known.
+    pub max_noise_estimate: Option<usize>,
+}
+
+impl OperationStats {
+    /// Returns the mean time spent in the operation.
+    pub fn mean_time(&self) -> Duration {
+        if self.count == 0 {
+            Duration::ZERO
+        } else {
+            self.total_time / self.count as u32
+        }
+    }
+}
+
+/// A report of the operations recorded by a [`Profiler`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ProfileReport {
+    /// The statistics of each operation, by name.
+    pub operations: BTreeMap<&'static str, OperationStats>,
+}
+
+impl ProfileReport {
+    /// Returns the total time spent in the recorded operations.
+    pub fn total_time(&self) -> Duration {
+        self.operations.values().map(|stats| stats.total_time).sum()
+    }
+}
+
+impl Display for ProfileReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(
+            f,
+            "{:<24} {:>8} {:>12} {:>12} {:>12} {:>8}",
+            "operation", "count", "total (ms)", "mean (µs)", "bytes", "noise"
+        )?;
+        for (operation, stats) in &self.operations {
+            writeln!(
+                f,
+                "{:<24} {:>8} {:>12.3} {:>12.1} {:>12} {:>8}",
+                operation,
+                stats.count,
+                stats.total_time.as_secs_f64() * 1e3,
+                stats.mean_time().as_secs_f64() * 1e6,
+                stats.total_bytes,
+                stats
+                    .max_noise_estimate
+                    .map_or("-".to_string(), |noise| noise.to_string())
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// A profiler recording the number, the time, the size of the results and the
+/// noise of the homomorphic operations.
+///
+/// The profiler can be attached to a
+/// [`Multiplicator`](crate::bfv::Multiplicator), which records the
+/// `multiply`, `relinearize` and `mod_switch` steps of the multiplications, or
+/// to an [`EvaluationKey`](crate::bfv::EvaluationKey), which records the
+/// rotations, inner sums and expansions. Other operations are recorded with
+/// [`Profiler::measure`]. Clones of a profiler share their records.
+#[derive(Debug, Clone, Default)]
+pub struct Profiler {
+    operations: Arc<Mutex<BTreeMap<&'static str, OperationStats>>>,
+}
+
+impl Profiler {
+    /// Creates a profiler without records.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn add(
+        &self,
+        operation: &'static str,
+        count: usize,
+        elapsed: Duration,
+        bytes: usize,
+        noise: Option<f64>,
+    ) {
+        let mut operations = self.operations.lock().unwrap();
+        let stats = operations.entry(operation).or_default();
+        stats.count += count;
+        stats.total_time += elapsed;
+        stats.max_time = stats.max_time.max(elapsed);
+        stats.total_bytes += bytes;
+        if let Some(noise) = noise {
+            let noise = noise.ceil().max(0.0) as usize;
+            stats.max_noise_estimate = stats.max_noise_estimate.max(Some(noise));
+        }
+    }
+
+    /// Records an operation `operation` which took `elapsed` and resulted in
+    /// the ciphertext `ct`.
+    pub fn record(&self, operation: &'static str, elapsed: Duration, ct: &Ciphertext) {
+        self.add(operation, 1, elapsed, size_of(&ct.c), ct.noise)
+    }
+
+    /// Runs and records the operation `operation` computed by `f`.
+    pub fn measure<F>(&self, operation: &'static str, f: F) -> Result<Ciphertext>
+    where
+        F: FnOnce() -> Result<Ciphertext>,
+    {
+        let start = Instant::now();
+        let ct = f()?;
+        self.record(operation, start.elapsed(), &ct);
+        Ok(ct)
+    }
+
+    /// Returns the report of the operations recorded so far.
+    pub fn report(&self) -> ProfileReport {
+        ProfileReport {
+            operations: self.operations.lock().unwrap().clone(),
+        }
+    }
+
+    /// Clears the operations recorded so far.
+    pub fn clear(&self) {
+        self.operations.lock().unwrap().clear()
+    }
+}
+
+impl PartialEq for Profiler {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.operations, &other.operations)
+    }
+}
+
+impl Eq for Profiler {}
+
+/// Returns the size of the polynomials `c` in memory, in bytes.
+fn size_of(c: &[Poly]) -> usize {
+    c.iter()
+        .map(|p| p.coefficients().len() * std::mem::size_of::<u64>())
+        .sum()
+}
+
+/// A stopwatch recording the successive steps of an operation with an
+/// optional profiler.
+pub(crate) struct Stopwatch<'a>(Option<(&'a Profiler, Instant)>);
+
+impl<'a> Stopwatch<'a> {
+    /// Starts the stopwatch if there is a profiler.
+    pub(crate) fn start(profiler: Option<&'a Profiler>) -> Self {
+        Self(profiler.map(|profiler| (profiler, Instant::now())))
+    }
+
+    fn add(&mut self, operation: &'static str, count: usize, c: &[Poly], noise: Option<f64>) {
+        if let Some((profiler, last)) = &mut self.0 {
+            let now = Instant::now();
+            profiler.add(operation, count, now - *last, size_of(c), noise);
+            *last = now;
+        }
+    }
+
+    /// Records the step `operation` since the previous step, which resulted
+    /// in the polynomials `c` with noise `noise`.
+    pub(crate) fn lap(&mut self, operation: &'static str, c: &[Poly], noise: Option<f64>) {
+        self.add(operation, 1, c, noise)
+    }
+
+    /// Adds the time since the previous step to the last occurrence of the
+    /// step `operation`, without counting a new occurrence.
+    pub(crate) fn extend(&mut self, operation: &'static str) {
+        self.add(operation, 0, &[], None)
+    }
+
+    /// Records the step `operation` since the previous step, which resulted
+    /// in the ciphertexts `cts`.
+    pub(crate) fn lap_ciphertexts(&mut self, operation: &'static str, cts: &[Ciphertext]) {
+        if let Some((profiler, last)) = &mut self.0 {
+            let now = Instant::now();
+            let bytes = cts.iter().map(|ct| size_of(&ct.c)).sum();
+            let noise = cts.iter().filter_map(|ct| ct.noise).reduce(f64::max);
+            profiler.add(operation, 1, now - *last, bytes, noise);
+            *last = now;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Profiler;
+    use crate::bfv::{
+        BfvParameters, Encoding, EvaluationKeyBuilder, Multiplicator, Plaintext,
+        RelinearizationKey, SecretKey,
+    };
+    use fhe_traits::{FheEncoder, FheEncrypter};
+    use rand::thread_rng;
+    use std::error::Error;
+
+    #[test]
+    fn profile() -> Result<(), Box<dyn Error>> {
+        let mut rng = thread_rng();
+        let par = BfvParameters::default_arc(3, 16);
+        let sk = SecretKey::random(&par, &mut rng);
+        let profiler = Profiler::new();
+
+        let v = par.plaintext.random_vec(par.degree(), &mut rng);
+        let pt = Plaintext::try_encode(&v, Encoding::simd(), &par)?;
+        let ct = sk.try_encrypt(&pt, &mut rng)?;
+
+        let rk = RelinearizationKey::new(&sk, &mut rng)?;
+        let mut multiplicator = Multiplicator::default(&rk)?;
+        multiplicator.enable_mod_switching()?;
+        multiplicator.set_profiler(&profiler);
+        let ct2 = multiplicator.multiply(&ct, &ct)?;
+        multiplicator.multiply(&ct, &ct)?;
+
+        let mut ek = EvaluationKeyBuilder::new_leveled(&sk, 1, 0)?
+            .enable_column_rotation(1)?
+            .build(&mut rng)?;
+        ek.set_profiler(&profiler);
+        ek.rotates_columns_by(&ct2, 1)?;
+        ek.rotates_columns_by_many(&ct2, &[1, 1])?;
+        profiler.measure("add", || Ok(&ct2 + &ct2))?;
+
+        let report = profiler.report();
+        let operations = report.operations.keys().copied().collect::<Vec<_>>();
+        assert_eq!(
+            operations,
+            [
+                "add",
+                "mod_switch",
+                "multiply",
+                "relinearize",
+                "rotates_columns_by",
+                "rotates_columns_by_many"
+            ]
+        );
+        let multiply = &report.operations["multiply"];
+        assert_eq!(multiply.count, 2);
+        assert_eq!(multiply.total_bytes, 2 * 3 * 3 * 16 * 8);
+        assert!(multiply.max_noise_estimate.is_some());
+        assert!(multiply.max_time <= multiply.total_time);
+        assert_eq!(
+            report.operations["mod_switch"].total_bytes,
+            2 * 2 * 2 * 16 * 8
+        );
+        assert_eq!(report.operations["rotates_columns_by_many"].count, 1);
+        assert_eq!(
+            report.operations["rotates_columns_by_many"].total_bytes,
+            2 * 2 * 2 * 16 * 8
+        );
+        assert!(report.total_time() >= multiply.total_time);
+        assert!(report.to_string().lines().count() == 7);
+
+        profiler.clear();
+        assert!(profiler.report().operations.is_empty());
+        Ok(())
+    }
+}
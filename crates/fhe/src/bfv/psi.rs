@@ -0,0 +1,313 @@
+//! Building blocks for private set intersection.
+//!
+//! The receiver encodes its items in the slots of a query with
+//! [`encode_query`] and encrypts it. The sender encodes its set as the roots
+//! of [`membership_polynomials`], evaluates them on the encrypted query with
+//! [`evaluate_membership`], and hides everything but the zeros of the
+//! evaluations with [`randomize_response`]. The receiver decrypts the
+//! responses and learns which of its items are in the intersection with
+//! [`decode_membership`].
+//!
+//! Alternatively, the sender can return an encrypted boolean for each slot of
+//! the query with [`membership`], at the cost of a larger multiplicative depth;
+//! [`membership_depth`] and [`check_membership_parameters`] help to choose
+//! parameters supporting the size of the set.
+//!
+//! The items are elements of the plaintext modulus `t`, so longer items must
+//! first be hashed into `[0, t)`; two items with the same hash are reported as
+//! equal.
+
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+use fhe_traits::{FheDecoder, FheEncoder, FheEncrypter};
+use itertools::Itertools;
+use rand::{CryptoRng, Rng, RngCore};
+
+use crate::bfv::{
+    noise, BfvParameters, Ciphertext, Encoding, Multiplicator, Plaintext, PublicKey,
+    RelinearizationKey,
+};
+use crate::{Error, Result};
+
+/// Encodes the `items` of the receiver in the slots of a plaintext, which
+/// requires the parameters to support SIMD encoding.
+pub fn encode_query(items: &[u64], par: &Arc<BfvParameters>) -> Result<Plaintext> {
+    if items.len() > par.degree() {
+        return Err(Error::TooManyValues(items.len(), par.degree()));
+    }
+    let items = items
+        .iter()
+        .map(|item| item % par.plaintext())
+        .collect_vec();
+    Plaintext::try_encode(&items, Encoding::simd(), par)
+}
+
+/// Returns the monic polynomials whose roots are the elements of `set`, each
+/// of degree at most `max_degree`, as lists of coefficients in increasing
+/// degree modulo the plaintext modulus.
+///
+/// An item is in the set when one of the polynomials vanishes on it; smaller
+/// degrees consume less noise budget at the cost of more responses.
+pub fn membership_polynomials(
+    set: &[u64],
+    max_degree: usize,
+    par: &Arc<BfvParameters>,
+) -> Result<Vec<Vec<u64>>> {
+    if set.is_empty() || max_degree == 0 {
+        return Err(Error::TooFewValues(0, 1));
+    }
+    let t = &par.plaintext;
+    Ok(set
+        .chunks(max_degree)
+        .map(|roots| {
+            // Multiply by (X - root) for each root.
+            let mut coefficients = vec![1u64];
+            for root in roots {
+                let minus_root = t.neg(t.reduce(*root));
+                coefficients.insert(0, 0);
+                for i in 0..coefficients.len() - 1 {
+                    coefficients[i] =
+                        t.add(coefficients[i], t.mul(minus_root, coefficients[i + 1]));
+                }
+            }
+            coefficients
+        })
+        .collect())
+}
+
+/// Evaluates the polynomial with the given `coefficients` on each slot of the
+/// encrypted `query`.
+///
+/// The powers of the query are computed with a multiplicative depth of
+/// `ceil(log2(degree))`.
+pub fn evaluate_membership(
+    query: &Ciphertext,
+    coefficients: &[u64],
+    rk: &RelinearizationKey,
+) -> Result<Ciphertext> {
+    Multiplicator::default(rk)?.evaluate_polynomial(query, coefficients)
+}
+
+/// Randomizes an evaluation of a membership polynomial, so that the receiver
+/// only learns whether each slot is zero.
+///
+/// The slots are multiplied by uniform non-zero values, the ciphertext is
+/// re-randomized with a fresh encryption of zero, and switched to the last
+/// level to reduce its size.
+pub fn randomize_response<R: RngCore + CryptoRng>(
+    response: &Ciphertext,
+    pk: &PublicKey,
+    rng: &mut R,
+) -> Result<Ciphertext> {
+    let par = &pk.par;
+    par.check_fingerprint(&response.par)?;
+    let mask = (0..par.degree())
+        .map(|_| rng.gen_range(1..par.plaintext()))
+        .collect_vec();
+    let mask = Plaintext::try_encode(&mask, Encoding::simd_at_level(response.level), par)?;
+    let zero = Plaintext::zero(Encoding::simd_at_level(response.level), par)?;
+
+    let mut randomized = response * &mask;
+    randomized += &pk.try_encrypt(&zero, rng)?;
+    randomized.mod_switch_to_last_level()?;
+    Ok(randomized)
+}
+
+/// Evaluates the indicator function of the sender's `set` on each slot of the
+/// encrypted `query`, i.e., returns an encryption of 1 in the slots whose item
+/// is in the set, and of 0 in the other slots.
+///
+/// The indicator `1 - P(x)^(t - 1)`, where `P` is the polynomial whose roots
+/// are the elements of the set, is computed with a multiplicative depth of
+/// [`membership_depth`].
+pub fn membership(query: &Ciphertext, set: &[u64], rk: &RelinearizationKey) -> Result<Ciphertext> {
+    let par = &query.par;
+    let coefficients = membership_polynomials(set, set.len(), par)?;
+    let evaluation = evaluate_membership(query, &coefficients[0], rk)?;
+
+    // By Fermat's little theorem, P(x)^(t - 1) is 0 if P(x) = 0, and 1
+    // otherwise.
+    let multiplicator = Multiplicator::default(rk)?;
+    let mut powers = BTreeMap::from([(1, evaluation)]);
+    let power = power(&multiplicator, &mut powers, par.plaintext() as usize - 1)?;
+    let mut indicator = &power * (par.plaintext() - 1);
+    indicator += 1u64;
+    Ok(indicator)
+}
+
+/// Returns the encryption of the `exponent`-th power of `powers[1]` with a
+/// multiplicative depth of `ceil(log2(exponent))`, and stores the intermediate
+/// powers in `powers`.
+pub(crate) fn power(
+    multiplicator: &Multiplicator,
+    powers: &mut BTreeMap<usize, Ciphertext>,
+    exponent: usize,
+) -> Result<Ciphertext> {
+    if let Some(power) = powers.get(&exponent) {
+        return Ok(power.clone());
+    }
+    let lhs = power(multiplicator, powers, exponent - exponent / 2)?;
+    let rhs = power(multiplicator, powers, exponent / 2)?;
+    let power = multiplicator.multiply(&lhs, &rhs)?;
+    powers.insert(exponent, power.clone());
+    Ok(power)
+}
+
+/// Returns the multiplicative depth of [`membership`] for a set of `set_size`
+/// elements.
+pub fn membership_depth(set_size: usize, par: &BfvParameters) -> usize {
+    ceil_log2(set_size) + ceil_log2(par.plaintext() as usize - 1)
+}
+
+/// Checks that the parameters support [`membership`] for a set of `set_size`
+/// elements, and returns its multiplicative depth.
+///
+/// The noise of the result is estimated heuristically from the noise of a fresh
+/// encryption under the public key, and an error is returned if it exceeds the
+/// noise capacity of the ciphertexts, in which case more moduli are needed.
+pub fn check_membership_parameters(set_size: usize, par: &BfvParameters) -> Result<usize> {
+    if set_size == 0 {
+        return Err(Error::TooFewValues(0, 1));
+    }
+    let mut estimate = Some(noise::fresh_public_key(par));
+    for i in 0..membership_depth(set_size, par) {
+        estimate = noise::mul(par, estimate, estimate);
+        if i + 1 == ceil_log2(set_size) {
+            // The powers of the query are multiplied by the coefficients of the
+            // polynomial, and summed.
+            estimate = estimate.map(|e| e + (par.plaintext() as f64 * set_size as f64).log2());
+        }
+    }
+    let noise = estimate.unwrap_or_default().ceil() as usize;
+    let capacity = noise::capacity(par, 0).floor().max(0.0) as usize;
+    if noise >= capacity {
+        return Err(Error::NoiseBudgetExceeded { noise, capacity });
+    }
+    Ok(membership_depth(set_size, par))
+}
+
+/// Returns `ceil(log2(x))`, for `x > 0`.
+pub(crate) fn ceil_log2(x: usize) -> usize {
+    x.next_power_of_two().trailing_zeros() as usize
+}
+
+/// Decodes the decrypted `responses` for the first `num_items` items of the
+/// query, and returns whether each item is in the set of the sender.
+pub fn decode_membership(responses: &[Plaintext], num_items: usize) -> Result<Vec<bool>> {
+    let mut members = vec![false; num_items];
+    for response in responses {
+        if num_items > response.par.degree() {
+            return Err(Error::TooManyValues(num_items, response.par.degree()));
+        }
+        let values = Vec::<u64>::try_decode(response, Encoding::simd_at_level(response.level))?;
+        members
+            .iter_mut()
+            .zip(values)
+            .for_each(|(member, value)| *member |= value == 0);
+    }
+    Ok(members)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        check_membership_parameters, decode_membership, encode_query, evaluate_membership,
+        membership, membership_depth, membership_polynomials, randomize_response,
+    };
+    use crate::bfv::{
+        BfvParameters, BfvParametersBuilder, Encoding, PublicKey, RelinearizationKey, SecretKey,
+    };
+    use fhe_traits::{FheDecoder, FheDecrypter, FheEncrypter};
+    use rand::{thread_rng, Rng};
+    use std::error::Error;
+
+    #[test]
+    fn polynomials() -> Result<(), Box<dyn Error>> {
+        let par = BfvParameters::default_arc(1, 16);
+        let t = &par.plaintext;
+        let set = [3, 10, 1000, 1152, 7];
+        let polynomials = membership_polynomials(&set, 2, &par)?;
+        assert_eq!(polynomials.len(), 3);
+        assert_eq!(polynomials[2], vec![1146, 1]);
+        for (roots, coefficients) in set.chunks(2).zip(&polynomials) {
+            assert_eq!(coefficients.len(), roots.len() + 1);
+            for x in 0..par.plaintext() {
+                let value = coefficients
+                    .iter()
+                    .rev()
+                    .fold(0, |acc, c| t.add(t.mul(acc, x), *c));
+                assert_eq!(value == 0, roots.contains(&x));
+            }
+        }
+        assert!(membership_polynomials(&[], 2, &par).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn intersection() -> Result<(), Box<dyn Error>> {
+        let mut rng = thread_rng();
+        let par = BfvParameters::default_arc(3, 16);
+        let sk = SecretKey::random(&par, &mut rng);
+        let pk = PublicKey::new(&sk, &mut rng);
+        let rk = RelinearizationKey::new(&sk, &mut rng)?;
+
+        let sender = (0..10)
+            .map(|_| rng.gen_range(0..par.plaintext()))
+            .collect::<Vec<_>>();
+        let mut items = (0..12)
+            .map(|_| rng.gen_range(0..par.plaintext()))
+            .collect::<Vec<_>>();
+        items[1] = sender[0];
+        items[5] = sender[9];
+        items[11] = sender[4];
+
+        let query = sk.try_encrypt(&encode_query(&items, &par)?, &mut rng)?;
+        let responses = membership_polynomials(&sender, 4, &par)?
+            .iter()
+            .map(|coefficients| {
+                let response = evaluate_membership(&query, coefficients, &rk)?;
+                let response = randomize_response(&response, &pk, &mut rng)?;
+                sk.try_decrypt(&response)
+            })
+            .collect::<crate::Result<Vec<_>>>()?;
+        assert_eq!(responses.len(), 3);
+
+        let members = decode_membership(&responses, items.len())?;
+        for (item, member) in items.iter().zip(members) {
+            assert_eq!(member, sender.contains(item));
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn indicator() -> Result<(), Box<dyn Error>> {
+        let mut rng = thread_rng();
+        let par = BfvParametersBuilder::new()
+            .set_degree(16)
+            .set_plaintext_modulus(97)
+            .set_moduli_sizes(&[62; 4])
+            .set_minimum_security(0)
+            .build_arc()?;
+        let set = [3, 10, 42, 96, 7];
+        assert_eq!(membership_depth(set.len(), &par), 10);
+        assert_eq!(check_membership_parameters(set.len(), &par)?, 10);
+        assert!(
+            check_membership_parameters(set.len(), &BfvParameters::default_arc(1, 16)).is_err()
+        );
+
+        let sk = SecretKey::random(&par, &mut rng);
+        let rk = RelinearizationKey::new(&sk, &mut rng)?;
+        let items = (0..par.degree() as u64)
+            .map(|i| (i * 7) % par.plaintext())
+            .collect::<Vec<_>>();
+        let query = sk.try_encrypt(&encode_query(&items, &par)?, &mut rng)?;
+        let result = membership(&query, &set, &rk)?;
+        let pt = sk.try_decrypt(&result)?;
+        let members = Vec::<u64>::try_decode(&pt, Encoding::simd_at_level(result.level))?;
+        for (item, member) in items.iter().zip(members) {
+            assert_eq!(member, set.contains(item) as u64);
+        }
+        Ok(())
+    }
+}
@@ -88,7 +88,7 @@ impl FheEncrypter<Plaintext, RGSWCiphertext> for SecretKey {
         let level = pt.level;
         let ctx = self.par.ctx_at_level(level)?;
 
-        let mut m = Zeroizing::new(pt.poly_ntt.clone());
+        let mut m = Zeroizing::new(pt.poly_ntt().clone());
         let mut m_s = Zeroizing::new(Poly::try_convert_from(
             self.coeffs.as_ref(),
             ctx,
@@ -134,6 +134,9 @@ impl Mul<&RGSWCiphertext> for &Ciphertext {
             seed: None,
             c: vec![&c0 + &c0p, &c1 + &c1p],
             level: self.level,
+            noise: None,
+            metadata: None,
+            key_id: self.key_id,
         }
     }
 }
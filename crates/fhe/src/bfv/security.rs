@@ -0,0 +1,114 @@
+//! Estimation of the security of the BFV parameters.
+
+/// Maximum number of bits of the ciphertext modulus for 128, 192 and 256 bits
+/// of classical security, for a secret and an error of standard deviation
+/// about 3.2, from the tables of the <https://homomorphicencryption.org>
+/// standard, which were derived with the lattice estimator.
+const MAX_LOG_Q: [(usize, [f64; 3]); 6] = [
+    (1024, [27.0, 19.0, 14.0]),
+    (2048, [54.0, 37.0, 29.0]),
+    (4096, [109.0, 75.0, 58.0]),
+    (8192, [218.0, 152.0, 118.0]),
+    (16384, [438.0, 305.0, 237.0]),
+    (32768, [881.0, 611.0, 476.0]),
+];
+
+/// Security levels of the columns of [`MAX_LOG_Q`].
+const SECURITY_LEVELS: [usize; 3] = [128, 192, 256];
+
+/// Variance of the error assumed by the table, which is the default variance
+/// of the parameters, of standard deviation about 3.2.
+const TABLE_VARIANCE: f64 = 10.0;
+
+/// Returns the maximum number of bits of the ciphertext modulus for `security`
+/// bits of classical security in the table of the standard, or `None` if the
+/// degree or the security level is not in the table.
+pub(crate) fn max_log_q(degree: usize, security: usize) -> Option<usize> {
+    let (_, row) = MAX_LOG_Q.iter().find(|(n, _)| *n == degree)?;
+    let i = SECURITY_LEVELS.iter().position(|s| *s == security)?;
+    Some(row[i] as usize)
+}
+
+/// Estimates the bits of classical security of a ring of degree `degree`, a
+/// ciphertext modulus of `log_q` bits and a secret and error sampled with
+/// variance `variance`.
+///
+/// The security is read from the table of the standard, where the maximum
+/// size of the modulus is scaled linearly for the degrees outside of the
+/// table, and the modulus is adjusted by the ratio between the standard
+/// deviation of the error and the one of the table. Below 128 bits, the
+/// security is extrapolated linearly in `degree / log_q`.
+pub(crate) fn estimate_security(degree: usize, log_q: usize, variance: usize) -> usize {
+    let (first_degree, first_row) = MAX_LOG_Q[0];
+    let (last_degree, last_row) = MAX_LOG_Q[MAX_LOG_Q.len() - 1];
+    let max_log_q = match MAX_LOG_Q.iter().find(|(n, _)| *n == degree) {
+        Some((_, row)) => *row,
+        None if degree < first_degree => first_row.map(|m| m * degree as f64 / first_degree as f64),
+        None => last_row.map(|m| m * degree as f64 / last_degree as f64),
+    };
+
+    // A larger error increases the security as much as a smaller modulus.
+    let log_q = log_q as f64 - 0.5 * (variance.max(1) as f64 / TABLE_VARIANCE).log2();
+    match SECURITY_LEVELS
+        .iter()
+        .zip(max_log_q.iter())
+        .rev()
+        .find(|(_, m)| log_q <= **m)
+    {
+        Some((security, _)) => *security,
+        None => (SECURITY_LEVELS[0] as f64 * max_log_q[0] / log_q) as usize,
+    }
+}
+
+/// Returns the maximum number of bits of a ciphertext modulus providing at
+/// least `security` bits of classical security according to
+/// [`estimate_security`], or 0 if there is none.
+pub(crate) fn max_secure_log_q(degree: usize, security: usize, variance: usize) -> usize {
+    debug_assert!(security > 0);
+    let mut log_q = 1;
+    while estimate_security(degree, log_q + 1, variance) >= security {
+        log_q += 1
+    }
+    if estimate_security(degree, log_q, variance) >= security {
+        log_q
+    } else {
+        0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{estimate_security, max_log_q, max_secure_log_q};
+
+    #[test]
+    fn security() {
+        assert_eq!(estimate_security(4096, 109, 10), 128);
+        assert_eq!(estimate_security(4096, 75, 10), 192);
+        assert_eq!(estimate_security(4096, 50, 10), 256);
+        assert_eq!(estimate_security(8192, 218, 10), 128);
+        assert!(estimate_security(8192, 219, 10) < 128);
+        assert!(estimate_security(8192, 436, 10) <= 64);
+        assert_eq!(estimate_security(65536, 1762, 10), 128);
+        assert!(estimate_security(16, 124, 10) < 10);
+
+        // A smaller error decreases the security.
+        assert!(estimate_security(4096, 109, 1) < 128);
+    }
+
+    #[test]
+    fn max_modulus() {
+        assert_eq!(max_log_q(4096, 128), Some(109));
+        assert_eq!(max_log_q(32768, 256), Some(476));
+        assert_eq!(max_log_q(4096, 100), None);
+        assert_eq!(max_log_q(65536, 128), None);
+        assert_eq!(max_secure_log_q(65536, 128, 10), 1762);
+        assert_eq!(max_secure_log_q(4, 128, 10), 0);
+        for (degree, _) in super::MAX_LOG_Q {
+            for security in super::SECURITY_LEVELS {
+                let log_q = max_log_q(degree, security).unwrap();
+                assert_eq!(estimate_security(degree, log_q, 10), security);
+                assert_eq!(max_secure_log_q(degree, security, 10), log_q);
+            }
+        }
+    }
+}
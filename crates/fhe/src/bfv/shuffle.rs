@@ -0,0 +1,264 @@
+//! Homomorphic permutation of the slots of ciphertexts.
+//!
+//! The slots of a SIMD-encoded plaintext form a matrix of two rows of
+//! `degree / 2` columns, which the evaluation keys can rotate. A
+//! [`SlotPermutation`] moves each slot of one or several ciphertexts to its
+//! destination by summing rotations of the ciphertexts, masked so that each
+//! rotation only contributes the slots it moves to their destination.
+//!
+//! The evaluator applying the permutation knows it. To hide it from the
+//! holder of the secret key, as in a mixnet, the result must be re-randomized
+//! and its noise flooded with [`SlotPermutation::apply_oblivious`].
+
+use std::collections::{btree_map::Entry, BTreeMap, BTreeSet};
+use std::sync::Arc;
+
+use fhe_traits::{FheEncoder, FheEncrypter};
+use rand::{seq::SliceRandom, CryptoRng, RngCore};
+
+use crate::bfv::{
+    ole::FLOODING_BITS, BfvParameters, Ciphertext, Encoding, EvaluationKey, EvaluationKeyBuilder,
+    Plaintext, PublicKey, SecretKeyOperations,
+};
+use crate::{Error, Result};
+
+/// A rotation of the slots of a ciphertext, i.e., whether the rows are
+/// swapped, and by how many columns the rows are rotated.
+type Rotation = (bool, usize);
+
+/// A permutation of the slots of a batch of ciphertexts.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SlotPermutation {
+    par: Arc<BfvParameters>,
+    permutation: Vec<usize>,
+    /// The masks of the slots of the ciphertext `destination` coming from the
+    /// ciphertext `source` by a rotation, indexed by `(destination, source,
+    /// rotation)`.
+    masks: BTreeMap<(usize, usize, Rotation), Vec<u64>>,
+}
+
+impl SlotPermutation {
+    /// Creates the permutation moving the slot `i` to the slot
+    /// `permutation[i]`, where the slots of a batch of ciphertexts are
+    /// numbered consecutively. The length of the permutation must be a
+    /// multiple of the degree.
+    pub fn new(permutation: &[usize], par: &Arc<BfvParameters>) -> Result<Self> {
+        let n = par.degree();
+        if permutation.is_empty() || permutation.len() % n != 0 {
+            return Err(Error::UnspecifiedInput(format!(
+                "The length of the permutation is not a multiple of {n}"
+            )));
+        }
+        let destinations = permutation.iter().copied().collect::<BTreeSet<_>>();
+        if destinations.len() != permutation.len()
+            || destinations.last() != Some(&(permutation.len() - 1))
+        {
+            return Err(Error::UnspecifiedInput(
+                "The input is not a permutation".to_string(),
+            ));
+        }
+
+        let row_size = n / 2;
+        let mut masks = BTreeMap::new();
+        for (i, j) in permutation.iter().enumerate() {
+            let (source, destination) = (i / n, j / n);
+            let (source_slot, destination_slot) = (i % n, j % n);
+            let swap_rows = source_slot / row_size != destination_slot / row_size;
+            let columns =
+                (source_slot % row_size + row_size - destination_slot % row_size) % row_size;
+            masks
+                .entry((destination, source, (swap_rows, columns)))
+                .or_insert_with(|| vec![0u64; n])[destination_slot] = 1;
+        }
+
+        Ok(Self {
+            par: par.clone(),
+            permutation: permutation.to_vec(),
+            masks,
+        })
+    }
+
+    /// Samples a uniformly random permutation of the slots of `num_ciphertexts`
+    /// ciphertexts.
+    pub fn random<R: RngCore + CryptoRng>(
+        num_ciphertexts: usize,
+        par: &Arc<BfvParameters>,
+        rng: &mut R,
+    ) -> Result<Self> {
+        let mut permutation = (0..num_ciphertexts * par.degree()).collect::<Vec<_>>();
+        permutation.shuffle(rng);
+        Self::new(&permutation, par)
+    }
+
+    /// Returns the destination of each slot.
+    pub fn permutation(&self) -> &[usize] {
+        &self.permutation
+    }
+
+    /// Returns the number of ciphertexts permuted.
+    pub fn num_ciphertexts(&self) -> usize {
+        self.permutation.len() / self.par.degree()
+    }
+
+    /// Returns the inverse permutation.
+    pub fn inverse(&self) -> Result<Self> {
+        let mut inverse = vec![0; self.permutation.len()];
+        for (i, j) in self.permutation.iter().enumerate() {
+            inverse[*j] = i
+        }
+        Self::new(&inverse, &self.par)
+    }
+
+    /// Enables in `builder` the rotations needed to apply the permutation.
+    pub fn enable_rotations<K: SecretKeyOperations + Clone>(
+        &self,
+        builder: &mut EvaluationKeyBuilder<K>,
+    ) -> Result<()> {
+        for (_, _, (swap_rows, columns)) in self.masks.keys() {
+            if *swap_rows {
+                builder.enable_row_rotation()?;
+            }
+            if *columns != 0 {
+                builder.enable_column_rotation(*columns)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Applies the permutation to the slots of the ciphertexts `cts`, which
+    /// must be SIMD-encoded, using the evaluation key `ek`.
+    pub fn apply(&self, cts: &[Ciphertext], ek: &EvaluationKey) -> Result<Vec<Ciphertext>> {
+        if cts.len() != self.num_ciphertexts() {
+            return Err(Error::CiphertextSizeMismatch {
+                expected: self.num_ciphertexts(),
+                found: cts.len(),
+            });
+        }
+        for ct in cts {
+            self.par.check_fingerprint(&ct.par)?;
+            if ct.level != cts[0].level {
+                return Err(Error::LevelMismatch {
+                    expected: cts[0].level,
+                    found: ct.level,
+                });
+            }
+        }
+
+        let mut rotated = BTreeMap::<(usize, Rotation), Ciphertext>::new();
+        let mut result = vec![None::<Ciphertext>; cts.len()];
+        for ((destination, source, rotation), mask) in &self.masks {
+            let ct = match rotated.entry((*source, *rotation)) {
+                Entry::Occupied(entry) => entry.into_mut(),
+                Entry::Vacant(entry) => {
+                    let (swap_rows, columns) = *rotation;
+                    let mut ct = cts[*source].clone();
+                    if swap_rows {
+                        ct = ek.rotates_rows(&ct)?;
+                    }
+                    if columns != 0 {
+                        ct = ek.rotates_columns_by(&ct, columns)?;
+                    }
+                    entry.insert(ct)
+                }
+            };
+            let mask = Plaintext::try_encode(mask, Encoding::simd_at_level(ct.level), &self.par)?;
+            let term = &*ct * &mask;
+            match &mut result[*destination] {
+                Some(sum) => *sum += &term,
+                sum => *sum = Some(term),
+            }
+        }
+        Ok(result.into_iter().map(Option::unwrap).collect())
+    }
+
+    /// Applies the permutation, then re-randomizes the ciphertexts with
+    /// encryptions of zero under `pk` and floods their noise, so that they do
+    /// not reveal the permutation to the holder of the secret key.
+    ///
+    /// The ciphertexts are switched to the last level.
+    pub fn apply_oblivious<R: RngCore + CryptoRng>(
+        &self,
+        cts: &[Ciphertext],
+        ek: &EvaluationKey,
+        pk: &PublicKey,
+        rng: &mut R,
+    ) -> Result<Vec<Ciphertext>> {
+        let mut result = self.apply(cts, ek)?;
+        for ct in result.iter_mut() {
+            let zero = Plaintext::zero(Encoding::simd_at_level(ct.level), &self.par)?;
+            *ct += &pk.try_encrypt(&zero, rng)?;
+            ct.mod_switch_to_last_level()?;
+            ct.flood_noise(FLOODING_BITS, rng)?;
+        }
+        Ok(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SlotPermutation;
+    use crate::bfv::{
+        BfvParameters, Encoding, EvaluationKeyBuilder, Plaintext, PublicKey, SecretKey,
+    };
+    use fhe_traits::{FheDecoder, FheDecrypter, FheEncoder, FheEncrypter};
+    use rand::thread_rng;
+    use std::error::Error;
+
+    #[test]
+    fn permutation() -> Result<(), Box<dyn Error>> {
+        let mut rng = thread_rng();
+        let par = BfvParameters::default_arc(3, 16);
+        let sk = SecretKey::random(&par, &mut rng);
+        let pk = PublicKey::new(&sk, &mut rng);
+
+        for num_ciphertexts in 1..=2 {
+            let permutation = SlotPermutation::random(num_ciphertexts, &par, &mut rng)?;
+            let mut builder = EvaluationKeyBuilder::new(&sk)?;
+            permutation.enable_rotations(&mut builder)?;
+            let ek = builder.build(&mut rng)?;
+
+            let values = (0..num_ciphertexts)
+                .map(|_| par.plaintext.random_vec(par.degree(), &mut rng))
+                .collect::<Vec<_>>();
+            let cts = values
+                .iter()
+                .map(|v| {
+                    let pt = Plaintext::try_encode(v, Encoding::simd(), &par)?;
+                    sk.try_encrypt(&pt, &mut rng)
+                })
+                .collect::<crate::Result<Vec<_>>>()?;
+
+            let mut expected = vec![0u64; num_ciphertexts * par.degree()];
+            for (i, j) in permutation.permutation().iter().enumerate() {
+                expected[*j] = values.concat()[i];
+            }
+
+            let decrypt = |cts: &[crate::bfv::Ciphertext]| -> crate::Result<Vec<u64>> {
+                let mut values = vec![];
+                for ct in cts {
+                    let pt = sk.try_decrypt(ct)?;
+                    values.extend(Vec::<u64>::try_decode(
+                        &pt,
+                        Encoding::simd_at_level(ct.level),
+                    )?);
+                }
+                Ok(values)
+            };
+            let permuted = permutation.apply(&cts, &ek)?;
+            assert_eq!(decrypt(&permuted)?, expected);
+            let permuted = permutation.apply_oblivious(&cts, &ek, &pk, &mut rng)?;
+            assert_eq!(decrypt(&permuted)?, expected);
+
+            let permuted = permutation.apply(&cts, &ek)?;
+            let inverse = permutation.inverse()?;
+            let mut builder = EvaluationKeyBuilder::new(&sk)?;
+            inverse.enable_rotations(&mut builder)?;
+            let ek = builder.build(&mut rng)?;
+            assert_eq!(decrypt(&inverse.apply(&permuted, &ek)?)?, values.concat());
+        }
+
+        assert!(SlotPermutation::new(&[0; 16], &par).is_err());
+        assert!(SlotPermutation::new(&(0..8).collect::<Vec<_>>(), &par).is_err());
+        Ok(())
+    }
+}
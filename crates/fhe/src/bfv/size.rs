@@ -0,0 +1,180 @@
+//! Estimation of the size of the serialized ciphertexts and keys.
+
+use std::collections::BTreeSet;
+
+use fhe_math::zq::Modulus;
+use prost::encoding::encoded_len_varint;
+
+use crate::bfv::{keys::KeySwitchingKey, BfvParameters};
+use crate::proto::FORMAT_VERSION;
+use crate::{Error, Result};
+
+/// The size of the seeds of the key switching keys, in bytes.
+const SEED_LENGTH: usize = 32;
+
+/// The size of a `fixed64` field, such as the fingerprints, in bytes.
+const FIXED64_FIELD_LENGTH: usize = 9;
+
+/// Returns the size of a varint field with a value `value`, which is omitted
+/// when the value is 0.
+fn varint_field(value: u64) -> usize {
+    if value == 0 {
+        0
+    } else {
+        1 + encoded_len_varint(value)
+    }
+}
+
+/// Returns the size of a length-delimited field of `length` bytes.
+fn bytes_field(length: usize) -> usize {
+    1 + encoded_len_varint(length as u64) + length
+}
+
+impl BfvParameters {
+    /// Returns the size of a serialized polynomial at level `level`, which
+    /// allows variable time computations as the polynomials of the
+    /// ciphertexts and of the keys do.
+    fn poly_size_bytes(&self, level: usize) -> Result<usize> {
+        let ctx = self.ctx_at_level(level)?;
+        let coefficients = ctx
+            .moduli()
+            .iter()
+            .map(|qi| Modulus::new(*qi).map(|qi| qi.serialization_length(self.degree())))
+            .sum::<fhe_math::Result<usize>>()?;
+        // The representation and the variable time flag are never the default
+        // values, and are always written.
+        Ok(2 + varint_field(self.degree() as u64)
+            + bytes_field(coefficients)
+            + 2
+            + varint_field(FORMAT_VERSION as u64))
+    }
+
+    /// Returns the size of a serialized key switching key from the ciphertext
+    /// level 0 to the key level 0, with or without its seed.
+    fn key_switching_key_size_bytes(&self, with_seed: bool) -> Result<usize> {
+        let ctx = self.ctx_at_level(0)?;
+        let gadget = KeySwitchingKey::default_gadget(ctx, ctx)?;
+        let c0 = bytes_field(self.poly_size_bytes(0)?);
+        let seed = if with_seed {
+            bytes_field(SEED_LENGTH)
+        } else {
+            0
+        };
+        Ok(gadget.len() * c0
+            + seed
+            + varint_field(gadget.log_base() as u64)
+            + FIXED64_FIELD_LENGTH
+            + varint_field(FORMAT_VERSION as u64))
+    }
+
+    /// Returns an estimate of the size in bytes of a serialized ciphertext
+    /// with `degree + 1` polynomials at level `level`, e.g. of degree 1 for a
+    /// fresh ciphertext and of degree 2 for a product before relinearization.
+    ///
+    /// The estimate is exact for the ciphertexts encrypted with a public key
+    /// or resulting from operations, which are tagged with the identifier of
+    /// their key. The ciphertexts freshly encrypted with a secret key store a
+    /// seed instead of their last polynomial, and are smaller.
+    pub fn ciphertext_size_bytes(&self, level: usize, degree: usize) -> Result<usize> {
+        let poly = bytes_field(self.poly_size_bytes(level)?);
+        Ok((degree + 1) * poly
+            + varint_field(level as u64)
+            + 2 * FIXED64_FIELD_LENGTH
+            + varint_field(FORMAT_VERSION as u64))
+    }
+
+    /// Returns the size in bytes of the serialized evaluation keys enabling
+    /// the rotations of the columns by the indices in `rotations`, as built
+    /// by [`EvaluationKeyBuilder`](crate::bfv::EvaluationKeyBuilder) at level
+    /// 0, plus the size of a serialized
+    /// [`RelinearizationKey`](crate::bfv::RelinearizationKey) if `relin` is
+    /// true.
+    ///
+    /// Returns an error if a rotation index is not in `[1, degree / 2)`.
+    pub fn evaluation_keys_size_bytes(&self, rotations: &[usize], relin: bool) -> Result<usize> {
+        let q = Modulus::new(2 * self.degree() as u64)?;
+        let exponents = rotations
+            .iter()
+            .map(|i| {
+                if *i == 0 || *i >= self.degree() / 2 {
+                    Err(Error::UnspecifiedInput(format!("Invalid column index {i}")))
+                } else {
+                    Ok(q.pow(3, *i as u64))
+                }
+            })
+            .collect::<Result<BTreeSet<_>>>()?;
+
+        let mut size = 0;
+        if !exponents.is_empty() {
+            // The seeds of the Galois keys are derived from a master seed.
+            let ksk = bytes_field(self.key_switching_key_size_bytes(false)?);
+            size += exponents
+                .iter()
+                .map(|exponent| bytes_field(ksk + varint_field(*exponent)))
+                .sum::<usize>()
+                + bytes_field(SEED_LENGTH)
+                + varint_field(FORMAT_VERSION as u64);
+        }
+        if relin {
+            size += bytes_field(self.key_switching_key_size_bytes(true)?);
+        }
+        Ok(size)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::bfv::{
+        BfvParameters, Encoding, EvaluationKeyBuilder, Plaintext, PublicKey, RelinearizationKey,
+        SecretKey,
+    };
+    use fhe_traits::{FheEncoder, FheEncrypter, Serialize};
+    use rand::thread_rng;
+    use std::error::Error;
+
+    #[test]
+    fn size_estimates() -> Result<(), Box<dyn Error>> {
+        let mut rng = thread_rng();
+        for par in [
+            BfvParameters::default_arc(2, 16),
+            BfvParameters::default_arc(3, 16),
+            BfvParameters::default_arc(2, 2048),
+        ] {
+            let sk = SecretKey::random(&par, &mut rng);
+            let pk = PublicKey::new(&sk, &mut rng);
+            let rk = RelinearizationKey::new(&sk, &mut rng)?;
+            let pt = Plaintext::try_encode(&[1u64, 2, 3], Encoding::poly(), &par)?;
+            let ct = pk.try_encrypt(&pt, &mut rng)?;
+            assert_eq!(par.ciphertext_size_bytes(0, 1)?, ct.to_bytes().len());
+            let ct3 = &ct * &ct;
+            assert_eq!(par.ciphertext_size_bytes(0, 2)?, ct3.to_bytes().len());
+            for level in 1..=par.max_level() {
+                let pt = Plaintext::try_encode(&[1u64], Encoding::poly_at_level(level), &par)?;
+                let ct = pk.try_encrypt(&pt, &mut rng)?;
+                assert_eq!(par.ciphertext_size_bytes(level, 1)?, ct.to_bytes().len());
+            }
+            assert!(par.ciphertext_size_bytes(par.max_level() + 1, 1).is_err());
+
+            let rotations = [1, 2, 3, 1];
+            let mut builder = EvaluationKeyBuilder::new(&sk)?;
+            for i in rotations {
+                builder.enable_column_rotation(i)?;
+            }
+            let ek = builder.build(&mut rng)?;
+            assert_eq!(
+                par.evaluation_keys_size_bytes(&rotations, true)?,
+                ek.to_bytes().len() + rk.to_bytes().len()
+            );
+            assert_eq!(
+                par.evaluation_keys_size_bytes(&[], true)?,
+                rk.to_bytes().len()
+            );
+            assert_eq!(par.evaluation_keys_size_bytes(&[], false)?, 0);
+            assert!(par.evaluation_keys_size_bytes(&[0], false).is_err());
+            assert!(par
+                .evaluation_keys_size_bytes(&[par.degree() / 2], false)
+                .is_err());
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,135 @@
+//! Homomorphic conversions between the SIMD and the polynomial encodings.
+//!
+//! The slots of a plaintext in the SIMD encoding and the coefficients of the
+//! plaintext polynomial are related by a linear map over the plaintext
+//! modulus, the NTT modulo the plaintext modulus followed by a permutation.
+//! [`SlotTransforms`] evaluates this map or its inverse on the slots of a
+//! ciphertext with [`apply_linear_transform`].
+
+use std::sync::Arc;
+
+use fhe_traits::{FheDecoder, FheEncoder};
+use itertools::Itertools;
+
+use crate::bfv::{
+    apply_linear_transform, BfvParameters, Ciphertext, Encoding, EvaluationKey, Plaintext,
+    PlaintextMatrix,
+};
+use crate::Result;
+
+/// Precomputed plaintexts to convert ciphertexts at a given level between the
+/// SIMD encoding and the polynomial encoding.
+///
+/// The conversions require an [`EvaluationKey`] enabling the row rotation and
+/// the column rotations listed by [`SlotTransforms::column_rotations`].
+pub struct SlotTransforms {
+    slots_to_coefficients: PlaintextMatrix,
+    coefficients_to_slots: PlaintextMatrix,
+}
+
+impl SlotTransforms {
+    /// Precomputes the transforms for ciphertexts at level `level`. Returns an
+    /// error if the parameters do not support the SIMD encoding.
+    pub fn new(par: &Arc<BfvParameters>, level: usize) -> Result<Self> {
+        let poly = Encoding::poly_at_level(level);
+        let simd = Encoding::simd_at_level(level);
+
+        // The columns of the matrices are the images of the unit vectors.
+        let mut unit = vec![0u64; par.degree()];
+        let mut to_slots = Vec::with_capacity(par.degree());
+        let mut to_coefficients = Vec::with_capacity(par.degree());
+        for j in 0..par.degree() {
+            unit[j] = 1;
+            let mut pt = Plaintext::try_encode(&unit, poly.clone(), par)?;
+            pt.encoding = None;
+            to_slots.push(Vec::<u64>::try_decode(&pt, simd.clone())?);
+            let mut pt = Plaintext::try_encode(&unit, simd.clone(), par)?;
+            pt.encoding = None;
+            to_coefficients.push(Vec::<u64>::try_decode(&pt, poly.clone())?);
+            unit[j] = 0;
+        }
+
+        let transpose = |columns: &[Vec<u64>]| {
+            (0..par.degree())
+                .map(|i| columns.iter().map(|column| column[i]).collect_vec())
+                .collect_vec()
+        };
+        Ok(Self {
+            slots_to_coefficients: PlaintextMatrix::new(&transpose(&to_slots), level, par)?,
+            coefficients_to_slots: PlaintextMatrix::new(&transpose(&to_coefficients), level, par)?,
+        })
+    }
+
+    /// Returns the indices of the column rotations that the evaluation key
+    /// must enable.
+    pub fn column_rotations(&self) -> Vec<usize> {
+        self.slots_to_coefficients
+            .column_rotations()
+            .into_iter()
+            .chain(self.coefficients_to_slots.column_rotations())
+            .sorted()
+            .dedup()
+            .collect()
+    }
+
+    /// Converts a ciphertext encrypting a vector in the SIMD encoding into a
+    /// ciphertext encrypting the same vector in the polynomial encoding.
+    pub fn slots_to_coefficients(&self, ct: &Ciphertext, ek: &EvaluationKey) -> Result<Ciphertext> {
+        apply_linear_transform(ct, &self.slots_to_coefficients, ek)
+    }
+
+    /// Converts a ciphertext encrypting a vector in the polynomial encoding
+    /// into a ciphertext encrypting the same vector in the SIMD encoding.
+    pub fn coefficients_to_slots(&self, ct: &Ciphertext, ek: &EvaluationKey) -> Result<Ciphertext> {
+        apply_linear_transform(ct, &self.coefficients_to_slots, ek)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SlotTransforms;
+    use crate::bfv::{BfvParameters, Encoding, EvaluationKeyBuilder, Plaintext, SecretKey};
+    use fhe_traits::{FheDecoder, FheDecrypter, FheEncoder, FheEncrypter};
+    use rand::thread_rng;
+    use std::error::Error;
+
+    #[test]
+    fn slot_transforms() -> Result<(), Box<dyn Error>> {
+        let mut rng = thread_rng();
+        for par in [
+            BfvParameters::default_arc(2, 16),
+            BfvParameters::default_arc(3, 32),
+        ] {
+            let sk = SecretKey::random(&par, &mut rng);
+            for level in [0, 1] {
+                let mut builder = EvaluationKeyBuilder::new_leveled(&sk, level, level)?;
+                let transforms = SlotTransforms::new(&par, level)?;
+                builder.enable_row_rotation()?;
+                for i in transforms.column_rotations() {
+                    builder.enable_column_rotation(i)?;
+                }
+                let ek = builder.build(&mut rng)?;
+
+                let v = par.plaintext.random_vec(par.degree(), &mut rng);
+                let simd = Encoding::simd_at_level(level);
+                let poly = Encoding::poly_at_level(level);
+
+                let pt = Plaintext::try_encode(&v, simd.clone(), &par)?;
+                let ct = sk.try_encrypt(&pt, &mut rng)?;
+                let ct2 = transforms.slots_to_coefficients(&ct, &ek)?;
+                let pt2 = sk.try_decrypt(&ct2)?;
+                assert_eq!(Vec::<u64>::try_decode(&pt2, poly.clone())?, v);
+
+                let pt = Plaintext::try_encode(&v, poly, &par)?;
+                let ct = sk.try_encrypt(&pt, &mut rng)?;
+                let ct2 = transforms.coefficients_to_slots(&ct, &ek)?;
+                let pt2 = sk.try_decrypt(&ct2)?;
+                assert_eq!(Vec::<u64>::try_decode(&pt2, simd)?, v);
+
+                let other = SlotTransforms::new(&par, 1 - level)?;
+                assert!(other.slots_to_coefficients(&ct, &ek).is_err());
+            }
+        }
+        Ok(())
+    }
+}
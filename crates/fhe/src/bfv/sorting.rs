@@ -0,0 +1,392 @@
+//! Comparisons and sorting networks over encrypted data.
+//!
+//! A [`Comparator`] compares two ciphertexts slot-wise by evaluating the
+//! polynomial interpolating the less-than function on their difference, and
+//! swaps them with select masks. A [`SortingNetwork`] sorts the elements of an
+//! [`EncryptedVector`] with layers of such compare-and-swap operations, which
+//! is enough to answer top-k or median queries on encrypted data.
+//!
+//! The values compared must lie in `[0, (t - 1) / 2]` where `t` is an odd prime
+//! plaintext modulus. Each comparison has a multiplicative depth of about
+//! `log2(t)`, so that these operations require small plaintext moduli and many
+//! ciphertext moduli; use [`SortingNetwork::check_parameters`] to check that
+//! the parameters support a network.
+
+use std::sync::Arc;
+
+use fhe_util::is_prime;
+
+use crate::bfv::{noise, BfvParameters, Ciphertext, Multiplicator, RelinearizationKey};
+use crate::{Error, Result};
+
+/// A vector of encrypted elements, each encrypted in its own ciphertext.
+///
+/// The operations are performed slot-wise, so that the slots of the ciphertexts
+/// hold as many independent vectors.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EncryptedVector {
+    elements: Vec<Ciphertext>,
+}
+
+impl EncryptedVector {
+    /// Creates a vector from the ciphertexts encrypting its elements.
+    pub fn new(elements: Vec<Ciphertext>) -> Result<Self> {
+        if elements.is_empty() {
+            return Err(Error::TooFewValues(0, 1));
+        }
+        for ct in &elements[1..] {
+            elements[0].par.check_fingerprint(&ct.par)?;
+        }
+        Ok(Self { elements })
+    }
+
+    /// Returns the number of elements.
+    pub fn len(&self) -> usize {
+        self.elements.len()
+    }
+
+    /// Returns whether the vector is empty, which is never the case.
+    pub fn is_empty(&self) -> bool {
+        self.elements.is_empty()
+    }
+
+    /// Returns the ciphertexts encrypting the elements.
+    pub fn elements(&self) -> &[Ciphertext] {
+        &self.elements
+    }
+
+    /// Returns the ciphertexts encrypting the elements.
+    pub fn into_elements(self) -> Vec<Ciphertext> {
+        self.elements
+    }
+}
+
+/// Compares and swaps ciphertexts slot-wise.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Comparator {
+    par: Arc<BfvParameters>,
+    multiplicator: Multiplicator,
+    /// The coefficients of the polynomial which is 1 on `[(t + 1) / 2, t)` and
+    /// 0 on `[0, (t + 1) / 2)`.
+    less_than: Vec<u64>,
+}
+
+impl Comparator {
+    /// Creates a comparator using the relinearization key `rk`.
+    ///
+    /// Returns an error if the plaintext modulus is not an odd prime.
+    pub fn new(rk: &RelinearizationKey) -> Result<Self> {
+        let par = &rk.ksk.par;
+        let t = &par.plaintext;
+        if t.modulus() == 2 || !is_prime(t.modulus()) {
+            return Err(Error::UnsupportedOperation(
+                "Comparisons require an odd prime plaintext modulus".to_string(),
+            ));
+        }
+
+        // The polynomial sum_{a < 0} (1 - (x - a)^(t - 1)) has coefficients
+        // -sum_{a < 0} a^(t - 1 - k) in degree k > 0, since the binomial
+        // coefficients C(t - 1, k) are congruent to (-1)^k modulo t.
+        let degree = t.modulus() as usize - 1;
+        let mut less_than = vec![0u64; degree + 1];
+        for a in t.modulus().div_ceil(2)..t.modulus() {
+            let mut power = 1;
+            for k in (1..=degree).rev() {
+                less_than[k] = t.sub(less_than[k], power);
+                power = t.mul(power, a);
+            }
+        }
+
+        Ok(Self {
+            par: par.clone(),
+            multiplicator: Multiplicator::default(rk)?,
+            less_than,
+        })
+    }
+
+    /// Returns the largest value which can be compared.
+    pub fn max_value(&self) -> u64 {
+        (self.par.plaintext() - 1) / 2
+    }
+
+    /// Returns an encryption of 1 in the slots where `a < b`, and of 0 in the
+    /// other slots.
+    pub fn less_than(&self, a: &Ciphertext, b: &Ciphertext) -> Result<Ciphertext> {
+        self.multiplicator
+            .evaluate_polynomial(&(a - b), &self.less_than)
+    }
+
+    /// Returns `a` in the slots where `mask` encrypts 1, and `b` in the slots
+    /// where `mask` encrypts 0.
+    pub fn select(&self, mask: &Ciphertext, a: &Ciphertext, b: &Ciphertext) -> Result<Ciphertext> {
+        let mut selected = self.multiplicator.multiply(mask, &(a - b))?;
+        selected += b;
+        Ok(selected)
+    }
+
+    /// Returns the slot-wise minimum and maximum of `a` and `b`.
+    pub fn compare_and_swap(
+        &self,
+        a: &Ciphertext,
+        b: &Ciphertext,
+    ) -> Result<(Ciphertext, Ciphertext)> {
+        let mask = self.less_than(a, b)?;
+        let min = self.select(&mask, a, b)?;
+        let max = &(a + b) - &min;
+        Ok((min, max))
+    }
+}
+
+/// A sorting network, i.e., a data-independent sequence of layers of
+/// compare-and-swap operations on disjoint pairs of elements.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SortingNetwork {
+    len: usize,
+    layers: Vec<Vec<(usize, usize)>>,
+}
+
+impl SortingNetwork {
+    /// Creates Batcher's odd-even merge sorting network for `len` elements,
+    /// which has `O(log2(len)^2)` layers.
+    pub fn batcher(len: usize) -> Self {
+        // The network for the next power of two is truncated, which is
+        // equivalent to padding the vector with larger elements.
+        let padded = len.next_power_of_two();
+        let mut layers = vec![];
+        let mut p = 1;
+        while p < padded {
+            let mut k = p;
+            while k >= 1 {
+                let mut layer = vec![];
+                let mut j = k % p;
+                while j + k < padded {
+                    for i in 0..k.min(padded - j - k) {
+                        let (lo, hi) = (i + j, i + j + k);
+                        if lo / (2 * p) == hi / (2 * p) && hi < len {
+                            layer.push((lo, hi))
+                        }
+                    }
+                    j += 2 * k;
+                }
+                if !layer.is_empty() {
+                    layers.push(layer)
+                }
+                k /= 2;
+            }
+            p *= 2;
+        }
+        Self { len, layers }
+    }
+
+    /// Returns the number of elements sorted by the network.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns whether the network sorts no elements.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns the layers of pairs of elements compared and swapped.
+    pub fn layers(&self) -> &[Vec<(usize, usize)>] {
+        &self.layers
+    }
+
+    /// Returns the number of compare-and-swap operations.
+    pub fn num_comparisons(&self) -> usize {
+        self.layers.iter().map(Vec::len).sum()
+    }
+
+    /// Returns the multiplicative depth of the network with the parameters
+    /// `par`.
+    pub fn multiplicative_depth(&self, par: &BfvParameters) -> usize {
+        self.layers.len() * compare_and_swap_depth(par)
+    }
+
+    /// Checks that the parameters support the network on fresh encryptions
+    /// under the public key, and returns its multiplicative depth.
+    ///
+    /// The noise is estimated heuristically, and an error is returned if it
+    /// exceeds the noise capacity of the ciphertexts, in which case more
+    /// moduli are needed.
+    pub fn check_parameters(&self, par: &BfvParameters) -> Result<usize> {
+        let t = par.plaintext() as f64;
+        let mut estimate = Some(noise::fresh_public_key(par));
+        for _ in 0..self.layers.len() {
+            let difference = noise::add(estimate, estimate);
+            let mut mask = difference;
+            for _ in 1..compare_and_swap_depth(par) {
+                mask = noise::mul(par, mask, mask);
+            }
+            // The powers are multiplied by the coefficients, and summed.
+            mask = mask.map(|e| e + (t * (t - 1.0)).log2());
+            let min = noise::add(noise::mul(par, mask, difference), estimate);
+            estimate = noise::add(noise::add(estimate, estimate), min);
+        }
+        let noise = estimate.unwrap_or_default().ceil() as usize;
+        let capacity = noise::capacity(par, 0).floor().max(0.0) as usize;
+        if noise >= capacity {
+            return Err(Error::NoiseBudgetExceeded { noise, capacity });
+        }
+        Ok(self.multiplicative_depth(par))
+    }
+
+    /// Sorts the elements of `vector` in increasing order, slot-wise.
+    pub fn sort(
+        &self,
+        vector: &EncryptedVector,
+        comparator: &Comparator,
+    ) -> Result<EncryptedVector> {
+        if vector.len() != self.len {
+            return Err(Error::CiphertextSizeMismatch {
+                expected: self.len,
+                found: vector.len(),
+            });
+        }
+        let mut elements = vector.elements.clone();
+        for layer in &self.layers {
+            for (i, j) in layer {
+                let (min, max) = comparator.compare_and_swap(&elements[*i], &elements[*j])?;
+                elements[*i] = min;
+                elements[*j] = max;
+            }
+        }
+        Ok(EncryptedVector { elements })
+    }
+
+    /// Returns the `k` largest elements of `vector`, in increasing order.
+    pub fn top_k(
+        &self,
+        vector: &EncryptedVector,
+        k: usize,
+        comparator: &Comparator,
+    ) -> Result<EncryptedVector> {
+        if k == 0 || k > self.len {
+            return Err(Error::UnspecifiedInput(format!(
+                "Cannot select {k} elements out of {}",
+                self.len
+            )));
+        }
+        let mut elements = self.sort(vector, comparator)?.elements;
+        Ok(EncryptedVector {
+            elements: elements.split_off(self.len - k),
+        })
+    }
+
+    /// Returns the median of the elements of `vector`, i.e., the element of
+    /// index `len / 2` in increasing order.
+    pub fn median(&self, vector: &EncryptedVector, comparator: &Comparator) -> Result<Ciphertext> {
+        let mut elements = self.sort(vector, comparator)?.elements;
+        Ok(elements.swap_remove(self.len / 2))
+    }
+}
+
+/// Returns the multiplicative depth of a compare-and-swap operation.
+fn compare_and_swap_depth(par: &BfvParameters) -> usize {
+    (par.plaintext() as usize - 1)
+        .next_power_of_two()
+        .trailing_zeros() as usize
+        + 1
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Comparator, EncryptedVector, SortingNetwork};
+    use crate::bfv::{
+        BfvParameters, BfvParametersBuilder, Encoding, Plaintext, RelinearizationKey, SecretKey,
+    };
+    use fhe_traits::{FheDecoder, FheDecrypter, FheEncoder, FheEncrypter};
+    use rand::{thread_rng, Rng};
+    use std::error::Error;
+
+    #[test]
+    fn batcher() {
+        for len in 1..=17 {
+            let network = SortingNetwork::batcher(len);
+            assert_eq!(network.len(), len);
+            // By the 0-1 principle, a network sorts all the vectors if it
+            // sorts all the binary vectors.
+            for bits in 0..(1u32 << len) {
+                let mut v = (0..len).map(|i| (bits >> i) & 1).collect::<Vec<_>>();
+                for layer in network.layers() {
+                    for (i, j) in layer {
+                        assert!(i < j);
+                        if v[*i] > v[*j] {
+                            v.swap(*i, *j)
+                        }
+                    }
+                }
+                assert!(v.windows(2).all(|w| w[0] <= w[1]));
+            }
+        }
+        assert_eq!(SortingNetwork::batcher(8).layers().len(), 6);
+        assert_eq!(SortingNetwork::batcher(8).num_comparisons(), 19);
+    }
+
+    #[test]
+    fn sort() -> Result<(), Box<dyn Error>> {
+        let mut rng = thread_rng();
+        let par = BfvParametersBuilder::new()
+            .set_degree(16)
+            .set_plaintext_modulus(97)
+            .set_moduli_sizes(&[62; 8])
+            .set_minimum_security(0)
+            .build_arc()?;
+        let network = SortingNetwork::batcher(4);
+        assert_eq!(network.multiplicative_depth(&par), 24);
+        assert_eq!(network.check_parameters(&par)?, 24);
+        assert!(network
+            .check_parameters(&BfvParameters::default_arc(2, 16))
+            .is_err());
+
+        let sk = SecretKey::random(&par, &mut rng);
+        let rk = RelinearizationKey::new(&sk, &mut rng)?;
+        let comparator = Comparator::new(&rk)?;
+        assert_eq!(comparator.max_value(), 48);
+
+        // Each slot holds an independent vector.
+        let values = (0..network.len())
+            .map(|_| {
+                (0..par.degree())
+                    .map(|_| rng.gen_range(0..=comparator.max_value()))
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+        let vector = EncryptedVector::new(
+            values
+                .iter()
+                .map(|v| {
+                    let pt = Plaintext::try_encode(v, Encoding::simd(), &par)?;
+                    sk.try_encrypt(&pt, &mut rng)
+                })
+                .collect::<crate::Result<Vec<_>>>()?,
+        )?;
+        let decrypt = |ct| -> crate::Result<Vec<u64>> {
+            Vec::<u64>::try_decode(&sk.try_decrypt(ct)?, Encoding::simd())
+        };
+
+        let less_than = comparator.less_than(&vector.elements()[0], &vector.elements()[1])?;
+        let less_than = decrypt(&less_than)?;
+        for (slot, lt) in less_than.iter().enumerate() {
+            assert_eq!(*lt, (values[0][slot] < values[1][slot]) as u64);
+        }
+
+        let sorted = network.sort(&vector, &comparator)?;
+        let sorted = sorted
+            .elements()
+            .iter()
+            .map(decrypt)
+            .collect::<crate::Result<Vec<_>>>()?;
+        for slot in 0..par.degree() {
+            let mut expected = values.iter().map(|v| v[slot]).collect::<Vec<_>>();
+            expected.sort();
+            let found = sorted.iter().map(|v| v[slot]).collect::<Vec<_>>();
+            assert_eq!(found, expected);
+        }
+
+        assert_eq!(network.top_k(&vector, 2, &comparator)?.len(), 2);
+        assert!(network.top_k(&vector, 5, &comparator).is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,120 @@
+//! Conversions of ciphertexts from and to the residues of the coefficients of
+//! their polynomials modulo each ciphertext modulus, which is how the RNS
+//! libraries store their polynomials in coefficient representation, e.g. the
+//! `Coeffs` of a `ring.Poly` in Lattigo or the towers of a `DCRTPoly` in
+//! OpenFHE.
+//!
+//! The libraries decrypt by computing `c_0 + c_1 s + ... + c_d s^d` and
+//! scaling it by `t / Q`, so that a ciphertext can be exchanged as long as the
+//! degree, the ciphertext moduli and the plaintext modulus of the parameters
+//! match, and the secret key is the same. Only the plaintexts encoded as
+//! coefficients with [`Encoding::poly`](crate::bfv::Encoding::poly) have the
+//! same meaning. The binary serializations and the keys of the other libraries
+//! are not supported.
+
+use std::sync::Arc;
+
+use fhe_math::rq::{traits::TryConvertFrom, Poly, Representation};
+use ndarray::Array2;
+
+use crate::bfv::{BfvParameters, Ciphertext};
+use crate::{Error, Result};
+
+/// Returns the towers of coefficients of the polynomials of the
+/// [`Ciphertext`] `ct`, indexed by polynomial, then by modulus.
+pub fn to_towers(ct: &Ciphertext) -> Vec<Vec<Vec<u64>>> {
+    ct.c.iter()
+        .map(|ci| {
+            let mut ci = ci.clone();
+            ci.change_representation(Representation::PowerBasis);
+            ci.coefficients()
+                .outer_iter()
+                .map(|tower| tower.to_vec())
+                .collect()
+        })
+        .collect()
+}
+
+/// Creates a [`Ciphertext`] from the towers of coefficients of its
+/// polynomials, indexed by polynomial, then by modulus. The number of towers
+/// determines the level of the ciphertext.
+pub fn from_towers(towers: &[Vec<Vec<u64>>], par: &Arc<BfvParameters>) -> Result<Ciphertext> {
+    let c = towers
+        .iter()
+        .map(|poly| {
+            if poly.is_empty() || poly.len() > par.moduli().len() {
+                return Err(Error::UnspecifiedInput(format!(
+                    "Invalid number of towers: {}",
+                    poly.len()
+                )));
+            }
+            if let Some(tower) = poly.iter().find(|tower| tower.len() != par.degree()) {
+                return Err(Error::UnspecifiedInput(format!(
+                    "Invalid tower length: {}",
+                    tower.len()
+                )));
+            }
+            let ctx = par.ctx_at_level(par.moduli().len() - poly.len())?;
+            let coefficients = poly.iter().flatten().copied().collect();
+            let coefficients =
+                Array2::from_shape_vec((poly.len(), par.degree()), coefficients).unwrap();
+            let mut ci =
+                Poly::try_convert_from(coefficients, ctx, false, Representation::PowerBasis)?;
+            ci.change_representation(Representation::Ntt);
+            Ok(ci)
+        })
+        .collect::<Result<Vec<_>>>()?;
+    Ciphertext::new(c, par)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{from_towers, to_towers};
+    use crate::bfv::{BfvParameters, Ciphertext, Encoding, Plaintext, SecretKey};
+    use fhe_traits::{FheDecoder, FheDecrypter, FheEncoder, FheEncrypter};
+    use rand::thread_rng;
+    use std::error::Error;
+
+    #[test]
+    fn conversions() -> Result<(), Box<dyn Error>> {
+        let mut rng = thread_rng();
+        let par = BfvParameters::default_arc(3, 16);
+        let sk = SecretKey::random(&par, &mut rng);
+        for level in 0..=par.max_level() {
+            let v = par.plaintext.random_vec(par.degree(), &mut rng);
+            let pt = Plaintext::try_encode(&v, Encoding::poly_at_level(level), &par)?;
+            let ct: Ciphertext = sk.try_encrypt(&pt, &mut rng)?;
+
+            // The number of towers is the number of moduli at the level.
+            let mut towers = to_towers(&ct);
+            assert_eq!(towers.len(), 2);
+            assert!(towers
+                .iter()
+                .all(|poly| poly.len() == par.moduli().len() - level));
+            let ct2 = from_towers(&towers, &par)?;
+            assert_eq!(ct2.level, level);
+            for (c2, c) in ct2.c.iter().zip(ct.c.iter()) {
+                assert_eq!(c2.coefficients(), c.coefficients());
+            }
+            let pt = sk.try_decrypt(&ct2)?;
+            assert_eq!(
+                Vec::<u64>::try_decode(&pt, Encoding::poly_at_level(level))?,
+                v
+            );
+
+            // The coefficients must be reduced, and the towers complete.
+            towers[0][0][0] = u64::MAX;
+            assert!(from_towers(&towers, &par).is_err());
+            towers[0][0].pop();
+            assert!(from_towers(&towers, &par).is_err());
+            assert!(from_towers(&towers[1..], &par).is_err());
+        }
+
+        let other = BfvParameters::default_arc(3, 32);
+        let ct: Ciphertext = sk.try_encrypt(&Plaintext::zero(Encoding::poly(), &par)?, &mut rng)?;
+        assert!(from_towers(&to_towers(&ct), &other).is_err());
+        assert!(from_towers(&[], &par).is_err());
+        assert!(from_towers(&[vec![], vec![]], &par).is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,437 @@
+//! Transciphering of a symmetric cipher into BFV ciphertexts.
+//!
+//! A client encrypts its messages with [`SymmetricCipher`], a stream cipher
+//! over the plaintext modulus whose ciphertexts are as large as the messages,
+//! and sends its key encrypted once under BFV with
+//! [`SymmetricCipher::encrypt_key`]. The server evaluates the keystream
+//! homomorphically and subtracts it from the symmetric ciphertexts with
+//! [`SymmetricCipher::transcipher`], to obtain BFV ciphertexts of the messages
+//! without the client uploading them.
+//!
+//! The cipher follows the structure of PASTA (Dobraunig et al., 2021) with the
+//! parameter sets of [`PastaVariant`]: the state of `2w` words is the key,
+//! split in two branches, and each round applies a random invertible affine
+//! layer to each branch, mixes the branches, and applies a Feistel S-box
+//! `x_i += x_{i-1}^2`, or the cube S-box in the last round. A final affine
+//! layer and mixing follow, and the keystream of a block is the first branch.
+//! The affine layers depend on the nonce and on the block counter, and are
+//! drawn from a [`ChaCha8Rng`] seeded with SHA-256 instead of SHAKE128, so the
+//! keystreams differ from those of the reference implementation of PASTA.
+//!
+//! **This cipher is experimental and insecure.** It is not PASTA, the
+//! generation of its affine layers has not been analyzed, and it must not be
+//! used to protect data.
+//!
+//! The blocks are evaluated in the slots of the ciphertexts: the ciphertext
+//! `i` of a transciphered message contains the word `i` of the block `b` in
+//! its slot `b`.
+
+use std::sync::Arc;
+
+use fhe_traits::{FheEncoder, FheEncrypter};
+use fhe_util::is_prime;
+use itertools::Itertools;
+use rand::{CryptoRng, RngCore, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use sha2::{Digest, Sha256};
+use zeroize::Zeroizing;
+
+use crate::bfv::{
+    dot_product_scalar, BfvParameters, Ciphertext, Encoding, Multiplicator, Plaintext,
+};
+use crate::{Error, Result};
+
+/// The domain separator of the seeds of the affine layers.
+const DOMAIN: &[u8] = b"fhe.rs transciphering";
+
+/// An affine map `x -> matrix * x + constants` of a branch.
+struct Affine {
+    matrix: Vec<Vec<u64>>,
+    constants: Vec<u64>,
+}
+
+/// The parameter sets of PASTA.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PastaVariant {
+    /// Blocks of 128 words and 3 rounds.
+    Pasta3,
+    /// Blocks of 32 words and 4 rounds.
+    Pasta4,
+}
+
+impl PastaVariant {
+    /// Returns the number of words and of rounds of the variant.
+    const fn words_and_rounds(self) -> (usize, usize) {
+        match self {
+            Self::Pasta3 => (128, 3),
+            Self::Pasta4 => (32, 4),
+        }
+    }
+}
+
+/// An experimental and insecure PASTA-style stream cipher over the plaintext
+/// modulus, which can be evaluated homomorphically.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SymmetricCipher {
+    par: Arc<BfvParameters>,
+    words: usize,
+    rounds: usize,
+}
+
+impl SymmetricCipher {
+    /// Creates a cipher with the words and rounds of the PASTA variant
+    /// `variant`. The key has `2 * words` words, and the homomorphic
+    /// evaluation has a multiplicative depth of `rounds + 1`.
+    ///
+    /// Returns an error if the plaintext modulus is not a prime `t` of at least
+    /// 17 bits, as in PASTA, such that 3 does not divide `t - 1`, for which the
+    /// cube is a permutation.
+    pub fn new(par: &Arc<BfvParameters>, variant: PastaVariant) -> Result<Self> {
+        let (words, rounds) = variant.words_and_rounds();
+        let t = &par.plaintext;
+        if t.modulus() < 1 << 16 || t.modulus() % 3 == 1 || !is_prime(t.modulus()) {
+            return Err(Error::UnsupportedOperation(
+                "The plaintext modulus must be a prime t of at least 17 bits with t - 1 not \
+                 divisible by 3"
+                    .to_string(),
+            ));
+        }
+        Ok(Self {
+            par: par.clone(),
+            words,
+            rounds,
+        })
+    }
+
+    /// Generates a random key of `2 * words` words.
+    pub fn generate_key<R: RngCore + CryptoRng>(&self, rng: &mut R) -> Zeroizing<Vec<u64>> {
+        Zeroizing::new(self.par.plaintext.random_vec(2 * self.words, rng))
+    }
+
+    /// Encrypts the message `message`, reduced modulo the plaintext modulus,
+    /// with the key `key` and the nonce `nonce`. A nonce must not be used
+    /// twice with the same key.
+    pub fn encrypt(&self, key: &[u64], nonce: u64, message: &[u64]) -> Result<Vec<u64>> {
+        let t = &self.par.plaintext;
+        self.apply_keystream(key, nonce, message, |m, k| t.add(t.reduce(m), k))
+    }
+
+    /// Decrypts the ciphertext `ciphertext` with the key `key` and the nonce
+    /// `nonce`.
+    pub fn decrypt(&self, key: &[u64], nonce: u64, ciphertext: &[u64]) -> Result<Vec<u64>> {
+        let t = &self.par.plaintext;
+        self.apply_keystream(key, nonce, ciphertext, |c, k| t.sub(t.reduce(c), k))
+    }
+
+    /// Encrypts the key `key` with `encrypter`, a secret or a public key, into
+    /// `2 * words` ciphertexts whose slots all contain a word of the key.
+    pub fn encrypt_key<E, R>(
+        &self,
+        key: &[u64],
+        encrypter: &E,
+        rng: &mut R,
+    ) -> Result<Vec<Ciphertext>>
+    where
+        E: FheEncrypter<Plaintext, Ciphertext, Parameters = BfvParameters, Error = Error>,
+        R: RngCore + CryptoRng,
+    {
+        self.check_key(key)?;
+        key.iter()
+            .map(|k| {
+                let words = Zeroizing::new(vec![*k; self.par.degree()]);
+                let pt = Plaintext::try_encode(words.as_slice(), Encoding::simd(), &self.par)?;
+                encrypter.try_encrypt(&pt, rng)
+            })
+            .collect()
+    }
+
+    /// Converts the symmetric ciphertext `ciphertext`, encrypted with the
+    /// nonce `nonce` under the key encrypted in `encrypted_key`, into `words`
+    /// ciphertexts in the SIMD encoding. The slot `b` of the ciphertext `i`
+    /// contains the word `b * words + i` of the message, or 0 past its end.
+    ///
+    /// The `multiplicator` computes the S-boxes at the level of the encrypted
+    /// key, and returns an error if their noise exceeds the noise budget.
+    /// Returns an error if the message has more than `degree` blocks.
+    pub fn transcipher(
+        &self,
+        encrypted_key: &[Ciphertext],
+        nonce: u64,
+        ciphertext: &[u64],
+        multiplicator: &Multiplicator,
+    ) -> Result<Vec<Ciphertext>> {
+        if encrypted_key.len() != 2 * self.words {
+            return Err(Error::UnspecifiedInput(format!(
+                "The encrypted key must have {} ciphertexts",
+                2 * self.words
+            )));
+        }
+        let blocks = ciphertext.len().div_ceil(self.words);
+        if blocks > self.par.degree() {
+            return Err(Error::TooManyValues(blocks, self.par.degree()));
+        }
+        for ct in encrypted_key {
+            self.par.check_fingerprint(&ct.par)?;
+        }
+        let level = encrypted_key[0].level;
+        let encoding = Encoding::simd_at_level(level);
+        let encode = |values: &[u64]| Plaintext::try_encode(values, encoding.clone(), &self.par);
+
+        // The affine layers of all the blocks, transposed so that the slot `b`
+        // of each plaintext contains an entry of the layer of the block `b`.
+        let layers = (0..blocks as u64)
+            .map(|b| self.affine_layers(nonce, b))
+            .collect_vec();
+        let mut state = encrypted_key.to_vec();
+        for (i, layer) in (0..2 * (self.rounds + 1)).step_by(2).enumerate() {
+            for (branch, offset) in [0, self.words].into_iter().enumerate() {
+                let affine = layers.iter().map(|l| &l[layer + branch]).collect_vec();
+                let input = &state[offset..offset + self.words];
+                let output = (0..self.words)
+                    .map(|j| {
+                        let row = (0..self.words)
+                            .map(|k| encode(&affine.iter().map(|a| a.matrix[j][k]).collect_vec()))
+                            .collect::<Result<Vec<_>>>()?;
+                        let constants =
+                            encode(&affine.iter().map(|a| a.constants[j]).collect_vec())?;
+                        Ok(&dot_product_scalar(input.iter(), row.iter())? + &constants)
+                    })
+                    .collect::<Result<Vec<_>>>()?;
+                state.splice(offset..offset + self.words, output);
+            }
+            self.mix(&mut state, |a, b| a + b);
+            if i < self.rounds {
+                self.s_box(
+                    &mut state,
+                    i + 1 == self.rounds,
+                    |a, b| a + b,
+                    |a, b| multiplicator.multiply(a, b),
+                )?;
+            }
+        }
+
+        let c = ciphertext
+            .iter()
+            .map(|c| self.par.plaintext.reduce(*c))
+            .collect_vec();
+        (0..self.words)
+            .map(|i| {
+                let slots = (0..blocks)
+                    .map(|b| c.get(b * self.words + i).copied().unwrap_or_default())
+                    .collect_vec();
+                let ct = &encode(&slots)? - &state[i];
+                if blocks * self.words + i < ciphertext.len() + self.words {
+                    Ok(ct)
+                } else {
+                    // The last block is incomplete, and its keystream is
+                    // masked out of the slot `blocks - 1`.
+                    let mask = (0..blocks).map(|b| (b + 1 < blocks) as u64).collect_vec();
+                    Ok(&ct * &encode(&mask)?)
+                }
+            })
+            .collect()
+    }
+
+    fn check_key(&self, key: &[u64]) -> Result<()> {
+        let t = self.par.plaintext();
+        if key.len() != 2 * self.words || key.iter().any(|k| *k >= t) {
+            Err(Error::UnspecifiedInput(format!(
+                "The key must have {} words smaller than {t}",
+                2 * self.words
+            )))
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Combines the words of `input` with the keystream using `f`.
+    fn apply_keystream<F>(&self, key: &[u64], nonce: u64, input: &[u64], f: F) -> Result<Vec<u64>>
+    where
+        F: Fn(u64, u64) -> u64,
+    {
+        self.check_key(key)?;
+        let t = &self.par.plaintext;
+        let mut output = Vec::with_capacity(input.len());
+        for (b, chunk) in input.chunks(self.words).enumerate() {
+            let layers = self.affine_layers(nonce, b as u64);
+            let mut state = Zeroizing::new(key.to_vec());
+            for (i, layer) in layers.chunks(2).enumerate() {
+                for (affine, branch) in layer.iter().zip(state.chunks_mut(self.words)) {
+                    let x = Zeroizing::new(branch.to_vec());
+                    for (y, (row, c)) in branch
+                        .iter_mut()
+                        .zip(affine.matrix.iter().zip(&affine.constants))
+                    {
+                        *y = row
+                            .iter()
+                            .zip(x.iter())
+                            .fold(*c, |acc, (m, x)| t.add(acc, t.mul(*m, *x)));
+                    }
+                }
+                self.mix(&mut state, |a, b| t.add(*a, *b));
+                if i < self.rounds {
+                    self.s_box(
+                        &mut state,
+                        i + 1 == self.rounds,
+                        |a, b| t.add(*a, *b),
+                        |a, b| Ok(t.mul(*a, *b)),
+                    )?;
+                }
+            }
+            output.extend(chunk.iter().zip(state.iter()).map(|(x, k)| f(*x, *k)));
+        }
+        Ok(output)
+    }
+
+    /// Replaces the branches `(l, r)` of the state by `(2l + r, l + 2r)`.
+    fn mix<T, F>(&self, state: &mut [T], add: F)
+    where
+        F: Fn(&T, &T) -> T,
+    {
+        let (l, r) = state.split_at_mut(self.words);
+        for (l, r) in l.iter_mut().zip(r.iter_mut()) {
+            let sum = add(l, r);
+            *l = add(&sum, l);
+            *r = add(&sum, r);
+        }
+    }
+
+    /// Applies the Feistel S-box `x_i += x_{i-1}^2` to each branch of the
+    /// state, or the cube S-box to all the words if `last` is true.
+    fn s_box<T, F, G>(&self, state: &mut [T], last: bool, add: F, mul: G) -> Result<()>
+    where
+        F: Fn(&T, &T) -> T,
+        G: Fn(&T, &T) -> Result<T>,
+    {
+        if last {
+            for x in state.iter_mut() {
+                *x = mul(&mul(x, x)?, x)?;
+            }
+        } else {
+            for branch in state.chunks_mut(self.words) {
+                let squares = branch[..self.words - 1]
+                    .iter()
+                    .map(|x| mul(x, x))
+                    .collect::<Result<Vec<_>>>()?;
+                for (x, square) in branch[1..].iter_mut().zip(&squares) {
+                    *x = add(x, square);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the `2 * (rounds + 1)` affine layers of the block `block`, in
+    /// the order in which they are applied, alternating between the branches.
+    fn affine_layers(&self, nonce: u64, block: u64) -> Vec<Affine> {
+        let seed = Sha256::new()
+            .chain_update(DOMAIN)
+            .chain_update(nonce.to_le_bytes())
+            .chain_update(block.to_le_bytes())
+            .finalize();
+        let mut rng = ChaCha8Rng::from_seed(seed.into());
+        let t = &self.par.plaintext;
+        (0..2 * (self.rounds + 1))
+            .map(|_| {
+                let matrix = loop {
+                    let matrix = (0..self.words)
+                        .map(|_| t.random_vec(self.words, &mut rng))
+                        .collect_vec();
+                    if self.is_invertible(&matrix) {
+                        break matrix;
+                    }
+                };
+                Affine {
+                    matrix,
+                    constants: t.random_vec(self.words, &mut rng),
+                }
+            })
+            .collect()
+    }
+
+    /// Returns whether the matrix `matrix` is invertible modulo the prime
+    /// plaintext modulus, using Gaussian elimination.
+    fn is_invertible(&self, matrix: &[Vec<u64>]) -> bool {
+        let t = &self.par.plaintext;
+        let mut m = matrix.to_vec();
+        for j in 0..self.words {
+            let Some(pivot) = (j..self.words).find(|i| m[*i][j] != 0) else {
+                return false;
+            };
+            m.swap(j, pivot);
+            let (above, below) = m.split_at_mut(j + 1);
+            let pivot = &above[j];
+            let inverse = t.inv(pivot[j]).unwrap();
+            for row in below {
+                let factor = t.mul(row[j], inverse);
+                for (x, p) in row[j..].iter_mut().zip(&pivot[j..]) {
+                    *x = t.sub(*x, t.mul(factor, *p));
+                }
+            }
+        }
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{PastaVariant, SymmetricCipher};
+    use crate::bfv::{
+        BfvParameters, BfvParametersBuilder, Encoding, Multiplicator, PublicKey,
+        RelinearizationKey, SecretKey,
+    };
+    use fhe_traits::{FheDecoder, FheDecrypter};
+    use rand::thread_rng;
+    use std::error::Error;
+
+    #[test]
+    fn transcipher() -> Result<(), Box<dyn Error>> {
+        let mut rng = thread_rng();
+        let par = BfvParametersBuilder::new()
+            .set_degree(16)
+            .set_plaintext_modulus(65537)
+            .set_moduli_sizes(&[62; 5])
+            .set_minimum_security(0)
+            .build_arc()?;
+        let sk = SecretKey::random(&par, &mut rng);
+        let pk = PublicKey::new(&sk, &mut rng);
+        let rk = RelinearizationKey::new(&sk, &mut rng)?;
+        let multiplicator = Multiplicator::default(&rk)?;
+
+        let words = 32;
+        let cipher = SymmetricCipher::new(&par, PastaVariant::Pasta4)?;
+        let key = cipher.generate_key(&mut rng);
+        let message = par.plaintext.random_vec(words * 5 + 3, &mut rng);
+        let ciphertext = cipher.encrypt(&key, 7, &message)?;
+        assert_ne!(ciphertext, message);
+        assert_eq!(cipher.decrypt(&key, 7, &ciphertext)?, message);
+        assert_ne!(cipher.decrypt(&key, 8, &ciphertext)?, message);
+
+        let encrypted_key = cipher.encrypt_key(&key, &pk, &mut rng)?;
+        let cts = cipher.transcipher(&encrypted_key, 7, &ciphertext, &multiplicator)?;
+        assert_eq!(cts.len(), words);
+        for (i, ct) in cts.iter().enumerate() {
+            let pt = sk.try_decrypt(ct)?;
+            let slots = Vec::<u64>::try_decode(&pt, Encoding::simd())?;
+            for (b, slot) in slots.iter().enumerate() {
+                let expected = message.get(b * words + i).copied().unwrap_or_default();
+                assert_eq!(*slot, expected);
+            }
+        }
+
+        let too_long = vec![0u64; words * par.degree() + 1];
+        assert!(cipher
+            .transcipher(&encrypted_key, 7, &too_long, &multiplicator)
+            .is_err());
+        assert!(cipher
+            .transcipher(&encrypted_key[1..], 7, &ciphertext, &multiplicator)
+            .is_err());
+        assert!(cipher.encrypt(&key[1..], 7, &message).is_err());
+        let pasta3 = SymmetricCipher::new(&par, PastaVariant::Pasta3)?;
+        assert_eq!(pasta3.generate_key(&mut rng).len(), 256);
+        assert!(
+            SymmetricCipher::new(&BfvParameters::default_arc(1, 16), PastaVariant::Pasta4).is_err()
+        );
+        Ok(())
+    }
+}
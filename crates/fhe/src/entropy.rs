@@ -0,0 +1,254 @@
+//! Sources of randomness.
+//!
+//! The operations of the library take their random number generator as an
+//! argument. [`EntropySource`] describes where these generators draw their
+//! randomness from, so that applications can satisfy the requirements of
+//! their deployment in one place: the operating system (the default), a
+//! ChaCha20 DRBG periodically reseeded from the operating system, or a fixed
+//! seed for reproducible tests. The bindings use the process-wide source set
+//! with [`set_default`], through [`rng`].
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::RwLock;
+
+use rand::rngs::OsRng;
+use rand::{CryptoRng, RngCore, SeedableRng};
+use rand_chacha::ChaCha20Rng;
+use zeroize::Zeroize;
+
+use crate::{Error, Result};
+
+/// The default number of bytes generated by a DRBG between two reseedings.
+pub const DEFAULT_RESEED_INTERVAL: u64 = 1 << 20;
+
+/// A source of randomness.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub enum EntropySource {
+    /// The random number generator of the operating system.
+    #[default]
+    Os,
+    /// A ChaCha20 DRBG seeded from the operating system, and reseeded from it
+    /// after generating `reseed_interval` bytes.
+    Drbg {
+        /// The number of bytes generated between two reseedings.
+        reseed_interval: u64,
+    },
+    /// A ChaCha20 generator with a fixed seed, whose outputs are
+    /// reproducible. This source is insecure, and must only be used in tests.
+    Deterministic([u8; 32]),
+}
+
+impl EntropySource {
+    /// Returns a DRBG source with the default reseed interval.
+    pub const fn drbg() -> Self {
+        Self::Drbg {
+            reseed_interval: DEFAULT_RESEED_INTERVAL,
+        }
+    }
+
+    /// Returns a random number generator drawing from this source.
+    ///
+    /// Returns an error if the reseed interval of a DRBG is zero, or if the
+    /// random number generator of the operating system fails.
+    pub fn rng(&self) -> Result<EntropyRng> {
+        self.rng_with_stream(0)
+    }
+
+    /// Returns a random number generator drawing from this source, using the
+    /// stream `stream` of a deterministic source.
+    fn rng_with_stream(&self, stream: u64) -> Result<EntropyRng> {
+        let inner = match self {
+            Self::Os => Inner::Os,
+            Self::Drbg { reseed_interval } => {
+                if *reseed_interval == 0 {
+                    return Err(Error::UnspecifiedInput(
+                        "The reseed interval must be positive".to_string(),
+                    ));
+                }
+                Inner::Drbg {
+                    rng: ChaCha20Rng::from_rng(OsRng)
+                        .map_err(|e| Error::UnspecifiedInput(e.to_string()))?,
+                    generated: 0,
+                    reseed_interval: *reseed_interval,
+                }
+            }
+            Self::Deterministic(seed) => {
+                let mut rng = ChaCha20Rng::from_seed(*seed);
+                rng.set_stream(stream);
+                Inner::Deterministic(rng)
+            }
+        };
+        Ok(EntropyRng { inner })
+    }
+}
+
+/// A random number generator drawing from an [`EntropySource`].
+#[derive(Debug)]
+pub struct EntropyRng {
+    inner: Inner,
+}
+
+#[derive(Debug)]
+enum Inner {
+    Os,
+    Drbg {
+        rng: ChaCha20Rng,
+        generated: u64,
+        reseed_interval: u64,
+    },
+    Deterministic(ChaCha20Rng),
+}
+
+impl EntropyRng {
+    /// Reseeds a DRBG which generated `reseed_interval` bytes, before
+    /// generating `len` more bytes.
+    fn before_generating(&mut self, len: usize) -> std::result::Result<(), rand::Error> {
+        if let Inner::Drbg {
+            rng,
+            generated,
+            reseed_interval,
+        } = &mut self.inner
+        {
+            if *generated >= *reseed_interval {
+                let mut seed = <ChaCha20Rng as SeedableRng>::Seed::default();
+                OsRng.try_fill_bytes(&mut seed)?;
+                *rng = ChaCha20Rng::from_seed(seed);
+                seed.zeroize();
+                *generated = 0;
+            }
+            *generated = generated.saturating_add(len as u64);
+        }
+        Ok(())
+    }
+}
+
+impl RngCore for EntropyRng {
+    fn next_u32(&mut self) -> u32 {
+        let mut bytes = [0u8; 4];
+        self.fill_bytes(&mut bytes);
+        u32::from_le_bytes(bytes)
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        let mut bytes = [0u8; 8];
+        self.fill_bytes(&mut bytes);
+        u64::from_le_bytes(bytes)
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        self.try_fill_bytes(dest).unwrap()
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> std::result::Result<(), rand::Error> {
+        self.before_generating(dest.len())?;
+        match &mut self.inner {
+            Inner::Os => OsRng.try_fill_bytes(dest),
+            Inner::Drbg { rng, .. } | Inner::Deterministic(rng) => rng.try_fill_bytes(dest),
+        }
+    }
+}
+
+impl CryptoRng for EntropyRng {}
+
+/// The process-wide source of randomness.
+static DEFAULT: RwLock<EntropySource> = RwLock::new(EntropySource::Os);
+
+/// The number of generators created from the process-wide source, which
+/// indexes the streams of a deterministic source.
+static STREAMS: AtomicU64 = AtomicU64::new(0);
+
+/// Sets the process-wide source of randomness, after checking that it can
+/// create generators.
+pub fn set_default(source: EntropySource) -> Result<()> {
+    source.rng()?;
+    *DEFAULT.write().unwrap() = source;
+    STREAMS.store(0, Ordering::SeqCst);
+    Ok(())
+}
+
+/// Returns the process-wide source of randomness.
+pub fn default_source() -> EntropySource {
+    DEFAULT.read().unwrap().clone()
+}
+
+/// Returns a random number generator drawing from the process-wide source.
+/// The successive generators of a deterministic source use distinct streams.
+///
+/// # Panics
+///
+/// Panics if the random number generator of the operating system fails.
+pub fn rng() -> EntropyRng {
+    let stream = STREAMS.fetch_add(1, Ordering::SeqCst);
+    DEFAULT
+        .read()
+        .unwrap()
+        .rng_with_stream(stream)
+        .expect("The process-wide source of randomness failed")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{default_source, rng, set_default, EntropySource, Inner};
+    use crate::bfv::{BfvParameters, Ciphertext, Encoding, Plaintext, SecretKey};
+    use fhe_traits::{FheDecoder, FheDecrypter, FheEncoder, FheEncrypter};
+    use rand::RngCore;
+    use std::error::Error;
+
+    #[test]
+    fn entropy_source() -> Result<(), Box<dyn Error>> {
+        assert_eq!(default_source(), EntropySource::Os);
+        let par = BfvParameters::default_arc(2, 16);
+        let pt = Plaintext::try_encode(&[1u64, 2, 3], Encoding::poly(), &par)?;
+
+        for source in [
+            EntropySource::Os,
+            EntropySource::drbg(),
+            EntropySource::Drbg { reseed_interval: 8 },
+            EntropySource::Deterministic([7; 32]),
+        ] {
+            let mut source_rng = source.rng()?;
+            let sk = SecretKey::random(&par, &mut source_rng);
+            let ct: Ciphertext = sk.try_encrypt(&pt, &mut source_rng)?;
+            let decrypted = Vec::<u64>::try_decode(&sk.try_decrypt(&ct)?, Encoding::poly())?;
+            assert_eq!(&decrypted[..3], &[1, 2, 3]);
+        }
+
+        // The DRBG reseeds after the interval.
+        let mut drbg = EntropySource::Drbg { reseed_interval: 8 }.rng()?;
+        drbg.next_u64();
+        let Inner::Drbg { generated, .. } = drbg.inner else {
+            unreachable!()
+        };
+        assert_eq!(generated, 8);
+        let mut drbg = EntropySource::Drbg { reseed_interval: 8 }.rng()?;
+        drbg.next_u64();
+        drbg.next_u32();
+        let Inner::Drbg { generated, .. } = drbg.inner else {
+            unreachable!()
+        };
+        assert_eq!(generated, 4);
+        assert!(EntropySource::Drbg { reseed_interval: 0 }.rng().is_err());
+
+        // Deterministic sources are reproducible.
+        let source = EntropySource::Deterministic([7; 32]);
+        assert_eq!(source.rng()?.next_u64(), source.rng()?.next_u64());
+        let sk = SecretKey::random(&par, &mut source.rng()?);
+        assert_eq!(sk, SecretKey::random(&par, &mut source.rng()?));
+
+        // The process-wide deterministic source uses a new stream for each
+        // generator.
+        set_default(source.clone())?;
+        assert_eq!(default_source(), source);
+        assert_ne!(rng().next_u64(), rng().next_u64());
+        set_default(source)?;
+        let first = rng().next_u64();
+        set_default(EntropySource::Os)?;
+        assert_eq!(
+            first,
+            EntropySource::Deterministic([7; 32]).rng()?.next_u64()
+        );
+        assert!(set_default(EntropySource::Drbg { reseed_interval: 0 }).is_err());
+        assert_eq!(default_source(), EntropySource::Os);
+        Ok(())
+    }
+}
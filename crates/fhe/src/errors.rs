@@ -9,7 +9,7 @@ pub enum Error {
     /// Indicates that an error from the underlying mathematical library was
     /// encountered.
     #[error("{0}")]
-    MathError(fhe_math::Error),
+    MathError(#[source] fhe_math::Error),
 
     /// Indicates a serialization error.
     #[error("Serialization error")]
@@ -37,12 +37,100 @@ pub enum Error {
 
     /// Indicates a parameter error.
     #[error("{0}")]
-    ParametersError(ParametersError),
+    ParametersError(#[source] ParametersError),
 
-    /// Indicates a default error
-    /// TODO: To delete eventually
-    #[error("{0}")]
-    DefaultError(String),
+    /// Indicates that objects were created with parameters of different
+    /// fingerprints.
+    #[error("Parameters mismatch: found parameters {found:016x}, expected {expected:016x}")]
+    ParameterMismatch {
+        /// The fingerprint of the expected parameters.
+        expected: u64,
+        /// The fingerprint of the parameters found.
+        found: u64,
+    },
+
+    /// Indicates that ciphertexts were encrypted under keys of different
+    /// identifiers, or that a ciphertext is decrypted with another key.
+    #[error("Key mismatch: found key {found:016x}, expected {expected:016x}")]
+    KeyMismatch {
+        /// The identifier of the expected key.
+        expected: u64,
+        /// The identifier of the key found.
+        found: u64,
+    },
+
+    /// Indicates that objects are at different levels.
+    #[error("Level mismatch: found level {found}, expected {expected}")]
+    LevelMismatch {
+        /// The expected level.
+        expected: usize,
+        /// The level found.
+        found: usize,
+    },
+
+    /// Indicates that a level exceeds the maximum level of the parameters.
+    #[error("Invalid level {level}: the maximum level is {max_level}")]
+    InvalidLevel {
+        /// The level requested.
+        level: usize,
+        /// The maximum level of the parameters.
+        max_level: usize,
+    },
+
+    /// Indicates that a ciphertext does not have the expected number of
+    /// polynomials.
+    #[error("Ciphertext size mismatch: found {found} polynomials, expected {expected}")]
+    CiphertextSizeMismatch {
+        /// The expected number of polynomials.
+        expected: usize,
+        /// The number of polynomials found.
+        found: usize,
+    },
+
+    /// Indicates that an operation is not supported.
+    #[error("Unsupported operation: {0}")]
+    UnsupportedOperation(String),
+
+    /// Indicates that a key store failed to store or retrieve a key.
+    #[error("Key store error: {0}")]
+    KeyStoreError(String),
+
+    /// Indicates that reading from or writing to a stream failed.
+    #[error("I/O error: {0}")]
+    IoError(String),
+
+    /// Indicates that a known-answer test failed.
+    #[error("Known-answer test failed: {0}")]
+    KnownAnswerTestFailed(String),
+
+    /// Indicates that the heuristic estimate of the noise of a ciphertext
+    /// exceeds its noise budget, so that it would likely decrypt incorrectly.
+    #[error("Noise budget exceeded: estimated noise of {noise} bits, capacity of {capacity} bits")]
+    NoiseBudgetExceeded {
+        /// The estimated noise, in bits.
+        noise: usize,
+        /// The noise capacity of the ciphertext, in bits.
+        capacity: usize,
+    },
+
+    /// Indicates that the verification of a decryption failed, which may be
+    /// caused by a fault injection.
+    #[error("Decryption verification failed")]
+    DecryptionVerificationFailed,
+
+    /// Indicates that a serialization uses an unsupported format version.
+    #[error("Unsupported serialization format version {0}")]
+    UnsupportedVersion(u32),
+
+    /// Indicates that the integrity tag of a serialization does not match its
+    /// content, which may be caused by a corruption or a tampering.
+    #[error("Integrity check failed")]
+    IntegrityCheckFailed,
+
+    /// Indicates that a value is not invertible modulo a modulus, such as the
+    /// plaintext modulus.
+    #[error("{0} is not invertible modulo {1}")]
+    NotInvertible(u64, u64),
 }
 
 impl From<fhe_math::Error> for Error {
@@ -51,17 +139,61 @@ impl From<fhe_math::Error> for Error {
     }
 }
 
+impl From<std::io::Error> for Error {
+    fn from(e: std::io::Error) -> Self {
+        Error::IoError(e.to_string())
+    }
+}
+
 /// Separate enum to indicate parameters-related errors.
 #[derive(Debug, Error, PartialEq, Eq)]
 pub enum ParametersError {
     /// Indicates that the degree is invalid.
-    #[error("Invalid degree: {0} is not a power of 2 larger than 8")]
+    #[error("Invalid degree: {0} is not a power of 2 larger or equal to 2")]
     InvalidDegree(usize),
 
+    /// Indicates that the estimated security is below the minimum security,
+    /// with the maximum number of bits of the ciphertext modulus which would
+    /// provide the minimum security.
+    #[error(
+        "Insufficient security: about {0} bits, expected at least {1} bits; the ciphertext modulus should have at most {2} bits"
+    )]
+    InsufficientSecurity(usize, usize, usize),
+
+    /// Indicates that a ciphertext modulus is not a prime, with the nearest
+    /// prime supporting the NTT.
+    #[error("Invalid ciphertext modulus {0}: expected a prime, such as {1}")]
+    InvalidCiphertextModulus(u64, u64),
+
+    /// Indicates that a ciphertext modulus is specified more than once.
+    #[error("Duplicate ciphertext modulus {0}")]
+    DuplicateCiphertextModulus(u64),
+
+    /// Indicates that the plaintext modulus is a multiple of a ciphertext
+    /// modulus.
+    #[error("The plaintext modulus {0} is a multiple of the ciphertext modulus {1}")]
+    PlaintextNotCoprime(u64, u64),
+
+    /// Indicates that the plaintext modulus does not support the SIMD
+    /// encoding, with the nearest plaintext modulus which does.
+    #[error(
+        "The plaintext modulus {0} does not support the SIMD encoding: expected a prime congruent to 1 modulo {1}, such as {2}"
+    )]
+    PlaintextNotSimdFriendly(u64, u64, u64),
+
+    /// Indicates that the standard does not define parameters for this degree
+    /// and security level.
+    #[error("No standard parameters for degree {0} and {1} bits of security")]
+    NoStandardParameters(usize, usize),
+
     /// Indicates that the moduli sizes are invalid.
     #[error("Invalid modulus size: {0}, expected an integer between {1} and {2}")]
     InvalidModulusSize(usize, usize, usize),
 
+    /// Indicates that the variance is invalid.
+    #[error("Invalid variance: {0}, expected an integer between 1 and {1}")]
+    InvalidVariance(usize, usize),
+
     /// Indicates that there exists not enough primes of this size.
     #[error("Not enough primes of size {0} for polynomials of degree {1}")]
     NotEnoughPrimes(usize, usize),
@@ -70,6 +202,11 @@ pub enum ParametersError {
     #[error("{0}")]
     InvalidPlaintext(String),
 
+    /// Indicates that an index of the order in which the moduli are dropped
+    /// is out of range or repeated, with the number of moduli.
+    #[error("Invalid drop order: index {0} is out of range or repeated for {1} moduli")]
+    InvalidDropOrder(usize, usize),
+
     /// Indicates that too many parameters were specified.
     #[error("{0}")]
     TooManySpecified(String),
@@ -114,22 +251,123 @@ mod tests {
             Error::ParametersError(ParametersError::InvalidDegree(10)).to_string(),
             ParametersError::InvalidDegree(10).to_string()
         );
+        assert_eq!(
+            Error::ParameterMismatch {
+                expected: 0xabcdef0123456789,
+                found: 0x1234
+            }
+            .to_string(),
+            "Parameters mismatch: found parameters 0000000000001234, expected abcdef0123456789"
+        );
+        assert_eq!(
+            Error::KeyMismatch {
+                expected: 0xabcdef0123456789,
+                found: 0x1234
+            }
+            .to_string(),
+            "Key mismatch: found key 0000000000001234, expected abcdef0123456789"
+        );
+        assert_eq!(
+            Error::LevelMismatch {
+                expected: 1,
+                found: 2
+            }
+            .to_string(),
+            "Level mismatch: found level 2, expected 1"
+        );
+        assert_eq!(
+            Error::InvalidLevel {
+                level: 3,
+                max_level: 2
+            }
+            .to_string(),
+            "Invalid level 3: the maximum level is 2"
+        );
+        assert_eq!(
+            Error::CiphertextSizeMismatch {
+                expected: 2,
+                found: 3
+            }
+            .to_string(),
+            "Ciphertext size mismatch: found 3 polynomials, expected 2"
+        );
+        assert_eq!(
+            Error::UnsupportedOperation("test".to_string()).to_string(),
+            "Unsupported operation: test"
+        );
+        assert_eq!(
+            Error::KnownAnswerTestFailed("test".to_string()).to_string(),
+            "Known-answer test failed: test"
+        );
+        assert_eq!(
+            Error::NotInvertible(4, 8).to_string(),
+            "4 is not invertible modulo 8"
+        );
+    }
+
+    #[test]
+    fn source() {
+        use std::error::Error as StdError;
+
+        let error = Error::MathError(fhe_math::Error::InvalidContext);
+        assert_eq!(
+            error.source().unwrap().to_string(),
+            fhe_math::Error::InvalidContext.to_string()
+        );
+        let error = Error::ParametersError(ParametersError::InvalidDegree(10));
+        assert_eq!(
+            error.source().unwrap().to_string(),
+            ParametersError::InvalidDegree(10).to_string()
+        );
+        assert!(Error::SerializationError.source().is_none());
     }
 
     #[test]
     fn parameters_error_strings() {
         assert_eq!(
             ParametersError::InvalidDegree(10).to_string(),
-            "Invalid degree: 10 is not a power of 2 larger than 8"
+            "Invalid degree: 10 is not a power of 2 larger or equal to 2"
+        );
+        assert_eq!(
+            ParametersError::InsufficientSecurity(100, 128, 109).to_string(),
+            "Insufficient security: about 100 bits, expected at least 128 bits; the ciphertext modulus should have at most 109 bits"
+        );
+        assert_eq!(
+            ParametersError::InvalidCiphertextModulus(1155, 1153).to_string(),
+            "Invalid ciphertext modulus 1155: expected a prime, such as 1153"
+        );
+        assert_eq!(
+            ParametersError::DuplicateCiphertextModulus(1153).to_string(),
+            "Duplicate ciphertext modulus 1153"
+        );
+        assert_eq!(
+            ParametersError::PlaintextNotCoprime(2306, 1153).to_string(),
+            "The plaintext modulus 2306 is a multiple of the ciphertext modulus 1153"
+        );
+        assert_eq!(
+            ParametersError::PlaintextNotSimdFriendly(1155, 32, 1153).to_string(),
+            "The plaintext modulus 1155 does not support the SIMD encoding: expected a prime congruent to 1 modulo 32, such as 1153"
+        );
+        assert_eq!(
+            ParametersError::NoStandardParameters(16, 128).to_string(),
+            "No standard parameters for degree 16 and 128 bits of security"
         );
         assert_eq!(
             ParametersError::InvalidModulusSize(1, 2, 3).to_string(),
             "Invalid modulus size: 1, expected an integer between 2 and 3"
         );
+        assert_eq!(
+            ParametersError::InvalidVariance(0, 16).to_string(),
+            "Invalid variance: 0, expected an integer between 1 and 16"
+        );
         assert_eq!(
             ParametersError::NotEnoughPrimes(1, 2).to_string(),
             "Not enough primes of size 1 for polynomials of degree 2"
         );
+        assert_eq!(
+            ParametersError::InvalidDropOrder(4, 3).to_string(),
+            "Invalid drop order: index 4 is out of range or repeated for 3 moduli"
+        );
         assert_eq!(
             ParametersError::InvalidPlaintext("test".to_string()).to_string(),
             "test"
@@ -0,0 +1,359 @@
+//! A server and a client of the evaluation service of `service.proto`.
+//!
+//! A client registers its parameters, which opens a session, uploads its
+//! evaluation and relinearization keys, then streams requests to evaluate
+//! functions on its ciphertexts and receives the results in order. The
+//! [`EvaluationService`] is a skeleton of such a server, which evaluates the
+//! functions `add`, `mul` and `inner_sum`; the [`EvaluationClient`] is the
+//! client generated by `tonic-build`.
+
+use std::collections::HashMap;
+use std::pin::Pin;
+use std::sync::{Arc, RwLock};
+
+use fhe_traits::Deserialize;
+use prost::Message;
+use rand::{rngs::OsRng, RngCore};
+use tonic::codegen::tokio_stream::{Stream, StreamExt};
+use tonic::{Request, Response, Status, Streaming};
+
+use crate::bfv::{
+    traits::TryConvertFrom, BfvParameters, Ciphertext, EvaluationKey, Multiplicator,
+    RelinearizationKey,
+};
+use crate::proto::bfv::Ciphertext as CiphertextProto;
+use crate::proto::service::{
+    upload_keys_request::Key, EvaluateRequest, EvaluateResponse, RegisterRequest, RegisterResponse,
+    UploadKeysRequest, UploadKeysResponse,
+};
+use crate::{Error, Result};
+
+pub use crate::proto::service::evaluation_client::EvaluationClient;
+pub use crate::proto::service::evaluation_server::{Evaluation, EvaluationServer};
+
+/// The length of the session identifiers, in bytes.
+const SESSION_ID_LENGTH: usize = 16;
+
+/// The parameters and keys of a client.
+struct Session {
+    par: Arc<BfvParameters>,
+    ek: Option<EvaluationKey>,
+    rk: Option<RelinearizationKey>,
+}
+
+impl Session {
+    /// Evaluates the function `function` on the ciphertexts `inputs`.
+    fn evaluate(&self, function: &str, inputs: &[CiphertextProto]) -> Result<Vec<Ciphertext>> {
+        let inputs = inputs
+            .iter()
+            .map(|ct| Ciphertext::try_convert_from(ct, &self.par))
+            .collect::<Result<Vec<_>>>()?;
+        match function {
+            "add" => {
+                let (first, rest) = inputs.split_first().ok_or(Error::TooFewValues(0, 1))?;
+                let sum = rest
+                    .iter()
+                    .try_fold(first.clone(), |acc, ct| acc.try_add(ct))?;
+                Ok(vec![sum])
+            }
+            "mul" => {
+                let [lhs, rhs] = inputs.as_slice() else {
+                    return Err(Error::UnspecifiedInput(
+                        "mul expects 2 ciphertexts".to_string(),
+                    ));
+                };
+                let rk = self.rk.as_ref().ok_or_else(|| {
+                    Error::UnsupportedOperation("No relinearization key".to_string())
+                })?;
+                Ok(vec![Multiplicator::default(rk)?.multiply(lhs, rhs)?])
+            }
+            "inner_sum" => {
+                let ek = self
+                    .ek
+                    .as_ref()
+                    .ok_or_else(|| Error::UnsupportedOperation("No evaluation key".to_string()))?;
+                inputs.iter().map(|ct| ek.computes_inner_sum(ct)).collect()
+            }
+            _ => Err(Error::UnsupportedOperation(format!(
+                "Unknown function {function}"
+            ))),
+        }
+    }
+}
+
+/// A skeleton of a server of the evaluation service, which keeps the sessions
+/// of its clients in memory.
+#[derive(Default, Clone)]
+pub struct EvaluationService {
+    sessions: Arc<RwLock<HashMap<Vec<u8>, Session>>>,
+}
+
+impl EvaluationService {
+    /// Creates a server without sessions.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the number of open sessions.
+    pub fn num_sessions(&self) -> usize {
+        self.sessions.read().unwrap().len()
+    }
+}
+
+/// Converts an error of the library into a status of the invalid argument
+/// code.
+fn invalid_argument(e: Error) -> Status {
+    Status::invalid_argument(e.to_string())
+}
+
+#[tonic::async_trait]
+impl Evaluation for EvaluationService {
+    async fn register(
+        &self,
+        request: Request<RegisterRequest>,
+    ) -> std::result::Result<Response<RegisterResponse>, Status> {
+        let request = request.into_inner();
+        let parameters = request
+            .parameters
+            .ok_or_else(|| Status::invalid_argument("Missing parameters"))?;
+        let par = BfvParameters::try_deserialize(&parameters.encode_to_vec())
+            .map_err(invalid_argument)?;
+        if par.fingerprint() != request.fingerprint {
+            return Err(invalid_argument(Error::ParameterMismatch {
+                expected: request.fingerprint,
+                found: par.fingerprint(),
+            }));
+        }
+        let mut session_id = vec![0u8; SESSION_ID_LENGTH];
+        OsRng.fill_bytes(&mut session_id);
+        let session = Session {
+            par: Arc::new(par),
+            ek: None,
+            rk: None,
+        };
+        self.sessions
+            .write()
+            .unwrap()
+            .insert(session_id.clone(), session);
+        Ok(Response::new(RegisterResponse { session_id }))
+    }
+
+    async fn upload_keys(
+        &self,
+        request: Request<Streaming<UploadKeysRequest>>,
+    ) -> std::result::Result<Response<UploadKeysResponse>, Status> {
+        let mut stream = request.into_inner();
+        let mut num_keys = 0;
+        while let Some(request) = stream.message().await? {
+            let mut sessions = self.sessions.write().unwrap();
+            let session = sessions
+                .get_mut(&request.session_id)
+                .ok_or_else(|| Status::not_found("Unknown session"))?;
+            match request.key {
+                Some(Key::EvaluationKey(ek)) => {
+                    session.ek = Some(
+                        EvaluationKey::try_convert_from(&ek, &session.par)
+                            .map_err(invalid_argument)?,
+                    )
+                }
+                Some(Key::RelinearizationKey(rk)) => {
+                    session.rk = Some(
+                        RelinearizationKey::try_convert_from(&rk, &session.par)
+                            .map_err(invalid_argument)?,
+                    )
+                }
+                None => return Err(Status::invalid_argument("Missing key")),
+            }
+            num_keys += 1;
+        }
+        Ok(Response::new(UploadKeysResponse { num_keys }))
+    }
+
+    type EvaluateStream =
+        Pin<Box<dyn Stream<Item = std::result::Result<EvaluateResponse, Status>> + Send>>;
+
+    // The items of the stream are results of a `Status`, as in the trait.
+    #[allow(clippy::result_large_err)]
+    async fn evaluate(
+        &self,
+        request: Request<Streaming<EvaluateRequest>>,
+    ) -> std::result::Result<Response<Self::EvaluateStream>, Status> {
+        let sessions = self.sessions.clone();
+        let responses = request.into_inner().map(move |request| {
+            let request = request?;
+            let sessions = sessions.read().unwrap();
+            let session = sessions
+                .get(&request.session_id)
+                .ok_or_else(|| Status::not_found("Unknown session"))?;
+            // The errors of the evaluation are returned in the response, so
+            // that the following requests of the stream are still evaluated.
+            Ok(match session.evaluate(&request.function, &request.inputs) {
+                Ok(outputs) => EvaluateResponse {
+                    request_id: request.request_id,
+                    outputs: outputs.iter().map(CiphertextProto::from).collect(),
+                    error: String::new(),
+                },
+                Err(e) => EvaluateResponse {
+                    request_id: request.request_id,
+                    outputs: vec![],
+                    error: e.to_string(),
+                },
+            })
+        });
+        Ok(Response::new(Box::pin(responses)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{EvaluationClient, EvaluationServer, EvaluationService};
+    use crate::bfv::{
+        traits::TryConvertFrom, BfvParameters, Ciphertext, Encoding, EvaluationKeyBuilder,
+        Plaintext, RelinearizationKey, SecretKey,
+    };
+    use crate::proto::bfv::{Ciphertext as CiphertextProto, Parameters};
+    use crate::proto::service::{
+        upload_keys_request::Key, EvaluateRequest, RegisterRequest, UploadKeysRequest,
+    };
+    use fhe_traits::{FheDecoder, FheDecrypter, FheEncoder, FheEncrypter, Serialize};
+    use prost::Message;
+    use rand::thread_rng;
+    use std::error::Error;
+    use tokio::net::TcpListener;
+    use tokio_stream::wrappers::TcpListenerStream;
+    use tonic::{transport::Server, Code};
+
+    #[tokio::test]
+    async fn evaluation_service() -> Result<(), Box<dyn Error>> {
+        let mut rng = thread_rng();
+        // The service refuses insecure parameters.
+        let par = BfvParameters::standard_128_classic(4096, 1)?
+            .set_plaintext_modulus(40961)
+            .build_arc()?;
+        let sk = SecretKey::random(&par, &mut rng);
+        let rk = RelinearizationKey::new(&sk, &mut rng)?;
+        let ek = EvaluationKeyBuilder::new(&sk)?
+            .enable_inner_sum()?
+            .build(&mut rng)?;
+        let a = par.plaintext.random_vec(par.degree(), &mut rng);
+        let b = par.plaintext.random_vec(par.degree(), &mut rng);
+        let ct_a: Ciphertext = sk.try_encrypt(
+            &Plaintext::try_encode(&a, Encoding::simd(), &par)?,
+            &mut rng,
+        )?;
+        let ct_b: Ciphertext = sk.try_encrypt(
+            &Plaintext::try_encode(&b, Encoding::simd(), &par)?,
+            &mut rng,
+        )?;
+
+        let service = EvaluationService::new();
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let address = listener.local_addr()?;
+        let server = Server::builder()
+            .add_service(EvaluationServer::new(service.clone()))
+            .serve_with_incoming(TcpListenerStream::new(listener));
+        let server = tokio::spawn(server);
+        let mut client = EvaluationClient::connect(format!("http://{address}")).await?;
+
+        // Register the parameters, whose fingerprint must match.
+        let insecure = BfvParameters::default_arc(2, 16);
+        let status = client
+            .register(RegisterRequest {
+                parameters: Some(Parameters::decode(insecure.to_bytes().as_slice())?),
+                fingerprint: insecure.fingerprint(),
+            })
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), Code::InvalidArgument);
+        let parameters = Parameters::decode(par.to_bytes().as_slice())?;
+        let status = client
+            .register(RegisterRequest {
+                parameters: Some(parameters.clone()),
+                fingerprint: par.fingerprint() ^ 1,
+            })
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), Code::InvalidArgument);
+        let session_id = client
+            .register(RegisterRequest {
+                parameters: Some(parameters),
+                fingerprint: par.fingerprint(),
+            })
+            .await?
+            .into_inner()
+            .session_id;
+        assert_eq!(service.num_sessions(), 1);
+
+        // Upload the keys.
+        let keys = vec![
+            UploadKeysRequest {
+                session_id: session_id.clone(),
+                key: Some(Key::EvaluationKey((&ek).into())),
+            },
+            UploadKeysRequest {
+                session_id: session_id.clone(),
+                key: Some(Key::RelinearizationKey((&rk).into())),
+            },
+        ];
+        let response = client.upload_keys(tokio_stream::iter(keys)).await?;
+        assert_eq!(response.into_inner().num_keys, 2);
+
+        // Stream the requests, and receive the results in order.
+        let request = |request_id, function: &str, inputs: &[&Ciphertext]| EvaluateRequest {
+            session_id: session_id.clone(),
+            request_id,
+            function: function.to_string(),
+            inputs: inputs.iter().map(|ct| CiphertextProto::from(*ct)).collect(),
+        };
+        let requests = vec![
+            request(1, "add", &[&ct_a, &ct_b]),
+            request(2, "mul", &[&ct_a, &ct_b]),
+            request(3, "inner_sum", &[&ct_a]),
+            request(4, "unknown", &[&ct_a]),
+        ];
+        let mut responses = client
+            .evaluate(tokio_stream::iter(requests))
+            .await?
+            .into_inner();
+        let mut results = vec![];
+        while let Some(response) = responses.message().await? {
+            results.push(response);
+        }
+        assert_eq!(
+            results.iter().map(|r| r.request_id).collect::<Vec<_>>(),
+            [1, 2, 3, 4]
+        );
+        let decrypt = |proto: &CiphertextProto| -> Result<Vec<u64>, Box<dyn Error>> {
+            let ct = Ciphertext::try_convert_from(proto, &par)?;
+            Ok(Vec::<u64>::try_decode(
+                &sk.try_decrypt(&ct)?,
+                Encoding::simd(),
+            )?)
+        };
+        let mut expected = a.clone();
+        par.plaintext.add_vec(&mut expected, &b);
+        assert_eq!(decrypt(&results[0].outputs[0])?, expected);
+        let mut expected = a.clone();
+        par.plaintext.mul_vec(&mut expected, &b);
+        assert_eq!(decrypt(&results[1].outputs[0])?, expected);
+        let sum = a.iter().fold(0, |acc, ai| par.plaintext.add(acc, *ai));
+        assert_eq!(decrypt(&results[2].outputs[0])?, vec![sum; par.degree()]);
+        assert!(results[3].outputs.is_empty());
+        assert!(results[3].error.contains("Unknown function"));
+
+        // Requests of unknown sessions are rejected.
+        let mut unknown = request(5, "add", &[&ct_a]);
+        unknown.session_id = vec![0; 16];
+        let mut responses = client
+            .evaluate(tokio_stream::iter([unknown]))
+            .await?
+            .into_inner();
+        assert_eq!(
+            responses.message().await.unwrap_err().code(),
+            Code::NotFound
+        );
+
+        server.abort();
+        Ok(())
+    }
+}
@@ -6,7 +6,12 @@
 mod errors;
 
 pub mod bfv;
+pub mod entropy;
+#[cfg(feature = "grpc")]
+pub mod grpc;
 pub mod mbfv;
+#[cfg(feature = "net")]
+pub mod net;
 pub mod proto;
 pub use errors::{Error, ParametersError, Result};
 
@@ -8,6 +8,8 @@ mod public_key_switch;
 mod relin_key_gen;
 pub mod round;
 mod secret_key_switch;
+pub mod secure_aggregation;
+pub mod voting;
 
 pub use aggregate::{Aggregate, AggregateIter};
 pub use crp::CommonRandomPoly;
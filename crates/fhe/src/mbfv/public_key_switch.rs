@@ -36,11 +36,8 @@ impl PublicKeySwitchShare {
         ct: &Ciphertext,
         rng: &mut R,
     ) -> Result<Self> {
-        if sk_share.par != public_key.par || public_key.par != ct.par {
-            return Err(Error::DefaultError(
-                "Incompatible BFV parameters".to_string(),
-            ));
-        }
+        sk_share.par.check_fingerprint(&public_key.par)?;
+        public_key.par.check_fingerprint(&ct.par)?;
         let par = sk_share.par.clone();
 
         // Get appropriate context / level for the following computations
@@ -4,8 +4,7 @@ use std::sync::Arc;
 use crate::bfv::{BfvParameters, KeySwitchingKey, RelinearizationKey, SecretKey};
 use crate::errors::Result;
 use crate::Error;
-use fhe_math::rns::RnsContext;
-use fhe_math::rq::{traits::TryConvertFrom, Poly, Representation};
+use fhe_math::rq::{gadget::Gadget, traits::TryConvertFrom, Poly, Representation};
 use itertools::izip;
 use rand::{CryptoRng, RngCore};
 use zeroize::Zeroizing;
@@ -82,14 +81,13 @@ impl<'a, 'b> RelinKeyGenerator<'a, 'b> {
         let par = sk_share.par.clone();
         let ctx = par.ctx_at_level(0)?;
         if ctx.moduli().len() == 1 {
-            Err(Error::DefaultError(
+            Err(Error::UnsupportedOperation(
                 "These parameters do not support key switching".to_string(),
             ))
-        } else if crp.len() != ctx.moduli().len() {
-            Err(Error::DefaultError(
-                "The size of the CRP polynomial vector must equal the number of ciphertext moduli."
-                    .to_string(),
-            ))
+        } else if crp.len() < ctx.moduli().len() {
+            Err(Error::TooFewValues(crp.len(), ctx.moduli().len()))
+        } else if crp.len() > ctx.moduli().len() {
+            Err(Error::TooManyValues(crp.len(), ctx.moduli().len()))
         } else {
             let u = Zeroizing::new(Poly::small(ctx, Representation::Ntt, par.variance, rng)?);
             Ok(Self { sk_share, crp, u })
@@ -120,11 +118,11 @@ impl RelinKeyShare<R1> {
     ) -> Result<Self> {
         let par = sk_share.par.clone();
 
-        if crp.len() != par.ctx_at_level(0)?.moduli().len() {
-            Err(Error::DefaultError(
-                "The size of the CRP polynomial vector must equal the number of ciphertext moduli."
-                    .to_string(),
-            ))
+        let num_moduli = par.ctx_at_level(0)?.moduli().len();
+        if crp.len() < num_moduli {
+            Err(Error::TooFewValues(crp.len(), num_moduli))
+        } else if crp.len() > num_moduli {
+            Err(Error::TooManyValues(crp.len(), num_moduli))
         } else {
             let h0 = Self::generate_h0(sk_share, crp, u, rng)?;
             let h1 = Self::generate_h1(sk_share, crp, rng)?;
@@ -153,12 +151,9 @@ impl RelinKeyShare<R1> {
             false,
             Representation::PowerBasis,
         )?);
-        let rns = RnsContext::new(&sk_share.par.moduli[..crp.len()])?;
-        let h0 = crp
-            .iter()
-            .enumerate()
-            .map(|(i, a)| {
-                let w = rns.get_garner(i).unwrap();
+        let factors = Gadget::rns(ctx).factors();
+        let h0 = izip!(crp, &factors)
+            .map(|(a, w)| {
                 let mut w_s = Zeroizing::new(w * s.as_ref());
                 w_s.change_representation(Representation::Ntt);
 
@@ -325,7 +320,7 @@ impl Aggregate<RelinKeyShare<R2>> for RelinearizationKey {
         let share = shares.next().ok_or(Error::TooFewValues(0, 1))?;
         let par = share.par.clone();
         let ctx = par.ctx_at_level(0)?.clone();
-        let r1 = share.last_round.ok_or(Error::DefaultError(
+        let r1 = share.last_round.ok_or(Error::UnspecifiedInput(
             "Shares from round 2 should include a copy for the round 1 aggregation.".to_string(),
         ))?;
 
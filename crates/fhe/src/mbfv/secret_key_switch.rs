@@ -43,11 +43,8 @@ impl SecretKeySwitchShare {
         ct: Arc<Ciphertext>,
         rng: &mut R,
     ) -> Result<Self> {
-        if sk_input_share.par != sk_output_share.par || sk_output_share.par != ct.par {
-            return Err(Error::DefaultError(
-                "Incompatible BFV parameters".to_string(),
-            ));
-        }
+        sk_input_share.par.check_fingerprint(&sk_output_share.par)?;
+        sk_output_share.par.check_fingerprint(&ct.par)?;
         // Note: M-BFV implementation only supports ciphertext of length 2
         if ct.c.len() != 2 {
             return Err(Error::TooManyValues(ct.c.len(), 2));
@@ -162,17 +159,7 @@ impl Aggregate<DecryptionShare> for Plaintext {
         q.reduce_vec(&mut w);
         par.plaintext.reduce_vec(&mut w);
 
-        let mut poly =
-            Poly::try_convert_from(&w, ct.c[0].ctx(), false, Representation::PowerBasis)?;
-        poly.change_representation(Representation::Ntt);
-
-        let pt = Plaintext {
-            par: par.clone(),
-            value: w.into_boxed_slice(),
-            encoding: None,
-            poly_ntt: poly,
-            level: ct.level,
-        };
+        let pt = Plaintext::new(&par, w.into_boxed_slice(), None, ct.level);
 
         Ok(pt)
     }
@@ -0,0 +1,315 @@
+//! Secure aggregation of vectors, e.g. of the gradients of a federated
+//! learning round.
+//!
+//! The parties holding the shares of the secret key first generate a
+//! collective [`PublicKey`] using [`PublicKeyShare`](super::PublicKeyShare).
+//! Each client encrypts its update under this key with
+//! [`EncryptedUpdate::new`], the server sums the updates it receives with an
+//! [`Aggregator`], and all the parties decrypt the resulting
+//! [`EncryptedAggregate`], so that only the sum of the updates is revealed.
+//!
+//! Clients which drop out of the round are simply left out of the sum, and the
+//! list of contributors is part of the aggregate. The server refuses to
+//! release an aggregate of fewer than a minimum number of contributors, since
+//! it would reveal too much about the individual updates. Note that all the
+//! parties holding the shares of the secret key are needed to decrypt.
+
+use std::{collections::BTreeSet, sync::Arc};
+
+use fhe_traits::{FheDecoder, FheEncoder, FheEncrypter};
+use rand::{CryptoRng, RngCore};
+
+use crate::bfv::{BfvParameters, Ciphertext, Encoding, Plaintext, PublicKey, SecretKey};
+use crate::{Error, Result};
+
+use super::{Aggregate, DecryptionShare};
+
+/// The update of a client, encrypted in chunks of `degree` values.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EncryptedUpdate {
+    len: usize,
+    ciphertexts: Vec<Ciphertext>,
+}
+
+impl EncryptedUpdate {
+    /// Encrypts the `update` of a client under the collective public key.
+    ///
+    /// The values are reduced modulo the plaintext modulus `t`, and the sum of
+    /// the updates is correctly decrypted as long as its values lie in
+    /// `(-t/2, t/2]`.
+    pub fn new<R: RngCore + CryptoRng>(
+        pk: &PublicKey,
+        update: &[i64],
+        rng: &mut R,
+    ) -> Result<Self> {
+        if update.is_empty() {
+            return Err(Error::TooFewValues(0, 1));
+        }
+        let ciphertexts = update
+            .chunks(pk.par.degree())
+            .map(|chunk| {
+                let pt = Plaintext::try_encode(chunk, Encoding::poly(), &pk.par)?;
+                pk.try_encrypt(&pt, rng)
+            })
+            .collect::<Result<Vec<Ciphertext>>>()?;
+        Ok(Self {
+            len: update.len(),
+            ciphertexts,
+        })
+    }
+
+    /// Returns the number of values of the update.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns whether the update is empty, which is never the case.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns the ciphertexts encrypting the chunks of the update.
+    pub fn ciphertexts(&self) -> &[Ciphertext] {
+        &self.ciphertexts
+    }
+}
+
+/// Sums the encrypted updates of the clients of a round.
+#[derive(Debug, Clone)]
+pub struct Aggregator {
+    par: Arc<BfvParameters>,
+    len: usize,
+    minimum_contributors: usize,
+    sum: Vec<Ciphertext>,
+    contributors: BTreeSet<u64>,
+}
+
+impl Aggregator {
+    /// Creates an aggregator of updates of `len` values, which requires two
+    /// contributors by default.
+    pub fn new(par: &Arc<BfvParameters>, len: usize) -> Result<Self> {
+        if len == 0 {
+            return Err(Error::TooFewValues(0, 1));
+        }
+        let sum = (0..len.div_ceil(par.degree()))
+            .map(|_| Ciphertext::zero(par))
+            .collect();
+        Ok(Self {
+            par: par.clone(),
+            len,
+            minimum_contributors: 2,
+            sum,
+            contributors: BTreeSet::new(),
+        })
+    }
+
+    /// Sets the minimum number of contributors for the aggregate to be
+    /// released.
+    pub fn set_minimum_contributors(&mut self, minimum_contributors: usize) -> &mut Self {
+        self.minimum_contributors = minimum_contributors.max(1);
+        self
+    }
+
+    /// Adds the update of the client `client_id`, which must not have
+    /// contributed yet.
+    pub fn add(&mut self, client_id: u64, update: &EncryptedUpdate) -> Result<()> {
+        if update.len != self.len {
+            return Err(Error::UnspecifiedInput(format!(
+                "The update has {} values instead of {}",
+                update.len, self.len
+            )));
+        }
+        if update.ciphertexts.len() != self.sum.len() {
+            return Err(Error::CiphertextSizeMismatch {
+                expected: self.sum.len(),
+                found: update.ciphertexts.len(),
+            });
+        }
+        if self.contributors.contains(&client_id) {
+            return Err(Error::UnspecifiedInput(format!(
+                "The client {client_id} has already contributed"
+            )));
+        }
+        for ct in &update.ciphertexts {
+            self.par.check_fingerprint(&ct.par)?;
+            if ct.level != 0 || ct.c.len() != 2 {
+                return Err(Error::UnspecifiedInput(
+                    "The update is not a fresh encryption".to_string(),
+                ));
+            }
+        }
+
+        for (sum, ct) in self.sum.iter_mut().zip(&update.ciphertexts) {
+            *sum += ct;
+        }
+        self.contributors.insert(client_id);
+        Ok(())
+    }
+
+    /// Returns the clients which have contributed, in increasing order.
+    pub fn contributors(&self) -> impl Iterator<Item = u64> + '_ {
+        self.contributors.iter().copied()
+    }
+
+    /// Releases the sum of the updates, or raises an error if there are fewer
+    /// contributors than the minimum.
+    pub fn finalize(&self) -> Result<EncryptedAggregate> {
+        if self.contributors.len() < self.minimum_contributors {
+            return Err(Error::TooFewValues(
+                self.contributors.len(),
+                self.minimum_contributors,
+            ));
+        }
+        Ok(EncryptedAggregate {
+            len: self.len,
+            ciphertexts: self.sum.iter().cloned().map(Arc::new).collect(),
+            contributors: self.contributors.iter().copied().collect(),
+        })
+    }
+}
+
+/// The encrypted sum of the updates of a round.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EncryptedAggregate {
+    len: usize,
+    ciphertexts: Vec<Arc<Ciphertext>>,
+    contributors: Vec<u64>,
+}
+
+/// A party's share in the decryption of an [`EncryptedAggregate`].
+pub struct AggregateDecryptionShare {
+    shares: Vec<DecryptionShare>,
+}
+
+impl EncryptedAggregate {
+    /// Returns the clients whose updates are summed, in increasing order.
+    pub fn contributors(&self) -> &[u64] {
+        &self.contributors
+    }
+
+    /// Participate in the decryption of the aggregate with a share of the
+    /// secret key.
+    pub fn decryption_share<R: RngCore + CryptoRng>(
+        &self,
+        sk_share: &SecretKey,
+        rng: &mut R,
+    ) -> Result<AggregateDecryptionShare> {
+        let shares = self
+            .ciphertexts
+            .iter()
+            .map(|ct| DecryptionShare::new(sk_share, ct, rng))
+            .collect::<Result<Vec<_>>>()?;
+        Ok(AggregateDecryptionShare { shares })
+    }
+
+    /// Decrypts the sum of the updates from the decryption shares of all the
+    /// parties.
+    pub fn decrypt<T>(&self, shares: T) -> Result<Vec<i64>>
+    where
+        T: IntoIterator<Item = AggregateDecryptionShare>,
+    {
+        let mut chunks = (0..self.ciphertexts.len())
+            .map(|_| vec![])
+            .collect::<Vec<_>>();
+        for share in shares {
+            if share.shares.len() != self.ciphertexts.len() {
+                return Err(Error::CiphertextSizeMismatch {
+                    expected: self.ciphertexts.len(),
+                    found: share.shares.len(),
+                });
+            }
+            for (chunk, (share, ct)) in chunks
+                .iter_mut()
+                .zip(share.shares.into_iter().zip(&self.ciphertexts))
+            {
+                if share.sks_share.ct != *ct {
+                    return Err(Error::UnspecifiedInput(
+                        "The decryption share is for another aggregate".to_string(),
+                    ));
+                }
+                chunk.push(share);
+            }
+        }
+
+        let mut sum = Vec::with_capacity(self.len);
+        for chunk in chunks {
+            let pt = Plaintext::from_shares(chunk)?;
+            sum.extend(Vec::<i64>::try_decode(&pt, Encoding::poly())?);
+        }
+        sum.truncate(self.len);
+        Ok(sum)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Aggregator, EncryptedUpdate};
+    use crate::bfv::{BfvParameters, PublicKey, SecretKey};
+    use crate::mbfv::{AggregateIter, CommonRandomPoly, PublicKeyShare};
+    use crate::Error;
+    use rand::{thread_rng, Rng};
+    use std::error::Error as StdError;
+
+    #[test]
+    fn secure_aggregation() -> Result<(), Box<dyn StdError>> {
+        let mut rng = thread_rng();
+        let par = BfvParameters::default_arc(2, 16);
+        let len = 40;
+
+        // The committee generates a collective public key.
+        let crp = CommonRandomPoly::new(&par, &mut rng)?;
+        let sk_shares = (0..3)
+            .map(|_| SecretKey::random(&par, &mut rng))
+            .collect::<Vec<_>>();
+        let pk: PublicKey = sk_shares
+            .iter()
+            .map(|sk_share| PublicKeyShare::new(sk_share, crp.clone(), &mut rng))
+            .aggregate()?;
+
+        // The clients encrypt their updates, and two of them drop out.
+        let updates = (0..10)
+            .map(|_| {
+                (0..len)
+                    .map(|_| rng.gen_range(-50..=50))
+                    .collect::<Vec<i64>>()
+            })
+            .collect::<Vec<_>>();
+        let mut aggregator = Aggregator::new(&par, len)?;
+        let mut expected = vec![0i64; len];
+        for (client_id, update) in updates.iter().enumerate() {
+            let encrypted = EncryptedUpdate::new(&pk, update, &mut rng)?;
+            assert_eq!(encrypted.len(), len);
+            assert_eq!(encrypted.ciphertexts().len(), 3);
+            if client_id % 5 == 4 {
+                continue;
+            }
+            aggregator.add(client_id as u64, &encrypted)?;
+            expected.iter_mut().zip(update).for_each(|(e, u)| *e += u);
+        }
+
+        // A client cannot contribute twice, nor with an update of another size.
+        let encrypted = EncryptedUpdate::new(&pk, &updates[0], &mut rng)?;
+        assert!(aggregator.add(0, &encrypted).is_err());
+        let encrypted = EncryptedUpdate::new(&pk, &updates[0][..20], &mut rng)?;
+        assert!(aggregator.add(4, &encrypted).is_err());
+
+        // The committee decrypts the aggregate.
+        let aggregate = aggregator.finalize()?;
+        assert_eq!(aggregate.contributors(), &[0, 1, 2, 3, 5, 6, 7, 8]);
+        let shares = sk_shares
+            .iter()
+            .map(|sk_share| aggregate.decryption_share(sk_share, &mut rng))
+            .collect::<crate::Result<Vec<_>>>()?;
+        assert_eq!(aggregate.decrypt(shares)?, expected);
+
+        // The aggregate is not released with too few contributors.
+        let mut aggregator = Aggregator::new(&par, len)?;
+        aggregator.set_minimum_contributors(3);
+        for (client_id, update) in updates.iter().enumerate().take(2) {
+            let encrypted = EncryptedUpdate::new(&pk, update, &mut rng)?;
+            aggregator.add(client_id as u64, &encrypted)?;
+        }
+        assert_eq!(aggregator.finalize(), Err(Error::TooFewValues(2, 3)));
+        Ok(())
+    }
+}
@@ -0,0 +1,351 @@
+//! Encrypted tallies of one-hot ballots, e.g. for electronic voting.
+//!
+//! As for [secure aggregation](super::secure_aggregation), the trustees
+//! holding the shares of the secret key first generate a collective
+//! [`PublicKey`]. Each voter encrypts its choice as a one-hot [`Ballot`], the
+//! ballots are validated and accumulated in a [`Tally`], and the trustees
+//! jointly decrypt the resulting [`EncryptedTally`], so that only the number
+//! of votes for each candidate is revealed. All the trustees are needed to
+//! decrypt.
+//!
+//! The encryption scheme does not prevent a voter from encrypting an invalid
+//! ballot, e.g. with several votes for a candidate. A [`BallotVerifier`] can be
+//! used to check a zero-knowledge proof of validity attached to each ballot
+//! before it is counted.
+
+use std::{collections::BTreeSet, sync::Arc};
+
+use fhe_traits::{FheDecoder, FheEncoder, FheEncrypter};
+use rand::{CryptoRng, RngCore};
+
+use crate::bfv::{BfvParameters, Ciphertext, Encoding, Plaintext, PublicKey, SecretKey};
+use crate::{Error, Result};
+
+use super::{Aggregate, DecryptionShare};
+
+/// An encrypted one-hot ballot, with an optional proof of validity.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Ballot {
+    num_candidates: usize,
+    ciphertext: Ciphertext,
+    proof: Option<Vec<u8>>,
+}
+
+impl Ballot {
+    /// Encrypts a vote for the candidate `choice` among `num_candidates`
+    /// candidates under the collective public key.
+    pub fn new<R: RngCore + CryptoRng>(
+        pk: &PublicKey,
+        choice: usize,
+        num_candidates: usize,
+        rng: &mut R,
+    ) -> Result<Self> {
+        check_num_candidates(num_candidates, &pk.par)?;
+        if choice >= num_candidates {
+            return Err(Error::UnspecifiedInput(format!(
+                "The choice {choice} is not among the {num_candidates} candidates"
+            )));
+        }
+        let mut vote = vec![0u64; num_candidates];
+        vote[choice] = 1;
+        let pt = Plaintext::try_encode(&vote, Encoding::poly(), &pk.par)?;
+        Ok(Self {
+            num_candidates,
+            ciphertext: pk.try_encrypt(&pt, rng)?,
+            proof: None,
+        })
+    }
+
+    /// Attaches a proof of validity to the ballot.
+    pub fn set_proof(&mut self, proof: Vec<u8>) -> &mut Self {
+        self.proof = Some(proof);
+        self
+    }
+
+    /// Returns the proof of validity attached to the ballot, if any.
+    pub fn proof(&self) -> Option<&[u8]> {
+        self.proof.as_deref()
+    }
+
+    /// Returns the number of candidates.
+    pub fn num_candidates(&self) -> usize {
+        self.num_candidates
+    }
+
+    /// Returns the ciphertext encrypting the ballot.
+    pub fn ciphertext(&self) -> &Ciphertext {
+        &self.ciphertext
+    }
+
+    /// Re-randomizes the ballot with an encryption of zero under the
+    /// collective public key, e.g. before it is published on a bulletin board.
+    ///
+    /// A proof of validity computed on the original ciphertext no longer
+    /// applies, and is removed.
+    pub fn rerandomize<R: RngCore + CryptoRng>(
+        &mut self,
+        pk: &PublicKey,
+        rng: &mut R,
+    ) -> Result<()> {
+        rerandomize(&mut self.ciphertext, pk, rng)?;
+        self.proof = None;
+        Ok(())
+    }
+}
+
+/// Verifies the validity of a [`Ballot`] before it is counted, typically by
+/// checking a zero-knowledge proof attached with [`Ballot::set_proof`].
+pub trait BallotVerifier {
+    /// Returns an error if the ballot is not valid.
+    fn verify(&self, ballot: &Ballot) -> Result<()>;
+}
+
+/// A [`BallotVerifier`] accepting all ballots, for when the voters are trusted
+/// to encrypt valid ballots.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct NoVerification;
+
+impl BallotVerifier for NoVerification {
+    fn verify(&self, _ballot: &Ballot) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// Accumulates the ballots of an election.
+#[derive(Debug, Clone)]
+pub struct Tally<V: BallotVerifier = NoVerification> {
+    par: Arc<BfvParameters>,
+    num_candidates: usize,
+    verifier: V,
+    sum: Ciphertext,
+    voters: BTreeSet<u64>,
+}
+
+impl Tally {
+    /// Creates a tally for `num_candidates` candidates, which counts all the
+    /// ballots without verification.
+    pub fn new(par: &Arc<BfvParameters>, num_candidates: usize) -> Result<Self> {
+        Self::new_with_verifier(par, num_candidates, NoVerification)
+    }
+}
+
+impl<V: BallotVerifier> Tally<V> {
+    /// Creates a tally for `num_candidates` candidates, which only counts the
+    /// ballots accepted by `verifier`.
+    pub fn new_with_verifier(
+        par: &Arc<BfvParameters>,
+        num_candidates: usize,
+        verifier: V,
+    ) -> Result<Self> {
+        check_num_candidates(num_candidates, par)?;
+        Ok(Self {
+            par: par.clone(),
+            num_candidates,
+            verifier,
+            sum: Ciphertext::zero(par),
+            voters: BTreeSet::new(),
+        })
+    }
+
+    /// Counts the ballot of the voter `voter_id`, which must not have voted
+    /// yet.
+    pub fn add(&mut self, voter_id: u64, ballot: &Ballot) -> Result<()> {
+        if ballot.num_candidates != self.num_candidates {
+            return Err(Error::UnspecifiedInput(format!(
+                "The ballot has {} candidates instead of {}",
+                ballot.num_candidates, self.num_candidates
+            )));
+        }
+        if self.voters.contains(&voter_id) {
+            return Err(Error::UnspecifiedInput(format!(
+                "The voter {voter_id} has already voted"
+            )));
+        }
+        // The counts must not wrap around the plaintext modulus.
+        if self.voters.len() as u64 + 1 >= self.par.plaintext() {
+            return Err(Error::TooManyValues(
+                self.voters.len() + 1,
+                self.par.plaintext() as usize - 1,
+            ));
+        }
+        let ct = &ballot.ciphertext;
+        self.par.check_fingerprint(&ct.par)?;
+        if ct.level != 0 || ct.c.len() != 2 {
+            return Err(Error::UnspecifiedInput(
+                "The ballot is not a fresh encryption".to_string(),
+            ));
+        }
+        self.verifier.verify(ballot)?;
+
+        self.sum += ct;
+        self.voters.insert(voter_id);
+        Ok(())
+    }
+
+    /// Returns the voters whose ballots are counted, in increasing order.
+    pub fn voters(&self) -> impl Iterator<Item = u64> + '_ {
+        self.voters.iter().copied()
+    }
+
+    /// Closes the election and returns the encrypted tally, re-randomized with
+    /// an encryption of zero under the collective public key.
+    pub fn finalize<R: RngCore + CryptoRng>(
+        &self,
+        pk: &PublicKey,
+        rng: &mut R,
+    ) -> Result<EncryptedTally> {
+        if self.voters.is_empty() {
+            return Err(Error::TooFewValues(0, 1));
+        }
+        let mut ciphertext = self.sum.clone();
+        rerandomize(&mut ciphertext, pk, rng)?;
+        Ok(EncryptedTally {
+            num_candidates: self.num_candidates,
+            num_voters: self.voters.len(),
+            ciphertext: Arc::new(ciphertext),
+        })
+    }
+}
+
+/// The encrypted number of votes for each candidate.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EncryptedTally {
+    num_candidates: usize,
+    num_voters: usize,
+    ciphertext: Arc<Ciphertext>,
+}
+
+impl EncryptedTally {
+    /// Returns the number of ballots counted.
+    pub fn num_voters(&self) -> usize {
+        self.num_voters
+    }
+
+    /// Participate in the decryption of the tally with a share of the secret
+    /// key.
+    pub fn decryption_share<R: RngCore + CryptoRng>(
+        &self,
+        sk_share: &SecretKey,
+        rng: &mut R,
+    ) -> Result<DecryptionShare> {
+        DecryptionShare::new(sk_share, &self.ciphertext, rng)
+    }
+
+    /// Decrypts the number of votes for each candidate from the decryption
+    /// shares of all the trustees.
+    pub fn decrypt<T>(&self, shares: T) -> Result<Vec<u64>>
+    where
+        T: IntoIterator<Item = DecryptionShare>,
+    {
+        let shares = shares.into_iter().collect::<Vec<_>>();
+        if shares
+            .iter()
+            .any(|share| share.sks_share.ct != self.ciphertext)
+        {
+            return Err(Error::UnspecifiedInput(
+                "The decryption share is for another tally".to_string(),
+            ));
+        }
+        let pt = Plaintext::from_shares(shares)?;
+        let mut counts = Vec::<u64>::try_decode(&pt, Encoding::poly())?;
+        counts.truncate(self.num_candidates);
+        if counts.iter().sum::<u64>() != self.num_voters as u64 {
+            return Err(Error::DecryptionVerificationFailed);
+        }
+        Ok(counts)
+    }
+}
+
+/// Checks that `num_candidates` fit in a plaintext.
+fn check_num_candidates(num_candidates: usize, par: &BfvParameters) -> Result<()> {
+    if num_candidates == 0 {
+        return Err(Error::TooFewValues(0, 1));
+    }
+    if num_candidates > par.degree() {
+        return Err(Error::TooManyValues(num_candidates, par.degree()));
+    }
+    Ok(())
+}
+
+/// Adds an encryption of zero under `pk` to `ct`.
+fn rerandomize<R: RngCore + CryptoRng>(
+    ct: &mut Ciphertext,
+    pk: &PublicKey,
+    rng: &mut R,
+) -> Result<()> {
+    pk.par.check_fingerprint(&ct.par)?;
+    let zero = Plaintext::zero(Encoding::poly_at_level(ct.level), &pk.par)?;
+    *ct += &pk.try_encrypt(&zero, rng)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Ballot, BallotVerifier, Tally};
+    use crate::bfv::{BfvParameters, PublicKey, SecretKey};
+    use crate::mbfv::{AggregateIter, CommonRandomPoly, PublicKeyShare};
+    use crate::{Error, Result};
+    use rand::{thread_rng, Rng};
+    use std::error::Error as StdError;
+
+    /// Accepts the ballots carrying a given proof.
+    struct ProofVerifier(Vec<u8>);
+
+    impl BallotVerifier for ProofVerifier {
+        fn verify(&self, ballot: &Ballot) -> Result<()> {
+            if ballot.proof() != Some(&self.0) {
+                return Err(Error::UnspecifiedInput("Invalid proof".to_string()));
+            }
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn election() -> std::result::Result<(), Box<dyn StdError>> {
+        let mut rng = thread_rng();
+        let par = BfvParameters::default_arc(2, 16);
+        let num_candidates = 5;
+
+        // The trustees generate a collective public key.
+        let crp = CommonRandomPoly::new(&par, &mut rng)?;
+        let sk_shares = (0..3)
+            .map(|_| SecretKey::random(&par, &mut rng))
+            .collect::<Vec<_>>();
+        let pk: PublicKey = sk_shares
+            .iter()
+            .map(|sk_share| PublicKeyShare::new(sk_share, crp.clone(), &mut rng))
+            .aggregate()?;
+
+        let mut tally = Tally::new_with_verifier(&par, num_candidates, ProofVerifier(vec![1]))?;
+        let mut expected = vec![0u64; num_candidates];
+        for voter_id in 0..20 {
+            let choice = rng.gen_range(0..num_candidates);
+            let mut ballot = Ballot::new(&pk, choice, num_candidates, &mut rng)?;
+            assert!(tally.add(voter_id, &ballot).is_err());
+            ballot.rerandomize(&pk, &mut rng)?;
+            ballot.set_proof(vec![1]);
+            tally.add(voter_id, &ballot)?;
+            expected[choice] += 1;
+
+            // A voter cannot vote twice.
+            assert!(tally.add(voter_id, &ballot).is_err());
+        }
+        assert!(Ballot::new(&pk, num_candidates, num_candidates, &mut rng).is_err());
+        let ballot = Ballot::new(&pk, 0, num_candidates + 1, &mut rng)?;
+        assert!(tally.add(20, &ballot).is_err());
+
+        // The trustees decrypt the tally.
+        let encrypted = tally.finalize(&pk, &mut rng)?;
+        assert_eq!(encrypted.num_voters(), 20);
+        let shares = sk_shares
+            .iter()
+            .map(|sk_share| encrypted.decryption_share(sk_share, &mut rng))
+            .collect::<Result<Vec<_>>>()?;
+        assert_eq!(encrypted.decrypt(shares)?, expected);
+
+        assert!(Tally::new(&par, num_candidates)?
+            .finalize(&pk, &mut rng)
+            .is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,259 @@
+//! Framing of ciphertexts and keys for network transports.
+//!
+//! A [`FrameCodec`] writes each object as its serialization prefixed by its
+//! length as a 4-byte big-endian integer, which is the default framing of
+//! [`LengthDelimitedCodec`](tokio_util::codec::LengthDelimitedCodec). It
+//! implements the [`Encoder`] and [`Decoder`] traits of `tokio-util`, so that
+//! it can be used with `Framed` over any transport.
+
+use std::marker::PhantomData;
+use std::sync::Arc;
+
+use fhe_traits::{DeserializeParametrized, FheParametrized, Serialize};
+use prost::bytes::{Buf, BufMut, BytesMut};
+use tokio_util::codec::{Decoder, Encoder};
+
+use crate::bfv::{BfvParameters, Ciphertext, EvaluationKey, PublicKey};
+use crate::{Error, Result};
+
+/// The size of the length prefix of the frames, in bytes.
+const PREFIX_SIZE: usize = 4;
+
+/// The default maximum length of a frame, in bytes, which is the default of
+/// `LengthDelimitedCodec`.
+pub const DEFAULT_MAX_FRAME_LENGTH: usize = 8 * 1024 * 1024;
+
+/// A length-delimited codec for objects of type `T`.
+#[derive(Debug, Clone)]
+pub struct FrameCodec<T> {
+    par: Arc<BfvParameters>,
+    max_frame_length: usize,
+    _marker: PhantomData<T>,
+}
+
+/// A length-delimited codec for [`Ciphertext`]s.
+pub type CiphertextCodec = FrameCodec<Ciphertext>;
+
+/// A length-delimited codec for [`PublicKey`]s.
+pub type PublicKeyCodec = FrameCodec<PublicKey>;
+
+/// A length-delimited codec for [`EvaluationKey`]s.
+pub type EvaluationKeyCodec = FrameCodec<EvaluationKey>;
+
+impl<T> FrameCodec<T>
+where
+    T: Serialize + DeserializeParametrized<Error = Error>,
+    T: FheParametrized<Parameters = BfvParameters>,
+{
+    /// Creates a codec for objects with the parameters `par`, accepting frames
+    /// of at most [`DEFAULT_MAX_FRAME_LENGTH`] bytes.
+    pub fn new(par: &Arc<BfvParameters>) -> Self {
+        Self {
+            par: par.clone(),
+            max_frame_length: DEFAULT_MAX_FRAME_LENGTH,
+            _marker: PhantomData,
+        }
+    }
+
+    /// Sets the maximum length of a frame, in bytes. Evaluation keys for large
+    /// parameters may exceed the default maximum length.
+    pub fn set_max_frame_length(&mut self, max_frame_length: usize) -> &mut Self {
+        self.max_frame_length = max_frame_length;
+        self
+    }
+
+    /// Returns the maximum length of a frame, in bytes.
+    pub fn max_frame_length(&self) -> usize {
+        self.max_frame_length
+    }
+
+    fn check_length(&self, length: usize) -> Result<()> {
+        if length > self.max_frame_length.min(u32::MAX as usize) {
+            Err(Error::TooManyValues(length, self.max_frame_length))
+        } else {
+            Ok(())
+        }
+    }
+}
+
+impl<T> Encoder<&T> for FrameCodec<T>
+where
+    T: Serialize + DeserializeParametrized<Error = Error>,
+    T: FheParametrized<Parameters = BfvParameters>,
+{
+    type Error = Error;
+
+    /// Appends the frame of `item` to `dst`, or returns an error if the
+    /// frame exceeds the maximum frame length.
+    fn encode(&mut self, item: &T, dst: &mut BytesMut) -> Result<()> {
+        let bytes = item.to_bytes();
+        self.check_length(bytes.len())?;
+        dst.reserve(PREFIX_SIZE + bytes.len());
+        dst.put_u32(bytes.len() as u32);
+        dst.put_slice(&bytes);
+        Ok(())
+    }
+}
+
+impl<T> Encoder<T> for FrameCodec<T>
+where
+    T: Serialize + DeserializeParametrized<Error = Error>,
+    T: FheParametrized<Parameters = BfvParameters>,
+{
+    type Error = Error;
+
+    fn encode(&mut self, item: T, dst: &mut BytesMut) -> Result<()> {
+        Encoder::<&T>::encode(self, &item, dst)
+    }
+}
+
+impl<T> Decoder for FrameCodec<T>
+where
+    T: Serialize + DeserializeParametrized<Error = Error>,
+    T: FheParametrized<Parameters = BfvParameters>,
+{
+    type Item = T;
+    type Error = Error;
+
+    /// Decodes an object from the first frame of `src`, and removes the frame
+    /// from `src`. Returns `None` when `src` does not contain a full frame yet,
+    /// and an error when the length of the frame exceeds the maximum frame
+    /// length, before its content is received.
+    fn decode(&mut self, src: &mut BytesMut) -> Result<Option<T>> {
+        if src.len() < PREFIX_SIZE {
+            return Ok(None);
+        }
+        let length = u32::from_be_bytes(src[..PREFIX_SIZE].try_into().unwrap()) as usize;
+        self.check_length(length)?;
+        if src.len() < PREFIX_SIZE + length {
+            src.reserve(PREFIX_SIZE + length - src.len());
+            return Ok(None);
+        }
+        src.advance(PREFIX_SIZE);
+        let frame = src.split_to(length);
+        T::from_bytes(&frame, &self.par).map(Some)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{CiphertextCodec, EvaluationKeyCodec, PublicKeyCodec, DEFAULT_MAX_FRAME_LENGTH};
+    use crate::bfv::{
+        BfvParameters, Ciphertext, Encoding, EvaluationKeyBuilder, Plaintext, PublicKey, SecretKey,
+    };
+    use crate::Error as FheError;
+    use fhe_traits::{FheEncoder, FheEncrypter};
+    use prost::bytes::BytesMut;
+    use rand::thread_rng;
+    use std::error::Error;
+    use tokio_util::codec::{Decoder, Encoder};
+
+    #[test]
+    fn frames() -> Result<(), Box<dyn Error>> {
+        let mut rng = thread_rng();
+        let par = BfvParameters::default_arc(2, 16);
+        let sk = SecretKey::random(&par, &mut rng);
+        let pk = PublicKey::new(&sk, &mut rng);
+        let ek = EvaluationKeyBuilder::new(&sk)?
+            .enable_inner_sum()?
+            .build(&mut rng)?;
+        let pt = Plaintext::try_encode(&[1u64, 2, 3], Encoding::poly(), &par)?;
+        let cts = (0..3)
+            .map(|_| pk.try_encrypt(&pt, &mut rng))
+            .collect::<Result<Vec<Ciphertext>, _>>()?;
+
+        let mut codec = CiphertextCodec::new(&par);
+        assert_eq!(codec.max_frame_length(), DEFAULT_MAX_FRAME_LENGTH);
+        let mut buffer = BytesMut::new();
+        for ct in &cts[..2] {
+            codec.encode(ct, &mut buffer)?;
+        }
+        codec.encode(cts[2].clone(), &mut buffer)?;
+        // The frames are decoded as they arrive, byte by byte.
+        let mut src = BytesMut::new();
+        let mut decoded = vec![];
+        for byte in buffer.iter() {
+            src.extend_from_slice(&[*byte]);
+            if let Some(ct) = codec.decode(&mut src)? {
+                decoded.push(ct);
+            }
+        }
+        assert_eq!(decoded, cts);
+        assert!(src.is_empty());
+        assert_eq!(codec.decode_eof(&mut src)?, None);
+        buffer.clear();
+
+        let mut codec = PublicKeyCodec::new(&par);
+        codec.encode(&pk, &mut buffer)?;
+        assert_eq!(codec.decode(&mut buffer)?, Some(pk));
+        let mut codec = EvaluationKeyCodec::new(&par);
+        codec.encode(&ek, &mut buffer)?;
+        assert_eq!(codec.decode(&mut buffer)?.as_ref(), Some(&ek));
+        assert_eq!(codec.decode(&mut buffer)?, None);
+        Ok(())
+    }
+
+    #[test]
+    fn partial_frames() -> Result<(), Box<dyn Error>> {
+        let mut rng = thread_rng();
+        let par = BfvParameters::default_arc(2, 16);
+        let sk = SecretKey::random(&par, &mut rng);
+        let pt = Plaintext::try_encode(&[1u64, 2, 3], Encoding::poly(), &par)?;
+        let ct: Ciphertext = sk.try_encrypt(&pt, &mut rng)?;
+
+        let mut codec = CiphertextCodec::new(&par);
+        let mut buffer = BytesMut::new();
+        codec.encode(&ct, &mut buffer)?;
+
+        // An incomplete prefix or frame is kept until the rest arrives.
+        let mut src = BytesMut::from(&buffer[..2]);
+        assert_eq!(codec.decode(&mut src)?, None);
+        assert_eq!(src.len(), 2);
+        src.extend_from_slice(&buffer[2..buffer.len() - 1]);
+        assert_eq!(codec.decode(&mut src)?, None);
+        assert_eq!(src.len(), buffer.len() - 1);
+        assert!(src.capacity() >= buffer.len());
+
+        // A stream ending in the middle of a frame is an error.
+        assert!(matches!(
+            codec.decode_eof(&mut src),
+            Err(FheError::IoError(_))
+        ));
+        src.extend_from_slice(&buffer[buffer.len() - 1..]);
+        assert_eq!(codec.decode(&mut src)?, Some(ct));
+        Ok(())
+    }
+
+    #[test]
+    fn oversized_frames() -> Result<(), Box<dyn Error>> {
+        let mut rng = thread_rng();
+        let par = BfvParameters::default_arc(2, 16);
+        let sk = SecretKey::random(&par, &mut rng);
+        let pt = Plaintext::try_encode(&[1u64, 2, 3], Encoding::poly(), &par)?;
+        let ct: Ciphertext = sk.try_encrypt(&pt, &mut rng)?;
+
+        let mut codec = CiphertextCodec::new(&par);
+        codec.set_max_frame_length(16);
+        let mut buffer = BytesMut::new();
+        assert!(matches!(
+            codec.encode(&ct, &mut buffer),
+            Err(FheError::TooManyValues(_, 16))
+        ));
+        assert!(buffer.is_empty());
+
+        // Oversized lengths are rejected from the prefix, before the content
+        // of the frame is received.
+        buffer.extend_from_slice(&[0, 0, 0, 17]);
+        assert_eq!(
+            codec.decode(&mut buffer).unwrap_err(),
+            FheError::TooManyValues(17, 16)
+        );
+        let mut buffer = BytesMut::from(&[0xff, 0xff, 0xff, 0xff][..]);
+        codec.set_max_frame_length(DEFAULT_MAX_FRAME_LENGTH);
+        assert_eq!(
+            codec.decode(&mut buffer).unwrap_err(),
+            FheError::TooManyValues(u32::MAX as usize, DEFAULT_MAX_FRAME_LENGTH)
+        );
+        Ok(())
+    }
+}
@@ -9,6 +9,16 @@ pub struct Ciphertext {
     pub seed: ::prost::alloc::vec::Vec<u8>,
     #[prost(uint32, tag = "3")]
     pub level: u32,
+    #[prost(fixed64, tag = "4")]
+    pub fingerprint: u64,
+    #[prost(uint32, tag = "5")]
+    pub version: u32,
+    #[prost(bytes = "vec", tag = "6")]
+    pub metadata: ::prost::alloc::vec::Vec<u8>,
+    #[prost(bytes = "vec", tag = "7")]
+    pub metadata_tag: ::prost::alloc::vec::Vec<u8>,
+    #[prost(fixed64, tag = "8")]
+    pub key_id: u64,
 }
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
@@ -33,6 +43,10 @@ pub struct KeySwitchingKey {
     pub ksk_level: u32,
     #[prost(uint32, tag = "6")]
     pub log_base: u32,
+    #[prost(fixed64, tag = "7")]
+    pub fingerprint: u64,
+    #[prost(uint32, tag = "8")]
+    pub version: u32,
 }
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
@@ -57,6 +71,10 @@ pub struct EvaluationKey {
     pub ciphertext_level: u32,
     #[prost(uint32, tag = "4")]
     pub evaluation_key_level: u32,
+    #[prost(bytes = "vec", tag = "5")]
+    pub master_seed: ::prost::alloc::vec::Vec<u8>,
+    #[prost(uint32, tag = "6")]
+    pub version: u32,
 }
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
@@ -69,6 +87,8 @@ pub struct Parameters {
     pub plaintext: u64,
     #[prost(uint32, tag = "4")]
     pub variance: u32,
+    #[prost(enumeration = "MultiplicationStrategy", tag = "5")]
+    pub multiplication_strategy: i32,
 }
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
@@ -76,3 +96,40 @@ pub struct PublicKey {
     #[prost(message, optional, tag = "1")]
     pub c: ::core::option::Option<Ciphertext>,
 }
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct SecretKey {
+    #[prost(sint64, repeated, tag = "1")]
+    pub coeffs: ::prost::alloc::vec::Vec<i64>,
+    #[prost(fixed64, tag = "2")]
+    pub fingerprint: u64,
+    #[prost(uint32, tag = "3")]
+    pub version: u32,
+}
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, ::prost::Enumeration)]
+#[repr(i32)]
+pub enum MultiplicationStrategy {
+    Hps = 0,
+    Behz = 1,
+}
+impl MultiplicationStrategy {
+    /// String value of the enum field names used in the ProtoBuf definition.
+    ///
+    /// The values are not transformed in any way and thus are considered stable
+    /// (if the ProtoBuf definition does not change) and safe for programmatic
+    /// use.
+    pub fn as_str_name(&self) -> &'static str {
+        match self {
+            MultiplicationStrategy::Hps => "HPS",
+            MultiplicationStrategy::Behz => "BEHZ",
+        }
+    }
+    /// Creates an enum from field names used in the ProtoBuf definition.
+    pub fn from_str_name(value: &str) -> ::core::option::Option<Self> {
+        match value {
+            "HPS" => Some(Self::Hps),
+            "BEHZ" => Some(Self::Behz),
+            _ => None,
+        }
+    }
+}
@@ -1,4 +1,83 @@
 //! Protobuf for the `fhe` crate.
+//!
+//! The messages are generated by `prost` from `bfv.proto`, so that services
+//! built on `prost` or `tonic` can embed them directly, and convert them from
+//! and to the types of the library with the `From` and `TryConvertFrom`
+//! implementations of these types.
 
 /// Protobuf for the BFV encryption scheme.
 pub mod bfv;
+/// Protobuf for an evaluation service.
+///
+/// The `Evaluation` service of `service.proto` lets clients register their
+/// parameters, upload their keys and stream ciphertexts to a server. The
+/// `tonic` server and client generated by `tonic-build` are only compiled with
+/// the `grpc` feature, which keeps this crate independent of any async
+/// runtime by default.
+pub mod service;
+
+use crate::{Error, Result};
+
+/// The version of the serialization format of the messages.
+///
+/// The version is only increased by changes which older releases cannot
+/// read; fields may be added without increasing it, since unknown fields are
+/// ignored when decoding. Messages of any version up to this one, including
+/// those serialized without a version by older releases, are deserialized.
+pub const FORMAT_VERSION: u32 = 1;
+
+/// Checks that a message of version `version` can be deserialized.
+pub(crate) fn check_version(version: u32) -> Result<()> {
+    if version > FORMAT_VERSION {
+        Err(Error::UnsupportedVersion(version))
+    } else {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::bfv::Ciphertext as CiphertextProto;
+    use super::service::{upload_keys_request::Key, EvaluateRequest, UploadKeysRequest};
+    use crate::bfv::{
+        traits::TryConvertFrom, BfvParameters, Ciphertext, Encoding, Plaintext, RelinearizationKey,
+        SecretKey,
+    };
+    use fhe_traits::{FheEncoder, FheEncrypter};
+    use prost::Message;
+    use rand::thread_rng;
+    use std::error::Error;
+
+    #[test]
+    fn service_messages() -> Result<(), Box<dyn Error>> {
+        let mut rng = thread_rng();
+        let par = BfvParameters::default_arc(2, 16);
+        let sk = SecretKey::random(&par, &mut rng);
+        let rk = RelinearizationKey::new(&sk, &mut rng)?;
+        let pt = Plaintext::try_encode(&[1u64, 2, 3], Encoding::poly(), &par)?;
+        let ct: Ciphertext = sk.try_encrypt(&pt, &mut rng)?;
+
+        let request = UploadKeysRequest {
+            session_id: vec![1, 2, 3],
+            key: Some(Key::RelinearizationKey((&rk).into())),
+        };
+        let decoded = UploadKeysRequest::decode(request.encode_to_vec().as_slice())?;
+        let Some(Key::RelinearizationKey(rk_proto)) = decoded.key else {
+            panic!("the key is not a relinearization key")
+        };
+        assert_eq!(RelinearizationKey::try_convert_from(&rk_proto, &par)?, rk);
+
+        let request = EvaluateRequest {
+            session_id: vec![1, 2, 3],
+            request_id: 42,
+            function: "square".to_string(),
+            inputs: vec![CiphertextProto::from(&ct); 2],
+        };
+        let decoded = EvaluateRequest::decode(request.encode_to_vec().as_slice())?;
+        assert_eq!(decoded, request);
+        for input in &decoded.inputs {
+            assert_eq!(Ciphertext::try_convert_from(input, &par)?, ct);
+        }
+        Ok(())
+    }
+}